@@ -0,0 +1,275 @@
+//! `/v1/admin`, inspecting and changing the state of the gateway.
+//!
+//! - `GET /v1/admin/providers` lists the providers with their models.
+//! - `POST /v1/admin/providers/{provider}/enable` and `POST
+//!   /v1/admin/providers/{provider}/disable` enable and disable a provider by
+//!   its name in the config. Requests to a disabled provider are rejected with
+//!   a `503 Service Unavailable`, so routers fail over, and load balanced
+//!   routers stop routing to it at the next health check.
+//! - `GET /v1/admin/health` lists the health of the providers, as seen by the
+//!   health monitors.
+//! - `GET /v1/admin/routers` lists the routers in the config.
+//! - `DELETE /v1/admin/cache` removes every cached response.
+//!
+//! Requires the admin key, see [`AdminConfig`], and is only served if one is
+//! configured.
+//!
+//! [`AdminConfig`]: crate::config::admin::AdminConfig
+use std::{
+    future::ready,
+    task::{Context, Poll},
+};
+
+use axum_core::response::{IntoResponse, Response};
+use futures::future::{BoxFuture, Either};
+use http::{Method, StatusCode};
+use indexmap::IndexSet;
+use serde::Serialize;
+use tower::{Layer, Service};
+use url::Url;
+
+use super::on_admin_port;
+use crate::{
+    app_state::AppState,
+    config::router::RouterConfigs,
+    discover::monitor::health::provider::ProviderHealth,
+    error::{
+        api::ApiError, auth::AuthError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+    types::{
+        json::Json, model_id::ModelId, provider::InferenceProvider,
+        request::Request,
+    },
+};
+
+pub const ADMIN_PATH: &str = "/v1/admin";
+
+#[derive(Debug, Clone)]
+pub struct AdminLayer {
+    app_state: AppState,
+}
+
+impl AdminLayer {
+    #[must_use]
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+}
+
+impl<S> Layer<S> for AdminLayer {
+    type Service = AdminService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AdminService {
+            inner,
+            app_state: self.app_state.clone(),
+        }
+    }
+}
+
+/// Serves `/v1/admin`, and rejects the requests received by the
+/// [`AdminServer`](super::AdminServer) for any path that is not an admin
+/// endpoint.
+#[derive(Debug, Clone)]
+pub struct AdminService<S> {
+    inner: S,
+    app_state: AppState,
+}
+
+impl<S> Service<Request> for AdminService<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Error: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Either<
+        BoxFuture<'static, Result<Self::Response, Self::Error>>,
+        S::Future,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let path = req.uri().path();
+        let is_admin_path = path == ADMIN_PATH
+            || path
+                .strip_prefix(ADMIN_PATH)
+                .is_some_and(|rest| rest.starts_with('/'));
+        let on_admin_port = on_admin_port(&req);
+        if !is_admin_path
+            || !self.app_state.config().admin.serves(on_admin_port)
+        {
+            if on_admin_port {
+                let error = InvalidRequestError::NotFound(path.to_string());
+                return Either::Left(Box::pin(ready(
+                    Ok(error.into_response()),
+                )));
+            }
+            return Either::Right(self.inner.call(req));
+        }
+
+        let app_state = self.app_state.clone();
+        Either::Left(Box::pin(async move {
+            let authorization = req
+                .headers()
+                .get(http::header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok());
+            if !app_state.config().admin.is_authorized(authorization) {
+                return Ok(ApiError::Authentication(
+                    AuthError::InvalidCredentials,
+                )
+                .into_response());
+            }
+            let method = req.method().clone();
+            let path = req.uri().path().to_string();
+            Ok(admin_response(&app_state, method, &path)
+                .await
+                .unwrap_or_else(IntoResponse::into_response))
+        }))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ListResponse<T> {
+    data: T,
+}
+
+#[derive(Debug, Serialize)]
+struct ProviderEntry {
+    provider: InferenceProvider,
+    enabled: bool,
+    base_url: Url,
+    models: IndexSet<ModelId>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    model_patterns: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct HealthEntry {
+    provider: InferenceProvider,
+    #[serde(flatten)]
+    health: ProviderHealth,
+}
+
+async fn admin_response(
+    app_state: &AppState,
+    method: Method,
+    path: &str,
+) -> Result<Response, ApiError> {
+    let segments = path
+        .strip_prefix(ADMIN_PATH)
+        .unwrap_or_default()
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    match (method, segments.as_slice()) {
+        (Method::GET, ["providers"]) => Ok(Json(ListResponse {
+            data: providers(app_state),
+        })
+        .into_response()),
+        (
+            Method::POST,
+            ["providers", provider, action @ ("enable" | "disable")],
+        ) => {
+            let entry = set_enabled(app_state, provider, *action == "enable")?;
+            Ok(Json(entry).into_response())
+        }
+        (Method::GET, ["health"]) => Ok(Json(ListResponse {
+            data: health(app_state),
+        })
+        .into_response()),
+        (Method::GET, ["routers"]) => {
+            let routers: &RouterConfigs = &app_state.config().routers;
+            Ok(Json(ListResponse { data: routers }).into_response())
+        }
+        (Method::DELETE, ["cache"]) => {
+            let cache =
+                app_state.0.cache_manager.as_ref().ok_or_else(|| {
+                    InvalidRequestError::NotFound("response cache".to_string())
+                })?;
+            cache.clear().await.map_err(InternalError::CacheError)?;
+            tracing::info!("flushed response cache");
+            Ok(StatusCode::NO_CONTENT.into_response())
+        }
+        (
+            _,
+            ["providers" | "health" | "routers" | "cache"]
+            | ["providers", _, "enable" | "disable"],
+        ) => Ok(StatusCode::METHOD_NOT_ALLOWED.into_response()),
+        _ => Err(InvalidRequestError::NotFound(path.to_string()).into()),
+    }
+}
+
+fn providers(app_state: &AppState) -> Vec<ProviderEntry> {
+    app_state
+        .providers()
+        .iter()
+        .map(|(provider, config)| ProviderEntry {
+            provider: provider.clone(),
+            enabled: config.enabled,
+            base_url: config.base_url.clone(),
+            models: config.models.clone(),
+            model_patterns: config.model_patterns.clone(),
+        })
+        .collect()
+}
+
+/// Providers without endpoint metrics, e.g. added after startup, are left
+/// out.
+fn health(app_state: &AppState) -> Vec<HealthEntry> {
+    app_state
+        .providers()
+        .keys()
+        .filter_map(|provider| {
+            let health = app_state
+                .provider_health(provider)
+                .inspect_err(|error| {
+                    tracing::debug!(%provider, %error, "no provider health");
+                })
+                .ok()?;
+            Some(HealthEntry {
+                provider: provider.clone(),
+                health,
+            })
+        })
+        .collect()
+}
+
+fn set_enabled(
+    app_state: &AppState,
+    name: &str,
+    enabled: bool,
+) -> Result<ProviderEntry, ApiError> {
+    let providers = app_state.providers();
+    // resolved like requests are, so any name of the provider can be used
+    let (provider, config) = providers
+        .get_key_value(&InferenceProvider::Named(name.into()))
+        .ok_or_else(|| {
+            InvalidRequestError::NotFound(format!("provider {name}"))
+        })?;
+    let mut config = config.clone();
+    config.enabled = enabled;
+    app_state
+        .0
+        .providers
+        .update_provider(provider.clone(), config.clone())
+        .map_err(|error| {
+            tracing::error!(%provider, %error, "failed to update provider");
+            InternalError::Internal
+        })?;
+    tracing::info!(%provider, enabled, "set provider enabled");
+    Ok(ProviderEntry {
+        provider: provider.clone(),
+        enabled,
+        base_url: config.base_url,
+        models: config.models,
+        model_patterns: config.model_patterns,
+    })
+}
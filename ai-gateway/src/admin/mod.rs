@@ -0,0 +1,88 @@
+//! The admin API under `/v1/admin`, to inspect and change the state of a
+//! running gateway without editing its config, see [`endpoint`].
+//!
+//! Like the other admin endpoints (`/v1/usage`, `/v1/keys` and
+//! `/v1/budgets`), it requires the admin key and is only served if one is
+//! configured, see [`AdminConfig`]. If [`AdminConfig::port`] is set, all of
+//! them are served by the [`AdminServer`] on that port instead of on the port
+//! of the gateway.
+//!
+//! Changes made with the admin API are per replica of the gateway, and are
+//! lost when the config is reloaded or the gateway restarts. They are kept
+//! when the models of the providers are refreshed, see
+//! [`crate::discover::provider_models`].
+//!
+//! [`AdminConfig`]: crate::config::admin::AdminConfig
+//! [`AdminConfig::port`]: crate::config::admin::AdminConfig::port
+pub mod endpoint;
+
+use std::net::SocketAddr;
+
+use futures::future::BoxFuture;
+use meltdown::Token;
+use tower::{ServiceBuilder, util::BoxCloneService};
+use tracing::info;
+
+use crate::{
+    app::{self, App, AppFactory, HyperApp},
+    error::runtime::RuntimeError,
+};
+
+/// Marks the requests received by the [`AdminServer`].
+#[derive(Debug, Clone, Copy)]
+pub struct AdminPort;
+
+/// Whether `req` was received by the [`AdminServer`].
+#[must_use]
+pub fn on_admin_port<B>(req: &http::Request<B>) -> bool {
+    req.extensions().get::<AdminPort>().is_some()
+}
+
+/// Serves the admin endpoints on [`AdminConfig::port`], with the same
+/// service stack as the gateway. Requests for any other path are rejected,
+/// see [`endpoint::AdminService`].
+///
+/// [`AdminConfig::port`]: crate::config::admin::AdminConfig::port
+#[derive(Clone)]
+pub struct AdminServer {
+    app: App,
+}
+
+impl AdminServer {
+    #[must_use]
+    pub fn new(app: App) -> Self {
+        Self { app }
+    }
+}
+
+impl meltdown::Service for AdminServer {
+    type Future = BoxFuture<'static, Result<(), RuntimeError>>;
+
+    fn run(self, token: Token) -> Self::Future {
+        Box::pin(async move {
+            let app_state = self.app.state.clone();
+            let config = app_state.config();
+            let Some(port) = config.admin.port else {
+                return Ok(());
+            };
+            let addr = SocketAddr::from((config.server.address, port));
+            info!(address = %addr, tls = %config.server.tls, "admin server starting");
+
+            let service_stack = ServiceBuilder::new()
+                .map_request(|req: http::Request<hyper::body::Incoming>| {
+                    let mut req = req.map(axum_core::body::Body::new);
+                    req.extensions_mut().insert(AdminPort);
+                    req
+                })
+                .service(self.app);
+            let app_factory = AppFactory::new(
+                app_state.clone(),
+                HyperApp {
+                    state: app_state.clone(),
+                    service_stack: BoxCloneService::new(service_stack),
+                },
+            );
+            app::serve(app_factory, addr, config, token).await
+        })
+    }
+}
@@ -29,19 +29,31 @@ use tower_http::{
 use tracing::{Level, info};
 
 use crate::{
+    admin::endpoint::AdminLayer,
     app_state::{AppState, InnerAppState},
+    budgets::{Budgets, endpoint::BudgetsLayer},
     cache::{CacheClient, RedisCacheManager},
     cli,
-    config::{Config, cache::CacheStore, server::TlsConfig},
+    config::{
+        Config, cache::CacheStore, providers_store::ProvidersConfigStore,
+        server::TlsConfig,
+    },
     control_plane::control_plane_state::StateWithMetadata,
-    discover::monitor::{
-        health::provider::HealthMonitorMap, metrics::EndpointMetricsRegistry,
-        rate_limit::RateLimitMonitorMap,
+    discover::{
+        monitor::{
+            health::provider::HealthMonitorMap,
+            metrics::EndpointMetricsRegistry, rate_limit::RateLimitMonitorMap,
+        },
+        provider_models::ModelDiscovery,
     },
     error::{init::InitError, runtime::RuntimeError},
-    logger::service::JawnClient,
+    logger::{service::JawnClient, sink::LogSink},
+    metering::{Metering, endpoint::UsageLayer},
     metrics::{self, Metrics, attribute_extractor::AttributeExtractor},
-    middleware::response_headers::ResponseHeaderLayer,
+    middleware::{
+        rate_limit::provider::ProviderRateLimiter,
+        response_headers::ResponseHeaderLayer,
+    },
     router::meta::MetaRouter,
     store::{connect, minio::BaseMinioClient, router::RouterStore},
     types::provider::ProviderKeys,
@@ -50,6 +62,7 @@ use crate::{
         health_check::HealthCheckLayer, timer::TimerLayer,
         validate_config::ValidateRouterConfigLayer,
     },
+    virtual_keys::{VirtualKeys, endpoint::KeysLayer},
 };
 
 const APP_BUFFER_SIZE: usize = 1024;
@@ -223,6 +236,7 @@ impl App {
                     .map(Arc::new)
             })
             .transpose()?;
+        let provider_rate_limits = ProviderRateLimiter::new(&config)?;
 
         let cache_manager = setup_cache(&config, metrics.clone());
 
@@ -246,10 +260,20 @@ impl App {
         } else {
             None
         };
-        let provider_keys = ProviderKeys::new(&config, &metrics);
+        let provider_keys = ProviderKeys::new(&config, &metrics)?;
+        let providers = ProvidersConfigStore::new(config.providers.clone())?;
+        let log_sink = config.log_sink.as_ref().map(LogSink::new);
+        let budgets = Budgets::new(&config.budgets).await?;
+        let virtual_keys = match config.virtual_keys.as_ref() {
+            Some(virtual_keys_config) => {
+                Some(VirtualKeys::new(virtual_keys_config).await?)
+            }
+            None => None,
+        };
 
         let app_state = AppState(Arc::new(InnerAppState {
             config,
+            providers,
             minio,
             router_store,
             jawn_http_client,
@@ -259,6 +283,11 @@ impl App {
             provider_keys,
             global_rate_limit,
             router_rate_limits: RwLock::new(HashMap::default()),
+            provider_rate_limits,
+            metering: Metering::default(),
+            budgets,
+            log_sink,
+            virtual_keys,
             metrics,
             endpoint_metrics,
             health_monitors: health_monitor,
@@ -271,6 +300,11 @@ impl App {
             router_organization_map: RwLock::new(HashMap::default()),
         }));
 
+        // before the routers are built so they can route to the models
+        if app_state.config().model_discovery.enabled {
+            ModelDiscovery::new(app_state.clone())?.refresh().await?;
+        }
+
         Ok(app_state)
     }
 
@@ -324,6 +358,11 @@ impl App {
             .layer(compression_layer)
             .layer(cors_layer)
             .layer(HealthCheckLayer::new())
+            .layer(metrics::prometheus::Layer)
+            .layer(UsageLayer::new(app_state.clone()))
+            .layer(KeysLayer::new(app_state.clone()))
+            .layer(BudgetsLayer::new(app_state.clone()))
+            .layer(AdminLayer::new(app_state.clone()))
             .layer(ValidateRouterConfigLayer::new())
             .layer(TimerLayer::new())
             .layer(ErrorHandlerLayer::new(app_state.clone()))
@@ -350,48 +389,59 @@ impl meltdown::Service for App {
                 SocketAddr::from((config.server.address, config.server.port));
             info!(address = %addr, tls = %config.server.tls, "server starting");
 
-            let handle = axum_server::Handle::new();
             let app_factory = AppFactory::new_hyper_app(self);
             // sleep so that the banner is not printed before the server is
             // ready
             tokio::time::sleep(std::time::Duration::from_millis(250)).await;
             cli::helpers::show_welcome_banner(&addr);
 
-            match &config.server.tls {
-                TlsConfig::Enabled { cert, key } => {
-                    let tls_config =
-                        RustlsConfig::from_pem_file(cert.clone(), key.clone())
-                            .await
-                            .map_err(InitError::Tls)?;
-
-                    tokio::select! {
-                        biased;
-                        server_output = axum_server::bind_rustls(addr, tls_config)
-                            // Why `NoDelayAcceptor`? See:
-                            // https://brooker.co.za/blog/2024/05/09/nagle.html
-                            .acceptor(NoDelayAcceptor)
-                            .handle(handle.clone())
-                            .serve(app_factory) => server_output.map_err(RuntimeError::Serve)?,
-                        () = token => {
-                            handle.graceful_shutdown(Some(config.server.shutdown_timeout));
-                        }
-                    };
+            serve(app_factory, addr, config, token).await
+        })
+    }
+}
+
+/// Serve `app_factory` on `addr` until `token` is triggered, with TLS if it
+/// is enabled in the server config.
+pub(crate) async fn serve(
+    app_factory: AppFactory<HyperApp>,
+    addr: SocketAddr,
+    config: &Config,
+    token: Token,
+) -> Result<(), RuntimeError> {
+    let handle = axum_server::Handle::new();
+    match &config.server.tls {
+        TlsConfig::Enabled { cert, key } => {
+            let tls_config =
+                RustlsConfig::from_pem_file(cert.clone(), key.clone())
+                    .await
+                    .map_err(InitError::Tls)?;
+
+            tokio::select! {
+                biased;
+                server_output = axum_server::bind_rustls(addr, tls_config)
+                    // Why `NoDelayAcceptor`? See:
+                    // https://brooker.co.za/blog/2024/05/09/nagle.html
+                    .acceptor(NoDelayAcceptor)
+                    .handle(handle.clone())
+                    .serve(app_factory) => server_output.map_err(RuntimeError::Serve)?,
+                () = token => {
+                    handle.graceful_shutdown(Some(config.server.shutdown_timeout));
                 }
-                TlsConfig::Disabled => {
-                    tokio::select! {
-                        biased;
-                        server_output = axum_server::bind(addr)
-                            .handle(handle.clone())
-                            .serve(app_factory) => server_output.map_err(RuntimeError::Serve)?,
-                        () = token => {
-                            handle.graceful_shutdown(Some(config.server.shutdown_timeout));
-                        }
-                    };
+            };
+        }
+        TlsConfig::Disabled => {
+            tokio::select! {
+                biased;
+                server_output = axum_server::bind(addr)
+                    .handle(handle.clone())
+                    .serve(app_factory) => server_output.map_err(RuntimeError::Serve)?,
+                () = token => {
+                    handle.graceful_shutdown(Some(config.server.shutdown_timeout));
                 }
-            }
-            Ok(())
-        })
+            };
+        }
     }
+    Ok(())
 }
 
 #[derive(Clone)]
@@ -9,9 +9,11 @@ use tokio::sync::{
 use tower::discover::Change;
 
 use crate::{
+    budgets::Budgets,
     cache::CacheClient,
     config::{
-        Config, rate_limit::RateLimiterConfig,
+        Config, providers::ProvidersConfig,
+        providers_store::ProvidersConfigStore, rate_limit::RateLimiterConfig,
         response_headers::ResponseHeadersConfig, router::RouterConfig,
     },
     control_plane::{control_plane_state::StateWithMetadata, types::Key},
@@ -20,8 +22,10 @@ use crate::{
         rate_limit::RateLimitMonitorMap,
     },
     error::init::InitError,
-    logger::service::JawnClient,
+    logger::{service::JawnClient, sink::LogSink},
+    metering::Metering,
     metrics::Metrics,
+    middleware::rate_limit::provider::ProviderRateLimiter,
     router::service::Router,
     store::{minio::BaseMinioClient, router::RouterStore},
     types::{
@@ -32,6 +36,7 @@ use crate::{
         },
         router::RouterId,
     },
+    virtual_keys::VirtualKeys,
 };
 
 #[derive(Debug, Clone)]
@@ -47,17 +52,40 @@ impl AppState {
     pub fn config(&self) -> &Config {
         &self.0.config
     }
+
+    /// A snapshot of the providers config, including any models loaded by
+    /// [`ModelDiscovery`](crate::discover::provider_models::ModelDiscovery)
+    /// and any changes reloaded by
+    /// [`ConfigReloader`](crate::discover::reload::ConfigReloader).
+    #[must_use]
+    pub fn providers(&self) -> Arc<ProvidersConfig> {
+        self.0.providers.get()
+    }
 }
 
 #[derive(Debug)]
 pub struct InnerAppState {
     pub config: Config,
+    /// The providers of `config`, updated at runtime, see
+    /// [`AppState::providers`].
+    pub providers: ProvidersConfigStore,
     pub minio: BaseMinioClient,
     pub router_store: Option<RouterStore>,
     pub jawn_http_client: JawnClient,
     pub cache_manager: Option<CacheClient>,
     pub global_rate_limit: Option<Arc<RateLimiterConfig>>,
     pub router_rate_limits: RwLock<HashMap<RouterId, Arc<RateLimiterConfig>>>,
+    /// The `limits` of providers, enforced by the dispatchers.
+    pub provider_rate_limits: ProviderRateLimiter,
+    /// The usage and cost of requests, per API key, provider and model.
+    pub metering: Metering,
+    /// Spend ceilings, enforced by the dispatchers.
+    pub budgets: Budgets,
+    /// Records of requests waiting to be written, `None` if the log sink is
+    /// disabled.
+    pub log_sink: Option<LogSink>,
+    /// API keys issued by the gateway, `None` if disabled.
+    pub virtual_keys: Option<VirtualKeys>,
     /// Top level metrics which are exported to OpenTelemetry.
     pub metrics: Metrics,
     /// Metrics to track provider health and rate limits.
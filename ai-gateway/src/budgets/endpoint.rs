@@ -0,0 +1,166 @@
+//! `/v1/budgets`, listing, setting and removing budgets.
+//!
+//! - `GET /v1/budgets` lists all budgets with their spend in the current
+//!   period.
+//! - `PUT /v1/budgets/{name}` adds or replaces a budget, the body has the
+//!   fields of a budget in the config, see [`Budget`].
+//! - `DELETE /v1/budgets/{name}` removes a budget.
+//!
+//! Requires the admin key, see [`AdminConfig`], and is only served if one is
+//! configured.
+//!
+//! [`AdminConfig`]: crate::config::admin::AdminConfig
+use std::task::{Context, Poll};
+
+use axum_core::response::{IntoResponse, Response};
+use chrono::Utc;
+use futures::future::{BoxFuture, Either};
+use http::{Method, StatusCode};
+use http_body_util::BodyExt;
+use serde::Serialize;
+use tower::{Layer, Service};
+
+use super::BudgetStatus;
+use crate::{
+    admin::on_admin_port,
+    app_state::AppState,
+    config::budgets::Budget,
+    error::{
+        api::ApiError, auth::AuthError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+    types::{json::Json, request::Request},
+};
+
+pub const BUDGETS_PATH: &str = "/v1/budgets";
+
+#[derive(Debug, Clone)]
+pub struct BudgetsLayer {
+    app_state: AppState,
+}
+
+impl BudgetsLayer {
+    #[must_use]
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+}
+
+impl<S> Layer<S> for BudgetsLayer {
+    type Service = BudgetsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BudgetsService {
+            inner,
+            app_state: self.app_state.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BudgetsService<S> {
+    inner: S,
+    app_state: AppState,
+}
+
+impl<S> Service<Request> for BudgetsService<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Error: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Either<
+        BoxFuture<'static, Result<Self::Response, Self::Error>>,
+        S::Future,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let path = req.uri().path();
+        let is_budgets_path = path == BUDGETS_PATH
+            || path
+                .strip_prefix(BUDGETS_PATH)
+                .is_some_and(|rest| rest.starts_with('/'));
+        if !is_budgets_path
+            || !self.app_state.config().admin.serves(on_admin_port(&req))
+        {
+            return Either::Right(self.inner.call(req));
+        }
+
+        let app_state = self.app_state.clone();
+        Either::Left(Box::pin(async move {
+            let authorization = req
+                .headers()
+                .get(http::header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok());
+            if !app_state.config().admin.is_authorized(authorization) {
+                return Ok(ApiError::Authentication(
+                    AuthError::InvalidCredentials,
+                )
+                .into_response());
+            }
+            Ok(budgets_response(&app_state, req)
+                .await
+                .unwrap_or_else(IntoResponse::into_response))
+        }))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct BudgetsResponse {
+    data: Vec<BudgetStatus>,
+}
+
+async fn budgets_response(
+    app_state: &AppState,
+    req: Request,
+) -> Result<Response, ApiError> {
+    let budgets = &app_state.0.budgets;
+    let name = req
+        .uri()
+        .path()
+        .strip_prefix(BUDGETS_PATH)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .filter(|name| !name.is_empty())
+        .map(ToString::to_string);
+    let now = Utc::now();
+    match (req.method().clone(), name) {
+        (Method::GET, None) => Ok(Json(BudgetsResponse {
+            data: budgets.list(now),
+        })
+        .into_response()),
+        (Method::PUT, Some(name)) => {
+            let body = req
+                .into_body()
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            let budget = serde_json::from_slice::<Budget>(&body)
+                .map_err(InvalidRequestError::InvalidRequestBody)?;
+            budgets.save(name.clone(), budget, now).await?;
+            tracing::info!(budget = %name, "set budget");
+            let status = budgets
+                .list(now)
+                .into_iter()
+                .find(|status| status.name == name)
+                .ok_or(InternalError::Internal)?;
+            Ok(Json(status).into_response())
+        }
+        (Method::DELETE, Some(name)) => {
+            let budget = budgets.delete(&name).await?.ok_or_else(|| {
+                InvalidRequestError::NotFound(format!("budget {name}"))
+            })?;
+            tracing::info!(budget = %name, "removed budget");
+            Ok(Json(budget).into_response())
+        }
+        _ => Ok(StatusCode::METHOD_NOT_ALLOWED.into_response()),
+    }
+}
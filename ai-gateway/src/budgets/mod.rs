@@ -0,0 +1,666 @@
+//! Spend ceilings per API key, router and provider, with a daily or monthly
+//! period.
+//!
+//! Budgets are defined in the config, see [`BudgetsConfig`], and can be set
+//! and removed at runtime with the `/v1/budgets` admin endpoint, see
+//! [`endpoint`]. The dispatchers reject requests covered by an exhausted
+//! budget with a `402 Payment Required`, and the cost of every request is
+//! added to the budgets covering it once its response is complete. Requests
+//! already sent when a budget is exhausted may still take it over its limit.
+//!
+//! As the spend of a budget reaches the thresholds of the webhook, a
+//! [`BudgetAlert`] is sent to it, once per threshold and period.
+//!
+//! With a [`store`](BudgetsConfig::store), the spend of every period and
+//! the budgets set at runtime are kept in its database, so they are shared
+//! by the replicas of the gateway and survive restarts. Each replica reloads
+//! them every
+//! [`refresh_interval`](crate::config::budgets::BudgetsStoreConfig::refresh_interval),
+//! see [`refresh`]. Without one, like [`crate::metering`], spend is per
+//! replica and resets on restart, as do budgets set at runtime.
+pub mod endpoint;
+pub mod refresh;
+
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use indexmap::IndexMap;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+use crate::{
+    config::budgets::{
+        Budget, BudgetPeriod, BudgetWebhookConfig, BudgetsConfig,
+    },
+    error::{
+        init::InitError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+    store::{
+        budgets::{BudgetStore, DbBudget, DbBudgetSpend},
+        from_nano_usd, to_nano_usd,
+    },
+    types::{provider::InferenceProvider, router::RouterId},
+};
+
+/// The notification sent to the webhook when the spend of a budget reaches
+/// one of its thresholds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BudgetAlert {
+    pub budget: String,
+    pub threshold_percent: u32,
+    pub limit_usd: Decimal,
+    pub spent_usd: Decimal,
+    pub period: BudgetPeriod,
+    pub period_start: DateTime<Utc>,
+    pub resets_at: DateTime<Utc>,
+}
+
+/// A budget and its spend in the current period.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BudgetStatus {
+    pub name: String,
+    pub budget: Budget,
+    pub spent_usd: Decimal,
+    pub period_start: DateTime<Utc>,
+    pub resets_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+struct BudgetState {
+    budget: Budget,
+    period_start: DateTime<Utc>,
+    spent_usd: Decimal,
+    /// The thresholds already notified in the current period.
+    notified: Vec<u32>,
+}
+
+impl BudgetState {
+    fn new(budget: Budget, now: DateTime<Utc>) -> Self {
+        Self {
+            period_start: budget.period.start(now),
+            budget,
+            spent_usd: Decimal::ZERO,
+            notified: Vec::new(),
+        }
+    }
+
+    /// Start a new period if the current one is over.
+    fn roll(&mut self, now: DateTime<Utc>) {
+        let period_start = self.budget.period.start(now);
+        if period_start != self.period_start {
+            self.period_start = period_start;
+            self.spent_usd = Decimal::ZERO;
+            self.notified.clear();
+        }
+    }
+
+    fn resets_at(&self) -> DateTime<Utc> {
+        self.budget.period.next_start(self.period_start)
+    }
+
+    fn reached(&self, threshold_percent: u32) -> bool {
+        self.spent_usd * Decimal::ONE_HUNDRED
+            >= self.budget.limit_usd * Decimal::from(threshold_percent)
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.spent_usd >= self.budget.limit_usd
+    }
+
+    /// An alert for each of `thresholds` reached that was not notified yet
+    /// in the current period, marking them as notified.
+    fn alerts(&mut self, name: &str, thresholds: &[u32]) -> Vec<BudgetAlert> {
+        let mut alerts = Vec::new();
+        for &threshold in thresholds {
+            if self.reached(threshold) && !self.notified.contains(&threshold) {
+                self.notified.push(threshold);
+                alerts.push(BudgetAlert {
+                    budget: name.to_string(),
+                    threshold_percent: threshold,
+                    limit_usd: self.budget.limit_usd,
+                    spent_usd: self.spent_usd,
+                    period: self.budget.period,
+                    period_start: self.period_start,
+                    resets_at: self.resets_at(),
+                });
+            }
+        }
+        alerts
+    }
+}
+
+/// Whether `a` and `b` cover the same requests over the same period, so
+/// that the spend of one counts towards the other.
+fn same_scope(a: &Budget, b: &Budget) -> bool {
+    a.period == b.period
+        && a.api_key_hash == b.api_key_hash
+        && a.router == b.router
+        && a.provider == b.provider
+}
+
+/// Add or replace the budget `name`, see [`Budgets::set`].
+fn insert(
+    budgets: &mut IndexMap<String, BudgetState>,
+    name: String,
+    budget: Budget,
+    now: DateTime<Utc>,
+) {
+    match budgets.get_mut(&name) {
+        Some(state) if same_scope(&state.budget, &budget) => {
+            state.roll(now);
+            state.budget = budget;
+            let notified = std::mem::take(&mut state.notified);
+            state.notified = notified
+                .into_iter()
+                .filter(|threshold| state.reached(*threshold))
+                .collect();
+        }
+        _ => {
+            budgets.insert(name, BudgetState::new(budget, now));
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Webhook {
+    config: BudgetWebhookConfig,
+    client: reqwest::Client,
+}
+
+/// The budgets, by name.
+#[derive(Debug)]
+pub struct Budgets {
+    budgets: Mutex<IndexMap<String, BudgetState>>,
+    webhook: Option<Webhook>,
+    store: Option<BudgetStore>,
+}
+
+impl Budgets {
+    /// The budgets of `config`, along with the budgets set at runtime and
+    /// the spend kept in its store, if it has one.
+    pub async fn new(config: &BudgetsConfig) -> Result<Self, InitError> {
+        let mut budgets = Self::in_memory(config)?;
+        if let Some(store_config) = &config.store {
+            budgets.store = Some(BudgetStore::connect(store_config).await?);
+            budgets
+                .refresh(Utc::now())
+                .await
+                .map_err(|e| InitError::InitBudgets(e.to_string()))?;
+        }
+        Ok(budgets)
+    }
+
+    fn in_memory(config: &BudgetsConfig) -> Result<Self, InitError> {
+        let now = Utc::now();
+        let budgets = config
+            .limits
+            .iter()
+            .map(|(name, budget)| {
+                (name.clone(), BudgetState::new(budget.clone(), now))
+            })
+            .collect();
+        let webhook = config
+            .webhook
+            .as_ref()
+            .map(|config| {
+                reqwest::Client::builder()
+                    .timeout(config.timeout)
+                    .tcp_nodelay(true)
+                    .build()
+                    .map(|client| Webhook {
+                        config: config.clone(),
+                        client,
+                    })
+            })
+            .transpose()
+            .map_err(InitError::CreateReqwestClient)?;
+        Ok(Self {
+            budgets: Mutex::new(budgets),
+            webhook,
+            store: None,
+        })
+    }
+
+    fn thresholds(&self) -> &[u32] {
+        self.webhook
+            .as_ref()
+            .map(|webhook| webhook.config.thresholds.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Reload the budgets set at runtime and the spend of the current
+    /// periods from the store, if there is one, to pick up the changes of
+    /// other replicas.
+    ///
+    /// The thresholds reached are not notified again, as the replica that
+    /// reached them already did.
+    pub async fn refresh(
+        &self,
+        now: DateTime<Utc>,
+    ) -> Result<(), InternalError> {
+        let Some(store) = &self.store else {
+            return Ok(());
+        };
+        let stored = store.get_budgets().await?;
+        // monthly periods start first
+        let since = BudgetPeriod::Monthly.start(now);
+        let spend = store.get_spend(since.timestamp_millis()).await?;
+        let thresholds = self.thresholds();
+        let mut budgets = self.budgets.lock().expect("never poisoned");
+        for DbBudget { name, budget } in stored {
+            match budget {
+                Some(budget) => {
+                    let budget = serde_json::from_str::<Budget>(&budget)
+                        .map_err(|error| InternalError::Deserialize {
+                            ty: "Budget",
+                            error,
+                        })?;
+                    insert(&mut budgets, name, budget, now);
+                }
+                None => {
+                    budgets.shift_remove(&name);
+                }
+            }
+        }
+        for state in budgets.values_mut() {
+            state.roll(now);
+        }
+        for DbBudgetSpend {
+            name,
+            period_start,
+            spent_nano_usd,
+        } in spend
+        {
+            let Some(state) = budgets.get_mut(&name) else {
+                continue;
+            };
+            if state.period_start.timestamp_millis() != period_start {
+                continue;
+            }
+            state.spent_usd = from_nano_usd(spent_nano_usd);
+            for &threshold in thresholds {
+                if state.reached(threshold)
+                    && !state.notified.contains(&threshold)
+                {
+                    state.notified.push(threshold);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether a request with the API key `api_key_hash` to `router_id` can
+    /// be sent to `provider` at `now`, i.e. none of the budgets covering it
+    /// are exhausted.
+    pub fn check(
+        &self,
+        api_key_hash: Option<&str>,
+        router_id: Option<&RouterId>,
+        provider: &InferenceProvider,
+        now: DateTime<Utc>,
+    ) -> Result<(), InvalidRequestError> {
+        let mut budgets = self.budgets.lock().expect("never poisoned");
+        for (name, state) in budgets.iter_mut() {
+            if !state.budget.applies_to(api_key_hash, router_id, provider) {
+                continue;
+            }
+            state.roll(now);
+            if state.is_exhausted() {
+                return Err(InvalidRequestError::BudgetExceeded {
+                    budget: name.clone(),
+                    resets_at: state.resets_at(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Add `cost_usd` to the budgets covering a request, returning an alert
+    /// for each threshold of the webhook their spend reached that was not
+    /// notified yet in the current period.
+    pub fn record(
+        &self,
+        api_key_hash: Option<&str>,
+        router_id: Option<&RouterId>,
+        provider: &InferenceProvider,
+        cost_usd: Decimal,
+        now: DateTime<Utc>,
+    ) -> Vec<BudgetAlert> {
+        let thresholds = self.thresholds();
+        let mut alerts = Vec::new();
+        let mut budgets = self.budgets.lock().expect("never poisoned");
+        for (name, state) in budgets.iter_mut() {
+            if !state.budget.applies_to(api_key_hash, router_id, provider) {
+                continue;
+            }
+            state.roll(now);
+            state.spent_usd += cost_usd;
+            alerts.extend(state.alerts(name, thresholds));
+        }
+        alerts
+    }
+
+    /// Like [`Budgets::record`], but `cost_usd` is added to the spend in
+    /// `store`, and the budgets take the spend of every replica from it.
+    async fn record_in_store(
+        &self,
+        store: &BudgetStore,
+        api_key_hash: Option<&str>,
+        router_id: Option<&RouterId>,
+        provider: &InferenceProvider,
+        cost_usd: Decimal,
+        now: DateTime<Utc>,
+    ) -> Vec<BudgetAlert> {
+        let cost_nano_usd = match to_nano_usd(cost_usd) {
+            Ok(cost_nano_usd) => cost_nano_usd,
+            Err(e) => {
+                tracing::warn!(error = %e, cost_usd = %cost_usd, "failed to persist spend of budgets");
+                return self.record(
+                    api_key_hash,
+                    router_id,
+                    provider,
+                    cost_usd,
+                    now,
+                );
+            }
+        };
+        let covering = {
+            let mut budgets = self.budgets.lock().expect("never poisoned");
+            budgets
+                .iter_mut()
+                .filter(|(_, state)| {
+                    state.budget.applies_to(api_key_hash, router_id, provider)
+                })
+                .map(|(name, state)| {
+                    state.roll(now);
+                    (name.clone(), state.period_start)
+                })
+                .collect::<Vec<_>>()
+        };
+        let mut spent = Vec::with_capacity(covering.len());
+        for (name, period_start) in covering {
+            let spent_usd = store
+                .add_spent_nano_usd(
+                    &name,
+                    period_start.timestamp_millis(),
+                    cost_nano_usd,
+                )
+                .await
+                .map(from_nano_usd)
+                .inspect_err(|e| {
+                    tracing::warn!(error = %e, budget = %name, "failed to persist spend of budget");
+                })
+                .ok();
+            spent.push((name, period_start, spent_usd));
+        }
+
+        let thresholds = self.thresholds();
+        let mut alerts = Vec::new();
+        let mut budgets = self.budgets.lock().expect("never poisoned");
+        for (name, period_start, spent_usd) in spent {
+            let Some(state) = budgets.get_mut(&name) else {
+                continue;
+            };
+            if state.period_start != period_start {
+                continue;
+            }
+            match spent_usd {
+                Some(spent_usd) => state.spent_usd = spent_usd,
+                None => state.spent_usd += cost_usd,
+            }
+            alerts.extend(state.alerts(&name, thresholds));
+        }
+        alerts
+    }
+
+    /// Add `cost_usd` to the budgets covering a request, and send the
+    /// alerts of the thresholds reached to the webhook.
+    pub async fn add_spend(
+        &self,
+        api_key_hash: Option<&str>,
+        router_id: Option<&RouterId>,
+        provider: &InferenceProvider,
+        cost_usd: Decimal,
+    ) {
+        let now = Utc::now();
+        let alerts = match &self.store {
+            Some(store) => {
+                self.record_in_store(
+                    store,
+                    api_key_hash,
+                    router_id,
+                    provider,
+                    cost_usd,
+                    now,
+                )
+                .await
+            }
+            None => {
+                self.record(api_key_hash, router_id, provider, cost_usd, now)
+            }
+        };
+        let Some(webhook) = &self.webhook else {
+            return;
+        };
+        for alert in alerts {
+            tracing::info!(
+                budget = %alert.budget,
+                threshold = alert.threshold_percent,
+                spent_usd = %alert.spent_usd,
+                "budget threshold reached"
+            );
+            let result = webhook
+                .client
+                .post(webhook.config.url.clone())
+                .json(&alert)
+                .send()
+                .await
+                .and_then(reqwest::Response::error_for_status);
+            if let Err(e) = result {
+                tracing::warn!(error = %e, budget = %alert.budget, "failed to send budget alert");
+            }
+        }
+    }
+
+    /// All budgets with their spend in the period that `now` is in.
+    pub fn list(&self, now: DateTime<Utc>) -> Vec<BudgetStatus> {
+        let mut budgets = self.budgets.lock().expect("never poisoned");
+        budgets
+            .iter_mut()
+            .map(|(name, state)| {
+                state.roll(now);
+                BudgetStatus {
+                    name: name.clone(),
+                    budget: state.budget.clone(),
+                    spent_usd: state.spent_usd,
+                    period_start: state.period_start,
+                    resets_at: state.resets_at(),
+                }
+            })
+            .collect()
+    }
+
+    /// Add or replace the budget `name`.
+    ///
+    /// The spend of a replaced budget is kept if it covers the same
+    /// requests over the same period, e.g. when only its limit changes.
+    pub fn set(&self, name: String, budget: Budget, now: DateTime<Utc>) {
+        let mut budgets = self.budgets.lock().expect("never poisoned");
+        insert(&mut budgets, name, budget, now);
+    }
+
+    /// Remove the budget `name`, returning `None` if there is no such
+    /// budget.
+    pub fn remove(&self, name: &str) -> Option<Budget> {
+        self.budgets
+            .lock()
+            .expect("never poisoned")
+            .shift_remove(name)
+            .map(|state| state.budget)
+    }
+
+    /// Like [`Budgets::set`], but the budget is also saved to the store, if
+    /// there is one.
+    pub async fn save(
+        &self,
+        name: String,
+        budget: Budget,
+        now: DateTime<Utc>,
+    ) -> Result<(), InternalError> {
+        if let Some(store) = &self.store {
+            let keeps_spend = self
+                .budgets
+                .lock()
+                .expect("never poisoned")
+                .get(&name)
+                .is_some_and(|state| same_scope(&state.budget, &budget));
+            if !keeps_spend {
+                store.delete_spend(&name).await?;
+            }
+            let json = serde_json::to_string(&budget).map_err(|error| {
+                InternalError::Serialize {
+                    ty: "Budget",
+                    error,
+                }
+            })?;
+            store.set_budget(&name, Some(json)).await?;
+        }
+        self.set(name, budget, now);
+        Ok(())
+    }
+
+    /// Like [`Budgets::remove`], but the budget is also removed from the
+    /// store, if there is one.
+    pub async fn delete(
+        &self,
+        name: &str,
+    ) -> Result<Option<Budget>, InternalError> {
+        if let Some(store) = &self.store {
+            let exists = self
+                .budgets
+                .lock()
+                .expect("never poisoned")
+                .contains_key(name);
+            if !exists {
+                return Ok(None);
+            }
+            store.set_budget(name, None).await?;
+            store.delete_spend(name).await?;
+        }
+        Ok(self.remove(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budgets(budget: Budget) -> Budgets {
+        let config = BudgetsConfig {
+            limits: IndexMap::from([("openai".to_string(), budget)]),
+            webhook: Some(BudgetWebhookConfig {
+                url: "http://localhost:9999/budgets".parse().unwrap(),
+                thresholds: vec![50, 90, 100],
+                timeout: std::time::Duration::from_secs(1),
+            }),
+            store: None,
+        };
+        Budgets::in_memory(&config).unwrap()
+    }
+
+    fn openai_budget() -> Budget {
+        Budget {
+            limit_usd: Decimal::TEN,
+            period: BudgetPeriod::Daily,
+            api_key_hash: None,
+            router: None,
+            provider: Some(InferenceProvider::OpenAI),
+        }
+    }
+
+    #[test]
+    fn test_exhausted_budget_rejects_requests() {
+        let budgets = budgets(openai_budget());
+        let now = Utc::now();
+        let openai = InferenceProvider::OpenAI;
+        assert!(budgets.check(None, None, &openai, now).is_ok());
+        budgets.record(None, None, &openai, Decimal::TEN, now);
+        assert!(matches!(
+            budgets.check(None, None, &openai, now),
+            Err(InvalidRequestError::BudgetExceeded { budget, .. })
+                if budget == "openai"
+        ));
+        // other providers are not covered by the budget
+        assert!(
+            budgets
+                .check(None, None, &InferenceProvider::Anthropic, now)
+                .is_ok()
+        );
+        // the budget resets with the next period
+        let tomorrow = now + chrono::Duration::days(1);
+        assert!(budgets.check(None, None, &openai, tomorrow).is_ok());
+    }
+
+    #[test]
+    fn test_thresholds_are_notified_once_per_period() {
+        let budgets = budgets(openai_budget());
+        let now = Utc::now();
+        let openai = InferenceProvider::OpenAI;
+        let alerts =
+            budgets.record(None, None, &openai, Decimal::new(4, 0), now);
+        assert!(alerts.is_empty());
+        let alerts = budgets.record(None, None, &openai, Decimal::ONE, now);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].threshold_percent, 50);
+        assert_eq!(alerts[0].spent_usd, Decimal::new(5, 0));
+        let alerts = budgets.record(None, None, &openai, Decimal::ONE, now);
+        assert!(alerts.is_empty());
+        // several thresholds can be reached at once
+        let alerts = budgets.record(None, None, &openai, Decimal::TEN, now);
+        let thresholds = alerts
+            .iter()
+            .map(|alert| alert.threshold_percent)
+            .collect::<Vec<_>>();
+        assert_eq!(thresholds, vec![90, 100]);
+
+        let tomorrow = now + chrono::Duration::days(1);
+        let alerts =
+            budgets.record(None, None, &openai, Decimal::new(5, 0), tomorrow);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].spent_usd, Decimal::new(5, 0));
+    }
+
+    #[test]
+    fn test_set_keeps_spend_of_same_budget() {
+        let budgets = budgets(openai_budget());
+        let now = Utc::now();
+        let openai = InferenceProvider::OpenAI;
+        budgets.record(None, None, &openai, Decimal::TEN, now);
+        budgets.set(
+            "openai".to_string(),
+            Budget {
+                limit_usd: Decimal::ONE_HUNDRED,
+                ..openai_budget()
+            },
+            now,
+        );
+        assert!(budgets.check(None, None, &openai, now).is_ok());
+        assert_eq!(budgets.list(now)[0].spent_usd, Decimal::TEN);
+
+        budgets.set(
+            "openai".to_string(),
+            Budget {
+                period: BudgetPeriod::Monthly,
+                ..openai_budget()
+            },
+            now,
+        );
+        assert_eq!(budgets.list(now)[0].spent_usd, Decimal::ZERO);
+        assert_eq!(
+            budgets.remove("openai").map(|b| b.period),
+            Some(BudgetPeriod::Monthly)
+        );
+        assert!(budgets.list(now).is_empty());
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::Utc;
+use futures::future::BoxFuture;
+use meltdown::Token;
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{info, warn};
+
+use crate::{app_state::AppState, error::runtime::RuntimeError};
+
+/// Reloads the budgets from their store every
+/// [`refresh_interval`](crate::config::budgets::BudgetsStoreConfig::refresh_interval),
+/// see [`Budgets::refresh`](super::Budgets::refresh).
+#[derive(Debug, Clone)]
+pub struct BudgetsRefresher {
+    app_state: AppState,
+}
+
+impl BudgetsRefresher {
+    /// `None` unless the budgets have a store.
+    #[must_use]
+    pub fn new(app_state: AppState) -> Option<Self> {
+        app_state
+            .config()
+            .budgets
+            .store
+            .is_some()
+            .then_some(Self { app_state })
+    }
+}
+
+impl meltdown::Service for BudgetsRefresher {
+    type Future = BoxFuture<'static, Result<(), RuntimeError>>;
+
+    fn run(self, mut token: Token) -> Self::Future {
+        Box::pin(async move {
+            let Some(config) = self.app_state.config().budgets.store.as_ref()
+            else {
+                return Ok(());
+            };
+            let budgets = &self.app_state.0.budgets;
+            let mut interval = interval(config.refresh_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // the budgets were just loaded
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        if let Err(e) = budgets.refresh(Utc::now()).await {
+                            warn!(error = %e, "failed to refresh budgets");
+                        }
+                    }
+                    () = &mut token => {
+                        info!(name = "budgets-refresher", "task shutting down");
+                        break;
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
@@ -66,6 +66,23 @@ impl CacheManager for RedisCacheManager {
     }
 }
 
+impl CacheClient {
+    /// Remove every cached response.
+    ///
+    /// With Redis, this flushes the whole database of the cache store, so it
+    /// must not hold any other data.
+    pub async fn clear(&self) -> Result<()> {
+        match self {
+            CacheClient::Redis(redis) => {
+                let mut conn = redis.pool.get()?;
+                let _: () = redis::cmd("FLUSHDB").query(&mut *conn)?;
+                Ok(())
+            }
+            CacheClient::Moka(moka) => moka.clear().await,
+        }
+    }
+}
+
 #[async_trait::async_trait]
 impl CacheManager for CacheClient {
     async fn get(
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+
+use crate::types::secret::Secret;
+
+/// Endpoints to administer the gateway, e.g. `/v1/usage` or the admin API
+/// under `/v1/admin`, see [`crate::admin`].
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct AdminConfig {
+    /// Requests to the admin endpoints must send this key as a bearer token
+    /// in the `Authorization` header.
+    ///
+    /// The admin endpoints are disabled if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<Secret<String>>,
+    /// Serve the admin endpoints on this port, on the address of the
+    /// gateway, instead of on the port of the gateway.
+    ///
+    /// Keeps them off the network the gateway is exposed on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+}
+
+impl AdminConfig {
+    /// Whether `authorization` is the value of an `Authorization` header
+    /// with the admin key, always `false` when the admin endpoints are
+    /// disabled.
+    ///
+    /// The key is compared in constant time.
+    #[must_use]
+    pub fn is_authorized(&self, authorization: Option<&str>) -> bool {
+        let Some(api_key) = &self.api_key else {
+            return false;
+        };
+        authorization
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|key| {
+                key.as_bytes().ct_eq(api_key.expose().as_bytes()).into()
+            })
+    }
+
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.api_key.is_some()
+    }
+
+    /// Whether the admin endpoints are served to a request received on the
+    /// admin port if `on_admin_port`, or else on the port of the gateway.
+    #[must_use]
+    pub fn serves(&self, on_admin_port: bool) -> bool {
+        self.is_enabled() && self.port.is_some() == on_admin_port
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_authorized() {
+        let config = AdminConfig {
+            api_key: Some(Secret::from("admin-key".to_string())),
+            port: None,
+        };
+        assert!(config.is_authorized(Some("Bearer admin-key")));
+        assert!(!config.is_authorized(Some("Bearer other-key")));
+        assert!(!config.is_authorized(Some("admin-key")));
+        assert!(!config.is_authorized(None));
+    }
+
+    #[test]
+    fn test_disabled_without_key() {
+        let config = AdminConfig::default();
+        assert!(!config.is_enabled());
+        assert!(!config.is_authorized(Some("Bearer ")));
+        assert!(!config.serves(false));
+    }
+
+    #[test]
+    fn test_served_on_admin_port() {
+        let mut config = AdminConfig {
+            api_key: Some(Secret::from("admin-key".to_string())),
+            port: None,
+        };
+        assert!(config.serves(false));
+        assert!(!config.serves(true));
+        config.port = Some(8081);
+        assert!(!config.serves(false));
+        assert!(config.serves(true));
+    }
+}
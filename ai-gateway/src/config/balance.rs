@@ -1,8 +1,8 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use derive_more::{AsRef, From};
 use indexmap::IndexSet;
-use nonempty_collections::{NESet, nes};
+use nonempty_collections::{NESet, NEVec, nes};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 
@@ -145,10 +145,44 @@ pub enum BalanceConfigInner {
     /// latency, but not always.
     #[serde(alias = "latency")]
     BalancedLatency { providers: NESet<InferenceProvider> },
+    /// Distributes and load balances requests among a set of providers.
+    /// Of two randomly picked providers, requests go to the one with fewer
+    /// requests in flight, so a provider that slows down quickly gets less
+    /// traffic without waiting for its latency estimate to catch up.
+    LeastPending { providers: NESet<InferenceProvider> },
+    /// Sends requests to the first healthy provider in the order listed. If
+    /// it responds with a server error, times out or rate limits us, the
+    /// request is retried against the next healthy provider, mapping the
+    /// model to one offered by that provider.
+    Failover {
+        providers: NEVec<InferenceProvider>,
+        /// Also fail over streamed responses whose stream fails right after
+        /// it started.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stream_failover: Option<StreamFailoverConfig>,
+    },
     /// Distributes and load balances requests among a set of (providers,model).
     ModelWeighted { models: NESet<WeightedModel> },
     /// Distributes and load balances requests among a set of (providers,model).
     ModelLatency { models: NESet<ModelId> },
+    /// Sends a percentage of the requests for a baseline model to a
+    /// candidate model, e.g. to migrate to a new model gradually. Requests
+    /// for any other model of the two providers are passed through, and
+    /// requests for the models of other providers are rejected.
+    ///
+    /// Requests are bucketed on a stable key, so that the requests of a user
+    /// keep going to the same model as long as the percentage is unchanged.
+    TrafficSplit {
+        baseline: ModelId,
+        candidate: ModelId,
+        /// From 0 to 100, in steps of 0.01.
+        candidate_percent: Decimal,
+        /// The header requests are bucketed on, e.g. `x-user-id`. Requests
+        /// without it are bucketed on their API key, and at random if they
+        /// are not authenticated either.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        bucket_header: Option<String>,
+    },
 }
 
 impl BalanceConfigInner {
@@ -158,7 +192,11 @@ impl BalanceConfigInner {
             Self::ProviderWeighted { providers } => {
                 providers.iter().map(|t| t.provider.clone()).collect()
             }
-            Self::BalancedLatency { providers } => {
+            Self::BalancedLatency { providers }
+            | Self::LeastPending { providers } => {
+                providers.iter().cloned().collect()
+            }
+            Self::Failover { providers, .. } => {
                 providers.iter().cloned().collect()
             }
             Self::ModelWeighted { models } => models
@@ -179,10 +217,44 @@ impl BalanceConfigInner {
                     }
                 })
                 .collect(),
+            Self::TrafficSplit {
+                baseline,
+                candidate,
+                ..
+            } => [baseline, candidate]
+                .into_iter()
+                .filter_map(ModelId::inference_provider)
+                .collect(),
+        }
+    }
+}
+
+/// Streamed responses of a [`BalanceConfigInner::Failover`] router are held
+/// back until their first chunk is received and `window` has passed since
+/// the request was sent. If the stream of a provider fails before then,
+/// nothing has been sent to the client yet, so the request is transparently
+/// retried against the next provider instead.
+///
+/// Failures after the start of a stream was forwarded still end the stream.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct StreamFailoverConfig {
+    #[serde(with = "humantime_serde", default = "default_stream_window")]
+    pub window: Duration,
+}
+
+impl Default for StreamFailoverConfig {
+    fn default() -> Self {
+        Self {
+            window: default_stream_window(),
         }
     }
 }
 
+fn default_stream_window() -> Duration {
+    Duration::from_millis(500)
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, Eq, Hash, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct WeightedProvider {
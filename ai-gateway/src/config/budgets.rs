@@ -0,0 +1,251 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Datelike, Days, Months, NaiveTime, Utc};
+use indexmap::IndexMap;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::types::{
+    provider::InferenceProvider, router::RouterId, secret::Secret,
+};
+
+/// Spend ceilings, see [`crate::budgets`].
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BudgetsConfig {
+    /// The budgets by name. Budgets can also be set and removed at runtime
+    /// with the `/v1/budgets` admin endpoint.
+    pub limits: IndexMap<String, Budget>,
+    /// Notifications sent as budgets are used up, disabled if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<BudgetWebhookConfig>,
+    /// The database the spend and the budgets set at runtime are kept in,
+    /// so that they are shared by replicas and survive restarts. Kept in
+    /// memory if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store: Option<BudgetsStoreConfig>,
+}
+
+/// See [`BudgetsConfig::store`].
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BudgetsStoreConfig {
+    /// The `sqlite://` or `postgres://` database of the budgets.
+    /// set via env vars: `AI_GATEWAY__BUDGETS__STORE__DATABASE_URL`
+    pub database_url: Secret<String>,
+    /// Maximum number of connections in the pool.
+    pub max_connections: u32,
+    /// How often the budgets and their spend are reloaded from the
+    /// database, to pick up the changes of other replicas.
+    #[serde(with = "humantime_serde")]
+    pub refresh_interval: Duration,
+}
+
+impl Default for BudgetsStoreConfig {
+    fn default() -> Self {
+        Self {
+            database_url: Secret::from(
+                "sqlite://ai-gateway-budgets.db?mode=rwc".to_string(),
+            ),
+            max_connections: 5,
+            refresh_interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A ceiling on the cost in USD of the requests matching every set field of
+/// the budget, or of all requests if none are set.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Budget {
+    pub limit_usd: Decimal,
+    pub period: BudgetPeriod,
+    /// The hash of an API key, as returned by the `/v1/usage` and
+    /// `/v1/keys` admin endpoints.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub router: Option<RouterId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<InferenceProvider>,
+}
+
+impl Budget {
+    /// Whether the budget covers a request with the API key `api_key_hash`
+    /// to `router_id`, sent to `provider`.
+    #[must_use]
+    pub fn applies_to(
+        &self,
+        api_key_hash: Option<&str>,
+        router_id: Option<&RouterId>,
+        provider: &InferenceProvider,
+    ) -> bool {
+        self.api_key_hash
+            .as_deref()
+            .is_none_or(|hash| api_key_hash == Some(hash))
+            && self
+                .router
+                .as_ref()
+                .is_none_or(|router| router_id == Some(router))
+            && self.provider.as_ref().is_none_or(|p| p == provider)
+    }
+}
+
+/// Budgets reset at midnight UTC, monthly budgets on the first day of the
+/// month.
+#[derive(
+    Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash, Default,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum BudgetPeriod {
+    Daily,
+    #[default]
+    Monthly,
+}
+
+impl BudgetPeriod {
+    /// The start of the period that `now` is in.
+    #[must_use]
+    pub fn start(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let date = match self {
+            Self::Daily => now.date_naive(),
+            Self::Monthly => now
+                .date_naive()
+                .with_day(1)
+                .expect("every month has a first day"),
+        };
+        date.and_time(NaiveTime::MIN).and_utc()
+    }
+
+    /// The start of the period following the one starting at `start`.
+    #[must_use]
+    pub fn next_start(self, start: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Self::Daily => start.checked_add_days(Days::new(1)),
+            Self::Monthly => start.checked_add_months(Months::new(1)),
+        }
+        .expect("dates of requests are never near the end of time")
+    }
+}
+
+/// The notifications of budgets are `POST`ed as JSON to `url`, see
+/// [`BudgetAlert`](crate::budgets::BudgetAlert).
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct BudgetWebhookConfig {
+    pub url: Url,
+    /// Percentages of the limit of a budget. A notification is sent once
+    /// per period when the spend of a budget reaches each of them.
+    #[serde(default = "default_thresholds")]
+    pub thresholds: Vec<u32>,
+    #[serde(with = "humantime_serde", default = "default_timeout")]
+    pub timeout: Duration,
+}
+
+fn default_thresholds() -> Vec<u32> {
+    vec![50, 90, 100]
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budgets_config() {
+        let yaml = r"
+limits:
+  openai-daily:
+    limit-usd: 50
+    period: daily
+    provider: openai
+  team-a:
+    limit-usd: '120.50'
+    period: monthly
+    router: my-router
+    api-key-hash: abc123
+webhook:
+  url: https://hooks.example.com/budgets
+";
+        let config = serde_yml::from_str::<BudgetsConfig>(yaml).unwrap();
+        let openai = &config.limits["openai-daily"];
+        assert_eq!(openai.limit_usd, Decimal::new(50, 0));
+        assert_eq!(openai.period, BudgetPeriod::Daily);
+        assert_eq!(openai.provider, Some(InferenceProvider::OpenAI));
+        let team = &config.limits["team-a"];
+        assert_eq!(team.limit_usd, Decimal::new(12050, 2));
+        assert_eq!(team.router, Some(RouterId::Named("my-router".into())));
+        let webhook = config.webhook.as_ref().unwrap();
+        assert_eq!(webhook.thresholds, vec![50, 90, 100]);
+        assert_eq!(webhook.timeout, Duration::from_secs(10));
+        assert!(config.store.is_none());
+
+        let serialized = serde_json::to_value(&config).unwrap();
+        let deserialized =
+            serde_json::from_value::<BudgetsConfig>(serialized).unwrap();
+        assert_eq!(config, deserialized);
+    }
+
+    #[test]
+    fn test_budgets_store_config() {
+        let yaml = r"
+store:
+  database-url: postgres://localhost/gateway
+";
+        let config = serde_yml::from_str::<BudgetsConfig>(yaml).unwrap();
+        let store = config.store.unwrap();
+        assert_eq!(store.database_url.expose(), "postgres://localhost/gateway");
+        assert_eq!(store.max_connections, 5);
+        assert_eq!(store.refresh_interval, Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_applies_to() {
+        let router_id = RouterId::Named("my-router".into());
+        let budget = Budget {
+            limit_usd: Decimal::ONE,
+            period: BudgetPeriod::Daily,
+            api_key_hash: None,
+            router: Some(router_id.clone()),
+            provider: Some(InferenceProvider::OpenAI),
+        };
+        assert!(budget.applies_to(
+            Some("hash"),
+            Some(&router_id),
+            &InferenceProvider::OpenAI
+        ));
+        assert!(!budget.applies_to(
+            None,
+            Some(&router_id),
+            &InferenceProvider::Anthropic
+        ));
+        assert!(!budget.applies_to(None, None, &InferenceProvider::OpenAI));
+    }
+
+    #[test]
+    fn test_period_start() {
+        let now = "2025-01-31T17:45:00Z".parse::<DateTime<Utc>>().unwrap();
+        let day = BudgetPeriod::Daily.start(now);
+        assert_eq!(
+            day,
+            "2025-01-31T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            BudgetPeriod::Daily.next_start(day),
+            "2025-02-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        let month = BudgetPeriod::Monthly.start(now);
+        assert_eq!(
+            month,
+            "2025-01-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+        assert_eq!(
+            BudgetPeriod::Monthly.next_start(month),
+            "2025-02-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap()
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+/// Policies that inspect the prompts sent to providers and the completions
+/// they return, in the order they are applied.
+#[derive(Debug, Default, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct GuardrailsConfig {
+    pub policies: Vec<GuardrailPolicyConfig>,
+}
+
+impl GuardrailsConfig {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub struct GuardrailPolicyConfig {
+    /// Included in the error returned when the policy denies a request.
+    pub name: String,
+    #[serde(default)]
+    pub apply_to: GuardrailStages,
+    #[serde(flatten)]
+    pub kind: GuardrailKind,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+pub enum GuardrailKind {
+    /// Matches the text against regular expressions.
+    Regex {
+        patterns: Vec<String>,
+        #[serde(default)]
+        action: GuardrailAction,
+        #[serde(default = "default_replacement")]
+        replacement: String,
+    },
+    /// Matches the text against a list of words or phrases.
+    Keywords {
+        keywords: Vec<String>,
+        #[serde(default, rename = "case-sensitive")]
+        case_sensitive: bool,
+        #[serde(default)]
+        action: GuardrailAction,
+        #[serde(default = "default_replacement")]
+        replacement: String,
+    },
+    /// Delegates the decision to an external policy engine, see
+    /// [`HttpGuardrail`](crate::middleware::guardrails::policies::HttpGuardrail)
+    /// for the protocol.
+    Http {
+        url: Url,
+        #[serde(with = "humantime_serde", default = "default_timeout")]
+        timeout: Duration,
+    },
+}
+
+/// What a policy does with matching text.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum GuardrailAction {
+    /// Reject the request.
+    #[default]
+    Deny,
+    /// Replace the matching text with the replacement.
+    Redact,
+}
+
+/// Whether a policy inspects the requests, the responses, or both.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, PartialEq, Eq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum GuardrailStages {
+    Request,
+    Response,
+    #[default]
+    Both,
+}
+
+fn default_replacement() -> String {
+    "[REDACTED]".to_string()
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_policies() {
+        let yaml = r#"
+policies:
+  - name: pii
+    type: regex
+    patterns:
+      - '\b\d{3}-\d{2}-\d{4}\b'
+    action: redact
+  - name: jailbreak
+    type: keywords
+    apply-to: request
+    keywords:
+      - ignore previous instructions
+  - name: external
+    type: http
+    url: http://localhost:8080/check
+    timeout: 1s
+"#;
+        let config = serde_yml::from_str::<GuardrailsConfig>(yaml).unwrap();
+        assert_eq!(config.policies.len(), 3);
+        assert_eq!(
+            config.policies[0].kind,
+            GuardrailKind::Regex {
+                patterns: vec![r"\b\d{3}-\d{2}-\d{4}\b".to_string()],
+                action: GuardrailAction::Redact,
+                replacement: "[REDACTED]".to_string(),
+            }
+        );
+        assert_eq!(config.policies[0].apply_to, GuardrailStages::Both);
+        assert_eq!(config.policies[1].apply_to, GuardrailStages::Request);
+        assert_eq!(
+            config.policies[1].kind,
+            GuardrailKind::Keywords {
+                keywords: vec!["ignore previous instructions".to_string()],
+                case_sensitive: false,
+                action: GuardrailAction::Deny,
+                replacement: "[REDACTED]".to_string(),
+            }
+        );
+        assert_eq!(
+            config.policies[2].kind,
+            GuardrailKind::Http {
+                url: "http://localhost:8080/check".parse().unwrap(),
+                timeout: Duration::from_secs(1),
+            }
+        );
+    }
+
+    #[test]
+    fn guardrails_config_round_trip() {
+        let config = GuardrailsConfig {
+            policies: vec![GuardrailPolicyConfig {
+                name: "jailbreak".to_string(),
+                apply_to: GuardrailStages::Request,
+                kind: GuardrailKind::Keywords {
+                    keywords: vec!["DAN mode".to_string()],
+                    case_sensitive: true,
+                    action: GuardrailAction::Deny,
+                    replacement: default_replacement(),
+                },
+            }],
+        };
+        let serialized = serde_json::to_string(&config).unwrap();
+        let deserialized =
+            serde_json::from_str::<GuardrailsConfig>(&serialized).unwrap();
+        assert_eq!(config, deserialized);
+    }
+}
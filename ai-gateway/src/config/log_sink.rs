@@ -0,0 +1,135 @@
+use std::{path::PathBuf, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// Writing a record of every request, with its request and response
+/// bodies, as JSON lines to a file or an S3 compatible bucket.
+///
+/// See [`crate::logger::sink`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LogSinkConfig {
+    pub backend: LogSinkBackend,
+    /// Records are written once this many are buffered, or every
+    /// `flush-interval`, whichever comes first.
+    pub batch_size: usize,
+    #[serde(with = "humantime_serde")]
+    pub flush_interval: Duration,
+    /// The number of records that can wait to be written. Records are
+    /// dropped when the buffer is full rather than slowing down requests.
+    pub buffer_size: usize,
+    pub redact: RedactionConfig,
+}
+
+impl Default for LogSinkConfig {
+    fn default() -> Self {
+        Self {
+            backend: LogSinkBackend::default(),
+            batch_size: default_batch_size(),
+            flush_interval: default_flush_interval(),
+            buffer_size: default_buffer_size(),
+            redact: RedactionConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", tag = "type")]
+pub enum LogSinkBackend {
+    /// Appends the records to a local file.
+    File { path: PathBuf },
+    /// Uploads each batch of records as an object of the bucket, under
+    /// `<prefix><yyyy>/<mm>/<dd>/`.
+    S3 {
+        #[serde(default)]
+        prefix: String,
+        #[serde(default)]
+        bucket: super::minio::Config,
+    },
+}
+
+impl Default for LogSinkBackend {
+    fn default() -> Self {
+        Self::File {
+            path: PathBuf::from("ai-gateway-requests.jsonl"),
+        }
+    }
+}
+
+/// What is removed from the request and response bodies before they are
+/// written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct RedactionConfig {
+    /// Replace strings that look like provider API keys, e.g. `sk-...`.
+    pub api_keys: bool,
+    /// Replace the prompts and completions, keeping only the structure of
+    /// the bodies, e.g. the model and parameters of the request.
+    pub message_content: bool,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            api_keys: true,
+            message_content: false,
+        }
+    }
+}
+
+fn default_batch_size() -> usize {
+    100
+}
+
+fn default_flush_interval() -> Duration {
+    Duration::from_secs(5)
+}
+
+fn default_buffer_size() -> usize {
+    10_000
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_s3_backend() {
+        let yaml = r"
+backend:
+  type: s3
+  prefix: gateway/
+  bucket:
+    bucket-name: logs
+    host: http://localhost:9000
+flush-interval: 1s
+redact:
+  message-content: true
+";
+        let config = serde_yml::from_str::<LogSinkConfig>(yaml).unwrap();
+        let LogSinkBackend::S3 { prefix, bucket } = &config.backend else {
+            panic!("expected s3 backend");
+        };
+        assert_eq!(prefix, "gateway/");
+        assert_eq!(bucket.bucket_name, "logs");
+        assert_eq!(config.flush_interval, Duration::from_secs(1));
+        assert_eq!(config.batch_size, default_batch_size());
+        assert!(config.redact.api_keys);
+        assert!(config.redact.message_content);
+    }
+
+    #[test]
+    fn log_sink_config_round_trip() {
+        let config = LogSinkConfig {
+            backend: LogSinkBackend::File {
+                path: PathBuf::from("/var/log/ai-gateway.jsonl"),
+            },
+            batch_size: 10,
+            ..Default::default()
+        };
+        let serialized = serde_json::to_string(&config).unwrap();
+        let deserialized =
+            serde_json::from_str::<LogSinkConfig>(&serialized).unwrap();
+        assert_eq!(config, deserialized);
+    }
+}
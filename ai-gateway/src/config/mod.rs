@@ -1,15 +1,24 @@
+pub mod admin;
 pub mod balance;
+pub mod budgets;
 pub mod cache;
 pub mod control_plane;
 pub mod database;
 pub mod deployment_target;
 pub mod discover;
 pub mod dispatcher;
+pub mod guardrails;
 pub mod helicone;
+pub mod log_sink;
 pub mod minio;
+pub mod model_discovery;
 pub mod model_mapping;
 pub mod monitor;
+pub mod multimodal;
+pub mod pricing;
 pub mod providers;
+pub mod providers_store;
+pub mod queue;
 pub mod rate_limit;
 pub mod redis;
 pub mod response_headers;
@@ -17,6 +26,7 @@ pub mod retry;
 pub mod router;
 pub mod server;
 pub mod validation;
+pub mod virtual_keys;
 use std::path::PathBuf;
 
 use config::ConfigError;
@@ -47,6 +57,12 @@ pub enum Error {
     ),
     /// URL parsing error: {0}
     UrlParse(#[from] url::ParseError),
+    /// error reading models file: {0}
+    ReadModelsFile(std::io::Error),
+    /// deserialization error for models file: {0}
+    ModelsFileDeserialization(serde_yml::Error),
+    /// invalid models file: {0}
+    InvalidModelsFile(#[from] self::providers::ProvidersConfigError),
 }
 
 #[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq, Hash)]
@@ -57,7 +73,7 @@ pub struct MiddlewareConfig {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub rate_limit: Option<self::rate_limit::RateLimitConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub retries: Option<self::retry::RetryConfig>,
+    pub retries: Option<self::retry::RetryPolicy>,
 }
 
 #[derive(Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
@@ -79,6 +95,28 @@ pub struct Config {
     pub helicone: self::helicone::HeliconeConfig,
     /// *ALL* supported providers, independent of router configuration.
     pub providers: self::providers::ProvidersConfig,
+    /// Model metadata kept in a separate file, merged into `providers` when
+    /// the config is read.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub models_file: Option<self::providers::ModelsFileConfig>,
+    /// Loading models from the APIs of providers, in addition to the models
+    /// listed in `providers`.
+    pub model_discovery: self::model_discovery::ModelDiscoveryConfig,
+    /// Fetching the images of chat completion requests.
+    pub multimodal: self::multimodal::MultimodalConfig,
+    /// Prices of models, used to compute the cost of requests.
+    pub pricing: self::pricing::PricingConfig,
+    pub admin: self::admin::AdminConfig,
+    /// Spend ceilings per API key, router and provider.
+    pub budgets: self::budgets::BudgetsConfig,
+    /// Writing the requests and responses of the gateway to a file or
+    /// bucket, disabled if not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub log_sink: Option<self::log_sink::LogSinkConfig>,
+    /// API keys issued by the gateway with scoped permissions, disabled if
+    /// not set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub virtual_keys: Option<self::virtual_keys::VirtualKeysConfig>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cache_store: Option<self::cache::CacheStore>,
@@ -172,6 +210,21 @@ impl Config {
                 Url::parse(&bedrock_url).map_err(Error::UrlParse)?;
         }
 
+        if let Some(models_file) = &config.models_file {
+            let models = std::fs::read_to_string(&models_file.path)
+                .map_err(Error::ReadModelsFile)
+                .map_err(Box::new)?;
+            let models: self::providers::ModelsConfig =
+                serde_yml::from_str(&models)
+                    .map_err(Error::ModelsFileDeserialization)
+                    .map_err(Box::new)?;
+            config
+                .providers
+                .merge_model_metadata(models, models_file.unlisted)
+                .map_err(Error::from)
+                .map_err(Box::new)?;
+        }
+
         Ok(config)
     }
 
@@ -210,6 +263,15 @@ impl crate::tests::TestDefault for Config {
             global: MiddlewareConfig::default(),
             unified_api: MiddlewareConfig::default(),
             providers: self::providers::ProvidersConfig::default(),
+            models_file: None,
+            model_discovery:
+                self::model_discovery::ModelDiscoveryConfig::default(),
+            multimodal: self::multimodal::MultimodalConfig::default(),
+            pricing: self::pricing::PricingConfig::default(),
+            admin: self::admin::AdminConfig::default(),
+            budgets: self::budgets::BudgetsConfig::default(),
+            log_sink: None,
+            virtual_keys: None,
             helicone: self::helicone::HeliconeConfig::test_default(),
             deployment_target:
                 self::deployment_target::DeploymentTarget::Sidecar,
@@ -345,6 +407,29 @@ mod tests {
         assert_eq!(config.discover, deserialized);
     }
 
+    #[test]
+    fn model_discovery_round_trip() {
+        let config = Config::default();
+        let serialized =
+            serde_json::to_string(&config.model_discovery).unwrap();
+        let deserialized = serde_json::from_str::<
+            self::model_discovery::ModelDiscoveryConfig,
+        >(&serialized)
+        .unwrap();
+        assert_eq!(config.model_discovery, deserialized);
+    }
+
+    #[test]
+    fn multimodal_round_trip() {
+        let config = Config::default();
+        let serialized = serde_json::to_string(&config.multimodal).unwrap();
+        let deserialized = serde_json::from_str::<
+            self::multimodal::MultimodalConfig,
+        >(&serialized)
+        .unwrap();
+        assert_eq!(config.multimodal, deserialized);
+    }
+
     #[test]
     fn response_headers_round_trip() {
         let config = Config::default();
@@ -403,6 +488,30 @@ mod tests {
         assert_eq!(config.providers, deserialized);
     }
 
+    #[test]
+    fn pricing_round_trip() {
+        let config = Config::default();
+        let serialized = serde_json::to_string(&config.pricing).unwrap();
+        let deserialized =
+            serde_json::from_str::<self::pricing::PricingConfig>(&serialized)
+                .unwrap();
+        assert_eq!(config.pricing, deserialized);
+    }
+
+    #[test]
+    fn log_sink_round_trip() {
+        let config = Config {
+            log_sink: Some(self::log_sink::LogSinkConfig::default()),
+            ..Default::default()
+        };
+        let serialized = serde_json::to_string(&config.log_sink).unwrap();
+        let deserialized = serde_json::from_str::<
+            Option<self::log_sink::LogSinkConfig>,
+        >(&serialized)
+        .unwrap();
+        assert_eq!(config.log_sink, deserialized);
+    }
+
     #[test]
     fn cache_store_round_trip() {
         let config = Config::default();
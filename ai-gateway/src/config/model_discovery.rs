@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use indexmap::IndexSet;
+use serde::{Deserialize, Serialize};
+
+use crate::types::provider::InferenceProvider;
+
+/// Loading the models of providers from their APIs, in addition to the
+/// models listed in the providers config.
+///
+/// See [`crate::discover::provider_models`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ModelDiscoveryConfig {
+    pub enabled: bool,
+    /// How often the models are reloaded after they are loaded on startup.
+    #[serde(with = "humantime_serde")]
+    pub refresh_interval: Duration,
+    /// Bounds each request to the models endpoint of a provider.
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    /// The providers to discover the models of. Every enabled provider with
+    /// a models endpoint if empty.
+    #[serde(skip_serializing_if = "IndexSet::is_empty")]
+    pub providers: IndexSet<InferenceProvider>,
+}
+
+impl ModelDiscoveryConfig {
+    /// Whether the models of `provider` should be discovered.
+    #[must_use]
+    pub fn discovers(&self, provider: &InferenceProvider) -> bool {
+        self.enabled
+            && (self.providers.is_empty() || self.providers.contains(provider))
+    }
+}
+
+impl Default for ModelDiscoveryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            refresh_interval: default_refresh_interval(),
+            timeout: default_timeout(),
+            providers: IndexSet::new(),
+        }
+    }
+}
+
+fn default_refresh_interval() -> Duration {
+    Duration::from_hours(1)
+}
+
+fn default_timeout() -> Duration {
+    Duration::from_secs(10)
+}
@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+const MB: usize = 1024 * 1024;
+
+/// The images of chat completion requests, see
+/// [`crate::middleware::multimodal`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct MultimodalConfig {
+    /// Fetch the images referenced by http URL and inline them for the
+    /// providers that can't fetch them themselves. Only public addresses are
+    /// fetched, and redirects are not followed.
+    ///
+    /// If disabled, images referenced by URL are rejected for those
+    /// providers, so routers fail over to one that fetches them itself.
+    pub fetch_images: bool,
+    /// The size of the largest image fetched, in bytes, before it is
+    /// downscaled to the size the provider accepts.
+    pub max_image_bytes: usize,
+}
+
+impl Default for MultimodalConfig {
+    fn default() -> Self {
+        Self {
+            fetch_images: false,
+            max_image_bytes: 20 * MB,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fetching_images_is_opt_in() {
+        let config = serde_yml::from_str::<MultimodalConfig>("{}").unwrap();
+        assert_eq!(config, MultimodalConfig::default());
+        assert!(!config.fetch_images);
+
+        let yaml = r"
+fetch-images: true
+max-image-bytes: 1048576
+";
+        let config = serde_yml::from_str::<MultimodalConfig>(yaml).unwrap();
+        assert!(config.fetch_images);
+        assert_eq!(config.max_image_bytes, MB);
+    }
+}
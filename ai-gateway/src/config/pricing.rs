@@ -0,0 +1,150 @@
+use derive_more::AsRef;
+use indexmap::IndexMap;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    metering::Usage,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+const PRICING_YAML: &str = include_str!("../../config/embedded/pricing.yaml");
+
+const TOKENS_PER_PRICE_UNIT: Decimal =
+    Decimal::from_parts(1_000_000, 0, 0, false, 0);
+
+/// Prices of models in USD per million tokens, keyed by provider and then
+/// by model name.
+///
+/// Defaults to the embedded `pricing.yaml`, entries in the config are merged
+/// on top of it.
+#[derive(Debug, Clone, Deserialize, Serialize, AsRef, PartialEq, Eq)]
+pub struct PricingConfig(
+    pub IndexMap<InferenceProvider, IndexMap<String, ModelPricing>>,
+);
+
+impl Default for PricingConfig {
+    fn default() -> Self {
+        serde_yml::from_str(PRICING_YAML).expect("Always valid if tests pass")
+    }
+}
+
+impl PricingConfig {
+    /// The pricing of `model`, looked up by its exact id first, e.g.
+    /// `gpt-4o-2024-08-06`, and then by its name without a version, e.g.
+    /// `gpt-4o`.
+    #[must_use]
+    pub fn get(
+        &self,
+        provider: &InferenceProvider,
+        model: &ModelId,
+    ) -> Option<&ModelPricing> {
+        let models = self.0.get(provider)?;
+        models
+            .get(&model.to_string())
+            .or_else(|| models.get(&model.as_model_name().to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ModelPricing {
+    /// USD per million prompt tokens.
+    pub input: Decimal,
+    /// USD per million completion tokens.
+    pub output: Decimal,
+    /// USD per million prompt tokens read from the prompt cache of the
+    /// provider. Priced as `input` if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_input: Option<Decimal>,
+}
+
+impl ModelPricing {
+    /// The cost of `usage` in USD.
+    #[must_use]
+    pub fn cost(&self, usage: &Usage) -> Decimal {
+        let cached = usage.cached_prompt_tokens.min(usage.prompt_tokens);
+        let uncached = usage.prompt_tokens - cached;
+        let cost = Decimal::from(uncached) * self.input
+            + Decimal::from(cached) * self.cached_input.unwrap_or(self.input)
+            + Decimal::from(usage.completion_tokens) * self.output;
+        cost / TOKENS_PER_PRICE_UNIT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_pricing_config_loads_from_yaml_string() {
+        let pricing = PricingConfig::default();
+        assert!(pricing.as_ref().contains_key(&InferenceProvider::OpenAI));
+    }
+
+    #[test]
+    fn test_lookup_by_model_name() {
+        let pricing = PricingConfig::default();
+        let model = ModelId::from_str_and_provider(
+            InferenceProvider::OpenAI,
+            "gpt-4o-mini-2024-07-18",
+        )
+        .unwrap();
+        let model_pricing =
+            pricing.get(&InferenceProvider::OpenAI, &model).unwrap();
+        assert_eq!(model_pricing.input, Decimal::new(15, 2));
+    }
+
+    #[test]
+    fn test_exact_model_id_takes_precedence() {
+        let yaml = r"
+openai:
+  gpt-4o:
+    input: 2.50
+    output: 10.00
+  gpt-4o-2024-05-13:
+    input: 5.00
+    output: 15.00
+";
+        let pricing: PricingConfig = serde_yml::from_str(yaml).unwrap();
+        let model = ModelId::from_str_and_provider(
+            InferenceProvider::OpenAI,
+            "gpt-4o-2024-05-13",
+        )
+        .unwrap();
+        let model_pricing =
+            pricing.get(&InferenceProvider::OpenAI, &model).unwrap();
+        assert_eq!(model_pricing.input, Decimal::new(5, 0));
+    }
+
+    #[test]
+    fn test_cost() {
+        let pricing = ModelPricing {
+            input: Decimal::new(250, 2),
+            output: Decimal::new(10, 0),
+            cached_input: Some(Decimal::new(125, 2)),
+        };
+        let usage = Usage {
+            prompt_tokens: 1_000,
+            completion_tokens: 500,
+            cached_prompt_tokens: 400,
+        };
+        // 600 * 2.50 + 400 * 1.25 + 500 * 10 = 7000 per million
+        assert_eq!(pricing.cost(&usage), Decimal::new(7, 3));
+    }
+
+    #[test]
+    fn test_cached_input_defaults_to_input_price() {
+        let pricing = ModelPricing {
+            input: Decimal::new(3, 0),
+            output: Decimal::new(15, 0),
+            cached_input: None,
+        };
+        let usage = Usage {
+            prompt_tokens: 1_000,
+            completion_tokens: 0,
+            cached_prompt_tokens: 1_000,
+        };
+        assert_eq!(pricing.cost(&usage), Decimal::new(3, 3));
+    }
+}
@@ -1,14 +1,31 @@
-use std::fmt;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    hash::{DefaultHasher, Hash, Hasher},
+    num::{NonZeroU32, NonZeroU64},
+    path::{Path, PathBuf},
+    time::Duration,
+};
 
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc, Weekday};
 use derive_more::{AsRef, Deref, DerefMut};
+use http::{HeaderMap, HeaderName, HeaderValue};
 use indexmap::{IndexMap, IndexSet};
+use rust_decimal::Decimal;
+use rustc_hash::FxHashMap as HashMap;
 use serde::{
     Deserialize, Deserializer, Serialize, Serializer,
     de::{self, MapAccess, Visitor},
 };
 use url::Url;
 
-use crate::types::{model_id::ModelId, provider::InferenceProvider};
+use crate::{
+    error::mapper::MapperError,
+    types::{
+        model_id::{DEFAULT_MODEL_SUFFIXES, ModelId, Version},
+        provider::InferenceProvider,
+    },
+};
 
 const PROVIDERS_YAML: &str =
     include_str!("../../config/embedded/providers.yaml");
@@ -20,260 +37,5531 @@ pub(crate) const DEFAULT_ANTHROPIC_VERSION: &str = "2023-06-01";
 #[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct GlobalProviderConfig {
-    /// NOTE: In the future we can delete the `model` field and
-    /// instead load the models from the provider's respective APIs
     pub models: IndexSet<ModelId>,
+    /// Glob patterns (`*` and `?`) in `models`, e.g. `gpt-4o-2024-*`, see
+    /// [`GlobalProviderConfig::matches_model`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub model_patterns: Vec<String>,
     pub base_url: Url,
-    #[serde(default)]
-    pub version: Option<String>,
+    /// The API versions accepted for this provider, most preferred first.
+    ///
+    /// Configured as either a single version or a list of versions.
+    #[serde(
+        default,
+        with = "api_versions",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub version: Vec<String>,
+    /// Sent as the `OpenAI-Organization` header for OpenAI-style providers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+    /// Sent as the `OpenAI-Project` header for OpenAI-style providers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    /// Environment variable holding the API key of this provider, read by
+    /// [`GlobalProviderConfig::resolve_api_key`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
+    /// Static headers sent with every request to this provider, e.g. an
+    /// `api-key` or the `X-Org-Id` a proxy in front of it requires.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub headers: IndexMap<String, String>,
+    /// Other names this provider answers to, see [`ProvidersConfig::get`].
+    #[serde(default, skip_serializing_if = "IndexSet::is_empty")]
+    pub aliases: IndexSet<InferenceProvider>,
+    /// Overrides the global dispatcher timeouts for this provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<TimeoutConfig>,
+    /// Limits applying to the provider as a whole.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<ProviderLimits>,
+    /// Per-model settings for models declared with the expanded
+    /// `{ name: ..., ... }` form in `models`.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub model_metadata: IndexMap<ModelId, ModelMetadata>,
+    /// Metadata shared by every version of a model, keyed by the bare model
+    /// name (e.g. `claude-3-opus`). A model's own metadata takes precedence.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub families: IndexMap<String, ModelMetadata>,
+    /// Disabled providers keep their config but are never prewarmed or
+    /// routed to.
+    #[serde(default = "default_enabled", skip_serializing_if = "is_enabled")]
+    pub enabled: bool,
+    /// Checked instead of the `base_url` when prewarming, see
+    /// [`ProvidersConfig::prewarm`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check_url: Option<Url>,
+    /// Trailing model name segments that are part of the name rather than a
+    /// version, see [`ModelId::from_str_and_provider_with_suffixes`].
+    /// Defaults to [`DEFAULT_MODEL_SUFFIXES`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model_suffixes: Option<Vec<String>>,
+    /// Whether the provider is tried first or only when primaries fail.
+    #[serde(default, skip_serializing_if = "ProviderRole::is_primary")]
+    pub role: ProviderRole,
+    /// Restricts when the provider is active, see
+    /// [`GlobalProviderConfig::is_active_at`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active_hours: Option<ActiveHours>,
+    /// Mirror a sample of the traffic sent to the `shadow_of` provider to
+    /// this provider, without using its responses. See
+    /// [`ProvidersConfig::shadow_targets`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shadow_of: Option<InferenceProvider>,
+    /// The fraction of `shadow_of`'s traffic to mirror, between 0 and 1.
+    #[serde(
+        default = "default_shadow_sample_rate",
+        skip_serializing_if = "is_default_shadow_sample_rate"
+    )]
+    pub shadow_sample_rate: Decimal,
+    /// Whether model names are matched case-sensitively, defaults to
+    /// [`default_case_sensitive`]. Unset unless it deviates from the default.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub case_sensitive: Option<bool>,
+    /// How requests to AWS providers are signed, see [`AwsConfig`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aws: Option<AwsConfig>,
+    /// Marks the provider as an instance run by the operator, e.g. Ollama,
+    /// vLLM or TGI, see [`SelfHostedConfig`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub self_hosted: Option<SelfHostedConfig>,
+    /// Send chat completions to the deployment serving the model rather
+    /// than to the `base_url`, as Azure OpenAI requires, see
+    /// [`GlobalProviderConfig::deployment_url`].
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub deployments: bool,
 }
 
-/// Map of *ALL* supported providers.
+/// Settings of a provider running on the operator's own infrastructure.
 ///
-/// In order to configure subsets of providers use
-#[derive(Debug, Clone, Eq, PartialEq, Deref, DerefMut, AsRef)]
-pub struct ProvidersConfig(IndexMap<InferenceProvider, GlobalProviderConfig>);
+/// Self-hosted providers don't require an API key: requests are only
+/// authenticated if a key is configured for them anyway.
+#[derive(
+    Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct SelfHostedConfig {
+    #[serde(default, skip_serializing_if = "SelfHostedDialect::is_openai")]
+    pub dialect: SelfHostedDialect,
+    /// A PEM encoded CA certificate trusted in addition to the system roots,
+    /// for instances behind a self-signed certificate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_cert: Option<PathBuf>,
+    /// Skips verifying the instance's certificate altogether. Only meant
+    /// for development.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub accept_invalid_certs: bool,
+}
 
-impl<'de> Deserialize<'de> for ProvidersConfig {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+/// The API spoken by a self-hosted provider.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, Eq, PartialEq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum SelfHostedDialect {
+    /// `/v1/chat/completions`, as served by vLLM, TGI and Ollama.
+    #[default]
+    #[serde(rename = "openai")]
+    OpenAI,
+    /// Ollama's native `/api/chat`.
+    Ollama,
+}
+
+impl SelfHostedDialect {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn is_openai(&self) -> bool {
+        *self == SelfHostedDialect::OpenAI
+    }
+}
+
+/// Signing settings of an AWS provider such as Bedrock.
+///
+/// Requests are signed with SigV4 using the provider key, i.e. static
+/// credentials with an optional session token. With `assume-role` set, these
+/// credentials are only used to assume the role, and requests are signed
+/// with the role's temporary credentials instead.
+#[derive(
+    Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct AwsConfig {
+    /// The region to sign requests for. Defaults to the region in the host
+    /// of the `base-url`, e.g. `us-east-1` for
+    /// `bedrock-runtime.us-east-1.amazonaws.com`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assume_role: Option<AssumeRoleConfig>,
+}
+
+/// The role assumed through STS `AssumeRole`.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub struct AssumeRoleConfig {
+    /// e.g. `arn:aws:iam::123456789012:role/bedrock-invoke`
+    pub role_arn: String,
+    #[serde(default = "default_role_session_name")]
+    pub session_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    /// How long the temporary credentials are valid for, between 900 and
+    /// 43200 seconds. Defaults to STS's default of one hour.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration_seconds: Option<u32>,
+}
+
+fn default_role_session_name() -> String {
+    "ai-gateway".to_string()
+}
+
+/// The tier of a provider, see [`ProvidersConfig::by_role`].
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, Eq, PartialEq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ProviderRole {
+    #[default]
+    Primary,
+    Fallback,
+}
+
+impl ProviderRole {
+    #[allow(clippy::trivially_copy_pass_by_ref)]
+    fn is_primary(&self) -> bool {
+        *self == ProviderRole::Primary
+    }
+}
+
+/// (De)serializes a list of API versions from either a single version or a
+/// list, serializing a single version back as a plain string.
+mod api_versions {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    pub(super) fn deserialize<'de, D>(
+        deserializer: D,
+    ) -> Result<Vec<String>, D::Error>
     where
         D: Deserializer<'de>,
     {
-        struct ProvidersConfigVisitor;
-        // Helper struct for deserializing the raw config
-        #[derive(Deserialize)]
-        #[serde(rename_all = "kebab-case")]
-        struct RawGlobalProviderConfig {
-            models: IndexSet<String>,
-            base_url: Url,
-            #[serde(default)]
-            version: Option<String>,
+        Ok(match Option::<OneOrMany>::deserialize(deserializer)? {
+            None => Vec::new(),
+            Some(OneOrMany::One(version)) => vec![version],
+            Some(OneOrMany::Many(versions)) => versions,
+        })
+    }
+
+    pub(super) fn serialize<S>(
+        versions: &[String],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match versions {
+            [version] => version.serialize(serializer),
+            versions => versions.serialize(serializer),
         }
+    }
+}
 
-        impl<'de> Visitor<'de> for ProvidersConfigVisitor {
-            type Value = ProvidersConfig;
+/// Whether `provider` distinguishes model names by case unless configured
+/// otherwise. Ollama normalizes model names to lowercase, every other
+/// provider is matched exactly.
+#[must_use]
+pub fn default_case_sensitive(provider: &InferenceProvider) -> bool {
+    !matches!(provider, InferenceProvider::Ollama)
+}
 
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str(
-                    "a map of inference providers to their configuration",
-                )
-            }
+fn parse_model(
+    provider: &InferenceProvider,
+    model: &str,
+    suffixes: Option<&[String]>,
+    case_sensitive: bool,
+) -> Result<ModelId, MapperError> {
+    let lowercased;
+    let model = if case_sensitive {
+        model
+    } else {
+        lowercased = model.to_lowercase();
+        &lowercased
+    };
+    match suffixes {
+        Some(suffixes) => ModelId::from_str_and_provider_with_suffixes(
+            provider.clone(),
+            model,
+            suffixes,
+        ),
+        None => ModelId::from_str_and_provider(provider.clone(), model),
+    }
+}
 
-            fn visit_map<V>(
-                self,
-                mut map: V,
-            ) -> Result<ProvidersConfig, V::Error>
-            where
-                V: MapAccess<'de>,
-            {
-                let mut providers = IndexMap::new();
+fn default_shadow_sample_rate() -> Decimal {
+    Decimal::ONE
+}
 
-                while let Some(provider) =
-                    map.next_key::<InferenceProvider>()?
-                {
-                    let raw_config: RawGlobalProviderConfig =
-                        map.next_value()?;
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_default_shadow_sample_rate(rate: &Decimal) -> bool {
+    *rate == Decimal::ONE
+}
 
-                    // Convert model strings to ModelId using the provider
-                    // context
-                    let models = raw_config
-                        .models
-                        .into_iter()
-                        .map(|model_str| {
-                            ModelId::from_str_and_provider(
-                                provider.clone(),
-                                &model_str,
-                            )
-                            .map_err(|e| {
-                                de::Error::custom(format!(
-                                    "Invalid model '{model_str}' for provider \
-                                     {provider}: {e}"
-                                ))
-                            })
-                        })
-                        .collect::<Result<IndexSet<_>, _>>()?;
+fn default_enabled() -> bool {
+    true
+}
 
-                    let config = GlobalProviderConfig {
-                        models,
-                        base_url: raw_config.base_url,
-                        version: raw_config.version,
-                    };
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn is_enabled(enabled: &bool) -> bool {
+    *enabled
+}
 
-                    providers.insert(provider, config);
-                }
+impl GlobalProviderConfig {
+    /// A config serving `models` at `base_url`, with every other setting
+    /// left at its default.
+    #[must_use]
+    pub fn new(base_url: Url, models: IndexSet<ModelId>) -> Self {
+        Self {
+            models,
+            model_patterns: Vec::new(),
+            base_url,
+            version: Vec::new(),
+            organization: None,
+            project: None,
+            api_key_env: None,
+            headers: IndexMap::new(),
+            aliases: IndexSet::new(),
+            timeout: None,
+            limits: None,
+            model_metadata: IndexMap::new(),
+            families: IndexMap::new(),
+            enabled: true,
+            health_check_url: None,
+            model_suffixes: None,
+            role: ProviderRole::Primary,
+            active_hours: None,
+            shadow_of: None,
+            shadow_sample_rate: default_shadow_sample_rate(),
+            case_sensitive: None,
+            aws: None,
+            self_hosted: None,
+            deployments: false,
+        }
+    }
 
-                Ok(ProvidersConfig(providers))
-            }
+    /// Whether the provider is enabled and, if it has `active_hours`, within
+    /// them at `now`.
+    #[must_use]
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        self.enabled
+            && self
+                .active_hours
+                .as_ref()
+                .is_none_or(|active_hours| active_hours.contains(now))
+    }
+
+    /// The API version to use when talking to this provider.
+    #[must_use]
+    pub fn preferred_version(&self) -> Option<&str> {
+        self.version.first().map(String::as_str)
+    }
+
+    #[must_use]
+    pub fn supported_versions(&self) -> &[String] {
+        &self.version
+    }
+
+    /// Parse `model` in the context of `provider`, honoring this provider's
+    /// `model_suffixes` and `case_sensitive` settings.
+    pub fn parse_model(
+        &self,
+        provider: &InferenceProvider,
+        model: &str,
+    ) -> Result<ModelId, MapperError> {
+        parse_model(
+            provider,
+            model,
+            self.model_suffixes.as_deref(),
+            self.is_case_sensitive(provider),
+        )
+    }
+
+    /// Whether `model` is one of the configured `models`, either exactly or
+    /// via one of the `model_patterns`.
+    ///
+    /// `model` is parsed in the context of `provider` like in
+    /// [`GlobalProviderConfig::parse_model`], and patterns are matched
+    /// against both the requested and the parsed model name.
+    #[must_use]
+    pub fn matches_model(
+        &self,
+        provider: &InferenceProvider,
+        model: &str,
+    ) -> bool {
+        let model_id = self.parse_model(provider, model).ok();
+        if model_id.as_ref().is_some_and(|id| self.models.contains(id)) {
+            return true;
         }
+        let requested = if self.is_case_sensitive(provider) {
+            model.to_string()
+        } else {
+            model.to_lowercase()
+        };
+        let parsed = model_id.map(|id| id.to_string());
+        self.model_patterns.iter().any(|pattern| {
+            glob_matches(pattern, &requested)
+                || parsed
+                    .as_deref()
+                    .is_some_and(|parsed| glob_matches(pattern, parsed))
+        })
+    }
 
-        deserializer.deserialize_map(ProvidersConfigVisitor)
+    /// The configured version of `model_name` (e.g. `claude-3-opus`) with the
+    /// newest [`Version`], where an implicit latest version is newer than any
+    /// dated one.
+    #[must_use]
+    pub fn latest_version_of(&self, model_name: &str) -> Option<&ModelId> {
+        self.models
+            .iter()
+            .filter_map(|model| match model {
+                ModelId::ModelIdWithVersion { id, .. }
+                    if id.model == model_name =>
+                {
+                    Some((&id.version, model))
+                }
+                ModelId::Bedrock(bedrock) if bedrock.model == model_name => {
+                    Some((&bedrock.version, model))
+                }
+                _ => None,
+            })
+            .max_by(|(version, _), (other, _)| version.cmp(other))
+            .map(|(_, model)| model)
     }
-}
 
-impl Serialize for ProvidersConfig {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        use serde::ser::SerializeMap;
-        #[derive(Serialize)]
-        #[serde(rename_all = "kebab-case")]
-        struct SerializedGlobalProviderConfig {
-            models: IndexSet<String>,
-            base_url: Url,
-            #[serde(skip_serializing_if = "Option::is_none")]
-            version: Option<String>,
+    /// Whether model names of `provider` are matched case-sensitively, see
+    /// [`default_case_sensitive`].
+    #[must_use]
+    pub fn is_case_sensitive(&self, provider: &InferenceProvider) -> bool {
+        self.case_sensitive
+            .unwrap_or_else(|| default_case_sensitive(provider))
+    }
+
+    #[must_use]
+    pub fn organization(&self) -> Option<&str> {
+        self.organization.as_deref()
+    }
+
+    #[must_use]
+    pub fn project(&self) -> Option<&str> {
+        self.project.as_deref()
+    }
+
+    /// Read the API key from the variable named by `api-key-env`.
+    ///
+    /// The variable is read on every call rather than when the config is
+    /// loaded, so a config can be loaded where the key is not available.
+    /// Returns `Ok(None)` if no variable is declared.
+    pub fn resolve_api_key(
+        &self,
+    ) -> Result<Option<String>, ProvidersConfigError> {
+        let Some(var) = &self.api_key_env else {
+            return Ok(None);
+        };
+        match std::env::var(var) {
+            Ok(key) if !key.is_empty() => Ok(Some(key)),
+            _ => Err(ProvidersConfigError::MissingApiKey(var.clone())),
         }
+    }
 
-        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+    #[must_use]
+    pub fn timeout(&self) -> Option<&TimeoutConfig> {
+        self.timeout.as_ref()
+    }
 
-        for (provider, config) in &self.0 {
-            // Create a temporary config with string model representations
-            let models_as_strings: IndexSet<String> =
-                config.models.iter().map(ToString::to_string).collect();
+    #[must_use]
+    pub fn is_self_hosted(&self) -> bool {
+        self.self_hosted.is_some()
+    }
 
-            let serialized_config = SerializedGlobalProviderConfig {
-                models: models_as_strings,
-                base_url: config.base_url.clone(),
-                version: config.version.clone(),
-            };
+    /// The API spoken by the provider if it is self-hosted, see
+    /// [`SelfHostedConfig::dialect`].
+    #[must_use]
+    pub fn dialect(&self) -> Option<SelfHostedDialect> {
+        self.self_hosted
+            .as_ref()
+            .map(|self_hosted| self_hosted.dialect)
+    }
 
-            map.serialize_entry(provider, &serialized_config)?;
+    /// The metadata shared by the family `model` belongs to, if any.
+    #[must_use]
+    pub fn family_metadata(&self, model: &ModelId) -> Option<&ModelMetadata> {
+        let model_name = model.as_model_name();
+        let family: &str = model_name.as_ref();
+        self.families.get(family)
+    }
+
+    /// The limits for `model`, falling back to its family's limits and then
+    /// the provider-level limits for any limit the model does not set itself.
+    #[must_use]
+    pub fn model_limits(&self, model: &ModelId) -> ProviderLimits {
+        let model_limits = self
+            .model_metadata
+            .get(model)
+            .and_then(|metadata| metadata.limits)
+            .unwrap_or_default();
+        let family_limits = self
+            .family_metadata(model)
+            .and_then(|metadata| metadata.limits)
+            .unwrap_or_default();
+        model_limits
+            .or(family_limits)
+            .or(self.limits.unwrap_or_default())
+    }
+
+    /// The deprecation of `model`, falling back to its family's deprecation.
+    #[must_use]
+    pub fn model_deprecation(
+        &self,
+        model: &ModelId,
+    ) -> Option<&ModelDeprecation> {
+        self.model_metadata
+            .get(model)
+            .and_then(|metadata| metadata.deprecation.as_ref())
+            .or_else(|| {
+                self.family_metadata(model)
+                    .and_then(|metadata| metadata.deprecation.as_ref())
+            })
+    }
+
+    /// Whether `model` is enabled, i.e. neither the model nor its family is
+    /// disabled.
+    #[must_use]
+    pub fn is_model_enabled(&self, model: &ModelId) -> bool {
+        self.model_metadata
+            .get(model)
+            .is_none_or(|metadata| metadata.enabled)
+            && self
+                .family_metadata(model)
+                .is_none_or(|metadata| metadata.enabled)
+    }
+
+    /// The capabilities of `model`, falling back to its family's capabilities
+    /// for any the model does not declare itself.
+    #[must_use]
+    pub fn model_capabilities(&self, model: &ModelId) -> ModelCapabilities {
+        let model_capabilities = self
+            .model_metadata
+            .get(model)
+            .and_then(|metadata| metadata.capabilities)
+            .unwrap_or_default();
+        let family_capabilities = self
+            .family_metadata(model)
+            .and_then(|metadata| metadata.capabilities)
+            .unwrap_or_default();
+        model_capabilities.or(family_capabilities)
+    }
+
+    /// Join `relative` onto the `base_url`, keeping any path the base url
+    /// already has.
+    ///
+    /// Unlike [`Url::join`], a base url without a trailing slash does not
+    /// lose its last path segment, and a leading slash on `relative` does not
+    /// replace the base path:
+    ///
+    /// ```text
+    /// https://gw.internal/openai/v1 + chat/completions
+    ///     => https://gw.internal/openai/v1/chat/completions
+    /// ```
+    #[must_use]
+    pub fn join_path(&self, relative: &str) -> Url {
+        let mut base = self.base_url.clone();
+        if !base.path().ends_with('/') {
+            let path = format!("{}/", base.path());
+            base.set_path(&path);
         }
+        base.join(relative.trim_start_matches('/'))
+            .expect("relative path joined with valid base url always succeeds")
+    }
 
-        map.end()
+    /// The chat completions url of the deployment serving `model`, for
+    /// providers such as Azure that take the deployment in the path
+    /// rather than the model in the body:
+    ///
+    /// ```text
+    /// {base_url}/openai/deployments/{deployment}/chat/completions?api-version={version}
+    /// ```
+    ///
+    /// The deployment is taken from the model's `deployment` metadata,
+    /// defaulting to the model name, and the `api-version` from
+    /// [`GlobalProviderConfig::preferred_version`].
+    #[must_use]
+    pub fn deployment_url(&self, model: &ModelId) -> Url {
+        let deployment = self
+            .model_metadata
+            .get(model)
+            .and_then(|metadata| metadata.deployment.clone())
+            .unwrap_or_else(|| model.to_string());
+        let mut url = self.join_path("openai/deployments/");
+        url.path_segments_mut()
+            .expect("http(s) urls can be a base")
+            .pop_if_empty()
+            .extend([deployment.as_str(), "chat", "completions"]);
+        if let Some(version) = self.preferred_version() {
+            url.query_pairs_mut().append_pair("api-version", version);
+        }
+        url
     }
-}
 
-impl FromIterator<(InferenceProvider, GlobalProviderConfig)>
-    for ProvidersConfig
-{
-    fn from_iter<T>(iter: T) -> Self
-    where
-        T: IntoIterator<Item = (InferenceProvider, GlobalProviderConfig)>,
-    {
-        Self(IndexMap::from_iter(iter))
+    /// The configured `headers`, validated at deserialization.
+    pub fn custom_headers(&self) -> Result<HeaderMap, http::Error> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            headers.insert(
+                HeaderName::from_bytes(name.as_bytes())?,
+                HeaderValue::from_str(value)?,
+            );
+        }
+        Ok(headers)
+    }
+
+    /// Headers derived from this config that should be sent with every
+    /// request to an OpenAI-style provider, including the
+    /// [`custom_headers`](GlobalProviderConfig::custom_headers), which take
+    /// precedence.
+    pub fn request_headers(&self) -> Result<HeaderMap, http::Error> {
+        let mut headers = HeaderMap::new();
+        if let Some(organization) = self.organization() {
+            headers.insert(
+                HeaderName::from_static("openai-organization"),
+                HeaderValue::from_str(organization)?,
+            );
+        }
+        if let Some(project) = self.project() {
+            headers.insert(
+                HeaderName::from_static("openai-project"),
+                HeaderValue::from_str(project)?,
+            );
+        }
+        headers.extend(self.custom_headers()?);
+        Ok(headers)
     }
 }
 
-impl Default for ProvidersConfig {
-    fn default() -> Self {
-        serde_yml::from_str(PROVIDERS_YAML).expect("Always valid if tests pass")
+/// Request limits for a provider or a single model.
+///
+/// Unset limits are unbounded. Zero is rejected at deserialization time.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, Eq, PartialEq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct ProviderLimits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<NonZeroU32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent: Option<NonZeroU32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_per_minute: Option<NonZeroU64>,
+}
+
+impl ProviderLimits {
+    /// Fill every limit unset in `self` from `fallback`.
+    #[must_use]
+    pub fn or(self, fallback: ProviderLimits) -> ProviderLimits {
+        ProviderLimits {
+            requests_per_minute: self
+                .requests_per_minute
+                .or(fallback.requests_per_minute),
+            max_concurrent: self.max_concurrent.or(fallback.max_concurrent),
+            tokens_per_minute: self
+                .tokens_per_minute
+                .or(fallback.tokens_per_minute),
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// What a model supports. Unset capabilities are unknown.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, Eq, PartialEq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub struct ModelCapabilities {
+    /// Maximum number of input and output tokens combined.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<NonZeroU32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_tokens: Option<NonZeroU32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_tools: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_vision: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub supports_audio: Option<bool>,
+    /// Set only for embedding models: the number of dimensions of their
+    /// embeddings, and the most a request may ask for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding_dimensions: Option<NonZeroU32>,
+}
 
-    #[test]
-    fn test_default_providers_config_loads_from_yaml_string() {
-        let _default_config = ProvidersConfig::default();
-        // just want to make sure we don't panic...
+impl ModelCapabilities {
+    /// Fill every capability unset in `self` from `fallback`.
+    #[must_use]
+    pub fn or(self, fallback: ModelCapabilities) -> ModelCapabilities {
+        ModelCapabilities {
+            context_window: self.context_window.or(fallback.context_window),
+            max_output_tokens: self
+                .max_output_tokens
+                .or(fallback.max_output_tokens),
+            supports_tools: self.supports_tools.or(fallback.supports_tools),
+            supports_vision: self.supports_vision.or(fallback.supports_vision),
+            supports_audio: self.supports_audio.or(fallback.supports_audio),
+            embedding_dimensions: self
+                .embedding_dimensions
+                .or(fallback.embedding_dimensions),
+        }
     }
+}
 
-    #[test]
-    fn test_aibadgr_provider_loads() {
-        let config = ProvidersConfig::default();
+/// Settings attached to a single model of a provider, or to a whole model
+/// family.
+///
+/// Declared by writing a model entry in its expanded form, or under
+/// `families` for settings shared by every version of a model:
+///
+/// ```yaml
+/// models:
+///   - gpt-4o-mini
+///   - name: gpt-4o
+///     limits:
+///       tokens-per-minute: 100000
+/// families:
+///   gpt-4o:
+///     capabilities:
+///       context-window: 128000
+/// ```
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub struct ModelMetadata {
+    /// Disabled models stay listed but are never routed to. Under `families`
+    /// this disables every version of the model.
+    #[serde(default = "default_enabled", skip_serializing_if = "is_enabled")]
+    pub enabled: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<ProviderLimits>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<ModelCapabilities>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation: Option<ModelDeprecation>,
+    /// The deployment serving this model on providers that route by
+    /// deployment rather than by model, see
+    /// [`GlobalProviderConfig::deployment_url`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deployment: Option<String>,
+}
 
-        // Verify AI Badgr provider is present
-        let aibadgr_provider = InferenceProvider::Named("aibadgr".into());
-        let aibadgr_config = config.get(&aibadgr_provider);
-        assert!(
-            aibadgr_config.is_some(),
-            "AI Badgr provider should be present in default config"
-        );
+/// When a model stops being served, and what should be used instead.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq, Hash)]
+#[serde(rename_all = "kebab-case")]
+pub struct ModelDeprecation {
+    /// The first day (in UTC) the model is no longer available.
+    pub sunset: NaiveDate,
+    /// The model to migrate to, parsed in the context of the same provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+}
 
-        let aibadgr_config = aibadgr_config.unwrap();
-        // Note: URL parser does NOT add trailing slash for paths
-        assert_eq!(
-            aibadgr_config.base_url.as_str(),
-            "https://aibadgr.com/api/v1"
-        );
+impl ModelDeprecation {
+    #[must_use]
+    pub fn is_sunset_at(&self, now: DateTime<Utc>) -> bool {
+        now.date_naive() >= self.sunset
+    }
+}
 
-        // Verify tier models are present (at least 3)
-        assert!(aibadgr_config.models.len() >= 3);
+impl Default for ModelMetadata {
+    fn default() -> Self {
+        Self {
+            enabled: default_enabled(),
+            limits: None,
+            capabilities: None,
+            deprecation: None,
+            deployment: None,
+        }
+    }
+}
 
-        // Check that basic, normal, and premium models exist
-        let has_basic = aibadgr_config
-            .models
-            .iter()
-            .any(|m| m.to_string().contains("basic"));
-        let has_normal = aibadgr_config
-            .models
-            .iter()
-            .any(|m| m.to_string().contains("normal"));
-        let has_premium = aibadgr_config
-            .models
-            .iter()
-            .any(|m| m.to_string().contains("premium"));
+impl ModelMetadata {
+    fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
 
-        assert!(has_basic, "Should have 'basic' model");
-        assert!(has_normal, "Should have 'normal' model");
-        assert!(has_premium, "Should have 'premium' model");
+    /// Fill every setting unset in `self` from `fallback`. The model is
+    /// disabled if either disables it.
+    #[must_use]
+    pub fn or(self, fallback: ModelMetadata) -> ModelMetadata {
+        ModelMetadata {
+            enabled: self.enabled && fallback.enabled,
+            limits: match (self.limits, fallback.limits) {
+                (Some(limits), Some(fallback)) => Some(limits.or(fallback)),
+                (limits, fallback) => limits.or(fallback),
+            },
+            capabilities: match (self.capabilities, fallback.capabilities) {
+                (Some(capabilities), Some(fallback)) => {
+                    Some(capabilities.or(fallback))
+                }
+                (capabilities, fallback) => capabilities.or(fallback),
+            },
+            deprecation: self.deprecation.or(fallback.deprecation),
+            deployment: self.deployment.or(fallback.deployment),
+        }
     }
+}
 
-    #[test]
-    fn test_providers_config_custom_deserialize() {
-        use chrono::TimeZone;
-        let yaml = r#"
-openai:
-  models:
-    - "gpt-4"
-    - "gpt-4-turbo"
-    - "gpt-4o"
-    - "gpt-4o-mini"
-  base-url: https://api.openai.com
-anthropic:
-  models:
-    - "claude-3-opus-20240229"
-    - "claude-3-sonnet-20240229"
-  base-url: https://api.anthropic.com
-  version: "2023-06-01"
-"#;
+/// A model entry in a provider's `models` list, either a plain model name or
+/// the expanded form carrying [`ModelMetadata`].
+#[derive(Debug)]
+struct RawModelEntry {
+    name: String,
+    metadata: ModelMetadata,
+}
 
-        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+impl<'de> Deserialize<'de> for RawModelEntry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct RawModelEntryVisitor;
+        #[derive(Deserialize)]
+        struct ExpandedModelEntry {
+            name: String,
+            #[serde(flatten)]
+            metadata: ModelMetadata,
+        }
 
-        // Check OpenAI provider
-        let openai_config = config.get(&InferenceProvider::OpenAI).unwrap();
-        assert_eq!(openai_config.models.len(), 4);
-        assert_eq!(openai_config.base_url.as_str(), "https://api.openai.com/");
+        impl<'de> Visitor<'de> for RawModelEntryVisitor {
+            type Value = RawModelEntry;
 
-        // Verify models are properly prefixed internally
-        let model_ids: Vec<ModelId> =
-            openai_config.models.clone().into_iter().collect();
-        assert_eq!(
-            model_ids[0],
-            ModelId::ModelIdWithVersion {
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a model name or a map with a model `name`")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<RawModelEntry, E>
+            where
+                E: de::Error,
+            {
+                Ok(RawModelEntry {
+                    name: value.to_string(),
+                    metadata: ModelMetadata::default(),
+                })
+            }
+
+            fn visit_map<V>(self, map: V) -> Result<RawModelEntry, V::Error>
+            where
+                V: MapAccess<'de>,
+            {
+                let entry = ExpandedModelEntry::deserialize(
+                    de::value::MapAccessDeserializer::new(map),
+                )?;
+                Ok(RawModelEntry {
+                    name: entry.name,
+                    metadata: entry.metadata,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(RawModelEntryVisitor)
+    }
+}
+
+#[derive(Serialize)]
+#[serde(untagged)]
+enum SerializedModelEntry<'a> {
+    Name(String),
+    Expanded {
+        name: String,
+        #[serde(flatten)]
+        metadata: &'a ModelMetadata,
+    },
+}
+
+/// Per-provider request timeouts.
+///
+/// Streaming responses can legitimately take a long time to complete, so
+/// rather than a single timeout the connection, each read, and optionally the
+/// whole request are bounded separately.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Eq, PartialEq, Hash)]
+#[serde(try_from = "RawTimeoutConfig", into = "RawTimeoutConfig")]
+pub struct TimeoutConfig {
+    /// Bounds establishing the connection, including the TLS handshake.
+    pub connect_timeout: Duration,
+    /// Bounds each read from the connection, e.g. the time between chunks
+    /// of a streaming response.
+    pub read_timeout: Duration,
+    /// Bounds the entire request, including reading the response body.
+    pub total_timeout: Option<Duration>,
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawTimeoutConfig {
+    #[serde(default = "default_connect_timeout_ms")]
+    connect_timeout_ms: u64,
+    #[serde(default = "default_read_timeout_ms")]
+    read_timeout_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    total_timeout_ms: Option<u64>,
+}
+
+fn default_connect_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_read_timeout_ms() -> u64 {
+    60_000
+}
+
+impl TryFrom<RawTimeoutConfig> for TimeoutConfig {
+    type Error = String;
+
+    fn try_from(raw: RawTimeoutConfig) -> Result<Self, Self::Error> {
+        let config = Self {
+            connect_timeout: Duration::from_millis(raw.connect_timeout_ms),
+            read_timeout: Duration::from_millis(raw.read_timeout_ms),
+            total_timeout: raw.total_timeout_ms.map(Duration::from_millis),
+        };
+        if let Some(total_timeout) = config.total_timeout {
+            if config.connect_timeout > total_timeout {
+                return Err(format!(
+                    "connect-timeout-ms ({}) must not exceed total-timeout-ms \
+                     ({})",
+                    raw.connect_timeout_ms,
+                    total_timeout.as_millis()
+                ));
+            }
+            if config.read_timeout > total_timeout {
+                return Err(format!(
+                    "read-timeout-ms ({}) must not exceed total-timeout-ms \
+                     ({})",
+                    raw.read_timeout_ms,
+                    total_timeout.as_millis()
+                ));
+            }
+        }
+        Ok(config)
+    }
+}
+
+impl From<TimeoutConfig> for RawTimeoutConfig {
+    fn from(config: TimeoutConfig) -> Self {
+        let as_millis = |duration: Duration| {
+            u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+        };
+        Self {
+            connect_timeout_ms: as_millis(config.connect_timeout),
+            read_timeout_ms: as_millis(config.read_timeout),
+            total_timeout_ms: config.total_timeout.map(as_millis),
+        }
+    }
+}
+
+/// A recurring UTC time window, e.g. off-peak hours:
+///
+/// ```yaml
+/// active-hours:
+///   start-hour: 22
+///   end-hour: 6
+///   days: [mon, tue, wed, thu, fri]
+/// ```
+///
+/// The window starts at `start-hour` and ends before `end-hour`, wrapping
+/// around midnight if `end-hour` is not after `start-hour`. Both default to
+/// the whole day. `days` restricts the window to the given days of the week
+/// (of the current time, not of when the window started), and defaults to
+/// every day.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq, Hash)]
+#[serde(try_from = "RawActiveHours", into = "RawActiveHours")]
+pub struct ActiveHours {
+    pub start_hour: u8,
+    pub end_hour: u8,
+    pub days: Vec<Weekday>,
+}
+
+impl ActiveHours {
+    #[must_use]
+    pub fn contains(&self, now: DateTime<Utc>) -> bool {
+        let hour = u8::try_from(now.hour()).unwrap_or(u8::MAX);
+        let in_hours = if self.start_hour < self.end_hour {
+            (self.start_hour..self.end_hour).contains(&hour)
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        };
+        in_hours && (self.days.is_empty() || self.days.contains(&now.weekday()))
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawActiveHours {
+    #[serde(default)]
+    start_hour: u8,
+    #[serde(default = "default_end_hour")]
+    end_hour: u8,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    days: Vec<Weekday>,
+}
+
+fn default_end_hour() -> u8 {
+    24
+}
+
+impl TryFrom<RawActiveHours> for ActiveHours {
+    type Error = String;
+
+    fn try_from(raw: RawActiveHours) -> Result<Self, Self::Error> {
+        if raw.start_hour > 23 {
+            return Err(format!(
+                "start-hour ({}) must be between 0 and 23",
+                raw.start_hour
+            ));
+        }
+        if raw.end_hour > 24 {
+            return Err(format!(
+                "end-hour ({}) must be between 0 and 24",
+                raw.end_hour
+            ));
+        }
+        if raw.start_hour == raw.end_hour {
+            return Err(format!(
+                "start-hour and end-hour ({}) must differ",
+                raw.start_hour
+            ));
+        }
+        Ok(Self {
+            start_hour: raw.start_hour,
+            end_hour: raw.end_hour,
+            days: raw.days,
+        })
+    }
+}
+
+impl From<ActiveHours> for RawActiveHours {
+    fn from(active_hours: ActiveHours) -> Self {
+        Self {
+            start_hour: active_hours.start_hour,
+            end_hour: active_hours.end_hour,
+            days: active_hours.days,
+        }
+    }
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` references in `input` against the
+/// process environment. As in a shell, the default is also used if the
+/// variable is set but empty.
+///
+/// Returns the name of the first referenced variable that is not set and has
+/// no default.
+fn interpolate_env(input: &str) -> Result<String, String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start + 2..].find('}') else {
+            break;
+        };
+        let reference = &rest[start + 2..start + 2 + len];
+        let value = match reference.split_once(":-") {
+            Some((var, default)) => std::env::var(var)
+                .ok()
+                .filter(|value| !value.is_empty())
+                .unwrap_or_else(|| default.to_string()),
+            None => {
+                std::env::var(reference).map_err(|_| reference.to_string())?
+            }
+        };
+        output.push_str(&rest[..start]);
+        output.push_str(&value);
+        rest = &rest[start + 2 + len + 1..];
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+/// A required model that [`ProvidersConfig::assert_covers`] could not resolve.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingModel {
+    pub provider: InferenceProvider,
+    pub model: String,
+    /// Whether the provider itself is configured, i.e. only the model is
+    /// missing.
+    pub provider_configured: bool,
+}
+
+impl fmt::Display for MissingModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.provider_configured {
+            write!(
+                f,
+                "model '{}' is not configured for provider {}",
+                self.model, self.provider
+            )
+        } else {
+            write!(
+                f,
+                "provider {} required for model '{}' is not configured",
+                self.provider, self.model
+            )
+        }
+    }
+}
+
+/// Why [`ProvidersConfig::explain_resolution`] did or did not resolve a model.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolutionOutcome {
+    /// The model is served by `provider`.
+    Resolved {
+        provider: InferenceProvider,
+        model: ModelId,
+    },
+    /// The requested provider is not configured.
+    ProviderNotFound { provider: InferenceProvider },
+    /// The provider is configured but disabled or outside its
+    /// `active_hours`.
+    ProviderDisabled { provider: InferenceProvider },
+    /// No provider (or not the requested one) lists the model. `suggestions`
+    /// are similarly named configured models, most similar first.
+    ModelNotListed {
+        model: String,
+        suggestions: Vec<(InferenceProvider, ModelId)>,
+    },
+    /// The model is listed but disabled, either itself or via its family.
+    ModelDisabled {
+        provider: InferenceProvider,
+        model: ModelId,
+    },
+    /// The model is listed but past its sunset date.
+    DeprecatedSunset {
+        provider: InferenceProvider,
+        model: ModelId,
+        deprecation: ModelDeprecation,
+    },
+}
+
+impl ResolutionOutcome {
+    /// The resolved model, if any.
+    #[must_use]
+    pub fn resolved(&self) -> Option<(&InferenceProvider, &ModelId)> {
+        match self {
+            ResolutionOutcome::Resolved { provider, model } => {
+                Some((provider, model))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ResolutionOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolutionOutcome::Resolved { provider, model } => {
+                write!(f, "model '{model}' resolved to provider {provider}")
+            }
+            ResolutionOutcome::ProviderNotFound { provider } => {
+                write!(f, "provider {provider} is not configured")
+            }
+            ResolutionOutcome::ProviderDisabled { provider } => {
+                write!(f, "provider {provider} is disabled")
+            }
+            ResolutionOutcome::ModelNotListed { model, suggestions } => {
+                write!(f, "model '{model}' is not configured")?;
+                if !suggestions.is_empty() {
+                    let suggestions = suggestions
+                        .iter()
+                        .map(|(provider, model)| format!("{provider}/{model}"))
+                        .collect::<Vec<_>>();
+                    write!(f, ", did you mean {}?", suggestions.join(", "))?;
+                }
+                Ok(())
+            }
+            ResolutionOutcome::ModelDisabled { provider, model } => {
+                write!(f, "model '{model}' is disabled for provider {provider}")
+            }
+            ResolutionOutcome::DeprecatedSunset {
+                provider,
+                model,
+                deprecation,
+            } => {
+                write!(
+                    f,
+                    "model '{model}' of provider {provider} was sunset on {}",
+                    deprecation.sunset
+                )?;
+                if let Some(replacement) = &deprecation.replacement {
+                    write!(f, ", use '{replacement}' instead")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Whether a `models` entry is a glob pattern rather than a model.
+fn is_model_pattern(model: &str) -> bool {
+    model.contains(['*', '?'])
+}
+
+/// Match `input` against a glob `pattern`, where `*` matches any sequence of
+/// characters (including none) and `?` matches exactly one.
+fn glob_matches(pattern: &str, input: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let input = input.chars().collect::<Vec<_>>();
+    let (mut p, mut i) = (0, 0);
+    // position of the last `*` and the input position it was tried at
+    let mut backtrack = None;
+    while i < input.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, i));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == input[i] => {
+                p += 1;
+                i += 1;
+            }
+            _ => match backtrack {
+                // let the last `*` consume one more character
+                Some((star, star_i)) => {
+                    backtrack = Some((star, star_i + 1));
+                    p = star + 1;
+                    i = star_i + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+/// Levenshtein distance between two strings.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b_chars.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b_chars.len() + 1];
+    for (i, a_char) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, b_char) in b_chars.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current[j + 1] =
+                substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b_chars.len()]
+}
+
+/// Map of *ALL* supported providers.
+///
+/// In order to configure subsets of providers use the `load-balance` config
+/// of a router, see [`RouterConfig`](crate::config::router::RouterConfig).
+///
+/// Can be embedded in a larger config, either as a nested field or
+/// flattened:
+///
+/// ```rust,ignore
+/// #[derive(Deserialize)]
+/// struct GatewayConfig {
+///     listen: String,
+///     providers: ProvidersConfig,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct FlattenedConfig {
+///     listen: String,
+///     #[serde(flatten)]
+///     providers: ProvidersConfig,
+/// }
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq, Deref, DerefMut, AsRef)]
+pub struct ProvidersConfig(IndexMap<InferenceProvider, GlobalProviderConfig>);
+
+/// Helper struct for deserializing the raw config of a single provider.
+#[derive(Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct RawGlobalProviderConfig {
+    models: Vec<RawModelEntry>,
+    base_url: String,
+    #[serde(default, deserialize_with = "api_versions::deserialize")]
+    version: Vec<String>,
+    #[serde(default)]
+    organization: Option<String>,
+    #[serde(default)]
+    project: Option<String>,
+    #[serde(default)]
+    api_key_env: Option<String>,
+    #[serde(default)]
+    headers: IndexMap<String, String>,
+    #[serde(default)]
+    aliases: IndexSet<InferenceProvider>,
+    #[serde(default)]
+    timeout: Option<TimeoutConfig>,
+    #[serde(default)]
+    limits: Option<ProviderLimits>,
+    #[serde(default)]
+    families: IndexMap<String, ModelMetadata>,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    #[serde(default)]
+    health_check_url: Option<Url>,
+    #[serde(default)]
+    model_suffixes: Option<Vec<String>>,
+    #[serde(default)]
+    role: ProviderRole,
+    #[serde(default)]
+    active_hours: Option<ActiveHours>,
+    #[serde(default)]
+    shadow_of: Option<InferenceProvider>,
+    #[serde(default = "default_shadow_sample_rate")]
+    shadow_sample_rate: Decimal,
+    #[serde(default)]
+    case_sensitive: Option<bool>,
+    #[serde(default)]
+    aws: Option<AwsConfig>,
+    #[serde(default)]
+    self_hosted: Option<SelfHostedConfig>,
+    #[serde(default)]
+    deployments: bool,
+}
+
+/// The models of a provider, the patterns among them, and the metadata of the
+/// models declared with it.
+type ParsedModels = (
+    IndexSet<ModelId>,
+    Vec<String>,
+    IndexMap<ModelId, ModelMetadata>,
+);
+
+/// Convert model strings to [`ModelId`]s using the provider context.
+fn parse_models<E: de::Error>(
+    provider: &InferenceProvider,
+    entries: Vec<RawModelEntry>,
+    suffixes: Option<&[String]>,
+    is_case_sensitive: bool,
+) -> Result<ParsedModels, E> {
+    let mut models = IndexSet::new();
+    let mut model_patterns = Vec::new();
+    let mut model_metadata = IndexMap::new();
+    for entry in entries {
+        let model_str = entry.name;
+        if is_model_pattern(&model_str) {
+            if !entry.metadata.is_empty() {
+                return Err(E::custom(format!(
+                    "Model pattern '{model_str}' of provider {provider} \
+                     cannot have metadata, use `families` instead"
+                )));
+            }
+            model_patterns.push(if is_case_sensitive {
+                model_str
+            } else {
+                model_str.to_lowercase()
+            });
+            continue;
+        }
+        let model_id =
+            parse_model(provider, &model_str, suffixes, is_case_sensitive)
+                .map_err(|e| {
+                    E::custom(format!(
+                        "Invalid model '{model_str}' for provider {provider}: \
+                         {e}"
+                    ))
+                })?;
+        if !entry.metadata.is_empty() {
+            model_metadata.insert(model_id.clone(), entry.metadata);
+        }
+        models.insert(model_id);
+    }
+    Ok((models, model_patterns, model_metadata))
+}
+
+/// Interpolate and parse the `base-url` of `provider`.
+fn parse_base_url<E: de::Error>(
+    provider: &InferenceProvider,
+    base_url: &str,
+) -> Result<Url, E> {
+    // interpolate before parsing so that the URL is normalized (e.g. the
+    // trailing slash added) the same way as a literal one
+    let base_url = interpolate_provider_env::<E>(provider, base_url)?;
+    Url::parse(&base_url)
+        .map_err(|e| e.to_string())
+        .and_then(|url| validate_base_url(&url).map(|()| url))
+        .map_err(|e| {
+            E::custom(format!(
+                "Invalid base URL '{base_url}' for provider {provider}: {e}"
+            ))
+        })
+}
+
+/// [`interpolate_env`] with an error naming `provider`.
+fn interpolate_provider_env<E: de::Error>(
+    provider: &InferenceProvider,
+    value: &str,
+) -> Result<String, E> {
+    interpolate_env(value).map_err(|var| {
+        E::custom(format!(
+            "Environment variable '{var}' referenced by provider {provider} \
+             is not set"
+        ))
+    })
+}
+
+/// Check that requests can be built by joining paths onto `base_url`.
+///
+/// Without a scheme, `localhost:11434` parses as a URL with the scheme
+/// `localhost`, which only fails once requests are sent.
+fn validate_base_url(base_url: &Url) -> Result<(), String> {
+    if !matches!(base_url.scheme(), "http" | "https") {
+        return Err(format!(
+            "scheme must be http or https, not '{}' (is the scheme missing?)",
+            base_url.scheme()
+        ));
+    }
+    if !base_url.has_host() {
+        return Err("missing host".to_string());
+    }
+    if base_url.query().is_some() {
+        return Err("must not have a query string".to_string());
+    }
+    if base_url.fragment().is_some() {
+        return Err("must not have a fragment".to_string());
+    }
+    Ok(())
+}
+
+/// Interpolate the values of `headers` and check that they are valid.
+fn parse_headers<E: de::Error>(
+    provider: &InferenceProvider,
+    headers: IndexMap<String, String>,
+) -> Result<IndexMap<String, String>, E> {
+    headers
+        .into_iter()
+        .map(|(name, value)| {
+            let value = interpolate_provider_env(provider, &value)?;
+            if let Err(e) = HeaderName::from_bytes(name.as_bytes()) {
+                return Err(E::custom(format!(
+                    "Invalid header name '{name}' for provider {provider}: {e}"
+                )));
+            }
+            if let Err(e) = HeaderValue::from_str(&value) {
+                return Err(E::custom(format!(
+                    "Invalid value of header '{name}' for provider \
+                     {provider}: {e}"
+                )));
+            }
+            Ok((name, value))
+        })
+        .collect()
+}
+
+impl RawGlobalProviderConfig {
+    fn into_config<E>(
+        self,
+        provider: &InferenceProvider,
+    ) -> Result<GlobalProviderConfig, E>
+    where
+        E: de::Error,
+    {
+        // only keep the flag if it deviates from the provider's default
+        let case_sensitive = self.case_sensitive.filter(|case_sensitive| {
+            *case_sensitive != default_case_sensitive(provider)
+        });
+        let is_case_sensitive =
+            case_sensitive.unwrap_or_else(|| default_case_sensitive(provider));
+
+        let (models, model_patterns, model_metadata) = parse_models::<E>(
+            provider,
+            self.models,
+            self.model_suffixes.as_deref(),
+            is_case_sensitive,
+        )?;
+
+        let interpolate_required =
+            |value: &str| interpolate_provider_env(provider, value);
+        let interpolate = |value: Option<String>| {
+            value.map(|value| interpolate_required(&value)).transpose()
+        };
+
+        let base_url = parse_base_url::<E>(provider, &self.base_url)?;
+        let version = self
+            .version
+            .iter()
+            .map(|version| interpolate_required(version))
+            .collect::<Result<Vec<_>, _>>()?;
+        let headers = parse_headers(provider, self.headers)?;
+
+        Ok(GlobalProviderConfig {
+            models,
+            model_patterns,
+            base_url,
+            version,
+            organization: interpolate(self.organization)?,
+            project: interpolate(self.project)?,
+            api_key_env: self.api_key_env,
+            headers,
+            aliases: self
+                .aliases
+                .iter()
+                .map(InferenceProvider::normalized)
+                .collect(),
+            timeout: self.timeout,
+            limits: self.limits,
+            model_metadata,
+            families: if is_case_sensitive {
+                self.families
+            } else {
+                self.families
+                    .into_iter()
+                    .map(|(family, metadata)| (family.to_lowercase(), metadata))
+                    .collect()
+            },
+            enabled: self.enabled,
+            health_check_url: self.health_check_url,
+            model_suffixes: self.model_suffixes,
+            role: self.role,
+            active_hours: self.active_hours,
+            shadow_of: self.shadow_of,
+            shadow_sample_rate: self.shadow_sample_rate,
+            case_sensitive,
+            aws: self.aws,
+            self_hosted: self.self_hosted,
+            deployments: self.deployments,
+        })
+    }
+}
+
+/// A [`ProvidersConfig`] that is well-formed but inconsistent.
+#[derive(
+    Debug, Clone, PartialEq, Eq, thiserror::Error, displaydoc::Display,
+)]
+pub enum ProvidersConfigError {
+    /// Alias '{alias}' of {provider} collides with a configured provider
+    AliasCollision {
+        alias: InferenceProvider,
+        provider: InferenceProvider,
+    },
+    /// Alias '{0}' is declared by more than one provider
+    DuplicateAlias(InferenceProvider),
+    /// Invalid compact providers config: {0}
+    InvalidCompactForm(String),
+    /// Provider {provider} shadows unknown provider {target}
+    UnknownShadowTarget {
+        provider: InferenceProvider,
+        target: InferenceProvider,
+    },
+    /// Shadow sample rate {rate} of provider {provider} is not between 0 and 1
+    InvalidShadowSampleRate {
+        provider: InferenceProvider,
+        rate: Decimal,
+    },
+    /// No enabled provider has the primary role
+    NoPrimaryProvider,
+    /// Invalid override for provider {provider}: {reason}
+    InvalidOverride {
+        provider: InferenceProvider,
+        reason: String,
+    },
+    /// Model metadata key '{0}' is not of the form `provider/model`
+    InvalidModelMetadataKey(String),
+    /// Model metadata for '{model}' but {provider} does not list it
+    UnlistedModelMetadata {
+        provider: InferenceProvider,
+        model: String,
+    },
+    /// API key environment variable '{0}' is not set
+    MissingApiKey(String),
+    /// Provider {0} is authenticated with AWS credentials, not `api-key-env`
+    ApiKeyEnvNotSupported(InferenceProvider),
+}
+
+/// Aliases must not shadow a configured provider or be declared twice.
+fn validate_aliases(
+    providers: &IndexMap<InferenceProvider, GlobalProviderConfig>,
+) -> Result<(), ProvidersConfigError> {
+    let mut seen_aliases = IndexSet::new();
+    for (provider, config) in providers {
+        for alias in &config.aliases {
+            if providers.contains_key(alias) {
+                return Err(ProvidersConfigError::AliasCollision {
+                    alias: alias.clone(),
+                    provider: provider.clone(),
+                });
+            }
+            if !seen_aliases.insert(alias) {
+                return Err(ProvidersConfigError::DuplicateAlias(
+                    alias.clone(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Shadows must mirror another configured provider at a sensible rate.
+fn validate_shadows(
+    providers: &IndexMap<InferenceProvider, GlobalProviderConfig>,
+) -> Result<(), ProvidersConfigError> {
+    for (provider, config) in providers {
+        if !(Decimal::ZERO..=Decimal::ONE).contains(&config.shadow_sample_rate)
+        {
+            return Err(ProvidersConfigError::InvalidShadowSampleRate {
+                provider: provider.clone(),
+                rate: config.shadow_sample_rate,
+            });
+        }
+        let Some(shadow_of) = &config.shadow_of else {
+            continue;
+        };
+        if shadow_of == provider || !providers.contains_key(shadow_of) {
+            return Err(ProvidersConfigError::UnknownShadowTarget {
+                provider: provider.clone(),
+                target: shadow_of.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Requests to Bedrock are signed with the AWS credentials of the
+/// environment, which a single API key can't replace.
+fn validate_api_key_env(
+    providers: &IndexMap<InferenceProvider, GlobalProviderConfig>,
+) -> Result<(), ProvidersConfigError> {
+    match providers.get(&InferenceProvider::Bedrock) {
+        Some(config) if config.api_key_env.is_some() => {
+            Err(ProvidersConfigError::ApiKeyEnvNotSupported(
+                InferenceProvider::Bedrock,
+            ))
+        }
+        _ => Ok(()),
+    }
+}
+
+impl<'de> Deserialize<'de> for ProvidersConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ProvidersConfigVisitor;
+
+        impl<'de> Visitor<'de> for ProvidersConfigVisitor {
+            type Value = ProvidersConfig;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str(
+                    "a map of inference providers to their configuration",
+                )
+            }
+
+            fn visit_map<V>(
+                self,
+                mut map: V,
+            ) -> Result<ProvidersConfig, V::Error>
+            where
+                V: MapAccess<'de>,
+            {
+                let mut providers = IndexMap::new();
+
+                while let Some(provider) =
+                    map.next_key::<InferenceProvider>()?
+                {
+                    let provider = provider.normalized();
+                    // the last block would silently win otherwise
+                    if providers.contains_key(&provider) {
+                        return Err(de::Error::custom(format!(
+                            "duplicate provider '{provider}' in configuration"
+                        )));
+                    }
+                    let raw_config: RawGlobalProviderConfig =
+                        map.next_value()?;
+                    let config = raw_config.into_config(&provider)?;
+
+                    providers.insert(provider, config);
+                }
+
+                let providers = ProvidersConfig(providers);
+                providers.validate().map_err(de::Error::custom)?;
+
+                Ok(providers)
+            }
+        }
+
+        deserializer.deserialize_map(ProvidersConfigVisitor)
+    }
+}
+
+impl Serialize for ProvidersConfig {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        use serde::ser::SerializeMap;
+        #[derive(Serialize)]
+        #[serde(rename_all = "kebab-case")]
+        struct SerializedGlobalProviderConfig<'a> {
+            models: Vec<SerializedModelEntry<'a>>,
+            base_url: Url,
+            #[serde(
+                skip_serializing_if = "Vec::is_empty",
+                serialize_with = "api_versions::serialize"
+            )]
+            version: Vec<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            organization: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            project: Option<String>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            api_key_env: Option<String>,
+            #[serde(skip_serializing_if = "IndexMap::is_empty")]
+            headers: IndexMap<String, String>,
+            #[serde(skip_serializing_if = "IndexSet::is_empty")]
+            aliases: IndexSet<InferenceProvider>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            timeout: Option<TimeoutConfig>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            limits: Option<ProviderLimits>,
+            #[serde(skip_serializing_if = "IndexMap::is_empty")]
+            families: &'a IndexMap<String, ModelMetadata>,
+            #[serde(skip_serializing_if = "is_enabled")]
+            enabled: bool,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            health_check_url: Option<Url>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            model_suffixes: Option<&'a [String]>,
+            #[serde(skip_serializing_if = "ProviderRole::is_primary")]
+            role: ProviderRole,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            active_hours: Option<&'a ActiveHours>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            shadow_of: Option<&'a InferenceProvider>,
+            #[serde(skip_serializing_if = "is_default_shadow_sample_rate")]
+            shadow_sample_rate: Decimal,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            case_sensitive: Option<bool>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            aws: Option<&'a AwsConfig>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            self_hosted: Option<&'a SelfHostedConfig>,
+            #[serde(skip_serializing_if = "std::ops::Not::not")]
+            deployments: bool,
+        }
+
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+
+        for (provider, config) in &self.0 {
+            // Create a temporary config with string model representations
+            let models =
+                config
+                    .models
+                    .iter()
+                    .map(|model| match config.model_metadata.get(model) {
+                        Some(metadata) => SerializedModelEntry::Expanded {
+                            name: model.to_string(),
+                            metadata,
+                        },
+                        None => SerializedModelEntry::Name(model.to_string()),
+                    })
+                    .chain(config.model_patterns.iter().map(|pattern| {
+                        SerializedModelEntry::Name(pattern.clone())
+                    }))
+                    .collect();
+
+            let serialized_config = SerializedGlobalProviderConfig {
+                models,
+                base_url: config.base_url.clone(),
+                version: config.version.clone(),
+                organization: config.organization.clone(),
+                project: config.project.clone(),
+                api_key_env: config.api_key_env.clone(),
+                headers: config.headers.clone(),
+                aliases: config.aliases.clone(),
+                timeout: config.timeout,
+                limits: config.limits,
+                families: &config.families,
+                enabled: config.enabled,
+                health_check_url: config.health_check_url.clone(),
+                model_suffixes: config.model_suffixes.as_deref(),
+                role: config.role,
+                active_hours: config.active_hours.as_ref(),
+                shadow_of: config.shadow_of.as_ref(),
+                shadow_sample_rate: config.shadow_sample_rate,
+                case_sensitive: config.case_sensitive,
+                aws: config.aws.as_ref(),
+                self_hosted: config.self_hosted.as_ref(),
+                deployments: config.deployments,
+            };
+
+            map.serialize_entry(provider, &serialized_config)?;
+        }
+
+        map.end()
+    }
+}
+
+impl FromIterator<(InferenceProvider, GlobalProviderConfig)>
+    for ProvidersConfig
+{
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = (InferenceProvider, GlobalProviderConfig)>,
+    {
+        Self(IndexMap::from_iter(iter))
+    }
+}
+
+impl ProvidersConfig {
+    /// Check the consistency rules that span providers.
+    ///
+    /// Always holds for a deserialized config, but not necessarily for one
+    /// built or modified programmatically.
+    pub fn validate(&self) -> Result<(), ProvidersConfigError> {
+        validate_aliases(&self.0)?;
+        validate_shadows(&self.0)?;
+        validate_api_key_env(&self.0)
+    }
+
+    /// Like [`ProvidersConfig::validate`], but additionally requires a
+    /// non-empty config to have at least one enabled primary provider so
+    /// that it is not accidentally all-fallback.
+    pub fn validate_strict(&self) -> Result<(), ProvidersConfigError> {
+        self.validate()?;
+        if !self.0.is_empty()
+            && self.by_role(ProviderRole::Primary).next().is_none()
+        {
+            return Err(ProvidersConfigError::NoPrimaryProvider);
+        }
+        Ok(())
+    }
+
+    #[must_use]
+    pub fn provider_count(&self) -> usize {
+        self.0.len()
+    }
+
+    /// The number of models configured across all providers.
+    #[must_use]
+    pub fn total_model_count(&self) -> usize {
+        self.0.values().map(|config| config.models.len()).sum()
+    }
+
+    /// The number of models configured for `provider`, resolving aliases.
+    /// Zero if the provider is not configured.
+    #[must_use]
+    pub fn model_count(&self, provider: &InferenceProvider) -> usize {
+        self.get(provider).map_or(0, |config| config.models.len())
+    }
+
+    /// The enabled providers, in config order.
+    ///
+    /// Disabled providers stay in the config so their models and base url
+    /// remain documented, but must never be routed to.
+    pub fn enabled(
+        &self,
+    ) -> impl Iterator<Item = (&InferenceProvider, &GlobalProviderConfig)> {
+        self.0.iter().filter(|(_, config)| config.enabled)
+    }
+
+    /// The providers that are active at `now`, in config order, see
+    /// [`GlobalProviderConfig::is_active_at`].
+    pub fn enabled_providers_at(
+        &self,
+        now: DateTime<Utc>,
+    ) -> impl Iterator<Item = (&InferenceProvider, &GlobalProviderConfig)> {
+        self.0
+            .iter()
+            .filter(move |(_, config)| config.is_active_at(now))
+    }
+
+    /// The enabled providers shadowing `primary`, with the fraction of
+    /// `primary`'s requests that should be mirrored to each.
+    #[must_use]
+    pub fn shadow_targets(
+        &self,
+        primary: &InferenceProvider,
+    ) -> Vec<(&InferenceProvider, f32)> {
+        self.0
+            .iter()
+            .filter(|(_, config)| {
+                config.enabled && config.shadow_of.as_ref() == Some(primary)
+            })
+            .map(|(provider, config)| {
+                let rate =
+                    f32::try_from(config.shadow_sample_rate).unwrap_or(0.0);
+                (provider, rate)
+            })
+            .collect()
+    }
+
+    /// The enabled providers with `role`, in config order.
+    pub fn by_role(
+        &self,
+        role: ProviderRole,
+    ) -> impl Iterator<Item = (&InferenceProvider, &GlobalProviderConfig)> {
+        self.enabled()
+            .filter(move |(_, config)| config.role == role)
+    }
+
+    /// A terse single-line summary of this config for log lines and CLI
+    /// flags, e.g.:
+    ///
+    /// ```text
+    /// openai[gpt-4,gpt-4o]@https://api.openai.com/; ollama[]@http://localhost:11434/
+    /// ```
+    ///
+    /// Only the providers, their models and base urls are included.
+    /// Everything else (versions, metadata, limits, timeouts, aliases,
+    /// enabled flags, ...) is elided, so this is not a substitute for the
+    /// YAML form.
+    #[must_use]
+    pub fn to_compact_string(&self) -> String {
+        self.0
+            .iter()
+            .map(|(provider, config)| {
+                let models = config
+                    .models
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(",");
+                format!("{provider}[{models}]@{}", config.base_url)
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    /// Parse the form produced by [`ProvidersConfig::to_compact_string`].
+    ///
+    /// Elided settings are left at their defaults.
+    pub fn from_compact_string(s: &str) -> Result<Self, ProvidersConfigError> {
+        let invalid =
+            |message: String| ProvidersConfigError::InvalidCompactForm(message);
+        let mut providers = IndexMap::new();
+        for entry in s.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            let (provider, rest) = entry.split_once('[').ok_or_else(|| {
+                invalid(format!("expected '{{provider}}[...]' in '{entry}'"))
+            })?;
+            let (models, base_url) =
+                rest.split_once("]@").ok_or_else(|| {
+                    invalid(format!(
+                        "expected '[...]@{{base-url}}' in '{entry}'"
+                    ))
+                })?;
+            if provider.is_empty() {
+                return Err(invalid(format!("missing provider in '{entry}'")));
+            }
+            let Ok(provider) = provider.parse::<InferenceProvider>();
+            let base_url = Url::parse(base_url)
+                .map_err(|e| e.to_string())
+                .and_then(|url| validate_base_url(&url).map(|()| url))
+                .map_err(|e| {
+                    invalid(format!("invalid base url '{base_url}': {e}"))
+                })?;
+            let models = models
+                .split(',')
+                .filter(|model| !model.is_empty())
+                .map(|model| {
+                    ModelId::from_str_and_provider(provider.clone(), model)
+                        .map_err(|e| {
+                            invalid(format!("invalid model '{model}': {e}"))
+                        })
+                })
+                .collect::<Result<IndexSet<_>, _>>()?;
+            providers
+                .insert(provider, GlobalProviderConfig::new(base_url, models));
+        }
+        let providers = ProvidersConfig(providers);
+        providers.validate()?;
+        Ok(providers)
+    }
+
+    /// Get the config for `provider`, resolving provider aliases.
+    ///
+    /// This shadows [`IndexMap::get`] so that a provider declared with
+    /// `aliases: [azure-openai]` is also returned for
+    /// `InferenceProvider::Named("azure-openai")`. Canonical names always win
+    /// over aliases.
+    ///
+    /// Named providers are looked up by their
+    /// [normalized](InferenceProvider::normalized) name, like they are when
+    /// deserializing, while built-in provider names must match exactly.
+    #[must_use]
+    pub fn get(
+        &self,
+        provider: &InferenceProvider,
+    ) -> Option<&GlobalProviderConfig> {
+        self.get_key_value(provider).map(|(_, config)| config)
+    }
+
+    /// Like [`Self::get`], but also returns the canonical name `provider`
+    /// resolved to, e.g. to update its config.
+    #[must_use]
+    pub fn get_key_value(
+        &self,
+        provider: &InferenceProvider,
+    ) -> Option<(&InferenceProvider, &GlobalProviderConfig)> {
+        self.0.get_key_value(provider).or_else(|| {
+            let provider = provider.normalized();
+            self.0.get_key_value(&provider).or_else(|| {
+                self.0
+                    .iter()
+                    .find(|(_, config)| config.aliases.contains(&provider))
+            })
+        })
+    }
+
+    /// Resolve `model` to the matching configured [`ModelId`] of `provider`.
+    ///
+    /// `model` is parsed in the context of `provider`, so e.g.
+    /// `claude-3-opus-20240229` only resolves if that exact snapshot is
+    /// configured.
+    #[must_use]
+    pub fn resolve_model(
+        &self,
+        provider: &InferenceProvider,
+        model: &str,
+    ) -> Option<&ModelId> {
+        let config = self.get(provider)?;
+        let model_id = config.parse_model(provider, model).ok()?;
+        config.models.get(&model_id)
+    }
+
+    /// The first provider, in config order, that lists `model`, along with
+    /// the resolved model.
+    ///
+    /// `model` is parsed in the context of each provider in turn like in
+    /// [`ProvidersConfig::resolve_model`]. Enabled and active hours are not
+    /// taken into account, see [`ProviderResolver::providers_serving`] for
+    /// that.
+    #[must_use]
+    pub fn find_provider_for_model(
+        &self,
+        model: &str,
+    ) -> Option<(&InferenceProvider, &ModelId)> {
+        self.0.keys().find_map(|provider| {
+            self.resolve_model(provider, model)
+                .map(|model_id| (provider, model_id))
+        })
+    }
+
+    /// Every provider that lists `model`, in config order, see
+    /// [`ProvidersConfig::find_provider_for_model`].
+    #[must_use]
+    pub fn find_all_providers_for_model(
+        &self,
+        model: &str,
+    ) -> Vec<(&InferenceProvider, &ModelId)> {
+        self.0
+            .keys()
+            .filter_map(|provider| {
+                self.resolve_model(provider, model)
+                    .map(|model_id| (provider, model_id))
+            })
+            .collect()
+    }
+
+    /// Check that every required `(provider, model)` pair resolves in this
+    /// config, returning all of the pairs that do not.
+    ///
+    /// Intended for CI guardrails, e.g.:
+    ///
+    /// ```rust,ignore
+    /// config.assert_covers(&[(InferenceProvider::OpenAI, "gpt-4o")])?;
+    /// ```
+    pub fn assert_covers(
+        &self,
+        required: &[(InferenceProvider, &str)],
+    ) -> Result<(), Vec<MissingModel>> {
+        let missing = required
+            .iter()
+            .filter(|(provider, model)| {
+                self.resolve_model(provider, model).is_none()
+            })
+            .map(|(provider, model)| MissingModel {
+                provider: provider.clone(),
+                model: (*model).to_string(),
+                provider_configured: self.get(provider).is_some(),
+            })
+            .collect::<Vec<_>>();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(missing)
+        }
+    }
+
+    /// Configured models whose name is close to `model`, most similar first.
+    ///
+    /// `model` may optionally be prefixed with a provider, e.g.
+    /// `openai/gpt-4-o`. Used to give actionable feedback when a requested
+    /// model is not configured.
+    #[must_use]
+    pub fn suggest_models(
+        &self,
+        model: &str,
+    ) -> Vec<(&InferenceProvider, &ModelId)> {
+        const MAX_SUGGESTIONS: usize = 5;
+        let requested = model
+            .split_once('/')
+            .filter(|(provider, _)| {
+                self.0.keys().any(|p| p.as_ref() == *provider)
+            })
+            .map_or(model, |(_, model)| model);
+        let max_distance = (requested.len() / 3).max(2);
+
+        let mut candidates = self
+            .0
+            .iter()
+            .flat_map(|(provider, config)| {
+                config.models.iter().map(move |m| (provider, m))
+            })
+            .filter_map(|(provider, model_id)| {
+                let name = model_id.to_string();
+                let distance = edit_distance(requested, &name);
+                (distance <= max_distance
+                    || name.contains(requested)
+                    || requested.contains(name.as_str()))
+                .then_some((distance, provider, model_id))
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|(distance, ..)| *distance);
+        candidates
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, provider, model_id)| (provider, model_id))
+            .collect()
+    }
+
+    /// Explain whether and why `model` resolves, for mapping routing
+    /// failures to precise errors.
+    ///
+    /// If `provider` is `None`, `model` may be prefixed with a provider (e.g.
+    /// `openai/gpt-4o`), otherwise every provider listing the model is
+    /// considered in order. The first one serving it wins, and if none does
+    /// the reason of the first one listing it is returned.
+    #[must_use]
+    pub fn explain_resolution(
+        &self,
+        provider: Option<&InferenceProvider>,
+        model: &str,
+    ) -> ResolutionOutcome {
+        self.explain_resolution_at(provider, model, Utc::now())
+    }
+
+    /// Like [`ProvidersConfig::explain_resolution`], but checks
+    /// `active_hours` and sunset dates at `now`.
+    #[must_use]
+    pub fn explain_resolution_at(
+        &self,
+        provider: Option<&InferenceProvider>,
+        model: &str,
+        now: DateTime<Utc>,
+    ) -> ResolutionOutcome {
+        let prefixed = model.split_once('/').and_then(|(provider, model)| {
+            self.0
+                .keys()
+                .find(|p| p.as_ref() == provider)
+                .map(|provider| (provider, model))
+        });
+        match (provider, prefixed) {
+            (Some(provider), _) => {
+                self.explain_provider_resolution(provider, model, now)
+            }
+            (None, Some((provider, model))) => {
+                self.explain_provider_resolution(provider, model, now)
+            }
+            (None, None) => {
+                let mut first_failure = None;
+                for provider in self.0.keys().filter(|provider| {
+                    self.resolve_model(provider, model).is_some()
+                }) {
+                    let outcome =
+                        self.explain_provider_resolution(provider, model, now);
+                    if outcome.resolved().is_some() {
+                        return outcome;
+                    }
+                    first_failure.get_or_insert(outcome);
+                }
+                first_failure.unwrap_or_else(|| {
+                    ResolutionOutcome::ModelNotListed {
+                        model: model.to_string(),
+                        suggestions: self.owned_suggestions(model),
+                    }
+                })
+            }
+        }
+    }
+
+    fn explain_provider_resolution(
+        &self,
+        provider: &InferenceProvider,
+        model: &str,
+        now: DateTime<Utc>,
+    ) -> ResolutionOutcome {
+        let Some(config) = self.get(provider) else {
+            return ResolutionOutcome::ProviderNotFound {
+                provider: provider.clone(),
+            };
+        };
+        if !config.is_active_at(now) {
+            return ResolutionOutcome::ProviderDisabled {
+                provider: provider.clone(),
+            };
+        }
+        let Some(model_id) = self.resolve_model(provider, model).cloned()
+        else {
+            return ResolutionOutcome::ModelNotListed {
+                model: model.to_string(),
+                suggestions: self.owned_suggestions(model),
+            };
+        };
+        let provider = provider.clone();
+        if !config.is_model_enabled(&model_id) {
+            return ResolutionOutcome::ModelDisabled {
+                provider,
+                model: model_id,
+            };
+        }
+        if let Some(deprecation) = config
+            .model_deprecation(&model_id)
+            .filter(|deprecation| deprecation.is_sunset_at(now))
+        {
+            return ResolutionOutcome::DeprecatedSunset {
+                provider,
+                model: model_id,
+                deprecation: deprecation.clone(),
+            };
+        }
+        ResolutionOutcome::Resolved {
+            provider,
+            model: model_id,
+        }
+    }
+
+    fn owned_suggestions(
+        &self,
+        model: &str,
+    ) -> Vec<(InferenceProvider, ModelId)> {
+        self.suggest_models(model)
+            .into_iter()
+            .map(|(provider, model)| (provider.clone(), model.clone()))
+            .collect()
+    }
+
+    /// A hash of the serialized form of this config.
+    ///
+    /// Two configs with the same providers, models and settings (in the same
+    /// order) hash to the same value, which lets derived state like
+    /// [`ModelIndex`] detect when it was built from a different config.
+    #[must_use]
+    pub fn canonical_hash(&self) -> u64 {
+        let serialized =
+            serde_json::to_vec(self).expect("providers config is serializable");
+        let mut hasher = DefaultHasher::new();
+        serialized.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// The outcome of [`ProvidersConfig::prune_deprecated`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PruneReport {
+    /// Models removed because they are past their sunset date.
+    pub removed: Vec<(InferenceProvider, ModelId)>,
+    /// Removed models that were substituted by their replacement, as
+    /// `(provider, removed, replacement)`.
+    pub replaced: Vec<(InferenceProvider, ModelId, ModelId)>,
+    /// Replacements that point at a model which is not available.
+    pub dangling: Vec<DanglingReplacement>,
+}
+
+/// A model whose `replacement` could not be resolved after pruning.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DanglingReplacement {
+    pub provider: InferenceProvider,
+    pub model: ModelId,
+    pub replacement: String,
+}
+
+impl ProvidersConfig {
+    /// Remove every model that is past its sunset date at `now`.
+    ///
+    /// If `substitute_replacements` is set, a removed model with a
+    /// `replacement` is substituted by it in the provider's model list
+    /// (keeping its position). Replacements that do not parse or are pruned
+    /// themselves, and remaining models whose replacement was pruned, are
+    /// reported as dangling rather than fixed.
+    pub fn prune_deprecated(
+        &mut self,
+        now: DateTime<Utc>,
+        substitute_replacements: bool,
+    ) -> PruneReport {
+        let mut report = PruneReport::default();
+        for (provider, config) in &mut self.0 {
+            let sunset = config
+                .models
+                .iter()
+                .filter_map(|model| {
+                    config
+                        .model_deprecation(model)
+                        .filter(|deprecation| deprecation.is_sunset_at(now))
+                        .map(|deprecation| {
+                            (model.clone(), deprecation.replacement.clone())
+                        })
+                })
+                .collect::<Vec<_>>();
+
+            for (model, replacement) in sunset.iter().cloned() {
+                let Some(index) = config.models.get_index_of(&model) else {
+                    continue;
+                };
+                config.models.shift_remove_index(index);
+                config.model_metadata.shift_remove(&model);
+                tracing::info!(
+                    provider = %provider,
+                    model = %model,
+                    "pruned sunset model"
+                );
+
+                let Some(replacement) =
+                    replacement.filter(|_| substitute_replacements)
+                else {
+                    report.removed.push((provider.clone(), model));
+                    continue;
+                };
+                let replacement_id = config
+                    .parse_model(provider, &replacement)
+                    .ok()
+                    .filter(|replacement_id| {
+                        !sunset
+                            .iter()
+                            .any(|(sunset, _)| sunset == replacement_id)
+                    });
+                if let Some(replacement_id) = replacement_id {
+                    config.models.shift_insert(index, replacement_id.clone());
+                    report.replaced.push((
+                        provider.clone(),
+                        model.clone(),
+                        replacement_id,
+                    ));
+                } else {
+                    report.dangling.push(DanglingReplacement {
+                        provider: provider.clone(),
+                        model: model.clone(),
+                        replacement,
+                    });
+                }
+                report.removed.push((provider.clone(), model));
+            }
+        }
+
+        for (provider, config) in &self.0 {
+            for model in &config.models {
+                let Some(replacement) = config
+                    .model_deprecation(model)
+                    .and_then(|d| d.replacement.as_ref())
+                else {
+                    continue;
+                };
+                let was_removed = config
+                    .parse_model(provider, replacement)
+                    .is_ok_and(|replacement_id| {
+                        !config.models.contains(&replacement_id)
+                            && report
+                                .removed
+                                .contains(&(provider.clone(), replacement_id))
+                    });
+                if was_removed {
+                    report.dangling.push(DanglingReplacement {
+                        provider: provider.clone(),
+                        model: model.clone(),
+                        replacement: replacement.clone(),
+                    });
+                }
+            }
+        }
+        report
+    }
+}
+
+/// The serialized provider fields that can be set through environment
+/// variables, see [`ProvidersConfig::to_env_vars`].
+const ENV_VAR_FIELDS: &[&str] = &[
+    "models",
+    "base-url",
+    "version",
+    "organization",
+    "project",
+    "api-key-env",
+    "headers",
+    "aliases",
+    "timeout",
+    "limits",
+    "families",
+    "enabled",
+    "health-check-url",
+    "model-suffixes",
+    "role",
+    "active-hours",
+    "shadow-of",
+    "shadow-sample-rate",
+    "case-sensitive",
+    "aws",
+    "self-hosted",
+];
+
+/// Model metadata maintained separately from the providers, keyed by
+/// `provider/model`, e.g. a `models.yaml` of:
+///
+/// ```yaml
+/// openai/gpt-4o:
+///   capabilities:
+///     context-window: 128000
+/// openrouter/anthropic/claude-3-5-sonnet:
+///   deprecation:
+///     sunset: 2026-01-01
+/// ```
+///
+/// See [`ProvidersConfig::merge_model_metadata`].
+#[derive(
+    Debug, Default, Clone, Deserialize, Serialize, Eq, PartialEq, Deref,
+)]
+pub struct ModelsConfig(IndexMap<String, ModelMetadata>);
+
+/// What to do with [`ModelsConfig`] entries for a model that is not listed
+/// under its provider.
+#[derive(
+    Debug, Default, Clone, Copy, Deserialize, Serialize, Eq, PartialEq, Hash,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum UnlistedModelPolicy {
+    /// Log a warning and ignore the entry.
+    #[default]
+    Warn,
+    /// Fail with [`ProvidersConfigError::UnlistedModelMetadata`].
+    Error,
+}
+
+/// Where to load a [`ModelsConfig`] from when reading the config.
+#[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq, Hash)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ModelsFileConfig {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub unlisted: UnlistedModelPolicy,
+}
+
+impl ProvidersConfig {
+    /// Add the `models` loaded from the API of `provider` to its models,
+    /// returning how many were not configured already.
+    ///
+    /// Configured models are kept as is, so their metadata takes precedence
+    /// over anything discovered. If the provider has `model_patterns` they
+    /// act as an allowlist, so only models matching one of them are added.
+    pub fn merge_discovered_models<I, S>(
+        &mut self,
+        provider: &InferenceProvider,
+        models: I,
+    ) -> usize
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let Some(config) = self.0.get_mut(provider) else {
+            return 0;
+        };
+        let mut added = 0;
+        for model in models {
+            let model = model.as_ref();
+            if !config.model_patterns.is_empty()
+                && !config.matches_model(provider, model)
+            {
+                continue;
+            }
+            match config.parse_model(provider, model) {
+                Ok(model_id) => {
+                    if config.models.insert(model_id) {
+                        added += 1;
+                    }
+                }
+                Err(error) => {
+                    tracing::debug!(
+                        %provider,
+                        model,
+                        %error,
+                        "skipping discovered model"
+                    );
+                }
+            }
+        }
+        added
+    }
+
+    /// Merge `models` into the metadata of the matching provider models.
+    ///
+    /// Metadata declared inline in the providers config takes precedence
+    /// over `models` for every setting it sets. Entries for providers that do
+    /// not exist or models they do not list are handled per `unlisted`.
+    pub fn merge_model_metadata(
+        &mut self,
+        models: ModelsConfig,
+        unlisted: UnlistedModelPolicy,
+    ) -> Result<(), ProvidersConfigError> {
+        for (key, metadata) in models.0 {
+            let Some((provider, model)) = key.split_once('/') else {
+                return Err(ProvidersConfigError::InvalidModelMetadataKey(key));
+            };
+            let Ok(provider) = provider.parse::<InferenceProvider>();
+            let listed = self
+                .0
+                .iter_mut()
+                .find(|(p, config)| {
+                    **p == provider || config.aliases.contains(&provider)
+                })
+                .and_then(|(p, config)| {
+                    let model_id = config.parse_model(p, model).ok()?;
+                    config
+                        .models
+                        .contains(&model_id)
+                        .then_some((model_id, config))
+                });
+            let Some((model_id, config)) = listed else {
+                match unlisted {
+                    UnlistedModelPolicy::Warn => {
+                        tracing::warn!(
+                            provider = %provider,
+                            model = %model,
+                            "ignoring metadata for model not listed by provider"
+                        );
+                        continue;
+                    }
+                    UnlistedModelPolicy::Error => {
+                        return Err(
+                            ProvidersConfigError::UnlistedModelMetadata {
+                                provider,
+                                model: model.to_string(),
+                            },
+                        );
+                    }
+                }
+            };
+            match config.model_metadata.get_mut(&model_id) {
+                Some(inline) => *inline = std::mem::take(inline).or(metadata),
+                None if !metadata.is_empty() => {
+                    config.model_metadata.insert(model_id, metadata);
+                }
+                None => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Partial provider configs overlaid onto a [`ProvidersConfig`] by
+/// [`ProvidersConfig::merge`], written like the providers config itself but
+/// with every field optional:
+///
+/// ```yaml
+/// anthropic:
+///   base-url: https://anthropic.proxy.internal
+/// groq:
+///   models:
+///     - llama-3.3-70b-versatile
+///   base-url: https://api.groq.com/openai
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct ProviderOverrides(
+    IndexMap<InferenceProvider, PartialProviderConfig>,
+);
+
+impl FromIterator<(InferenceProvider, PartialProviderConfig)>
+    for ProviderOverrides
+{
+    fn from_iter<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = (InferenceProvider, PartialProviderConfig)>,
+    {
+        Self(IndexMap::from_iter(iter))
+    }
+}
+
+/// The override of a single provider in [`ProviderOverrides`], with the
+/// fields of [`GlobalProviderConfig`] that are not set left as they are.
+///
+/// Unknown fields are rejected, so a misspelled override fails to load
+/// instead of being ignored.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PartialProviderConfig {
+    #[serde(default)]
+    models: Option<Vec<RawModelEntry>>,
+    #[serde(default)]
+    base_url: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_some_versions")]
+    version: Option<Vec<String>>,
+    #[serde(default)]
+    organization: Option<String>,
+    #[serde(default)]
+    project: Option<String>,
+    #[serde(default)]
+    api_key_env: Option<String>,
+    #[serde(default)]
+    headers: Option<IndexMap<String, String>>,
+    #[serde(default)]
+    aliases: Option<IndexSet<InferenceProvider>>,
+    #[serde(default)]
+    timeout: Option<TimeoutConfig>,
+    #[serde(default)]
+    limits: Option<ProviderLimits>,
+    #[serde(default)]
+    families: Option<IndexMap<String, ModelMetadata>>,
+    #[serde(default)]
+    enabled: Option<bool>,
+    #[serde(default)]
+    health_check_url: Option<Url>,
+    #[serde(default)]
+    model_suffixes: Option<Vec<String>>,
+    #[serde(default)]
+    role: Option<ProviderRole>,
+    #[serde(default)]
+    active_hours: Option<ActiveHours>,
+    #[serde(default)]
+    shadow_of: Option<InferenceProvider>,
+    #[serde(default)]
+    shadow_sample_rate: Option<Decimal>,
+    #[serde(default)]
+    case_sensitive: Option<bool>,
+    #[serde(default)]
+    aws: Option<AwsConfig>,
+    #[serde(default)]
+    self_hosted: Option<SelfHostedConfig>,
+    #[serde(default)]
+    deployments: Option<bool>,
+}
+
+fn deserialize_some_versions<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    api_versions::deserialize(deserializer).map(Some)
+}
+
+impl PartialProviderConfig {
+    /// The config of a provider that is not configured yet, which needs at
+    /// least its `models` and `base-url`.
+    fn into_raw(mut self) -> Result<RawGlobalProviderConfig, String> {
+        let (Some(models), Some(base_url)) =
+            (self.models.take(), self.base_url.take())
+        else {
+            return Err(
+                "new providers must set `models` and `base-url`".to_string()
+            );
+        };
+        let mut raw = RawGlobalProviderConfig {
+            models,
+            base_url,
+            version: Vec::new(),
+            organization: None,
+            project: None,
+            api_key_env: None,
+            headers: IndexMap::new(),
+            aliases: IndexSet::new(),
+            timeout: None,
+            limits: None,
+            families: IndexMap::new(),
+            enabled: default_enabled(),
+            health_check_url: None,
+            model_suffixes: None,
+            role: ProviderRole::default(),
+            active_hours: None,
+            shadow_of: None,
+            shadow_sample_rate: default_shadow_sample_rate(),
+            case_sensitive: None,
+            aws: None,
+            self_hosted: None,
+            deployments: false,
+        };
+        self.apply(&mut raw);
+        Ok(raw)
+    }
+
+    /// Replace the fields of `raw` that are set, except for `headers` and
+    /// `families` which are extended per key.
+    fn apply(self, raw: &mut RawGlobalProviderConfig) {
+        fn set<T>(field: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+        fn set_some<T>(field: &mut Option<T>, value: Option<T>) {
+            if value.is_some() {
+                *field = value;
+            }
+        }
+
+        set(&mut raw.models, self.models);
+        set(&mut raw.base_url, self.base_url);
+        set(&mut raw.version, self.version);
+        set_some(&mut raw.organization, self.organization);
+        set_some(&mut raw.project, self.project);
+        set_some(&mut raw.api_key_env, self.api_key_env);
+        raw.headers.extend(self.headers.unwrap_or_default());
+        set(&mut raw.aliases, self.aliases);
+        set_some(&mut raw.timeout, self.timeout);
+        set_some(&mut raw.limits, self.limits);
+        raw.families.extend(self.families.unwrap_or_default());
+        set(&mut raw.enabled, self.enabled);
+        set_some(&mut raw.health_check_url, self.health_check_url);
+        set_some(&mut raw.model_suffixes, self.model_suffixes);
+        set(&mut raw.role, self.role);
+        set_some(&mut raw.active_hours, self.active_hours);
+        set_some(&mut raw.shadow_of, self.shadow_of);
+        set(&mut raw.shadow_sample_rate, self.shadow_sample_rate);
+        set_some(&mut raw.case_sensitive, self.case_sensitive);
+        set_some(&mut raw.aws, self.aws);
+        set_some(&mut raw.self_hosted, self.self_hosted);
+        set(&mut raw.deployments, self.deployments);
+    }
+}
+
+impl PartialProviderConfig {
+    /// Like [`Self::apply`], but onto the config of a provider that is
+    /// already parsed. Only the values of the override are interpolated and
+    /// parsed, so the existing ones are left exactly as they are.
+    fn apply_to_config<E: de::Error>(
+        self,
+        provider: &InferenceProvider,
+        config: &mut GlobalProviderConfig,
+    ) -> Result<(), E> {
+        fn set<T>(field: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *field = value;
+            }
+        }
+        fn set_some<T>(field: &mut Option<T>, value: Option<T>) {
+            if value.is_some() {
+                *field = value;
+            }
+        }
+        let interpolate = |value: Option<String>| {
+            value
+                .map(|value| interpolate_provider_env::<E>(provider, &value))
+                .transpose()
+        };
+
+        if let Some(case_sensitive) = self.case_sensitive {
+            // only keep the flag if it deviates from the provider's default
+            config.case_sensitive = Some(case_sensitive)
+                .filter(|cs| *cs != default_case_sensitive(provider));
+        }
+        set_some(&mut config.model_suffixes, self.model_suffixes);
+        let is_case_sensitive = config
+            .case_sensitive
+            .unwrap_or_else(|| default_case_sensitive(provider));
+        if let Some(models) = self.models {
+            let (models, model_patterns, model_metadata) = parse_models::<E>(
+                provider,
+                models,
+                config.model_suffixes.as_deref(),
+                is_case_sensitive,
+            )?;
+            config.models = models;
+            config.model_patterns = model_patterns;
+            config.model_metadata = model_metadata;
+        }
+        if let Some(base_url) = self.base_url {
+            config.base_url = parse_base_url::<E>(provider, &base_url)?;
+        }
+        if let Some(version) = self.version {
+            config.version = version
+                .iter()
+                .map(|version| interpolate_provider_env::<E>(provider, version))
+                .collect::<Result<_, _>>()?;
+        }
+        set_some(&mut config.organization, interpolate(self.organization)?);
+        set_some(&mut config.project, interpolate(self.project)?);
+        set_some(&mut config.api_key_env, self.api_key_env);
+        if let Some(headers) = self.headers {
+            config
+                .headers
+                .extend(parse_headers::<E>(provider, headers)?);
+        }
+        if let Some(aliases) = self.aliases {
+            config.aliases =
+                aliases.iter().map(InferenceProvider::normalized).collect();
+        }
+        set_some(&mut config.timeout, self.timeout);
+        set_some(&mut config.limits, self.limits);
+        config.families.extend(
+            self.families.unwrap_or_default().into_iter().map(
+                |(family, metadata)| {
+                    if is_case_sensitive {
+                        (family, metadata)
+                    } else {
+                        (family.to_lowercase(), metadata)
+                    }
+                },
+            ),
+        );
+        set(&mut config.enabled, self.enabled);
+        set_some(&mut config.health_check_url, self.health_check_url);
+        set(&mut config.role, self.role);
+        set_some(&mut config.active_hours, self.active_hours);
+        set_some(&mut config.shadow_of, self.shadow_of);
+        set(&mut config.shadow_sample_rate, self.shadow_sample_rate);
+        set_some(&mut config.aws, self.aws);
+        set_some(&mut config.self_hosted, self.self_hosted);
+        set(&mut config.deployments, self.deployments);
+        Ok(())
+    }
+}
+
+impl ProvidersConfig {
+    /// Overlay `overrides` onto this config, e.g. to extend the embedded
+    /// defaults rather than replace them.
+    ///
+    /// Fields set by an override replace the existing ones, except for
+    /// `headers` and `families` which are extended per key. Providers that
+    /// are not configured yet are added after the existing ones and must set
+    /// `models` and `base-url`. On error, `self` is left unchanged.
+    pub fn merge(
+        &mut self,
+        overrides: ProviderOverrides,
+    ) -> Result<(), ProvidersConfigError> {
+        let mut merged = self.0.clone();
+        for (provider, partial) in overrides.0 {
+            let invalid =
+                |reason: String| ProvidersConfigError::InvalidOverride {
+                    provider: provider.clone(),
+                    reason,
+                };
+            let config = match merged.get(&provider) {
+                Some(config) => {
+                    let mut config = config.clone();
+                    partial
+                        .apply_to_config::<serde_json::Error>(
+                            &provider,
+                            &mut config,
+                        )
+                        .map_err(|e| invalid(e.to_string()))?;
+                    config
+                }
+                None => partial
+                    .into_raw()
+                    .map_err(invalid)?
+                    .into_config::<serde_json::Error>(&provider)
+                    .map_err(|e| invalid(e.to_string()))?,
+            };
+            merged.insert(provider, config);
+        }
+        let merged = ProvidersConfig(merged);
+        merged.validate()?;
+        *self = merged;
+        Ok(())
+    }
+
+    /// Consuming version of [`ProvidersConfig::merge`].
+    pub fn with_overrides(
+        mut self,
+        overrides: ProviderOverrides,
+    ) -> Result<Self, ProvidersConfigError> {
+        self.merge(overrides)?;
+        Ok(self)
+    }
+}
+
+impl ProvidersConfig {
+    /// The environment variables that reproduce this config when read with
+    /// [`ProvidersConfig::from_env_vars`].
+    ///
+    /// Every serialized field of a provider becomes a
+    /// `{PREFIX}_{PROVIDER}_{FIELD}` variable, e.g. `AIGW_OPENAI_BASE_URL`.
+    /// Plain string values are emitted as-is, `models` as a comma separated
+    /// list when none of them carry metadata, and everything else as JSON.
+    ///
+    /// Provider names are uppercased with `-` replaced by `_` in the variable
+    /// names, so the names of the providers are also listed, in order, in
+    /// `{PREFIX}_PROVIDERS`, e.g. `AIGW_PROVIDERS=openai,my_provider`.
+    #[must_use]
+    pub fn to_env_vars(&self, prefix: &str) -> BTreeMap<String, String> {
+        let serialized = serde_json::to_value(self)
+            .expect("providers config is serializable");
+        let mut vars = BTreeMap::new();
+        let Some(providers) = serialized.as_object() else {
+            return vars;
+        };
+        vars.insert(
+            format!("{prefix}_PROVIDERS").to_uppercase(),
+            providers.keys().cloned().collect::<Vec<_>>().join(","),
+        );
+        for (provider, fields) in providers {
+            let Some(fields) = fields.as_object() else {
+                continue;
+            };
+            for (field, value) in fields {
+                let key = format!("{prefix}_{provider}_{field}")
+                    .replace('-', "_")
+                    .to_uppercase();
+                vars.insert(key, env_var_value(field, value));
+            }
+        }
+        vars
+    }
+
+    /// Build a config from the `{PREFIX}_{PROVIDER}_{FIELD}` variables in
+    /// the current environment, see [`ProvidersConfig::to_env_vars`].
+    pub fn from_env_prefix(prefix: &str) -> Result<Self, serde_json::Error> {
+        Self::from_env_vars(prefix, std::env::vars())
+    }
+
+    /// Load a config from a YAML (`.yaml`/`.yml`), JSON (`.json`) or TOML
+    /// (`.toml`) file, picking the format from the extension of `path`.
+    ///
+    /// Every format goes through the same [`Deserialize`] impl, so models
+    /// and provider keys are parsed the same way regardless of the format.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, ConfigLoadError> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let read =
+            || std::fs::read_to_string(path).map_err(ConfigLoadError::Io);
+        match extension.as_deref() {
+            Some("yaml" | "yml") => {
+                serde_yml::from_str(&read()?).map_err(ConfigLoadError::Yaml)
+            }
+            Some("json") => {
+                serde_json::from_str(&read()?).map_err(ConfigLoadError::Json)
+            }
+            Some("toml") => {
+                toml::from_str(&read()?).map_err(ConfigLoadError::Toml)
+            }
+            _ => Err(ConfigLoadError::UnsupportedFormat(
+                path.display().to_string(),
+            )),
+        }
+    }
+
+    /// Build a config from `{PREFIX}_{PROVIDER}_{FIELD}` variables, ignoring
+    /// any variable that does not match that pattern.
+    ///
+    /// If `{PREFIX}_PROVIDERS` lists the names of the providers, only those
+    /// providers are read, in that order and with those exact names.
+    /// Otherwise provider names are lowercased with `_` replaced by `-`.
+    pub fn from_env_vars<I>(
+        prefix: &str,
+        vars: I,
+    ) -> Result<Self, serde_json::Error>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let prefix = format!("{}_", prefix.to_uppercase());
+        let vars = vars
+            .into_iter()
+            .filter_map(|(key, value)| {
+                Some((key.strip_prefix(&prefix)?.to_string(), value))
+            })
+            .collect::<Vec<_>>();
+        let names = vars.iter().find(|(key, _)| key == "PROVIDERS").map(
+            |(_, names)| {
+                names
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|name| !name.is_empty())
+                    .collect::<Vec<_>>()
+            },
+        );
+
+        let mut providers = serde_json::Map::new();
+        if let Some(names) = &names {
+            for name in names {
+                providers.insert(
+                    (*name).to_string(),
+                    serde_json::Value::Object(serde_json::Map::new()),
+                );
+            }
+        }
+        for (rest, value) in &vars {
+            let Some((provider, field)) =
+                ENV_VAR_FIELDS.iter().find_map(|field| {
+                    let suffix =
+                        format!("_{field}").replace('-', "_").to_uppercase();
+                    rest.strip_suffix(&suffix)
+                        .filter(|provider| !provider.is_empty())
+                        .map(|provider| (provider, *field))
+                })
+            else {
+                continue;
+            };
+            let provider = match &names {
+                Some(names) => {
+                    let Some(name) = names.iter().find(|name| {
+                        name.replace('-', "_").to_uppercase() == provider
+                    }) else {
+                        continue;
+                    };
+                    (*name).to_string()
+                }
+                None => provider.replace('_', "-").to_lowercase(),
+            };
+            let fields = providers.entry(provider).or_insert_with(|| {
+                serde_json::Value::Object(serde_json::Map::new())
+            });
+            if let Some(fields) = fields.as_object_mut() {
+                fields.insert(
+                    field.to_string(),
+                    parse_env_var_value(field, value),
+                );
+            }
+        }
+        // listed providers without any variable are left out
+        providers.retain(|_, fields| {
+            fields.as_object().is_none_or(|fields| !fields.is_empty())
+        });
+        serde_json::from_value(serde_json::Value::Object(providers))
+    }
+}
+
+fn env_var_value(field: &str, value: &serde_json::Value) -> String {
+    if let Some(models) = value.as_array().filter(|_| field == "models") {
+        let names = models
+            .iter()
+            .map(|model| model.as_str().filter(|name| !name.contains(',')))
+            .collect::<Option<Vec<_>>>();
+        if let Some(names) = names.filter(|names| {
+            names.first().is_none_or(|first| !first.starts_with('['))
+        }) {
+            return names.join(",");
+        }
+    }
+    match value {
+        // strings that would be read back as JSON are quoted
+        serde_json::Value::String(value)
+            if serde_json::from_str::<serde_json::Value>(value).is_err() =>
+        {
+            value.clone()
+        }
+        value => value.to_string(),
+    }
+}
+
+fn parse_env_var_value(field: &str, value: &str) -> serde_json::Value {
+    if field == "models" && !value.starts_with('[') {
+        let models = value
+            .split(',')
+            .filter(|model| !model.is_empty())
+            .map(|model| serde_json::Value::String(model.to_string()))
+            .collect();
+        return serde_json::Value::Array(models);
+    }
+    serde_json::from_str(value)
+        .unwrap_or_else(|_| serde_json::Value::String(value.to_string()))
+}
+
+/// Why [`ProvidersConfig::from_path`] failed.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum ConfigLoadError {
+    /// Unsupported config format of '{0}', use .yaml, .yml, .json or .toml
+    UnsupportedFormat(String),
+    /// Failed to read providers config: {0}
+    Io(std::io::Error),
+    /// Invalid YAML providers config: {0}
+    Yaml(serde_yml::Error),
+    /// Invalid JSON providers config: {0}
+    Json(serde_json::Error),
+    /// Invalid TOML providers config: {0}
+    Toml(toml::de::Error),
+}
+
+/// Used when prewarming a provider without a [`TimeoutConfig`].
+#[cfg(feature = "prewarm")]
+const DEFAULT_PREWARM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Why a provider could not be reached while prewarming.
+#[cfg(feature = "prewarm")]
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum PrewarmError {
+    /// Provider did not respond within {0:?}
+    Timeout(Duration),
+    /// Request to provider failed: {0}
+    Request(reqwest::Error),
+}
+
+#[cfg(feature = "prewarm")]
+impl ProvidersConfig {
+    /// Concurrently send a `HEAD` request to every enabled provider to check
+    /// that it is reachable and to warm up DNS and TLS connections.
+    ///
+    /// The `health_check_url` is used if set, otherwise the `base_url`. Any
+    /// HTTP response counts as reachable, since most providers reject
+    /// unauthenticated requests. Each request is bounded by the provider's
+    /// total timeout, or its connect timeout if it has none. Failures are
+    /// logged and returned rather than aborting startup.
+    pub async fn prewarm(
+        &self,
+        client: &reqwest::Client,
+    ) -> Vec<(InferenceProvider, Result<(), PrewarmError>)> {
+        let requests = self.0.iter().filter(|(_, config)| config.enabled).map(
+            |(provider, config)| async move {
+                let url = config
+                    .health_check_url
+                    .clone()
+                    .unwrap_or_else(|| config.base_url.clone());
+                let timeout = config.timeout().map_or(
+                    DEFAULT_PREWARM_TIMEOUT,
+                    |timeout| {
+                        timeout.total_timeout.unwrap_or(timeout.connect_timeout)
+                    },
+                );
+                let result = client
+                    .head(url)
+                    .timeout(timeout)
+                    .send()
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| {
+                        if e.is_timeout() {
+                            PrewarmError::Timeout(timeout)
+                        } else {
+                            PrewarmError::Request(e)
+                        }
+                    });
+                if let Err(error) = &result {
+                    tracing::warn!(
+                        provider = %provider,
+                        error = %error,
+                        "failed to prewarm provider"
+                    );
+                }
+                (provider.clone(), result)
+            },
+        );
+        futures::future::join_all(requests).await
+    }
+}
+
+/// Resolves models to the providers serving them.
+///
+/// Implemented by [`ProvidersConfig`], and object safe so that routing code
+/// can take a `&dyn ProviderResolver` and be backed by something other than
+/// a static config (e.g. a database). Results are owned so that
+/// implementations are not required to keep them in memory.
+pub trait ProviderResolver: Send + Sync {
+    /// Resolve `model` to the matching configured [`ModelId`] of `provider`.
+    fn resolve_model(
+        &self,
+        provider: &InferenceProvider,
+        model: &str,
+    ) -> Option<ModelId>;
+
+    /// The base url requests to `provider` are sent to.
+    fn base_url_for(&self, provider: &InferenceProvider) -> Option<Url>;
+
+    /// Every enabled provider that serves `model`, in priority order.
+    fn providers_serving(&self, model: &str) -> Vec<InferenceProvider>;
+}
+
+impl ProviderResolver for ProvidersConfig {
+    fn resolve_model(
+        &self,
+        provider: &InferenceProvider,
+        model: &str,
+    ) -> Option<ModelId> {
+        ProvidersConfig::resolve_model(self, provider, model).cloned()
+    }
+
+    fn base_url_for(&self, provider: &InferenceProvider) -> Option<Url> {
+        self.get(provider).map(|config| config.base_url.clone())
+    }
+
+    fn providers_serving(&self, model: &str) -> Vec<InferenceProvider> {
+        self.enabled()
+            .filter(|(provider, _)| {
+                ProvidersConfig::resolve_model(self, provider, model).is_some()
+            })
+            .map(|(provider, _)| provider.clone())
+            .collect()
+    }
+}
+
+/// A reverse index from model names to the providers serving them.
+///
+/// Built from a [`ProvidersConfig`] so that resolving a model does not require
+/// scanning every provider's model list. The index must be rebuilt whenever
+/// the config it was built from changes, which the
+/// [`ProvidersConfigStore`](super::providers_store::ProvidersConfigStore) does
+/// on every change, see
+/// [`ProvidersConfigStore::snapshot`](super::providers_store::ProvidersConfigStore::snapshot).
+#[derive(Debug, Clone, Default)]
+pub struct ModelIndex {
+    config_hash: u64,
+    models: HashMap<String, Vec<(InferenceProvider, ModelId)>>,
+}
+
+impl ModelIndex {
+    #[must_use]
+    pub fn new(config: &ProvidersConfig) -> Self {
+        let mut models: HashMap<String, Vec<(InferenceProvider, ModelId)>> =
+            HashMap::default();
+        for (provider, provider_config) in config.iter() {
+            for model in &provider_config.models {
+                let entry = (provider.clone(), model.clone());
+                let bare_name = model.as_model_name().to_string();
+                let full_name = model.to_string();
+                let names = if full_name == bare_name {
+                    vec![bare_name]
+                } else {
+                    vec![full_name, bare_name]
+                };
+                for name in names {
+                    let providers = models.entry(name).or_default();
+                    if !providers.contains(&entry) {
+                        providers.push(entry.clone());
+                    }
+                }
+            }
+        }
+
+        Self {
+            config_hash: config.canonical_hash(),
+            models,
+        }
+    }
+
+    /// All `(provider, model)` pairs serving `model`, in provider order.
+    ///
+    /// `model` may be either a bare model name (`claude-3-opus`) or the exact
+    /// configured name including its version (`claude-3-opus-20240229`).
+    #[must_use]
+    pub fn resolve(&self, model: &str) -> &[(InferenceProvider, ModelId)] {
+        self.models.get(model).map_or(&[], Vec::as_slice)
+    }
+
+    /// The [`ProvidersConfig::canonical_hash`] of the config this index was
+    /// built from.
+    #[must_use]
+    pub fn config_hash(&self) -> u64 {
+        self.config_hash
+    }
+}
+
+impl Default for ProvidersConfig {
+    fn default() -> Self {
+        serde_yml::from_str(PROVIDERS_YAML).expect("Always valid if tests pass")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sets an environment variable for the lifetime of the guard.
+    struct EnvGuard(&'static str);
+
+    impl EnvGuard {
+        fn set(var: &'static str, value: &str) -> Self {
+            // SAFETY: each test uses its own variables
+            unsafe {
+                std::env::set_var(var, value);
+            }
+            Self(var)
+        }
+    }
+
+    impl Drop for EnvGuard {
+        fn drop(&mut self) {
+            // SAFETY: each test uses its own variables
+            unsafe {
+                std::env::remove_var(self.0);
+            }
+        }
+    }
+
+    #[test]
+    fn test_default_providers_config_loads_from_yaml_string() {
+        let _default_config = ProvidersConfig::default();
+        // just want to make sure we don't panic...
+    }
+
+    #[test]
+    fn test_aibadgr_provider_loads() {
+        let config = ProvidersConfig::default();
+
+        // Verify AI Badgr provider is present
+        let aibadgr_provider = InferenceProvider::Named("aibadgr".into());
+        let aibadgr_config = config.get(&aibadgr_provider);
+        assert!(
+            aibadgr_config.is_some(),
+            "AI Badgr provider should be present in default config"
+        );
+
+        let aibadgr_config = aibadgr_config.unwrap();
+        // Note: URL parser does NOT add trailing slash for paths
+        assert_eq!(
+            aibadgr_config.base_url.as_str(),
+            "https://aibadgr.com/api/v1"
+        );
+
+        // Verify tier models are present (at least 3)
+        assert!(aibadgr_config.models.len() >= 3);
+
+        // Check that basic, normal, and premium models exist
+        let has_basic = aibadgr_config
+            .models
+            .iter()
+            .any(|m| m.to_string().contains("basic"));
+        let has_normal = aibadgr_config
+            .models
+            .iter()
+            .any(|m| m.to_string().contains("normal"));
+        let has_premium = aibadgr_config
+            .models
+            .iter()
+            .any(|m| m.to_string().contains("premium"));
+
+        assert!(has_basic, "Should have 'basic' model");
+        assert!(has_normal, "Should have 'normal' model");
+        assert!(has_premium, "Should have 'premium' model");
+    }
+
+    #[test]
+    fn test_providers_config_custom_deserialize() {
+        use chrono::TimeZone;
+        let yaml = r#"
+openai:
+  models:
+    - "gpt-4"
+    - "gpt-4-turbo"
+    - "gpt-4o"
+    - "gpt-4o-mini"
+  base-url: https://api.openai.com
+anthropic:
+  models:
+    - "claude-3-opus-20240229"
+    - "claude-3-sonnet-20240229"
+  base-url: https://api.anthropic.com
+  version: "2023-06-01"
+"#;
+
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+
+        // Check OpenAI provider
+        let openai_config = config.get(&InferenceProvider::OpenAI).unwrap();
+        assert_eq!(openai_config.models.len(), 4);
+        assert_eq!(openai_config.base_url.as_str(), "https://api.openai.com/");
+
+        // Verify models are properly prefixed internally
+        let model_ids: Vec<ModelId> =
+            openai_config.models.clone().into_iter().collect();
+        assert_eq!(
+            model_ids[0],
+            ModelId::ModelIdWithVersion {
+                provider: InferenceProvider::OpenAI,
+                id: crate::types::model_id::ModelIdWithVersion {
+                    model: "gpt-4".to_string(),
+                    version: crate::types::model_id::Version::ImplicitLatest,
+                },
+            }
+        );
+        // Check Anthropic provider
+        let anthropic_config =
+            config.get(&InferenceProvider::Anthropic).unwrap();
+        assert_eq!(anthropic_config.models.len(), 2);
+        let model_ids: Vec<ModelId> =
+            anthropic_config.models.clone().into_iter().collect();
+        let date =
+            chrono::NaiveDate::parse_from_str("20240229", "%Y%m%d").unwrap();
+        let naive_dt = date.and_hms_opt(0, 0, 0).unwrap();
+        let date = chrono::Utc.from_utc_datetime(&naive_dt).fixed_offset();
+        assert_eq!(
+            model_ids[0],
+            ModelId::ModelIdWithVersion {
+                provider: InferenceProvider::Anthropic,
+                id: crate::types::model_id::ModelIdWithVersion {
+                    model: "claude-3-opus".to_string(),
+                    version: crate::types::model_id::Version::Date {
+                        date,
+                        format: "%Y%m%d",
+                    },
+                },
+            }
+        );
+    }
+
+    #[test]
+    fn test_openai_organization_and_project_headers() {
+        let _project =
+            EnvGuard::set("AI_GATEWAY_TEST_OPENAI_PROJECT", "proj_123");
+        let yaml = r#"
+openai:
+  models:
+    - "gpt-4o"
+  base-url: https://api.openai.com
+  organization: org-abc
+  project: ${AI_GATEWAY_TEST_OPENAI_PROJECT}
+"#;
+
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let openai_config = config.get(&InferenceProvider::OpenAI).unwrap();
+        assert_eq!(openai_config.organization(), Some("org-abc"));
+        assert_eq!(openai_config.project(), Some("proj_123"));
+
+        let headers = openai_config.request_headers().unwrap();
+        assert_eq!(headers["openai-organization"], "org-abc");
+        assert_eq!(headers["openai-project"], "proj_123");
+
+        let serialized = serde_yml::to_string(&config).unwrap();
+        assert!(serialized.contains("organization: org-abc"));
+        let default_serialized =
+            serde_yml::to_string(&ProvidersConfig::default()).unwrap();
+        assert!(!default_serialized.contains("organization"));
+    }
+
+    #[test]
+    fn test_unset_env_var_in_organization_errors() {
+        let yaml = r#"
+openai:
+  models:
+    - "gpt-4o"
+  base-url: https://api.openai.com
+  organization: ${AI_GATEWAY_TEST_DEFINITELY_UNSET}
+"#;
+
+        let err = serde_yml::from_str::<ProvidersConfig>(yaml).unwrap_err();
+        assert!(
+            err.to_string().contains("AI_GATEWAY_TEST_DEFINITELY_UNSET"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn test_env_vars_in_base_url_and_version() {
+        let _base_url = EnvGuard::set(
+            "AI_GATEWAY_TEST_ANTHROPIC_BASE_URL",
+            "https://anthropic.staging.internal",
+        );
+        let _version =
+            EnvGuard::set("AI_GATEWAY_TEST_ANTHROPIC_VERSION", "2024-01-01");
+        let yaml = r"
+anthropic:
+  models:
+    - claude-3-5-sonnet
+  base-url: ${AI_GATEWAY_TEST_ANTHROPIC_BASE_URL}
+  version: ${AI_GATEWAY_TEST_ANTHROPIC_VERSION:-2023-06-01}
+openai:
+  models:
+    - gpt-4o
+  base-url: ${AI_GATEWAY_TEST_DEFINITELY_UNSET:-https://api.openai.com}
+  version: ${AI_GATEWAY_TEST_DEFINITELY_UNSET:-2024-10-21}
+";
+
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let anthropic = &config[&InferenceProvider::Anthropic];
+        assert_eq!(
+            anthropic.base_url.as_str(),
+            "https://anthropic.staging.internal/"
+        );
+        assert_eq!(anthropic.preferred_version(), Some("2024-01-01"));
+        let openai = &config[&InferenceProvider::OpenAI];
+        assert_eq!(openai.base_url.as_str(), "https://api.openai.com/");
+        assert_eq!(openai.preferred_version(), Some("2024-10-21"));
+
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+  base-url: ${AI_GATEWAY_TEST_DEFINITELY_UNSET}
+";
+        let err = serde_yml::from_str::<ProvidersConfig>(yaml).unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "'AI_GATEWAY_TEST_DEFINITELY_UNSET' referenced by provider \
+                 openai"
+            ),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn test_model_index_resolves_bare_and_versioned_names() {
+        let yaml = r#"
+openai:
+  models:
+    - "gpt-4o"
+  base-url: https://api.openai.com
+anthropic:
+  models:
+    - "claude-3-opus-20240229"
+    - "claude-3-opus"
+  base-url: https://api.anthropic.com
+"#;
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let index = ModelIndex::new(&config);
+
+        let resolved = index.resolve("gpt-4o");
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].0, InferenceProvider::OpenAI);
+
+        let resolved = index.resolve("claude-3-opus");
+        assert_eq!(resolved.len(), 2);
+        assert!(
+            resolved
+                .iter()
+                .all(|(provider, _)| *provider == InferenceProvider::Anthropic)
+        );
+
+        let resolved = index.resolve("claude-3-opus-20240229");
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].1.to_string(), "claude-3-opus-20240229");
+
+        assert!(index.resolve("gpt-5").is_empty());
+    }
+
+    #[test]
+    fn test_model_index_tracks_config_hash() {
+        let mut config = ProvidersConfig::default();
+        let index = ModelIndex::new(&config);
+        assert_eq!(index.config_hash(), config.canonical_hash());
+
+        config.shift_remove(&InferenceProvider::OpenAI);
+        assert_ne!(index.config_hash(), config.canonical_hash());
+    }
+
+    #[test]
+    fn test_model_index_has_no_duplicates() {
+        let index = ModelIndex::new(&ProvidersConfig::default());
+        for (name, entries) in &index.models {
+            let unique = entries.iter().collect::<IndexSet<_>>();
+            assert_eq!(unique.len(), entries.len(), "{name}");
+        }
+    }
+
+    #[test]
+    fn test_join_path_preserves_base_path() {
+        let config_for = |base_url: &str| {
+            GlobalProviderConfig::new(
+                Url::parse(base_url).unwrap(),
+                IndexSet::new(),
+            )
+        };
+
+        let cases = [
+            ("https://api.openai.com", "v1/chat/completions"),
+            ("https://api.openai.com/", "/v1/chat/completions"),
+            ("https://gw.internal/openai", "v1/chat/completions"),
+            ("https://gw.internal/openai/", "v1/chat/completions"),
+            ("https://gw.internal/openai/", "/v1/chat/completions"),
+        ];
+        let expected = [
+            "https://api.openai.com/v1/chat/completions",
+            "https://api.openai.com/v1/chat/completions",
+            "https://gw.internal/openai/v1/chat/completions",
+            "https://gw.internal/openai/v1/chat/completions",
+            "https://gw.internal/openai/v1/chat/completions",
+        ];
+        for ((base_url, relative), expected) in cases.into_iter().zip(expected)
+        {
+            assert_eq!(
+                config_for(base_url).join_path(relative).as_str(),
+                expected,
+                "joining '{relative}' onto '{base_url}'"
+            );
+        }
+
+        let config = config_for("https://gw.internal/openai/v1");
+        assert_eq!(
+            config.join_path("chat/completions?stream=true").as_str(),
+            "https://gw.internal/openai/v1/chat/completions?stream=true"
+        );
+    }
+
+    #[test]
+    fn test_provider_aliases_resolve_to_canonical_config() {
+        let yaml = r#"
+openai:
+  models:
+    - "gpt-4o"
+  base-url: https://api.openai.com
+  aliases:
+    - azure-openai
+"#;
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let alias = InferenceProvider::Named("azure-openai".into());
+        assert_eq!(config.get(&alias), config.get(&InferenceProvider::OpenAI));
+        assert!(!config.contains_key(&alias));
+        let (canonical, _) = config
+            .get_key_value(&InferenceProvider::Named(" Azure-OpenAI".into()))
+            .unwrap();
+        assert_eq!(*canonical, InferenceProvider::OpenAI);
+
+        let serialized = serde_yml::to_string(&config).unwrap();
+        assert!(serialized.contains("azure-openai"));
+        let round_tripped: ProvidersConfig =
+            serde_yml::from_str(&serialized).unwrap();
+        assert_eq!(config, round_tripped);
+    }
+
+    #[test]
+    fn test_provider_alias_colliding_with_provider_errors() {
+        let yaml = r#"
+openai:
+  models:
+    - "gpt-4o"
+  base-url: https://api.openai.com
+  aliases:
+    - anthropic
+anthropic:
+  models:
+    - "claude-3-opus"
+  base-url: https://api.anthropic.com
+"#;
+        let err = serde_yml::from_str::<ProvidersConfig>(yaml).unwrap_err();
+        assert!(
+            err.to_string().contains("collides"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn test_suggest_models_for_typo() {
+        let config = ProvidersConfig::default();
+        let suggestions = config.suggest_models("openai/gpt-4-o");
+        assert!(!suggestions.is_empty());
+        assert!(
+            suggestions.iter().any(|(provider, model)| **provider
+                == InferenceProvider::OpenAI
+                && model.to_string() == "gpt-4o")
+        );
+
+        assert!(config.suggest_models("zzzzzzzzzzzzzzzzzzzz").is_empty());
+    }
+
+    #[test]
+    fn test_provider_timeout_config() {
+        let yaml = r#"
+anthropic:
+  models:
+    - "claude-3-opus"
+  base-url: https://api.anthropic.com
+  timeout:
+    connect-timeout-ms: 2000
+    read-timeout-ms: 30000
+    total-timeout-ms: 90000
+"#;
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let timeout = config
+            .get(&InferenceProvider::Anthropic)
+            .unwrap()
+            .timeout()
+            .unwrap();
+        assert_eq!(timeout.connect_timeout, Duration::from_secs(2));
+        assert_eq!(timeout.read_timeout, Duration::from_secs(30));
+        assert_eq!(timeout.total_timeout, Some(Duration::from_secs(90)));
+
+        let serialized = serde_json::to_string(&config).unwrap();
+        let round_tripped: ProvidersConfig =
+            serde_json::from_str(&serialized).unwrap();
+        assert_eq!(config, round_tripped);
+    }
+
+    #[test]
+    fn test_provider_timeout_connect_exceeding_total_errors() {
+        let yaml = r#"
+anthropic:
+  models:
+    - "claude-3-opus"
+  base-url: https://api.anthropic.com
+  timeout:
+    connect-timeout-ms: 5000
+    total-timeout-ms: 1000
+"#;
+        let err = serde_yml::from_str::<ProvidersConfig>(yaml).unwrap_err();
+        assert!(
+            err.to_string().contains("connect-timeout-ms"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn test_assert_covers_reports_all_missing_models() {
+        let config = ProvidersConfig::default();
+        assert!(
+            config
+                .assert_covers(&[
+                    (InferenceProvider::OpenAI, "gpt-4o"),
+                    (InferenceProvider::Anthropic, "claude-3-opus"),
+                ])
+                .is_ok()
+        );
+
+        let missing = config
+            .assert_covers(&[
+                (InferenceProvider::OpenAI, "gpt-4o"),
+                (InferenceProvider::OpenAI, "gpt-9"),
+                (InferenceProvider::Named("nope".into()), "some-model"),
+            ])
+            .unwrap_err();
+        assert_eq!(
+            missing,
+            vec![
+                MissingModel {
+                    provider: InferenceProvider::OpenAI,
+                    model: "gpt-9".to_string(),
+                    provider_configured: true,
+                },
+                MissingModel {
+                    provider: InferenceProvider::Named("nope".into()),
+                    model: "some-model".to_string(),
+                    provider_configured: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_model_limits_fall_back_to_provider_limits() {
+        let yaml = r#"
+openai:
+  models:
+    - "gpt-4o-mini"
+    - name: "gpt-4o"
+      limits:
+        requests-per-minute: 10
+        tokens-per-minute: 50000
+  base-url: https://api.openai.com
+  limits:
+    requests-per-minute: 100
+    max-concurrent: 20
+"#;
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let openai_config = config.get(&InferenceProvider::OpenAI).unwrap();
+        assert_eq!(openai_config.models.len(), 2);
+
+        let gpt_4o =
+            ModelId::from_str_and_provider(InferenceProvider::OpenAI, "gpt-4o")
+                .unwrap();
+        let limits = openai_config.model_limits(&gpt_4o);
+        assert_eq!(limits.requests_per_minute, NonZeroU32::new(10));
+        assert_eq!(limits.max_concurrent, NonZeroU32::new(20));
+        assert_eq!(limits.tokens_per_minute, NonZeroU64::new(50000));
+
+        let gpt_4o_mini = ModelId::from_str_and_provider(
+            InferenceProvider::OpenAI,
+            "gpt-4o-mini",
+        )
+        .unwrap();
+        assert_eq!(
+            openai_config.model_limits(&gpt_4o_mini),
+            ProviderLimits {
+                requests_per_minute: NonZeroU32::new(100),
+                max_concurrent: NonZeroU32::new(20),
+                tokens_per_minute: None,
+            }
+        );
+
+        let serialized = serde_json::to_string(&config).unwrap();
+        let round_tripped: ProvidersConfig =
+            serde_json::from_str(&serialized).unwrap();
+        assert_eq!(config, round_tripped);
+    }
+
+    #[test]
+    fn test_zero_model_limit_errors() {
+        let yaml = r#"
+openai:
+  models:
+    - name: "gpt-4o"
+      limits:
+        requests-per-minute: 0
+  base-url: https://api.openai.com
+"#;
+        assert!(serde_yml::from_str::<ProvidersConfig>(yaml).is_err());
+    }
+
+    #[cfg(feature = "prewarm")]
+    #[tokio::test]
+    async fn test_prewarm_skips_disabled_and_reports_failures() {
+        // bind and drop a listener to get a port that refuses connections
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let yaml = format!(
+            r"
+openai:
+  models: []
+  base-url: https://api.openai.com
+  enabled: false
+unreachable:
+  models: []
+  base-url: https://api.unreachable.example
+  health-check-url: http://127.0.0.1:{port}/health
+"
+        );
+        let config: ProvidersConfig = serde_yml::from_str(&yaml).unwrap();
+
+        let results = config.prewarm(&reqwest::Client::new()).await;
+
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            results[0].0,
+            InferenceProvider::Named("unreachable".into())
+        );
+        assert!(matches!(results[0].1, Err(PrewarmError::Request(_))));
+    }
+
+    #[test]
+    fn test_providers_config_embeds_in_larger_config() {
+        #[derive(Debug, Deserialize)]
+        struct GatewayConfig {
+            listen: String,
+            providers: ProvidersConfig,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct AppConfig {
+            gateway: GatewayConfig,
+        }
+
+        #[derive(Debug, Deserialize)]
+        struct FlattenedConfig {
+            listen: String,
+            #[serde(flatten)]
+            providers: ProvidersConfig,
+        }
+
+        let yaml = r#"
+gateway:
+  listen: "0.0.0.0:8080"
+  providers:
+    openai:
+      models:
+        - gpt-4o
+        - name: gpt-4o-mini
+          limits:
+            requests-per-minute: 10
+      base-url: https://api.openai.com
+      timeout:
+        connect-timeout-ms: 1000
+"#;
+        let config: AppConfig = serde_yml::from_str(yaml).unwrap();
+        assert_eq!(config.gateway.listen, "0.0.0.0:8080");
+        let openai = config
+            .gateway
+            .providers
+            .get(&InferenceProvider::OpenAI)
+            .unwrap();
+        assert_eq!(openai.models.len(), 2);
+        assert_eq!(openai.model_metadata.len(), 1);
+
+        let yaml = r"
+listen: 0.0.0.0:8080
+openai:
+  models:
+    - gpt-4o
+  base-url: https://api.openai.com
+";
+        let config: FlattenedConfig = serde_yml::from_str(yaml).unwrap();
+        assert_eq!(config.listen, "0.0.0.0:8080");
+        assert_eq!(config.providers.len(), 1);
+        assert!(config.providers.get(&InferenceProvider::OpenAI).is_some());
+    }
+
+    #[test]
+    fn test_family_metadata_is_inherited_unless_overridden() {
+        let yaml = r"
+anthropic:
+  models:
+    - claude-3-opus-20240229
+    - name: claude-3-opus-latest
+      capabilities:
+        max-output-tokens: 8192
+      limits:
+        requests-per-minute: 5
+    - claude-3-haiku-20240307
+  base-url: https://api.anthropic.com
+  limits:
+    requests-per-minute: 100
+    max-concurrent: 10
+  families:
+    claude-3-opus:
+      capabilities:
+        context-window: 200000
+        max-output-tokens: 4096
+      limits:
+        requests-per-minute: 20
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let anthropic = config.get(&InferenceProvider::Anthropic).unwrap();
+        let model = |name: &str| {
+            ModelId::from_str_and_provider(InferenceProvider::Anthropic, name)
+                .unwrap()
+        };
+
+        let dated = model("claude-3-opus-20240229");
+        let capabilities = anthropic.model_capabilities(&dated);
+        assert_eq!(capabilities.context_window, NonZeroU32::new(200_000));
+        assert_eq!(capabilities.max_output_tokens, NonZeroU32::new(4096));
+        let limits = anthropic.model_limits(&dated);
+        assert_eq!(limits.requests_per_minute, NonZeroU32::new(20));
+        assert_eq!(limits.max_concurrent, NonZeroU32::new(10));
+
+        let latest = model("claude-3-opus-latest");
+        let capabilities = anthropic.model_capabilities(&latest);
+        assert_eq!(capabilities.context_window, NonZeroU32::new(200_000));
+        assert_eq!(capabilities.max_output_tokens, NonZeroU32::new(8192));
+        assert_eq!(
+            anthropic.model_limits(&latest).requests_per_minute,
+            NonZeroU32::new(5)
+        );
+
+        let haiku = model("claude-3-haiku-20240307");
+        assert_eq!(
+            anthropic.model_capabilities(&haiku),
+            ModelCapabilities::default()
+        );
+        assert_eq!(
+            anthropic.model_limits(&haiku).requests_per_minute,
+            NonZeroU32::new(100)
+        );
+
+        let serialized = serde_yml::to_string(&config).unwrap();
+        let round_tripped: ProvidersConfig =
+            serde_yml::from_str(&serialized).unwrap();
+        assert_eq!(config, round_tripped);
+    }
+
+    #[test]
+    fn test_prune_deprecated_removes_sunset_models() {
+        let yaml = r"
+openai:
+  models:
+    - name: gpt-4-0613
+      deprecation:
+        sunset: 2025-06-01
+        replacement: gpt-4o
+    - name: gpt-3.5-turbo
+      deprecation:
+        sunset: 2025-01-01
+        replacement: gpt-4-0613
+    - name: gpt-4-turbo
+      deprecation:
+        sunset: 2030-01-01
+        replacement: gpt-4-0613
+    - gpt-4o-mini
+  base-url: https://api.openai.com
+";
+        let now = DateTime::parse_from_rfc3339("2025-06-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let model = |name: &str| {
+            ModelId::from_str_and_provider(InferenceProvider::OpenAI, name)
+                .unwrap()
+        };
+
+        let mut config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let report = config.prune_deprecated(now, false);
+        assert_eq!(
+            report.removed,
+            vec![
+                (InferenceProvider::OpenAI, model("gpt-4-0613")),
+                (InferenceProvider::OpenAI, model("gpt-3.5-turbo")),
+            ]
+        );
+        assert!(report.replaced.is_empty());
+        assert_eq!(
+            report.dangling,
+            vec![DanglingReplacement {
                 provider: InferenceProvider::OpenAI,
-                id: crate::types::model_id::ModelIdWithVersion {
-                    model: "gpt-4".to_string(),
-                    version: crate::types::model_id::Version::ImplicitLatest,
-                },
+                model: model("gpt-4-turbo"),
+                replacement: "gpt-4-0613".to_string(),
+            }]
+        );
+        let openai = config.get(&InferenceProvider::OpenAI).unwrap();
+        let models = openai.models.iter().cloned().collect::<Vec<_>>();
+        assert_eq!(models, vec![model("gpt-4-turbo"), model("gpt-4o-mini")]);
+        assert_eq!(openai.model_metadata.len(), 1);
+
+        let mut config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let report = config.prune_deprecated(now, true);
+        assert_eq!(report.removed.len(), 2);
+        assert_eq!(
+            report.replaced,
+            vec![(
+                InferenceProvider::OpenAI,
+                model("gpt-4-0613"),
+                model("gpt-4o")
+            )]
+        );
+        // gpt-3.5-turbo's replacement is itself sunset, and gpt-4-turbo still
+        // points at it
+        assert_eq!(report.dangling.len(), 2);
+        let openai = config.get(&InferenceProvider::OpenAI).unwrap();
+        let models = openai.models.iter().cloned().collect::<Vec<_>>();
+        assert_eq!(
+            models,
+            vec![model("gpt-4o"), model("gpt-4-turbo"), model("gpt-4o-mini")]
+        );
+    }
+
+    #[test]
+    fn test_version_accepts_a_single_version_or_a_list() {
+        let yaml = r#"
+anthropic:
+  models: []
+  base-url: https://api.anthropic.com
+  version: "2023-06-01"
+openai:
+  models: []
+  base-url: https://api.openai.com
+  version: ["2024-10-21", "2024-06-01"]
+ollama:
+  models: []
+  base-url: http://localhost:11434
+"#;
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+
+        let anthropic = config.get(&InferenceProvider::Anthropic).unwrap();
+        assert_eq!(anthropic.preferred_version(), Some("2023-06-01"));
+        assert_eq!(anthropic.supported_versions(), ["2023-06-01"]);
+        let openai = config.get(&InferenceProvider::OpenAI).unwrap();
+        assert_eq!(openai.preferred_version(), Some("2024-10-21"));
+        assert_eq!(openai.supported_versions(), ["2024-10-21", "2024-06-01"]);
+        let ollama = config.get(&InferenceProvider::Ollama).unwrap();
+        assert_eq!(ollama.preferred_version(), None);
+
+        let serialized =
+            serde_json::to_value(&config).expect("config is serializable");
+        assert_eq!(serialized["anthropic"]["version"], "2023-06-01");
+        assert_eq!(
+            serialized["openai"]["version"],
+            serde_json::json!(["2024-10-21", "2024-06-01"])
+        );
+        assert!(serialized["ollama"].get("version").is_none());
+        let round_tripped: ProvidersConfig =
+            serde_json::from_value(serialized).unwrap();
+        assert_eq!(config, round_tripped);
+    }
+
+    #[test]
+    fn test_provider_resolver_is_object_safe() {
+        struct SingleModel;
+
+        impl ProviderResolver for SingleModel {
+            fn resolve_model(
+                &self,
+                provider: &InferenceProvider,
+                model: &str,
+            ) -> Option<ModelId> {
+                ModelId::from_str_and_provider(provider.clone(), model).ok()
+            }
+
+            fn base_url_for(&self, _: &InferenceProvider) -> Option<Url> {
+                Url::parse("http://localhost:8080").ok()
+            }
+
+            fn providers_serving(&self, _: &str) -> Vec<InferenceProvider> {
+                vec![InferenceProvider::Ollama]
+            }
+        }
+
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+  base-url: https://api.openai.com
+groq:
+  models:
+    - gpt-4o
+  base-url: https://api.groq.com
+  enabled: false
+openrouter:
+  models:
+    - gpt-4o
+  base-url: https://openrouter.ai/api
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let resolvers: [&dyn ProviderResolver; 2] = [&config, &SingleModel];
+
+        let config_resolver = resolvers[0];
+        assert_eq!(
+            config_resolver
+                .resolve_model(&InferenceProvider::OpenAI, "gpt-4o")
+                .map(|model| model.to_string()),
+            Some("gpt-4o".to_string())
+        );
+        assert!(
+            config_resolver
+                .resolve_model(&InferenceProvider::OpenAI, "gpt-5")
+                .is_none()
+        );
+        assert_eq!(
+            config_resolver
+                .base_url_for(&InferenceProvider::OpenAI)
+                .map(String::from),
+            Some("https://api.openai.com/".to_string())
+        );
+        assert_eq!(
+            config_resolver.providers_serving("gpt-4o"),
+            vec![
+                InferenceProvider::OpenAI,
+                InferenceProvider::Named("openrouter".into())
+            ]
+        );
+
+        assert_eq!(
+            resolvers[1].providers_serving("gemma3"),
+            vec![InferenceProvider::Ollama]
+        );
+    }
+
+    #[test]
+    fn test_model_suffixes_are_configurable_per_provider() {
+        let yaml = r"
+openai:
+  models:
+    - gpt-4-turbo-preview
+  base-url: https://api.openai.com
+together:
+  models:
+    - llama-3-70b-chat
+    - llama-3-70b-preview
+  base-url: https://api.together.xyz
+  model-suffixes: [chat]
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+
+        let openai = config.get(&InferenceProvider::OpenAI).unwrap();
+        let model = openai.models.first().unwrap();
+        assert_eq!(model.as_model_name().to_string(), "gpt-4-turbo-preview");
+
+        let together = InferenceProvider::Named("together".into());
+        let models = &config.get(&together).unwrap().models;
+        let names = models
+            .iter()
+            .map(|model| model.as_model_name().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["llama-3-70b-chat", "llama-3-70b"]);
+        assert!(
+            config
+                .resolve_model(&together, "llama-3-70b-chat")
+                .is_some()
+        );
+
+        let serialized = serde_yml::to_string(&config).unwrap();
+        let round_tripped: ProvidersConfig =
+            serde_yml::from_str(&serialized).unwrap();
+        assert_eq!(config, round_tripped);
+    }
+
+    #[test]
+    fn test_env_vars_round_trip() {
+        let yaml = r#"
+openai:
+  models:
+    - gpt-4o
+    - name: gpt-4o-mini
+      limits:
+        requests-per-minute: 10
+  base-url: https://api.openai.com
+  organization: "123"
+  enabled: false
+anthropic:
+  models:
+    - claude-3-opus-20240229
+    - claude-3-7-sonnet-latest
+  base-url: https://api.anthropic.com
+  version: "2023-06-01"
+  timeout:
+    connect-timeout-ms: 1000
+azure-openai:
+  models: []
+  base-url: https://example.openai.azure.com
+  version: ["2024-10-21", "2024-06-01"]
+"#;
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+
+        let vars = config.to_env_vars("AIGW");
+        assert_eq!(vars["AIGW_OPENAI_BASE_URL"], "https://api.openai.com/");
+        assert_eq!(vars["AIGW_OPENAI_ORGANIZATION"], r#""123""#);
+        assert_eq!(vars["AIGW_OPENAI_ENABLED"], "false");
+        assert_eq!(
+            vars["AIGW_ANTHROPIC_MODELS"],
+            "claude-3-opus-20240229,claude-3-7-sonnet-latest"
+        );
+        assert_eq!(vars["AIGW_ANTHROPIC_VERSION"], "2023-06-01");
+        assert_eq!(
+            vars["AIGW_AZURE_OPENAI_VERSION"],
+            r#"["2024-10-21","2024-06-01"]"#
+        );
+        assert!(vars["AIGW_OPENAI_MODELS"].starts_with('['));
+
+        let unrelated = ("AIGW_LOG_LEVEL".to_string(), "debug".to_string());
+        let round_tripped = ProvidersConfig::from_env_vars(
+            "AIGW",
+            vars.into_iter().chain(std::iter::once(unrelated)),
+        )
+        .unwrap();
+        assert_eq!(config, round_tripped);
+    }
+
+    #[test]
+    fn test_env_vars_round_trip_aws() {
+        let yaml = r"
+bedrock:
+  models:
+    - anthropic.claude-3-haiku-20240307-v1:0
+  base-url: https://bedrock-runtime.us-east-1.amazonaws.com
+  aws:
+    region: us-west-2
+    assume-role:
+      role-arn: arn:aws:iam::123456789012:role/bedrock-invoke
+      external-id: gateway
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+
+        let vars = config.to_env_vars("AIGW");
+        assert!(vars["AIGW_BEDROCK_AWS"].contains("us-west-2"));
+
+        let round_tripped =
+            ProvidersConfig::from_env_vars("AIGW", vars).unwrap();
+        assert_eq!(config, round_tripped);
+    }
+
+    #[test]
+    fn test_env_vars_round_trip_self_hosted() {
+        let yaml = r"
+vllm:
+  models:
+    - llama-3.1-8b-instruct
+  base-url: https://vllm.internal:8000
+  self-hosted:
+    dialect: ollama
+    ca-cert: /etc/ssl/vllm.pem
+    accept-invalid-certs: true
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+
+        let vars = config.to_env_vars("AIGW");
+        assert!(vars.contains_key("AIGW_VLLM_SELF_HOSTED"));
+
+        let round_tripped =
+            ProvidersConfig::from_env_vars("AIGW", vars).unwrap();
+        assert_eq!(config, round_tripped);
+    }
+
+    #[test]
+    fn test_env_vars_round_trip_names_and_order() {
+        let mut config = ProvidersConfig::default();
+        config.insert(
+            InferenceProvider::Named("my_provider".into()),
+            GlobalProviderConfig::new(
+                Url::parse("https://llm.internal").unwrap(),
+                IndexSet::new(),
+            ),
+        );
+
+        let vars = config.to_env_vars("AIGW");
+        assert!(vars["AIGW_PROVIDERS"].ends_with(",my_provider"));
+        assert!(vars.contains_key("AIGW_MY_PROVIDER_BASE_URL"));
+
+        let round_tripped =
+            ProvidersConfig::from_env_vars("AIGW", vars).unwrap();
+        assert_eq!(config, round_tripped);
+        assert_eq!(
+            config.keys().collect::<Vec<_>>(),
+            round_tripped.keys().collect::<Vec<_>>()
+        );
+        assert!(
+            round_tripped
+                .contains_key(&InferenceProvider::Named("my_provider".into()))
+        );
+    }
+
+    #[test]
+    fn test_deployment_url() {
+        let yaml = r#"
+azure-openai:
+  models:
+    - name: gpt-4o
+      deployment: prod-gpt4o
+    - gpt-4o-mini
+  base-url: https://example.openai.azure.com
+  version: "2024-10-21"
+  deployments: true
+"#;
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let azure = config
+            .get(&InferenceProvider::Named("azure-openai".into()))
+            .unwrap();
+        assert!(azure.deployments);
+        let urls = azure
+            .models
+            .iter()
+            .map(|model| azure.deployment_url(model).to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            [
+                "https://example.openai.azure.com/openai/deployments/\
+                 prod-gpt4o/chat/completions?api-version=2024-10-21",
+                "https://example.openai.azure.com/openai/deployments/\
+                 gpt-4o-mini/chat/completions?api-version=2024-10-21",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_providers_by_role() {
+        let yaml = r"
+openai:
+  models: []
+  base-url: https://api.openai.com
+openrouter:
+  models: []
+  base-url: https://openrouter.ai/api
+  role: fallback
+anthropic:
+  models: []
+  base-url: https://api.anthropic.com
+groq:
+  models: []
+  base-url: https://api.groq.com
+  role: fallback
+  enabled: false
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let names = |role| {
+            config
+                .by_role(role)
+                .map(|(provider, _)| provider.to_string())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(ProviderRole::Primary), ["openai", "anthropic"]);
+        assert_eq!(names(ProviderRole::Fallback), ["openrouter"]);
+        assert!(config.validate_strict().is_ok());
+
+        let serialized = serde_yml::to_string(&config).unwrap();
+        assert_eq!(serialized.matches("role:").count(), 2);
+
+        let mut all_fallback = config.clone();
+        for provider_config in all_fallback.values_mut() {
+            provider_config.role = ProviderRole::Fallback;
+        }
+        assert!(all_fallback.validate().is_ok());
+        assert_eq!(
+            all_fallback.validate_strict(),
+            Err(ProvidersConfigError::NoPrimaryProvider)
+        );
+    }
+
+    #[test]
+    fn test_compact_string_round_trip() {
+        let yaml = r"
+openai:
+  models:
+    - gpt-4
+    - gpt-4o-2024-08-06
+  base-url: https://api.openai.com
+  organization: org-abc
+ollama:
+  models: []
+  base-url: http://localhost:11434
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let compact = config.to_compact_string();
+        assert_eq!(
+            compact,
+            "openai[gpt-4,gpt-4o-2024-08-06]@https://api.openai.com/; \
+             ollama[]@http://localhost:11434/"
+        );
+
+        let parsed = ProvidersConfig::from_compact_string(&compact).unwrap();
+        assert_eq!(parsed.to_compact_string(), compact);
+        let openai = parsed.get(&InferenceProvider::OpenAI).unwrap();
+        assert_eq!(openai.models, config[&InferenceProvider::OpenAI].models);
+        assert_eq!(openai.organization(), None);
+
+        assert!(matches!(
+            ProvidersConfig::from_compact_string(
+                "openai@https://api.openai.com"
+            ),
+            Err(ProvidersConfigError::InvalidCompactForm(_))
+        ));
+        assert!(matches!(
+            ProvidersConfig::from_compact_string("openai[gpt-4]@not a url"),
+            Err(ProvidersConfigError::InvalidCompactForm(_))
+        ));
+    }
+
+    #[test]
+    fn test_active_hours() {
+        let yaml = r"
+openai:
+  models: []
+  base-url: https://api.openai.com
+cheap:
+  models: []
+  base-url: https://cheap.example
+  active-hours:
+    start-hour: 22
+    end-hour: 6
+    days: [mon, tue, wed, thu, fri]
+weekend:
+  models: []
+  base-url: https://weekend.example
+  active-hours:
+    days: [sat, sun]
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let active_at = |time: &str| {
+            let now = DateTime::parse_from_rfc3339(time)
+                .unwrap()
+                .with_timezone(&Utc);
+            config
+                .enabled_providers_at(now)
+                .map(|(provider, _)| provider.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        // 2025-06-02 is a monday
+        assert_eq!(active_at("2025-06-02T23:00:00Z"), ["openai", "cheap"]);
+        assert_eq!(active_at("2025-06-02T05:59:59Z"), ["openai", "cheap"]);
+        assert_eq!(active_at("2025-06-02T06:00:00Z"), ["openai"]);
+        assert_eq!(active_at("2025-06-01T23:00:00Z"), ["openai", "weekend"]);
+
+        let serialized = serde_yml::to_string(&config).unwrap();
+        assert_eq!(serialized.matches("active-hours").count(), 2);
+        let round_tripped: ProvidersConfig =
+            serde_yml::from_str(&serialized).unwrap();
+        assert_eq!(config, round_tripped);
+
+        for invalid in [
+            "{ start-hour: 24 }",
+            "{ end-hour: 25 }",
+            "{ start-hour: 6, end-hour: 6 }",
+        ] {
+            let yaml = format!(
+                "openai:\n  models: []\n  base-url: https://api.openai.com\n  \
+                 active-hours: {invalid}\n"
+            );
+            let err = serde_yml::from_str::<ProvidersConfig>(&yaml)
+                .expect_err("invalid active hours should be rejected");
+            assert!(err.to_string().contains("hour"), "{invalid}: {err}");
+        }
+    }
+
+    #[test]
+    fn test_provider_and_model_counts() {
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+    - gpt-4o-mini
+  base-url: https://api.openai.com
+  aliases: [azure-openai]
+anthropic:
+  models:
+    - claude-3-opus-20240229
+  base-url: https://api.anthropic.com
+ollama:
+  models: []
+  base-url: http://localhost:11434
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        assert_eq!(config.provider_count(), 3);
+        assert_eq!(config.total_model_count(), 3);
+        assert_eq!(config.model_count(&InferenceProvider::OpenAI), 2);
+        assert_eq!(
+            config
+                .model_count(&InferenceProvider::Named("azure-openai".into())),
+            2
+        );
+        assert_eq!(config.model_count(&InferenceProvider::Ollama), 0);
+        assert_eq!(config.model_count(&InferenceProvider::Bedrock), 0);
+    }
+
+    #[test]
+    fn test_shadow_targets() {
+        let yaml = r"
+openai:
+  models: []
+  base-url: https://api.openai.com
+candidate:
+  models: []
+  base-url: https://candidate.example
+  shadow-of: openai
+  shadow-sample-rate: 0.25
+mirror:
+  models: []
+  base-url: https://mirror.example
+  shadow-of: openai
+disabled:
+  models: []
+  base-url: https://disabled.example
+  shadow-of: openai
+  enabled: false
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let targets = config
+            .shadow_targets(&InferenceProvider::OpenAI)
+            .into_iter()
+            .map(|(provider, rate)| (provider.to_string(), rate))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            targets,
+            [("candidate".to_string(), 0.25), ("mirror".to_string(), 1.0)]
+        );
+        assert!(
+            config
+                .shadow_targets(&InferenceProvider::Named("candidate".into()))
+                .is_empty()
+        );
+
+        let serialized = serde_yml::to_string(&config).unwrap();
+        let round_tripped: ProvidersConfig =
+            serde_yml::from_str(&serialized).unwrap();
+        assert_eq!(config, round_tripped);
+
+        let unknown = r"
+candidate:
+  models: []
+  base-url: https://candidate.example
+  shadow-of: anthropic
+";
+        assert!(serde_yml::from_str::<ProvidersConfig>(unknown).is_err());
+        let out_of_range = r"
+openai:
+  models: []
+  base-url: https://api.openai.com
+candidate:
+  models: []
+  base-url: https://candidate.example
+  shadow-of: openai
+  shadow-sample-rate: 1.5
+";
+        let err = serde_yml::from_str::<ProvidersConfig>(out_of_range)
+            .expect_err("sample rate above 1 should be rejected");
+        assert!(err.to_string().contains("between 0 and 1"), "{err}");
+    }
+
+    #[test]
+    fn test_aws_config() {
+        let yaml = r"
+bedrock:
+  models: []
+  base-url: https://bedrock-runtime.us-east-1.amazonaws.com
+  aws:
+    region: eu-west-1
+    assume-role:
+      role-arn: arn:aws:iam::123456789012:role/bedrock-invoke
+      external-id: gateway
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let aws = config[&InferenceProvider::Bedrock].aws.as_ref().unwrap();
+        assert_eq!(aws.region.as_deref(), Some("eu-west-1"));
+        let assume_role = aws.assume_role.as_ref().unwrap();
+        assert_eq!(
+            assume_role.role_arn,
+            "arn:aws:iam::123456789012:role/bedrock-invoke"
+        );
+        assert_eq!(assume_role.session_name, "ai-gateway");
+        assert_eq!(assume_role.external_id.as_deref(), Some("gateway"));
+        assert_eq!(assume_role.duration_seconds, None);
+
+        let serialized = serde_yml::to_string(&config).unwrap();
+        let round_tripped: ProvidersConfig =
+            serde_yml::from_str(&serialized).unwrap();
+        assert_eq!(config, round_tripped);
+    }
+
+    #[test]
+    fn test_self_hosted_config() {
+        let yaml = r"
+vllm:
+  models: []
+  base-url: https://vllm.internal:8000
+  self-hosted:
+    ca-cert: /etc/ssl/certs/internal-ca.pem
+gpu-box:
+  models: []
+  base-url: http://gpu-box:11434
+  self-hosted:
+    dialect: ollama
+    accept-invalid-certs: true
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let vllm = &config[&InferenceProvider::Named("vllm".into())];
+        assert!(vllm.is_self_hosted());
+        assert_eq!(vllm.dialect(), Some(SelfHostedDialect::OpenAI));
+        let self_hosted = vllm.self_hosted.as_ref().unwrap();
+        assert_eq!(
+            self_hosted.ca_cert.as_deref(),
+            Some(Path::new("/etc/ssl/certs/internal-ca.pem"))
+        );
+        assert!(!self_hosted.accept_invalid_certs);
+
+        let gpu_box = &config[&InferenceProvider::Named("gpu-box".into())];
+        assert_eq!(gpu_box.dialect(), Some(SelfHostedDialect::Ollama));
+        assert!(gpu_box.self_hosted.as_ref().unwrap().accept_invalid_certs);
+
+        let serialized = serde_yml::to_string(&config).unwrap();
+        let round_tripped: ProvidersConfig =
+            serde_yml::from_str(&serialized).unwrap();
+        assert_eq!(config, round_tripped);
+    }
+
+    #[test]
+    fn test_case_sensitivity_is_configurable_per_provider() {
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+  base-url: https://api.openai.com
+ollama:
+  models:
+    - Gemma3:1B
+  base-url: http://localhost:11434
+  case-sensitive: true
+groq:
+  models:
+    - Llama-3.3-70B-Versatile
+  base-url: https://api.groq.com
+  case-sensitive: false
+openrouter:
+  models:
+    - gpt-4o
+  base-url: https://openrouter.ai/api
+  case-sensitive: true
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let groq = InferenceProvider::Named("groq".into());
+        let openrouter = InferenceProvider::Named("openrouter".into());
+
+        assert!(
+            config
+                .resolve_model(&InferenceProvider::OpenAI, "gpt-4o")
+                .is_some()
+        );
+        assert!(
+            config
+                .resolve_model(&InferenceProvider::OpenAI, "GPT-4o")
+                .is_none()
+        );
+        assert!(
+            config
+                .resolve_model(&groq, "llama-3.3-70b-versatile")
+                .is_some()
+        );
+        assert!(
+            config
+                .resolve_model(&groq, "LLAMA-3.3-70B-VERSATILE")
+                .is_some()
+        );
+        assert!(
+            config
+                .resolve_model(&InferenceProvider::Ollama, "Gemma3:1B")
+                .is_some()
+        );
+        assert!(
+            config
+                .resolve_model(&InferenceProvider::Ollama, "gemma3:1b")
+                .is_none()
+        );
+
+        // only flags that deviate from the provider default are kept
+        assert_eq!(config[&groq].case_sensitive, Some(false));
+        assert_eq!(
+            config[&InferenceProvider::Ollama].case_sensitive,
+            Some(true)
+        );
+        assert_eq!(config[&openrouter].case_sensitive, None);
+        let serialized = serde_yml::to_string(&config).unwrap();
+        assert_eq!(serialized.matches("case-sensitive").count(), 2);
+    }
+
+    #[test]
+    fn test_explain_resolution() {
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+    - name: gpt-4-0613
+      deprecation:
+        sunset: 2025-06-01
+        replacement: gpt-4o
+    - name: gpt-4o-mini
+      enabled: false
+  base-url: https://api.openai.com
+anthropic:
+  models:
+    - claude-3-5-sonnet
+  base-url: https://api.anthropic.com
+  enabled: false
+openrouter:
+  models:
+    - gpt-4o-mini
+  base-url: https://openrouter.ai/api
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let now = DateTime::parse_from_rfc3339("2025-06-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let openai = InferenceProvider::OpenAI;
+        let openrouter = InferenceProvider::Named("openrouter".into());
+        let model = |provider: &InferenceProvider, name: &str| {
+            ModelId::from_str_and_provider(provider.clone(), name).unwrap()
+        };
+        let explain =
+            |provider, name| config.explain_resolution_at(provider, name, now);
+
+        assert_eq!(
+            explain(Some(&openai), "gpt-4o"),
+            ResolutionOutcome::Resolved {
+                provider: openai.clone(),
+                model: model(&openai, "gpt-4o"),
+            }
+        );
+        assert_eq!(
+            explain(Some(&InferenceProvider::Ollama), "llama3"),
+            ResolutionOutcome::ProviderNotFound {
+                provider: InferenceProvider::Ollama,
+            }
+        );
+        assert_eq!(
+            explain(Some(&InferenceProvider::Anthropic), "claude-3-5-sonnet"),
+            ResolutionOutcome::ProviderDisabled {
+                provider: InferenceProvider::Anthropic,
+            }
+        );
+        assert_eq!(
+            explain(Some(&openai), "gpt-4o-mini"),
+            ResolutionOutcome::ModelDisabled {
+                provider: openai.clone(),
+                model: model(&openai, "gpt-4o-mini"),
+            }
+        );
+        let outcome = explain(Some(&openai), "gpt-4-0613");
+        assert!(matches!(
+            &outcome,
+            ResolutionOutcome::DeprecatedSunset { deprecation, .. }
+                if deprecation.replacement.as_deref() == Some("gpt-4o")
+        ));
+        assert_eq!(
+            outcome.to_string(),
+            "model 'gpt-4-0613' of provider openai was sunset on 2025-06-01, \
+             use 'gpt-4o' instead"
+        );
+        let ResolutionOutcome::ModelNotListed { suggestions, .. } =
+            explain(Some(&openai), "gpt-4-o")
+        else {
+            panic!("expected the model to not be listed");
+        };
+        assert_eq!(suggestions[0], (openai.clone(), model(&openai, "gpt-4o")));
+
+        // without a provider, the first provider serving the model wins
+        assert_eq!(
+            explain(None, "gpt-4o-mini"),
+            ResolutionOutcome::Resolved {
+                provider: openrouter.clone(),
+                model: model(&openrouter, "gpt-4o-mini"),
             }
         );
-        // Check Anthropic provider
-        let anthropic_config =
-            config.get(&InferenceProvider::Anthropic).unwrap();
-        assert_eq!(anthropic_config.models.len(), 2);
-        let model_ids: Vec<ModelId> =
-            anthropic_config.models.clone().into_iter().collect();
-        let date =
-            chrono::NaiveDate::parse_from_str("20240229", "%Y%m%d").unwrap();
-        let naive_dt = date.and_hms_opt(0, 0, 0).unwrap();
-        let date = chrono::Utc.from_utc_datetime(&naive_dt);
         assert_eq!(
-            model_ids[0],
-            ModelId::ModelIdWithVersion {
-                provider: InferenceProvider::Anthropic,
-                id: crate::types::model_id::ModelIdWithVersion {
-                    model: "claude-3-opus".to_string(),
-                    version: crate::types::model_id::Version::Date {
-                        date,
-                        format: "%Y%m%d",
-                    },
-                },
+            explain(None, "openai/gpt-4o-mini"),
+            ResolutionOutcome::ModelDisabled {
+                provider: openai.clone(),
+                model: model(&openai, "gpt-4o-mini"),
             }
         );
+        assert!(matches!(
+            explain(None, "claude-3-5-sonnet"),
+            ResolutionOutcome::ProviderDisabled { .. }
+        ));
+        assert!(matches!(
+            explain(None, "unknown-model"),
+            ResolutionOutcome::ModelNotListed { .. }
+        ));
+    }
+
+    #[test]
+    fn test_merge_model_metadata() {
+        let providers_yaml = r"
+openai:
+  models:
+    - gpt-4o
+    - name: gpt-4o-mini
+      capabilities:
+        context-window: 64000
+  base-url: https://api.openai.com
+openrouter:
+  models:
+    - anthropic/claude-3-5-sonnet
+  base-url: https://openrouter.ai/api
+";
+        let models_yaml = r"
+openai/gpt-4o:
+  capabilities:
+    context-window: 128000
+openai/gpt-4o-mini:
+  capabilities:
+    context-window: 128000
+    supports-tools: true
+openrouter/anthropic/claude-3-5-sonnet:
+  enabled: false
+";
+        let mut config: ProvidersConfig =
+            serde_yml::from_str(providers_yaml).unwrap();
+        let models: ModelsConfig = serde_yml::from_str(models_yaml).unwrap();
+        config
+            .merge_model_metadata(models, UnlistedModelPolicy::Error)
+            .unwrap();
+
+        let openai = &config[&InferenceProvider::OpenAI];
+        let model = |provider: &InferenceProvider, name: &str| {
+            ModelId::from_str_and_provider(provider.clone(), name).unwrap()
+        };
+        let gpt_4o = openai
+            .model_capabilities(&model(&InferenceProvider::OpenAI, "gpt-4o"));
+        assert_eq!(gpt_4o.context_window.map(NonZeroU32::get), Some(128_000));
+        // inline metadata wins over the models file
+        let gpt_4o_mini = openai.model_capabilities(&model(
+            &InferenceProvider::OpenAI,
+            "gpt-4o-mini",
+        ));
+        assert_eq!(
+            gpt_4o_mini.context_window.map(NonZeroU32::get),
+            Some(64_000)
+        );
+        assert_eq!(gpt_4o_mini.supports_tools, Some(true));
+        let openrouter = InferenceProvider::Named("openrouter".into());
+        assert!(!config[&openrouter].is_model_enabled(&model(
+            &openrouter,
+            "anthropic/claude-3-5-sonnet"
+        )));
+
+        let unlisted: ModelsConfig =
+            serde_yml::from_str("openai/gpt-5:\n  enabled: false\n").unwrap();
+        let before = config.clone();
+        assert_eq!(
+            config.merge_model_metadata(
+                unlisted.clone(),
+                UnlistedModelPolicy::Error
+            ),
+            Err(ProvidersConfigError::UnlistedModelMetadata {
+                provider: InferenceProvider::OpenAI,
+                model: "gpt-5".to_string(),
+            })
+        );
+        config
+            .merge_model_metadata(unlisted, UnlistedModelPolicy::Warn)
+            .unwrap();
+        assert_eq!(config, before);
+
+        let invalid: ModelsConfig =
+            serde_yml::from_str("gpt-4o:\n  enabled: false\n").unwrap();
+        assert_eq!(
+            config.merge_model_metadata(invalid, UnlistedModelPolicy::Warn),
+            Err(ProvidersConfigError::InvalidModelMetadataKey(
+                "gpt-4o".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_duplicate_provider_keys_are_rejected() {
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+  base-url: https://api.openai.com
+groq:
+  models:
+    - llama-3.3-70b-versatile
+  base-url: https://api.groq.com
+openai:
+  models:
+    - gpt-4o-mini
+  base-url: https://proxy.internal
+";
+        let err = serde_yml::from_str::<ProvidersConfig>(yaml).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("duplicate provider 'openai' in configuration"),
+            "unexpected error: {err}"
+        );
+
+        let provider = |models: &str| {
+            format!(
+                r#"{{"models": ["{models}"], "base-url": "https://api.groq.com"}}"#
+            )
+        };
+        let json = format!(
+            r#"{{"groq": {}, "anthropic": {}, "groq": {}}}"#,
+            provider("llama-3.3-70b-versatile"),
+            provider("claude-3-5-sonnet"),
+            provider("llama-3.1-8b-instant"),
+        );
+        let err = serde_json::from_str::<ProvidersConfig>(&json).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("duplicate provider 'groq' in configuration"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn test_merge_overrides_onto_defaults() {
+        let overrides = r"
+anthropic:
+  base-url: https://anthropic.proxy.internal
+together:
+  models:
+    - meta-llama/Llama-3.3-70B-Instruct-Turbo
+  base-url: https://api.together.xyz
+";
+        let overrides: ProviderOverrides =
+            serde_yml::from_str(overrides).unwrap();
+        let defaults = ProvidersConfig::default();
+        let config = defaults.clone().with_overrides(overrides).unwrap();
+
+        let anthropic = &config[&InferenceProvider::Anthropic];
+        let default_anthropic = &defaults[&InferenceProvider::Anthropic];
+        assert_eq!(
+            anthropic.base_url.as_str(),
+            "https://anthropic.proxy.internal/"
+        );
+        assert_eq!(anthropic.models, default_anthropic.models);
+        assert_eq!(anthropic.version, default_anthropic.version);
+        assert_eq!(
+            config[&InferenceProvider::OpenAI],
+            defaults[&InferenceProvider::OpenAI]
+        );
+
+        // defaults keep their position, new providers come last
+        let providers = config.keys().cloned().collect::<Vec<_>>();
+        let mut expected = defaults.keys().cloned().collect::<Vec<_>>();
+        expected.push(InferenceProvider::Named("together".into()));
+        assert_eq!(providers, expected);
+
+        // new providers must be complete, and errors leave the config as is
+        let mut config = defaults.clone();
+        let incomplete: ProviderOverrides = serde_yml::from_str(
+            "cerebras:\n  base-url: https://api.cerebras.ai\n",
+        )
+        .unwrap();
+        assert!(matches!(
+            config.merge(incomplete),
+            Err(ProvidersConfigError::InvalidOverride { provider, .. })
+                if provider == InferenceProvider::Named("cerebras".into())
+        ));
+        assert_eq!(config, defaults);
+
+        // misspelled fields are rejected rather than ignored
+        let err = serde_yml::from_str::<ProviderOverrides>(
+            "anthropic:\n  base_url: https://anthropic.proxy.internal\n",
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("unknown field"), "{err}");
+    }
+
+    #[test]
+    fn test_merge_overrides_extends_headers() {
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+  base-url: https://api.openai.com
+  headers:
+    x-team: platform
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let overrides: ProviderOverrides = serde_yml::from_str(
+            r"
+openai:
+  organization: org-123
+  headers:
+    x-region: eu
+",
+        )
+        .unwrap();
+        let config = config.with_overrides(overrides).unwrap();
+
+        let openai = &config[&InferenceProvider::OpenAI];
+        assert_eq!(openai.organization.as_deref(), Some("org-123"));
+        assert_eq!(openai.headers["x-team"], "platform");
+        assert_eq!(openai.headers["x-region"], "eu");
+        assert_eq!(openai.base_url.as_str(), "https://api.openai.com/");
+    }
+
+    #[test]
+    fn test_merge_does_not_interpolate_existing_values_again() {
+        let _org = EnvGuard::set("AIGW_TEST_MERGE_ORG", "org-${literal}");
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+  base-url: https://api.openai.com
+  organization: ${AIGW_TEST_MERGE_ORG}
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        assert_eq!(
+            config[&InferenceProvider::OpenAI].organization.as_deref(),
+            Some("org-${literal}")
+        );
+        let overrides: ProviderOverrides =
+            serde_yml::from_str("openai:\n  headers:\n    x-region: eu\n")
+                .unwrap();
+        let config = config.with_overrides(overrides).unwrap();
+
+        let openai = &config[&InferenceProvider::OpenAI];
+        assert_eq!(openai.organization.as_deref(), Some("org-${literal}"));
+        assert_eq!(openai.headers["x-region"], "eu");
+    }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("gpt-4*", "gpt-4"));
+        assert!(glob_matches("gpt-4*", "gpt-4o-mini"));
+        assert!(glob_matches(
+            "claude-3-*-20240229",
+            "claude-3-opus-20240229"
+        ));
+        assert!(glob_matches("claude-3-?-*", "claude-3-x-y"));
+        assert!(glob_matches("*-preview*", "o1-preview-2024-09-12"));
+        assert!(!glob_matches("gpt-4*", "gpt-3.5-turbo"));
+        assert!(!glob_matches(
+            "claude-3-*-20240229",
+            "claude-3-opus-20240307"
+        ));
+        assert!(!glob_matches("claude-3-?-*", "claude-3-opus"));
+    }
+
+    #[test]
+    fn test_model_patterns() {
+        let yaml = r"
+openai:
+  models:
+    - gpt-3.5-turbo
+    - gpt-4o-2024-*
+  base-url: https://api.openai.com
+anthropic:
+  models:
+    - claude-3-*-20240229
+  base-url: https://api.anthropic.com
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let openai = &config[&InferenceProvider::OpenAI];
+        let anthropic = &config[&InferenceProvider::Anthropic];
+        assert_eq!(openai.models.len(), 1);
+        assert_eq!(openai.model_patterns, vec!["gpt-4o-2024-*".to_string()]);
+
+        assert!(
+            openai.matches_model(&InferenceProvider::OpenAI, "gpt-3.5-turbo")
+        );
+        assert!(
+            openai
+                .matches_model(&InferenceProvider::OpenAI, "gpt-4o-2024-08-06")
+        );
+        assert!(anthropic.matches_model(
+            &InferenceProvider::Anthropic,
+            "claude-3-opus-20240229"
+        ));
+        assert!(!openai.matches_model(&InferenceProvider::OpenAI, "gpt-4o"));
+        assert!(!anthropic.matches_model(
+            &InferenceProvider::Anthropic,
+            "claude-3-5-sonnet-20240620"
+        ));
+
+        let serialized = serde_yml::to_string(&config).unwrap();
+        assert!(serialized.contains("- gpt-4o-2024-*"));
+        assert!(serialized.contains("- claude-3-*-20240229"));
+        let deserialized: ProvidersConfig =
+            serde_yml::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, config);
+
+        let yaml = r"
+openai:
+  models:
+    - name: gpt-4*
+      enabled: false
+  base-url: https://api.openai.com
+";
+        assert!(serde_yml::from_str::<ProvidersConfig>(yaml).is_err());
+    }
+
+    #[test]
+    fn test_latest_version_of() {
+        let yaml = r"
+anthropic:
+  models:
+    - claude-3-opus-20240307
+    - claude-3-opus-20240229
+    - claude-3-sonnet
+    - claude-3-sonnet-20240229
+  base-url: https://api.anthropic.com
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let anthropic = &config[&InferenceProvider::Anthropic];
+        let model = |name: &str| {
+            ModelId::from_str_and_provider(InferenceProvider::Anthropic, name)
+                .unwrap()
+        };
+
+        assert_eq!(
+            anthropic.latest_version_of("claude-3-opus"),
+            Some(&model("claude-3-opus-20240307"))
+        );
+        assert_eq!(
+            anthropic.latest_version_of("claude-3-sonnet"),
+            Some(&model("claude-3-sonnet"))
+        );
+        assert_eq!(anthropic.latest_version_of("claude-3-haiku"), None);
+    }
+
+    #[test]
+    fn test_custom_headers_round_trip() {
+        let yaml = r"
+openai:
+  models:
+  - gpt-4o
+  base-url: https://my-deployment.openai.azure.com/
+  headers:
+    api-key: secret-key
+    X-Org-Id: org-123
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let openai = &config[&InferenceProvider::OpenAI];
+        assert_eq!(openai.headers.len(), 2);
+        assert_eq!(serde_yml::to_string(&config).unwrap().trim(), yaml.trim());
+
+        let headers = openai.request_headers().unwrap();
+        assert_eq!(headers["api-key"], "secret-key");
+        assert_eq!(headers["x-org-id"], "org-123");
+
+        let default_serialized =
+            serde_yml::to_string(&ProvidersConfig::default()).unwrap();
+        assert!(!default_serialized.contains("headers"));
+
+        let yaml = r"
+openai:
+  models:
+  - gpt-4o
+  base-url: https://api.openai.com
+  headers:
+    not a header: value
+";
+        let err = serde_yml::from_str::<ProvidersConfig>(yaml).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("Invalid header name 'not a header'"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn test_find_provider_for_model() {
+        let config = ProvidersConfig::default();
+        let (provider, model) =
+            config.find_provider_for_model("gpt-4o-mini").unwrap();
+        assert_eq!(*provider, InferenceProvider::OpenAI);
+        assert_eq!(model.to_string(), "gpt-4o-mini");
+        assert!(config.find_provider_for_model("not-a-real-model").is_none());
+        assert!(
+            config
+                .find_all_providers_for_model("not-a-real-model")
+                .is_empty()
+        );
+
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+  base-url: https://api.openai.com
+anthropic:
+  models:
+    - claude-3-5-sonnet
+  base-url: https://api.anthropic.com
+openrouter:
+  models:
+    - gpt-4o
+  base-url: https://openrouter.ai/api
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let providers = config
+            .find_all_providers_for_model("gpt-4o")
+            .into_iter()
+            .map(|(provider, _)| provider.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            providers,
+            vec![
+                InferenceProvider::OpenAI,
+                InferenceProvider::Named("openrouter".into())
+            ]
+        );
+        assert_eq!(
+            config.find_provider_for_model("gpt-4o").unwrap().0,
+            &InferenceProvider::OpenAI
+        );
+    }
+
+    #[test]
+    fn test_resolve_api_key() {
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+  base-url: https://api.openai.com
+  api-key-env: AI_GATEWAY_TEST_RESOLVE_OPENAI_KEY
+anthropic:
+  models:
+    - claude-3-5-sonnet
+  base-url: https://api.anthropic.com
+";
+        // loading does not read the variable
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let openai = &config[&InferenceProvider::OpenAI];
+        assert_eq!(
+            openai.resolve_api_key(),
+            Err(ProvidersConfigError::MissingApiKey(
+                "AI_GATEWAY_TEST_RESOLVE_OPENAI_KEY".to_string()
+            ))
+        );
+        assert_eq!(
+            config[&InferenceProvider::Anthropic].resolve_api_key(),
+            Ok(None)
+        );
+
+        {
+            let _guard =
+                EnvGuard::set("AI_GATEWAY_TEST_RESOLVE_OPENAI_KEY", "sk-123");
+            assert_eq!(openai.resolve_api_key(), Ok(Some("sk-123".into())));
+        }
+        {
+            let _guard =
+                EnvGuard::set("AI_GATEWAY_TEST_RESOLVE_OPENAI_KEY", "");
+            assert!(openai.resolve_api_key().is_err());
+        }
+        assert!(openai.resolve_api_key().is_err());
+
+        let serialized = serde_yml::to_string(&config).unwrap();
+        assert!(
+            serialized
+                .contains("api-key-env: AI_GATEWAY_TEST_RESOLVE_OPENAI_KEY")
+        );
+    }
+
+    #[test]
+    fn test_api_key_env_is_rejected_for_bedrock() {
+        let yaml = r"
+bedrock:
+  models:
+    - anthropic.claude-3-5-sonnet-20240620-v1:0
+  base-url: https://bedrock-runtime.us-east-1.amazonaws.com
+  api-key-env: AI_GATEWAY_TEST_BEDROCK_KEY
+";
+        let err = serde_yml::from_str::<ProvidersConfig>(yaml).unwrap_err();
+        assert!(
+            err.to_string().contains("AWS credentials"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn test_named_provider_keys_are_normalized() {
+        let yaml = r"
+OpenRouter:
+  models:
+    - gpt-4o
+  base-url: https://openrouter.ai/api
+  aliases:
+    - ' My-Router '
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let key = InferenceProvider::Named("openrouter".into());
+        assert!(config.contains_key(&key));
+        assert!(config.get(&key).is_some());
+        assert!(
+            config
+                .get(&InferenceProvider::Named(" OpenRouter ".into()))
+                .is_some()
+        );
+        assert!(
+            config
+                .get(&InferenceProvider::Named("my-router".into()))
+                .is_some()
+        );
+
+        let yaml = r"
+openrouter:
+  models:
+    - gpt-4o
+  base-url: https://openrouter.ai/api
+OpenRouter:
+  models:
+    - gpt-4o
+  base-url: https://openrouter.ai/api
+";
+        let err = serde_yml::from_str::<ProvidersConfig>(yaml).unwrap_err();
+        assert!(
+            err.to_string().contains("duplicate provider 'openrouter'"),
+            "unexpected error: {err}"
+        );
+    }
+
+    #[test]
+    fn test_enabled_providers() {
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+  base-url: https://api.openai.com
+anthropic:
+  models:
+    - claude-3-5-sonnet
+  base-url: https://api.anthropic.com
+  enabled: false
+ollama:
+  models:
+    - llama3
+  base-url: http://localhost:11434
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let enabled = config
+            .enabled()
+            .map(|(provider, _)| provider.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            enabled,
+            vec![InferenceProvider::OpenAI, InferenceProvider::Ollama]
+        );
+        assert_eq!(config.len(), 3);
+        assert!(!config[&InferenceProvider::Anthropic].enabled);
+        assert!(config.providers_serving("claude-3-5-sonnet").is_empty());
+
+        let serialized = serde_yml::to_string(&config).unwrap();
+        assert_eq!(serialized.matches("enabled").count(), 1);
+        let round_tripped: ProvidersConfig =
+            serde_yml::from_str(&serialized).unwrap();
+        assert_eq!(round_tripped, config);
+    }
+
+    #[test]
+    fn test_from_path_is_format_agnostic() {
+        let dir = std::env::temp_dir()
+            .join(format!("ai-gateway-from-path-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+    - name: gpt-4o-mini
+      capabilities:
+        context-window: 128000
+  base-url: https://api.openai.com
+anthropic:
+  models:
+    - claude-3-opus-20240229
+  base-url: https://api.anthropic.com
+  version: 2023-06-01
+";
+        let json = r#"{
+  "openai": {
+    "models": [
+      "gpt-4o",
+      {"name": "gpt-4o-mini", "capabilities": {"context-window": 128000}}
+    ],
+    "base-url": "https://api.openai.com"
+  },
+  "anthropic": {
+    "models": ["claude-3-opus-20240229"],
+    "base-url": "https://api.anthropic.com",
+    "version": "2023-06-01"
+  }
+}"#;
+        let toml = r#"
+[openai]
+models = [
+  "gpt-4o",
+  { name = "gpt-4o-mini", capabilities = { context-window = 128000 } },
+]
+base-url = "https://api.openai.com"
+
+[anthropic]
+models = ["claude-3-opus-20240229"]
+base-url = "https://api.anthropic.com"
+version = "2023-06-01"
+"#;
+        let load = |name: &str, contents: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, contents).unwrap();
+            ProvidersConfig::from_path(&path)
+        };
+        let from_yaml = load("providers.yaml", yaml).unwrap();
+        assert_eq!(from_yaml, serde_yml::from_str(yaml).unwrap());
+        let openai = &from_yaml[&InferenceProvider::OpenAI];
+        let gpt_4o_mini = openai
+            .parse_model(&InferenceProvider::OpenAI, "gpt-4o-mini")
+            .unwrap();
+        assert_eq!(
+            openai
+                .model_capabilities(&gpt_4o_mini)
+                .context_window
+                .map(NonZeroU32::get),
+            Some(128_000)
+        );
+        assert_eq!(load("providers.json", json).unwrap(), from_yaml);
+        assert_eq!(load("providers.toml", toml).unwrap(), from_yaml);
+
+        assert!(matches!(
+            load("providers.ini", yaml),
+            Err(ConfigLoadError::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            load("providers", yaml),
+            Err(ConfigLoadError::UnsupportedFormat(_))
+        ));
+        assert!(matches!(
+            load("invalid.json", yaml),
+            Err(ConfigLoadError::Json(_))
+        ));
+        assert!(matches!(
+            ProvidersConfig::from_path(dir.join("missing.yml")),
+            Err(ConfigLoadError::Io(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_base_urls_are_rejected() {
+        let config_with = |base_url: &str| {
+            serde_yml::from_str::<ProvidersConfig>(&format!(
+                "openai:\n  models:\n    - gpt-4o\n  base-url: '{base_url}'\n"
+            ))
+        };
+        let cases = [
+            ("api.openai.com", "relative URL without a base"),
+            (
+                "localhost:11434",
+                "scheme must be http or https, not 'localhost'",
+            ),
+            (
+                "ftp://api.openai.com",
+                "scheme must be http or https, not 'ftp'",
+            ),
+            (
+                "https://api.openai.com?foo=bar",
+                "must not have a query string",
+            ),
+            ("https://api.openai.com/v1#chat", "must not have a fragment"),
+        ];
+        for (base_url, problem) in cases {
+            let err = config_with(base_url).unwrap_err().to_string();
+            assert!(
+                err.contains(&format!(
+                    "Invalid base URL '{base_url}' for provider openai: \
+                     {problem}"
+                )),
+                "unexpected error for {base_url}: {err}"
+            );
+        }
+
+        let config = config_with("https://api.openai.com").unwrap();
+        assert_eq!(
+            config[&InferenceProvider::OpenAI].base_url.as_str(),
+            "https://api.openai.com/"
+        );
+        assert!(config_with("http://localhost:11434").is_ok());
+    }
+
+    #[test]
+    fn test_merge_discovered_models() {
+        let yaml = r"
+openai:
+  models:
+    - name: gpt-4o
+      capabilities:
+        context-window: 128000
+  base-url: https://api.openai.com
+groq:
+  models:
+    - llama-3.3-*
+  base-url: https://api.groq.com/openai
+";
+        let mut config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let openai = InferenceProvider::OpenAI;
+        let added = config
+            .merge_discovered_models(&openai, ["gpt-4o", "gpt-4o-mini", "o3"]);
+        assert_eq!(added, 2);
+        assert!(config.resolve_model(&openai, "gpt-4o-mini").is_some());
+        assert!(config.resolve_model(&openai, "o3").is_some());
+        let gpt_4o = config[&openai].parse_model(&openai, "gpt-4o").unwrap();
+        assert_eq!(
+            config[&openai]
+                .model_capabilities(&gpt_4o)
+                .context_window
+                .map(NonZeroU32::get),
+            Some(128_000)
+        );
+
+        // patterns act as an allowlist
+        let groq = InferenceProvider::Named("groq".into());
+        let added = config.merge_discovered_models(
+            &groq,
+            ["llama-3.3-70b-versatile", "gemma2-9b-it"],
+        );
+        assert_eq!(added, 1);
+        assert!(
+            config
+                .resolve_model(&groq, "llama-3.3-70b-versatile")
+                .is_some()
+        );
+        assert!(config.resolve_model(&groq, "gemma2-9b-it").is_none());
+
+        let unknown = InferenceProvider::Named("unknown".into());
+        assert_eq!(config.merge_discovered_models(&unknown, ["gpt-4o"]), 0);
+    }
+
+    #[test]
+    fn test_embedding_dimensions_capability() {
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+    - name: text-embedding-3-small
+      capabilities:
+        embedding-dimensions: 1536
+  base-url: https://api.openai.com
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        let openai = &config[&InferenceProvider::OpenAI];
+        let model = |name: &str| {
+            ModelId::from_str_and_provider(InferenceProvider::OpenAI, name)
+                .unwrap()
+        };
+
+        assert_eq!(
+            openai
+                .model_capabilities(&model("text-embedding-3-small"))
+                .embedding_dimensions,
+            NonZeroU32::new(1536)
+        );
+        assert_eq!(
+            openai
+                .model_capabilities(&model("gpt-4o"))
+                .embedding_dimensions,
+            None
+        );
     }
 }
@@ -0,0 +1,390 @@
+use std::sync::{Arc, Mutex, PoisonError, RwLock};
+
+use rustc_hash::FxHashMap as HashMap;
+use tokio::sync::broadcast;
+
+use crate::{
+    config::providers::{
+        GlobalProviderConfig, ModelIndex, ProvidersConfig, ProvidersConfigError,
+    },
+    types::provider::InferenceProvider,
+};
+
+/// How many config versions a slow subscriber may fall behind before it
+/// starts missing updates.
+const SUBSCRIBER_CAPACITY: usize = 16;
+
+tokio::task_local! {
+    /// See [`ProvidersConfigStore::staged`].
+    static STAGED: ProvidersSnapshot;
+}
+
+/// A [`ProvidersConfig`] together with the [`ModelIndex`] built from it, so
+/// the two are always consistent.
+#[derive(Debug, Clone)]
+pub struct ProvidersSnapshot {
+    pub config: Arc<ProvidersConfig>,
+    pub index: Arc<ModelIndex>,
+}
+
+impl ProvidersSnapshot {
+    fn new(config: Arc<ProvidersConfig>) -> Self {
+        let index = Arc::new(ModelIndex::new(&config));
+        Self { config, index }
+    }
+}
+
+/// What changed between two versions of the config in a
+/// [`ProvidersConfigStore`].
+#[derive(Debug, Clone)]
+pub enum ProvidersConfigChange {
+    /// The whole config was replaced.
+    Reloaded(Arc<ProvidersConfig>),
+    /// Only the config of `provider` was replaced.
+    Changed {
+        provider: InferenceProvider,
+        config: Arc<ProvidersConfig>,
+    },
+}
+
+impl ProvidersConfigChange {
+    /// The config after the change.
+    #[must_use]
+    pub fn config(&self) -> &Arc<ProvidersConfig> {
+        match self {
+            ProvidersConfigChange::Reloaded(config)
+            | ProvidersConfigChange::Changed { config, .. } => config,
+        }
+    }
+}
+
+/// Holds the current [`ProvidersConfig`] and notifies subscribers whenever
+/// it is replaced.
+///
+/// Decouples where the config comes from (file, env, API) from the
+/// components that have to react to it. Configs are validated before they
+/// are swapped in, so readers only ever see a validated config.
+///
+/// Providers replaced at runtime with
+/// [`ProvidersConfigStore::update_provider`] keep their config across
+/// [`ProvidersConfigStore::refresh`]es, until the next
+/// [`ProvidersConfigStore::load`].
+///
+/// The [`ModelIndex`] of the config is rebuilt on every change, see
+/// [`ProvidersConfigStore::snapshot`].
+#[derive(Debug)]
+pub struct ProvidersConfigStore {
+    current: RwLock<ProvidersSnapshot>,
+    /// Only locked while holding the write lock of `current`.
+    overrides: Mutex<HashMap<InferenceProvider, GlobalProviderConfig>>,
+    updates: broadcast::Sender<Arc<ProvidersConfig>>,
+    changes: broadcast::Sender<ProvidersConfigChange>,
+}
+
+impl ProvidersConfigStore {
+    pub fn new(config: ProvidersConfig) -> Result<Self, ProvidersConfigError> {
+        config.validate()?;
+        let (updates, _) = broadcast::channel(SUBSCRIBER_CAPACITY);
+        let (changes, _) = broadcast::channel(SUBSCRIBER_CAPACITY);
+        Ok(Self {
+            current: RwLock::new(ProvidersSnapshot::new(Arc::new(config))),
+            overrides: Mutex::default(),
+            updates,
+            changes,
+        })
+    }
+
+    /// A snapshot of the current config, or of the staged config within
+    /// [`ProvidersConfigStore::staged`].
+    #[must_use]
+    pub fn get(&self) -> Arc<ProvidersConfig> {
+        self.snapshot().config
+    }
+
+    /// Like [`ProvidersConfigStore::get`], but with the [`ModelIndex`] of
+    /// the config.
+    #[must_use]
+    pub fn snapshot(&self) -> ProvidersSnapshot {
+        STAGED.try_with(Clone::clone).unwrap_or_else(|_| {
+            self.current
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        })
+    }
+
+    /// Run `f` as if `config` were the current config, without loading it,
+    /// e.g. to build the routers of a config before swapping it in.
+    ///
+    /// Only [`ProvidersConfigStore::get`]s on the task of `f` see `config`,
+    /// every other task keeps seeing the current config.
+    pub async fn staged<F: Future>(
+        config: Arc<ProvidersConfig>,
+        f: F,
+    ) -> F::Output {
+        STAGED.scope(ProvidersSnapshot::new(config), f).await
+    }
+
+    /// Validate `config` and, if valid, make it the current config and
+    /// notify subscribers. An invalid config leaves the current one in
+    /// place.
+    ///
+    /// Drops the providers replaced with
+    /// [`ProvidersConfigStore::update_provider`].
+    pub fn load(
+        &self,
+        config: ProvidersConfig,
+    ) -> Result<(), ProvidersConfigError> {
+        config.validate()?;
+        let snapshot = ProvidersSnapshot::new(Arc::new(config));
+        let mut current =
+            self.current.write().unwrap_or_else(PoisonError::into_inner);
+        self.overrides
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+        let config = snapshot.config.clone();
+        *current = snapshot;
+        self.notify(ProvidersConfigChange::Reloaded(config));
+        Ok(())
+    }
+
+    /// Like [`ProvidersConfigStore::load`], but the providers replaced with
+    /// [`ProvidersConfigStore::update_provider`] are replaced again in
+    /// `config` before `update` is applied to it, e.g. to add the models
+    /// discovered since.
+    pub fn refresh(
+        &self,
+        mut config: ProvidersConfig,
+        update: impl FnOnce(&mut ProvidersConfig),
+    ) -> Result<(), ProvidersConfigError> {
+        let mut current =
+            self.current.write().unwrap_or_else(PoisonError::into_inner);
+        for (provider, provider_config) in self
+            .overrides
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+        {
+            config.insert(provider.clone(), provider_config.clone());
+        }
+        update(&mut config);
+        config.validate()?;
+        let snapshot = ProvidersSnapshot::new(Arc::new(config));
+        let config = snapshot.config.clone();
+        *current = snapshot;
+        self.notify(ProvidersConfigChange::Reloaded(config));
+        Ok(())
+    }
+
+    /// Replace only the config of `provider`, keeping every other provider
+    /// as is, and notify subscribers with a
+    /// [`ProvidersConfigChange::Changed`].
+    ///
+    /// The resulting config is validated before it is swapped in, and
+    /// concurrent updates are applied one after the other so none are lost.
+    pub fn update_provider(
+        &self,
+        provider: InferenceProvider,
+        provider_config: GlobalProviderConfig,
+    ) -> Result<(), ProvidersConfigError> {
+        let mut current =
+            self.current.write().unwrap_or_else(PoisonError::into_inner);
+        let mut config = ProvidersConfig::clone(&current.config);
+        config.insert(provider.clone(), provider_config.clone());
+        config.validate()?;
+        self.overrides
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(provider.clone(), provider_config);
+        let snapshot = ProvidersSnapshot::new(Arc::new(config));
+        let config = snapshot.config.clone();
+        *current = snapshot;
+        self.notify(ProvidersConfigChange::Changed { provider, config });
+        Ok(())
+    }
+
+    /// Must be called while holding the write lock so that subscribers see
+    /// changes in the order they were applied.
+    fn notify(&self, change: ProvidersConfigChange) {
+        // no subscribers is fine
+        let _ = self.updates.send(change.config().clone());
+        let _ = self.changes.send(change);
+    }
+
+    /// Receive every config loaded after this call.
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<ProvidersConfig>> {
+        self.updates.subscribe()
+    }
+
+    /// Like [`ProvidersConfigStore::subscribe`], but also receive what
+    /// changed, so subscribers can avoid rebuilding state that is not
+    /// affected.
+    #[must_use]
+    pub fn subscribe_changes(
+        &self,
+    ) -> broadcast::Receiver<ProvidersConfigChange> {
+        self.changes.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load_validates_and_notifies_subscribers() {
+        let store = ProvidersConfigStore::new(ProvidersConfig::default())
+            .expect("default config is valid");
+        let mut updates = store.subscribe();
+
+        let mut config = ProvidersConfig::default();
+        config.shift_remove(&InferenceProvider::Ollama);
+        store.load(config.clone()).unwrap();
+        assert_eq!(*store.get(), config);
+        assert_eq!(*updates.recv().await.unwrap(), config);
+
+        let mut invalid = ProvidersConfig::default();
+        invalid
+            .get_mut(&InferenceProvider::OpenAI)
+            .unwrap()
+            .aliases
+            .insert(InferenceProvider::Anthropic);
+        assert_eq!(
+            store.load(invalid),
+            Err(ProvidersConfigError::AliasCollision {
+                alias: InferenceProvider::Anthropic,
+                provider: InferenceProvider::OpenAI,
+            })
+        );
+        assert_eq!(*store.get(), config);
+        assert!(updates.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_update_provider_replaces_only_that_provider() {
+        let store = ProvidersConfigStore::new(ProvidersConfig::default())
+            .expect("default config is valid");
+        let mut updates = store.subscribe();
+        let mut changes = store.subscribe_changes();
+
+        let mut openai = store.get()[&InferenceProvider::OpenAI].clone();
+        openai.base_url = "https://gw.internal/openai".parse().unwrap();
+        store
+            .update_provider(InferenceProvider::OpenAI, openai.clone())
+            .unwrap();
+
+        let current = store.get();
+        assert_eq!(current[&InferenceProvider::OpenAI], openai);
+        assert_eq!(
+            current[&InferenceProvider::Anthropic],
+            ProvidersConfig::default()[&InferenceProvider::Anthropic]
+        );
+        assert_eq!(*updates.recv().await.unwrap(), *current);
+        let ProvidersConfigChange::Changed { provider, config } =
+            changes.recv().await.unwrap()
+        else {
+            panic!("expected a single provider change");
+        };
+        assert_eq!(provider, InferenceProvider::OpenAI);
+        assert_eq!(*config, *current);
+
+        let mut invalid = openai.clone();
+        invalid.aliases.insert(InferenceProvider::Anthropic);
+        assert!(
+            store
+                .update_provider(InferenceProvider::OpenAI, invalid)
+                .is_err()
+        );
+        assert_eq!(store.get()[&InferenceProvider::OpenAI], openai);
+        assert!(changes.try_recv().is_err());
+
+        store.load(ProvidersConfig::default()).unwrap();
+        assert!(matches!(
+            changes.recv().await.unwrap(),
+            ProvidersConfigChange::Reloaded(_)
+        ));
+    }
+
+    #[test]
+    fn test_model_index_is_rebuilt_with_the_config() {
+        let store = ProvidersConfigStore::new(ProvidersConfig::default())
+            .expect("default config is valid");
+        let serves_gpt_4o = |snapshot: &ProvidersSnapshot| {
+            assert_eq!(
+                snapshot.index.config_hash(),
+                snapshot.config.canonical_hash()
+            );
+            snapshot
+                .index
+                .resolve("gpt-4o")
+                .iter()
+                .any(|(provider, _)| *provider == InferenceProvider::OpenAI)
+        };
+        assert!(serves_gpt_4o(&store.snapshot()));
+
+        let mut openai = store.get()[&InferenceProvider::OpenAI].clone();
+        openai
+            .models
+            .retain(|model| model.as_model_name().to_string() != "gpt-4o");
+        store
+            .update_provider(InferenceProvider::OpenAI, openai)
+            .unwrap();
+        assert!(!serves_gpt_4o(&store.snapshot()));
+
+        store.load(ProvidersConfig::default()).unwrap();
+        assert!(serves_gpt_4o(&store.snapshot()));
+    }
+
+    #[tokio::test]
+    async fn test_staged_config_is_only_seen_by_its_task() {
+        let store = Arc::new(
+            ProvidersConfigStore::new(ProvidersConfig::default())
+                .expect("default config is valid"),
+        );
+        let mut staged = ProvidersConfig::default();
+        staged.shift_remove(&InferenceProvider::Ollama);
+        let staged = Arc::new(staged);
+
+        let store_in_scope = store.clone();
+        let (in_scope, other_task) =
+            ProvidersConfigStore::staged(staged.clone(), async move {
+                let spawned = store_in_scope.clone();
+                let other_task =
+                    tokio::spawn(async move { spawned.get() }).await.unwrap();
+                (store_in_scope.get(), other_task)
+            })
+            .await;
+        assert_eq!(*in_scope, *staged);
+        assert_eq!(*other_task, ProvidersConfig::default());
+        assert_eq!(*store.get(), ProvidersConfig::default());
+    }
+
+    #[test]
+    fn test_refresh_keeps_updated_providers_until_load() {
+        let store = ProvidersConfigStore::new(ProvidersConfig::default())
+            .expect("default config is valid");
+        let mut openai = store.get()[&InferenceProvider::OpenAI].clone();
+        openai.enabled = false;
+        store
+            .update_provider(InferenceProvider::OpenAI, openai.clone())
+            .unwrap();
+
+        let mut anthropic =
+            ProvidersConfig::default()[&InferenceProvider::Anthropic].clone();
+        anthropic.base_url = "https://gw.internal/anthropic".parse().unwrap();
+        store
+            .refresh(ProvidersConfig::default(), |config| {
+                assert!(!config[&InferenceProvider::OpenAI].enabled);
+                config.insert(InferenceProvider::Anthropic, anthropic.clone());
+            })
+            .unwrap();
+        assert_eq!(store.get()[&InferenceProvider::OpenAI], openai);
+        assert_eq!(store.get()[&InferenceProvider::Anthropic], anthropic);
+
+        store.load(ProvidersConfig::default()).unwrap();
+        store.refresh(ProvidersConfig::default(), |_| {}).unwrap();
+        assert!(store.get()[&InferenceProvider::OpenAI].enabled);
+    }
+}
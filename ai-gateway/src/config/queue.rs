@@ -0,0 +1,36 @@
+use std::{num::NonZeroUsize, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// Queueing the requests of a router once the concurrency limit towards a
+/// provider is reached, instead of sending them upstream anyway.
+///
+/// See [`crate::router::queue`].
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct QueueConfig {
+    /// The number of requests in flight to each provider of the router.
+    /// Can be replaced per provider with the `max-concurrency` of its
+    /// router provider config.
+    pub max_concurrency: NonZeroUsize,
+    /// The number of requests that can wait across all providers of the
+    /// router. Once it is full, the lowest priority requests are shed.
+    pub max_size: usize,
+    /// Requests waiting for longer than this are shed.
+    #[serde(with = "humantime_serde")]
+    pub timeout: Duration,
+    /// The `retry-after` of the responses to shed requests.
+    #[serde(with = "humantime_serde")]
+    pub retry_after: Duration,
+}
+
+impl Default for QueueConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrency: NonZeroUsize::new(64).unwrap(),
+            max_size: 256,
+            timeout: Duration::from_secs(30),
+            retry_after: Duration::from_secs(1),
+        }
+    }
+}
@@ -10,6 +10,10 @@ pub struct ResponseHeadersConfig {
     pub provider: bool,
     #[serde(default = "default_true")]
     pub provider_request_id: bool,
+    /// The tokens used by the request and its cost in USD, for non-streaming
+    /// requests to models with pricing.
+    #[serde(default = "default_true")]
+    pub cost: bool,
 }
 
 impl Default for ResponseHeadersConfig {
@@ -17,6 +21,7 @@ impl Default for ResponseHeadersConfig {
         Self {
             provider: true,
             provider_request_id: true,
+            cost: true,
         }
     }
 }
@@ -1,11 +1,84 @@
 use std::time::Duration;
 
 use backon::{BackoffBuilder, ConstantBuilder, ExponentialBuilder};
+use http::StatusCode;
 use rust_decimal::{Decimal, prelude::ToPrimitive};
 use serde::{Deserialize, Serialize};
 
 pub(crate) const DEFAULT_RETRY_FACTOR: f32 = 2.0;
 
+/// How failed upstream requests are retried on the same provider, before a
+/// failover router moves on to the next provider.
+#[derive(Debug, Clone, Deserialize, Eq, PartialEq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct RetryPolicy {
+    #[serde(flatten)]
+    pub backoff: RetryConfig,
+    /// Status codes of upstream responses that are retried.
+    #[serde(default = "default_retryable_status_codes")]
+    pub retryable_status_codes: Vec<u16>,
+    /// Error codes in upstream error responses that are retried whatever
+    /// their status code, e.g. `overloaded_error`. Matched against the
+    /// `code`, `type` and `status` fields of the error object.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub retryable_error_codes: Vec<String>,
+    /// Waits for the `retry-after` header of upstream responses instead of
+    /// the backoff delay if it asks for longer.
+    #[serde(default = "default_respect_retry_after")]
+    pub respect_retry_after: bool,
+    /// Upstream responses asking to retry after longer than this are not
+    /// retried, so that a failover router can try another provider instead.
+    #[serde(with = "humantime_serde", default = "default_max_retry_after")]
+    pub max_retry_after: Duration,
+}
+
+impl RetryPolicy {
+    #[must_use]
+    pub fn is_retryable_status(&self, status: StatusCode) -> bool {
+        self.retryable_status_codes.contains(&status.as_u16())
+    }
+
+    #[must_use]
+    pub fn is_retryable_error_code(&self, code: &str) -> bool {
+        self.retryable_error_codes.iter().any(|c| c == code)
+    }
+
+    /// The delay before the next attempt, given the `backoff` delay and the
+    /// `retry-after` of the failed attempt, if any.
+    ///
+    /// Returns `None` if the attempt should not be retried.
+    #[must_use]
+    pub fn next_delay(
+        &self,
+        backoff: Option<Duration>,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        let backoff = backoff?;
+        match retry_after {
+            Some(retry_after) if self.respect_retry_after => {
+                if retry_after > self.max_retry_after {
+                    None
+                } else {
+                    Some(backoff.max(retry_after))
+                }
+            }
+            _ => Some(backoff),
+        }
+    }
+}
+
+impl From<RetryConfig> for RetryPolicy {
+    fn from(backoff: RetryConfig) -> Self {
+        Self {
+            backoff,
+            retryable_status_codes: default_retryable_status_codes(),
+            retryable_error_codes: Vec::new(),
+            respect_retry_after: default_respect_retry_after(),
+            max_retry_after: default_max_retry_after(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Deserialize, Eq, PartialEq, Hash, Serialize)]
 #[serde(rename_all = "kebab-case", tag = "strategy")]
 pub enum RetryConfig {
@@ -85,6 +158,18 @@ fn default_max_delay() -> Duration {
     Duration::from_secs(30)
 }
 
+fn default_retryable_status_codes() -> Vec<u16> {
+    vec![429, 500, 502, 503, 504]
+}
+
+fn default_respect_retry_after() -> bool {
+    true
+}
+
+fn default_max_retry_after() -> Duration {
+    Duration::from_secs(30)
+}
+
 #[cfg(feature = "testing")]
 impl crate::tests::TestDefault for RetryConfig {
     fn test_default() -> Self {
@@ -94,3 +179,79 @@ impl crate::tests::TestDefault for RetryConfig {
         }
     }
 }
+
+#[cfg(feature = "testing")]
+impl crate::tests::TestDefault for RetryPolicy {
+    fn test_default() -> Self {
+        Self::from(RetryConfig::test_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn policy_defaults() {
+        let yaml = r"
+strategy: constant
+delay: 10ms
+max-retries: 3
+";
+        let policy = serde_yml::from_str::<RetryPolicy>(yaml).unwrap();
+        assert_eq!(
+            policy.backoff,
+            RetryConfig::Constant {
+                delay: Duration::from_millis(10),
+                max_retries: 3,
+            }
+        );
+        assert!(policy.is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(policy.is_retryable_status(StatusCode::BAD_GATEWAY));
+        assert!(!policy.is_retryable_status(StatusCode::BAD_REQUEST));
+        assert!(!policy.is_retryable_error_code("overloaded_error"));
+        assert!(policy.respect_retry_after);
+    }
+
+    #[test]
+    fn policy_error_classes() {
+        let yaml = r"
+strategy: exponential
+retryable-status-codes: [503]
+retryable-error-codes: [overloaded_error]
+respect-retry-after: false
+";
+        let policy = serde_yml::from_str::<RetryPolicy>(yaml).unwrap();
+        assert!(policy.is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(!policy.is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(policy.is_retryable_error_code("overloaded_error"));
+        assert_eq!(
+            policy.next_delay(
+                Some(Duration::from_secs(1)),
+                Some(Duration::from_secs(5))
+            ),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn retry_after_is_honored_up_to_max() {
+        let policy = RetryPolicy::from(RetryConfig::Constant {
+            delay: Duration::from_secs(1),
+            max_retries: 2,
+        });
+        let backoff = Some(Duration::from_secs(1));
+        assert_eq!(
+            policy.next_delay(backoff, Some(Duration::from_secs(3))),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(policy.next_delay(backoff, None), backoff);
+        // too long to wait, leave it to failover instead
+        assert_eq!(
+            policy.next_delay(backoff, Some(Duration::from_secs(60))),
+            None
+        );
+        // out of attempts
+        assert_eq!(policy.next_delay(None, None), None);
+    }
+}
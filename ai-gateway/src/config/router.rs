@@ -1,6 +1,7 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, num::NonZeroUsize};
 
 use derive_more::{AsMut, AsRef};
+use nonempty_collections::NEVec;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use url::Url;
@@ -8,12 +9,15 @@ use url::Url;
 use super::{
     balance::{BalanceConfig, BalanceConfigInner},
     model_mapping::ModelMappingConfig,
-    retry::RetryConfig,
+    retry::RetryPolicy,
 };
 use crate::{
-    config::{cache::CacheConfig, rate_limit::RateLimitConfig},
+    config::{
+        cache::CacheConfig, guardrails::GuardrailsConfig, queue::QueueConfig,
+        rate_limit::RateLimitConfig,
+    },
     error::init::InitError,
-    types::{provider::InferenceProvider, router::RouterId},
+    types::{model_id::ModelId, provider::InferenceProvider, router::RouterId},
 };
 
 #[derive(
@@ -44,10 +48,19 @@ pub struct RouterConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub cache: Option<CacheConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub retries: Option<RetryConfig>,
+    pub retries: Option<RetryPolicy>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub rate_limit: Option<RateLimitConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue: Option<QueueConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub guardrails: Option<GuardrailsConfig>,
+    /// Virtual models, e.g. `smart`, and the `{provider}/{model}` targets
+    /// they resolve to. Requests for an alias are sent to its first target
+    /// and fail over to the next ones in order.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_aliases: Option<HashMap<String, NEVec<ModelId>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub providers: Option<HashMap<InferenceProvider, RouterProviderConfig>>,
 }
 
@@ -73,7 +86,44 @@ impl RouterConfig {
                         )));
                     }
                 }
+                BalanceConfigInner::Failover { providers, .. } => {
+                    if providers.len().get() != balance_config.providers().len()
+                    {
+                        return Err(InitError::InvalidBalancer(
+                            "Failover providers must be unique".to_string(),
+                        ));
+                    }
+                }
+                BalanceConfigInner::TrafficSplit {
+                    baseline,
+                    candidate,
+                    candidate_percent,
+                    ..
+                } => {
+                    if !(Decimal::ZERO..=Decimal::ONE_HUNDRED)
+                        .contains(candidate_percent)
+                    {
+                        return Err(InitError::InvalidBalancer(format!(
+                            "Candidate percent must be between 0 and 100: \
+                             {candidate_percent}"
+                        )));
+                    }
+                    if baseline == candidate {
+                        return Err(InitError::InvalidBalancer(
+                            "Baseline and candidate models must differ"
+                                .to_string(),
+                        ));
+                    }
+                    for model in [baseline, candidate] {
+                        if model.inference_provider().is_none() {
+                            return Err(InitError::ModelIdNotRecognized(
+                                model.to_string(),
+                            ));
+                        }
+                    }
+                }
                 BalanceConfigInner::BalancedLatency { .. }
+                | BalanceConfigInner::LeastPending { .. }
                 | BalanceConfigInner::ModelLatency { .. } => {}
             }
         }
@@ -105,6 +155,9 @@ impl crate::tests::TestDefault for RouterConfigs {
                 )])),
                 retries: None,
                 rate_limit: None,
+                queue: None,
+                guardrails: None,
+                model_aliases: None,
                 providers: None,
             },
         )]))
@@ -114,17 +167,38 @@ impl crate::tests::TestDefault for RouterConfigs {
 #[derive(Debug, Clone, Deserialize, Serialize, Eq, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 pub struct RouterProviderConfig {
-    pub base_url: Url,
+    /// Replaces the base url of the provider for this router.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<Url>,
     #[serde(default)]
     pub version: Option<String>,
+    /// Lets requests pin themselves to this provider with the
+    /// `x-gateway-provider` header, and to one of the listed models with the
+    /// `x-gateway-model` header. An empty list only allows pinning the
+    /// provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_overrides: Option<Vec<String>>,
+    /// Replaces the retry policy of the router for requests to this
+    /// provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<RetryPolicy>,
+    /// Replaces the `max-concurrency` of the queue of the router for
+    /// requests to this provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrency: Option<NonZeroUsize>,
 }
 
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
 
+    use indexmap::IndexSet;
+
     use super::*;
-    use crate::config::cache::CacheConfig;
+    use crate::{
+        config::{cache::CacheConfig, retry::RetryConfig},
+        endpoints::EndpointType,
+    };
 
     fn test_router_config() -> RouterConfig {
         let cache = CacheConfig {
@@ -145,8 +219,11 @@ mod tests {
             model_mappings: None,
             cache: Some(cache),
             load_balance: balance,
-            retries: Some(retries),
+            retries: Some(RetryPolicy::from(retries)),
             rate_limit: None,
+            queue: None,
+            guardrails: None,
+            model_aliases: None,
             providers: None,
         }
     }
@@ -168,4 +245,228 @@ mod tests {
             serde_json::from_str::<RouterConfigs>(&serialized).unwrap();
         assert_eq!(config, deserialized);
     }
+
+    #[test]
+    fn least_pending_balance_config() {
+        let yaml = r"
+load-balance:
+  chat:
+    strategy: least-pending
+    providers:
+      - openai
+      - anthropic
+";
+        let config = serde_yml::from_str::<RouterConfig>(yaml).unwrap();
+        let balance_config = &config.load_balance.0[&EndpointType::Chat];
+        assert_eq!(balance_config.as_ref(), "least-pending");
+        assert_eq!(
+            balance_config.providers(),
+            IndexSet::from([
+                InferenceProvider::OpenAI,
+                InferenceProvider::Anthropic
+            ])
+        );
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn failover_providers_must_be_unique() {
+        let yaml = r"
+load-balance:
+  chat:
+    strategy: failover
+    providers:
+      - openai
+      - anthropic
+      - openai
+";
+        let config = serde_yml::from_str::<RouterConfig>(yaml).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn failover_with_stream_failover() {
+        let yaml = r"
+load-balance:
+  chat:
+    strategy: failover
+    providers:
+      - openai
+      - anthropic
+    stream-failover:
+      window: 2s
+";
+        let config = serde_yml::from_str::<RouterConfig>(yaml).unwrap();
+        let balance_config = &config.load_balance.0[&EndpointType::Chat];
+        let BalanceConfigInner::Failover {
+            stream_failover, ..
+        } = balance_config
+        else {
+            panic!("expected failover strategy");
+        };
+        assert_eq!(
+            stream_failover.unwrap().window,
+            std::time::Duration::from_secs(2)
+        );
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn traffic_split_config() {
+        let yaml = r"
+load-balance:
+  chat:
+    strategy: traffic-split
+    baseline: openai/gpt-4o
+    candidate: anthropic/claude-3-7-sonnet
+    candidate-percent: 12.5
+    bucket-header: x-user-id
+";
+        let config = serde_yml::from_str::<RouterConfig>(yaml).unwrap();
+        let balance_config = &config.load_balance.0[&EndpointType::Chat];
+        let BalanceConfigInner::TrafficSplit {
+            candidate_percent,
+            bucket_header,
+            ..
+        } = balance_config
+        else {
+            panic!("expected traffic split strategy");
+        };
+        assert_eq!(*candidate_percent, Decimal::new(125, 1));
+        assert_eq!(bucket_header.as_deref(), Some("x-user-id"));
+        assert_eq!(
+            balance_config.providers(),
+            IndexSet::from([
+                InferenceProvider::OpenAI,
+                InferenceProvider::Anthropic
+            ])
+        );
+        config.validate().unwrap();
+
+        let yaml = r"
+load-balance:
+  chat:
+    strategy: traffic-split
+    baseline: openai/gpt-4o
+    candidate: anthropic/claude-3-7-sonnet
+    candidate-percent: 120
+";
+        let config = serde_yml::from_str::<RouterConfig>(yaml).unwrap();
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn model_aliases_config() {
+        let yaml = r"
+model-aliases:
+  smart:
+    - anthropic/claude-3-5-sonnet
+    - openai/gpt-4o
+";
+        let config = serde_yml::from_str::<RouterConfig>(yaml).unwrap();
+        let targets = &config.model_aliases.as_ref().unwrap()["smart"];
+        assert_eq!(targets.len().get(), 2);
+        assert_eq!(
+            targets.first().inference_provider(),
+            Some(InferenceProvider::Anthropic)
+        );
+        config.validate().unwrap();
+
+        let serialized = serde_json::to_string(&config).unwrap();
+        let deserialized =
+            serde_json::from_str::<RouterConfig>(&serialized).unwrap();
+        assert_eq!(config, deserialized);
+    }
+
+    #[test]
+    fn model_alias_targets_need_a_provider() {
+        let yaml = r"
+model-aliases:
+  fast:
+    - gpt-4o-mini
+";
+        assert!(serde_yml::from_str::<RouterConfig>(yaml).is_err());
+    }
+
+    #[test]
+    fn provider_allow_overrides() {
+        let yaml = r"
+load-balance:
+  chat:
+    strategy: balanced-latency
+    providers:
+      - openai
+providers:
+  anthropic:
+    allow-overrides:
+      - claude-3-5-haiku
+  gemini:
+    base-url: https://gw.internal/gemini/
+";
+        let config = serde_yml::from_str::<RouterConfig>(yaml).unwrap();
+        let providers = config.providers.unwrap();
+        let anthropic = &providers[&InferenceProvider::Anthropic];
+        assert_eq!(anthropic.base_url, None);
+        assert_eq!(
+            anthropic.allow_overrides,
+            Some(vec!["claude-3-5-haiku".to_string()])
+        );
+        assert_eq!(
+            providers[&InferenceProvider::GoogleGemini].allow_overrides,
+            None
+        );
+    }
+
+    #[test]
+    fn provider_retries() {
+        let yaml = r"
+retries:
+  strategy: constant
+  delay: 10ms
+providers:
+  anthropic:
+    retries:
+      strategy: exponential
+      max-retries: 5
+      retryable-error-codes:
+        - overloaded_error
+";
+        let config = serde_yml::from_str::<RouterConfig>(yaml).unwrap();
+        let router_retries = config.retries.unwrap();
+        assert!(router_retries.retryable_error_codes.is_empty());
+        let providers = config.providers.unwrap();
+        let anthropic_retries = providers[&InferenceProvider::Anthropic]
+            .retries
+            .clone()
+            .unwrap();
+        assert!(matches!(
+            anthropic_retries.backoff,
+            RetryConfig::Exponential { max_retries: 5, .. }
+        ));
+        assert!(anthropic_retries.is_retryable_error_code("overloaded_error"));
+    }
+
+    #[test]
+    fn queue_config() {
+        let yaml = r"
+queue:
+  max-concurrency: 8
+  timeout: 10s
+providers:
+  anthropic:
+    max-concurrency: 2
+";
+        let config = serde_yml::from_str::<RouterConfig>(yaml).unwrap();
+        let queue = config.queue.unwrap();
+        assert_eq!(queue.max_concurrency.get(), 8);
+        assert_eq!(queue.timeout, std::time::Duration::from_secs(10));
+        assert_eq!(queue.max_size, QueueConfig::default().max_size);
+        let providers = config.providers.unwrap();
+        assert_eq!(
+            providers[&InferenceProvider::Anthropic]
+                .max_concurrency
+                .map(NonZeroUsize::get),
+            Some(2)
+        );
+    }
 }
@@ -2,7 +2,7 @@ use indexmap::IndexSet;
 use thiserror::Error;
 
 use crate::{
-    config::{Config, router::RouterConfig},
+    config::{Config, providers::GlobalProviderConfig, router::RouterConfig},
     types::{
         model_id::{ModelId, ModelName},
         provider::InferenceProvider,
@@ -61,15 +61,22 @@ impl Config {
                 }
             }
 
-            let all_models_offered_by_configured_providers: IndexSet<
+            // Embedding models are only mapped to embedding models, and chat
+            // models to chat models, so each kind only needs a mapping to
+            // the providers that offer models of that kind.
+            let all_models_offered_by_configured_providers: IndexSet<(
                 ModelName,
-            > = router_providers
+                bool,
+            )> = router_providers
                 .iter()
                 .flat_map(|provider| {
-                    self.providers[provider]
-                        .models
-                        .iter()
-                        .map(|m| m.as_model_name())
+                    let provider_config = &self.providers[provider];
+                    provider_config.models.iter().map(|m| {
+                        (
+                            m.as_model_name(),
+                            is_embedding_model(provider_config, m),
+                        )
+                    })
                 })
                 .collect();
 
@@ -82,9 +89,26 @@ impl Config {
                     .iter()
                     .map(|m| m.clone().with_latest_version())
                     .collect::<IndexSet<ModelId>>();
-
-                for source_model in &all_models_offered_by_configured_providers
+                let target_offers = |embedding: bool| {
+                    target_provider_config.models.iter().any(|m| {
+                        is_embedding_model(target_provider_config, m)
+                            == embedding
+                    })
+                };
+                let (offers_embeddings, offers_chat) =
+                    (target_offers(true), target_offers(false));
+
+                for (source_model, embedding) in
+                    &all_models_offered_by_configured_providers
                 {
+                    let offered = if *embedding {
+                        offers_embeddings
+                    } else {
+                        offers_chat
+                    };
+                    if !offered {
+                        continue;
+                    }
                     self.can_map_model(
                         source_model,
                         target_provider.clone(),
@@ -150,6 +174,16 @@ impl Config {
     }
 }
 
+fn is_embedding_model(
+    provider_config: &GlobalProviderConfig,
+    model: &ModelId,
+) -> bool {
+    provider_config
+        .model_capabilities(model)
+        .embedding_dimensions
+        .is_some()
+}
+
 #[cfg(test)]
 mod tests {
     use compact_str::CompactString;
@@ -0,0 +1,36 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::types::secret::Secret;
+
+const DEFAULT_DATABASE_URL: &str = "sqlite://ai-gateway-keys.db?mode=rwc";
+
+/// API keys issued by the gateway, see [`crate::virtual_keys`].
+///
+/// Keys are minted and revoked with the `/v1/keys` admin endpoint, so the
+/// admin key must be configured as well, see
+/// [`AdminConfig`](super::admin::AdminConfig).
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct VirtualKeysConfig {
+    /// The `sqlite://` or `postgres://` database the keys are stored in.
+    /// set via env vars: `AI_GATEWAY__VIRTUAL_KEYS__DATABASE_URL`
+    pub database_url: Secret<String>,
+    /// Maximum number of connections in the pool.
+    pub max_connections: u32,
+    /// How often the keys are reloaded from the database, to pick up the
+    /// keys revoked and the spend recorded by other replicas.
+    #[serde(with = "humantime_serde")]
+    pub refresh_interval: Duration,
+}
+
+impl Default for VirtualKeysConfig {
+    fn default() -> Self {
+        Self {
+            database_url: Secret::from(DEFAULT_DATABASE_URL.to_string()),
+            max_connections: 5,
+            refresh_interval: Duration::from_secs(30),
+        }
+    }
+}
@@ -2,6 +2,8 @@ pub mod dispatcher;
 pub mod model;
 pub mod monitor;
 pub mod provider;
+pub mod provider_models;
+pub mod reload;
 pub mod router;
 
 use std::{
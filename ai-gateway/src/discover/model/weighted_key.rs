@@ -73,7 +73,22 @@ impl DispatcherDiscovery<WeightedKey> {
                             .to_string(),
                     ));
                 }
-                BalanceConfigInner::BalancedLatency { .. } => {
+                BalanceConfigInner::Failover { .. } => {
+                    return Err(InitError::InvalidBalancer(
+                        "Failover balancer not supported for model weighted \
+                         discovery"
+                            .to_string(),
+                    ));
+                }
+                BalanceConfigInner::TrafficSplit { .. } => {
+                    return Err(InitError::InvalidBalancer(
+                        "Traffic split balancer not supported for model \
+                         weighted discovery"
+                            .to_string(),
+                    ));
+                }
+                BalanceConfigInner::BalancedLatency { .. }
+                | BalanceConfigInner::LeastPending { .. } => {
                     return Err(InitError::InvalidBalancer(
                         "P2C balancer not supported for weighted discovery"
                             .to_string(),
@@ -6,6 +6,7 @@ use meltdown::Token;
 use opentelemetry::KeyValue;
 use rust_decimal::prelude::ToPrimitive;
 use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use serde::Serialize;
 use tokio::{
     sync::{RwLock, mpsc::Sender},
     task::JoinSet,
@@ -175,8 +176,10 @@ async fn check_provider_weighted_monitor(
                         }
                     }
 
-                    let metric_attributes =
-                        [KeyValue::new("provider", provider.to_string())];
+                    let metric_attributes = [
+                        KeyValue::new("router_id", inner.router_id.to_string()),
+                        KeyValue::new("provider", provider.to_string()),
+                    ];
                     if is_healthy {
                         inner
                             .app_state
@@ -200,7 +203,10 @@ async fn check_provider_weighted_monitor(
                 );
                 return Err(InternalError::Internal.into());
             }
-            BalanceConfigInner::BalancedLatency { .. } => {
+            BalanceConfigInner::BalancedLatency { .. }
+            | BalanceConfigInner::LeastPending { .. }
+            | BalanceConfigInner::Failover { .. }
+            | BalanceConfigInner::TrafficSplit { .. } => {
                 tracing::error!("P2C entries in a weighted monitor");
                 return Err(InternalError::Internal.into());
             }
@@ -340,8 +346,10 @@ async fn check_model_weighted_monitor(
                         }
                     }
 
-                    let metric_attributes =
-                        [KeyValue::new("provider", provider.to_string())];
+                    let metric_attributes = [
+                        KeyValue::new("router_id", inner.router_id.to_string()),
+                        KeyValue::new("provider", provider.to_string()),
+                    ];
                     if is_healthy {
                         inner
                             .app_state
@@ -365,7 +373,10 @@ async fn check_model_weighted_monitor(
                 );
                 return Err(InternalError::Internal.into());
             }
-            BalanceConfigInner::BalancedLatency { .. } => {
+            BalanceConfigInner::BalancedLatency { .. }
+            | BalanceConfigInner::LeastPending { .. }
+            | BalanceConfigInner::Failover { .. }
+            | BalanceConfigInner::TrafficSplit { .. } => {
                 tracing::error!("P2C entries in a weighted monitor");
                 return Err(InternalError::Internal.into());
             }
@@ -388,8 +399,11 @@ async fn check_provider_latency_monitor(
         inner.router_config.load_balance.as_ref()
     {
         match balance_config {
-            BalanceConfigInner::BalancedLatency { providers } => {
-                for provider in providers {
+            BalanceConfigInner::BalancedLatency { .. }
+            | BalanceConfigInner::LeastPending { .. }
+            | BalanceConfigInner::Failover { .. }
+            | BalanceConfigInner::TrafficSplit { .. } => {
+                for provider in &balance_config.providers() {
                     let key =
                         ProviderKey::new(provider.clone(), *endpoint_type);
                     let is_healthy = inner.check_health(provider)?;
@@ -422,8 +436,10 @@ async fn check_provider_latency_monitor(
                         }
                     }
 
-                    let metric_attributes =
-                        [KeyValue::new("provider", provider.to_string())];
+                    let metric_attributes = [
+                        KeyValue::new("router_id", inner.router_id.to_string()),
+                        KeyValue::new("provider", provider.to_string()),
+                    ];
                     if is_healthy {
                         inner
                             .app_state
@@ -550,8 +566,10 @@ async fn check_model_latency_monitor(
                         }
                     }
 
-                    let metric_attributes =
-                        [KeyValue::new("provider", provider.to_string())];
+                    let metric_attributes = [
+                        KeyValue::new("router_id", inner.router_id.to_string()),
+                        KeyValue::new("provider", provider.to_string()),
+                    ];
                     if is_healthy {
                         inner
                             .app_state
@@ -577,10 +595,11 @@ async fn check_model_latency_monitor(
                 tracing::error!("Weighted entries in a P2C monitor");
                 return Err(InternalError::Internal.into());
             }
-            BalanceConfigInner::BalancedLatency { .. } => {
-                tracing::error!(
-                    "provider latency entries in a model latency monitor"
-                );
+            BalanceConfigInner::BalancedLatency { .. }
+            | BalanceConfigInner::LeastPending { .. }
+            | BalanceConfigInner::Failover { .. }
+            | BalanceConfigInner::TrafficSplit { .. } => {
+                tracing::error!("provider entries in a model latency monitor");
                 return Err(InternalError::Internal.into());
             }
         }
@@ -620,34 +639,28 @@ impl<K> ProviderMonitorInner<K> {
         &self,
         provider: &InferenceProvider,
     ) -> Result<bool, InternalError> {
-        let provider_endpoints = provider.endpoints();
-        let config = self.app_state.config();
-        let grace_period = config.discover.monitor.grace_period();
-        let mut all_healthy = true;
-        for endpoint in provider_endpoints {
-            let endpoint_metrics =
-                self.app_state.0.endpoint_metrics.health_metrics(endpoint)?;
-            let requests = endpoint_metrics.request_count.total();
-            match grace_period {
-                GracePeriod::Requests { min_requests } => {
-                    if requests < *min_requests {
-                        continue;
-                    }
-                }
-            }
-
-            let errors = endpoint_metrics.remote_internal_error_count.total();
-            let error_ratio = f64::from(errors) / f64::from(requests);
-
-            if error_ratio > config.discover.monitor.error_threshold() {
-                all_healthy = false;
-            }
-        }
-
-        Ok(all_healthy)
+        let health = self.app_state.provider_health(provider)?;
+        Ok(health.enabled && health.healthy)
     }
 }
 
+/// The health of a provider over the window of the endpoint metrics, as
+/// seen by the health monitors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ProviderHealth {
+    /// Disabled providers are removed from load balanced routers, see
+    /// [`GlobalProviderConfig::enabled`].
+    ///
+    /// [`GlobalProviderConfig::enabled`]: crate::config::providers::GlobalProviderConfig::enabled
+    pub enabled: bool,
+    pub requests: u64,
+    /// Requests that failed with a server error or never got a response.
+    pub errors: u64,
+    /// Whether the error ratio of each endpoint of the provider is below the
+    /// error threshold, endpoints still in their grace period are healthy.
+    pub healthy: bool,
+}
+
 #[derive(Debug, Clone)]
 pub struct HealthMonitor {
     app_state: AppState,
@@ -715,6 +728,47 @@ impl meltdown::Service for HealthMonitor {
 }
 
 impl AppState {
+    pub fn provider_health(
+        &self,
+        provider: &InferenceProvider,
+    ) -> Result<ProviderHealth, InternalError> {
+        let enabled = self
+            .providers()
+            .get(provider)
+            .is_none_or(|config| config.enabled);
+        let config = self.config();
+        let grace_period = config.discover.monitor.grace_period();
+        let mut health = ProviderHealth {
+            enabled,
+            requests: 0,
+            errors: 0,
+            healthy: true,
+        };
+        for endpoint in provider.endpoints() {
+            let endpoint_metrics =
+                self.0.endpoint_metrics.health_metrics(endpoint)?;
+            let requests = endpoint_metrics.request_count.total();
+            let errors = endpoint_metrics.remote_internal_error_count.total();
+            health.requests += u64::from(requests);
+            health.errors += u64::from(errors);
+            match grace_period {
+                GracePeriod::Requests { min_requests } => {
+                    if requests < *min_requests {
+                        continue;
+                    }
+                }
+            }
+
+            let error_ratio = f64::from(errors) / f64::from(requests);
+
+            if error_ratio > config.discover.monitor.error_threshold() {
+                health.healthy = false;
+            }
+        }
+
+        Ok(health)
+    }
+
     pub async fn add_provider_weighted_router_health_monitor(
         &self,
         router_id: RouterId,
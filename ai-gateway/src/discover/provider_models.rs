@@ -0,0 +1,312 @@
+//! Load the models of providers from their APIs, so that models which are
+//! not listed in the providers config can be routed to.
+//!
+//! Models are loaded once on startup and then periodically refreshed into
+//! the providers of the [`AppState`], see [`AppState::providers`]. Each
+//! refresh starts from the configured providers, so configured models keep
+//! their metadata and models a provider stops listing are dropped again.
+//! Providers changed at runtime, e.g. disabled with the admin API, keep their
+//! changes, see [`ProvidersConfigStore::refresh`].
+//!
+//! [`ProvidersConfigStore::refresh`]: crate::config::providers_store::ProvidersConfigStore::refresh
+use std::sync::{Arc, PoisonError, RwLock};
+
+use futures::future::{self, BoxFuture};
+use http::{HeaderName, HeaderValue};
+use meltdown::Token;
+use serde::Deserialize;
+use tokio::time;
+use tracing::{debug, info, warn};
+
+use crate::{
+    app_state::AppState,
+    config::{
+        model_discovery::ModelDiscoveryConfig,
+        providers::{
+            DEFAULT_ANTHROPIC_VERSION, GlobalProviderConfig, ProvidersConfig,
+            ProvidersConfigError,
+        },
+    },
+    error::{init::InitError, runtime::RuntimeError},
+    types::provider::{InferenceProvider, ProviderKey},
+};
+
+/// Why the models of a provider could not be loaded.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum ModelDiscoveryError {
+    /// Provider {0} does not have a models endpoint
+    Unsupported(InferenceProvider),
+    /// Invalid header: {0}
+    InvalidHeader(#[from] http::Error),
+    /// Request to models endpoint failed: {0}
+    Request(#[from] reqwest::Error),
+}
+
+/// The response of the OpenAI-style models endpoints. Anthropic's has the
+/// same shape.
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<ListedModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListedModel {
+    id: String,
+}
+
+/// The path of the models endpoint of `provider`, relative to its base url.
+///
+/// Bedrock is not supported since listing its models requires a different
+/// API (and signing) than invoking them.
+#[must_use]
+pub fn models_path(provider: &InferenceProvider) -> Option<&'static str> {
+    match provider {
+        InferenceProvider::OpenAI
+        | InferenceProvider::Anthropic
+        | InferenceProvider::Ollama
+        | InferenceProvider::Named(_) => Some("v1/models"),
+        InferenceProvider::GoogleGemini => Some("v1beta/openai/models"),
+        InferenceProvider::Bedrock => None,
+    }
+}
+
+/// Fetch the names of the models `provider` serves.
+pub async fn fetch_models(
+    client: &reqwest::Client,
+    provider: &InferenceProvider,
+    config: &GlobalProviderConfig,
+    provider_key: Option<&ProviderKey>,
+) -> Result<Vec<String>, ModelDiscoveryError> {
+    let path = models_path(provider)
+        .ok_or_else(|| ModelDiscoveryError::Unsupported(provider.clone()))?;
+    let mut headers = config.request_headers()?;
+    if *provider == InferenceProvider::Anthropic {
+        let version = config
+            .preferred_version()
+            .unwrap_or(DEFAULT_ANTHROPIC_VERSION);
+        headers.insert(
+            HeaderName::from_static("anthropic-version"),
+            HeaderValue::from_str(version).map_err(http::Error::from)?,
+        );
+    }
+    if let Some(key) = provider_key.and_then(ProviderKey::as_secret) {
+        let (name, value) = if *provider == InferenceProvider::Anthropic {
+            (
+                HeaderName::from_static("x-api-key"),
+                key.expose().to_string(),
+            )
+        } else {
+            (
+                http::header::AUTHORIZATION,
+                format!("Bearer {}", key.expose()),
+            )
+        };
+        headers.insert(
+            name,
+            HeaderValue::from_str(&value).map_err(http::Error::from)?,
+        );
+    }
+
+    let models = client
+        .get(config.join_path(path))
+        .headers(headers)
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<ModelList>()
+        .await?;
+    Ok(models
+        .data
+        .into_iter()
+        // gemini lists its models as `models/{name}`
+        .map(|model| match model.id.strip_prefix("models/") {
+            Some(name) => name.to_string(),
+            None => model.id,
+        })
+        .collect())
+}
+
+/// Periodically reloads the models of the providers.
+#[derive(Debug, Clone)]
+pub struct ModelDiscovery {
+    app_state: AppState,
+    client: reqwest::Client,
+    /// The providers as configured, before any discovered models are added.
+    /// Shared between clones so that a reloaded config is picked up by the
+    /// periodic refresh, see [`ModelDiscovery::load`].
+    configured: Arc<RwLock<Arc<ProvidersConfig>>>,
+}
+
+impl ModelDiscovery {
+    pub fn new(app_state: AppState) -> Result<Self, InitError> {
+        let client = reqwest::Client::builder()
+            .timeout(app_state.config().model_discovery.timeout)
+            .build()
+            .map_err(InitError::CreateReqwestClient)?;
+        let configured = Arc::new(RwLock::new(Arc::new(
+            app_state.config().providers.clone(),
+        )));
+        Ok(Self {
+            app_state,
+            client,
+            configured,
+        })
+    }
+
+    fn config(&self) -> &ModelDiscoveryConfig {
+        &self.app_state.config().model_discovery
+    }
+
+    /// Load the models of every discovered provider and swap the result into
+    /// the providers store.
+    ///
+    /// A provider whose models can not be loaded keeps the models it had
+    /// before, so a flaky models endpoint does not make models unroutable.
+    pub async fn refresh(&self) -> Result<(), ProvidersConfigError> {
+        let current = self.app_state.providers();
+        let configured = self.configured();
+        let discovered = self.discover(&configured).await;
+        self.app_state
+            .0
+            .providers
+            .refresh(ProvidersConfig::clone(&configured), |providers| {
+                merge_discovered(providers, discovered, &current)
+            })
+    }
+
+    /// Request the models of every discovered provider in `configured`.
+    async fn discover<'a>(
+        &self,
+        configured: &'a ProvidersConfig,
+    ) -> Vec<(
+        &'a InferenceProvider,
+        Result<Vec<String>, ModelDiscoveryError>,
+    )> {
+        let requests = configured
+            .enabled()
+            .filter(|(provider, _)| {
+                self.config().discovers(provider)
+                    && models_path(provider).is_some()
+            })
+            .map(|(provider, config)| async move {
+                let provider_key = self
+                    .app_state
+                    .0
+                    .provider_keys
+                    .get_provider_key(provider, None)
+                    .await;
+                let result = fetch_models(
+                    &self.client,
+                    provider,
+                    config,
+                    provider_key.as_ref(),
+                )
+                .await;
+                (provider, result)
+            });
+        future::join_all(requests).await
+    }
+
+    /// The providers as configured, before any discovered models are added.
+    #[must_use]
+    pub fn configured(&self) -> Arc<ProvidersConfig> {
+        self.configured
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// `providers` with their discovered models, e.g. after the config file
+    /// was reloaded, without loading them, see [`ModelDiscovery::load`].
+    pub async fn discover_models(
+        &self,
+        providers: &ProvidersConfig,
+    ) -> ProvidersConfig {
+        let current = self.app_state.providers();
+        let discovered = self.discover(providers).await;
+        let mut with_models = providers.clone();
+        merge_discovered(&mut with_models, discovered, &current);
+        with_models
+    }
+
+    /// Replace the configured providers with `configured`, and load
+    /// `providers`, their providers with discovered models.
+    ///
+    /// Unlike [`ModelDiscovery::refresh`], drops the changes made to the
+    /// providers at runtime. An invalid `providers` config leaves the
+    /// configured providers as they were.
+    pub fn load(
+        &self,
+        configured: ProvidersConfig,
+        providers: ProvidersConfig,
+    ) -> Result<(), ProvidersConfigError> {
+        configured.validate()?;
+        self.app_state.0.providers.load(providers)?;
+        *self
+            .configured
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Arc::new(configured);
+        Ok(())
+    }
+
+    async fn run_forever(self) {
+        info!("starting model discovery");
+        let mut interval = time::interval(self.config().refresh_interval);
+        // the models were already loaded on startup
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if let Err(error) = self.refresh().await {
+                warn!(%error, "discovered models are invalid, keeping old ones");
+            }
+        }
+    }
+}
+
+/// Add the `discovered` models to `providers`. Providers whose models could
+/// not be loaded keep their `current` config.
+fn merge_discovered(
+    providers: &mut ProvidersConfig,
+    discovered: Vec<(
+        &InferenceProvider,
+        Result<Vec<String>, ModelDiscoveryError>,
+    )>,
+    current: &ProvidersConfig,
+) {
+    for (provider, result) in discovered {
+        match result {
+            Ok(models) => {
+                let added =
+                    providers.merge_discovered_models(provider, &models);
+                debug!(
+                    %provider,
+                    listed = models.len(),
+                    added,
+                    "discovered models"
+                );
+            }
+            Err(error) => {
+                warn!(%provider, %error, "failed to discover models");
+                if let Some(previous) = current.get(provider) {
+                    providers.insert(provider.clone(), previous.clone());
+                }
+            }
+        }
+    }
+}
+
+impl meltdown::Service for ModelDiscovery {
+    type Future = BoxFuture<'static, Result<(), RuntimeError>>;
+
+    fn run(self, mut token: Token) -> Self::Future {
+        Box::pin(async move {
+            tokio::select! {
+                () = self.run_forever() => {}
+                () = &mut token => {
+                    debug!(name = "model-discovery-task", "task shut down successfully");
+                }
+            }
+            Ok(())
+        })
+    }
+}
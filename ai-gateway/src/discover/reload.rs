@@ -0,0 +1,302 @@
+//! Reload the providers and routers from the config file without a restart.
+//!
+//! A reload is triggered by sending `SIGHUP` to the gateway. The new config
+//! is validated and its routers are built before anything is swapped in, so
+//! an invalid config is rejected and the running config is kept. The
+//! providers, routers and the channels of the routers' monitors are then
+//! swapped together.
+//!
+//! Only the providers and, for sidecar deployments, the routers are
+//! reloaded. Every other section of the config still requires a restart.
+use std::{path::PathBuf, sync::Arc};
+
+use futures::future::BoxFuture;
+use meltdown::Token;
+use rustc_hash::FxHashMap as HashMap;
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    sync::mpsc::Sender,
+};
+use tower::discover::Change;
+use tracing::{debug, error, info};
+
+use crate::{
+    app_state::AppState,
+    config::{
+        Config,
+        providers::{ProvidersConfig, ProvidersConfigError},
+        providers_store::ProvidersConfigStore,
+        router::RouterConfigs,
+        validation::ModelMappingValidationError,
+    },
+    discover::{
+        monitor::health::provider::ProviderHealthMonitor,
+        provider_models::ModelDiscovery,
+    },
+    error::{init::InitError, runtime::RuntimeError},
+    router::service::Router,
+    types::{rate_limit::RateLimitEvent, router::RouterId},
+};
+
+/// Why a reload was rejected.
+#[derive(Debug, thiserror::Error, displaydoc::Display)]
+pub enum ReloadError {
+    /// Failed to read config: {0}
+    Read(#[from] Box<crate::config::Error>),
+    /// Invalid config: {0}
+    Invalid(#[from] InitError),
+    /// Invalid providers config: {0}
+    Providers(#[from] ProvidersConfigError),
+    /// Invalid router config: {0}
+    Router(#[from] ModelMappingValidationError),
+    /// Failed to build router {0}: {1}
+    BuildRouter(RouterId, InitError),
+    /// Router discovery is not running
+    RouterDiscoveryNotRunning,
+    /// Too many router changes to apply at once: {0}
+    TooManyRouters(usize),
+}
+
+/// Reloads the config file whenever the gateway receives `SIGHUP`.
+#[derive(Debug)]
+pub struct ConfigReloader {
+    app_state: AppState,
+    config_path: Option<PathBuf>,
+    /// Set if models are discovered, so that the discovered models of the
+    /// reloaded providers are loaded too.
+    model_discovery: Option<ModelDiscovery>,
+    /// The routers that are currently running.
+    routers: RouterConfigs,
+}
+
+impl ConfigReloader {
+    #[must_use]
+    pub fn new(
+        app_state: AppState,
+        config_path: Option<PathBuf>,
+        model_discovery: Option<ModelDiscovery>,
+    ) -> Self {
+        let routers = app_state.config().routers.clone();
+        Self {
+            app_state,
+            config_path,
+            model_discovery,
+            routers,
+        }
+    }
+
+    /// Read and validate the config file and build its routers, then swap
+    /// its providers and routers in.
+    ///
+    /// If the routers can not be built nothing is swapped in, so the running
+    /// routers keep the providers and monitors they were built with.
+    pub async fn reload(&mut self) -> Result<(), ReloadError> {
+        let config = Config::try_read(self.config_path.clone())?;
+        config.validate()?;
+        config.providers.validate()?;
+        let reload_routers =
+            self.app_state.config().deployment_target.is_sidecar();
+        let router_tx = if reload_routers {
+            validate_router_providers(&config)?;
+            let tx = self
+                .app_state
+                .get_router_tx()
+                .await
+                .ok_or(ReloadError::RouterDiscoveryNotRunning)?;
+            let removed = self
+                .routers
+                .keys()
+                .filter(|router_id| !config.routers.contains_key(*router_id))
+                .count();
+            let changes = config.routers.len() + removed;
+            if changes > tx.max_capacity() {
+                return Err(ReloadError::TooManyRouters(changes));
+            }
+            Some(tx)
+        } else {
+            None
+        };
+
+        let providers = match &self.model_discovery {
+            Some(model_discovery) => {
+                model_discovery.discover_models(&config.providers).await
+            }
+            None => config.providers.clone(),
+        };
+        providers.validate()?;
+        let routers = match router_tx {
+            Some(tx) => {
+                let built = self
+                    .build_routers(&config.routers, providers.clone())
+                    .await?;
+                let changes = self.router_changes(&config.routers, built);
+                // reserved up front, so the changes are sent without
+                // yielding in between and are applied together
+                let permits = tx
+                    .reserve_many(changes.len())
+                    .await
+                    .map_err(|_| ReloadError::RouterDiscoveryNotRunning)?;
+                Some((permits, changes))
+            }
+            None => None,
+        };
+
+        match &self.model_discovery {
+            Some(model_discovery) => {
+                model_discovery.load(config.providers, providers)?;
+            }
+            None => self.app_state.0.providers.load(providers)?,
+        }
+        if let Some((permits, changes)) = routers {
+            // discovery increments the metrics of the inserted routers
+            for (router_id, router_config) in self.routers.iter() {
+                self.app_state.decrement_router_metrics(
+                    router_id,
+                    router_config,
+                    None,
+                );
+            }
+            for (permit, change) in permits.zip(changes) {
+                permit.send(change);
+            }
+            self.routers = config.routers;
+        }
+        Ok(())
+    }
+
+    /// Build every router against `providers`, since the providers they
+    /// dispatch to may have changed.
+    ///
+    /// Building a router replaces its monitors and rate limit channels, so
+    /// those of the running routers are restored if any router can not be
+    /// built.
+    async fn build_routers(
+        &self,
+        routers: &RouterConfigs,
+        providers: ProvidersConfig,
+    ) -> Result<Vec<(RouterId, Router)>, ReloadError> {
+        let registrations = Registrations::save(&self.app_state).await;
+        let built = ProvidersConfigStore::staged(Arc::new(providers), async {
+            let mut built = Vec::with_capacity(routers.len());
+            for (router_id, router_config) in routers.iter() {
+                let router = Router::new(
+                    router_id.clone(),
+                    Arc::new(router_config.clone()),
+                    self.app_state.clone(),
+                )
+                .await
+                .map_err(|e| ReloadError::BuildRouter(router_id.clone(), e))?;
+                built.push((router_id.clone(), router));
+            }
+            Ok::<_, ReloadError>(built)
+        })
+        .await;
+        if built.is_err() {
+            registrations.restore(&self.app_state, routers).await;
+        }
+        built
+    }
+
+    /// The changes inserting the `built` routers and removing the routers
+    /// that are no longer configured.
+    fn router_changes(
+        &self,
+        routers: &RouterConfigs,
+        built: Vec<(RouterId, Router)>,
+    ) -> Vec<Change<RouterId, Router>> {
+        let mut changes = built
+            .into_iter()
+            .map(|(router_id, router)| Change::Insert(router_id, router))
+            .collect::<Vec<_>>();
+        for router_id in self.routers.keys() {
+            if !routers.contains_key(router_id) {
+                changes.push(Change::Remove(router_id.clone()));
+            }
+        }
+        changes
+    }
+
+    async fn run_forever(mut self) {
+        let mut hangup = signal(SignalKind::hangup())
+            .expect("failed to register SIGHUP signal");
+        while hangup.recv().await.is_some() {
+            info!("SIGHUP received, reloading config");
+            match self.reload().await {
+                Ok(()) => info!("config reloaded"),
+                Err(error) => error!(
+                    %error,
+                    "config reload rejected, keeping running config"
+                ),
+            }
+        }
+    }
+}
+
+/// The health monitors and rate limit senders of the running routers.
+struct Registrations {
+    health_monitors: HashMap<RouterId, ProviderHealthMonitor>,
+    rate_limit_senders: HashMap<RouterId, Sender<RateLimitEvent>>,
+}
+
+impl Registrations {
+    async fn save(app_state: &AppState) -> Self {
+        Self {
+            health_monitors: app_state.0.health_monitors.read().await.clone(),
+            rate_limit_senders: app_state
+                .0
+                .rate_limit_senders
+                .read()
+                .await
+                .clone(),
+        }
+    }
+
+    /// Restore the saved registrations, and drop the rate limit monitors and
+    /// receivers registered for `routers` that have not been started yet.
+    async fn restore(self, app_state: &AppState, routers: &RouterConfigs) {
+        *app_state.0.health_monitors.write().await = self.health_monitors;
+        *app_state.0.rate_limit_senders.write().await = self.rate_limit_senders;
+        let mut monitors = app_state.0.rate_limit_monitors.write().await;
+        let mut receivers = app_state.0.rate_limit_receivers.write().await;
+        for router_id in routers.keys() {
+            monitors.remove(router_id);
+            receivers.remove(router_id);
+        }
+    }
+}
+
+/// Every provider a router balances between must be configured, otherwise
+/// the router can not be built.
+fn validate_router_providers(
+    config: &Config,
+) -> Result<(), ModelMappingValidationError> {
+    for (router_id, router_config) in config.routers.iter() {
+        for provider in router_config.load_balance.providers() {
+            if config.providers.get(&provider).is_none() {
+                return Err(
+                    ModelMappingValidationError::ProviderNotConfigured {
+                        router: router_id.clone(),
+                        provider,
+                    },
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+impl meltdown::Service for ConfigReloader {
+    type Future = BoxFuture<'static, Result<(), RuntimeError>>;
+
+    fn run(self, mut token: Token) -> Self::Future {
+        Box::pin(async move {
+            tokio::select! {
+                () = self.run_forever() => {}
+                () = &mut token => {
+                    debug!(name = "config-reloader-task", "task shut down successfully");
+                }
+            }
+            Ok(())
+        })
+    }
+}
@@ -5,8 +5,10 @@ use std::{
     task::{Context, Poll},
 };
 
-use futures::Stream;
+use futures::{Stream, StreamExt};
 use pin_project_lite::pin_project;
+use tokio::sync::mpsc::Receiver;
+use tokio_stream::wrappers::ReceiverStream;
 use tower::discover::Change;
 
 use crate::{
@@ -15,17 +17,22 @@ use crate::{
 };
 
 pin_project! {
-    /// Reads available routers from the config file
+    /// Reads available routers from the config file, and then any routers
+    /// changed by reloading it.
     #[derive(Debug)]
     pub struct ConfigDiscovery {
         #[pin]
         initial: ServiceMap<RouterId, Router>,
+        events: Option<ReceiverStream<Change<RouterId, Router>>>,
         app_state: AppState,
     }
 }
 
 impl ConfigDiscovery {
-    pub async fn new(app_state: &AppState) -> Result<Self, InitError> {
+    pub async fn new(
+        app_state: &AppState,
+        rx: Option<Receiver<Change<RouterId, Router>>>,
+    ) -> Result<Self, InitError> {
         let mut service_map: HashMap<RouterId, Router> = HashMap::new();
         for (router_id, router_config) in app_state.0.config.routers.as_ref() {
             let key = router_id.clone();
@@ -40,6 +47,7 @@ impl ConfigDiscovery {
 
         Ok(Self {
             initial: ServiceMap::new(service_map),
+            events: rx.map(ReceiverStream::new),
             app_state: app_state.clone(),
         })
     }
@@ -57,8 +65,14 @@ impl Stream for ConfigDiscovery {
         {
             return handle_change(this.app_state, change);
         }
-
-        Poll::Ready(None)
+        let Some(events) = this.events.as_mut() else {
+            return Poll::Ready(None);
+        };
+        match events.poll_next_unpin(ctx) {
+            Poll::Ready(Some(change)) => handle_change(this.app_state, change),
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(None) => Poll::Ready(None),
+        }
     }
 }
 
@@ -78,8 +92,8 @@ fn handle_change(
         }
         Change::Remove(key) => {
             tracing::debug!(key = ?key, "Removed router");
-            // in practice, with config discovery, routers are never removed,
-            // so we don't need to decrement metrics here
+            // the metrics of removed and replaced routers are decremented by
+            // the `ConfigReloader`, which still has their config
             Poll::Ready(Some(Change::Remove(key)))
         }
     }
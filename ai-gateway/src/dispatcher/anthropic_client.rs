@@ -1,4 +1,4 @@
-use http::{HeaderMap, HeaderName, HeaderValue};
+use http::{HeaderName, HeaderValue};
 use reqwest::ClientBuilder;
 
 use crate::{
@@ -32,11 +32,12 @@ impl Client {
 
         let base_url = provider_config.base_url.clone();
         let version = provider_config
-            .version
-            .as_deref()
+            .preferred_version()
             .unwrap_or(DEFAULT_ANTHROPIC_VERSION);
 
-        let mut default_headers = HeaderMap::new();
+        let mut default_headers = provider_config
+            .custom_headers()
+            .map_err(InitError::InvalidProviderHeader)?;
         if let Some(ProviderKey::Secret(key)) = provider_key {
             default_headers.insert(
                 HeaderName::from_static("x-api-key"),
@@ -1,32 +1,51 @@
-use std::time::SystemTime;
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 use aws_credential_types::Credentials;
 use aws_sigv4::{
     http_request::{SignableBody, SignableRequest, SigningSettings},
     sign::v4,
 };
-use http::{HeaderMap, HeaderValue};
+use http::HeaderValue;
 use reqwest::ClientBuilder;
+use serde::Deserialize;
+use tokio::sync::RwLock;
+use url::Url;
 
 use crate::{
     app_state::AppState,
+    config::providers::AssumeRoleConfig,
     error::{
         api::ApiError, auth::AuthError, init::InitError,
         internal::InternalError, invalid_req::InvalidRequestError,
         provider::ProviderError,
     },
-    types::{
-        provider::{InferenceProvider, ProviderKey},
-        secret::Secret,
-    },
+    types::provider::{InferenceProvider, ProviderKey},
     utils::host_header,
 };
 
+/// Assumed role credentials are refreshed this long before they expire, so
+/// that they don't expire while a request is in flight.
+const ASSUMED_ROLE_REFRESH_MARGIN: Duration = Duration::from_secs(5 * 60);
+
 #[derive(Debug, Clone)]
 pub struct Client {
     pub(super) inner: reqwest::Client,
-    pub(super) access_key: Option<Secret<String>>,
-    pub(super) secret_key: Option<Secret<String>>,
+    pub(super) credentials: Option<Credentials>,
+    /// Overrides the region parsed from the request host.
+    pub(super) region: Option<String>,
+    pub(super) assume_role: Option<Arc<AssumedRole>>,
+}
+
+/// The role requests are signed as, with its cached temporary credentials.
+#[derive(Debug)]
+pub(super) struct AssumedRole {
+    config: AssumeRoleConfig,
+    credentials: RwLock<Option<Credentials>>,
+    /// Overrides the regional STS endpoint.
+    sts_url: Option<Url>,
 }
 
 impl Client {
@@ -35,10 +54,8 @@ impl Client {
         client_builder: ClientBuilder,
         provider_key: Option<&ProviderKey>,
     ) -> Result<Self, InitError> {
-        let provider_config = app_state
-            .0
-            .config
-            .providers
+        let providers = app_state.providers();
+        let provider_config = providers
             .get(&InferenceProvider::Bedrock)
             .ok_or(ProviderError::ProviderNotConfigured(
                 InferenceProvider::Bedrock,
@@ -46,13 +63,22 @@ impl Client {
 
         let base_url = provider_config.base_url.clone();
 
-        let mut default_headers = HeaderMap::new();
+        let mut default_headers = provider_config
+            .custom_headers()
+            .map_err(InitError::InvalidProviderHeader)?;
 
         default_headers.insert(http::header::HOST, host_header(&base_url));
 
-        let (access_key, secret_key) = provider_key
-            .map(|k| k.as_aws_credentials())
-            .unwrap_or_default();
+        let credentials = provider_key.and_then(|key| {
+            let (access_key, secret_key) = key.as_aws_credentials();
+            Some(Credentials::new(
+                access_key?.expose(),
+                secret_key?.expose(),
+                key.aws_session_token().map(|t| t.expose().clone()),
+                None,
+                "Environment",
+            ))
+        });
 
         default_headers.insert(
             http::header::CONTENT_TYPE,
@@ -63,113 +89,492 @@ impl Client {
             .default_headers(default_headers)
             .build()
             .map_err(InitError::CreateReqwestClient)?;
+        let aws_config = provider_config.aws.clone().unwrap_or_default();
         Ok(Self {
             inner,
-            access_key: access_key.cloned(),
-            secret_key: secret_key.cloned(),
+            credentials,
+            region: aws_config.region,
+            assume_role: aws_config.assume_role.map(|config| {
+                Arc::new(AssumedRole {
+                    config,
+                    credentials: RwLock::new(None),
+                    sts_url: None,
+                })
+            }),
         })
     }
 
-    pub fn extract_and_sign_aws_headers(
+    pub async fn extract_and_sign_aws_headers(
         &self,
-        mut request_builder: reqwest::RequestBuilder,
+        request_builder: reqwest::RequestBuilder,
         req_body_bytes: &bytes::Bytes,
     ) -> Result<reqwest::RequestBuilder, ApiError> {
-        let access_key_id = self
-            .access_key
+        let credentials = self
+            .credentials
             .as_ref()
-            .ok_or(ApiError::Authentication(AuthError::InvalidCredentials))?
-            .expose();
-        let secret_key = self
-            .secret_key
-            .as_ref()
-            .ok_or(ApiError::Authentication(AuthError::InvalidCredentials))?
-            .expose();
-        let identity = Credentials::new(
-            access_key_id,
-            secret_key,
-            None,
-            None,
-            "Environment",
-        )
-        .into();
+            .ok_or(ApiError::Authentication(AuthError::InvalidCredentials))?;
 
         let request = request_builder
             .try_clone()
             .ok_or(InternalError::AwsRequestSigningError(
                 "Failed to clone request builder".to_string(),
             ))?
-            .body(req_body_bytes.clone())
             .build()
             .map_err(InternalError::from)?;
-        let host = request
-            .url()
-            .host()
-            .ok_or(InvalidRequestError::UnsupportedEndpoint(
-                "host is required in request url".to_string(),
-            ))?
-            .to_string();
-        let host_region: Vec<&str> = host.split('.').collect();
-        let host_region = host_region.get(1).ok_or(
+        let region = match &self.region {
+            Some(region) => region.clone(),
+            None => region_from_url(request.url())?,
+        };
+
+        let credentials = match &self.assume_role {
+            Some(assume_role) => {
+                assume_role
+                    .credentials(&self.inner, credentials, &region)
+                    .await?
+            }
+            None => credentials.clone(),
+        };
+
+        sign_request(
+            request_builder,
+            req_body_bytes,
+            &credentials,
+            &region,
+            "bedrock",
+        )
+    }
+}
+
+impl AssumedRole {
+    /// The cached credentials of the role, assuming it again if they are
+    /// missing or about to expire.
+    async fn credentials(
+        &self,
+        client: &reqwest::Client,
+        source_credentials: &Credentials,
+        region: &str,
+    ) -> Result<Credentials, ApiError> {
+        let is_fresh = |credentials: &Credentials| {
+            credentials.expiry().is_some_and(|expiry| {
+                expiry
+                    > SystemTime::now()
+                        .checked_add(ASSUMED_ROLE_REFRESH_MARGIN)
+                        .unwrap_or(expiry)
+            })
+        };
+
+        if let Some(credentials) = self.credentials.read().await.as_ref()
+            && is_fresh(credentials)
+        {
+            return Ok(credentials.clone());
+        }
+
+        let mut cached = self.credentials.write().await;
+        // another request may have refreshed them while we waited
+        if let Some(credentials) = cached.as_ref()
+            && is_fresh(credentials)
+        {
+            return Ok(credentials.clone());
+        }
+        let credentials =
+            self.assume(client, source_credentials, region).await?;
+        *cached = Some(credentials.clone());
+        Ok(credentials)
+    }
+
+    /// Call STS `AssumeRole` with `source_credentials`.
+    ///
+    /// <https://docs.aws.amazon.com/STS/latest/APIReference/API_AssumeRole.html>
+    async fn assume(
+        &self,
+        client: &reqwest::Client,
+        source_credentials: &Credentials,
+        region: &str,
+    ) -> Result<Credentials, ApiError> {
+        let mut form = url::form_urlencoded::Serializer::new(String::new());
+        form.append_pair("Action", "AssumeRole")
+            .append_pair("Version", "2011-06-15")
+            .append_pair("RoleArn", &self.config.role_arn)
+            .append_pair("RoleSessionName", &self.config.session_name);
+        if let Some(external_id) = &self.config.external_id {
+            form.append_pair("ExternalId", external_id);
+        }
+        if let Some(duration_seconds) = self.config.duration_seconds {
+            form.append_pair("DurationSeconds", &duration_seconds.to_string());
+        }
+        let body = bytes::Bytes::from(form.finish());
+
+        let sts_url = match &self.sts_url {
+            Some(url) => url.clone(),
+            None => Url::parse(&format!("https://sts.{region}.amazonaws.com/"))
+                .map_err(|e| {
+                    InternalError::AwsAssumeRoleError(e.to_string())
+                })?,
+        };
+        let request_builder = client
+            .post(sts_url.clone())
+            .header(http::header::HOST, host_header(&sts_url))
+            .header(
+                http::header::CONTENT_TYPE,
+                mime::APPLICATION_WWW_FORM_URLENCODED.essence_str(),
+            )
+            // STS responds with XML unless asked for JSON
+            .header(http::header::ACCEPT, mime::APPLICATION_JSON.essence_str());
+        let request_builder = sign_request(
+            request_builder,
+            &body,
+            source_credentials,
+            region,
+            "sts",
+        )?;
+
+        let response =
+            request_builder.body(body).send().await.map_err(|e| {
+                InternalError::AwsAssumeRoleError(e.to_string())
+            })?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            tracing::error!(
+                status = %status,
+                body = %body,
+                role_arn = %self.config.role_arn,
+                "failed to assume role"
+            );
+            return Err(InternalError::AwsAssumeRoleError(format!(
+                "STS responded with {status}"
+            ))
+            .into());
+        }
+        let response = response
+            .json::<AssumeRoleResponse>()
+            .await
+            .map_err(|e| InternalError::AwsAssumeRoleError(e.to_string()))?;
+        let credentials =
+            response.assume_role_response.assume_role_result.credentials;
+        let expiry = SystemTime::UNIX_EPOCH
+            .checked_add(Duration::from_secs_f64(credentials.expiration))
+            .ok_or_else(|| {
+                InternalError::AwsAssumeRoleError(
+                    "invalid credentials expiration".to_string(),
+                )
+            })?;
+        tracing::debug!(role_arn = %self.config.role_arn, "assumed role");
+
+        Ok(Credentials::new(
+            credentials.access_key_id,
+            credentials.secret_access_key,
+            Some(credentials.session_token),
+            Some(expiry),
+            "AssumeRole",
+        ))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AssumeRoleResponse {
+    assume_role_response: AssumeRoleResponseInner,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AssumeRoleResponseInner {
+    assume_role_result: AssumeRoleResult,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AssumeRoleResult {
+    credentials: StsCredentials,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct StsCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: String,
+    /// Seconds since the epoch.
+    expiration: f64,
+}
+
+/// The region of a regional AWS endpoint, e.g. `us-east-1` for
+/// `bedrock-runtime.us-east-1.amazonaws.com`.
+fn region_from_url(url: &Url) -> Result<String, InvalidRequestError> {
+    let host = url.host_str().ok_or_else(|| {
+        InvalidRequestError::UnsupportedEndpoint(
+            "host is required in request url".to_string(),
+        )
+    })?;
+    host.split('.')
+        .nth(1)
+        .map(ToString::to_string)
+        .ok_or_else(|| {
             InvalidRequestError::UnsupportedEndpoint(
                 "host is required in request url".to_string(),
-            ),
-        )?;
+            )
+        })
+}
 
-        let signing_settings = SigningSettings::default();
-        let signing_params = v4::SigningParams::builder()
-            .identity(&identity)
-            .region(host_region)
-            .name("bedrock")
-            .time(SystemTime::now())
-            .settings(signing_settings)
-            .build()
+/// Add the SigV4 signature headers of the request to `request_builder`.
+fn sign_request(
+    mut request_builder: reqwest::RequestBuilder,
+    req_body_bytes: &bytes::Bytes,
+    credentials: &Credentials,
+    region: &str,
+    service: &str,
+) -> Result<reqwest::RequestBuilder, ApiError> {
+    let identity = credentials.clone().into();
+
+    let request = request_builder
+        .try_clone()
+        .ok_or(InternalError::AwsRequestSigningError(
+            "Failed to clone request builder".to_string(),
+        ))?
+        .body(req_body_bytes.clone())
+        .build()
+        .map_err(InternalError::from)?;
+
+    let signing_settings = SigningSettings::default();
+    let signing_params = v4::SigningParams::builder()
+        .identity(&identity)
+        .region(region)
+        .name(service)
+        .time(SystemTime::now())
+        .settings(signing_settings)
+        .build()
+        .map_err(|e| InternalError::AwsRequestSigningError(e.to_string()))?
+        .into();
+
+    let mut temp_request = http::Request::builder()
+        .uri(request.url().as_str())
+        .method(request.method().clone())
+        .body(req_body_bytes.clone())
+        .map_err(InternalError::from)?;
+    temp_request.headers_mut().extend(request.headers().clone());
+
+    let method_str = temp_request.method().to_string();
+    let url_str = temp_request.uri().to_string();
+
+    let signable_request = SignableRequest::new(
+        method_str.as_str(),
+        url_str.as_str(),
+        temp_request.headers().iter().filter_map(|(k, v)| {
+            if let Ok(v) = v.to_str() {
+                Some((k.as_str(), v))
+            } else {
+                None
+            }
+        }),
+        SignableBody::Bytes(req_body_bytes.as_ref()),
+    )
+    .map_err(|e| InternalError::AwsRequestSigningError(e.to_string()))?;
+
+    let (signing_output, _signature) =
+        aws_sigv4::http_request::sign(signable_request, &signing_params)
             .map_err(|e| InternalError::AwsRequestSigningError(e.to_string()))?
-            .into();
+            .into_parts();
+    signing_output.apply_to_request_http1x(&mut temp_request);
 
-        let mut temp_request = http::Request::builder()
-            .uri(request.url().as_str())
-            .method(request.method().clone())
-            .body(req_body_bytes.clone())
-            .map_err(InternalError::from)?;
-        temp_request.headers_mut().extend(request.headers().clone());
-
-        let method_str = temp_request.method().to_string();
-        let url_str = temp_request.uri().to_string();
-
-        let signable_request = SignableRequest::new(
-            method_str.as_str(),
-            url_str.as_str(),
-            temp_request.headers().iter().filter_map(|(k, v)| {
-                if let Ok(v) = v.to_str() {
-                    Some((k.as_str(), v))
-                } else {
-                    None
+    // Get the headers from the original request
+    let req_headers = request.headers();
+
+    // Copy all the aws signed credentials from temp_request since the
+    // apply_to_request_http1x is only for http::Request types
+    for (key, value) in temp_request.headers() {
+        if !req_headers.contains_key(key) {
+            request_builder = request_builder.header(key, value);
+        }
+    }
+
+    Ok(request_builder)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    /// Serve `status` and `body` to every request, returning the url of the
+    /// server, the number of requests and the last request received.
+    async fn mock_sts(
+        status: u16,
+        body: String,
+    ) -> (Url, Arc<AtomicUsize>, Arc<RwLock<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url =
+            Url::parse(&format!("http://{}/", listener.local_addr().unwrap()))
+                .unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let last_request = Arc::new(RwLock::new(String::new()));
+        let (counter, last) = (requests.clone(), last_request.clone());
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0; 4096];
+                // read the headers, then as much body as they announce
+                while !is_complete(&request) {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
                 }
-            }),
-            SignableBody::Bytes(req_body_bytes.as_ref()),
-        )
-        .map_err(|e| InternalError::AwsRequestSigningError(e.to_string()))?;
+                counter.fetch_add(1, Ordering::SeqCst);
+                *last.write().await =
+                    String::from_utf8_lossy(&request).into_owned();
+                let response = format!(
+                    "HTTP/1.1 {status} Mock\r\ncontent-type: \
+                     application/json\r\ncontent-length: {}\r\nconnection: \
+                     close\r\n\r\n{body}",
+                    body.len()
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        (url, requests, last_request)
+    }
 
-        let (signing_output, _signature) =
-            aws_sigv4::http_request::sign(signable_request, &signing_params)
-                .map_err(|e| {
-                    InternalError::AwsRequestSigningError(e.to_string())
-                })?
-                .into_parts();
-        signing_output.apply_to_request_http1x(&mut temp_request);
-
-        // Get the headers from the original request
-        let req_headers = request.headers();
-
-        // Copy all the aws signed credentials from temp_request since the
-        // apply_to_request_http1x is only for http::Request types
-        for (key, value) in temp_request.headers() {
-            if !req_headers.contains_key(key) {
-                request_builder = request_builder.header(key, value);
+    fn is_complete(request: &[u8]) -> bool {
+        let request = String::from_utf8_lossy(request);
+        let Some((headers, body)) = request.split_once("\r\n\r\n") else {
+            return false;
+        };
+        let content_length = headers
+            .lines()
+            .find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("content-length")
+                    .then(|| value.trim().parse::<usize>().ok())?
+            })
+            .unwrap_or_default();
+        body.len() >= content_length
+    }
+
+    fn sts_response(expires_in: Duration) -> String {
+        let expiration = SystemTime::now()
+            .checked_add(expires_in)
+            .unwrap()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        serde_json::json!({
+            "AssumeRoleResponse": {
+                "AssumeRoleResult": {
+                    "Credentials": {
+                        "AccessKeyId": "ASIAASSUMED",
+                        "SecretAccessKey": "assumed-secret",
+                        "SessionToken": "assumed-token",
+                        "Expiration": expiration,
+                    }
+                }
             }
+        })
+        .to_string()
+    }
+
+    fn assumed_role(sts_url: Url) -> AssumedRole {
+        AssumedRole {
+            config: AssumeRoleConfig {
+                role_arn: "arn:aws:iam::123456789012:role/bedrock-invoke"
+                    .to_string(),
+                session_name: "ai-gateway".to_string(),
+                external_id: Some("gateway".to_string()),
+                duration_seconds: None,
+            },
+            credentials: RwLock::new(None),
+            sts_url: Some(sts_url),
+        }
+    }
+
+    fn source_credentials() -> Credentials {
+        Credentials::new("AKIASOURCE", "source-secret", None, None, "test")
+    }
+
+    #[tokio::test]
+    async fn assumed_role_credentials_are_cached() {
+        let (url, requests, last_request) =
+            mock_sts(200, sts_response(Duration::from_secs(3600))).await;
+        let role = assumed_role(url);
+        let client = reqwest::Client::new();
+
+        let credentials = role
+            .credentials(&client, &source_credentials(), "us-east-1")
+            .await
+            .unwrap();
+        assert_eq!(credentials.access_key_id(), "ASIAASSUMED");
+        assert_eq!(credentials.secret_access_key(), "assumed-secret");
+        assert_eq!(credentials.session_token(), Some("assumed-token"));
+        assert!(credentials.expiry().is_some());
+
+        let request = last_request.read().await.clone();
+        assert!(request.contains("Action=AssumeRole"), "{request}");
+        assert!(request.contains("ExternalId=gateway"), "{request}");
+        assert!(
+            request.contains("Credential=AKIASOURCE/"),
+            "request should be signed with the source credentials: {request}"
+        );
+        assert!(request.contains("/us-east-1/sts/aws4_request"), "{request}");
+
+        let cached = role
+            .credentials(&client, &source_credentials(), "us-east-1")
+            .await
+            .unwrap();
+        assert_eq!(cached.session_token(), Some("assumed-token"));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn expiring_credentials_are_refreshed() {
+        // expire within the refresh margin, so every call assumes the role
+        let (url, requests, _) =
+            mock_sts(200, sts_response(Duration::from_secs(60))).await;
+        let role = assumed_role(url);
+        let client = reqwest::Client::new();
+
+        for _ in 0..2 {
+            role.credentials(&client, &source_credentials(), "us-east-1")
+                .await
+                .unwrap();
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn sts_errors_are_not_cached() {
+        let (url, requests, _) =
+            mock_sts(403, r#"{"Error":{"Code":"AccessDenied"}}"#.to_string())
+                .await;
+        let role = assumed_role(url);
+        let client = reqwest::Client::new();
+
+        for _ in 0..2 {
+            let error = role
+                .credentials(&client, &source_credentials(), "us-east-1")
+                .await
+                .unwrap_err();
+            assert!(matches!(
+                error,
+                ApiError::Internal(InternalError::AwsAssumeRoleError(_))
+            ));
         }
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        assert!(role.credentials.read().await.is_none());
+    }
 
-        Ok(request_builder)
+    #[test]
+    fn region_is_parsed_from_the_host() {
+        let url =
+            Url::parse("https://bedrock-runtime.eu-west-1.amazonaws.com/model")
+                .unwrap();
+        assert_eq!(region_from_url(&url).unwrap(), "eu-west-1");
     }
 }
@@ -1,18 +1,23 @@
-use bytes::Bytes;
+use aws_smithy_eventstream::frame::{DecodedFrame, MessageFrameDecoder};
+use aws_smithy_types::event_stream::Message;
+use bytes::{Bytes, BytesMut};
 use futures::StreamExt;
 use http_body_util::BodyExt;
-use reqwest::RequestBuilder;
+use reqwest::{ClientBuilder, RequestBuilder};
 use reqwest_eventsource::{Event, EventSource, RequestBuilderExt};
 use tracing::{Instrument, info_span};
 
 use crate::{
     app_state::AppState,
+    config::providers::{GlobalProviderConfig, SelfHostedConfig},
     discover::monitor::metrics::EndpointMetricsRegistry,
     dispatcher::{
         SSEStream, anthropic_client::Client as AnthropicClient,
         bedrock_client::Client as BedrockClient,
+        gemini_client::Client as GeminiClient,
         ollama_client::Client as OllamaClient,
         openai_compatible_client::Client as OpenAICompatibleClient,
+        service::UpstreamErrorCodes,
     },
     endpoints::ApiEndpoint,
     error::{
@@ -46,9 +51,17 @@ impl ProviderClient for Client {
         provider: InferenceProvider,
     ) -> Result<reqwest::RequestBuilder, ApiError> {
         match self {
-            Client::Bedrock(inner) => inner
-                .extract_and_sign_aws_headers(request_builder, req_body_bytes),
-            Client::OpenAICompatible(_) | Client::Anthropic(_) => {
+            Client::Bedrock(inner) => {
+                inner
+                    .extract_and_sign_aws_headers(
+                        request_builder,
+                        req_body_bytes,
+                    )
+                    .await
+            }
+            Client::OpenAICompatible(_)
+            | Client::Anthropic(_)
+            | Client::Gemini(_) => {
                 self.authenticate_inner(
                     app_state,
                     request_builder,
@@ -66,6 +79,7 @@ impl ProviderClient for Client {
 pub enum Client {
     OpenAICompatible(OpenAICompatibleClient),
     Anthropic(AnthropicClient),
+    Gemini(GeminiClient),
     Ollama(OllamaClient),
     Bedrock(BedrockClient),
 }
@@ -104,6 +118,9 @@ impl Client {
                                 &key,
                             )
                         }
+                        Client::Gemini(_) => {
+                            GeminiClient::set_auth_header(request_builder, &key)
+                        }
                         _ => request_builder,
                     };
 
@@ -142,15 +159,22 @@ impl Client {
                                 key,
                             )
                         }
+                        Client::Gemini(_) => {
+                            GeminiClient::set_auth_header(request_builder, key)
+                        }
                         _ => request_builder,
                     };
 
                     return Ok(request_builder);
                 }
-
-                return Err(ApiError::Authentication(
-                    AuthError::ProviderKeyNotFound,
-                ));
+            }
+            if app_state
+                .providers()
+                .get(&provider)
+                .is_some_and(GlobalProviderConfig::is_self_hosted)
+            {
+                // self-hosted providers only get a key if one is configured
+                return Ok(request_builder);
             }
             Err(ApiError::Authentication(AuthError::ProviderKeyNotFound))
         } else {
@@ -177,6 +201,66 @@ impl Client {
         Ok(stream)
     }
 
+    /// Like [`Client::sse_stream`], for providers that stream AWS event
+    /// streams rather than SSE, i.e. Bedrock.
+    pub(crate) async fn aws_event_stream<B>(
+        request_builder: RequestBuilder,
+        body: B,
+        api_endpoint: Option<ApiEndpoint>,
+        metrics_registry: &EndpointMetricsRegistry,
+    ) -> Result<SSEStream, ApiError>
+    where
+        B: Into<reqwest::Body>,
+    {
+        let error = match request_builder.body(body).send().await {
+            Ok(response) if response.status().is_success() => {
+                return Ok(aws_event_stream(
+                    response,
+                    api_endpoint,
+                    metrics_registry.clone(),
+                ));
+            }
+            Ok(response) => reqwest_eventsource::Error::InvalidStatusCode(
+                response.status(),
+                response,
+            ),
+            Err(e) => reqwest_eventsource::Error::Transport(e),
+        };
+        handle_stream_error(error, api_endpoint, metrics_registry).await?;
+        // `handle_stream_error` always errors for the above errors
+        Err(ApiError::Internal(InternalError::Internal))
+    }
+
+    /// Like [`Client::sse_stream`], for providers that stream newline
+    /// delimited JSON rather than SSE, i.e. Ollama's native API.
+    pub(crate) async fn ndjson_stream<B>(
+        request_builder: RequestBuilder,
+        body: B,
+        api_endpoint: Option<ApiEndpoint>,
+        metrics_registry: &EndpointMetricsRegistry,
+    ) -> Result<SSEStream, ApiError>
+    where
+        B: Into<reqwest::Body>,
+    {
+        let error = match request_builder.body(body).send().await {
+            Ok(response) if response.status().is_success() => {
+                return Ok(ndjson_stream(
+                    response,
+                    api_endpoint,
+                    metrics_registry.clone(),
+                ));
+            }
+            Ok(response) => reqwest_eventsource::Error::InvalidStatusCode(
+                response.status(),
+                response,
+            ),
+            Err(e) => reqwest_eventsource::Error::Transport(e),
+        };
+        handle_stream_error(error, api_endpoint, metrics_registry).await?;
+        // `handle_stream_error` always errors for the above errors
+        Err(ApiError::Internal(InternalError::Internal))
+    }
+
     pub(crate) async fn new(
         app_state: &AppState,
         inference_provider: InferenceProvider,
@@ -199,15 +283,31 @@ impl Client {
         api_key: Option<&ProviderKey>,
     ) -> Result<Self, InitError> {
         // connection timeout, timeout, etc.
-        let base_client = reqwest::Client::builder()
+        let mut base_client = reqwest::Client::builder()
             .connect_timeout(app_state.0.config.dispatcher.connection_timeout)
             .timeout(app_state.0.config.dispatcher.timeout)
             .tcp_nodelay(true);
+        let providers = app_state.providers();
+        let provider_config = providers.get(&inference_provider);
+        if let Some(timeout) =
+            provider_config.and_then(GlobalProviderConfig::timeout)
+        {
+            base_client = base_client
+                .connect_timeout(timeout.connect_timeout)
+                .read_timeout(timeout.read_timeout);
+            if let Some(total_timeout) = timeout.total_timeout {
+                base_client = base_client.timeout(total_timeout);
+            }
+        }
+        if let Some(self_hosted) =
+            provider_config.and_then(|config| config.self_hosted.as_ref())
+        {
+            base_client =
+                self_hosted_tls(base_client, &inference_provider, self_hosted)?;
+        }
 
         match inference_provider {
-            InferenceProvider::OpenAI
-            | InferenceProvider::GoogleGemini
-            | InferenceProvider::Named(_) => {
+            InferenceProvider::OpenAI | InferenceProvider::Named(_) => {
                 let openai_compatible_client = OpenAICompatibleClient::new(
                     app_state,
                     base_client,
@@ -219,6 +319,9 @@ impl Client {
             InferenceProvider::Anthropic => Ok(Self::Anthropic(
                 AnthropicClient::new(app_state, base_client, api_key)?,
             )),
+            InferenceProvider::GoogleGemini => Ok(Self::Gemini(
+                GeminiClient::new(app_state, base_client, api_key)?,
+            )),
             InferenceProvider::Bedrock => Ok(Self::Bedrock(
                 BedrockClient::new(app_state, base_client, api_key)?,
             )),
@@ -229,11 +332,34 @@ impl Client {
     }
 }
 
+/// Apply the TLS settings of a self-hosted provider to its client.
+fn self_hosted_tls(
+    client_builder: ClientBuilder,
+    provider: &InferenceProvider,
+    config: &SelfHostedConfig,
+) -> Result<ClientBuilder, InitError> {
+    let mut client_builder =
+        client_builder.danger_accept_invalid_certs(config.accept_invalid_certs);
+    if let Some(ca_cert) = &config.ca_cert {
+        let ca_cert_error = |error: String| {
+            InitError::InvalidProviderCaCert(provider.clone(), error)
+        };
+        let pem = std::fs::read(ca_cert).map_err(|e| {
+            ca_cert_error(format!("{}: {e}", ca_cert.display()))
+        })?;
+        let certificate = reqwest::Certificate::from_pem(&pem)
+            .map_err(|e| ca_cert_error(e.to_string()))?;
+        client_builder = client_builder.add_root_certificate(certificate);
+    }
+    Ok(client_builder)
+}
+
 impl AsRef<reqwest::Client> for Client {
     fn as_ref(&self) -> &reqwest::Client {
         match self {
             Client::OpenAICompatible(client) => &client.0,
             Client::Anthropic(client) => &client.0,
+            Client::Gemini(client) => &client.0,
             Client::Ollama(client) => &client.0,
             Client::Bedrock(client) => &client.inner,
         }
@@ -314,6 +440,178 @@ pub(super) async fn sse_stream(
     ))
 }
 
+/// Decode an [AWS event stream](https://docs.aws.amazon.com/AmazonS3/latest/API/RESTSelectObjectAppendix.html)
+/// response.
+///
+/// Each event is sent on as `{"<event type>": <payload>}`, the JSON
+/// representation of the event union, e.g. `ConverseStreamOutput`.
+/// Exceptions end the stream with a [`StreamError::Exception`].
+fn aws_event_stream(
+    response: reqwest::Response,
+    api_endpoint: Option<ApiEndpoint>,
+    metrics_registry: EndpointMetricsRegistry,
+) -> SSEStream {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+    tokio::spawn(
+        async move {
+            let mut body = response.bytes_stream();
+            let mut decoder = MessageFrameDecoder::new();
+            let mut buffer = BytesMut::new();
+            'stream: while let Some(chunk) = body.next().await {
+                match chunk {
+                    Ok(chunk) => buffer.extend_from_slice(&chunk),
+                    Err(e) => {
+                        let error = reqwest_eventsource::Error::Transport(e);
+                        if let Err(e) = handle_stream_error_with_tx(error, tx.clone(), api_endpoint.clone(), &metrics_registry).await {
+                            tracing::error!(error = %e, "failed to handle stream error");
+                        }
+                        break;
+                    }
+                }
+
+                loop {
+                    match decoder.decode_frame(&mut buffer) {
+                        Ok(DecodedFrame::Complete(message)) => {
+                            let data = match aws_event_data(&message) {
+                                Ok(data) => data,
+                                Err(e) => {
+                                    let _ = tx.send(Err(ApiError::StreamError(e)));
+                                    break 'stream;
+                                }
+                            };
+                            if let Err(_e) = tx.send(Ok(data)) {
+                                tracing::trace!("rx dropped before stream ended");
+                                break 'stream;
+                            }
+                        }
+                        Ok(DecodedFrame::Incomplete) => break,
+                        Err(e) => {
+                            tracing::error!(error = %e, "failed to decode event stream frame");
+                            let _ = tx.send(Err(ApiError::StreamError(
+                                StreamError::BodyError(axum_core::Error::new(e)),
+                            )));
+                            break 'stream;
+                        }
+                    }
+                }
+            }
+        }
+        .instrument(info_span!("aws_event_stream")),
+    );
+
+    Box::pin(tokio_stream::wrappers::UnboundedReceiverStream::new(rx))
+}
+
+/// Split a newline delimited JSON response into its lines, sending each
+/// non-empty line on as a chunk.
+fn ndjson_stream(
+    response: reqwest::Response,
+    api_endpoint: Option<ApiEndpoint>,
+    metrics_registry: EndpointMetricsRegistry,
+) -> SSEStream {
+    let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+
+    tokio::spawn(
+        async move {
+            let mut body = response.bytes_stream();
+            let mut buffer = BytesMut::new();
+            while let Some(chunk) = body.next().await {
+                match chunk {
+                    Ok(chunk) => buffer.extend_from_slice(&chunk),
+                    Err(e) => {
+                        let error = reqwest_eventsource::Error::Transport(e);
+                        if let Err(e) = handle_stream_error_with_tx(error, tx.clone(), api_endpoint.clone(), &metrics_registry).await {
+                            tracing::error!(error = %e, "failed to handle stream error");
+                        }
+                        return;
+                    }
+                }
+
+                while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                    let line = buffer.split_to(end + 1).freeze();
+                    let line = line.trim_ascii();
+                    if line.is_empty() {
+                        continue;
+                    }
+                    if let Err(_e) = tx.send(Ok(Bytes::copy_from_slice(line))) {
+                        tracing::trace!("rx dropped before stream ended");
+                        return;
+                    }
+                }
+            }
+            // the last line may not be terminated
+            let line = buffer.trim_ascii();
+            if !line.is_empty() {
+                let _ = tx.send(Ok(Bytes::copy_from_slice(line)));
+            }
+        }
+        .instrument(info_span!("ndjson_stream")),
+    );
+
+    Box::pin(tokio_stream::wrappers::UnboundedReceiverStream::new(rx))
+}
+
+fn aws_event_data(message: &Message) -> Result<Bytes, StreamError> {
+    let header = |name: &str| {
+        message
+            .headers()
+            .iter()
+            .find(|header| header.name().as_str() == name)
+            .and_then(|header| header.value().as_string().ok())
+            .map(|value| value.as_str().to_string())
+    };
+    let invalid_payload = |e: serde_json::Error| {
+        tracing::error!(error = %e, "invalid event stream payload");
+        StreamError::BodyError(axum_core::Error::new(e))
+    };
+
+    match (header(":message-type").as_deref(), header(":event-type")) {
+        (Some("event"), Some(event_type)) => {
+            let payload: serde_json::Value =
+                serde_json::from_slice(message.payload())
+                    .map_err(invalid_payload)?;
+            let mut event = serde_json::Map::new();
+            event.insert(event_type, payload);
+            Ok(Bytes::from(
+                serde_json::to_vec(&event).map_err(invalid_payload)?,
+            ))
+        }
+        (message_type, _) => {
+            // exceptions carry their type in `:exception-type` and a
+            // `{"message": ..}` payload, errors both in headers
+            let exception_type = header(":exception-type")
+                .or_else(|| header(":error-code"))
+                .unwrap_or_else(|| {
+                    message_type.unwrap_or("unknown").to_string()
+                });
+            let message =
+                serde_json::from_slice::<serde_json::Value>(message.payload())
+                    .ok()
+                    .and_then(|payload| {
+                        payload
+                            .get("message")
+                            .or_else(|| payload.get("Message"))
+                            .and_then(serde_json::Value::as_str)
+                            .map(ToString::to_string)
+                    })
+                    .or_else(|| header(":error-message"))
+                    .unwrap_or_else(|| {
+                        String::from_utf8_lossy(message.payload()).into_owned()
+                    });
+            tracing::debug!(
+                message_type = ?message_type,
+                exception_type = %exception_type,
+                "received exception in event stream"
+            );
+            Err(StreamError::Exception {
+                exception_type,
+                message,
+            })
+        }
+    }
+}
+
 async fn handle_stream_error_with_tx(
     error: reqwest_eventsource::Error,
     tx: tokio::sync::mpsc::UnboundedSender<Result<Bytes, ApiError>>,
@@ -371,40 +669,40 @@ async fn handle_stream_error(
             status_code,
             response,
         ) => {
+            let http_resp = http::Response::from(response);
+            let (mut parts, body) = http_resp.into_parts();
+            let body = match body.collect().await {
+                Err(e) => {
+                    let error =
+                        axum_core::Error::new(InternalError::ReqwestError(e));
+                    return Err(StreamError::BodyError(error));
+                }
+                Ok(body) => body.to_bytes(),
+            };
             cfg_if::cfg_if! {
                 // this is compiled out in release builds
                 if #[cfg(debug_assertions)] {
-                    let http_resp = http::Response::from(response);
-                    let (parts, body) = http_resp.into_parts();
-                    let body = match body.collect().await {
-                        Err(e) => {
-                            let error =
-                                axum_core::Error::new(InternalError::ReqwestError(e));
-                            return Err(StreamError::BodyError(error));
-                        }
-                        Ok(body) => body.to_bytes(),
-                    };
                     let text = String::from_utf8_lossy(&body);
                     tracing::debug!(status_code = %status_code, body = %text, "received error response in stream");
-                    let response = http::Response::from_parts(parts, body);
-                    Err(StreamError::StreamError(Box::new(reqwest_eventsource::Error::InvalidStatusCode(
-                        status_code,
-                        response.into(),
-                    ))))
                 } else {
                     if status_code.is_server_error() {
                         tracing::error!(status_code = %status_code, "received server error in stream");
                     } else if status_code.is_client_error() {
                         tracing::debug!(status_code = %status_code, "received client error in stream");
                     }
-
-
-                    Err(StreamError::StreamError(Box::new(reqwest_eventsource::Error::InvalidStatusCode(
-                        status_code,
-                        response,
-                    ))))
                 }
             }
+            // matched against the retry policy's `retryable-error-codes`
+            parts
+                .extensions
+                .insert(UpstreamErrorCodes::from_body(&body));
+            let response = http::Response::from_parts(parts, body);
+            Err(StreamError::StreamError(Box::new(
+                reqwest_eventsource::Error::InvalidStatusCode(
+                    status_code,
+                    response.into(),
+                ),
+            )))
         }
         e => {
             tracing::error!(error = %e, "received error in stream");
@@ -426,3 +724,181 @@ fn record_stream_err_metrics(
         }).ok();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use aws_smithy_eventstream::frame::write_message_to;
+    use aws_smithy_types::event_stream::{Header, HeaderValue};
+
+    use super::*;
+    use crate::config::Config;
+
+    fn message(
+        headers: &[(&'static str, &'static str)],
+        payload: &str,
+    ) -> Message {
+        headers.iter().fold(
+            Message::new(payload.to_string()),
+            |message, (name, value)| {
+                message.add_header(Header::new(
+                    *name,
+                    HeaderValue::String((*value).into()),
+                ))
+            },
+        )
+    }
+
+    fn event(event_type: &'static str, payload: &str) -> Message {
+        message(
+            &[(":message-type", "event"), (":event-type", event_type)],
+            payload,
+        )
+    }
+
+    fn encode(messages: &[Message]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        for message in messages {
+            write_message_to(message, &mut buf).unwrap();
+        }
+        buf
+    }
+
+    async fn decode(
+        body: Vec<u8>,
+        chunk_size: usize,
+    ) -> Vec<Result<Bytes, ApiError>> {
+        let chunks = body
+            .chunks(chunk_size)
+            .map(|chunk| Ok::<_, std::io::Error>(Bytes::copy_from_slice(chunk)))
+            .collect::<Vec<_>>();
+        let response = http::Response::new(reqwest::Body::wrap_stream(
+            futures::stream::iter(chunks),
+        ));
+        let stream = aws_event_stream(
+            reqwest::Response::from(response),
+            None,
+            EndpointMetricsRegistry::new(&Config::default()),
+        );
+        stream.collect().await
+    }
+
+    #[test]
+    fn aws_event_data_wraps_events_in_their_type() {
+        let data = aws_event_data(&event(
+            "contentBlockDelta",
+            r#"{"contentBlockIndex":0,"delta":{"text":"Hi"}}"#,
+        ))
+        .unwrap();
+        let data: serde_json::Value = serde_json::from_slice(&data).unwrap();
+        assert_eq!(data["contentBlockDelta"]["delta"]["text"], "Hi");
+    }
+
+    #[test]
+    fn aws_event_data_turns_exceptions_into_errors() {
+        let error = aws_event_data(&message(
+            &[
+                (":message-type", "exception"),
+                (":exception-type", "throttlingException"),
+            ],
+            r#"{"message":"Too many requests, please wait"}"#,
+        ))
+        .unwrap_err();
+        let StreamError::Exception {
+            exception_type,
+            message: text,
+        } = &error
+        else {
+            panic!("expected an exception, got {error:?}");
+        };
+        assert_eq!(exception_type, "throttlingException");
+        assert_eq!(text, "Too many requests, please wait");
+        assert!(error.is_retryable());
+
+        let error = aws_event_data(&message(
+            &[
+                (":message-type", "error"),
+                (":error-code", "InternalFailure"),
+                (":error-message", "oops"),
+            ],
+            "",
+        ))
+        .unwrap_err();
+        assert!(matches!(
+            error,
+            StreamError::Exception { ref exception_type, ref message }
+                if exception_type == "InternalFailure" && message == "oops"
+        ));
+    }
+
+    #[tokio::test]
+    async fn aws_event_stream_decodes_frames_split_across_chunks() {
+        let body = encode(&[
+            event("messageStart", r#"{"role":"assistant"}"#),
+            event(
+                "contentBlockDelta",
+                r#"{"contentBlockIndex":0,"delta":{"text":"Hello"}}"#,
+            ),
+            event("messageStop", r#"{"stopReason":"end_turn"}"#),
+        ]);
+
+        for chunk_size in [1, 7, body.len()] {
+            let events = decode(body.clone(), chunk_size)
+                .await
+                .into_iter()
+                .map(|event| {
+                    let event = event.unwrap();
+                    serde_json::from_slice::<serde_json::Value>(&event).unwrap()
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(events.len(), 3, "chunk size {chunk_size}");
+            assert_eq!(events[0]["messageStart"]["role"], "assistant");
+            assert_eq!(
+                events[1]["contentBlockDelta"]["delta"]["text"],
+                "Hello"
+            );
+            assert_eq!(events[2]["messageStop"]["stopReason"], "end_turn");
+        }
+    }
+
+    #[tokio::test]
+    async fn aws_event_stream_ends_with_an_error_on_exceptions() {
+        let body = encode(&[
+            event("messageStart", r#"{"role":"assistant"}"#),
+            message(
+                &[
+                    (":message-type", "exception"),
+                    (":exception-type", "modelStreamErrorException"),
+                ],
+                r#"{"message":"model stream failed"}"#,
+            ),
+            event("messageStop", r#"{"stopReason":"end_turn"}"#),
+        ]);
+
+        let events = decode(body, 16).await;
+        assert_eq!(events.len(), 2);
+        assert!(events[0].is_ok());
+        assert!(matches!(
+            &events[1],
+            Err(ApiError::StreamError(StreamError::Exception {
+                exception_type,
+                ..
+            })) if exception_type == "modelStreamErrorException"
+        ));
+    }
+
+    #[tokio::test]
+    async fn aws_event_stream_errors_on_corrupt_frames() {
+        let mut body =
+            encode(&[event("messageStart", r#"{"role":"assistant"}"#)]);
+        // corrupt the message CRC at the end of the frame
+        let last = body.len() - 1;
+        body[last] ^= 0xff;
+
+        let events = decode(body, 64).await;
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            events[0],
+            Err(ApiError::StreamError(StreamError::BodyError(_)))
+        ));
+    }
+}
@@ -0,0 +1,66 @@
+use http::{HeaderName, HeaderValue};
+use reqwest::ClientBuilder;
+
+use crate::{
+    app_state::AppState,
+    error::{init::InitError, provider::ProviderError},
+    types::{
+        provider::{InferenceProvider, ProviderKey},
+        secret::Secret,
+    },
+    utils::host_header,
+};
+
+/// <https://ai.google.dev/gemini-api/docs/api-key>
+const API_KEY_HEADER: &str = "x-goog-api-key";
+
+#[derive(Debug, Clone, Default)]
+pub struct Client(pub(super) reqwest::Client);
+
+impl Client {
+    pub fn new(
+        app_state: &AppState,
+        client_builder: ClientBuilder,
+        provider_key: Option<&ProviderKey>,
+    ) -> Result<Self, InitError> {
+        let providers = app_state.providers();
+        let provider_config = providers
+            .get(&InferenceProvider::GoogleGemini)
+            .ok_or(ProviderError::ProviderNotConfigured(
+            InferenceProvider::GoogleGemini,
+        ))?;
+        let base_url = provider_config.base_url.clone();
+
+        let mut default_headers = provider_config
+            .custom_headers()
+            .map_err(InitError::InvalidProviderHeader)?;
+        if let Some(ProviderKey::Secret(key)) = provider_key {
+            default_headers.insert(
+                HeaderName::from_static(API_KEY_HEADER),
+                HeaderValue::from_str(key.expose()).unwrap(),
+            );
+        }
+        default_headers.insert(http::header::HOST, host_header(&base_url));
+        default_headers.insert(
+            http::header::CONTENT_TYPE,
+            HeaderValue::from_str(mime::APPLICATION_JSON.essence_str())
+                .unwrap(),
+        );
+
+        let inner = client_builder
+            .default_headers(default_headers)
+            .build()
+            .map_err(InitError::CreateReqwestClient)?;
+        Ok(Self(inner))
+    }
+
+    pub fn set_auth_header(
+        request_builder: reqwest::RequestBuilder,
+        key: &Secret<String>,
+    ) -> reqwest::RequestBuilder {
+        request_builder.header(
+            HeaderName::from_static(API_KEY_HEADER),
+            HeaderValue::from_str(key.expose()).unwrap(),
+        )
+    }
+}
@@ -2,6 +2,7 @@ pub mod anthropic_client;
 mod bedrock_client;
 pub mod client;
 mod extensions;
+pub mod gemini_client;
 pub mod ollama_client;
 pub mod openai_compatible_client;
 pub mod service;
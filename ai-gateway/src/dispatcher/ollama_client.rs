@@ -1,4 +1,4 @@
-use http::{HeaderMap, HeaderValue};
+use http::HeaderValue;
 use reqwest::ClientBuilder;
 
 use crate::{
@@ -16,18 +16,15 @@ impl Client {
         app_state: &AppState,
         client_builder: ClientBuilder,
     ) -> Result<Self, InitError> {
-        let base_url = app_state
-            .0
-            .config
-            .providers
-            .get(&InferenceProvider::Ollama)
-            .ok_or(ProviderError::ProviderNotConfigured(
-                InferenceProvider::Ollama,
-            ))?
-            .base_url
-            .clone();
+        let providers = app_state.providers();
+        let provider_config = providers.get(&InferenceProvider::Ollama).ok_or(
+            ProviderError::ProviderNotConfigured(InferenceProvider::Ollama),
+        )?;
+        let base_url = provider_config.base_url.clone();
 
-        let mut default_headers = HeaderMap::new();
+        let mut default_headers = provider_config
+            .custom_headers()
+            .map_err(InitError::InvalidProviderHeader)?;
         default_headers.insert(http::header::HOST, host_header(&base_url));
         default_headers.insert(
             http::header::CONTENT_TYPE,
@@ -1,4 +1,4 @@
-use http::{HeaderMap, HeaderValue};
+use http::HeaderValue;
 use reqwest::ClientBuilder;
 
 use crate::{
@@ -21,16 +21,15 @@ impl Client {
         provider: InferenceProvider,
         provider_key: Option<&ProviderKey>,
     ) -> Result<Self, InitError> {
-        let base_url = app_state
-            .0
-            .config
-            .providers
+        let providers = app_state.providers();
+        let provider_config = providers
             .get(&provider)
-            .ok_or_else(|| ProviderError::ProviderNotConfigured(provider))?
-            .base_url
-            .clone();
+            .ok_or_else(|| ProviderError::ProviderNotConfigured(provider))?;
+        let base_url = provider_config.base_url.clone();
 
-        let mut default_headers = HeaderMap::new();
+        let mut default_headers = provider_config
+            .request_headers()
+            .map_err(InitError::InvalidProviderHeader)?;
         if let Some(ProviderKey::Secret(key)) = provider_key {
             default_headers.insert(
                 http::header::AUTHORIZATION,
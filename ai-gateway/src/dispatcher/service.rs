@@ -5,15 +5,13 @@ use std::{
     time::Duration,
 };
 
-use backon::{BackoffBuilder, ConstantBuilder, ExponentialBuilder, Retryable};
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
-use futures::{TryStreamExt, future::BoxFuture};
+use futures::{StreamExt, TryStreamExt, future::BoxFuture};
 use http::{HeaderMap, HeaderName, HeaderValue, StatusCode, uri::PathAndQuery};
 use http_body_util::BodyExt;
 use opentelemetry::KeyValue;
 use reqwest::RequestBuilder;
-use rust_decimal::prelude::ToPrimitive;
 use tokio::{
     sync::{mpsc::Sender, oneshot},
     time::Instant,
@@ -24,20 +22,26 @@ use uuid::Uuid;
 
 use crate::{
     app_state::AppState,
-    config::{retry::RetryConfig, router::RouterConfig},
+    config::{retry::RetryPolicy, router::RouterConfig},
+    control_plane::types::hash_key,
     discover::monitor::metrics::EndpointMetricsRegistry,
     dispatcher::{
         client::{Client, ProviderClient},
         extensions::ExtensionsCopier,
     },
-    endpoints::ApiEndpoint,
-    error::{api::ApiError, init::InitError, internal::InternalError},
-    logger::service::LoggerService,
+    endpoints::{ApiEndpoint, EndpointType, ollama::Ollama},
+    error::{
+        api::ApiError, init::InitError, internal::InternalError,
+        invalid_req::InvalidRequestError, stream::StreamError,
+    },
+    logger::{service::LoggerService, sink::LogRecord},
+    metering::{RequestCost, Usage},
     metrics::tfft::TFFTFuture,
     middleware::{
         add_extension::{AddExtensions, AddExtensionsLayer},
         mapper::{model::ModelMapper, registry::EndpointConverterRegistry},
     },
+    router::queue::{Permit, Priority, RequestQueue},
     types::{
         body::BodyReader,
         extensions::{
@@ -49,15 +53,25 @@ use crate::{
         request::Request,
         router::RouterId,
     },
-    utils::handle_error::{ErrorHandler, ErrorHandlerLayer},
+    utils::{
+        handle_error::{ErrorHandler, ErrorHandlerLayer},
+        retry::RetryWithResult,
+    },
 };
 
 pub type DispatcherFuture = BoxFuture<
     'static,
     Result<http::Response<crate::types::body::Body>, ApiError>,
 >;
-pub type DispatcherService =
-    AddExtensions<ErrorHandler<crate::middleware::mapper::Service<Dispatcher>>>;
+pub type DispatcherService = AddExtensions<
+    ErrorHandler<
+        crate::middleware::embeddings::Service<
+            crate::middleware::multimodal::Service<
+                crate::middleware::mapper::Service<Dispatcher>,
+            >,
+        >,
+    >,
+>;
 pub type DispatcherServiceWithoutMapper =
     AddExtensions<ErrorHandler<Dispatcher>>;
 
@@ -87,7 +101,10 @@ impl Dispatcher {
             provider: provider.clone(),
             rate_limit_tx: Some(rate_limit_tx),
         };
-        let converter_registry = EndpointConverterRegistry::new(&model_mapper);
+        let converter_registry = EndpointConverterRegistry::new(
+            &model_mapper,
+            &app_state.providers(),
+        );
 
         let extensions_layer = AddExtensionsLayer::builder()
             .inference_provider(provider.clone())
@@ -96,7 +113,15 @@ impl Dispatcher {
 
         Ok(ServiceBuilder::new()
             .layer(extensions_layer)
-            .layer(ErrorHandlerLayer::new(app_state))
+            .layer(ErrorHandlerLayer::new(app_state.clone()))
+            .layer(crate::middleware::embeddings::Layer::new(
+                app_state.clone(),
+                model_mapper.clone(),
+            ))
+            .layer(crate::middleware::multimodal::Layer::new(
+                app_state,
+                model_mapper,
+            )?)
             .layer(crate::middleware::mapper::Layer::new(converter_registry))
             // other middleware: rate limiting, logging, etc, etc
             // will be added here as well
@@ -144,7 +169,10 @@ impl Dispatcher {
             rate_limit_tx: None,
         };
         let model_mapper = ModelMapper::new(app_state.clone());
-        let converter_registry = EndpointConverterRegistry::new(&model_mapper);
+        let converter_registry = EndpointConverterRegistry::new(
+            &model_mapper,
+            &app_state.providers(),
+        );
 
         let extensions_layer = AddExtensionsLayer::builder()
             .inference_provider(provider.clone())
@@ -153,7 +181,15 @@ impl Dispatcher {
 
         Ok(ServiceBuilder::new()
             .layer(extensions_layer)
-            .layer(ErrorHandlerLayer::new(app_state))
+            .layer(ErrorHandlerLayer::new(app_state.clone()))
+            .layer(crate::middleware::embeddings::Layer::new(
+                app_state.clone(),
+                model_mapper.clone(),
+            ))
+            .layer(crate::middleware::multimodal::Layer::new(
+                app_state,
+                model_mapper,
+            )?)
             .layer(crate::middleware::mapper::Layer::new(converter_registry))
             // other middleware: rate limiting, logging, etc, etc
             // will be added here as well
@@ -199,13 +235,16 @@ impl Service<Request> for Dispatcher {
         Poll::Ready(Ok(()))
     }
 
-    #[tracing::instrument(name = "dispatcher", skip_all)]
+    #[tracing::instrument(name = "dispatcher", skip_all, fields(provider = %self.provider))]
     fn call(&mut self, req: Request) -> Self::Future {
         // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
         let this = self.clone();
         let this = std::mem::replace(self, this);
         tracing::trace!(provider = ?this.provider, "dispatcher received request");
-        Box::pin(async move { this.dispatch(req).await })
+        Box::pin(
+            async move { this.dispatch(req).await }
+                .instrument(tracing::Span::current()),
+        )
     }
 }
 
@@ -228,9 +267,28 @@ impl Dispatcher {
             request_kind,
             prompt_ctx,
         ) = Self::extract_request_context(&mut req)?;
+        let queue = req.extensions().get::<RequestQueue>().cloned();
+        let priority = req
+            .extensions()
+            .get::<Priority>()
+            .copied()
+            .unwrap_or_default();
 
         let auth_ctx = req_ctx.auth_context.as_ref();
         let target_provider = &self.provider;
+        // providers can be disabled at runtime with the admin API, load
+        // balanced routers only stop routing to them at the next health check
+        if self
+            .app_state
+            .providers()
+            .get(target_provider)
+            .is_some_and(|config| !config.enabled)
+        {
+            return Err(InvalidRequestError::ProviderDisabled(
+                target_provider.clone(),
+            )
+            .into());
+        }
         {
             let h = req.headers_mut();
             h.remove(http::header::HOST);
@@ -243,6 +301,7 @@ impl Dispatcher {
                 http::header::ACCEPT_ENCODING,
                 HeaderValue::from_static("identity"),
             );
+            telemetry::tracing::inject_current_context(h);
         }
         let method = req.method().clone();
         let headers = req.headers().clone();
@@ -250,6 +309,8 @@ impl Dispatcher {
             &req_ctx,
             target_provider,
             extracted_path_and_query.as_str(),
+            api_endpoint.as_ref(),
+            mapper_ctx.model.as_ref(),
         )?;
         // TODO: could change request type of dispatcher to
         // http::Request<reqwest::Body>
@@ -260,6 +321,28 @@ impl Dispatcher {
             .await
             .map_err(|e| InternalError::RequestBodyError(Box::new(e)))?
             .to_bytes();
+        if let Some(model) = mapper_ctx.model.as_ref() {
+            self.check_provider_limits(
+                model,
+                &req_body_bytes,
+                api_endpoint.as_ref(),
+            )
+            .await?;
+        }
+        self.app_state.0.budgets.check(
+            auth_ctx
+                .map(|auth_ctx| hash_key(auth_ctx.api_key.expose()))
+                .as_deref(),
+            router_id.as_ref(),
+            target_provider,
+            Utc::now(),
+        )?;
+        let permit = match &queue {
+            Some(queue) => {
+                Some(queue.acquire(target_provider, priority).await?)
+            }
+            None => None,
+        };
 
         let request_builder = self
             .client
@@ -279,6 +362,12 @@ impl Dispatcher {
             .await?;
 
         let metrics_for_stream = self.app_state.0.endpoint_metrics.clone();
+        let retry_policy = get_retry_policy(
+            &self.app_state,
+            request_kind,
+            &req_ctx,
+            target_provider,
+        );
         if let Some(ref api_endpoint) = api_endpoint {
             let endpoint_metrics = self
                 .app_state
@@ -294,24 +383,24 @@ impl Dispatcher {
             oneshot::Receiver<()>,
         ) = if mapper_ctx.is_stream {
             dispatch_stream_with_retry(
-                &self.app_state,
                 request_builder,
                 req_body_bytes.clone(),
                 api_endpoint.clone(),
                 metrics_for_stream,
-                &req_ctx,
-                request_kind,
+                retry_policy,
             )
             .await?
         } else {
-            self.dispatch_sync_with_retry(
+            Self::dispatch_sync_with_retry(
                 request_builder,
                 req_body_bytes.clone(),
-                &req_ctx,
-                request_kind,
+                retry_policy,
             )
             .instrument(info_span!("dispatch_sync"))
-            .await?
+            .await
+            .inspect_err(|e| {
+                self.record_transport_error(e, api_endpoint.as_ref());
+            })?
         };
         tracing::info!(
             method = %method,
@@ -357,6 +446,15 @@ impl Dispatcher {
         )
         .await?;
 
+        if !mapper_ctx.is_stream
+            && response_status.is_success()
+            && self.app_state.response_headers_config().cost
+        {
+            client_response = self
+                .add_request_cost(client_response, mapper_ctx.model.as_ref())
+                .await?;
+        }
+
         // Handle logging
         self.handle_logging(
             &req_ctx,
@@ -374,6 +472,11 @@ impl Dispatcher {
             prompt_ctx,
         );
 
+        if let Some(permit) = permit {
+            client_response =
+                client_response.map(|body| hold_permit(body, permit));
+        }
+
         Ok(client_response)
     }
 
@@ -458,6 +561,69 @@ impl Dispatcher {
         ))
     }
 
+    /// Enforce the configured limits of the provider before sending it the
+    /// request.
+    ///
+    /// Exceeding them is signaled to the rate limit monitor like a rate limit
+    /// of the provider itself, so that load balanced routers stop sending
+    /// requests to the provider until it is available again.
+    async fn check_provider_limits(
+        &self,
+        model: &ModelId,
+        body: &Bytes,
+        api_endpoint: Option<&ApiEndpoint>,
+    ) -> Result<(), ApiError> {
+        let Some(limits) = self
+            .app_state
+            .providers()
+            .get(&self.provider)
+            .map(|config| config.model_limits(model))
+        else {
+            return Ok(());
+        };
+        let result = self.app_state.0.provider_rate_limits.check(
+            &self.provider,
+            model,
+            limits,
+            body,
+        );
+        if let Err(ApiError::InvalidRequest(
+            InvalidRequestError::TooManyRequests(error),
+        )) = &result
+            && let Some(api_endpoint) = api_endpoint
+            && let Some(rate_limit_tx) = &self.rate_limit_tx
+            && let Err(e) = rate_limit_tx
+                .send(RateLimitEvent::new(
+                    api_endpoint.clone(),
+                    Some(error.retry_after),
+                ))
+                .await
+        {
+            tracing::error!(error = %e, "failed to send rate limit event");
+        }
+        result
+    }
+
+    /// Timeouts and failed connections never produce a response, but should
+    /// still count against the health of the provider.
+    fn record_transport_error(
+        &self,
+        error: &ApiError,
+        api_endpoint: Option<&ApiEndpoint>,
+    ) {
+        if let ApiError::Internal(InternalError::ReqwestError(error)) = error
+            && (error.is_timeout() || error.is_connect())
+            && let Some(api_endpoint) = api_endpoint
+            && let Ok(endpoint_metrics) = self
+                .app_state
+                .0
+                .endpoint_metrics
+                .health_metrics(api_endpoint.clone())
+        {
+            endpoint_metrics.incr_remote_internal_error_count();
+        }
+    }
+
     /// Handles error responses and rate limiting
     async fn handle_error_and_rate_limiting(
         &self,
@@ -500,6 +666,33 @@ impl Dispatcher {
         Ok(())
     }
 
+    /// Buffer a non-streaming response to add the [`RequestCost`] of the
+    /// request to its extensions, which is sent in the response headers.
+    async fn add_request_cost(
+        &self,
+        response: http::Response<crate::types::body::Body>,
+        model: Option<&ModelId>,
+    ) -> Result<http::Response<crate::types::body::Body>, ApiError> {
+        let (mut parts, body) = response.into_parts();
+        let body = body
+            .collect()
+            .await
+            .map_err(InternalError::CollectBodyError)?
+            .to_bytes();
+        if let Some(usage) = Usage::from_response_body(&body) {
+            parts.extensions.insert(RequestCost::new(
+                &self.app_state.config().pricing,
+                &self.provider,
+                model,
+                usage,
+            ));
+        }
+        Ok(http::Response::from_parts(
+            parts,
+            crate::types::body::Body::from(body),
+        ))
+    }
+
     /// Handles logging logic for both observability and metrics
     #[allow(clippy::too_many_arguments)]
     fn handle_logging(
@@ -563,15 +756,72 @@ impl Dispatcher {
                 std::string::ToString::to_string,
             );
             let path = target_url.path().to_string();
+            let provider = self.provider.clone();
             let provider_string = self.provider.to_string();
+            let router_id_string = router_id
+                .as_ref()
+                .map_or_else(|| "none".to_string(), ToString::to_string);
+            let auth_ctx = req_ctx.auth_context.clone();
+            let model_id = mapper_ctx.model.clone();
+            let response_status = client_response.status();
+            let is_stream = mapper_ctx.is_stream;
             tokio::spawn(
                     async move {
                         let tfft_future = TFFTFuture::new(start_instant, tfft_rx);
                         let collect_future = response_body_for_logger.collect();
-                        let (_response_body, tfft_duration) = tokio::join!(collect_future, tfft_future);
+                        let (response_body, tfft_duration) = tokio::join!(collect_future, tfft_future);
+                        let response_body = response_body
+                            .expect("infallible never errors")
+                            .to_bytes();
+                        let cost = if response_status.is_success() {
+                            app_state.0.metering.record(
+                                &app_state.config().pricing,
+                                auth_ctx.as_ref(),
+                                &provider,
+                                model_id.as_ref(),
+                                &response_body,
+                            )
+                        } else {
+                            None
+                        };
+                        if let Some(cost) = cost {
+                            app_state.record_tokens(router_id.as_ref(), &provider, model_id.as_ref(), &cost.usage);
+                            if let Some(cost_usd) = cost.cost_usd {
+                                app_state.add_spend(
+                                    auth_ctx.as_ref(),
+                                    router_id.as_ref(),
+                                    &provider,
+                                    cost_usd,
+                                ).await;
+                            }
+                        }
+                        if let Some(log_sink) = &app_state.0.log_sink {
+                            log_sink.send(
+                                LogRecord::builder()
+                                    .request_id(helicone_request_id)
+                                    .request_created_at(start_time)
+                                    .router_id(router_id)
+                                    .provider(provider.clone())
+                                    .model(model_id.as_ref().map(ToString::to_string))
+                                    .path(path.clone())
+                                    .status(response_status.as_u16())
+                                    .is_stream(is_stream)
+                                    .latency(start_instant.elapsed())
+                                    .ttft(tfft_duration.as_ref().ok().copied())
+                                    .api_key_hash(auth_ctx.as_ref().map(|auth_ctx| {
+                                        hash_key(auth_ctx.api_key.expose())
+                                    }))
+                                    .usage(cost.map(|cost| cost.usage))
+                                    .cost_usd(cost.and_then(|cost| cost.cost_usd))
+                                    .request_body(req_body_bytes)
+                                    .response_body(response_body)
+                                    .build(),
+                            );
+                        }
                         if let Ok(tfft_duration) = tfft_duration {
                             tracing::trace!(tfft_duration = ?tfft_duration, "tfft_duration");
                             let attributes = [
+                                KeyValue::new("router_id", router_id_string),
                                 KeyValue::new("provider", provider_string),
                                 KeyValue::new("model", model),
                                 KeyValue::new("path", path),
@@ -590,25 +840,33 @@ impl Dispatcher {
         req_ctx: &RequestContext,
         target_provider: &InferenceProvider,
         extracted_path_and_query: &str,
+        api_endpoint: Option<&ApiEndpoint>,
+        model: Option<&ModelId>,
     ) -> Result<url::Url, ApiError> {
-        let config = self.app_state.config();
         if let Some(router_config) = req_ctx.router_config.as_ref()
             && let Some(router_provider_config) =
                 router_config.providers.as_ref()
-            && let Some(provider_config) =
-                router_provider_config.get(target_provider)
+            && let Some(base_url) = router_provider_config
+                .get(target_provider)
+                .and_then(|provider_config| provider_config.base_url.as_ref())
         {
-            return Ok(provider_config
-                .base_url
-                .join(extracted_path_and_query)
-                .expect(
-                    "PathAndQuery joined with valid url will always succeed",
-                ));
+            return Ok(base_url.join(extracted_path_and_query).expect(
+                "PathAndQuery joined with valid url will always succeed",
+            ));
         }
+        let providers = self.app_state.providers();
         let provider_config =
-            config.providers.get(target_provider).ok_or_else(|| {
+            providers.get(target_provider).ok_or_else(|| {
                 InternalError::ProviderNotConfigured(target_provider.clone())
             })?;
+        if provider_config.deployments
+            && api_endpoint.is_some_and(|api_endpoint| {
+                api_endpoint.endpoint_type() == EndpointType::Chat
+            })
+            && let Some(model) = model
+        {
+            return Ok(provider_config.deployment_url(model));
+        }
         Ok(provider_config
             .base_url
             .join(extracted_path_and_query)
@@ -638,13 +896,38 @@ impl Dispatcher {
             );
             ApiError::Internal(InternalError::Internal)
         })?;
-        let response_stream = Client::sse_stream(
-            request_builder,
-            req_body_bytes,
-            api_endpoint,
-            &metrics_registry,
-        )
-        .await?;
+        let response_stream = match api_endpoint {
+            Some(ApiEndpoint::Bedrock(_)) => {
+                Client::aws_event_stream(
+                    request_builder,
+                    req_body_bytes,
+                    api_endpoint,
+                    &metrics_registry,
+                )
+                .await?
+            }
+            Some(
+                ApiEndpoint::Ollama(Ollama::Chat(_))
+                | ApiEndpoint::OllamaCompatible { .. },
+            ) => {
+                Client::ndjson_stream(
+                    request_builder,
+                    req_body_bytes,
+                    api_endpoint,
+                    &metrics_registry,
+                )
+                .await?
+            }
+            _ => {
+                Client::sse_stream(
+                    request_builder,
+                    req_body_bytes,
+                    api_endpoint,
+                    &metrics_registry,
+                )
+                .await?
+            }
+        };
         let mut resp_builder = http::Response::builder();
         *resp_builder.headers_mut().unwrap() = stream_response_headers();
         resp_builder = resp_builder.status(StatusCode::OK);
@@ -687,20 +970,23 @@ impl Dispatcher {
         let mut resp_builder = http::Response::builder().status(status);
         *resp_builder.headers_mut().unwrap() = response.headers().clone();
 
-        // this is compiled out in release builds
-        #[cfg(debug_assertions)]
+        // error responses are small, so they are collected to check their
+        // error codes against the retry policy
         if status.is_server_error() || status.is_client_error() {
-            let body =
-                response.text().await.map_err(InternalError::ReqwestError)?;
-            tracing::debug!(status_code = %status, error_resp = %body, "received error response");
-            let bytes = bytes::Bytes::from(body);
-            let stream = futures::stream::once(futures::future::ok::<
-                _,
-                ApiError,
-            >(bytes));
+            let body = response
+                .bytes()
+                .await
+                .map_err(InternalError::ReqwestError)?;
+            // this is compiled out in release builds
+            #[cfg(debug_assertions)]
+            tracing::debug!(status_code = %status, error_resp = %String::from_utf8_lossy(&body), "received error response");
+            let error_codes = UpstreamErrorCodes::from_body(&body);
+            let stream =
+                futures::stream::once(futures::future::ok::<_, ApiError>(body));
             let (error_body, error_reader, tfft_rx) =
                 BodyReader::wrap_stream(stream, false);
             let response = resp_builder
+                .extension(error_codes)
                 .body(error_body)
                 .map_err(InternalError::HttpError)?;
 
@@ -719,13 +1005,10 @@ impl Dispatcher {
         Ok((response, body_reader, tfft_rx))
     }
 
-    #[allow(clippy::too_many_lines)]
     async fn dispatch_sync_with_retry(
-        &self,
         request_builder: RequestBuilder,
         req_body_bytes: Bytes,
-        req_ctx: &RequestContext,
-        request_kind: RequestKind,
+        retry_policy: Option<&RetryPolicy>,
     ) -> Result<
         (
             http::Response<crate::types::body::Body>,
@@ -734,126 +1017,52 @@ impl Dispatcher {
         ),
         ApiError,
     > {
-        let retry_config =
-            get_retry_config(&self.app_state, request_kind, req_ctx);
-        if let Some(retry_config) = retry_config {
-            match retry_config {
-                RetryConfig::Exponential {
-                    min_delay,
-                    max_delay,
-                    max_retries,
-                    factor,
-                } => {
-                    let retry_strategy = ExponentialBuilder::default()
-                        .with_max_delay(*max_delay)
-                        .with_min_delay(*min_delay)
-                        .with_max_times(usize::from(*max_retries))
-                        .with_factor(factor.to_f32().unwrap_or(
-                            crate::config::retry::DEFAULT_RETRY_FACTOR,
-                        ))
-                        .with_jitter()
-                        .build();
-                    let future_fn = || async {
-                        let result = Self::dispatch_sync(
-                            &request_builder,
-                            req_body_bytes.clone(),
-                        )
-                        .await?;
-
-                        Ok(result)
-                    };
-
-                    crate::utils::retry::RetryWithResult::new(future_fn, retry_strategy)
-                    .when(|result: &Result<_, _>| match result {
-                        Ok(response) => response.0.status().is_server_error(),
-                        Err(e) => match e {
-                            ApiError::Internal(InternalError::ReqwestError(
-                                reqwest_error,
-                            )) => reqwest_error.is_connect() || reqwest_error.status().is_some_and(|s| s.is_server_error()),
-                            _ => false,
-                        },
-                    })
-                    .notify(|result: &Result<_, _>, dur: Duration| match result {
-                        Ok(result) if result.0.status().is_server_error() => {
-                                tracing::warn!(
-                                    error = %result.0.status(),
-                                    retry_in = ?dur,
-                                    "got error dispatching sync request, retrying...",
-                                );
-                        }
-                        Err(ApiError::Internal(InternalError::ReqwestError(
-                            reqwest_error,
-                        ))) if reqwest_error.is_connect() || reqwest_error.status().is_some_and(|s| s.is_server_error()) => {
-                                tracing::warn!(
-                                    error = %reqwest_error,
-                                    retry_in = ?dur,
-                                    "got error dispatching sync request, retrying...",
-                                );
-                            }
-                        _ => {}
-                    })
-                    .await
+        let Some(retry_policy) = retry_policy else {
+            return Self::dispatch_sync(&request_builder, req_body_bytes).await;
+        };
+        let future_fn =
+            || Self::dispatch_sync(&request_builder, req_body_bytes.clone());
+        RetryWithResult::new(future_fn, retry_policy.backoff.as_iterator())
+            .when(|result: &Result<_, _>| match result {
+                Ok((response, ..)) => {
+                    is_retryable_response(retry_policy, response)
                 }
-                RetryConfig::Constant { delay, max_retries } => {
-                    let retry_strategy = ConstantBuilder::default()
-                        .with_delay(*delay)
-                        .with_max_times(usize::from(*max_retries))
-                        .with_jitter()
-                        .build();
-                    let future_fn = || async {
-                        Self::dispatch_sync(
-                            &request_builder,
-                            req_body_bytes.clone(),
-                        )
-                        .await
-                    };
-
-                    crate::utils::retry::RetryWithResult::new(future_fn, retry_strategy)
-                    .when(|result: &Result<_, _>| match result {
-                        Ok(response) => response.0.status().is_server_error(),
-                        Err(e) => match e {
-                            ApiError::Internal(InternalError::ReqwestError(
-                                reqwest_error,
-                            )) => reqwest_error.is_connect() || reqwest_error.status().is_some_and(|s| s.is_server_error()),
-                            _ => false,
-                        },
-                    })
-                    .notify(|result: &Result<_, _>, dur: Duration| match result {
-                        Ok(result) if result.0.status().is_server_error() => {
-                                tracing::warn!(
-                                    error = %result.0.status(),
-                                    retry_in = ?dur,
-                                    "got error dispatching sync request, retrying...",
-                                );
-                        }
-                        Err(ApiError::Internal(InternalError::ReqwestError(
-                            reqwest_error,
-                        ))) if reqwest_error.is_connect() || reqwest_error.status().is_some_and(|s| s.is_server_error()) => {
-                                tracing::warn!(
-                                    error = %reqwest_error,
-                                    retry_in = ?dur,
-                                    "got error dispatching sync request, retrying...",
-                                );
-                            }
-                        _ => {}
-                    })
-                    .await
+                Err(e) => is_retryable_transport_error(e),
+            })
+            .adjust(|result: &Result<_, _>, backoff| {
+                let retry_after =
+                    result.as_ref().ok().and_then(|(response, ..)| {
+                        extract_retry_after(response.headers())
+                    });
+                retry_policy
+                    .next_delay(backoff, retry_after.map(Duration::from_secs))
+            })
+            .notify(|result: &Result<_, _>, dur: Duration| match result {
+                Ok((response, ..)) => {
+                    tracing::warn!(
+                        error = %response.status(),
+                        retry_in = ?dur,
+                        "got error dispatching sync request, retrying...",
+                    );
                 }
-            }
-        } else {
-            Self::dispatch_sync(&request_builder, req_body_bytes.clone()).await
-        }
+                Err(e) => {
+                    tracing::warn!(
+                        error = %e,
+                        retry_in = ?dur,
+                        "got error dispatching sync request, retrying...",
+                    );
+                }
+            })
+            .await
     }
 }
 
 async fn dispatch_stream_with_retry(
-    app_state: &AppState,
     request_builder: RequestBuilder,
     req_body_bytes: Bytes,
     api_endpoint: Option<ApiEndpoint>,
     metrics_registry: EndpointMetricsRegistry,
-    request_ctx: &RequestContext,
-    request_kind: RequestKind,
+    retry_policy: Option<&RetryPolicy>,
 ) -> Result<
     (
         http::Response<crate::types::body::Body>,
@@ -862,93 +1071,133 @@ async fn dispatch_stream_with_retry(
     ),
     ApiError,
 > {
-    let retry_config = get_retry_config(app_state, request_kind, request_ctx);
-
-    if let Some(retry_config) = retry_config {
-        match retry_config {
-            RetryConfig::Exponential {
-                min_delay,
-                max_delay,
-                max_retries,
-                factor,
-            } => {
-                let retry_strategy =
-                    ExponentialBuilder::default()
-                        .with_max_delay(*max_delay)
-                        .with_min_delay(*min_delay)
-                        .with_max_times(usize::from(*max_retries))
-                        .with_factor(factor.to_f32().unwrap_or(
-                            crate::config::retry::DEFAULT_RETRY_FACTOR,
-                        ))
-                        .with_jitter()
-                        .build();
-                (|| async {
-                    Dispatcher::dispatch_stream(
-                        &request_builder,
-                        req_body_bytes.clone(),
-                        api_endpoint.clone(),
-                        metrics_registry.clone(),
-                    )
-                    .await
-                })
-                .retry(retry_strategy)
-                .sleep(tokio::time::sleep)
-                .when(|e: &ApiError| match e {
-                    ApiError::StreamError(s) => s.is_retryable(),
-                    _ => false,
-                })
-                .notify(|err: &ApiError, dur: Duration| {
-                    if let ApiError::StreamError(_s) = err {
-                        tracing::warn!(
-                            error = %err,
-                            retry_in = ?dur,
-                            "upstream server error in stream, retrying...",
-                        );
-                    }
-                })
-                .await
-            }
-            RetryConfig::Constant { delay, max_retries } => {
-                let retry_strategy = ConstantBuilder::default()
-                    .with_delay(*delay)
-                    .with_max_times(usize::from(*max_retries))
-                    .with_jitter()
-                    .build();
-                (|| async {
-                    Dispatcher::dispatch_stream(
-                        &request_builder,
-                        req_body_bytes.clone(),
-                        api_endpoint.clone(),
-                        metrics_registry.clone(),
-                    )
-                    .await
-                })
-                .retry(retry_strategy)
-                .sleep(tokio::time::sleep)
-                .when(|e: &ApiError| match e {
-                    ApiError::StreamError(s) => s.is_retryable(),
-                    _ => false,
-                })
-                .notify(|err: &ApiError, dur: Duration| {
-                    if let ApiError::StreamError(_s) = err {
-                        tracing::warn!(
-                            error = %err,
-                            retry_in = ?dur,
-                            "upstream server error in stream, retrying...",
-                        );
-                    }
-                })
-                .await
-            }
-        }
-    } else {
-        Dispatcher::dispatch_stream(
+    let Some(retry_policy) = retry_policy else {
+        return Dispatcher::dispatch_stream(
             &request_builder,
-            req_body_bytes.clone(),
+            req_body_bytes,
             api_endpoint,
             metrics_registry,
         )
+        .await;
+    };
+    let future_fn = || {
+        Dispatcher::dispatch_stream(
+            &request_builder,
+            req_body_bytes.clone(),
+            api_endpoint.clone(),
+            metrics_registry.clone(),
+        )
+    };
+    RetryWithResult::new(future_fn, retry_policy.backoff.as_iterator())
+        .when(|result: &Result<_, _>| match result {
+            Err(ApiError::StreamError(e)) => {
+                is_retryable_stream_error(retry_policy, e)
+            }
+            _ => false,
+        })
+        .adjust(|result: &Result<_, _>, backoff| {
+            let retry_after = match result {
+                Err(ApiError::StreamError(StreamError::StreamError(e))) => {
+                    match &**e {
+                        reqwest_eventsource::Error::InvalidStatusCode(
+                            _,
+                            response,
+                        ) => extract_retry_after(response.headers()),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+            retry_policy
+                .next_delay(backoff, retry_after.map(Duration::from_secs))
+        })
+        .notify(|result: &Result<_, _>, dur: Duration| {
+            if let Err(err) = result {
+                tracing::warn!(
+                    error = %err,
+                    retry_in = ?dur,
+                    "upstream server error in stream, retrying...",
+                );
+            }
+        })
         .await
+}
+
+/// The error codes of an upstream error response, see
+/// [`RetryPolicy::retryable_error_codes`].
+#[derive(Debug, Clone, Default)]
+pub(super) struct UpstreamErrorCodes(Vec<String>);
+
+impl UpstreamErrorCodes {
+    /// The `code`, `type` and `status` of the error object of the body, e.g.
+    /// `overloaded_error` for `{"error": {"type": "overloaded_error"}}`.
+    pub(super) fn from_body(body: &[u8]) -> Self {
+        let Ok(body) = serde_json::from_slice::<serde_json::Value>(body) else {
+            return Self::default();
+        };
+        let error = body.get("error").unwrap_or(&body);
+        let codes = ["code", "type", "status"]
+            .into_iter()
+            .filter_map(|field| match error.get(field)? {
+                serde_json::Value::String(code) => Some(code.clone()),
+                serde_json::Value::Number(code) => Some(code.to_string()),
+                _ => None,
+            })
+            .collect();
+        Self(codes)
+    }
+
+    fn is_retryable(&self, retry_policy: &RetryPolicy) -> bool {
+        self.0
+            .iter()
+            .any(|code| retry_policy.is_retryable_error_code(code))
+    }
+}
+
+fn is_retryable_response(
+    retry_policy: &RetryPolicy,
+    response: &http::Response<crate::types::body::Body>,
+) -> bool {
+    if response.status().is_success() {
+        return false;
+    }
+    retry_policy.is_retryable_status(response.status())
+        || response
+            .extensions()
+            .get::<UpstreamErrorCodes>()
+            .is_some_and(|codes| codes.is_retryable(retry_policy))
+}
+
+fn is_retryable_transport_error(error: &ApiError) -> bool {
+    match error {
+        ApiError::Internal(InternalError::ReqwestError(reqwest_error)) => {
+            reqwest_error.is_connect()
+                || reqwest_error.status().is_some_and(|s| s.is_server_error())
+        }
+        _ => false,
+    }
+}
+
+fn is_retryable_stream_error(
+    retry_policy: &RetryPolicy,
+    error: &StreamError,
+) -> bool {
+    match error {
+        StreamError::StreamError(e) => match &**e {
+            reqwest_eventsource::Error::InvalidStatusCode(status, response) => {
+                retry_policy.is_retryable_status(*status)
+                    || response
+                        .extensions()
+                        .get::<UpstreamErrorCodes>()
+                        .is_some_and(|codes| codes.is_retryable(retry_policy))
+            }
+            _ => error.is_retryable(),
+        },
+        StreamError::BodyError(_) => false,
+        StreamError::Exception { exception_type, .. } => {
+            error.is_retryable()
+                || retry_policy.is_retryable_error_code(exception_type)
+        }
     }
 }
 
@@ -981,6 +1230,20 @@ fn extract_retry_after(headers: &HeaderMap) -> Option<u64> {
     None
 }
 
+/// Keeps the queue slot of a request until its response body is done or
+/// dropped, so that streamed responses count as in flight too.
+fn hold_permit(
+    body: crate::types::body::Body,
+    permit: Permit,
+) -> crate::types::body::Body {
+    crate::types::body::Body::from_stream(body.into_data_stream().map(
+        move |chunk| {
+            let _permit = &permit;
+            chunk
+        },
+    ))
+}
+
 fn stream_response_headers() -> HeaderMap {
     HeaderMap::from_iter([
         (
@@ -998,15 +1261,25 @@ fn stream_response_headers() -> HeaderMap {
     ])
 }
 
-fn get_retry_config<'a>(
+/// The retry policy of the provider in the router, falling back to the
+/// policy of the router, or of the unified API for its requests.
+fn get_retry_policy<'a>(
     app_state: &'a AppState,
     request_kind: RequestKind,
     req_ctx: &'a RequestContext,
-) -> Option<&'a RetryConfig> {
+    provider: &InferenceProvider,
+) -> Option<&'a RetryPolicy> {
     match request_kind {
         RequestKind::Router => {
             if let Some(router_config) = req_ctx.router_config.as_ref() {
-                router_config.retries.as_ref()
+                router_config
+                    .providers
+                    .as_ref()
+                    .and_then(|providers| providers.get(provider))
+                    .and_then(|provider_config| {
+                        provider_config.retries.as_ref()
+                    })
+                    .or(router_config.retries.as_ref())
             } else {
                 app_state.config().global.retries.as_ref()
             }
@@ -18,6 +18,28 @@ impl Endpoint for Messages {
     type ResponseBody = CreateMessageResponse;
     type StreamResponseBody = message::StreamEvent;
     type ErrorResponseBody = AnthropicApiError;
+
+    fn stream_event_name(
+        chunk: &Self::StreamResponseBody,
+    ) -> Option<&'static str> {
+        let name = match chunk {
+            message::StreamEvent::MessageStart { .. } => "message_start",
+            message::StreamEvent::ContentBlockStart { .. } => {
+                "content_block_start"
+            }
+            message::StreamEvent::ContentBlockDelta { .. } => {
+                "content_block_delta"
+            }
+            message::StreamEvent::ContentBlockStop { .. } => {
+                "content_block_stop"
+            }
+            message::StreamEvent::MessageDelta { .. } => "message_delta",
+            message::StreamEvent::MessageStop => "message_stop",
+            message::StreamEvent::Ping => "ping",
+            message::StreamEvent::Error { .. } => "error",
+        };
+        Some(name)
+    }
 }
 
 impl AiRequest for CreateMessageParams {
@@ -33,6 +55,9 @@ impl AiRequest for CreateMessageParams {
     }
 }
 
+/// The `type` of the top level object in an Anthropic error response.
+pub(crate) const ANTHROPIC_ERROR_TYPE: &str = "error";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AnthropicApiError {
     pub error: ErrorDetails,
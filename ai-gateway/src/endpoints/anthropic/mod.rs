@@ -2,6 +2,9 @@ pub mod messages;
 
 use super::{Endpoint, EndpointType};
 pub use crate::endpoints::anthropic::messages::Messages;
+use crate::{
+    endpoints::EndpointRoute, error::invalid_req::InvalidRequestError,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumIter)]
 pub enum Anthropic {
@@ -28,3 +31,18 @@ impl Anthropic {
         }
     }
 }
+
+impl TryFrom<&EndpointRoute> for Anthropic {
+    type Error = InvalidRequestError;
+
+    fn try_from(endpoint: &EndpointRoute) -> Result<Self, Self::Error> {
+        match endpoint {
+            EndpointRoute::Messages => Ok(Self::Messages(Messages)),
+            EndpointRoute::ChatCompletions | EndpointRoute::Embeddings => {
+                Err(InvalidRequestError::UnsupportedEndpoint(
+                    endpoint.path().to_string(),
+                ))
+            }
+        }
+    }
+}
@@ -12,6 +12,17 @@ pub enum Bedrock {
 impl Bedrock {
     #[must_use]
     pub fn path(self, model_id: &ModelId, is_stream: bool) -> String {
+        // ARNs contain a `/`, so they need to be encoded to fit in a single
+        // path segment
+        let model_id = match model_id {
+            ModelId::Bedrock(bedrock) if bedrock.is_arn() => {
+                url::form_urlencoded::byte_serialize(
+                    model_id.to_string().as_bytes(),
+                )
+                .collect::<String>()
+            }
+            _ => model_id.to_string(),
+        };
         match self {
             Self::Converse(_) => {
                 if is_stream {
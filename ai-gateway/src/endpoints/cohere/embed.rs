@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+use super::COHERE;
+use crate::{
+    endpoints::{AiRequest, Endpoint},
+    error::mapper::MapperError,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+/// The most texts Cohere accepts in a single embed request.
+pub(crate) const MAX_INPUTS: usize = 96;
+/// OpenAI doesn't distinguish documents from queries, so everything is
+/// embedded as a document to be searched.
+pub(crate) const SEARCH_DOCUMENT_INPUT_TYPE: &str = "search_document";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Embed;
+
+impl Endpoint for Embed {
+    // https://docs.cohere.com/reference/embed
+    const PATH: &'static str = "v2/embed";
+    type RequestBody = EmbedRequest;
+    type ResponseBody = EmbedResponse;
+    type StreamResponseBody = EmbedResponse;
+    type ErrorResponseBody = CohereApiError;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmbedRequest {
+    pub model: String,
+    pub texts: Vec<String>,
+    /// Required by v3 and later models, e.g. `search_document` or
+    /// `search_query`.
+    pub input_type: String,
+    pub embedding_types: Vec<EmbeddingType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_dimension: Option<u32>,
+}
+
+impl AiRequest for EmbedRequest {
+    fn is_stream(&self) -> bool {
+        false
+    }
+
+    fn model(&self) -> Result<ModelId, MapperError> {
+        ModelId::from_str_and_provider(
+            InferenceProvider::Named(COHERE.into()),
+            &self.model,
+        )
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddingType {
+    #[default]
+    Float,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EmbedResponse {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub embeddings: Embeddings,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Meta>,
+}
+
+/// The embeddings of each requested [`EmbeddingType`], in the order of the
+/// texts.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Embeddings {
+    #[serde(default)]
+    pub float: Vec<Vec<f32>>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Meta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub billed_units: Option<BilledUnits>,
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct BilledUnits {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_tokens: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CohereApiError {
+    #[serde(default)]
+    pub message: String,
+}
@@ -0,0 +1,34 @@
+pub(crate) mod embed;
+
+use super::{Endpoint, EndpointType};
+pub(crate) use crate::endpoints::cohere::embed::Embed;
+
+/// Cohere is configured like any other named provider, but is only supported
+/// for embeddings, through its native API.
+pub const COHERE: &str = "cohere";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumIter)]
+pub enum Cohere {
+    Embed(Embed),
+}
+
+impl Cohere {
+    #[must_use]
+    pub fn path(&self) -> &str {
+        match self {
+            Self::Embed(_) => Embed::PATH,
+        }
+    }
+
+    #[must_use]
+    pub fn embed() -> Self {
+        Self::Embed(Embed)
+    }
+
+    #[must_use]
+    pub fn endpoint_type(&self) -> EndpointType {
+        match self {
+            Self::Embed(_) => EndpointType::Embeddings,
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use serde::{Deserialize, Serialize};
+
+use super::generate_contents::{Content, GeminiApiError};
+use crate::{
+    endpoints::{AiRequest, Endpoint},
+    error::mapper::MapperError,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+/// The most requests Gemini accepts in a single `batchEmbedContents` call.
+pub(crate) const MAX_INPUTS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct BatchEmbedContents;
+
+impl Endpoint for BatchEmbedContents {
+    // https://ai.google.dev/api/embeddings#method:-models.batchembedcontents
+    const PATH: &'static str = "v1beta/models/{model}:batchEmbedContents";
+    type RequestBody = BatchEmbedContentsRequest;
+    type ResponseBody = BatchEmbedContentsResponse;
+    type StreamResponseBody = BatchEmbedContentsResponse;
+    type ErrorResponseBody = GeminiApiError;
+}
+
+/// A `batchEmbedContents` request.
+///
+/// The model of the batch is part of the path, but every request of the
+/// batch names it again as `models/{model}`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatchEmbedContentsRequest {
+    #[serde(skip)]
+    pub model: String,
+    pub requests: Vec<EmbedContentRequest>,
+}
+
+impl AiRequest for BatchEmbedContentsRequest {
+    fn is_stream(&self) -> bool {
+        false
+    }
+
+    fn model(&self) -> Result<ModelId, MapperError> {
+        ModelId::from_str_and_provider(
+            InferenceProvider::GoogleGemini,
+            &self.model,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbedContentRequest {
+    pub model: String,
+    pub content: Content,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_dimensionality: Option<u32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BatchEmbedContentsResponse {
+    /// In the order of the requests of the batch.
+    #[serde(default)]
+    pub embeddings: Vec<ContentEmbedding>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ContentEmbedding {
+    #[serde(default)]
+    pub values: Vec<f32>,
+}
@@ -1,18 +1,36 @@
+pub(crate) mod embed_contents;
 pub(crate) mod generate_contents;
 
-use super::{Endpoint, EndpointType};
-pub(crate) use crate::endpoints::google::generate_contents::GenerateContents;
+use super::EndpointType;
+pub(crate) use crate::endpoints::google::{
+    embed_contents::BatchEmbedContents,
+    generate_contents::{GeminiChatCompletions, GenerateContents},
+};
+use crate::types::model_id::ModelId;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumIter)]
 pub enum Google {
     GenerateContents(GenerateContents),
+    BatchEmbedContents(BatchEmbedContents),
 }
 
 impl Google {
     #[must_use]
-    pub fn path(&self) -> &str {
+    pub fn path(self, model_id: &ModelId, is_stream: bool) -> String {
         match self {
-            Self::GenerateContents(_) => GenerateContents::PATH,
+            Self::GenerateContents(_) => {
+                if is_stream {
+                    format!(
+                        "v1beta/models/{model_id}:streamGenerateContent?\
+                         alt=sse"
+                    )
+                } else {
+                    format!("v1beta/models/{model_id}:generateContent")
+                }
+            }
+            Self::BatchEmbedContents(_) => {
+                format!("v1beta/models/{model_id}:batchEmbedContents")
+            }
         }
     }
 
@@ -21,28 +39,16 @@ impl Google {
         Self::GenerateContents(GenerateContents)
     }
 
+    #[must_use]
+    pub fn batch_embed_contents() -> Self {
+        Self::BatchEmbedContents(BatchEmbedContents)
+    }
+
     #[must_use]
     pub fn endpoint_type(&self) -> EndpointType {
         match self {
             Self::GenerateContents(_) => EndpointType::Chat,
-        }
-    }
-}
-
-impl TryFrom<&str> for Google {
-    type Error = crate::error::invalid_req::InvalidRequestError;
-
-    fn try_from(path: &str) -> Result<Self, Self::Error> {
-        match path {
-            GenerateContents::PATH => {
-                Ok(Self::GenerateContents(GenerateContents))
-            }
-            path => {
-                tracing::warn!(path = %path, "unsupported Google path");
-                Err(crate::error::invalid_req::InvalidRequestError::NotFound(
-                    path.to_string(),
-                ))
-            }
+            Self::BatchEmbedContents(_) => EndpointType::Embeddings,
         }
     }
 }
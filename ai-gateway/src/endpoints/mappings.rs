@@ -1,6 +1,10 @@
-use crate::endpoints::{
-    anthropic::Anthropic, bedrock::Bedrock, google::Google, ollama::Ollama,
-    openai::OpenAI,
+use crate::{
+    endpoints::{
+        anthropic::Anthropic, bedrock::Bedrock, google::Google, ollama::Ollama,
+        openai::OpenAI,
+    },
+    error::invalid_req::InvalidRequestError,
+    types::provider::InferenceProvider,
 };
 
 impl From<Anthropic> for OpenAI {
@@ -11,10 +15,17 @@ impl From<Anthropic> for OpenAI {
     }
 }
 
-impl From<OpenAI> for Anthropic {
-    fn from(value: OpenAI) -> Self {
+impl TryFrom<OpenAI> for Anthropic {
+    type Error = InvalidRequestError;
+
+    fn try_from(value: OpenAI) -> Result<Self, Self::Error> {
         match value {
-            OpenAI::ChatCompletions(_) => Self::messages(),
+            OpenAI::ChatCompletions(_) => Ok(Self::messages()),
+            OpenAI::Embeddings(_) => {
+                Err(InvalidRequestError::UnsupportedProvider(
+                    InferenceProvider::Anthropic,
+                ))
+            }
         }
     }
 }
@@ -23,6 +34,7 @@ impl From<Google> for OpenAI {
     fn from(value: Google) -> Self {
         match value {
             Google::GenerateContents(_) => Self::chat_completions(),
+            Google::BatchEmbedContents(_) => Self::embeddings(),
         }
     }
 }
@@ -31,14 +43,22 @@ impl From<OpenAI> for Google {
     fn from(value: OpenAI) -> Self {
         match value {
             OpenAI::ChatCompletions(_) => Self::generate_contents(),
+            OpenAI::Embeddings(_) => Self::batch_embed_contents(),
         }
     }
 }
 
-impl From<OpenAI> for Ollama {
-    fn from(value: OpenAI) -> Self {
+impl TryFrom<OpenAI> for Ollama {
+    type Error = InvalidRequestError;
+
+    fn try_from(value: OpenAI) -> Result<Self, Self::Error> {
         match value {
-            OpenAI::ChatCompletions(_) => Self::chat_completions(),
+            OpenAI::ChatCompletions(_) => Ok(Self::chat_completions()),
+            OpenAI::Embeddings(_) => {
+                Err(InvalidRequestError::UnsupportedProvider(
+                    InferenceProvider::Ollama,
+                ))
+            }
         }
     }
 }
@@ -46,14 +66,23 @@ impl From<OpenAI> for Ollama {
 impl From<Ollama> for OpenAI {
     fn from(value: Ollama) -> Self {
         match value {
-            Ollama::ChatCompletions(_) => Self::chat_completions(),
+            Ollama::ChatCompletions(_) | Ollama::Chat(_) => {
+                Self::chat_completions()
+            }
         }
     }
 }
-impl From<OpenAI> for Bedrock {
-    fn from(value: OpenAI) -> Self {
+impl TryFrom<OpenAI> for Bedrock {
+    type Error = InvalidRequestError;
+
+    fn try_from(value: OpenAI) -> Result<Self, Self::Error> {
         match value {
-            OpenAI::ChatCompletions(_) => Self::converse(),
+            OpenAI::ChatCompletions(_) => Ok(Self::converse()),
+            OpenAI::Embeddings(_) => {
+                Err(InvalidRequestError::UnsupportedProvider(
+                    InferenceProvider::Bedrock,
+                ))
+            }
         }
     }
 }
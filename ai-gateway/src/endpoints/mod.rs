@@ -1,15 +1,23 @@
 pub mod anthropic;
 pub(crate) mod bedrock;
+pub mod cohere;
 pub mod google;
 pub mod mappings;
+#[cfg(feature = "axum")]
+pub mod models;
 pub mod ollama;
 pub mod openai;
 
 use serde::{Deserialize, Serialize};
 
 use crate::{
+    config::providers::SelfHostedDialect,
     endpoints::{
-        anthropic::Anthropic, bedrock::Bedrock, google::Google, ollama::Ollama,
+        anthropic::Anthropic,
+        bedrock::Bedrock,
+        cohere::{COHERE, Cohere},
+        google::Google,
+        ollama::Ollama,
         openai::OpenAI,
     },
     error::{
@@ -27,6 +35,14 @@ pub trait Endpoint {
     /// To support streaming response body types with different
     /// concrete type than the regular response body type.
     type StreamResponseBody;
+
+    /// The SSE `event` field to send along with a streamed chunk, for APIs
+    /// whose clients dispatch on it rather than on the chunk itself.
+    fn stream_event_name(
+        _chunk: &Self::StreamResponseBody,
+    ) -> Option<&'static str> {
+        None
+    }
 }
 
 macro_rules! define_endpoints {
@@ -57,6 +73,8 @@ macro_rules! define_endpoints {
 
 define_endpoints! {
     (ChatCompletions, "chat/completions"),
+    (Messages, "anthropic/v1/messages"),
+    (Embeddings, "embeddings"),
 }
 
 pub trait AiRequest {
@@ -71,17 +89,30 @@ pub enum ApiEndpoint {
     Google(Google),
     Ollama(Ollama),
     Bedrock(Bedrock),
+    Cohere(Cohere),
     OpenAICompatible {
         provider: InferenceProvider,
         openai_endpoint: OpenAI,
     },
+    /// A named self-hosted provider speaking Ollama's native API.
+    OllamaCompatible {
+        provider: InferenceProvider,
+        ollama_endpoint: Ollama,
+    },
 }
 
 impl ApiEndpoint {
     #[must_use]
     pub fn new(path: &str) -> Option<Self> {
         let endpoint_route = EndpointRoute::from_path(path)?;
-        Some(Self::OpenAI(OpenAI::try_from(&endpoint_route).ok()?))
+        match endpoint_route {
+            EndpointRoute::ChatCompletions | EndpointRoute::Embeddings => {
+                Some(Self::OpenAI(OpenAI::try_from(&endpoint_route).ok()?))
+            }
+            EndpointRoute::Messages => Some(Self::Anthropic(
+                Anthropic::try_from(&endpoint_route).ok()?,
+            )),
+        }
     }
 
     pub fn mapped(
@@ -90,7 +121,7 @@ impl ApiEndpoint {
     ) -> Result<Self, InvalidRequestError> {
         match (source_endpoint, target_provider) {
             (Self::OpenAI(source), InferenceProvider::Anthropic) => {
-                Ok(Self::Anthropic(Anthropic::from(source)))
+                Ok(Self::Anthropic(Anthropic::try_from(source)?))
             }
             (Self::OpenAI(source), InferenceProvider::OpenAI) => {
                 Ok(Self::OpenAI(source))
@@ -99,10 +130,19 @@ impl ApiEndpoint {
                 Ok(Self::Google(Google::from(source)))
             }
             (Self::OpenAI(source), InferenceProvider::Ollama) => {
-                Ok(Self::Ollama(Ollama::from(source)))
+                Ok(Self::Ollama(Ollama::try_from(source)?))
             }
             (Self::OpenAI(source), InferenceProvider::Bedrock) => {
-                Ok(Self::Bedrock(Bedrock::from(source)))
+                Ok(Self::Bedrock(Bedrock::try_from(source)?))
+            }
+            (
+                Self::OpenAI(OpenAI::Embeddings(_)),
+                InferenceProvider::Named(name),
+            ) if name == COHERE => Ok(Self::Cohere(Cohere::embed())),
+            (_, InferenceProvider::Named(name)) if name == COHERE => {
+                Err(InvalidRequestError::UnsupportedProvider(
+                    target_provider.clone(),
+                ))
             }
             (Self::OpenAI(source), InferenceProvider::Named(name)) => {
                 Ok(Self::OpenAICompatible {
@@ -110,12 +150,62 @@ impl ApiEndpoint {
                     openai_endpoint: source,
                 })
             }
+            (Self::Anthropic(source), InferenceProvider::Anthropic) => {
+                Ok(Self::Anthropic(source))
+            }
+            (Self::Anthropic(source), InferenceProvider::OpenAI) => {
+                Ok(Self::OpenAI(OpenAI::from(source)))
+            }
+            (Self::Anthropic(source), InferenceProvider::GoogleGemini) => {
+                Ok(Self::Google(Google::from(OpenAI::from(source))))
+            }
+            (Self::Anthropic(source), InferenceProvider::Ollama) => {
+                Ok(Self::Ollama(Ollama::try_from(OpenAI::from(source))?))
+            }
+            (Self::Anthropic(source), InferenceProvider::Named(name)) => {
+                Ok(Self::OpenAICompatible {
+                    provider: InferenceProvider::Named(name.clone()),
+                    openai_endpoint: OpenAI::from(source),
+                })
+            }
             _ => Err(InvalidRequestError::UnsupportedProvider(
                 target_provider.clone(),
             )),
         }
     }
 
+    /// Like [`ApiEndpoint::mapped`], for a target provider configured with
+    /// the self-hosted `dialect`.
+    ///
+    /// Chat requests to providers speaking the native Ollama dialect go to
+    /// its `/api/chat`, everything else is mapped as usual.
+    pub fn mapped_with_dialect(
+        source_endpoint: ApiEndpoint,
+        target_provider: &InferenceProvider,
+        dialect: Option<SelfHostedDialect>,
+    ) -> Result<Self, InvalidRequestError> {
+        let is_chat = matches!(
+            source_endpoint,
+            Self::OpenAI(OpenAI::ChatCompletions(_)) | Self::Anthropic(_)
+        );
+        match (dialect, target_provider) {
+            (Some(SelfHostedDialect::Ollama), InferenceProvider::Ollama)
+                if is_chat =>
+            {
+                Ok(Self::Ollama(Ollama::chat()))
+            }
+            (Some(SelfHostedDialect::Ollama), InferenceProvider::Named(_))
+                if is_chat =>
+            {
+                Ok(Self::OllamaCompatible {
+                    provider: target_provider.clone(),
+                    ollama_endpoint: Ollama::chat(),
+                })
+            }
+            _ => Self::mapped(source_endpoint, target_provider),
+        }
+    }
+
     #[must_use]
     pub fn provider(&self) -> InferenceProvider {
         match self {
@@ -124,7 +214,9 @@ impl ApiEndpoint {
             Self::Google(_) => InferenceProvider::GoogleGemini,
             Self::Ollama(_) => InferenceProvider::Ollama,
             Self::Bedrock(_) => InferenceProvider::Bedrock,
-            Self::OpenAICompatible { provider, .. } => provider.clone(),
+            Self::Cohere(_) => InferenceProvider::Named(COHERE.into()),
+            Self::OpenAICompatible { provider, .. }
+            | Self::OllamaCompatible { provider, .. } => provider.clone(),
         }
     }
 
@@ -139,8 +231,20 @@ impl ApiEndpoint {
                 openai_endpoint, ..
             } => Ok(openai_endpoint.path().to_string()),
             Self::Anthropic(anthropic) => Ok(anthropic.path().to_string()),
-            Self::Google(google) => Ok(google.path().to_string()),
-            Self::Ollama(ollama) => Ok(ollama.path().to_string()),
+            Self::Google(google) => {
+                if let Some(model_id) = model_id {
+                    Ok(google.path(model_id, is_stream))
+                } else {
+                    tracing::error!("Google path requires model id");
+                    Err(InternalError::Internal)
+                }
+            }
+            Self::Ollama(ollama)
+            | Self::OllamaCompatible {
+                ollama_endpoint: ollama,
+                ..
+            } => Ok(ollama.path().to_string()),
+            Self::Cohere(cohere) => Ok(cohere.path().to_string()),
             Self::Bedrock(bedrock) => {
                 if let Some(model_id) = model_id {
                     Ok(bedrock.path(model_id, is_stream))
@@ -161,8 +265,13 @@ impl ApiEndpoint {
             } => openai_endpoint.endpoint_type(),
             Self::Anthropic(anthropic) => anthropic.endpoint_type(),
             Self::Google(google) => google.endpoint_type(),
-            Self::Ollama(ollama) => ollama.endpoint_type(),
+            Self::Ollama(ollama)
+            | Self::OllamaCompatible {
+                ollama_endpoint: ollama,
+                ..
+            } => ollama.endpoint_type(),
             Self::Bedrock(bedrock) => bedrock.endpoint_type(),
+            Self::Cohere(cohere) => cohere.endpoint_type(),
         }
     }
 }
@@ -182,6 +291,7 @@ impl ApiEndpoint {
 #[strum(serialize_all = "kebab-case")]
 pub enum EndpointType {
     Chat,
+    Embeddings,
     Image,
     Audio,
 }
@@ -0,0 +1,177 @@
+//! Ready-to-mount handlers for the OpenAI-compatible `/v1/models` endpoints,
+//! backed by a [`ProvidersConfig`].
+//!
+//! The handlers extract `State<Arc<ProvidersConfig>>`, so they can be mounted
+//! on any router whose state implements `FromRef` for `Arc<ProvidersConfig>`.
+use std::sync::Arc;
+
+use async_openai::types::{ListModelResponse, Model};
+use axum::{
+    Json, Router,
+    extract::{FromRef, Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+
+use crate::{
+    config::providers::ProvidersConfig,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+/// A router serving `GET /v1/models` and `GET /v1/models/{id}`.
+pub fn router<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    Arc<ProvidersConfig>: FromRef<S>,
+{
+    Router::new()
+        .route("/v1/models", get(list_models))
+        .route("/v1/models/{*id}", get(get_model))
+}
+
+/// List the models of every enabled provider, with ids in the
+/// `{provider}/{model}` format accepted by the unified API.
+pub async fn list_models(
+    State(providers): State<Arc<ProvidersConfig>>,
+) -> Json<ListModelResponse> {
+    let data = providers
+        .enabled()
+        .flat_map(|(provider, config)| {
+            config
+                .models
+                .iter()
+                .map(move |model| to_model(provider, model))
+        })
+        .collect();
+    Json(ListModelResponse {
+        object: "list".to_string(),
+        data,
+    })
+}
+
+/// Get a single model by its `{provider}/{model}` id.
+///
+/// Responds with a 404 listing similarly named models if the id is not
+/// configured, or its provider is disabled.
+pub async fn get_model(
+    State(providers): State<Arc<ProvidersConfig>>,
+    Path(id): Path<String>,
+) -> Response {
+    let found = providers.enabled().find_map(|(provider, config)| {
+        config
+            .models
+            .iter()
+            .find(|model| model_id(provider, model) == id)
+            .map(|model| to_model(provider, model))
+    });
+    if let Some(model) = found {
+        return Json(model).into_response();
+    }
+
+    let suggestions = providers
+        .suggest_models(&id)
+        .into_iter()
+        .filter(|(provider, _)| {
+            providers.enabled().any(|(enabled, _)| enabled == *provider)
+        })
+        .map(|(provider, model)| model_id(provider, model))
+        .collect::<Vec<_>>();
+    let body = serde_json::json!({
+        "error": {
+            "message": format!("The model `{id}` does not exist"),
+            "type": "invalid_request_error",
+            "param": "model",
+            "code": "model_not_found",
+            "suggestions": suggestions,
+        }
+    });
+    (StatusCode::NOT_FOUND, Json(body)).into_response()
+}
+
+fn model_id(provider: &InferenceProvider, model: &ModelId) -> String {
+    format!("{provider}/{model}")
+}
+
+fn to_model(provider: &InferenceProvider, model: &ModelId) -> Model {
+    Model {
+        id: model_id(provider, model),
+        object: "model".to_string(),
+        created: 0,
+        owned_by: provider.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt;
+
+    use super::*;
+
+    fn providers() -> Arc<ProvidersConfig> {
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+    - gpt-4o-mini
+  base-url: https://api.openai.com
+anthropic:
+  models:
+    - claude-3-5-haiku
+  base-url: https://api.anthropic.com
+  enabled: false
+";
+        Arc::new(serde_yml::from_str(yaml).unwrap())
+    }
+
+    async fn json_body(response: Response) -> serde_json::Value {
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        serde_json::from_slice(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn list_models_skips_disabled_providers() {
+        let Json(response) = list_models(State(providers())).await;
+        let ids = response
+            .data
+            .iter()
+            .map(|model| model.id.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(ids, ["openai/gpt-4o", "openai/gpt-4o-mini"]);
+        assert_eq!(response.data[0].owned_by, "openai");
+    }
+
+    #[tokio::test]
+    async fn get_model_by_id() {
+        let response =
+            get_model(State(providers()), Path("openai/gpt-4o".to_string()))
+                .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = json_body(response).await;
+        assert_eq!(body["id"], "openai/gpt-4o");
+        assert_eq!(body["object"], "model");
+    }
+
+    #[tokio::test]
+    async fn get_model_not_found() {
+        let response =
+            get_model(State(providers()), Path("openai/gpt-4p".to_string()))
+                .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body = json_body(response).await;
+        assert_eq!(body["error"]["code"], "model_not_found");
+        assert!(
+            body["error"]["suggestions"]
+                .as_array()
+                .unwrap()
+                .contains(&"openai/gpt-4o".into())
+        );
+
+        let response = get_model(
+            State(providers()),
+            Path("anthropic/claude-3-5-haiku".to_string()),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
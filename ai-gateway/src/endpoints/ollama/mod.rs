@@ -1,14 +1,19 @@
+pub mod chat;
 pub mod chat_completions;
 
 use super::EndpointType;
 use crate::{
-    endpoints::{Endpoint, ollama::chat_completions::ChatCompletions},
+    endpoints::{
+        Endpoint,
+        ollama::{chat::Chat, chat_completions::ChatCompletions},
+    },
     error::invalid_req::InvalidRequestError,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumIter)]
 pub enum Ollama {
     ChatCompletions(ChatCompletions),
+    Chat(Chat),
 }
 
 impl Ollama {
@@ -16,6 +21,7 @@ impl Ollama {
     pub fn path(&self) -> &str {
         match self {
             Self::ChatCompletions(_) => ChatCompletions::PATH,
+            Self::Chat(_) => Chat::PATH,
         }
     }
 
@@ -24,10 +30,15 @@ impl Ollama {
         Self::ChatCompletions(ChatCompletions)
     }
 
+    #[must_use]
+    pub fn chat() -> Self {
+        Self::Chat(Chat)
+    }
+
     #[must_use]
     pub fn endpoint_type(&self) -> EndpointType {
         match self {
-            Self::ChatCompletions(_) => EndpointType::Chat,
+            Self::ChatCompletions(_) | Self::Chat(_) => EndpointType::Chat,
         }
     }
 }
@@ -38,6 +49,7 @@ impl TryFrom<&str> for Ollama {
     fn try_from(path: &str) -> Result<Self, Self::Error> {
         match path {
             ChatCompletions::PATH => Ok(Self::ChatCompletions(ChatCompletions)),
+            Chat::PATH => Ok(Self::Chat(Chat)),
             path => {
                 tracing::debug!(path = %path, "unsupported ollama path");
                 Err(InvalidRequestError::NotFound(path.to_string()))
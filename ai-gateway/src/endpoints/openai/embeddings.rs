@@ -0,0 +1,158 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    endpoints::{AiRequest, Endpoint},
+    error::mapper::MapperError,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+pub(crate) const EMBEDDING_LIST_OBJECT: &str = "list";
+pub(crate) const EMBEDDING_OBJECT: &str = "embedding";
+/// The most inputs OpenAI accepts in a single embeddings request.
+pub(crate) const MAX_INPUTS: usize = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Embeddings;
+
+impl Endpoint for Embeddings {
+    // https://platform.openai.com/docs/api-reference/embeddings/create
+    const PATH: &'static str = "v1/embeddings";
+    type RequestBody = CreateEmbeddingRequest;
+    type ResponseBody = CreateEmbeddingResponse;
+    /// Embeddings are never streamed.
+    type StreamResponseBody = CreateEmbeddingResponse;
+    type ErrorResponseBody = async_openai::error::WrappedError;
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CreateEmbeddingRequest {
+    pub model: String,
+    pub input: EmbeddingInput,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding_format: Option<EncodingFormat>,
+    /// The number of dimensions of the returned embeddings, for models that
+    /// can shorten their embeddings.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+impl AiRequest for CreateEmbeddingRequest {
+    fn is_stream(&self) -> bool {
+        false
+    }
+
+    fn model(&self) -> Result<ModelId, MapperError> {
+        ModelId::from_str_and_provider(InferenceProvider::OpenAI, &self.model)
+    }
+}
+
+/// The text to embed, or text already tokenized by the client.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingInput {
+    String(String),
+    StringArray(Vec<String>),
+    Tokens(Vec<u32>),
+    TokensArray(Vec<Vec<u32>>),
+}
+
+impl Default for EmbeddingInput {
+    fn default() -> Self {
+        Self::String(String::new())
+    }
+}
+
+impl EmbeddingInput {
+    /// The number of embeddings requested.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        match self {
+            Self::String(_) | Self::Tokens(_) => 1,
+            Self::StringArray(inputs) => inputs.len(),
+            Self::TokensArray(inputs) => inputs.len(),
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[must_use]
+    pub fn is_tokens(&self) -> bool {
+        matches!(self, Self::Tokens(_) | Self::TokensArray(_))
+    }
+
+    /// The texts to embed, or `None` if the input is tokenized.
+    #[must_use]
+    pub fn into_texts(self) -> Option<Vec<String>> {
+        match self {
+            Self::String(input) => Some(vec![input]),
+            Self::StringArray(inputs) => Some(inputs),
+            Self::Tokens(_) | Self::TokensArray(_) => None,
+        }
+    }
+
+    /// Split the input into batches of at most `size` inputs, in order.
+    #[must_use]
+    pub fn batches(&self, size: usize) -> Vec<EmbeddingInput> {
+        let size = size.max(1);
+        match self {
+            Self::StringArray(inputs) if inputs.len() > size => inputs
+                .chunks(size)
+                .map(|batch| Self::StringArray(batch.to_vec()))
+                .collect(),
+            Self::TokensArray(inputs) if inputs.len() > size => inputs
+                .chunks(size)
+                .map(|batch| Self::TokensArray(batch.to_vec()))
+                .collect(),
+            _ => vec![self.clone()],
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum EncodingFormat {
+    #[default]
+    Float,
+    /// Little-endian `f32`s, base64 encoded.
+    Base64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CreateEmbeddingResponse {
+    pub object: String,
+    pub model: String,
+    pub data: Vec<Embedding>,
+    #[serde(default)]
+    pub usage: EmbeddingUsage,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Embedding {
+    pub index: u32,
+    pub object: String,
+    pub embedding: EmbeddingVector,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EmbeddingVector {
+    Float(Vec<f32>),
+    Base64(String),
+}
+
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize,
+)]
+pub struct EmbeddingUsage {
+    #[serde(default)]
+    pub prompt_tokens: u32,
+    #[serde(default)]
+    pub total_tokens: u32,
+}
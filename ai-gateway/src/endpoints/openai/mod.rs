@@ -1,7 +1,10 @@
 pub mod chat_completions;
+pub mod embeddings;
 
 use super::EndpointType;
-pub use crate::endpoints::openai::chat_completions::ChatCompletions;
+pub use crate::endpoints::openai::{
+    chat_completions::ChatCompletions, embeddings::Embeddings,
+};
 use crate::{
     endpoints::{Endpoint, EndpointRoute},
     error::invalid_req::InvalidRequestError,
@@ -10,6 +13,7 @@ use crate::{
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, strum::EnumIter)]
 pub enum OpenAI {
     ChatCompletions(ChatCompletions),
+    Embeddings(Embeddings),
 }
 
 impl OpenAI {
@@ -17,6 +21,7 @@ impl OpenAI {
     pub fn path(&self) -> &str {
         match self {
             Self::ChatCompletions(_) => ChatCompletions::PATH,
+            Self::Embeddings(_) => Embeddings::PATH,
         }
     }
 
@@ -25,10 +30,16 @@ impl OpenAI {
         Self::ChatCompletions(ChatCompletions)
     }
 
+    #[must_use]
+    pub fn embeddings() -> Self {
+        Self::Embeddings(Embeddings)
+    }
+
     #[must_use]
     pub fn endpoint_type(&self) -> EndpointType {
         match self {
             Self::ChatCompletions(_) => EndpointType::Chat,
+            Self::Embeddings(_) => EndpointType::Embeddings,
         }
     }
 }
@@ -41,6 +52,12 @@ impl TryFrom<&EndpointRoute> for OpenAI {
             EndpointRoute::ChatCompletions => {
                 Ok(Self::ChatCompletions(ChatCompletions))
             }
+            EndpointRoute::Embeddings => Ok(Self::Embeddings(Embeddings)),
+            EndpointRoute::Messages => {
+                Err(InvalidRequestError::UnsupportedEndpoint(
+                    endpoint.path().to_string(),
+                ))
+            }
         }
     }
 }
@@ -84,3 +101,42 @@ impl super::AiRequest for OpenAICompatibleChatCompletionRequest {
         )
     }
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct OpenAICompatibleEmbeddings;
+
+impl Endpoint for OpenAICompatibleEmbeddings {
+    const PATH: &'static str = "v1/embeddings";
+    type RequestBody = OpenAICompatibleEmbeddingRequest;
+    type ResponseBody = embeddings::CreateEmbeddingResponse;
+    type StreamResponseBody = embeddings::CreateEmbeddingResponse;
+    type ErrorResponseBody = async_openai::error::WrappedError;
+}
+
+#[derive(
+    Clone, serde::Serialize, Default, Debug, serde::Deserialize, PartialEq,
+)]
+pub struct OpenAICompatibleEmbeddingRequest {
+    #[serde(skip)]
+    pub(crate) provider: crate::types::provider::InferenceProvider,
+    #[serde(flatten)]
+    pub(crate) inner: embeddings::CreateEmbeddingRequest,
+}
+
+impl super::AiRequest for OpenAICompatibleEmbeddingRequest {
+    fn is_stream(&self) -> bool {
+        false
+    }
+
+    fn model(
+        &self,
+    ) -> Result<
+        crate::types::model_id::ModelId,
+        crate::error::mapper::MapperError,
+    > {
+        crate::types::model_id::ModelId::from_str_and_provider(
+            self.provider.clone(),
+            &self.inner.model,
+        )
+    }
+}
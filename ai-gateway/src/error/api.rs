@@ -30,6 +30,8 @@ pub enum ApiError {
     StreamError(#[from] StreamError),
     /// Service panicked: {0}
     Panic(String),
+    /// Too many requests queued, retry after {retry_after}s
+    Overloaded { retry_after: u64 },
 }
 
 impl From<dynamic_router::router::Error> for ApiError {
@@ -84,6 +86,22 @@ impl IntoResponse for ApiError {
                 )
                     .into_response()
             }
+            ApiError::Overloaded { retry_after } => {
+                tracing::warn!(error = %self, "overloaded");
+                (
+                    StatusCode::SERVICE_UNAVAILABLE,
+                    [(http::header::RETRY_AFTER, retry_after.to_string())],
+                    Json(ErrorResponse {
+                        error: ErrorDetails {
+                            message: self.to_string(),
+                            r#type: Some(SERVER_ERROR_TYPE.to_string()),
+                            param: None,
+                            code: Some("overloaded".to_string()),
+                        },
+                    }),
+                )
+                    .into_response()
+            }
         }
     }
 }
@@ -102,6 +120,8 @@ pub enum ApiErrorMetric {
     StreamError(#[from] StreamErrorMetric),
     /// Panic
     Panic,
+    /// Too many requests queued
+    Overloaded,
 }
 
 impl From<&ApiError> for ApiErrorMetric {
@@ -127,6 +147,7 @@ impl From<&ApiError> for ApiErrorMetric {
                 _ => Self::StreamError(StreamErrorMetric::from(error)),
             },
             ApiError::Panic(_error) => Self::Panic,
+            ApiError::Overloaded { .. } => Self::Overloaded,
         }
     }
 }
@@ -151,6 +172,7 @@ impl ErrorMetric for ApiErrorMetric {
                 format!("StreamError:{}", error.as_ref())
             }
             Self::Panic => String::from("Panic"),
+            Self::Overloaded => String::from("Overloaded"),
         }
     }
 }
@@ -17,6 +17,14 @@ pub enum AuthError {
     InvalidCredentials,
     /// Provider key not found
     ProviderKeyNotFound,
+    /// API key expired
+    ExpiredCredentials,
+    /// API key not permitted to use this router
+    RouterNotPermitted,
+    /// API key not permitted to use model: {0}
+    ModelNotPermitted(String),
+    /// API key exceeded its spend limit
+    SpendLimitExceeded,
 }
 
 impl IntoResponse for AuthError {
@@ -58,6 +66,42 @@ impl IntoResponse for AuthError {
                 }),
             )
                 .into_response(),
+            Self::ExpiredCredentials => (
+                StatusCode::UNAUTHORIZED,
+                Json(ErrorResponse {
+                    error: ErrorDetails {
+                        message: Self::ExpiredCredentials.to_string(),
+                        r#type: Some(INVALID_REQUEST_ERROR_TYPE.to_string()),
+                        param: None,
+                        code: Some("expired_api_key".to_string()),
+                    },
+                }),
+            )
+                .into_response(),
+            Self::RouterNotPermitted | Self::ModelNotPermitted(_) => (
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: ErrorDetails {
+                        message: self.to_string(),
+                        r#type: Some(INVALID_REQUEST_ERROR_TYPE.to_string()),
+                        param: None,
+                        code: Some("permission_denied".to_string()),
+                    },
+                }),
+            )
+                .into_response(),
+            Self::SpendLimitExceeded => (
+                StatusCode::TOO_MANY_REQUESTS,
+                Json(ErrorResponse {
+                    error: ErrorDetails {
+                        message: Self::SpendLimitExceeded.to_string(),
+                        r#type: Some(INVALID_REQUEST_ERROR_TYPE.to_string()),
+                        param: None,
+                        code: Some("insufficient_quota".to_string()),
+                    },
+                }),
+            )
+                .into_response(),
         }
     }
 }
@@ -73,6 +117,12 @@ pub enum AuthErrorMetric {
     InvalidCredentials,
     /// Provider key not found
     ProviderKeyNotFound,
+    /// Expired credentials
+    ExpiredCredentials,
+    /// Permission denied
+    PermissionDenied,
+    /// Spend limit exceeded
+    SpendLimitExceeded,
 }
 
 impl From<&AuthError> for AuthErrorMetric {
@@ -83,6 +133,11 @@ impl From<&AuthError> for AuthErrorMetric {
             }
             AuthError::InvalidCredentials => Self::InvalidCredentials,
             AuthError::ProviderKeyNotFound => Self::ProviderKeyNotFound,
+            AuthError::ExpiredCredentials => Self::ExpiredCredentials,
+            AuthError::RouterNotPermitted | AuthError::ModelNotPermitted(_) => {
+                Self::PermissionDenied
+            }
+            AuthError::SpendLimitExceeded => Self::SpendLimitExceeded,
         }
     }
 }
@@ -3,7 +3,10 @@ use telemetry::TelemetryError;
 use thiserror::Error;
 
 use crate::{
-    config::validation::ModelMappingValidationError,
+    config::{
+        providers::ProvidersConfigError,
+        validation::ModelMappingValidationError,
+    },
     types::{provider::InferenceProvider, router::RouterId},
 };
 
@@ -24,6 +27,12 @@ pub enum InitError {
     OAuthConfig(url::ParseError),
     /// Failed to create reqwest client: {0}
     CreateReqwestClient(reqwest::Error),
+    /// Invalid provider header: {0}
+    InvalidProviderHeader(http::Error),
+    /// Invalid CA certificate for provider {0}: {1}
+    InvalidProviderCaCert(InferenceProvider, String),
+    /// Invalid providers config: {0}
+    InvalidProvidersConfig(#[from] ProvidersConfigError),
     /// Failed to create balancer: {0}
     CreateBalancer(tower::BoxError),
     /// Provider error: {0}
@@ -44,6 +53,8 @@ pub enum InitError {
     InitSystemMetrics,
     /// Invalid rate limit config: {0}
     InvalidRateLimitConfig(&'static str),
+    /// Invalid guardrail `{0}`: {1}
+    InvalidGuardrail(String, String),
     /// Invalid mappings config: {0}
     InvalidMappingsConfig(#[from] ModelMappingValidationError),
     /// Failed to connect to websocket: {0}
@@ -82,4 +93,8 @@ pub enum InitError {
     InitHeliconeKeys(String),
     /// Failed to load initial routers from db: {0}
     InitRouters(String),
+    /// Failed to load virtual keys from db: {0}
+    InitVirtualKeys(String),
+    /// Failed to load budgets from db: {0}
+    InitBudgets(String),
 }
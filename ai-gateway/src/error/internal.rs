@@ -67,6 +67,8 @@ pub enum InternalError {
     MetricsNotConfigured(ApiEndpoint),
     /// Failed to sign AWS request: {0}
     AwsRequestSigningError(String),
+    /// Failed to assume AWS role: {0}
+    AwsAssumeRoleError(String),
     /// Dynamic router discovery error: {0}
     DynamicRouterDiscoveryError(BoxError),
     /// Cache error: {0}
@@ -152,6 +154,8 @@ pub enum InternalErrorMetric {
     MetricsNotConfigured,
     /// Failed to sign AWS request
     AwsRequestSigningError,
+    /// Failed to assume AWS role
+    AwsAssumeRoleError,
     /// Cache error
     CacheError,
     /// Dynamic router discovery error
@@ -201,6 +205,7 @@ impl From<&InternalError> for InternalErrorMetric {
             InternalError::AwsRequestSigningError(_) => {
                 Self::AwsRequestSigningError
             }
+            InternalError::AwsAssumeRoleError(_) => Self::AwsAssumeRoleError,
             InternalError::CacheError(_) => Self::CacheError,
             InternalError::RedisError(_) => Self::RedisError,
             InternalError::PoolError(_) => Self::PoolError,
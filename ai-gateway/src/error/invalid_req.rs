@@ -1,4 +1,5 @@
 use axum_core::response::IntoResponse;
+use chrono::{DateTime, Utc};
 use displaydoc::Display;
 use http::{HeaderMap, StatusCode};
 use thiserror::Error;
@@ -6,7 +7,9 @@ use tracing::debug;
 
 use crate::{
     error::api::{ErrorDetails, ErrorResponse},
-    middleware::mapper::openai::INVALID_REQUEST_ERROR_TYPE,
+    middleware::mapper::openai::{
+        INVALID_REQUEST_ERROR_TYPE, SERVER_ERROR_TYPE,
+    },
     types::{json::Json, provider::InferenceProvider},
 };
 
@@ -55,8 +58,34 @@ pub enum InvalidRequestError {
     InvalidRequestHeader(http::header::ToStrError),
     /// Invalid prompt inputs: {0}
     InvalidPromptInputs(String),
+    /// Denied by guardrail `{guardrail}`: {reason}
+    GuardrailDenied { guardrail: String, reason: String },
+    /// Invalid embeddings request: {0}
+    InvalidEmbeddingsRequest(String),
+    /// Override `{header}: {value}` not allowed, permitted: {permitted}
+    OverrideNotAllowed {
+        header: &'static str,
+        value: String,
+        permitted: String,
+    },
+    /// Unsupported content: {0}
+    UnsupportedContent(String),
+    /// Invalid image: {0}
+    InvalidImage(String),
+    /// Budget `{budget}` exceeded until {resets_at}
+    BudgetExceeded {
+        budget: String,
+        resets_at: DateTime<Utc>,
+    },
+    /// Provider disabled: {0}
+    ProviderDisabled(InferenceProvider),
 }
 
+/// Set on the responses to [`InvalidRequestError::UnsupportedContent`], so
+/// that routers can fail over to a provider whose model supports the content.
+#[derive(Debug, Clone, Copy)]
+pub struct ContentNotSupported;
+
 impl IntoResponse for InvalidRequestError {
     fn into_response(self) -> axum_core::response::Response {
         debug!(error = %self, "Invalid request");
@@ -74,6 +103,18 @@ impl IntoResponse for InvalidRequestError {
                 }),
             )
                 .into_response(),
+            Self::OverrideNotAllowed { .. } => (
+                StatusCode::FORBIDDEN,
+                Json(ErrorResponse {
+                    error: ErrorDetails {
+                        message,
+                        r#type: Some(INVALID_REQUEST_ERROR_TYPE.to_string()),
+                        param: None,
+                        code: None,
+                    },
+                }),
+            )
+                .into_response(),
             Self::Provider4xxError(status) => (
                 status,
                 Json(ErrorResponse {
@@ -120,6 +161,54 @@ impl IntoResponse for InvalidRequestError {
                 )
                     .into_response()
             }
+            Self::BudgetExceeded { resets_at, .. } => {
+                let retry_after = (resets_at - Utc::now()).num_seconds().max(0);
+                (
+                    StatusCode::PAYMENT_REQUIRED,
+                    [(http::header::RETRY_AFTER, retry_after.to_string())],
+                    Json(ErrorResponse {
+                        error: ErrorDetails {
+                            message,
+                            r#type: Some(
+                                INVALID_REQUEST_ERROR_TYPE.to_string(),
+                            ),
+                            param: None,
+                            code: Some("budget_exceeded".to_string()),
+                        },
+                    }),
+                )
+                    .into_response()
+            }
+            Self::ProviderDisabled(_) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                Json(ErrorResponse {
+                    error: ErrorDetails {
+                        message,
+                        r#type: Some(SERVER_ERROR_TYPE.to_string()),
+                        param: None,
+                        code: Some("provider_disabled".to_string()),
+                    },
+                }),
+            )
+                .into_response(),
+            Self::UnsupportedContent(_) => {
+                let mut response = (
+                    StatusCode::BAD_REQUEST,
+                    Json(ErrorResponse {
+                        error: ErrorDetails {
+                            message,
+                            r#type: Some(
+                                INVALID_REQUEST_ERROR_TYPE.to_string(),
+                            ),
+                            param: None,
+                            code: Some("unsupported_content".to_string()),
+                        },
+                    }),
+                )
+                    .into_response();
+                response.extensions_mut().insert(ContentNotSupported);
+                response
+            }
             _ => (
                 StatusCode::BAD_REQUEST,
                 Json(ErrorResponse {
@@ -155,6 +244,16 @@ pub enum InvalidRequestErrorMetric {
     Provider4xxError,
     /// Too many requests
     TooManyRequests,
+    /// Denied by guardrail
+    GuardrailDenied,
+    /// Override not allowed
+    OverrideNotAllowed,
+    /// Content not supported by the model
+    UnsupportedContent,
+    /// Budget exceeded
+    BudgetExceeded,
+    /// Provider disabled
+    ProviderDisabled,
 }
 
 impl From<&InvalidRequestError> for InvalidRequestErrorMetric {
@@ -171,6 +270,8 @@ impl From<&InvalidRequestError> for InvalidRequestErrorMetric {
             | InvalidRequestError::UnsupportedEndpoint(_)
             | InvalidRequestError::InvalidCacheConfig
             | InvalidRequestError::InvalidPromptInputs(_)
+            | InvalidRequestError::InvalidEmbeddingsRequest(_)
+            | InvalidRequestError::InvalidImage(_)
             | InvalidRequestError::MissingModelId
             | InvalidRequestError::InvalidModelId => Self::InvalidRequest,
             InvalidRequestError::InvalidUrl(_) => Self::InvalidUrl,
@@ -179,6 +280,17 @@ impl From<&InvalidRequestError> for InvalidRequestErrorMetric {
             }
             InvalidRequestError::Provider4xxError(_) => Self::Provider4xxError,
             InvalidRequestError::TooManyRequests(_) => Self::TooManyRequests,
+            InvalidRequestError::GuardrailDenied { .. } => {
+                Self::GuardrailDenied
+            }
+            InvalidRequestError::OverrideNotAllowed { .. } => {
+                Self::OverrideNotAllowed
+            }
+            InvalidRequestError::UnsupportedContent(_) => {
+                Self::UnsupportedContent
+            }
+            InvalidRequestError::BudgetExceeded { .. } => Self::BudgetExceeded,
+            InvalidRequestError::ProviderDisabled(_) => Self::ProviderDisabled,
         }
     }
 }
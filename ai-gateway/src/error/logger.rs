@@ -20,4 +20,6 @@ pub enum LoggerError {
     NoAuthContextSet,
     /// Unexpected response: {0}
     UnexpectedResponse(String),
+    /// Failed to write log file: {0}
+    WriteFile(std::io::Error),
 }
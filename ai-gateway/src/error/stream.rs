@@ -18,6 +18,12 @@ pub enum StreamError {
     StreamError(#[from] Box<reqwest_eventsource::Error>),
     /// Body error: {0}
     BodyError(axum_core::Error),
+    /// Upstream exception in stream: {exception_type}: {message}
+    Exception {
+        /// e.g. `throttlingException` in a Bedrock event stream.
+        exception_type: String,
+        message: String,
+    },
 }
 
 impl StreamError {
@@ -38,6 +44,13 @@ impl StreamError {
                 | reqwest_eventsource::Error::StreamEnded => false,
             },
             StreamError::BodyError(_error) => false,
+            StreamError::Exception { exception_type, .. } => matches!(
+                exception_type.as_str(),
+                "throttlingException"
+                    | "internalServerException"
+                    | "serviceUnavailableException"
+                    | "modelStreamErrorException"
+            ),
         }
     }
 }
@@ -123,6 +136,37 @@ impl IntoResponse for StreamError {
                 }),
             )
                 .into_response(),
+            Self::Exception {
+                exception_type,
+                message,
+            } => {
+                let (status_code, error_type) = match exception_type.as_str() {
+                    "validationException" => {
+                        (StatusCode::BAD_REQUEST, INVALID_REQUEST_ERROR_TYPE)
+                    }
+                    "throttlingException" => {
+                        (StatusCode::TOO_MANY_REQUESTS, SERVER_ERROR_TYPE)
+                    }
+                    _ => (StatusCode::INTERNAL_SERVER_ERROR, SERVER_ERROR_TYPE),
+                };
+                tracing::debug!(
+                    exception_type = %exception_type,
+                    message = %message,
+                    "upstream exception in stream"
+                );
+                (
+                    status_code,
+                    Json(ErrorResponse {
+                        error: ErrorDetails {
+                            message: format!("{exception_type}: {message}"),
+                            r#type: Some(error_type.to_string()),
+                            param: None,
+                            code: None,
+                        },
+                    }),
+                )
+                    .into_response()
+            }
         }
     }
 }
@@ -136,6 +180,8 @@ pub enum StreamErrorMetric {
     StreamError,
     /// Body error
     BodyError,
+    /// Upstream exception
+    Exception,
 }
 
 impl From<&StreamError> for StreamErrorMetric {
@@ -143,6 +189,7 @@ impl From<&StreamError> for StreamErrorMetric {
         match error {
             StreamError::StreamError(_) => Self::StreamError,
             StreamError::BodyError(_) => Self::BodyError,
+            StreamError::Exception { .. } => Self::Exception,
         }
     }
 }
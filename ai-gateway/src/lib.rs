@@ -1,5 +1,7 @@
+pub mod admin;
 pub mod app;
 pub mod app_state;
+pub mod budgets;
 pub mod cache;
 pub mod cli;
 pub mod config;
@@ -9,6 +11,7 @@ pub(crate) mod dispatcher;
 pub mod endpoints;
 pub mod error;
 pub mod logger;
+pub mod metering;
 pub mod metrics;
 pub mod middleware;
 pub(crate) mod router;
@@ -17,3 +20,4 @@ pub mod store;
 pub mod tests;
 pub mod types;
 pub mod utils;
+pub mod virtual_keys;
@@ -15,7 +15,9 @@ use uuid::Uuid;
 use crate::{
     app_state::AppState,
     config::deployment_target::DeploymentTarget,
+    control_plane::types::hash_key,
     error::{init::InitError, logger::LoggerError},
+    logger::sink::LogRecord,
     metrics::tfft::TFFTFuture,
     store::minio::MinioClient,
     types::{
@@ -95,6 +97,60 @@ impl LoggerService {
             Duration::from_secs(0)
         });
         tracing::trace!(tfft_duration = ?tfft_duration, "tfft_duration");
+        let cost = if self.response_status.is_success() {
+            self.app_state.0.metering.record(
+                &self.app_state.config().pricing,
+                Some(&self.auth_ctx),
+                &self.provider,
+                self.mapper_ctx.model.as_ref(),
+                &response_body,
+            )
+        } else {
+            None
+        };
+        if let Some(cost) = cost {
+            self.app_state.record_tokens(
+                self.router_id.as_ref(),
+                &self.provider,
+                self.mapper_ctx.model.as_ref(),
+                &cost.usage,
+            );
+        }
+        if let Some(cost_usd) = cost.and_then(|cost| cost.cost_usd) {
+            self.app_state
+                .add_spend(
+                    Some(&self.auth_ctx),
+                    self.router_id.as_ref(),
+                    &self.provider,
+                    cost_usd,
+                )
+                .await;
+        }
+        if let Some(log_sink) = &self.app_state.0.log_sink {
+            log_sink.send(
+                LogRecord::builder()
+                    .request_id(self.request_id)
+                    .request_created_at(self.start_time)
+                    .router_id(self.router_id.clone())
+                    .provider(self.provider.clone())
+                    .model(
+                        self.mapper_ctx.model.as_ref().map(ToString::to_string),
+                    )
+                    .path(self.target_url.path().to_string())
+                    .status(self.response_status.as_u16())
+                    .is_stream(self.mapper_ctx.is_stream)
+                    .latency(self.start_instant.elapsed())
+                    .ttft(Some(tfft_duration))
+                    .api_key_hash(Some(hash_key(
+                        self.auth_ctx.api_key.expose(),
+                    )))
+                    .usage(cost.map(|cost| cost.usage))
+                    .cost_usd(cost.and_then(|cost| cost.cost_usd))
+                    .request_body(self.request_body.clone())
+                    .response_body(response_body.clone())
+                    .build(),
+            );
+        }
         let req_body_len = self.request_body.len();
         let resp_body_len = response_body.len();
         let s3_client = if self.app_state.config().deployment_target.is_cloud()
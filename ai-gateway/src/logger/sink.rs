@@ -0,0 +1,435 @@
+//! Writes a record of every request, with its request and response bodies,
+//! as JSON lines to a file or an S3 compatible bucket.
+//!
+//! Records are sent to a bounded channel from the request path and written
+//! in batches by the [`LogSinkWriter`] service, so that a slow backend never
+//! delays requests. Records are dropped if the channel is full.
+//!
+//! See [`LogSinkConfig`] to configure the sink.
+use std::{
+    path::PathBuf,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use meltdown::Token;
+use regex::Regex;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use serde_json::Value;
+use tokio::{
+    io::AsyncWriteExt,
+    sync::mpsc::{self, error::TrySendError},
+};
+use typed_builder::TypedBuilder;
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    config::log_sink::{LogSinkBackend, LogSinkConfig, RedactionConfig},
+    error::{init::InitError, logger::LoggerError, runtime::RuntimeError},
+    metering::Usage,
+    middleware::guardrails::text::Texts,
+    store::minio::BaseMinioClient,
+    types::{provider::InferenceProvider, router::RouterId},
+};
+
+const REDACTED: &str = "[REDACTED]";
+const PUT_OBJECT_SIGN_DURATION: Duration = Duration::from_secs(120);
+
+/// Provider API keys, and bearer tokens in general.
+static API_KEY_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"sk-[A-Za-z0-9_-]{16,}|AIza[0-9A-Za-z_-]{35}|AKIA[0-9A-Z]{16}|(?i:bearer)\s+[A-Za-z0-9._~+/-]{16,}=*",
+    )
+    .expect("api key regex is valid")
+});
+
+/// A request handled by the gateway.
+#[derive(Debug, Serialize, TypedBuilder)]
+pub struct LogRecord {
+    pub request_id: Uuid,
+    pub request_created_at: DateTime<Utc>,
+    #[builder(default)]
+    pub router_id: Option<RouterId>,
+    pub provider: InferenceProvider,
+    #[builder(default)]
+    pub model: Option<String>,
+    pub path: String,
+    pub status: u16,
+    pub is_stream: bool,
+    /// Until the whole response was received.
+    #[serde(rename = "latency_ms", serialize_with = "serialize_millis")]
+    pub latency: Duration,
+    /// Until the first chunk of the response was received.
+    #[builder(default)]
+    #[serde(rename = "ttft_ms", serialize_with = "serialize_opt_millis")]
+    pub ttft: Option<Duration>,
+    /// The API key itself is never written.
+    #[builder(default)]
+    pub api_key_hash: Option<String>,
+    #[builder(default)]
+    pub usage: Option<Usage>,
+    #[builder(default)]
+    pub cost_usd: Option<Decimal>,
+    /// Written after redaction, as JSON if the body is JSON and as a string
+    /// otherwise.
+    #[serde(skip)]
+    pub request_body: Bytes,
+    #[serde(skip)]
+    pub response_body: Bytes,
+}
+
+#[allow(clippy::trivially_copy_pass_by_ref)]
+fn serialize_millis<S: serde::Serializer>(
+    duration: &Duration,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer
+        .serialize_u64(u64::try_from(duration.as_millis()).unwrap_or(u64::MAX))
+}
+
+#[allow(clippy::ref_option)]
+fn serialize_opt_millis<S: serde::Serializer>(
+    duration: &Option<Duration>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match duration {
+        Some(duration) => serialize_millis(duration, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+impl LogRecord {
+    fn to_json_line(
+        &self,
+        redaction: RedactionConfig,
+    ) -> Result<Vec<u8>, serde_json::Error> {
+        let mut value = serde_json::to_value(self)?;
+        if let Value::Object(map) = &mut value {
+            map.insert(
+                "request_body".to_string(),
+                redact_body(&self.request_body, redaction),
+            );
+            map.insert(
+                "response_body".to_string(),
+                redact_body(&self.response_body, redaction),
+            );
+        }
+        let mut line = serde_json::to_vec(&value)?;
+        line.push(b'\n');
+        Ok(line)
+    }
+}
+
+fn redact_body(body: &[u8], redaction: RedactionConfig) -> Value {
+    if body.is_empty() {
+        return Value::Null;
+    }
+    let mut text = String::from_utf8_lossy(body).into_owned();
+    if redaction.api_keys {
+        text = API_KEY_REGEX.replace_all(&text, REDACTED).into_owned();
+    }
+    if let Ok(mut value) = serde_json::from_str::<Value>(&text) {
+        if redaction.message_content {
+            redact_content(&mut value);
+        }
+        return value;
+    }
+    if !redaction.message_content {
+        return Value::String(text);
+    }
+
+    // the events of a stream
+    let lines = text
+        .lines()
+        .map(|line| {
+            let data = line.strip_prefix("data:").and_then(|data| {
+                serde_json::from_str::<Value>(data.trim()).ok()
+            });
+            match data {
+                Some(mut data) => {
+                    redact_content(&mut data);
+                    format!("data: {data}")
+                }
+                None => line.to_string(),
+            }
+        })
+        .collect::<Vec<_>>();
+    Value::String(lines.join("\n"))
+}
+
+fn redact_content(value: &mut Value) {
+    let texts = Texts::find(value);
+    let redacted = vec![REDACTED.to_string(); texts.texts.len()];
+    texts.replace(value, redacted);
+}
+
+/// Sends records to the [`LogSinkWriter`].
+#[derive(Debug)]
+pub struct LogSink {
+    tx: mpsc::Sender<LogRecord>,
+    /// Taken by the writer when it is created.
+    rx: Mutex<Option<mpsc::Receiver<LogRecord>>>,
+}
+
+impl LogSink {
+    #[must_use]
+    pub fn new(config: &LogSinkConfig) -> Self {
+        let (tx, rx) = mpsc::channel(config.buffer_size.max(1));
+        Self {
+            tx,
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    /// Queue `record` to be written, without waiting for space in the
+    /// buffer.
+    pub fn send(&self, record: LogRecord) {
+        match self.tx.try_send(record) {
+            Ok(()) => {}
+            Err(TrySendError::Full(record)) => {
+                tracing::warn!(
+                    request_id = %record.request_id,
+                    "log sink buffer is full, dropping record"
+                );
+            }
+            Err(TrySendError::Closed(_)) => {
+                tracing::debug!("log sink is closed, dropping record");
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Backend {
+    File(PathBuf),
+    S3 {
+        prefix: String,
+        minio: BaseMinioClient,
+    },
+}
+
+impl Backend {
+    async fn write(&self, lines: Vec<u8>) -> Result<(), LoggerError> {
+        match self {
+            Self::File(path) => {
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .map_err(LoggerError::WriteFile)?;
+                file.write_all(&lines)
+                    .await
+                    .map_err(LoggerError::WriteFile)?;
+                file.flush().await.map_err(LoggerError::WriteFile)?;
+            }
+            Self::S3 { prefix, minio } => {
+                let object = format!(
+                    "{prefix}{}-{}.jsonl",
+                    Utc::now().format("%Y/%m/%d/%H%M%S"),
+                    Uuid::new_v4()
+                );
+                let signed_url =
+                    minio.put_object(&object).sign(PUT_OBJECT_SIGN_DURATION);
+                minio
+                    .client
+                    .put(signed_url)
+                    .header(http::header::CONTENT_TYPE, "application/x-ndjson")
+                    .body(lines)
+                    .send()
+                    .await
+                    .map_err(LoggerError::FailedToSendRequest)?
+                    .error_for_status()
+                    .map_err(LoggerError::ResponseError)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes the records sent to the [`LogSink`] of the app in batches.
+#[derive(Debug)]
+pub struct LogSinkWriter {
+    config: LogSinkConfig,
+    rx: mpsc::Receiver<LogRecord>,
+    backend: Backend,
+}
+
+impl LogSinkWriter {
+    /// `None` if the log sink is disabled.
+    pub fn new(app_state: &AppState) -> Result<Option<Self>, InitError> {
+        let (Some(config), Some(sink)) =
+            (&app_state.config().log_sink, &app_state.0.log_sink)
+        else {
+            return Ok(None);
+        };
+        let Some(rx) = sink.rx.lock().expect("never poisoned").take() else {
+            tracing::warn!("log sink writer already created");
+            return Ok(None);
+        };
+        let backend = match &config.backend {
+            LogSinkBackend::File { path } => Backend::File(path.clone()),
+            LogSinkBackend::S3 { prefix, bucket } => Backend::S3 {
+                prefix: prefix.clone(),
+                minio: BaseMinioClient::new(bucket.clone())?,
+            },
+        };
+        Ok(Some(Self {
+            config: config.clone(),
+            rx,
+            backend,
+        }))
+    }
+
+    async fn run_until_shutdown(mut self, mut token: Token) {
+        let batch_size = self.config.batch_size.max(1);
+        let mut batch = Vec::with_capacity(batch_size);
+        let mut interval = tokio::time::interval(self.config.flush_interval);
+        interval
+            .set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                biased;
+                () = &mut token => break,
+                record = self.rx.recv() => {
+                    let Some(record) = record else { break };
+                    batch.push(record);
+                    if batch.len() >= batch_size {
+                        self.flush(&mut batch).await;
+                    }
+                }
+                _ = interval.tick() => self.flush(&mut batch).await,
+            }
+        }
+
+        // write the records that were already sent before shutting down
+        self.rx.close();
+        while let Ok(record) = self.rx.try_recv() {
+            batch.push(record);
+        }
+        self.flush(&mut batch).await;
+        tracing::debug!(
+            name = "log-sink-writer",
+            "task shut down successfully"
+        );
+    }
+
+    async fn flush(&self, batch: &mut Vec<LogRecord>) {
+        if batch.is_empty() {
+            return;
+        }
+        let records = batch.len();
+        let mut lines = Vec::new();
+        for record in batch.drain(..) {
+            match record.to_json_line(self.config.redact) {
+                Ok(line) => lines.extend(line),
+                Err(error) => {
+                    tracing::error!(%error, "failed to serialize log record");
+                }
+            }
+        }
+        match self.backend.write(lines).await {
+            Ok(()) => tracing::trace!(records, "wrote log records"),
+            Err(error) => {
+                tracing::error!(%error, records, "failed to write log records");
+            }
+        }
+    }
+}
+
+impl meltdown::Service for LogSinkWriter {
+    type Future = BoxFuture<'static, Result<(), RuntimeError>>;
+
+    fn run(self, token: Token) -> Self::Future {
+        Box::pin(async move {
+            self.run_until_shutdown(token).await;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(request_body: &str, response_body: &str) -> LogRecord {
+        LogRecord::builder()
+            .request_id(Uuid::nil())
+            .request_created_at(DateTime::UNIX_EPOCH)
+            .provider(InferenceProvider::OpenAI)
+            .model(Some("gpt-4o-mini".to_string()))
+            .path("/v1/chat/completions".to_string())
+            .status(200)
+            .is_stream(false)
+            .latency(Duration::from_millis(120))
+            .usage(Some(Usage {
+                prompt_tokens: 19,
+                completion_tokens: 10,
+                cached_prompt_tokens: 0,
+            }))
+            .request_body(Bytes::copy_from_slice(request_body.as_bytes()))
+            .response_body(Bytes::copy_from_slice(response_body.as_bytes()))
+            .build()
+    }
+
+    #[test]
+    fn test_json_line() {
+        let record = record(
+            r#"{"messages":[{"role":"user","content":"my key is sk-abcdefghijklmnopqrstuvwx"}]}"#,
+            r#"{"choices":[{"message":{"content":"Hello!"}}]}"#,
+        );
+        let line = record.to_json_line(RedactionConfig::default()).unwrap();
+        assert_eq!(line.last(), Some(&b'\n'));
+        let value = serde_json::from_slice::<Value>(&line).unwrap();
+        assert_eq!(value["provider"], "openai");
+        assert_eq!(value["model"], "gpt-4o-mini");
+        assert_eq!(value["latency_ms"], 120);
+        assert_eq!(value["usage"]["prompt_tokens"], 19);
+        assert_eq!(
+            value["request_body"]["messages"][0]["content"],
+            "my key is [REDACTED]"
+        );
+        assert_eq!(
+            value["response_body"]["choices"][0]["message"]["content"],
+            "Hello!"
+        );
+    }
+
+    #[test]
+    fn test_redact_message_content_of_stream() {
+        let body = b"data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\ndata: [DONE]\n\n";
+        let redaction = RedactionConfig {
+            api_keys: true,
+            message_content: true,
+        };
+        assert_eq!(
+            redact_body(body, redaction),
+            Value::String(
+                "data: {\"choices\":[{\"delta\":{\"content\":\"[REDACTED]\"\
+                 }}]}\n\ndata: [DONE]\n"
+                    .to_string()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_backend_appends_lines() {
+        let path = std::env::temp_dir()
+            .join(format!("log-sink-{}.jsonl", Uuid::new_v4()));
+        let backend = Backend::File(path.clone());
+        for _ in 0..2 {
+            let line = record("{}", "{}")
+                .to_json_line(RedactionConfig::default())
+                .unwrap();
+            backend.write(line).await.unwrap();
+        }
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(contents.lines().count(), 2);
+    }
+}
@@ -1,17 +1,25 @@
 use std::{path::PathBuf, time::Duration};
 
 use ai_gateway::{
+    admin::AdminServer,
     app::App,
+    budgets::refresh::BudgetsRefresher,
     config::Config,
     control_plane::websocket::ControlPlaneClient,
-    discover::monitor::{
-        health::provider::HealthMonitor, rate_limit::RateLimitMonitor,
+    discover::{
+        monitor::{
+            health::provider::HealthMonitor, rate_limit::RateLimitMonitor,
+        },
+        provider_models::ModelDiscovery,
+        reload::ConfigReloader,
     },
     error::{init::InitError, runtime::RuntimeError},
+    logger::sink::LogSinkWriter,
     metrics::system::SystemMetrics,
     middleware::rate_limit,
     store::db_listener::DatabaseListener,
     utils::meltdown::TaggedService,
+    virtual_keys::refresh::KeysRefresher,
 };
 use clap::Parser;
 use meltdown::Meltdown;
@@ -43,11 +51,12 @@ async fn main() -> Result<(), RuntimeError> {
     rustls::crypto::ring::default_provider()
         .install_default()
         .expect("Failed to install rustls crypto provider");
-    let config = load_and_validate_config()?;
+    let args = Args::parse();
+    let config = load_and_validate_config(&args)?;
     let (logger_provider, tracer_provider, metrics_provider) =
         init_telemetry(&config)?;
 
-    run_app(config).await?;
+    run_app(config, args.config).await?;
 
     shutdown_telemetry(logger_provider, &tracer_provider, metrics_provider);
 
@@ -56,10 +65,9 @@ async fn main() -> Result<(), RuntimeError> {
     Ok(())
 }
 
-fn load_and_validate_config() -> Result<Config, RuntimeError> {
+fn load_and_validate_config(args: &Args) -> Result<Config, RuntimeError> {
     dotenvy::dotenv().ok();
-    let args = Args::parse();
-    let mut config = match Config::try_read(args.config) {
+    let mut config = match Config::try_read(args.config.clone()) {
         Ok(config) => config,
         Err(error) => {
             eprintln!("failed to read config: {error}");
@@ -103,7 +111,10 @@ fn init_telemetry(
     Ok((logger_provider, tracer_provider, metrics_provider))
 }
 
-async fn run_app(config: Config) -> Result<(), RuntimeError> {
+async fn run_app(
+    config: Config,
+    config_path: Option<PathBuf>,
+) -> Result<(), RuntimeError> {
     // 5 mins
     const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 5);
     let mut shutting_down = false;
@@ -122,17 +133,34 @@ async fn run_app(config: Config) -> Result<(), RuntimeError> {
             )
         });
 
+    let model_discovery = config
+        .model_discovery
+        .enabled
+        .then(|| ModelDiscovery::new(app.state.clone()))
+        .transpose()?;
+    let log_sink_writer = LogSinkWriter::new(&app.state)?;
+    let keys_refresher = KeysRefresher::new(app.state.clone());
+    let budgets_refresher = BudgetsRefresher::new(app.state.clone());
+    let config_reloader = ConfigReloader::new(
+        app.state.clone(),
+        config_path,
+        model_discovery.clone(),
+    );
+
     let mut tasks = vec![
         "shutdown-signals",
+        "config-reloader",
         "gateway",
         "provider-health-monitor",
         "provider-rate-limit-monitor",
         "system-metrics",
     ];
-    let mut meltdown = Meltdown::new().register(TaggedService::new(
-        "shutdown-signals",
-        ai_gateway::utils::meltdown::wait_for_shutdown_signals,
-    ));
+    let mut meltdown = Meltdown::new()
+        .register(TaggedService::new(
+            "shutdown-signals",
+            ai_gateway::utils::meltdown::wait_for_shutdown_signals,
+        ))
+        .register(TaggedService::new("config-reloader", config_reloader));
 
     if config.helicone.is_auth_enabled()
         && config.deployment_target.is_sidecar()
@@ -162,6 +190,14 @@ async fn run_app(config: Config) -> Result<(), RuntimeError> {
         tasks.push("database-listener");
     }
 
+    if config.admin.is_enabled() && config.admin.port.is_some() {
+        meltdown = meltdown.register(TaggedService::new(
+            "admin-server",
+            AdminServer::new(app.clone()),
+        ));
+        tasks.push("admin-server");
+    }
+
     meltdown = meltdown
         .register(TaggedService::new("gateway", app))
         .register(TaggedService::new(
@@ -174,6 +210,34 @@ async fn run_app(config: Config) -> Result<(), RuntimeError> {
         ))
         .register(TaggedService::new("system-metrics", SystemMetrics));
 
+    if let Some(model_discovery) = model_discovery {
+        meltdown = meltdown
+            .register(TaggedService::new("model-discovery", model_discovery));
+        tasks.push("model-discovery");
+    }
+
+    if let Some(log_sink_writer) = log_sink_writer {
+        meltdown = meltdown
+            .register(TaggedService::new("log-sink-writer", log_sink_writer));
+        tasks.push("log-sink-writer");
+    }
+
+    if let Some(keys_refresher) = keys_refresher {
+        meltdown = meltdown.register(TaggedService::new(
+            "virtual-keys-refresher",
+            keys_refresher,
+        ));
+        tasks.push("virtual-keys-refresher");
+    }
+
+    if let Some(budgets_refresher) = budgets_refresher {
+        meltdown = meltdown.register(TaggedService::new(
+            "budgets-refresher",
+            budgets_refresher,
+        ));
+        tasks.push("budgets-refresher");
+    }
+
     if let Some(rate_limiting_cleanup_service) = rate_limiting_cleanup_service {
         meltdown = meltdown.register(TaggedService::new(
             "rate-limiting-cleanup",
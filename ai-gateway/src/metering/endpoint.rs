@@ -0,0 +1,129 @@
+//! `GET /v1/usage`, the usage totals of the gateway.
+//!
+//! Requires the admin key, see [`AdminConfig`], and is only served if one is
+//! configured. The totals can be filtered with the `provider`, `model` and
+//! `api-key-hash` query parameters.
+//!
+//! [`AdminConfig`]: crate::config::admin::AdminConfig
+use std::{
+    future::{Ready, ready},
+    task::{Context, Poll},
+};
+
+use axum_core::response::{IntoResponse, Response};
+use futures::future::Either;
+use http::{Method, Request, StatusCode};
+use serde::Serialize;
+use tower::{Layer, Service};
+
+use super::{UsageFilter, UsageKey, UsageTotals};
+use crate::{
+    admin::on_admin_port,
+    app_state::AppState,
+    error::{api::ApiError, auth::AuthError},
+    types::json::Json,
+};
+
+pub const USAGE_PATH: &str = "/v1/usage";
+
+#[derive(Debug, Clone)]
+pub struct UsageLayer {
+    app_state: AppState,
+}
+
+impl UsageLayer {
+    #[must_use]
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+}
+
+impl<S> Layer<S> for UsageLayer {
+    type Service = UsageService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        UsageService {
+            inner,
+            app_state: self.app_state.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct UsageService<S> {
+    inner: S,
+    app_state: AppState,
+}
+
+impl<S, ReqBody> Service<Request<ReqBody>> for UsageService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Either<Ready<Result<Self::Response, Self::Error>>, S::Future>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let admin = &self.app_state.config().admin;
+        if req.uri().path() != USAGE_PATH || !admin.serves(on_admin_port(&req))
+        {
+            return Either::Right(self.inner.call(req));
+        }
+
+        let authorization = req
+            .headers()
+            .get(http::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok());
+        let response = if req.method() != Method::GET {
+            StatusCode::METHOD_NOT_ALLOWED.into_response()
+        } else if admin.is_authorized(authorization) {
+            usage_response(&self.app_state, req.uri().query())
+        } else {
+            ApiError::Authentication(AuthError::InvalidCredentials)
+                .into_response()
+        };
+        Either::Left(ready(Ok(response)))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct UsageResponse {
+    data: Vec<UsageEntry>,
+    total: UsageTotals,
+}
+
+#[derive(Debug, Serialize)]
+struct UsageEntry {
+    #[serde(flatten)]
+    key: UsageKey,
+    #[serde(flatten)]
+    totals: UsageTotals,
+}
+
+fn usage_response(app_state: &AppState, query: Option<&str>) -> Response {
+    let mut filter = UsageFilter::default();
+    for (name, value) in
+        url::form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+    {
+        match name.as_ref() {
+            "provider" => filter.provider = value.parse().ok(),
+            "model" => filter.model = Some(value.into_owned()),
+            "api-key-hash" => filter.api_key_hash = Some(value.into_owned()),
+            _ => {}
+        }
+    }
+
+    let (totals, total) = app_state.0.metering.totals(&filter);
+    let data = totals
+        .into_iter()
+        .map(|(key, totals)| UsageEntry { key, totals })
+        .collect();
+    Json(UsageResponse { data, total }).into_response()
+}
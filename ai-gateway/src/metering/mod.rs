@@ -0,0 +1,319 @@
+//! Usage accounting, i.e. the tokens used by requests and their cost, per API
+//! key, provider and model.
+//!
+//! The usage of every successful request is read from the response of the
+//! provider and added to in-memory totals, which are exposed by the
+//! `/v1/usage` admin endpoint, see [`endpoint`]. Totals are per replica of
+//! the gateway and reset on restart.
+pub mod endpoint;
+pub mod usage;
+
+use std::sync::Mutex;
+
+use opentelemetry::KeyValue;
+use rust_decimal::Decimal;
+use rustc_hash::FxHashMap as HashMap;
+use serde::Serialize;
+
+pub use self::usage::Usage;
+use crate::{
+    app_state::AppState,
+    config::pricing::PricingConfig,
+    control_plane::types::hash_key,
+    types::{
+        extensions::AuthContext, model_id::ModelId,
+        provider::InferenceProvider, router::RouterId,
+    },
+};
+
+/// The usage and cost of a request, added to the response extensions so
+/// that they can be sent in the response headers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestCost {
+    pub usage: Usage,
+    /// `None` if the model has no pricing.
+    pub cost_usd: Option<Decimal>,
+}
+
+impl RequestCost {
+    #[must_use]
+    pub fn new(
+        pricing: &PricingConfig,
+        provider: &InferenceProvider,
+        model: Option<&ModelId>,
+        usage: Usage,
+    ) -> Self {
+        let cost_usd = model
+            .and_then(|model| pricing.get(provider, model))
+            .map(|model_pricing| model_pricing.cost(&usage));
+        Self { usage, cost_usd }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct UsageKey {
+    /// The hash of the API key of the request, `None` if the request was not
+    /// authenticated.
+    pub api_key_hash: Option<String>,
+    pub provider: InferenceProvider,
+    pub model: String,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cached_prompt_tokens: u64,
+    /// The cost of the requests of models with pricing.
+    pub cost_usd: Decimal,
+    /// Requests of models without pricing, which are not part of
+    /// `cost_usd`.
+    pub unpriced_requests: u64,
+}
+
+impl UsageTotals {
+    fn add(&mut self, cost: &RequestCost) {
+        self.requests += 1;
+        self.prompt_tokens += cost.usage.prompt_tokens;
+        self.completion_tokens += cost.usage.completion_tokens;
+        self.cached_prompt_tokens += cost.usage.cached_prompt_tokens;
+        match cost.cost_usd {
+            Some(cost_usd) => self.cost_usd += cost_usd,
+            None => self.unpriced_requests += 1,
+        }
+    }
+
+    fn merge(&mut self, other: &Self) {
+        self.requests += other.requests;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cached_prompt_tokens += other.cached_prompt_tokens;
+        self.cost_usd += other.cost_usd;
+        self.unpriced_requests += other.unpriced_requests;
+    }
+}
+
+/// Only the totals matching every set field are returned.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UsageFilter {
+    pub api_key_hash: Option<String>,
+    pub provider: Option<InferenceProvider>,
+    pub model: Option<String>,
+}
+
+impl UsageFilter {
+    fn matches(&self, key: &UsageKey) -> bool {
+        self.api_key_hash
+            .as_ref()
+            .is_none_or(|hash| key.api_key_hash.as_ref() == Some(hash))
+            && self
+                .provider
+                .as_ref()
+                .is_none_or(|provider| &key.provider == provider)
+            && self.model.as_ref().is_none_or(|model| &key.model == model)
+    }
+}
+
+/// Records the usage of requests.
+#[derive(Debug, Default)]
+pub struct Metering {
+    totals: Mutex<HashMap<UsageKey, UsageTotals>>,
+}
+
+impl Metering {
+    /// Add the usage reported in `response_body` to the totals, returning
+    /// the cost of the request.
+    ///
+    /// Does nothing if the response doesn't report any usage, e.g. for
+    /// error responses.
+    pub fn record(
+        &self,
+        pricing: &PricingConfig,
+        auth_ctx: Option<&AuthContext>,
+        provider: &InferenceProvider,
+        model: Option<&ModelId>,
+        response_body: &[u8],
+    ) -> Option<RequestCost> {
+        let usage = Usage::from_response_body(response_body)?;
+        let cost = RequestCost::new(pricing, provider, model, usage);
+        let key = UsageKey {
+            api_key_hash: auth_ctx
+                .map(|auth_ctx| hash_key(auth_ctx.api_key.expose())),
+            provider: provider.clone(),
+            model: model
+                .map_or_else(|| "unknown".to_string(), ToString::to_string),
+        };
+        tracing::trace!(?key, ?cost, "recording usage");
+        self.totals
+            .lock()
+            .expect("never poisoned")
+            .entry(key)
+            .or_default()
+            .add(&cost);
+        Some(cost)
+    }
+
+    /// The totals matching `filter`, and their sum.
+    #[must_use]
+    pub fn totals(
+        &self,
+        filter: &UsageFilter,
+    ) -> (Vec<(UsageKey, UsageTotals)>, UsageTotals) {
+        let totals = self
+            .totals
+            .lock()
+            .expect("never poisoned")
+            .iter()
+            .filter(|(key, _)| filter.matches(key))
+            .map(|(key, totals)| (key.clone(), *totals))
+            .collect::<Vec<_>>();
+        let sum = totals.iter().fold(UsageTotals::default(), |mut sum, t| {
+            sum.merge(&t.1);
+            sum
+        });
+        (totals, sum)
+    }
+}
+
+impl AppState {
+    /// Add the tokens of a request to the `tokens` metric.
+    pub(crate) fn record_tokens(
+        &self,
+        router_id: Option<&RouterId>,
+        provider: &InferenceProvider,
+        model: Option<&ModelId>,
+        usage: &Usage,
+    ) {
+        let router_id =
+            router_id.map_or_else(|| "none".to_string(), ToString::to_string);
+        let model =
+            model.map_or_else(|| "unknown".to_string(), ToString::to_string);
+        let attributes = |token_type: &'static str| {
+            [
+                KeyValue::new("router_id", router_id.clone()),
+                KeyValue::new("provider", provider.to_string()),
+                KeyValue::new("model", model.clone()),
+                KeyValue::new("type", token_type),
+            ]
+        };
+        let tokens = &self.0.metrics.tokens;
+        tokens.add(usage.prompt_tokens, &attributes("prompt"));
+        tokens.add(usage.completion_tokens, &attributes("completion"));
+    }
+
+    /// Add the cost of a request to the spend of its virtual key, if it was
+    /// made with one, and to the budgets it counts toward.
+    ///
+    /// Called once per request, whether or not observability is enabled.
+    pub(crate) async fn add_spend(
+        &self,
+        auth_ctx: Option<&AuthContext>,
+        router_id: Option<&RouterId>,
+        provider: &InferenceProvider,
+        cost_usd: Decimal,
+    ) {
+        let api_key_hash =
+            auth_ctx.map(|auth_ctx| hash_key(auth_ctx.api_key.expose()));
+        if let Some(virtual_keys) = &self.0.virtual_keys
+            && let Some(api_key_hash) = &api_key_hash
+        {
+            virtual_keys.add_spend(api_key_hash, cost_usd).await;
+        }
+        self.0
+            .budgets
+            .add_spend(api_key_hash.as_deref(), router_id, provider, cost_usd)
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPENAI_RESPONSE: &[u8] =
+        br#"{"usage":{"prompt_tokens":1000,"completion_tokens":100}}"#;
+
+    #[test]
+    fn test_record_aggregates_by_model() {
+        let metering = Metering::default();
+        let pricing = PricingConfig::default();
+        let model = ModelId::from_str_and_provider(
+            InferenceProvider::OpenAI,
+            "gpt-4o-mini",
+        )
+        .unwrap();
+        for _ in 0..2 {
+            let cost = metering
+                .record(
+                    &pricing,
+                    None,
+                    &InferenceProvider::OpenAI,
+                    Some(&model),
+                    OPENAI_RESPONSE,
+                )
+                .unwrap();
+            // 1000 * 0.15 + 100 * 0.60 = 210 per million
+            assert_eq!(cost.cost_usd, Some(Decimal::new(21, 5)));
+        }
+
+        let (totals, sum) = metering.totals(&UsageFilter::default());
+        assert_eq!(totals.len(), 1);
+        assert_eq!(sum.requests, 2);
+        assert_eq!(sum.prompt_tokens, 2000);
+        assert_eq!(sum.cost_usd, Decimal::new(42, 5));
+        assert_eq!(sum.unpriced_requests, 0);
+    }
+
+    #[test]
+    fn test_unpriced_model() {
+        let metering = Metering::default();
+        let model = ModelId::from_str_and_provider(
+            InferenceProvider::OpenAI,
+            "not-a-real-model",
+        )
+        .unwrap();
+        let cost = metering
+            .record(
+                &PricingConfig::default(),
+                None,
+                &InferenceProvider::OpenAI,
+                Some(&model),
+                OPENAI_RESPONSE,
+            )
+            .unwrap();
+        assert_eq!(cost.cost_usd, None);
+        let (_, sum) = metering.totals(&UsageFilter::default());
+        assert_eq!(sum.unpriced_requests, 1);
+        assert_eq!(sum.cost_usd, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_totals_filter() {
+        let metering = Metering::default();
+        let pricing = PricingConfig::default();
+        for (provider, model) in [
+            (InferenceProvider::OpenAI, "gpt-4o"),
+            (InferenceProvider::Anthropic, "claude-3-5-haiku"),
+        ] {
+            let model = ModelId::from_str_and_provider(provider.clone(), model)
+                .unwrap();
+            metering.record(
+                &pricing,
+                None,
+                &provider,
+                Some(&model),
+                OPENAI_RESPONSE,
+            );
+        }
+
+        let filter = UsageFilter {
+            provider: Some(InferenceProvider::Anthropic),
+            ..Default::default()
+        };
+        let (totals, sum) = metering.totals(&filter);
+        assert_eq!(totals.len(), 1);
+        assert_eq!(totals[0].0.provider, InferenceProvider::Anthropic);
+        assert_eq!(sum.requests, 1);
+    }
+}
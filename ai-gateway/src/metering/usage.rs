@@ -0,0 +1,280 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// The tokens used by a request, as reported by the provider.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    /// Includes `cached_prompt_tokens`.
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cached_prompt_tokens: u64,
+}
+
+impl Usage {
+    /// The usage reported in a response body of a provider, in the native
+    /// format of the provider.
+    ///
+    /// The body may also be a stream, as server-sent events or newline
+    /// delimited JSON. Providers report the usage of a stream either once
+    /// or cumulatively in several chunks, so the largest count of each
+    /// chunk is taken.
+    ///
+    /// Returns `None` if the body doesn't report any usage.
+    #[must_use]
+    pub fn from_response_body(body: &[u8]) -> Option<Self> {
+        if let Ok(value) = serde_json::from_slice::<Value>(body) {
+            return Self::from_value(&value);
+        }
+
+        let mut usage: Option<Self> = None;
+        for line in body.split(|b| *b == b'\n') {
+            let mut line = line.trim_ascii();
+            // stream chunks logged by the gateway get a `data:` prefix of
+            // their own, even if they already had one
+            while let Some(data) = line.strip_prefix(b"data:") {
+                line = data.trim_ascii();
+            }
+            let Ok(value) = serde_json::from_slice::<Value>(line) else {
+                continue;
+            };
+            if let Some(chunk_usage) = Self::from_value(&value) {
+                usage = Some(usage.unwrap_or_default().max(chunk_usage));
+            }
+        }
+        usage
+    }
+
+    fn from_value(value: &Value) -> Option<Self> {
+        // Gemini
+        if let Some(usage) = value.get("usageMetadata") {
+            return Some(Self {
+                prompt_tokens: count(usage, "promptTokenCount"),
+                completion_tokens: count(usage, "candidatesTokenCount")
+                    + count(usage, "thoughtsTokenCount"),
+                cached_prompt_tokens: count(usage, "cachedContentTokenCount"),
+            });
+        }
+        // Ollama
+        if value.get("prompt_eval_count").is_some()
+            || value.get("eval_count").is_some()
+        {
+            return Some(Self {
+                prompt_tokens: count(value, "prompt_eval_count"),
+                completion_tokens: count(value, "eval_count"),
+                cached_prompt_tokens: 0,
+            });
+        }
+
+        let usage = value
+            .get("usage")
+            // Anthropic `message_start` stream events
+            .or_else(|| value.pointer("/message/usage"))
+            // Bedrock `metadata` stream events
+            .or_else(|| value.pointer("/metadata/usage"))
+            // Cohere embeddings
+            .or_else(|| value.pointer("/meta/billed_units"))
+            .filter(|usage| usage.is_object())?;
+        if usage.get("prompt_tokens").is_some() {
+            // OpenAI and OpenAI compatible providers
+            Some(Self {
+                prompt_tokens: count(usage, "prompt_tokens"),
+                completion_tokens: count(usage, "completion_tokens"),
+                cached_prompt_tokens: usage
+                    .pointer("/prompt_tokens_details/cached_tokens")
+                    .and_then(Value::as_u64)
+                    .unwrap_or_default(),
+            })
+        } else if usage.get("inputTokens").is_some() {
+            // Bedrock, where `inputTokens` excludes the cached tokens
+            let cached = count(usage, "cacheReadInputTokens");
+            Some(Self {
+                prompt_tokens: count(usage, "inputTokens")
+                    + cached
+                    + count(usage, "cacheWriteInputTokens"),
+                completion_tokens: count(usage, "outputTokens"),
+                cached_prompt_tokens: cached,
+            })
+        } else if usage.get("input_tokens").is_some()
+            || usage.get("output_tokens").is_some()
+        {
+            // Anthropic, where `input_tokens` excludes the cached tokens
+            let cached = count(usage, "cache_read_input_tokens");
+            Some(Self {
+                prompt_tokens: count(usage, "input_tokens")
+                    + cached
+                    + count(usage, "cache_creation_input_tokens"),
+                completion_tokens: count(usage, "output_tokens"),
+                cached_prompt_tokens: cached,
+            })
+        } else {
+            None
+        }
+    }
+
+    #[must_use]
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    fn max(self, other: Self) -> Self {
+        Self {
+            prompt_tokens: self.prompt_tokens.max(other.prompt_tokens),
+            completion_tokens: self
+                .completion_tokens
+                .max(other.completion_tokens),
+            cached_prompt_tokens: self
+                .cached_prompt_tokens
+                .max(other.cached_prompt_tokens),
+        }
+    }
+}
+
+fn count(value: &Value, field: &str) -> u64 {
+    value.get(field).and_then(Value::as_u64).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openai_usage() {
+        let body = br#"{
+            "id": "chatcmpl-123",
+            "usage": {
+                "prompt_tokens": 120,
+                "completion_tokens": 30,
+                "total_tokens": 150,
+                "prompt_tokens_details": { "cached_tokens": 100 }
+            }
+        }"#;
+        assert_eq!(
+            Usage::from_response_body(body),
+            Some(Usage {
+                prompt_tokens: 120,
+                completion_tokens: 30,
+                cached_prompt_tokens: 100,
+            })
+        );
+    }
+
+    #[test]
+    fn test_anthropic_usage_includes_cache_reads() {
+        let body = br#"{
+            "type": "message",
+            "usage": {
+                "input_tokens": 20,
+                "output_tokens": 10,
+                "cache_read_input_tokens": 100
+            }
+        }"#;
+        assert_eq!(
+            Usage::from_response_body(body),
+            Some(Usage {
+                prompt_tokens: 120,
+                completion_tokens: 10,
+                cached_prompt_tokens: 100,
+            })
+        );
+    }
+
+    #[test]
+    fn test_anthropic_stream_usage() {
+        let body = b"data: event: message_start\ndata: {\"type\":\"message_start\",\"message\":{\"usage\":{\"input_tokens\":25,\"output_tokens\":1}}}\n\n\
+            data: event: message_delta\ndata: {\"type\":\"message_delta\",\"usage\":{\"output_tokens\":15}}\n\n";
+        assert_eq!(
+            Usage::from_response_body(body),
+            Some(Usage {
+                prompt_tokens: 25,
+                completion_tokens: 15,
+                cached_prompt_tokens: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_openai_stream_usage_in_last_chunk() {
+        let body = b"data: data: {\"choices\":[{\"delta\":{\"content\":\"hi\"}}]}\n\n\n\n\
+            data: data: {\"choices\":[],\"usage\":{\"prompt_tokens\":9,\"completion_tokens\":2}}\n\n\n\n\
+            data: data: [DONE]\n\n\n\n";
+        assert_eq!(
+            Usage::from_response_body(body),
+            Some(Usage {
+                prompt_tokens: 9,
+                completion_tokens: 2,
+                cached_prompt_tokens: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_bedrock_stream_usage() {
+        let body = b"data: {\"contentBlockDelta\":{\"delta\":{\"text\":\"hi\"},\"contentBlockIndex\":0}}\n\n\
+            data: {\"metadata\":{\"usage\":{\"inputTokens\":12,\"outputTokens\":4,\"totalTokens\":16},\"metrics\":{\"latencyMs\":100}}}\n\n";
+        assert_eq!(
+            Usage::from_response_body(body),
+            Some(Usage {
+                prompt_tokens: 12,
+                completion_tokens: 4,
+                cached_prompt_tokens: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_gemini_usage() {
+        let body = br#"{
+            "candidates": [],
+            "usageMetadata": {
+                "promptTokenCount": 50,
+                "candidatesTokenCount": 10,
+                "thoughtsTokenCount": 5,
+                "cachedContentTokenCount": 20
+            }
+        }"#;
+        assert_eq!(
+            Usage::from_response_body(body),
+            Some(Usage {
+                prompt_tokens: 50,
+                completion_tokens: 15,
+                cached_prompt_tokens: 20,
+            })
+        );
+    }
+
+    #[test]
+    fn test_ollama_usage() {
+        let body = br#"{"model":"llama3","done":true,"prompt_eval_count":26,"eval_count":290}"#;
+        assert_eq!(
+            Usage::from_response_body(body),
+            Some(Usage {
+                prompt_tokens: 26,
+                completion_tokens: 290,
+                cached_prompt_tokens: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_cohere_usage() {
+        let body = br#"{
+            "id": "embed-123",
+            "embeddings": {"float": [[0.1, 0.2]]},
+            "meta": {"billed_units": {"input_tokens": 7}}
+        }"#;
+        assert_eq!(
+            Usage::from_response_body(body),
+            Some(Usage {
+                prompt_tokens: 7,
+                completion_tokens: 0,
+                cached_prompt_tokens: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_no_usage() {
+        assert_eq!(Usage::from_response_body(br#"{"data":[]}"#), None);
+        assert_eq!(Usage::from_response_body(b"not json"), None);
+    }
+}
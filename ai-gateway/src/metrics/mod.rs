@@ -1,4 +1,5 @@
 pub mod attribute_extractor;
+pub mod prometheus;
 pub mod request_count;
 pub mod rolling_counter;
 pub mod system;
@@ -13,12 +14,34 @@ pub use self::rolling_counter::RollingCounter;
 #[derive(Debug, Clone)]
 pub struct Metrics {
     pub error_count: Counter<u64>,
+    /// 0 while the circuit of the provider is open.
+    ///
+    /// labels:
+    /// - `router_id`
+    /// - `provider`
     pub provider_health: Gauge<u64>,
     pub auth_attempts: Counter<u64>,
     pub auth_rejections: Counter<u64>,
     pub request_count: Counter<u64>,
     pub response_count: Counter<u64>,
     pub tfft_duration: Histogram<f64>,
+    /// labels:
+    /// - `router_id`
+    /// - `provider`
+    /// - `model`
+    /// - `type`: `prompt` or `completion`
+    pub tokens: Counter<u64>,
+    /// Requests of traffic split routers.
+    ///
+    /// labels:
+    /// - `router_id`
+    /// - `split`: `baseline` or `candidate`
+    /// - `model`
+    /// - `status`
+    pub split_requests: Counter<u64>,
+    /// Time until the response headers of requests of traffic split
+    /// routers, with the labels of `split_requests`.
+    pub split_latency: Histogram<f64>,
     pub cache: CacheMetrics,
     pub routers: RouterMetrics,
 }
@@ -32,7 +55,9 @@ impl Metrics {
             .build();
         let provider_health = meter
             .u64_gauge("provider_health")
-            .with_description("Upstream provider health")
+            .with_description(
+                "Upstream provider health, 0 while its circuit is open",
+            )
             .build();
         let auth_attempts = meter
             .u64_counter("auth_attempts")
@@ -55,6 +80,21 @@ impl Metrics {
             .with_unit("ms")
             .with_description("Time to first token duration")
             .build();
+        let tokens = meter
+            .u64_counter("tokens")
+            .with_description("Number of tokens used by upstream providers")
+            .build();
+        let split_requests = meter
+            .u64_counter("split_requests")
+            .with_description("Number of requests of traffic split routers")
+            .build();
+        let split_latency = meter
+            .f64_histogram("split_latency")
+            .with_unit("ms")
+            .with_description(
+                "Time until the response of traffic split router requests",
+            )
+            .build();
         let cache = CacheMetrics::new(meter);
         let routers = RouterMetrics::new(meter);
         Self {
@@ -65,6 +105,9 @@ impl Metrics {
             request_count,
             response_count,
             tfft_duration,
+            tokens,
+            split_requests,
+            split_latency,
             cache,
             routers,
         }
@@ -0,0 +1,102 @@
+use std::{
+    future::{Ready, ready},
+    task::{Context, Poll},
+};
+
+use axum_core::response::Response;
+use futures::future::Either;
+use http::{HeaderValue, Method, Request, StatusCode};
+use prometheus::{Encoder, Registry, TextEncoder};
+
+const METRICS_PATH: &str = "/metrics";
+
+/// Serves the metrics of the gateway in the Prometheus text format at
+/// `/metrics`, if enabled with the `telemetry.prometheus` config.
+///
+/// Other requests, and all requests when disabled, are passed through.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Layer;
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+}
+
+impl<S, ReqBody> tower::Service<Request<ReqBody>> for Service<S>
+where
+    S: tower::Service<Request<ReqBody>, Response = Response>,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Either<Ready<Result<Self::Response, Self::Error>>, S::Future>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        if req.method() == Method::GET
+            && req.uri().path() == METRICS_PATH
+            && let Some(registry) = telemetry::prometheus_registry()
+        {
+            Either::Left(ready(Ok(metrics_response(registry))))
+        } else {
+            Either::Right(self.inner.call(req))
+        }
+    }
+}
+
+fn metrics_response(registry: &Registry) -> Response {
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    if let Err(e) = encoder.encode(&registry.gather(), &mut body) {
+        tracing::error!(error = %e, "failed to encode prometheus metrics");
+        return http::Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(axum_core::body::Body::empty())
+            .expect("always valid if tests pass");
+    }
+    http::Response::builder()
+        .status(StatusCode::OK)
+        .header(
+            http::header::CONTENT_TYPE,
+            HeaderValue::from_static(prometheus::TEXT_FORMAT),
+        )
+        .body(axum_core::body::Body::from(body))
+        .expect("always valid if tests pass")
+}
+
+#[cfg(test)]
+mod tests {
+    use http_body_util::BodyExt;
+    use prometheus::{IntCounter, Opts};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_metrics_response() {
+        let registry = Registry::new();
+        let counter =
+            IntCounter::with_opts(Opts::new("request_count", "Requests"))
+                .unwrap();
+        registry.register(Box::new(counter.clone())).unwrap();
+        counter.inc_by(3);
+
+        let response = metrics_response(&registry);
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("request_count 3"), "{body}");
+    }
+}
@@ -1,4 +1,5 @@
 use axum_core::response::IntoResponse;
+use chrono::Utc;
 use futures::future::BoxFuture;
 use http::Request;
 use tower_http::auth::AsyncAuthorizeRequest;
@@ -12,9 +13,12 @@ use crate::{
     },
     types::{
         extensions::{AuthContext, RequestKind},
+        org::OrgId,
         router::RouterId,
         secret::Secret,
+        user::UserId,
     },
+    virtual_keys::{VirtualKey, VirtualKeys},
 };
 
 #[derive(Clone)]
@@ -103,6 +107,38 @@ impl AuthService {
             }
         }
     }
+
+    /// Authenticate a key issued by the gateway, checking that it may be
+    /// used with `router_id`.
+    async fn authenticate_virtual_key(
+        app_state: &AppState,
+        virtual_keys: &VirtualKeys,
+        api_key: &str,
+        router_id: Option<&RouterId>,
+    ) -> Result<(AuthContext, VirtualKey), ApiError> {
+        let api_key_without_bearer = api_key.replace("Bearer ", "");
+        let Some(virtual_key) =
+            virtual_keys.get(&hash_key(&api_key_without_bearer)).await
+        else {
+            return Err(AuthError::InvalidCredentials.into());
+        };
+        virtual_key.authorize(router_id, Utc::now())?;
+        let org_id = app_state
+            .0
+            .control_plane_state
+            .read()
+            .await
+            .state
+            .as_ref()
+            .map(|state| state.auth.organization_id)
+            .unwrap_or_default();
+        let auth_ctx = AuthContext {
+            api_key: Secret::from(api_key_without_bearer),
+            user_id: UserId::new(virtual_key.id),
+            org_id,
+        };
+        Ok((auth_ctx, virtual_key))
+    }
 }
 
 impl<B> AsyncAuthorizeRequest<B> for AuthService
@@ -120,7 +156,10 @@ where
     fn authorize(&mut self, mut request: Request<B>) -> Self::Future {
         let app_state = self.app_state.clone();
         Box::pin(async move {
-            if app_state.0.config.helicone.is_auth_disabled() {
+            let virtual_keys = app_state.0.virtual_keys.as_ref();
+            let is_auth_disabled =
+                app_state.0.config.helicone.is_auth_disabled();
+            if is_auth_disabled && virtual_keys.is_none() {
                 tracing::trace!("auth middleware: auth disabled");
                 return Ok(request);
             }
@@ -139,16 +178,42 @@ where
             let request_kind = request.extensions().get::<RequestKind>();
             let router_id = request.extensions().get::<RouterId>();
 
-            match Self::authenticate_request_inner(
-                app_state.clone(),
-                api_key,
-                request_kind,
-                router_id,
-            )
-            .await
-            {
-                Ok(auth_ctx) => {
+            let result = match virtual_keys {
+                Some(virtual_keys)
+                    if VirtualKeys::is_virtual_key(
+                        api_key.trim_start_matches("Bearer "),
+                    ) =>
+                {
+                    Self::authenticate_virtual_key(
+                        &app_state,
+                        virtual_keys,
+                        api_key,
+                        router_id,
+                    )
+                    .await
+                    .map(|(auth_ctx, virtual_key)| {
+                        (auth_ctx, Some(virtual_key))
+                    })
+                }
+                // only virtual keys are accepted
+                _ if is_auth_disabled => {
+                    Err(AuthError::InvalidCredentials.into())
+                }
+                _ => Self::authenticate_request_inner(
+                    app_state.clone(),
+                    api_key,
+                    request_kind,
+                    router_id,
+                )
+                .await
+                .map(|auth_ctx| (auth_ctx, None)),
+            };
+            match result {
+                Ok((auth_ctx, virtual_key)) => {
                     request.extensions_mut().insert(auth_ctx);
+                    if let Some(virtual_key) = virtual_key {
+                        request.extensions_mut().insert(virtual_key);
+                    }
                     Ok(request)
                 }
                 Err(e) => {
@@ -156,7 +221,11 @@ where
                         match auth_error {
                             AuthError::MissingAuthorizationHeader
                             | AuthError::InvalidCredentials
-                            | AuthError::ProviderKeyNotFound => {
+                            | AuthError::ProviderKeyNotFound
+                            | AuthError::ExpiredCredentials
+                            | AuthError::RouterNotPermitted
+                            | AuthError::ModelNotPermitted(_)
+                            | AuthError::SpendLimitExceeded => {
                                 app_state.0.metrics.auth_rejections.add(1, &[]);
                             }
                         }
@@ -622,7 +622,12 @@ fn get_hasher(parts: &Parts, body: &Bytes, seed: Option<&str>) -> FxHasher {
     if let Some(pq) = parts.uri.path_and_query() {
         pq.hash(&mut hasher);
     }
-    body.hash(&mut hasher);
+    // hash the parsed body so that requests which only differ in whitespace
+    // or the order of their fields share a cache entry
+    match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(value) => value.hash(&mut hasher),
+        Err(_) => body.hash(&mut hasher),
+    }
     hasher
 }
 
@@ -811,3 +816,43 @@ impl ResponseLike for CacheableResponse {
         &self.resp_headers
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(body: &str) -> u64 {
+        let (parts, ()) = http::Request::builder()
+            .uri("/router/my-router/chat/completions")
+            .body(())
+            .unwrap()
+            .into_parts();
+        get_hasher(&parts, &Bytes::from(body.to_string()), None).finish()
+    }
+
+    #[test]
+    fn key_ignores_whitespace_and_field_order() {
+        let compact = r#"{"model":"openai/gpt-4o-mini","temperature":0.5,"messages":[{"role":"user","content":"hi"}]}"#;
+        let reordered = r#"{
+            "messages": [{ "content": "hi", "role": "user" }],
+            "temperature": 0.5,
+            "model": "openai/gpt-4o-mini"
+        }"#;
+        assert_eq!(key(compact), key(reordered));
+    }
+
+    #[test]
+    fn key_depends_on_request_content() {
+        let base = r#"{"model":"openai/gpt-4o-mini","temperature":0.5,"messages":[{"role":"user","content":"hi"}]}"#;
+        let temperature = r#"{"model":"openai/gpt-4o-mini","temperature":0.7,"messages":[{"role":"user","content":"hi"}]}"#;
+        let messages = r#"{"model":"openai/gpt-4o-mini","temperature":0.5,"messages":[{"role":"user","content":"hello"}]}"#;
+        assert_ne!(key(base), key(temperature));
+        assert_ne!(key(base), key(messages));
+    }
+
+    #[test]
+    fn key_of_non_json_body_uses_raw_bytes() {
+        assert_eq!(key("not json"), key("not json"));
+        assert_ne!(key("not json"), key("not  json"));
+    }
+}
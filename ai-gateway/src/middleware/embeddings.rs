@@ -0,0 +1,428 @@
+//! Checks OpenAI embeddings requests against the model they are sent to, and
+//! splits requests with more inputs than the provider accepts at once into
+//! batches.
+//!
+//! Sits in front of the mapper, so it sees the request as the client sent
+//! it and the response after it has been mapped back to OpenAI's format.
+//! Batches are sent concurrently and their embeddings merged into a single
+//! response, in the order of the inputs.
+use std::{
+    str::FromStr,
+    task::{Context, Poll},
+};
+
+use base64::Engine;
+use bytes::Bytes;
+use futures::future::{BoxFuture, try_join_all};
+use http::header::CONTENT_LENGTH;
+use http_body_util::BodyExt;
+use tower::ServiceExt;
+
+use crate::{
+    app_state::AppState,
+    endpoints::{
+        ApiEndpoint, cohere, google,
+        openai::{
+            OpenAI,
+            embeddings::{
+                self, CreateEmbeddingRequest, CreateEmbeddingResponse,
+                EmbeddingInput, EmbeddingVector, EncodingFormat,
+            },
+        },
+    },
+    error::{
+        api::ApiError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+    middleware::mapper::model::ModelMapper,
+    types::{
+        body::Body, model_id::ModelId, provider::InferenceProvider,
+        request::Request, response::Response,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    app_state: AppState,
+    model_mapper: ModelMapper,
+}
+
+impl Layer {
+    #[must_use]
+    pub fn new(app_state: AppState, model_mapper: ModelMapper) -> Self {
+        Self {
+            app_state,
+            model_mapper,
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            app_state: self.app_state.clone(),
+            model_mapper: self.model_mapper.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    app_state: AppState,
+    model_mapper: ModelMapper,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request, Response = Response, Error = ApiError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "embeddings", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        if !matches!(
+            req.extensions().get::<ApiEndpoint>(),
+            Some(ApiEndpoint::OpenAI(OpenAI::Embeddings(_)))
+        ) {
+            return Box::pin(self.inner.call(req));
+        }
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut inner = self.inner.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        let app_state = self.app_state.clone();
+        let model_mapper = self.model_mapper.clone();
+        Box::pin(async move {
+            let provider =
+                req.extensions().get::<InferenceProvider>().cloned().ok_or(
+                    InternalError::ExtensionNotFound("InferenceProvider"),
+                )?;
+            let (mut parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            let request =
+                serde_json::from_slice::<CreateEmbeddingRequest>(&body)
+                    .map_err(InvalidRequestError::InvalidRequestBody)?;
+            let target_endpoint = ApiEndpoint::mapped(
+                ApiEndpoint::OpenAI(OpenAI::embeddings()),
+                &provider,
+            )?;
+            let source_model = ModelId::from_str(&request.model)
+                .map_err(InternalError::MapperError)?;
+            let target_model = model_mapper
+                .map_model(&source_model, &provider)
+                .map_err(InternalError::MapperError)?;
+            let max_dimensions =
+                app_state.providers().get(&provider).and_then(|config| {
+                    config
+                        .model_capabilities(&target_model)
+                        .embedding_dimensions
+                });
+            validate(
+                &request,
+                &target_endpoint,
+                max_dimensions.map(u32::from),
+            )?;
+
+            let batches = max_inputs(&target_endpoint).map_or_else(
+                || vec![request.input.clone()],
+                |max| request.input.batches(max),
+            );
+            let base64 =
+                request.encoding_format == Some(EncodingFormat::Base64);
+            if batches.len() == 1
+                && !base64
+                && matches!(target_endpoint, ApiEndpoint::OpenAI(_))
+            {
+                return inner
+                    .call(Request::from_parts(parts, Body::from(body)))
+                    .await;
+            }
+
+            parts.headers.remove(CONTENT_LENGTH);
+            let batch_request = |input: EmbeddingInput| {
+                let body = serde_json::to_vec(&CreateEmbeddingRequest {
+                    input,
+                    // providers other than OpenAI only return floats
+                    encoding_format: None,
+                    ..request.clone()
+                })
+                .map_err(|error| {
+                    InternalError::Serialize {
+                        ty: "CreateEmbeddingRequest",
+                        error,
+                    }
+                })?;
+                let mut batch = Request::new(Body::from(body));
+                *batch.method_mut() = parts.method.clone();
+                *batch.uri_mut() = parts.uri.clone();
+                *batch.version_mut() = parts.version;
+                *batch.headers_mut() = parts.headers.clone();
+                *batch.extensions_mut() = parts.extensions.clone();
+                Ok::<_, ApiError>(batch)
+            };
+            let batch_count = batches.len();
+            let requests = batches
+                .into_iter()
+                .map(batch_request)
+                .collect::<Result<Vec<_>, _>>()?;
+            tracing::trace!(
+                batches = batch_count,
+                "sending embeddings batches"
+            );
+            let responses = try_join_all(
+                requests
+                    .into_iter()
+                    .map(|request| inner.clone().oneshot(request)),
+            )
+            .await?;
+
+            let mut merged: Option<(
+                http::response::Parts,
+                CreateEmbeddingResponse,
+            )> = None;
+            for response in responses {
+                if !response.status().is_success() {
+                    return Ok(response);
+                }
+                let (response_parts, body) = response.into_parts();
+                let body = body
+                    .collect()
+                    .await
+                    .map_err(InternalError::CollectBodyError)?
+                    .to_bytes();
+                let batch =
+                    serde_json::from_slice::<CreateEmbeddingResponse>(&body)
+                        .map_err(|error| InternalError::Deserialize {
+                            ty: "CreateEmbeddingResponse",
+                            error,
+                        })?;
+                merged = Some(match merged {
+                    Some((parts, merged)) => (parts, merge(merged, batch)),
+                    None => (response_parts, batch),
+                });
+            }
+            let (mut response_parts, mut response) =
+                merged.ok_or(InternalError::Internal)?;
+            if response.model.is_empty() {
+                response.model = target_model.to_string();
+            }
+            if base64 {
+                encode_base64(&mut response);
+            }
+
+            let body = serde_json::to_vec(&response).map_err(|error| {
+                InternalError::Serialize {
+                    ty: "CreateEmbeddingResponse",
+                    error,
+                }
+            })?;
+            response_parts.headers.remove(CONTENT_LENGTH);
+            Ok(Response::from_parts(
+                response_parts,
+                Body::from(Bytes::from(body)),
+            ))
+        })
+    }
+}
+
+/// The most inputs `endpoint` accepts in a single request, if it has a limit
+/// we know of.
+fn max_inputs(endpoint: &ApiEndpoint) -> Option<usize> {
+    match endpoint {
+        ApiEndpoint::OpenAI(_) => Some(embeddings::MAX_INPUTS),
+        ApiEndpoint::Google(_) => Some(google::embed_contents::MAX_INPUTS),
+        ApiEndpoint::Cohere(_) => Some(cohere::embed::MAX_INPUTS),
+        _ => None,
+    }
+}
+
+/// Reject requests the target model can't serve, rather than sending them
+/// only to have the provider reject them in its own words.
+fn validate(
+    request: &CreateEmbeddingRequest,
+    target_endpoint: &ApiEndpoint,
+    max_dimensions: Option<u32>,
+) -> Result<(), InvalidRequestError> {
+    if request.input.is_empty() {
+        return Err(InvalidRequestError::InvalidEmbeddingsRequest(
+            "input must not be empty".to_string(),
+        ));
+    }
+    if request.input.is_tokens()
+        && !matches!(target_endpoint, ApiEndpoint::OpenAI(_))
+    {
+        return Err(InvalidRequestError::InvalidEmbeddingsRequest(format!(
+            "tokenized input is not supported by {}",
+            target_endpoint.provider()
+        )));
+    }
+    match (request.dimensions, max_dimensions) {
+        (Some(0), _) => Err(InvalidRequestError::InvalidEmbeddingsRequest(
+            "dimensions must be at least 1".to_string(),
+        )),
+        (Some(dimensions), Some(max)) if dimensions > max => {
+            Err(InvalidRequestError::InvalidEmbeddingsRequest(format!(
+                "dimensions must be at most {max} for {}",
+                request.model
+            )))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Append the embeddings of the next `batch` to `merged`.
+fn merge(
+    mut merged: CreateEmbeddingResponse,
+    batch: CreateEmbeddingResponse,
+) -> CreateEmbeddingResponse {
+    let offset = u32::try_from(merged.data.len()).unwrap_or(u32::MAX);
+    merged
+        .data
+        .extend(batch.data.into_iter().map(|mut embedding| {
+            embedding.index += offset;
+            embedding
+        }));
+    merged.usage.prompt_tokens += batch.usage.prompt_tokens;
+    merged.usage.total_tokens += batch.usage.total_tokens;
+    merged
+}
+
+/// Encode float embeddings as base64 little-endian `f32`s, as OpenAI does for
+/// `encoding_format: base64`.
+fn encode_base64(response: &mut CreateEmbeddingResponse) {
+    for embedding in &mut response.data {
+        if let EmbeddingVector::Float(vector) = &embedding.embedding {
+            let bytes = vector
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect::<Vec<_>>();
+            embedding.embedding = EmbeddingVector::Base64(
+                base64::engine::general_purpose::STANDARD.encode(bytes),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::endpoints::openai::embeddings::{
+        EMBEDDING_OBJECT, Embedding, EmbeddingUsage,
+    };
+
+    fn response(
+        vectors: &[&[f32]],
+        prompt_tokens: u32,
+    ) -> CreateEmbeddingResponse {
+        CreateEmbeddingResponse {
+            object: embeddings::EMBEDDING_LIST_OBJECT.to_string(),
+            model: "text-embedding-3-small".to_string(),
+            data: (0..)
+                .zip(vectors)
+                .map(|(index, vector)| Embedding {
+                    index,
+                    object: EMBEDDING_OBJECT.to_string(),
+                    embedding: EmbeddingVector::Float(vector.to_vec()),
+                })
+                .collect(),
+            usage: EmbeddingUsage {
+                prompt_tokens,
+                total_tokens: prompt_tokens,
+            },
+        }
+    }
+
+    fn request(
+        input: EmbeddingInput,
+        dimensions: Option<u32>,
+    ) -> CreateEmbeddingRequest {
+        CreateEmbeddingRequest {
+            model: "openai/text-embedding-3-small".to_string(),
+            input,
+            dimensions,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merge_offsets_indexes_and_sums_usage() {
+        let merged =
+            merge(response(&[&[0.1], &[0.2]], 4), response(&[&[0.3]], 3));
+
+        let indexes = merged.data.iter().map(|e| e.index).collect::<Vec<_>>();
+        assert_eq!(indexes, vec![0, 1, 2]);
+        assert_eq!(merged.data[2].embedding, EmbeddingVector::Float(vec![0.3]));
+        assert_eq!(merged.usage.prompt_tokens, 7);
+        assert_eq!(merged.usage.total_tokens, 7);
+    }
+
+    #[test]
+    fn base64_is_little_endian_f32() {
+        let mut response = response(&[&[1.0, -2.0]], 1);
+        encode_base64(&mut response);
+
+        let EmbeddingVector::Base64(encoded) = &response.data[0].embedding
+        else {
+            panic!("expected a base64 embedding");
+        };
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .unwrap();
+        assert_eq!(bytes[..4], 1.0f32.to_le_bytes());
+        assert_eq!(bytes[4..], (-2.0f32).to_le_bytes());
+    }
+
+    #[test]
+    fn dimensions_are_checked_against_the_model() {
+        let openai = ApiEndpoint::OpenAI(OpenAI::embeddings());
+        let text = || EmbeddingInput::String("hello".to_string());
+
+        assert!(
+            validate(&request(text(), Some(512)), &openai, Some(1536)).is_ok()
+        );
+        assert!(validate(&request(text(), None), &openai, Some(1536)).is_ok());
+        assert!(matches!(
+            validate(&request(text(), Some(2048)), &openai, Some(1536)),
+            Err(InvalidRequestError::InvalidEmbeddingsRequest(_))
+        ));
+        assert!(matches!(
+            validate(&request(text(), Some(0)), &openai, None),
+            Err(InvalidRequestError::InvalidEmbeddingsRequest(_))
+        ));
+    }
+
+    #[test]
+    fn tokens_are_only_sent_to_openai() {
+        let tokens = || EmbeddingInput::Tokens(vec![1, 2, 3]);
+        let openai = ApiEndpoint::OpenAI(OpenAI::embeddings());
+        let gemini =
+            ApiEndpoint::Google(google::Google::batch_embed_contents());
+
+        assert!(validate(&request(tokens(), None), &openai, None).is_ok());
+        assert!(matches!(
+            validate(&request(tokens(), None), &gemini, None),
+            Err(InvalidRequestError::InvalidEmbeddingsRequest(_))
+        ));
+    }
+}
@@ -0,0 +1,242 @@
+//! Guardrails inspect the prompts of requests before they are sent to a
+//! provider, and the completions of responses before they are sent to the
+//! client, e.g. to keep PII from leaving the network or to block jailbreak
+//! attempts.
+//!
+//! Every policy of a router is a [`Guardrail`] that allows the text, denies
+//! the request, or redacts parts of the text. Policies are applied in the
+//! order they are configured, and each policy sees the text as redacted by
+//! the previous ones.
+//!
+//! Streamed responses are inspected chunk by chunk as they are forwarded, so
+//! the built-in policies don't match text that spans several chunks. A
+//! stream that is denied ends with an error event.
+pub mod optional;
+pub mod policies;
+mod service;
+pub(crate) mod text;
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+pub use self::optional::{
+    Layer as GuardrailLayer, Service as GuardrailService,
+};
+use crate::{
+    config::guardrails::{GuardrailStages, GuardrailsConfig},
+    error::{
+        api::ApiError, init::InitError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+};
+
+/// What is being inspected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stage {
+    Request,
+    /// A complete, non-streamed response.
+    Response,
+    /// A chunk of a streamed response.
+    ResponseChunk,
+}
+
+impl Stage {
+    #[must_use]
+    pub fn is_included_in(self, stages: GuardrailStages) -> bool {
+        match (self, stages) {
+            (_, GuardrailStages::Both)
+            | (Self::Request, GuardrailStages::Request)
+            | (
+                Self::Response | Self::ResponseChunk,
+                GuardrailStages::Response,
+            ) => true,
+            (Self::Request, GuardrailStages::Response)
+            | (
+                Self::Response | Self::ResponseChunk,
+                GuardrailStages::Request,
+            ) => false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Allow,
+    Deny {
+        reason: String,
+    },
+    /// The inspected texts with parts of them replaced, in the same order.
+    Redact {
+        texts: Vec<String>,
+    },
+}
+
+#[async_trait]
+pub trait Guardrail: std::fmt::Debug + Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Whether the guardrail inspects the text of `stage`.
+    fn applies_to(&self, stage: Stage) -> bool;
+
+    /// Inspect the texts of a request or response, e.g. the content of each
+    /// message of a chat completion request.
+    async fn inspect(
+        &self,
+        stage: Stage,
+        texts: &[String],
+    ) -> Result<Verdict, ApiError>;
+}
+
+/// The guardrails of a router.
+#[derive(Debug, Clone)]
+pub struct Guardrails(Arc<[Arc<dyn Guardrail>]>);
+
+impl Guardrails {
+    pub fn new(config: &GuardrailsConfig) -> Result<Self, InitError> {
+        let guardrails = config
+            .policies
+            .iter()
+            .map(policies::from_config)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self(guardrails.into()))
+    }
+
+    /// For guardrails that can't be configured, e.g. in tests or when
+    /// embedding the gateway as a library.
+    #[must_use]
+    pub fn from_guardrails(guardrails: Vec<Arc<dyn Guardrail>>) -> Self {
+        Self(guardrails.into())
+    }
+
+    /// Apply every guardrail of `stage` to `texts`.
+    ///
+    /// Returns the redacted texts, or `None` if no guardrail redacted
+    /// anything.
+    ///
+    /// # Errors
+    /// [`InvalidRequestError::GuardrailDenied`] if a guardrail denies the
+    /// texts.
+    pub async fn inspect(
+        &self,
+        stage: Stage,
+        texts: Vec<String>,
+    ) -> Result<Option<Vec<String>>, ApiError> {
+        if texts.is_empty() {
+            return Ok(None);
+        }
+        let mut redacted: Option<Vec<String>> = None;
+        for guardrail in self.0.iter().filter(|g| g.applies_to(stage)) {
+            let current = redacted.as_deref().unwrap_or(&texts);
+            match guardrail.inspect(stage, current).await? {
+                Verdict::Allow => {}
+                Verdict::Deny { reason } => {
+                    tracing::debug!(
+                        guardrail = guardrail.name(),
+                        ?stage,
+                        %reason,
+                        "denied by guardrail"
+                    );
+                    return Err(ApiError::InvalidRequest(
+                        InvalidRequestError::GuardrailDenied {
+                            guardrail: guardrail.name().to_string(),
+                            reason,
+                        },
+                    ));
+                }
+                Verdict::Redact { texts } => {
+                    if texts.len() != current.len() {
+                        tracing::error!(
+                            guardrail = guardrail.name(),
+                            expected = current.len(),
+                            actual = texts.len(),
+                            "guardrail redacted a different number of texts"
+                        );
+                        return Err(InternalError::Internal.into());
+                    }
+                    redacted = Some(texts);
+                }
+            }
+        }
+        Ok(redacted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::guardrails::{
+        GuardrailAction, GuardrailKind, GuardrailPolicyConfig,
+    };
+
+    fn config(policies: Vec<(&str, GuardrailKind)>) -> GuardrailsConfig {
+        GuardrailsConfig {
+            policies: policies
+                .into_iter()
+                .map(|(name, kind)| GuardrailPolicyConfig {
+                    name: name.to_string(),
+                    apply_to: GuardrailStages::Both,
+                    kind,
+                })
+                .collect(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_policies_see_previous_redactions() {
+        let guardrails = Guardrails::new(&config(vec![
+            (
+                "ssn",
+                GuardrailKind::Regex {
+                    patterns: vec![r"\d{3}-\d{2}-\d{4}".to_string()],
+                    action: GuardrailAction::Redact,
+                    replacement: "[SSN]".to_string(),
+                },
+            ),
+            (
+                "no-numbers",
+                GuardrailKind::Regex {
+                    patterns: vec![r"\d".to_string()],
+                    action: GuardrailAction::Deny,
+                    replacement: String::new(),
+                },
+            ),
+        ]))
+        .unwrap();
+
+        let redacted = guardrails
+            .inspect(Stage::Request, vec!["my ssn is 123-45-6789".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(redacted, Some(vec!["my ssn is [SSN]".to_string()]));
+
+        let error = guardrails
+            .inspect(Stage::Request, vec!["call me at 555".to_string()])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            ApiError::InvalidRequest(InvalidRequestError::GuardrailDenied {
+                guardrail,
+                ..
+            }) if guardrail == "no-numbers"
+        ));
+
+        let allowed = guardrails
+            .inspect(Stage::Request, vec!["hello".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(allowed, None);
+    }
+
+    #[test]
+    fn test_stage_is_included_in() {
+        assert!(Stage::Request.is_included_in(GuardrailStages::Both));
+        assert!(Stage::Request.is_included_in(GuardrailStages::Request));
+        assert!(!Stage::Request.is_included_in(GuardrailStages::Response));
+        assert!(Stage::ResponseChunk.is_included_in(GuardrailStages::Response));
+        assert!(!Stage::Response.is_included_in(GuardrailStages::Request));
+    }
+}
@@ -0,0 +1,365 @@
+//! The built-in guardrails.
+use std::{sync::Arc, time::Duration};
+
+use async_trait::async_trait;
+use regex::{Regex, RegexSet};
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{Guardrail, Stage, Verdict};
+use crate::{
+    config::guardrails::{
+        GuardrailAction, GuardrailKind, GuardrailPolicyConfig, GuardrailStages,
+    },
+    error::{api::ApiError, init::InitError, internal::InternalError},
+};
+
+pub(super) fn from_config(
+    config: &GuardrailPolicyConfig,
+) -> Result<Arc<dyn Guardrail>, InitError> {
+    let guardrail: Arc<dyn Guardrail> = match &config.kind {
+        GuardrailKind::Regex {
+            patterns,
+            action,
+            replacement,
+        } => Arc::new(RegexGuardrail::new(
+            config.name.clone(),
+            config.apply_to,
+            patterns,
+            *action,
+            replacement.clone(),
+        )?),
+        GuardrailKind::Keywords {
+            keywords,
+            case_sensitive,
+            action,
+            replacement,
+        } => Arc::new(RegexGuardrail::keywords(
+            config.name.clone(),
+            config.apply_to,
+            keywords,
+            *case_sensitive,
+            *action,
+            replacement.clone(),
+        )?),
+        GuardrailKind::Http { url, timeout } => Arc::new(HttpGuardrail::new(
+            config.name.clone(),
+            config.apply_to,
+            url.clone(),
+            *timeout,
+        )?),
+    };
+    Ok(guardrail)
+}
+
+/// Denies or redacts text matching any of a set of regular expressions.
+#[derive(Debug)]
+pub struct RegexGuardrail {
+    name: String,
+    stages: GuardrailStages,
+    set: RegexSet,
+    regexes: Vec<Regex>,
+    action: GuardrailAction,
+    replacement: String,
+}
+
+impl RegexGuardrail {
+    pub fn new(
+        name: String,
+        stages: GuardrailStages,
+        patterns: &[String],
+        action: GuardrailAction,
+        replacement: String,
+    ) -> Result<Self, InitError> {
+        let invalid = |e: regex::Error| {
+            InitError::InvalidGuardrail(name.clone(), e.to_string())
+        };
+        let set = RegexSet::new(patterns).map_err(invalid)?;
+        let regexes = patterns
+            .iter()
+            .map(|pattern| Regex::new(pattern))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(invalid)?;
+        Ok(Self {
+            name,
+            stages,
+            set,
+            regexes,
+            action,
+            replacement,
+        })
+    }
+
+    /// Matches any of `keywords` literally.
+    pub fn keywords(
+        name: String,
+        stages: GuardrailStages,
+        keywords: &[String],
+        case_sensitive: bool,
+        action: GuardrailAction,
+        replacement: String,
+    ) -> Result<Self, InitError> {
+        let flags = if case_sensitive { "" } else { "(?i)" };
+        let patterns = keywords
+            .iter()
+            .map(|keyword| format!("{flags}{}", regex::escape(keyword)))
+            .collect::<Vec<_>>();
+        Self::new(name, stages, &patterns, action, replacement)
+    }
+}
+
+#[async_trait]
+impl Guardrail for RegexGuardrail {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn applies_to(&self, stage: Stage) -> bool {
+        stage.is_included_in(self.stages)
+    }
+
+    async fn inspect(
+        &self,
+        _stage: Stage,
+        texts: &[String],
+    ) -> Result<Verdict, ApiError> {
+        if !texts.iter().any(|text| self.set.is_match(text)) {
+            return Ok(Verdict::Allow);
+        }
+        let verdict = match self.action {
+            GuardrailAction::Deny => Verdict::Deny {
+                reason: "content matches a blocked pattern".to_string(),
+            },
+            GuardrailAction::Redact => Verdict::Redact {
+                texts: texts
+                    .iter()
+                    .map(|text| {
+                        self.regexes.iter().fold(text.clone(), |text, regex| {
+                            regex
+                                .replace_all(&text, self.replacement.as_str())
+                                .into_owned()
+                        })
+                    })
+                    .collect(),
+            },
+        };
+        Ok(verdict)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct PolicyRequest<'a> {
+    guardrail: &'a str,
+    stage: Stage,
+    texts: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", tag = "action")]
+enum PolicyResponse {
+    Allow,
+    Deny {
+        #[serde(default)]
+        reason: Option<String>,
+    },
+    Redact {
+        texts: Vec<String>,
+    },
+}
+
+/// Delegates to an external policy engine.
+///
+/// The texts are sent as a `POST` request with a JSON body of the form
+/// `{"guardrail": "<name>", "stage": "request", "texts": ["..."]}`, where
+/// `stage` is one of `request`, `response` or `response-chunk`. The engine
+/// must respond with one of:
+/// - `{"action": "allow"}`
+/// - `{"action": "deny", "reason": "..."}`
+/// - `{"action": "redact", "texts": ["..."]}`, with as many texts as were sent.
+///
+/// Requests are denied if the engine can't be reached or returns an error,
+/// so that guardrails fail closed.
+#[derive(Debug)]
+pub struct HttpGuardrail {
+    name: String,
+    stages: GuardrailStages,
+    url: Url,
+    client: reqwest::Client,
+}
+
+impl HttpGuardrail {
+    pub fn new(
+        name: String,
+        stages: GuardrailStages,
+        url: Url,
+        timeout: Duration,
+    ) -> Result<Self, InitError> {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .tcp_nodelay(true)
+            .build()
+            .map_err(InitError::CreateReqwestClient)?;
+        Ok(Self {
+            name,
+            stages,
+            url,
+            client,
+        })
+    }
+}
+
+#[async_trait]
+impl Guardrail for HttpGuardrail {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn applies_to(&self, stage: Stage) -> bool {
+        stage.is_included_in(self.stages)
+    }
+
+    async fn inspect(
+        &self,
+        stage: Stage,
+        texts: &[String],
+    ) -> Result<Verdict, ApiError> {
+        let response = self
+            .client
+            .post(self.url.clone())
+            .json(&PolicyRequest {
+                guardrail: &self.name,
+                stage,
+                texts,
+            })
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| {
+                tracing::error!(
+                    guardrail = %self.name,
+                    error = %e,
+                    "guardrail policy engine request failed"
+                );
+                InternalError::ReqwestError(e)
+            })?;
+        let body = response
+            .bytes()
+            .await
+            .map_err(InternalError::ReqwestError)?;
+        let response = serde_json::from_slice::<PolicyResponse>(&body)
+            .map_err(|error| InternalError::Deserialize {
+                ty: "PolicyResponse",
+                error,
+            })?;
+        let verdict = match response {
+            PolicyResponse::Allow => Verdict::Allow,
+            PolicyResponse::Deny { reason } => Verdict::Deny {
+                reason: reason
+                    .unwrap_or_else(|| "denied by policy engine".to_string()),
+            },
+            PolicyResponse::Redact { texts } => Verdict::Redact { texts },
+        };
+        Ok(verdict)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(texts: &[&str]) -> Vec<String> {
+        texts.iter().map(ToString::to_string).collect()
+    }
+
+    #[tokio::test]
+    async fn test_regex_redacts_every_match() {
+        let guardrail = RegexGuardrail::new(
+            "pii".to_string(),
+            GuardrailStages::Both,
+            &[
+                r"\b\d{3}-\d{2}-\d{4}\b".to_string(),
+                r"[\w.+-]+@[\w-]+\.[\w.]+".to_string(),
+            ],
+            GuardrailAction::Redact,
+            "[REDACTED]".to_string(),
+        )
+        .unwrap();
+        let verdict = guardrail
+            .inspect(
+                Stage::Request,
+                &texts(&["mail jane@example.com", "ssn 123-45-6789", "hi"]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            verdict,
+            Verdict::Redact {
+                texts: texts(&["mail [REDACTED]", "ssn [REDACTED]", "hi"]),
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_keywords_are_literal_and_case_insensitive() {
+        let guardrail = RegexGuardrail::keywords(
+            "jailbreak".to_string(),
+            GuardrailStages::Request,
+            &[
+                "ignore previous instructions".to_string(),
+                "d.a.n".to_string(),
+            ],
+            false,
+            GuardrailAction::Deny,
+            String::new(),
+        )
+        .unwrap();
+        assert!(guardrail.applies_to(Stage::Request));
+        assert!(!guardrail.applies_to(Stage::ResponseChunk));
+
+        let verdict = guardrail
+            .inspect(
+                Stage::Request,
+                &texts(&["Please IGNORE previous instructions"]),
+            )
+            .await
+            .unwrap();
+        assert!(matches!(verdict, Verdict::Deny { .. }));
+
+        // `.` is not a wildcard
+        let verdict = guardrail
+            .inspect(Stage::Request, &texts(&["dxaxn"]))
+            .await
+            .unwrap();
+        assert_eq!(verdict, Verdict::Allow);
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let result = RegexGuardrail::new(
+            "broken".to_string(),
+            GuardrailStages::Both,
+            &["(unclosed".to_string()],
+            GuardrailAction::Deny,
+            String::new(),
+        );
+        assert!(matches!(
+            result,
+            Err(InitError::InvalidGuardrail(name, _)) if name == "broken"
+        ));
+    }
+
+    #[test]
+    fn test_deserialize_policy_response() {
+        let response = serde_json::from_str::<PolicyResponse>(
+            r#"{"action": "redact", "texts": ["[REDACTED]"]}"#,
+        )
+        .unwrap();
+        assert!(
+            matches!(response, PolicyResponse::Redact { texts } if texts == ["[REDACTED]"])
+        );
+        let response =
+            serde_json::from_str::<PolicyResponse>(r#"{"action": "deny"}"#)
+                .unwrap();
+        assert!(matches!(response, PolicyResponse::Deny { reason: None }));
+    }
+}
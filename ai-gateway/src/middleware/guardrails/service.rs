@@ -0,0 +1,323 @@
+use std::task::{Context, Poll};
+
+use bytes::Bytes;
+use futures::{StreamExt, future::BoxFuture};
+use http_body_util::BodyExt;
+use serde_json::Value;
+
+use super::{Guardrails, Stage, text::Texts};
+use crate::{
+    error::{
+        api::{ApiError, ErrorDetails, ErrorResponse},
+        internal::InternalError,
+    },
+    middleware::mapper::openai::{
+        INVALID_REQUEST_ERROR_TYPE, SERVER_ERROR_TYPE,
+    },
+    types::{
+        body::Body, extensions::MapperContext, request::Request,
+        response::Response,
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct GuardrailLayer {
+    guardrails: Guardrails,
+}
+
+impl GuardrailLayer {
+    pub fn new(guardrails: Guardrails) -> Self {
+        Self { guardrails }
+    }
+}
+
+impl<S> tower::Layer<S> for GuardrailLayer {
+    type Service = GuardrailService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        GuardrailService {
+            inner,
+            guardrails: self.guardrails.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GuardrailService<S> {
+    inner: S,
+    guardrails: Guardrails,
+}
+
+impl<S> tower::Service<Request> for GuardrailService<S>
+where
+    S: tower::Service<Request, Response = Response, Error = ApiError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "guardrails", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        let mut inner = self.inner.clone();
+        let guardrails = self.guardrails.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        Box::pin(async move {
+            let req = inspect_request(&guardrails, req).await?;
+            let response = inner.call(req).await?;
+            if !response.status().is_success() {
+                return Ok(response);
+            }
+            if is_stream(&response) {
+                Ok(inspect_stream(guardrails, response))
+            } else {
+                inspect_response(&guardrails, response).await
+            }
+        })
+    }
+}
+
+async fn inspect_request(
+    guardrails: &Guardrails,
+    req: Request,
+) -> Result<Request, ApiError> {
+    let (parts, body) = req.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map_err(InternalError::CollectBodyError)?
+        .to_bytes();
+    let body = inspect_json(guardrails, Stage::Request, body).await?;
+    Ok(Request::from_parts(parts, Body::from(body)))
+}
+
+async fn inspect_response(
+    guardrails: &Guardrails,
+    response: Response,
+) -> Result<Response, ApiError> {
+    let (mut parts, body) = response.into_parts();
+    let body = body
+        .collect()
+        .await
+        .map_err(InternalError::CollectBodyError)?
+        .to_bytes();
+    let body = inspect_json(guardrails, Stage::Response, body).await?;
+    parts.headers.remove(http::header::CONTENT_LENGTH);
+    Ok(Response::from_parts(parts, Body::from(body)))
+}
+
+/// Inspect a JSON body, returning it with the redactions of the guardrails.
+///
+/// Bodies that aren't JSON are passed through, since they have no text we
+/// know how to find.
+async fn inspect_json(
+    guardrails: &Guardrails,
+    stage: Stage,
+    body: Bytes,
+) -> Result<Bytes, ApiError> {
+    let Ok(mut value) = serde_json::from_slice::<Value>(&body) else {
+        return Ok(body);
+    };
+    let texts = Texts::find(&value);
+    let Some(redacted) = guardrails.inspect(stage, texts.texts.clone()).await?
+    else {
+        return Ok(body);
+    };
+    texts.replace(&mut value, redacted);
+    let body = serde_json::to_vec(&value).map_err(|error| {
+        InternalError::Serialize {
+            ty: "serde_json::Value",
+            error,
+        }
+    })?;
+    Ok(Bytes::from(body))
+}
+
+fn is_stream(response: &Response) -> bool {
+    response
+        .extensions()
+        .get::<MapperContext>()
+        .is_some_and(|mapper_ctx| mapper_ctx.is_stream)
+        || response
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("text/event-stream"))
+}
+
+/// Inspect each event of a server-sent event stream as it is forwarded.
+///
+/// If a guardrail denies an event, the event is replaced with an error event
+/// and the stream ends.
+fn inspect_stream(guardrails: Guardrails, response: Response) -> Response {
+    let (parts, body) = response.into_parts();
+    let stream = futures::stream::unfold(
+        (body.into_data_stream(), guardrails, false),
+        |(mut stream, guardrails, denied)| async move {
+            if denied {
+                return None;
+            }
+            let chunk = match stream.next().await? {
+                Ok(chunk) => chunk,
+                Err(e) => return Some((Err(e), (stream, guardrails, true))),
+            };
+            match inspect_chunk(&guardrails, chunk).await {
+                Ok(chunk) => Some((Ok(chunk), (stream, guardrails, false))),
+                Err(e) => {
+                    Some((Ok(error_event(e)), (stream, guardrails, true)))
+                }
+            }
+        },
+    );
+    Response::from_parts(parts, Body::from_stream(stream))
+}
+
+async fn inspect_chunk(
+    guardrails: &Guardrails,
+    chunk: Bytes,
+) -> Result<Bytes, ApiError> {
+    let Ok(text) = std::str::from_utf8(&chunk) else {
+        return Ok(chunk);
+    };
+    let mut modified = false;
+    let mut lines = Vec::new();
+    for line in text.split_inclusive('\n') {
+        let Some(data) = line.strip_prefix("data:") else {
+            lines.push(line.to_string());
+            continue;
+        };
+        let data = data.trim();
+        let inspected = inspect_json(
+            guardrails,
+            Stage::ResponseChunk,
+            Bytes::copy_from_slice(data.as_bytes()),
+        )
+        .await?;
+        if inspected.as_ref() == data.as_bytes() {
+            lines.push(line.to_string());
+        } else {
+            let inspected = String::from_utf8_lossy(&inspected);
+            let ending = &line[line.trim_end().len()..];
+            lines.push(format!("data: {inspected}{ending}"));
+            modified = true;
+        }
+    }
+    if modified {
+        Ok(Bytes::from(lines.concat()))
+    } else {
+        Ok(chunk)
+    }
+}
+
+fn error_event(error: ApiError) -> Bytes {
+    let error = match error {
+        ApiError::InvalidRequest(error) => ErrorResponse {
+            error: ErrorDetails {
+                message: error.to_string(),
+                r#type: Some(INVALID_REQUEST_ERROR_TYPE.to_string()),
+                param: None,
+                code: Some("guardrail_denied".to_string()),
+            },
+        },
+        error => {
+            tracing::error!(error = %error, "failed to inspect stream chunk");
+            ErrorResponse {
+                error: ErrorDetails {
+                    message: "Internal server error".to_string(),
+                    r#type: Some(SERVER_ERROR_TYPE.to_string()),
+                    param: None,
+                    code: None,
+                },
+            }
+        }
+    };
+    let json = serde_json::to_string(&error)
+        .expect("error response is always serializable");
+    Bytes::from(format!("data: {json}\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::guardrails::{
+        GuardrailAction, GuardrailKind, GuardrailPolicyConfig, GuardrailStages,
+        GuardrailsConfig,
+    };
+
+    fn guardrails(action: GuardrailAction) -> Guardrails {
+        Guardrails::new(&GuardrailsConfig {
+            policies: vec![GuardrailPolicyConfig {
+                name: "secrets".to_string(),
+                apply_to: GuardrailStages::Response,
+                kind: GuardrailKind::Keywords {
+                    keywords: vec!["hunter2".to_string()],
+                    case_sensitive: false,
+                    action,
+                    replacement: "***".to_string(),
+                },
+            }],
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_inspect_chunk_redacts_data_lines() {
+        let chunk = Bytes::from_static(
+            b"data: {\"choices\":[{\"delta\":{\"content\":\"pw is hunter2\"}}]}\n\n",
+        );
+        let chunk = inspect_chunk(&guardrails(GuardrailAction::Redact), chunk)
+            .await
+            .unwrap();
+        assert_eq!(
+            chunk,
+            Bytes::from_static(
+                b"data: {\"choices\":[{\"delta\":{\"content\":\"pw is ***\"}}]}\n\n"
+            )
+        );
+
+        let done = Bytes::from_static(b"data: [DONE]\n\n");
+        let chunk =
+            inspect_chunk(&guardrails(GuardrailAction::Redact), done.clone())
+                .await
+                .unwrap();
+        assert_eq!(chunk, done);
+    }
+
+    #[tokio::test]
+    async fn test_denied_stream_ends_with_error_event() {
+        let chunks = [
+            "data: {\"choices\":[{\"delta\":{\"content\":\"the pw\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"is \
+             hunter2\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\"!\"}}]}\n\n",
+        ];
+        let body = Body::from_stream(futures::stream::iter(
+            chunks.map(|chunk| Ok::<_, std::io::Error>(Bytes::from(chunk))),
+        ));
+        let mut response = Response::new(body);
+        response.extensions_mut().insert(MapperContext {
+            is_stream: true,
+            model: None,
+        });
+
+        let response =
+            inspect_stream(guardrails(GuardrailAction::Deny), response);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.starts_with(chunks[0]));
+        assert!(body.contains("guardrail_denied"));
+        assert!(!body.contains("hunter2"));
+        assert!(!body.contains("\"!\""));
+    }
+}
@@ -0,0 +1,111 @@
+//! The text of request and response bodies, i.e. the prompts and
+//! completions that guardrails inspect.
+use serde_json::Value;
+
+/// Keys whose string values are text, in the request and response formats
+/// of the supported APIs, e.g. `messages[].content` and
+/// `messages[].content[].text` of chat completions, or `system` of the
+/// Anthropic Messages API.
+const TEXT_KEYS: &[&str] = &[
+    "content",
+    "text",
+    "system",
+    "prompt",
+    "input",
+    "instructions",
+];
+
+/// The texts of a JSON body and where they were found.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub(crate) struct Texts {
+    /// The JSON pointer of each text.
+    pointers: Vec<String>,
+    pub texts: Vec<String>,
+}
+
+impl Texts {
+    pub fn find(value: &Value) -> Self {
+        let mut texts = Self::default();
+        texts.collect(value, String::new(), false);
+        texts
+    }
+
+    fn collect(&mut self, value: &Value, pointer: String, is_text: bool) {
+        match value {
+            Value::String(text) if is_text => {
+                self.pointers.push(pointer);
+                self.texts.push(text.clone());
+            }
+            Value::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    self.collect(item, format!("{pointer}/{i}"), is_text);
+                }
+            }
+            Value::Object(map) => {
+                for (key, item) in map {
+                    let escaped = key.replace('~', "~0").replace('/', "~1");
+                    self.collect(
+                        item,
+                        format!("{pointer}/{escaped}"),
+                        TEXT_KEYS.contains(&key.as_str()),
+                    );
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Replace the texts found in `value` with `redacted`, which must be in
+    /// the same order.
+    pub fn replace(&self, value: &mut Value, redacted: Vec<String>) {
+        for (pointer, text) in self.pointers.iter().zip(redacted) {
+            if let Some(target) = value.pointer_mut(pointer) {
+                *target = Value::String(text);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_find_and_replace() {
+        let mut body = json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                { "role": "user", "content": "hello" },
+                {
+                    "role": "user",
+                    "content": [
+                        { "type": "text", "text": "look at this" },
+                        {
+                            "type": "image_url",
+                            "image_url": { "url": "https://example.com" }
+                        }
+                    ]
+                }
+            ],
+            "temperature": 0.5
+        });
+        let texts = Texts::find(&body);
+        assert_eq!(texts.texts, ["hello", "look at this"]);
+
+        texts.replace(&mut body, vec!["b".to_string(), "c".to_string()]);
+        assert_eq!(body["messages"][0]["content"], "b");
+        assert_eq!(body["messages"][1]["content"][0]["text"], "c");
+        assert_eq!(body["messages"][1]["content"][0]["type"], "text");
+        assert_eq!(body["model"], "openai/gpt-4o-mini");
+    }
+
+    #[test]
+    fn test_find_in_stream_chunk() {
+        let chunk = json!({
+            "choices": [{ "index": 0, "delta": { "content": "Hi" } }]
+        });
+        assert_eq!(Texts::find(&chunk).texts, ["Hi"]);
+    }
+}
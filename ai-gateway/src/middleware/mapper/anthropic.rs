@@ -7,8 +7,7 @@ use crate::{
     endpoints::openai::chat_completions::system_prompt,
     error::mapper::MapperError,
     middleware::mapper::{
-        DEFAULT_MAX_TOKENS, TryConvertError, mime_from_data_uri,
-        model::ModelMapper,
+        DEFAULT_MAX_TOKENS, TryConvertError, model::ModelMapper, split_data_uri,
     },
     types::{
         model_id::{ModelId, Version},
@@ -92,7 +91,7 @@ impl
                         .function
                         .parameters
                         .clone()
-                        .unwrap_or_default(),
+                        .unwrap_or_else(empty_input_schema),
                 })
                 .collect();
             Some(mapped_tools)
@@ -146,11 +145,10 @@ impl
                                                 data: image.image_url.url,
                                             }
                                         } else {
-                                            let mime = mime_from_data_uri(&image.image_url.url)?;
-                                            let (_, b64) = image.image_url.url.split_once(',')?;
+                                            let (media_type, b64) = split_data_uri(&image.image_url.url)?;
                                             anthropic::ImageSource {
                                                 type_: "base64".to_string(),
-                                                media_type: mime.mime_type().to_string(),
+                                                media_type,
                                                 data: b64.to_string(),
                                             }
                                         };
@@ -239,31 +237,49 @@ impl
                     }
                 }
                 openai::ChatCompletionRequestMessage::Tool(message) => {
-                    tracing::info!(message = ?message, "tool message");
-                    let mapped_content = match message.content {
-                        openai::ChatCompletionRequestToolMessageContent::Text(content) => {
-                            let block = anthropic::ContentBlock::ToolResult {
-                                tool_use_id: message.tool_call_id,
-                                content,
-                            };
-                            anthropic::MessageContent::Blocks { content: vec![block] }
-                        },
+                    let content = match message.content {
+                        openai::ChatCompletionRequestToolMessageContent::Text(content) => content,
                         openai::ChatCompletionRequestToolMessageContent::Array(content) => {
-                            let mapped_content_blocks = content.into_iter().map(|part| {
+                            content.into_iter().map(|part| {
                                 match part {
-                                    openai::ChatCompletionRequestToolMessageContentPart::Text(text) => {
-                                        anthropic::ContentBlock::ToolResult { tool_use_id: message.tool_call_id.clone(), content: text.text }
-                                    },
+                                    openai::ChatCompletionRequestToolMessageContentPart::Text(text) => text.text,
                                 }
-                            }).collect();
-                            anthropic::MessageContent::Blocks { content: mapped_content_blocks }
+                            }).collect::<Vec<_>>().join("\n")
                         },
                     };
-                    let mapped_message = anthropic::Message {
-                        role: anthropic::Role::User,
-                        content: mapped_content,
+                    let block = anthropic::ContentBlock::ToolResult {
+                        tool_use_id: message.tool_call_id,
+                        content,
                     };
-                    mapped_messages.push(mapped_message);
+                    // OpenAI sends the results of parallel tool calls as one
+                    // message each, while Anthropic expects all of them in
+                    // the user turn following the tool calls.
+                    let tool_results = match mapped_messages.last_mut() {
+                        Some(anthropic::Message {
+                            role: anthropic::Role::User,
+                            content:
+                                anthropic::MessageContent::Blocks { content },
+                        }) if content.iter().all(|block| {
+                            matches!(
+                                block,
+                                anthropic::ContentBlock::ToolResult { .. }
+                            )
+                        }) =>
+                        {
+                            Some(content)
+                        }
+                        _ => None,
+                    };
+                    if let Some(tool_results) = tool_results {
+                        tool_results.push(block);
+                    } else {
+                        mapped_messages.push(anthropic::Message {
+                            role: anthropic::Role::User,
+                            content: anthropic::MessageContent::Blocks {
+                                content: vec![block],
+                            },
+                        });
+                    }
                 }
                 openai::ChatCompletionRequestMessage::Function(message) => {
                     let Some(tool) = tools.as_ref().and_then(|tools| {
@@ -365,7 +381,7 @@ impl
                 }),
 
                 anthropic::ContentBlock::Text { text, .. } => {
-                    content = Some(text.clone());
+                    content.get_or_insert_with(String::new).push_str(&text);
                 }
                 anthropic::ContentBlock::Image { .. }
                 | anthropic::ContentBlock::Thinking { .. }
@@ -391,7 +407,7 @@ impl
         let choice = openai::ChatChoice {
             index: 0,
             message,
-            finish_reason: None,
+            finish_reason: value.stop_reason.as_ref().map(finish_reason),
             logprobs: None,
         };
 
@@ -409,6 +425,77 @@ impl
     }
 }
 
+/// The schema of a tool without parameters, Anthropic requires one for
+/// every tool while OpenAI allows it to be omitted.
+fn empty_input_schema() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+fn finish_reason(
+    stop_reason: &anthropic_ai_sdk::types::message::StopReason,
+) -> async_openai::types::FinishReason {
+    use anthropic_ai_sdk::types::message::StopReason;
+    use async_openai::types::FinishReason;
+    match stop_reason {
+        StopReason::EndTurn | StopReason::StopSequence => FinishReason::Stop,
+        StopReason::MaxTokens => FinishReason::Length,
+        StopReason::ToolUse => FinishReason::ToolCalls,
+        StopReason::Refusal => FinishReason::ContentFilter,
+    }
+}
+
+/// The arguments of a tool call as OpenAI streams them.
+///
+/// Anthropic starts a streamed `tool_use` block with an empty `input` and
+/// sends the actual input as partial JSON deltas, which OpenAI clients
+/// concatenate to the arguments. Those must hence start out empty rather than
+/// as `{}`.
+fn tool_arguments(input: &serde_json::Value) -> Result<String, MapperError> {
+    match input {
+        serde_json::Value::Object(map) if map.is_empty() => Ok(String::new()),
+        input => serde_json::to_string(input).map_err(MapperError::SerdeError),
+    }
+}
+
+const PLACEHOLDER_STREAM_ID: &str = "anthropic-stream-id";
+const PLACEHOLDER_MODEL_NAME: &str = "anthropic-model";
+
+/// State carried across the events of a single Anthropic stream while
+/// translating it to OpenAI chunks.
+#[derive(Debug, Default)]
+pub struct AnthropicStreamState {
+    /// The message id and model, which Anthropic only sends in the
+    /// `message_start` event but OpenAI expects on every chunk.
+    message: Option<(String, String)>,
+    /// OpenAI tool call indices by Anthropic content block index, since
+    /// Anthropic counts text blocks too.
+    tool_call_indices: HashMap<usize, u32>,
+}
+
+impl AnthropicStreamState {
+    fn id(&self) -> String {
+        self.message
+            .as_ref()
+            .map_or(PLACEHOLDER_STREAM_ID, |(id, _)| id.as_str())
+            .to_string()
+    }
+
+    fn model(&self) -> String {
+        self.message
+            .as_ref()
+            .map_or(PLACEHOLDER_MODEL_NAME, |(_, model)| model.as_str())
+            .to_string()
+    }
+
+    fn tool_call_index(&mut self, content_block_index: usize) -> u32 {
+        let next = u32::try_from(self.tool_call_indices.len()).unwrap_or(0);
+        *self
+            .tool_call_indices
+            .entry(content_block_index)
+            .or_insert(next)
+    }
+}
+
 impl
     TryConvertStreamData<
         anthropic_ai_sdk::types::message::StreamEvent,
@@ -416,29 +503,28 @@ impl
     > for AnthropicConverter
 {
     type Error = MapperError;
+    type State = AnthropicStreamState;
 
     #[allow(clippy::too_many_lines)]
     fn try_convert_chunk(
         &self,
+        state: &mut Self::State,
         value: anthropic_ai_sdk::types::message::StreamEvent,
     ) -> std::result::Result<
-        Option<async_openai::types::CreateChatCompletionStreamResponse>,
+        Vec<async_openai::types::CreateChatCompletionStreamResponse>,
         Self::Error,
     > {
         use anthropic_ai_sdk::types::message as anthropic;
         use async_openai::types as openai;
 
         const CHAT_COMPLETION_CHUNK_OBJECT: &str = "chat.completion.chunk";
-        // TODO: These placeholder values for id, model, and created should be
-        // replaced by actual values from the MessageStart event,
-        // propagated by the stream handling logic.
-        const PLACEHOLDER_STREAM_ID: &str = "anthropic-stream-id";
-        const PLACEHOLDER_MODEL_NAME: &str = "anthropic-model";
         const DEFAULT_CREATED_TIMESTAMP: u32 = 0;
 
         #[allow(deprecated)]
         match value {
             anthropic::StreamEvent::MessageStart { message } => {
+                state.message =
+                    Some((message.id.clone(), message.model.clone()));
                 let mut current_text_content = String::new();
                 let mut tool_calls = Vec::new();
 
@@ -454,15 +540,12 @@ impl
                         } => {
                             tool_calls.push(
                                 openai::ChatCompletionMessageToolCallChunk {
-                                    index: u32::try_from(idx).unwrap_or(0),
+                                    index: state.tool_call_index(idx),
                                     id: Some(id.clone()),
                                     r#type: Some(openai::ChatCompletionToolType::Function),
                                     function: Some(openai::FunctionCallStream {
                                         name: Some(name.clone()),
-                                        arguments: Some(
-                                            serde_json::to_string(input)
-                                                .map_err(MapperError::SerdeError)?,
-                                        ),
+                                        arguments: Some(tool_arguments(input)?),
                                     }),
                                 }
                             );
@@ -478,22 +561,8 @@ impl
                     }
                 }
 
-                let finish_reason = match message.stop_reason {
-                    Some(
-                        anthropic::StopReason::EndTurn
-                        | anthropic::StopReason::StopSequence,
-                    ) => Some(openai::FinishReason::Stop),
-                    Some(anthropic::StopReason::MaxTokens) => {
-                        Some(openai::FinishReason::Length)
-                    }
-                    Some(anthropic::StopReason::ToolUse) => {
-                        Some(openai::FinishReason::ToolCalls)
-                    }
-                    Some(anthropic::StopReason::Refusal) => {
-                        Some(openai::FinishReason::ContentFilter)
-                    }
-                    None => None,
-                };
+                let finish_reason =
+                    message.stop_reason.as_ref().map(finish_reason);
 
                 let refusal_content = if matches!(
                     message.stop_reason,
@@ -514,14 +583,15 @@ impl
                             }
                         }),
                         content: Some(current_text_content),
-                        tool_calls: Some(tool_calls),
+                        tool_calls: (!tool_calls.is_empty())
+                            .then_some(tool_calls),
                         refusal: refusal_content,
                         function_call: None,
                     },
                     finish_reason,
                     logprobs: None,
                 };
-                Ok(Some(openai::CreateChatCompletionStreamResponse {
+                Ok(vec![openai::CreateChatCompletionStreamResponse {
                     id: message.id,
                     choices: vec![choice],
                     created: DEFAULT_CREATED_TIMESTAMP, /* Or use message.
@@ -540,7 +610,7 @@ impl
                         prompt_tokens_details: None,
                         completion_tokens_details: None,
                     }),
-                }))
+                }])
             }
             anthropic::StreamEvent::ContentBlockStart {
                 index,
@@ -550,17 +620,14 @@ impl
                     anthropic::ContentBlock::ToolUse { id, name, input } => {
                         let tool_call_chunk =
                             openai::ChatCompletionMessageToolCallChunk {
-                                index: u32::try_from(index).unwrap_or(0),
+                                index: state.tool_call_index(index),
                                 id: Some(id),
                                 r#type: Some(
                                     openai::ChatCompletionToolType::Function,
                                 ),
                                 function: Some(openai::FunctionCallStream {
                                     name: Some(name),
-                                    arguments: Some(
-                                        serde_json::to_string(&input)
-                                            .map_err(MapperError::SerdeError)?,
-                                    ),
+                                    arguments: Some(tool_arguments(&input)?),
                                 }),
                             };
                         let choice = openai::ChatChoiceStream {
@@ -575,25 +642,26 @@ impl
                             finish_reason: None,
                             logprobs: None,
                         };
-                        Ok(Some(openai::CreateChatCompletionStreamResponse {
-                            id: PLACEHOLDER_STREAM_ID.to_string(), /* TODO: Use actual stream ID */
+                        Ok(vec![openai::CreateChatCompletionStreamResponse {
+                            id: state.id(),
                             choices: vec![choice],
                             created: DEFAULT_CREATED_TIMESTAMP,
-                            model: PLACEHOLDER_MODEL_NAME.to_string(),
+                            model: state.model(),
                             object: CHAT_COMPLETION_CHUNK_OBJECT.to_string(),
                             system_fingerprint: None,
                             service_tier: None,
                             usage: None,
-                        }))
+                        }])
                     }
-                    _ => Ok(None), // Text start, etc., content comes in delta
+                    // Text start, etc., content comes in delta
+                    _ => Ok(Vec::new()),
                 }
             }
             anthropic::StreamEvent::ContentBlockDelta { index, delta } => {
                 match delta {
                     anthropic::ContentBlockDelta::TextDelta { text } => {
                         let choice = openai::ChatChoiceStream {
-                            index: u32::try_from(index).unwrap_or(0),
+                            index: 0,
                             delta: openai::ChatCompletionStreamResponseDelta {
                                 role: None,
                                 content: Some(text),
@@ -604,35 +672,35 @@ impl
                             finish_reason: None,
                             logprobs: None,
                         };
-                        Ok(Some(openai::CreateChatCompletionStreamResponse {
-                            id: PLACEHOLDER_STREAM_ID.to_string(), /* TODO: Use actual stream ID */
+                        Ok(vec![openai::CreateChatCompletionStreamResponse {
+                            id: state.id(),
                             choices: vec![choice],
-                            created: DEFAULT_CREATED_TIMESTAMP, /* TODO: Use actual created timestamp */
-                            model: PLACEHOLDER_MODEL_NAME.to_string(), /* TODO: Use actual model name */
+                            created: DEFAULT_CREATED_TIMESTAMP,
+                            model: state.model(),
                             object: CHAT_COMPLETION_CHUNK_OBJECT.to_string(),
                             system_fingerprint: None,
                             service_tier: None,
                             usage: None,
-                        }))
+                        }])
                     }
                     anthropic::ContentBlockDelta::InputJsonDelta {
                         partial_json,
                     } => {
                         let tool_call_chunk =
                             openai::ChatCompletionMessageToolCallChunk {
-                                index: u32::try_from(index).unwrap_or(0),
-                                id: None, /* ID would have been sent with ContentBlockStart for this tool */
-                                r#type: Some(
-                                    openai::ChatCompletionToolType::Function,
-                                ), // Assuming function
+                                index: state.tool_call_index(index),
+                                // the id, type and name were sent with the
+                                // content block start, OpenAI only repeats
+                                // the index on the following deltas
+                                id: None,
+                                r#type: None,
                                 function: Some(openai::FunctionCallStream {
-                                    name: None, /* Name would have been sent
-                                                 * with ContentBlockStart */
+                                    name: None,
                                     arguments: Some(partial_json),
                                 }),
                             };
                         let choice = openai::ChatChoiceStream {
-                            index: u32::try_from(index).unwrap_or(0),
+                            index: 0,
                             delta: openai::ChatCompletionStreamResponseDelta {
                                 role: None,
                                 content: None,
@@ -643,45 +711,31 @@ impl
                             finish_reason: None,
                             logprobs: None,
                         };
-                        Ok(Some(openai::CreateChatCompletionStreamResponse {
-                            id: PLACEHOLDER_STREAM_ID.to_string(), /* TODO: Use actual stream ID */
+                        Ok(vec![openai::CreateChatCompletionStreamResponse {
+                            id: state.id(),
                             choices: vec![choice],
-                            created: DEFAULT_CREATED_TIMESTAMP, /* TODO: Use actual created timestamp */
-                            model: PLACEHOLDER_MODEL_NAME.to_string(), /* TODO: Use actual model name */
+                            created: DEFAULT_CREATED_TIMESTAMP,
+                            model: state.model(),
                             object: CHAT_COMPLETION_CHUNK_OBJECT.to_string(),
                             system_fingerprint: None,
                             service_tier: None,
                             usage: None,
-                        }))
+                        }])
                     }
                     anthropic::ContentBlockDelta::ThinkingDelta { .. }
                     | anthropic::ContentBlockDelta::SignatureDelta { .. } => {
-                        Ok(None)
+                        Ok(Vec::new())
                     } // No direct OpenAI mapping for these deltas
                 }
             }
             anthropic::StreamEvent::ContentBlockStop { index: _ }
             | anthropic::StreamEvent::MessageStop
-            | anthropic::StreamEvent::Ping => Ok(None), /* Usually no */
+            | anthropic::StreamEvent::Ping => Ok(Vec::new()), /* Usually no */
             // separate OpenAI
             // chunk for this
             anthropic::StreamEvent::MessageDelta { delta, usage } => {
-                let finish_reason = match delta.stop_reason {
-                    Some(
-                        anthropic::StopReason::EndTurn
-                        | anthropic::StopReason::StopSequence,
-                    ) => Some(openai::FinishReason::Stop),
-                    Some(anthropic::StopReason::MaxTokens) => {
-                        Some(openai::FinishReason::Length)
-                    }
-                    Some(anthropic::StopReason::ToolUse) => {
-                        Some(openai::FinishReason::ToolCalls)
-                    }
-                    Some(anthropic::StopReason::Refusal) => {
-                        Some(openai::FinishReason::ContentFilter)
-                    }
-                    None => None,
-                };
+                let finish_reason =
+                    delta.stop_reason.as_ref().map(finish_reason);
 
                 let completion_usage = openai::CompletionUsage {
                     prompt_tokens: usage.as_ref().map_or(0, |u| u.input_tokens),
@@ -709,20 +763,20 @@ impl
                     finish_reason,
                     logprobs: None,
                 };
-                Ok(Some(openai::CreateChatCompletionStreamResponse {
-                    id: PLACEHOLDER_STREAM_ID.to_string(), /* TODO: Use actual stream ID */
+                Ok(vec![openai::CreateChatCompletionStreamResponse {
+                    id: state.id(),
                     choices: vec![choice],
-                    created: DEFAULT_CREATED_TIMESTAMP, /* TODO: Use actual created timestamp */
-                    model: PLACEHOLDER_MODEL_NAME.to_string(), /* TODO: Use actual model name */
+                    created: DEFAULT_CREATED_TIMESTAMP,
+                    model: state.model(),
                     object: CHAT_COMPLETION_CHUNK_OBJECT.to_string(),
                     system_fingerprint: None,
                     service_tier: None,
                     usage: Some(completion_usage),
-                }))
+                }])
             }
             anthropic::StreamEvent::Error { error } => {
                 tracing::warn!(error = ?error, "error in stream event");
-                Ok(None)
+                Ok(Vec::new())
             }
         }
     }
@@ -779,15 +833,15 @@ impl
     > for AnthropicConverter
 {
     type Error = MapperError;
+    type State = ();
 
     fn try_convert_chunk(
         &self,
+        _state: &mut Self::State,
         value: anthropic_ai_sdk::types::message::StreamEvent,
-    ) -> Result<
-        Option<anthropic_ai_sdk::types::message::StreamEvent>,
-        Self::Error,
-    > {
-        Ok(Some(value))
+    ) -> Result<Vec<anthropic_ai_sdk::types::message::StreamEvent>, Self::Error>
+    {
+        Ok(vec![value])
     }
 }
 
@@ -809,3 +863,23 @@ impl
         Ok(error)
     }
 }
+
+impl
+    TryConvertError<
+        crate::endpoints::anthropic::messages::AnthropicApiError,
+        crate::endpoints::anthropic::messages::AnthropicApiError,
+    > for AnthropicConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_error(
+        &self,
+        _resp_parts: &Parts,
+        value: crate::endpoints::anthropic::messages::AnthropicApiError,
+    ) -> Result<
+        crate::endpoints::anthropic::messages::AnthropicApiError,
+        Self::Error,
+    > {
+        Ok(value)
+    }
+}
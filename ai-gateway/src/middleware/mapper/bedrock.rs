@@ -3,11 +3,13 @@ use std::{collections::HashMap, str::FromStr};
 use async_openai::types::{
     CreateChatCompletionResponse, CreateChatCompletionStreamResponse,
 };
+use base64::Engine;
 use http::response::Parts;
 use uuid::Uuid;
 
 use super::{
     MapperError, TryConvert, TryConvertStreamData, model::ModelMapper,
+    split_data_uri,
 };
 use crate::{
     middleware::mapper::{DEFAULT_MAX_TOKENS, TryConvertError},
@@ -92,7 +94,13 @@ impl
             let mapped_tools: Vec<_> = tools
                 .into_iter()
                 .filter_map(|tool| {
-                    let parameters = tool.function.parameters?;
+                    let parameters =
+                        tool.function.parameters.unwrap_or_else(|| {
+                            serde_json::json!({
+                                "type": "object",
+                                "properties": {}
+                            })
+                        });
                     let json_value = serde_json::from_value(parameters).ok()?;
                     let tool_spec = bedrock::ToolSpecification::builder()
                         .name(tool.function.name.clone())
@@ -122,28 +130,20 @@ impl
                             vec![bedrock::ContentBlock::Text(content)]
                         }
                         openai::ChatCompletionRequestUserMessageContent::Array(content) => {
-                            content.into_iter().filter_map(|part| {
+                            let mut mapped_content = Vec::with_capacity(content.len());
+                            for part in content {
                                 match part {
                                     openai::ChatCompletionRequestUserMessageContentPart::Text(text) => {
-                                        Some(bedrock::ContentBlock::Text(text.text))
+                                        mapped_content.push(bedrock::ContentBlock::Text(text.text));
                                     }
                                     openai::ChatCompletionRequestUserMessageContentPart::ImageUrl(image) => {
-                                        if image.image_url.url.starts_with("http") {
-                                            None
-                                        } else {
-                                            let mapped_image = bedrock::ImageBlock::builder().format(
-                                                bedrock::ImageFormat::Png,
-                                            ).source(
-                                                bedrock::ImageSource::Bytes(aws_sdk_bedrockruntime::primitives::Blob::new(image.image_url.url))
-                                            ).build().ok()?;
-                                            Some(bedrock::ContentBlock::Image(mapped_image))
-                                        }
-                                    }
-                                    openai::ChatCompletionRequestUserMessageContentPart::InputAudio(_audio) => {
-                                        None
+                                        mapped_content.push(image_block(&image.image_url.url)?);
                                     }
+                                    // Bedrock's Converse API does not support audio
+                                    openai::ChatCompletionRequestUserMessageContentPart::InputAudio(_audio) => {}
                                 }
-                            }).collect()
+                            }
+                            mapped_content
                         }
                     };
                     let mapped_message = bedrock::Message::builder()
@@ -156,8 +156,8 @@ impl
                     }
                 }
                 openai::ChatCompletionRequestMessage::Assistant(message) => {
-                    let mapped_content = match message.content {
-                        Some(openai::ChatCompletionRequestAssistantMessageContent::Text(content)) => {
+                    let mut mapped_content = match message.content {
+                        Some(openai::ChatCompletionRequestAssistantMessageContent::Text(content)) if !content.is_empty() => {
                             vec![bedrock::ContentBlock::Text(content)]
                         }
                         Some(openai::ChatCompletionRequestAssistantMessageContent::Array(content)) => {
@@ -172,8 +172,26 @@ impl
                                 }
                             }).collect()
                         }
-                        None => continue,
+                        Some(openai::ChatCompletionRequestAssistantMessageContent::Text(_)) | None => Vec::new(),
                     };
+                    for tool_call in message.tool_calls.unwrap_or_default() {
+                        let input = serde_json::from_str::<serde_json::Value>(
+                            &tool_call.function.arguments,
+                        )
+                        .unwrap_or_else(|_| serde_json::json!({}));
+                        let tool_use = bedrock::ToolUseBlock::builder()
+                            .tool_use_id(tool_call.id)
+                            .name(tool_call.function.name)
+                            .input(serde_json::from_value(input)?)
+                            .build();
+                        if let Ok(tool_use) = tool_use {
+                            mapped_content
+                                .push(bedrock::ContentBlock::ToolUse(tool_use));
+                        }
+                    }
+                    if mapped_content.is_empty() {
+                        continue;
+                    }
                     let mapped_message = bedrock::Message::builder()
                         .role(bedrock::ConversationRole::Assistant)
                         .set_content(Some(mapped_content))
@@ -183,40 +201,54 @@ impl
                     }
                 }
                 openai::ChatCompletionRequestMessage::Tool(message) => {
-                    let mapped_content = match message.content {
+                    let content = match message.content {
                         openai::ChatCompletionRequestToolMessageContent::Text(text) => {
-                            let x = bedrock::ToolResultBlock::builder().tool_use_id(message.tool_call_id).content(
-                                    bedrock::ToolResultContentBlock::Text(text)
-                                ).build();
-                            if let Ok(tool_result_block) = x {
-                                vec![bedrock::ContentBlock::ToolResult(tool_result_block)]
-                            } else {
-                                vec![]
-                            }
+                            vec![bedrock::ToolResultContentBlock::Text(text)]
                         }
                         openai::ChatCompletionRequestToolMessageContent::Array(content) => {
-                            content.into_iter().filter_map(|part| {
+                            content.into_iter().map(|part| {
                                 match part {
                                     openai::ChatCompletionRequestToolMessageContentPart::Text(text) => {
-                                        let tool_result_block = bedrock::ToolResultBlock::builder()
-                                                .tool_use_id(message.tool_call_id.clone())
-                                                .content(
-                                                    bedrock::ToolResultContentBlock::Text(text.text)
-                                                )
-                                                .build().ok()?;
-                                        Some(bedrock::ContentBlock::ToolResult(tool_result_block))
+                                        bedrock::ToolResultContentBlock::Text(text.text)
                                     }
                                 }
                             }).collect()
                         }
                     };
-
-                    let mapped_message = bedrock::Message::builder()
-                        .role(bedrock::ConversationRole::Assistant)
-                        .set_content(Some(mapped_content))
-                        .build();
-                    if let Ok(mapped_message) = mapped_message {
-                        mapped_messages.push(mapped_message);
+                    let Ok(tool_result) = bedrock::ToolResultBlock::builder()
+                        .tool_use_id(message.tool_call_id)
+                        .set_content(Some(content))
+                        .build()
+                    else {
+                        continue;
+                    };
+                    let tool_result =
+                        bedrock::ContentBlock::ToolResult(tool_result);
+
+                    // Bedrock expects the results of parallel tool calls in
+                    // the single user turn following the tool calls, OpenAI
+                    // sends one message for each of them.
+                    let tool_results = mapped_messages.last_mut().filter(
+                        |message: &&mut bedrock::Message| {
+                            message.role == bedrock::ConversationRole::User
+                                && message.content.iter().all(|block| {
+                                    matches!(
+                                        block,
+                                        bedrock::ContentBlock::ToolResult(_)
+                                    )
+                                })
+                        },
+                    );
+                    if let Some(tool_results) = tool_results {
+                        tool_results.content.push(tool_result);
+                    } else {
+                        let mapped_message = bedrock::Message::builder()
+                            .role(bedrock::ConversationRole::User)
+                            .content(tool_result)
+                            .build();
+                        if let Ok(mapped_message) = mapped_message {
+                            mapped_messages.push(mapped_message);
+                        }
                     }
                 }
                 openai::ChatCompletionRequestMessage::Function(message) => {
@@ -371,11 +403,9 @@ impl
                         r#type: openai::ChatCompletionToolType::Function,
                         function: openai::FunctionCall {
                             name: tool_use_block.name.clone(),
-                            arguments: tool_use_block
-                                .input
-                                .as_string()
-                                .unwrap_or_default()
-                                .to_string(),
+                            arguments: serde_json::to_string(
+                                &tool_use_block.input,
+                            )?,
                         },
                     });
                 }
@@ -390,7 +420,7 @@ impl
                     });
                 }
                 bedrock::ContentBlock::Text(text) => {
-                    content = Some(text.clone());
+                    content.get_or_insert_with(String::new).push_str(&text);
                 }
                 bedrock::ContentBlock::ReasoningContent(reasoning) => {
                     if let Ok(reasoning_text) = reasoning.as_reasoning_text() {
@@ -428,7 +458,7 @@ impl
         let choice = openai::ChatChoice {
             index: 0,
             message,
-            finish_reason: None,
+            finish_reason: finish_reason(&value.stop_reason),
             logprobs: None,
         };
 
@@ -446,6 +476,25 @@ impl
     }
 }
 
+/// State carried across the events of a single Bedrock stream while
+/// translating it to OpenAI chunks.
+#[derive(Debug, Default)]
+pub struct BedrockStreamState {
+    /// OpenAI tool call indices by Bedrock content block index, since
+    /// Bedrock counts text blocks too.
+    tool_call_indices: HashMap<i32, u32>,
+}
+
+impl BedrockStreamState {
+    fn tool_call_index(&mut self, content_block_index: i32) -> u32 {
+        let next = u32::try_from(self.tool_call_indices.len()).unwrap_or(0);
+        *self
+            .tool_call_indices
+            .entry(content_block_index)
+            .or_insert(next)
+    }
+}
+
 impl
     TryConvertStreamData<
         aws_sdk_bedrockruntime::types::ConverseStreamOutput,
@@ -453,15 +502,14 @@ impl
     > for BedrockConverter
 {
     type Error = MapperError;
+    type State = BedrockStreamState;
 
     #[allow(clippy::too_many_lines)]
     fn try_convert_chunk(
         &self,
+        state: &mut Self::State,
         value: aws_sdk_bedrockruntime::types::ConverseStreamOutput,
-    ) -> Result<
-        std::option::Option<CreateChatCompletionStreamResponse>,
-        Self::Error,
-    > {
+    ) -> Result<Vec<CreateChatCompletionStreamResponse>, Self::Error> {
         use async_openai::types as openai;
         use aws_sdk_bedrockruntime::types as bedrock;
         const CHAT_COMPLETION_CHUNK_OBJECT: &str = "chat.completion.chunk";
@@ -516,10 +564,9 @@ impl
                 {
                     let tool_call_chunk =
                         openai::ChatCompletionMessageToolCallChunk {
-                            index: content_block_start
-                                .content_block_index
-                                .try_into()
-                                .unwrap_or(0),
+                            index: state.tool_call_index(
+                                content_block_start.content_block_index,
+                            ),
                             id: Some(tool_use.tool_use_id),
                             r#type: Some(
                                 openai::ChatCompletionToolType::Function,
@@ -572,18 +619,16 @@ impl
                     Some(bedrock::ContentBlockDelta::ToolUse(tool_use)) => {
                         let tool_call_chunk =
                             openai::ChatCompletionMessageToolCallChunk {
-                                index: u32::try_from(
+                                index: state.tool_call_index(
                                     content_block_delta_event
                                         .content_block_index,
-                                )
-                                .unwrap_or(0),
-                                id: None, /* ID would have been sent with ContentBlockStart for this tool */
-                                r#type: Some(
-                                    openai::ChatCompletionToolType::Function,
-                                ), // Assuming function
+                                ),
+                                // the id, type and name were sent with the
+                                // content block start
+                                id: None,
+                                r#type: None,
                                 function: Some(openai::FunctionCallStream {
-                                    name: None, /* Name would have been sent
-                                                 * with ContentBlockStart */
+                                    name: None,
                                     arguments: Some(tool_use.input),
                                 }),
                             };
@@ -617,12 +662,26 @@ impl
                         u32::try_from(usage.total_tokens).unwrap_or(0);
                 }
             }
-            bedrock::ConverseStreamOutput::ContentBlockStop(_)
-            | bedrock::ConverseStreamOutput::MessageStop(_)
-            | _ => {}
+            bedrock::ConverseStreamOutput::MessageStop(message_stop) => {
+                let choice = openai::ChatChoiceStream {
+                    index: 0,
+                    delta: openai::ChatCompletionStreamResponseDelta {
+                        role: None,
+                        content: None,
+                        tool_calls: None,
+                        refusal: None,
+                        #[allow(deprecated)]
+                        function_call: None,
+                    },
+                    finish_reason: finish_reason(&message_stop.stop_reason),
+                    logprobs: None,
+                };
+                choices.push(choice);
+            }
+            bedrock::ConverseStreamOutput::ContentBlockStop(_) | _ => {}
         }
 
-        Ok(Some(CreateChatCompletionStreamResponse {
+        Ok(vec![CreateChatCompletionStreamResponse {
             id: PLACEHOLDER_STREAM_ID.to_string(), /* TODO: Use actual
                                                     * stream
                                                     * ID */
@@ -636,7 +695,7 @@ impl
             system_fingerprint: None,
             service_tier: None,
             usage: Some(completion_usage),
-        }))
+        }])
     }
 }
 
@@ -656,3 +715,57 @@ impl
         Ok(super::openai_error_from_status(resp_parts.status, None))
     }
 }
+
+/// Bedrock only takes images as bytes, so they must have been inlined as a
+/// base64 data URI, see [`crate::middleware::multimodal`].
+fn image_block(
+    url: &str,
+) -> Result<aws_sdk_bedrockruntime::types::ContentBlock, MapperError> {
+    use aws_sdk_bedrockruntime::{
+        primitives::Blob,
+        types::{ContentBlock, ImageBlock, ImageFormat, ImageSource},
+    };
+    let (media_type, data) = split_data_uri(url).ok_or_else(|| {
+        MapperError::ImageMappingInvalid(
+            "Bedrock only supports base64 encoded images".to_string(),
+        )
+    })?;
+    let format = match media_type.as_str() {
+        "image/png" => ImageFormat::Png,
+        "image/jpeg" => ImageFormat::Jpeg,
+        "image/gif" => ImageFormat::Gif,
+        "image/webp" => ImageFormat::Webp,
+        _ => {
+            return Err(MapperError::ImageMappingInvalid(format!(
+                "unsupported image type: {media_type}"
+            )));
+        }
+    };
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|e| MapperError::ImageMappingInvalid(e.to_string()))?;
+    let image = ImageBlock::builder()
+        .format(format)
+        .source(ImageSource::Bytes(Blob::new(bytes)))
+        .build()
+        .map_err(|e| MapperError::FailedToMapBedrockMessage(e.into()))?;
+    Ok(ContentBlock::Image(image))
+}
+
+fn finish_reason(
+    stop_reason: &aws_sdk_bedrockruntime::types::StopReason,
+) -> Option<async_openai::types::FinishReason> {
+    use async_openai::types::FinishReason;
+    use aws_sdk_bedrockruntime::types::StopReason;
+    match stop_reason {
+        StopReason::EndTurn | StopReason::StopSequence => {
+            Some(FinishReason::Stop)
+        }
+        StopReason::MaxTokens => Some(FinishReason::Length),
+        StopReason::ToolUse => Some(FinishReason::ToolCalls),
+        StopReason::ContentFiltered | StopReason::GuardrailIntervened => {
+            Some(FinishReason::ContentFilter)
+        }
+        _ => None,
+    }
+}
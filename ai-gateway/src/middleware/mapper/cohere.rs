@@ -0,0 +1,112 @@
+use std::str::FromStr;
+
+use http::response::Parts;
+
+use super::{
+    TryConvert, TryConvertError, TryConvertStreamData, model::ModelMapper,
+};
+use crate::{
+    endpoints::{
+        cohere::{
+            COHERE,
+            embed::{
+                CohereApiError, EmbedRequest, EmbedResponse, EmbeddingType,
+                SEARCH_DOCUMENT_INPUT_TYPE,
+            },
+        },
+        openai::embeddings::{CreateEmbeddingRequest, CreateEmbeddingResponse},
+    },
+    error::mapper::MapperError,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+pub struct CohereConverter {
+    model_mapper: ModelMapper,
+}
+
+impl CohereConverter {
+    #[must_use]
+    pub fn new(model_mapper: ModelMapper) -> Self {
+        Self { model_mapper }
+    }
+}
+
+impl TryConvert<CreateEmbeddingRequest, EmbedRequest> for CohereConverter {
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: CreateEmbeddingRequest,
+    ) -> Result<EmbedRequest, Self::Error> {
+        let source_model = ModelId::from_str(&value.model)?;
+        let target_model = self.model_mapper.map_model(
+            &source_model,
+            &InferenceProvider::Named(COHERE.into()),
+        )?;
+        tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
+        // Tokenized input is rejected before it reaches the mapper.
+        let texts = value
+            .input
+            .into_texts()
+            .ok_or(MapperError::InvalidRequest)?;
+
+        Ok(EmbedRequest {
+            model: target_model.to_string(),
+            texts,
+            input_type: SEARCH_DOCUMENT_INPUT_TYPE.to_string(),
+            embedding_types: vec![EmbeddingType::Float],
+            output_dimension: value.dimensions,
+        })
+    }
+}
+
+impl TryConvert<EmbedResponse, CreateEmbeddingResponse> for CohereConverter {
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: EmbedResponse,
+    ) -> Result<CreateEmbeddingResponse, Self::Error> {
+        let prompt_tokens = value
+            .meta
+            .and_then(|meta| meta.billed_units)
+            .and_then(|billed_units| billed_units.input_tokens)
+            .unwrap_or_default();
+        Ok(super::openai_embeddings_response(
+            value.embeddings.float,
+            prompt_tokens,
+        ))
+    }
+}
+
+impl TryConvertStreamData<EmbedResponse, CreateEmbeddingResponse>
+    for CohereConverter
+{
+    type Error = MapperError;
+    type State = ();
+
+    fn try_convert_chunk(
+        &self,
+        _state: &mut Self::State,
+        value: EmbedResponse,
+    ) -> Result<Vec<CreateEmbeddingResponse>, Self::Error> {
+        Ok(vec![self.try_convert(value)?])
+    }
+}
+
+impl TryConvertError<CohereApiError, async_openai::error::WrappedError>
+    for CohereConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_error(
+        &self,
+        resp_parts: &Parts,
+        value: CohereApiError,
+    ) -> Result<async_openai::error::WrappedError, Self::Error> {
+        Ok(super::openai_error_from_status(
+            resp_parts.status,
+            Some(value.message).filter(|message| !message.is_empty()),
+        ))
+    }
+}
@@ -0,0 +1,841 @@
+use std::{collections::HashMap, str::FromStr};
+
+use http::response::Parts;
+use uuid::Uuid;
+
+use super::{
+    TryConvert, TryConvertError, TryConvertStreamData,
+    anthropic::OPENAI_CHAT_COMPLETION_OBJECT,
+    model::ModelMapper,
+    openai::{
+        MessagesStreamState, chat_request_from_messages,
+        messages_events_from_chat_chunk, messages_response_from_chat,
+    },
+    split_data_uri,
+};
+use crate::{
+    endpoints::{
+        google::{
+            embed_contents::{
+                BatchEmbedContentsRequest, BatchEmbedContentsResponse,
+                EmbedContentRequest,
+            },
+            generate_contents::{
+                Blob, Candidate, Content, FileData, FinishReason, FunctionCall,
+                FunctionCallingConfig, FunctionCallingMode,
+                FunctionDeclaration, FunctionResponse, GeminiApiError,
+                GeminiChatCompletionRequest, GenerateContentRequest,
+                GenerateContentResponse, GenerationConfig, Part, Role,
+                SafetySetting, Tool, ToolConfig, UsageMetadata,
+            },
+        },
+        openai::embeddings::{CreateEmbeddingRequest, CreateEmbeddingResponse},
+    },
+    error::mapper::MapperError,
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+const CHAT_COMPLETION_CHUNK_OBJECT: &str = "chat.completion.chunk";
+
+pub struct GeminiConverter {
+    model_mapper: ModelMapper,
+}
+
+impl GeminiConverter {
+    #[must_use]
+    pub fn new(model_mapper: ModelMapper) -> Self {
+        Self { model_mapper }
+    }
+
+    fn map_model(&self, model: &str) -> Result<ModelId, MapperError> {
+        let source_model = ModelId::from_str(model)?;
+        let target_model = self
+            .model_mapper
+            .map_model(&source_model, &InferenceProvider::GoogleGemini)?;
+        tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
+        Ok(target_model)
+    }
+}
+
+impl TryConvert<GeminiChatCompletionRequest, GenerateContentRequest>
+    for GeminiConverter
+{
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: GeminiChatCompletionRequest,
+    ) -> Result<GenerateContentRequest, Self::Error> {
+        let target_model = self.map_model(&value.inner.model)?;
+        generate_content_request_from_chat(
+            value.inner,
+            &target_model,
+            value.safety_settings,
+        )
+    }
+}
+
+impl
+    TryConvert<
+        GenerateContentResponse,
+        async_openai::types::CreateChatCompletionResponse,
+    > for GeminiConverter
+{
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: GenerateContentResponse,
+    ) -> Result<async_openai::types::CreateChatCompletionResponse, Self::Error>
+    {
+        chat_response_from_generate_content(value)
+    }
+}
+
+/// State carried across the chunks of a single Gemini stream while
+/// translating it to OpenAI chunks.
+#[derive(Debug, Default)]
+pub struct GeminiStreamState {
+    /// Gemini may not send a response id, but OpenAI expects the same id on
+    /// every chunk.
+    id: Option<String>,
+    role_sent: bool,
+    /// The number of tool calls sent so far, since Gemini sends each
+    /// function call whole but OpenAI indexes them across the stream.
+    tool_calls: u32,
+}
+
+impl
+    TryConvertStreamData<
+        GenerateContentResponse,
+        async_openai::types::CreateChatCompletionStreamResponse,
+    > for GeminiConverter
+{
+    type Error = MapperError;
+    type State = GeminiStreamState;
+
+    fn try_convert_chunk(
+        &self,
+        state: &mut Self::State,
+        value: GenerateContentResponse,
+    ) -> Result<
+        Vec<async_openai::types::CreateChatCompletionStreamResponse>,
+        Self::Error,
+    > {
+        chat_chunk_from_generate_content(state, value)
+    }
+}
+
+impl TryConvertError<GeminiApiError, async_openai::error::WrappedError>
+    for GeminiConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_error(
+        &self,
+        resp_parts: &Parts,
+        value: GeminiApiError,
+    ) -> Result<async_openai::error::WrappedError, Self::Error> {
+        Ok(super::openai_error_from_status(
+            resp_parts.status,
+            error_message(value),
+        ))
+    }
+}
+
+impl
+    TryConvert<
+        anthropic_ai_sdk::types::message::CreateMessageParams,
+        GenerateContentRequest,
+    > for GeminiConverter
+{
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: anthropic_ai_sdk::types::message::CreateMessageParams,
+    ) -> Result<GenerateContentRequest, Self::Error> {
+        let target_model = self.map_model(&value.model)?;
+        let request = chat_request_from_messages(value, &target_model)?;
+        generate_content_request_from_chat(request, &target_model, None)
+    }
+}
+
+impl
+    TryConvert<
+        GenerateContentResponse,
+        anthropic_ai_sdk::types::message::CreateMessageResponse,
+    > for GeminiConverter
+{
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: GenerateContentResponse,
+    ) -> Result<
+        anthropic_ai_sdk::types::message::CreateMessageResponse,
+        Self::Error,
+    > {
+        messages_response_from_chat(chat_response_from_generate_content(value)?)
+    }
+}
+
+/// State carried across the chunks of a single Gemini stream while
+/// translating it to Anthropic events, by way of OpenAI chunks.
+#[derive(Debug, Default)]
+pub struct GeminiMessagesStreamState {
+    chat: GeminiStreamState,
+    messages: MessagesStreamState,
+}
+
+impl
+    TryConvertStreamData<
+        GenerateContentResponse,
+        anthropic_ai_sdk::types::message::StreamEvent,
+    > for GeminiConverter
+{
+    type Error = MapperError;
+    type State = GeminiMessagesStreamState;
+
+    fn try_convert_chunk(
+        &self,
+        state: &mut Self::State,
+        value: GenerateContentResponse,
+    ) -> Result<Vec<anthropic_ai_sdk::types::message::StreamEvent>, Self::Error>
+    {
+        let mut events = Vec::new();
+        for chunk in chat_chunk_from_generate_content(&mut state.chat, value)? {
+            events.extend(messages_events_from_chat_chunk(
+                &mut state.messages,
+                chunk,
+            )?);
+        }
+        Ok(events)
+    }
+}
+
+impl
+    TryConvertError<
+        GeminiApiError,
+        crate::endpoints::anthropic::messages::AnthropicApiError,
+    > for GeminiConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_error(
+        &self,
+        resp_parts: &Parts,
+        value: GeminiApiError,
+    ) -> Result<
+        crate::endpoints::anthropic::messages::AnthropicApiError,
+        Self::Error,
+    > {
+        Ok(super::anthropic_error_from_status(
+            resp_parts.status,
+            error_message(value),
+        ))
+    }
+}
+
+impl TryConvert<CreateEmbeddingRequest, BatchEmbedContentsRequest>
+    for GeminiConverter
+{
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: CreateEmbeddingRequest,
+    ) -> Result<BatchEmbedContentsRequest, Self::Error> {
+        let model = self.map_model(&value.model)?.to_string();
+        // Tokenized input is rejected before it reaches the mapper.
+        let texts = value
+            .input
+            .into_texts()
+            .ok_or(MapperError::InvalidRequest)?;
+        let requests = texts
+            .into_iter()
+            .map(|text| EmbedContentRequest {
+                model: format!("models/{model}"),
+                content: Content {
+                    role: None,
+                    parts: vec![Part {
+                        text: Some(text),
+                        ..Default::default()
+                    }],
+                },
+                output_dimensionality: value.dimensions,
+            })
+            .collect();
+
+        Ok(BatchEmbedContentsRequest { model, requests })
+    }
+}
+
+impl TryConvert<BatchEmbedContentsResponse, CreateEmbeddingResponse>
+    for GeminiConverter
+{
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: BatchEmbedContentsResponse,
+    ) -> Result<CreateEmbeddingResponse, Self::Error> {
+        // Gemini doesn't report the tokens of embeddings requests.
+        Ok(super::openai_embeddings_response(
+            value
+                .embeddings
+                .into_iter()
+                .map(|embedding| embedding.values)
+                .collect(),
+            0,
+        ))
+    }
+}
+
+impl TryConvertStreamData<BatchEmbedContentsResponse, CreateEmbeddingResponse>
+    for GeminiConverter
+{
+    type Error = MapperError;
+    type State = ();
+
+    fn try_convert_chunk(
+        &self,
+        _state: &mut Self::State,
+        value: BatchEmbedContentsResponse,
+    ) -> Result<Vec<CreateEmbeddingResponse>, Self::Error> {
+        Ok(vec![self.try_convert(value)?])
+    }
+}
+
+fn error_message(value: GeminiApiError) -> Option<String> {
+    Some(value.error.message).filter(|message| !message.is_empty())
+}
+
+/// Maps an OpenAI chat completions request to a Gemini `generateContent`
+/// request for `target_model`.
+#[allow(clippy::too_many_lines)]
+fn generate_content_request_from_chat(
+    value: async_openai::types::CreateChatCompletionRequest,
+    target_model: &ModelId,
+    safety_settings: Option<Vec<SafetySetting>>,
+) -> Result<GenerateContentRequest, MapperError> {
+    use async_openai::types as openai;
+
+    let mut system_parts = Vec::new();
+    let mut contents: Vec<Content> = Vec::with_capacity(value.messages.len());
+    // Gemini matches function responses to calls by name rather than id
+    let mut tool_call_names: HashMap<String, String> = HashMap::new();
+    for message in value.messages {
+        match message {
+            openai::ChatCompletionRequestMessage::Developer(message) => {
+                match message.content {
+                    openai::ChatCompletionRequestDeveloperMessageContent::Text(text) => {
+                        system_parts.push(Part::text(text));
+                    }
+                    openai::ChatCompletionRequestDeveloperMessageContent::Array(parts) => {
+                        system_parts.extend(
+                            parts.into_iter().map(|part| Part::text(part.text)),
+                        );
+                    }
+                }
+            }
+            openai::ChatCompletionRequestMessage::System(message) => {
+                match message.content {
+                    openai::ChatCompletionRequestSystemMessageContent::Text(text) => {
+                        system_parts.push(Part::text(text));
+                    }
+                    openai::ChatCompletionRequestSystemMessageContent::Array(parts) => {
+                        system_parts.extend(parts.into_iter().map(|part| match part {
+                            openai::ChatCompletionRequestSystemMessageContentPart::Text(text) => {
+                                Part::text(text.text)
+                            }
+                        }));
+                    }
+                }
+            }
+            openai::ChatCompletionRequestMessage::User(message) => {
+                let parts = match message.content {
+                    openai::ChatCompletionRequestUserMessageContent::Text(text) => {
+                        vec![Part::text(text)]
+                    }
+                    openai::ChatCompletionRequestUserMessageContent::Array(content) => {
+                        let mut parts = Vec::with_capacity(content.len());
+                        for part in content {
+                            match part {
+                                openai::ChatCompletionRequestUserMessageContentPart::Text(text) => {
+                                    parts.push(Part::text(text.text));
+                                }
+                                openai::ChatCompletionRequestUserMessageContentPart::ImageUrl(image) => {
+                                    parts.push(image_part(image.image_url.url)?);
+                                }
+                                openai::ChatCompletionRequestUserMessageContentPart::InputAudio(audio) => {
+                                    parts.push(audio_part(audio.input_audio));
+                                }
+                            }
+                        }
+                        parts
+                    }
+                };
+                push_content(&mut contents, Role::User, parts);
+            }
+            openai::ChatCompletionRequestMessage::Assistant(message) => {
+                let mut parts = Vec::new();
+                match message.content {
+                    Some(openai::ChatCompletionRequestAssistantMessageContent::Text(text)) => {
+                        if !text.is_empty() {
+                            parts.push(Part::text(text));
+                        }
+                    }
+                    Some(openai::ChatCompletionRequestAssistantMessageContent::Array(content)) => {
+                        parts.extend(content.into_iter().map(|part| match part {
+                            openai::ChatCompletionRequestAssistantMessageContentPart::Text(text) => {
+                                Part::text(text.text)
+                            }
+                            openai::ChatCompletionRequestAssistantMessageContentPart::Refusal(refusal) => {
+                                Part::text(refusal.refusal)
+                            }
+                        }));
+                    }
+                    None => {}
+                }
+                for tool_call in message.tool_calls.unwrap_or_default() {
+                    let args = if tool_call.function.arguments.is_empty() {
+                        serde_json::Value::Object(serde_json::Map::new())
+                    } else {
+                        serde_json::from_str(&tool_call.function.arguments)?
+                    };
+                    tool_call_names
+                        .insert(tool_call.id.clone(), tool_call.function.name.clone());
+                    parts.push(Part {
+                        function_call: Some(FunctionCall {
+                            id: Some(tool_call.id),
+                            name: tool_call.function.name,
+                            args,
+                        }),
+                        ..Default::default()
+                    });
+                }
+                push_content(&mut contents, Role::Model, parts);
+            }
+            openai::ChatCompletionRequestMessage::Tool(message) => {
+                let text = match message.content {
+                    openai::ChatCompletionRequestToolMessageContent::Text(text) => text,
+                    openai::ChatCompletionRequestToolMessageContent::Array(content) => content
+                        .into_iter()
+                        .map(|part| match part {
+                            openai::ChatCompletionRequestToolMessageContentPart::Text(text) => {
+                                text.text
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                };
+                let name = tool_call_names
+                    .get(&message.tool_call_id)
+                    .cloned()
+                    .unwrap_or_else(|| message.tool_call_id.clone());
+                let part = Part {
+                    function_response: Some(FunctionResponse {
+                        id: Some(message.tool_call_id),
+                        name,
+                        response: function_response(text),
+                    }),
+                    ..Default::default()
+                };
+                push_content(&mut contents, Role::User, vec![part]);
+            }
+            openai::ChatCompletionRequestMessage::Function(message) => {
+                let part = Part {
+                    function_response: Some(FunctionResponse {
+                        id: None,
+                        name: message.name,
+                        response: function_response(
+                            message.content.unwrap_or_default(),
+                        ),
+                    }),
+                    ..Default::default()
+                };
+                push_content(&mut contents, Role::User, vec![part]);
+            }
+        }
+    }
+
+    let system_instruction = if system_parts.is_empty() {
+        None
+    } else {
+        Some(Content {
+            role: None,
+            parts: system_parts,
+        })
+    };
+
+    let tools = value.tools.map(|tools| {
+        vec![Tool {
+            function_declarations: tools
+                .into_iter()
+                .map(|tool| FunctionDeclaration {
+                    name: tool.function.name,
+                    description: tool.function.description,
+                    parameters: tool.function.parameters,
+                })
+                .collect(),
+        }]
+    });
+    let tool_config = value.tool_choice.map(|tool_choice| {
+        let (mode, allowed_function_names) = match tool_choice {
+            openai::ChatCompletionToolChoiceOption::None => {
+                (FunctionCallingMode::None, None)
+            }
+            openai::ChatCompletionToolChoiceOption::Auto => {
+                (FunctionCallingMode::Auto, None)
+            }
+            openai::ChatCompletionToolChoiceOption::Required => {
+                (FunctionCallingMode::Any, None)
+            }
+            openai::ChatCompletionToolChoiceOption::Named(tool) => {
+                (FunctionCallingMode::Any, Some(vec![tool.function.name]))
+            }
+        };
+        ToolConfig {
+            function_calling_config: FunctionCallingConfig {
+                mode,
+                allowed_function_names,
+            },
+        }
+    });
+
+    let (response_mime_type, response_schema) = match value.response_format {
+        Some(openai::ResponseFormat::JsonObject) => {
+            (Some(mime::APPLICATION_JSON.to_string()), None)
+        }
+        Some(openai::ResponseFormat::JsonSchema { json_schema }) => {
+            (Some(mime::APPLICATION_JSON.to_string()), json_schema.schema)
+        }
+        Some(openai::ResponseFormat::Text) | None => (None, None),
+    };
+    #[allow(deprecated)]
+    let max_output_tokens = value.max_completion_tokens.or(value.max_tokens);
+    let generation_config = GenerationConfig {
+        stop_sequences: match value.stop {
+            Some(openai::Stop::String(stop)) => Some(vec![stop]),
+            Some(openai::Stop::StringArray(stops)) => Some(stops),
+            None => None,
+        },
+        candidate_count: value.n,
+        max_output_tokens,
+        temperature: value.temperature,
+        top_p: value.top_p,
+        seed: value.seed,
+        presence_penalty: value.presence_penalty,
+        frequency_penalty: value.frequency_penalty,
+        response_mime_type,
+        response_schema,
+    };
+
+    Ok(GenerateContentRequest {
+        model: target_model.to_string(),
+        stream: value.stream.unwrap_or(false),
+        contents,
+        system_instruction,
+        tools,
+        tool_config,
+        safety_settings,
+        generation_config: Some(generation_config),
+    })
+}
+
+/// Appends `parts` to the last content if it has the same role, since Gemini
+/// expects turns to alternate, e.g. for several tool results in a row.
+fn push_content(contents: &mut Vec<Content>, role: Role, parts: Vec<Part>) {
+    if parts.is_empty() {
+        return;
+    }
+    match contents.last_mut() {
+        Some(last) if last.role == Some(role) => last.parts.extend(parts),
+        _ => contents.push(Content {
+            role: Some(role),
+            parts,
+        }),
+    }
+}
+
+/// Gemini expects a function response to be a JSON object, so anything else
+/// is wrapped in one.
+fn function_response(text: String) -> serde_json::Value {
+    match serde_json::from_str(&text) {
+        Ok(value @ serde_json::Value::Object(_)) => value,
+        _ => serde_json::json!({ "content": text }),
+    }
+}
+
+/// Images are either inlined from a base64 data URI or referenced by URL.
+fn image_part(url: String) -> Result<Part, MapperError> {
+    if !url.starts_with("data:") {
+        return Ok(Part {
+            file_data: Some(FileData {
+                mime_type: None,
+                file_uri: url,
+            }),
+            ..Default::default()
+        });
+    }
+    let (mime_type, data) = split_data_uri(&url).ok_or_else(|| {
+        MapperError::ImageMappingInvalid(
+            "invalid data URI or unknown image type".to_string(),
+        )
+    })?;
+    Ok(Part {
+        inline_data: Some(Blob {
+            mime_type,
+            data: data.to_string(),
+        }),
+        ..Default::default()
+    })
+}
+
+/// Audio is always inlined, OpenAI only takes it base64 encoded.
+fn audio_part(audio: async_openai::types::InputAudio) -> Part {
+    use async_openai::types::InputAudioFormat;
+    let mime_type = match audio.format {
+        InputAudioFormat::Wav => "audio/wav",
+        InputAudioFormat::Mp3 => "audio/mp3",
+    };
+    Part {
+        inline_data: Some(Blob {
+            mime_type: mime_type.to_string(),
+            data: audio.data,
+        }),
+        ..Default::default()
+    }
+}
+
+fn finish_reason(
+    finish_reason: FinishReason,
+    has_tool_calls: bool,
+) -> async_openai::types::FinishReason {
+    use async_openai::types as openai;
+    match finish_reason {
+        FinishReason::Stop | FinishReason::Other if has_tool_calls => {
+            openai::FinishReason::ToolCalls
+        }
+        FinishReason::MaxTokens => openai::FinishReason::Length,
+        FinishReason::Safety
+        | FinishReason::Recitation
+        | FinishReason::Blocklist
+        | FinishReason::ProhibitedContent
+        | FinishReason::Spii => openai::FinishReason::ContentFilter,
+        FinishReason::Stop
+        | FinishReason::MalformedFunctionCall
+        | FinishReason::Other => openai::FinishReason::Stop,
+    }
+}
+
+fn usage(usage: UsageMetadata) -> async_openai::types::CompletionUsage {
+    use async_openai::types as openai;
+    let reasoning_tokens = usage.thoughts_token_count.unwrap_or_default();
+    openai::CompletionUsage {
+        prompt_tokens: usage.prompt_token_count,
+        // OpenAI counts reasoning as part of the completion
+        completion_tokens: usage.candidates_token_count + reasoning_tokens,
+        total_tokens: usage.total_token_count,
+        prompt_tokens_details: usage.cached_content_token_count.map(
+            |cached_tokens| openai::PromptTokensDetails {
+                audio_tokens: None,
+                cached_tokens: Some(cached_tokens),
+            },
+        ),
+        completion_tokens_details: usage.thoughts_token_count.map(
+            |reasoning_tokens| openai::CompletionTokensDetails {
+                accepted_prediction_tokens: None,
+                audio_tokens: None,
+                reasoning_tokens: Some(reasoning_tokens),
+                rejected_prediction_tokens: None,
+            },
+        ),
+    }
+}
+
+/// The text and tool calls of a candidate, skipping the model's thoughts.
+fn candidate_parts(
+    candidate: &mut Candidate,
+) -> (Option<String>, Vec<FunctionCall>) {
+    let mut text: Option<String> = None;
+    let mut function_calls = Vec::new();
+    let parts = candidate
+        .content
+        .take()
+        .map(|content| content.parts)
+        .unwrap_or_default();
+    for part in parts {
+        if part.thought == Some(true) {
+            continue;
+        }
+        if let Some(part_text) = part.text {
+            text.get_or_insert_with(String::new).push_str(&part_text);
+        }
+        if let Some(function_call) = part.function_call {
+            function_calls.push(function_call);
+        }
+    }
+    (text, function_calls)
+}
+
+fn tool_call_id(function_call: &FunctionCall) -> String {
+    function_call
+        .id
+        .clone()
+        .unwrap_or_else(|| format!("call_{}", Uuid::new_v4().simple()))
+}
+
+fn chat_response_from_generate_content(
+    value: GenerateContentResponse,
+) -> Result<async_openai::types::CreateChatCompletionResponse, MapperError> {
+    use async_openai::types as openai;
+
+    let mut choices = Vec::with_capacity(value.candidates.len());
+    for (index, mut candidate) in value.candidates.into_iter().enumerate() {
+        let (content, function_calls) = candidate_parts(&mut candidate);
+        let tool_calls = function_calls
+            .into_iter()
+            .map(|function_call| {
+                Ok(openai::ChatCompletionMessageToolCall {
+                    id: tool_call_id(&function_call),
+                    r#type: openai::ChatCompletionToolType::Function,
+                    function: openai::FunctionCall {
+                        name: function_call.name,
+                        arguments: serde_json::to_string(&function_call.args)?,
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, MapperError>>()?;
+        let finish_reason = candidate
+            .finish_reason
+            .map(|reason| finish_reason(reason, !tool_calls.is_empty()));
+
+        #[allow(deprecated)]
+        let message = openai::ChatCompletionResponseMessage {
+            content,
+            refusal: None,
+            tool_calls: if tool_calls.is_empty() {
+                None
+            } else {
+                Some(tool_calls)
+            },
+            role: openai::Role::Assistant,
+            function_call: None,
+            audio: None,
+        };
+        choices.push(openai::ChatChoice {
+            index: candidate
+                .index
+                .unwrap_or_else(|| u32::try_from(index).unwrap_or(0)),
+            message,
+            finish_reason,
+            logprobs: None,
+        });
+    }
+
+    Ok(openai::CreateChatCompletionResponse {
+        id: value
+            .response_id
+            .unwrap_or_else(|| String::from(Uuid::new_v4())),
+        choices,
+        created: 0,
+        model: value.model_version.unwrap_or_default(),
+        object: OPENAI_CHAT_COMPLETION_OBJECT.to_string(),
+        usage: value.usage_metadata.map(usage),
+        service_tier: None,
+        system_fingerprint: None,
+    })
+}
+
+fn chat_chunk_from_generate_content(
+    state: &mut GeminiStreamState,
+    value: GenerateContentResponse,
+) -> Result<
+    Vec<async_openai::types::CreateChatCompletionStreamResponse>,
+    MapperError,
+> {
+    use async_openai::types as openai;
+
+    let id = state
+        .id
+        .get_or_insert_with(|| {
+            value
+                .response_id
+                .clone()
+                .unwrap_or_else(|| String::from(Uuid::new_v4()))
+        })
+        .clone();
+    let mut finished = false;
+    let mut choices = Vec::with_capacity(value.candidates.len());
+    for (index, mut candidate) in value.candidates.into_iter().enumerate() {
+        let (content, function_calls) = candidate_parts(&mut candidate);
+        let mut tool_calls = Vec::with_capacity(function_calls.len());
+        for function_call in function_calls {
+            tool_calls.push(openai::ChatCompletionMessageToolCallChunk {
+                index: state.tool_calls,
+                id: Some(tool_call_id(&function_call)),
+                r#type: Some(openai::ChatCompletionToolType::Function),
+                function: Some(openai::FunctionCallStream {
+                    name: Some(function_call.name),
+                    arguments: Some(serde_json::to_string(
+                        &function_call.args,
+                    )?),
+                }),
+            });
+            state.tool_calls += 1;
+        }
+        let finish_reason = candidate
+            .finish_reason
+            .map(|reason| finish_reason(reason, state.tool_calls > 0));
+        finished |= finish_reason.is_some();
+
+        let role = if state.role_sent {
+            None
+        } else {
+            state.role_sent = true;
+            Some(openai::Role::Assistant)
+        };
+        #[allow(deprecated)]
+        choices.push(openai::ChatChoiceStream {
+            index: candidate
+                .index
+                .unwrap_or_else(|| u32::try_from(index).unwrap_or(0)),
+            delta: openai::ChatCompletionStreamResponseDelta {
+                role,
+                content,
+                tool_calls: if tool_calls.is_empty() {
+                    None
+                } else {
+                    Some(tool_calls)
+                },
+                refusal: None,
+                function_call: None,
+            },
+            finish_reason,
+            logprobs: None,
+        });
+    }
+
+    if choices.is_empty() && value.usage_metadata.is_none() {
+        return Ok(Vec::new());
+    }
+
+    Ok(vec![openai::CreateChatCompletionStreamResponse {
+        id,
+        choices,
+        created: 0,
+        model: value.model_version.unwrap_or_default(),
+        object: CHAT_COMPLETION_CHUNK_OBJECT.to_string(),
+        system_fingerprint: None,
+        service_tier: None,
+        // Gemini sends the running usage with every chunk, OpenAI only
+        // with the last one
+        usage: if finished {
+            value.usage_metadata.map(usage)
+        } else {
+            None
+        },
+    }])
+}
@@ -1,5 +1,7 @@
 pub mod anthropic;
 mod bedrock;
+pub mod cohere;
+pub mod gemini;
 pub mod model;
 pub mod ollama;
 pub mod openai;
@@ -7,15 +9,26 @@ pub mod openai_compatible;
 pub mod registry;
 pub mod service;
 
+use std::any::Any;
+
 use async_openai::error::WrappedError;
 use base64::Engine;
-use bytes::Bytes;
+use bytes::{BufMut, Bytes, BytesMut};
 use http::{StatusCode, response::Parts};
 use serde::{Serialize, de::DeserializeOwned};
 
 pub use self::service::*;
 use crate::{
-    endpoints::{AiRequest, Endpoint},
+    endpoints::{
+        AiRequest, Endpoint,
+        anthropic::messages::{
+            ANTHROPIC_ERROR_TYPE, AnthropicApiError, ErrorDetails,
+        },
+        openai::embeddings::{
+            CreateEmbeddingResponse, EMBEDDING_LIST_OBJECT, EMBEDDING_OBJECT,
+            Embedding, EmbeddingUsage, EmbeddingVector,
+        },
+    },
     error::{
         api::ApiError, internal::InternalError,
         invalid_req::InvalidRequestError, mapper::MapperError,
@@ -48,14 +61,45 @@ pub trait TryConvertError<Source, Target>: Sized {
 
 pub trait TryConvertStreamData<Source, Target>: Sized {
     type Error;
+    /// State carried from one chunk of a stream to the next, e.g. a message
+    /// id that is only sent in the first chunk. A fresh state is created for
+    /// every stream.
+    type State: Default + Send + 'static;
 
-    /// Returns `None` if the chunk in `value` cannot be converted to an
-    /// equivalent chunk in `Target`.
+    /// Returns the chunks in `Target` equivalent to the chunk in `value`,
+    /// which is empty if it has no equivalent and may hold several chunks
+    /// if `Target` splits up what `Source` sends at once.
     fn try_convert_chunk(
         &self,
+        state: &mut Self::State,
         value: Source,
-    ) -> std::result::Result<Option<Target>, Self::Error>;
+    ) -> std::result::Result<Vec<Target>, Self::Error>;
+}
+
+/// The type-erased [`TryConvertStreamData::State`] of a single streaming
+/// response, created empty and passed to every
+/// [`EndpointConverter::convert_resp_body`] call for that response.
+#[derive(Default)]
+pub struct StreamState(Option<Box<dyn Any + Send>>);
+
+impl StreamState {
+    fn get_or_default<T: Default + Send + 'static>(&mut self) -> &mut T {
+        if !self.0.as_ref().is_some_and(|state| state.is::<T>()) {
+            self.0 = Some(Box::new(T::default()));
+        }
+        self.0
+            .as_mut()
+            .and_then(|state| state.downcast_mut())
+            .expect("stream state was just initialized")
+    }
 }
+
+impl std::fmt::Debug for StreamState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("StreamState").finish_non_exhaustive()
+    }
+}
+
 pub trait EndpointConverter {
     /// Convert a request body to a target request body with raw bytes.
     ///
@@ -68,13 +112,15 @@ pub trait EndpointConverter {
     ) -> Result<(Bytes, MapperContext), ApiError>;
     /// Convert a response body to a target response body with raw bytes.
     ///
-    /// Returns `None` if there is no applicable mapping for a given chunk
-    /// when converting stream response bodies.
+    /// Stream chunks are returned framed as server-sent events, and `None`
+    /// is returned if there is no applicable mapping for a given chunk.
+    /// `stream_state` must be the same for every chunk of a stream.
     fn convert_resp_body(
         &self,
         resp_parts: Parts,
         resp_body_bytes: Bytes,
         is_stream: bool,
+        stream_state: &mut StreamState,
     ) -> Result<Option<Bytes>, ApiError>;
 }
 
@@ -158,6 +204,7 @@ where
         resp_parts: Parts,
         bytes: Bytes,
         is_stream: bool,
+        stream_state: &mut StreamState,
     ) -> Result<Option<Bytes>, ApiError> {
         if is_stream {
             let source_response: T::StreamResponseBody =
@@ -166,24 +213,36 @@ where
                         ty: std::any::type_name::<T::StreamResponseBody>(),
                         error: e,
                     })?;
-            let target_response: Option<S::StreamResponseBody> = self
+            let state = stream_state.get_or_default::<
+                <C as TryConvertStreamData<T::StreamResponseBody, S::StreamResponseBody>>::State,
+            >();
+            let target_responses: Vec<S::StreamResponseBody> = self
                 .converter
-                .try_convert_chunk(source_response)
+                .try_convert_chunk(state, source_response)
                 .map_err(|e| InternalError::MapperError(e.into()))?;
 
-            if let Some(target_response) = target_response {
-                let target_bytes =
-                serde_json::to_vec(&target_response).map_err(|e| {
+            if target_responses.is_empty() {
+                return Ok(None);
+            }
+            let mut target_bytes = BytesMut::new();
+            for target_response in &target_responses {
+                let data =
+                serde_json::to_vec(target_response).map_err(|e| {
                     InternalError::Serialize {
-                        ty: std::any::type_name::<T::ResponseBody>(),
+                        ty: std::any::type_name::<S::StreamResponseBody>(),
                         error: e,
                     }
                 })?;
-
-                Ok(Some(Bytes::from(target_bytes)))
-            } else {
-                Ok(None)
+                if let Some(event) = S::stream_event_name(target_response) {
+                    target_bytes.put(format!("event: {event}\n").as_bytes());
+                }
+                // add the `data: ` prefix expected by SSE clients
+                target_bytes.put("data: ".as_bytes());
+                target_bytes.put(data.as_slice());
+                target_bytes.put("\n\n".as_bytes());
             }
+
+            Ok(Some(target_bytes.freeze()))
         } else if resp_parts.status.is_client_error() || resp_parts.status.is_server_error() {
             let source_error: T::ErrorResponseBody = serde_json::from_slice(&bytes)
                 .map_err(|e| InternalError::Deserialize {
@@ -247,7 +306,60 @@ pub(crate) fn openai_error_from_status(
     }
 }
 
-pub(super) fn mime_from_data_uri(uri: &str) -> Option<infer::Type> {
+/// An OpenAI embeddings response for `vectors`, in the order of the inputs
+/// of the request.
+///
+/// Providers that don't echo the model leave it empty; the embeddings layer
+/// fills in the model the request was sent to.
+pub(crate) fn openai_embeddings_response(
+    vectors: Vec<Vec<f32>>,
+    prompt_tokens: u32,
+) -> CreateEmbeddingResponse {
+    CreateEmbeddingResponse {
+        object: EMBEDDING_LIST_OBJECT.to_string(),
+        model: String::new(),
+        data: (0..)
+            .zip(vectors)
+            .map(|(index, vector)| Embedding {
+                index,
+                object: EMBEDDING_OBJECT.to_string(),
+                embedding: EmbeddingVector::Float(vector),
+            })
+            .collect(),
+        usage: EmbeddingUsage {
+            prompt_tokens,
+            total_tokens: prompt_tokens,
+        },
+    }
+}
+
+pub(crate) fn anthropic_error_from_status(
+    status_code: StatusCode,
+    message: Option<String>,
+) -> AnthropicApiError {
+    // https://docs.anthropic.com/en/api/errors
+    let kind = match status_code {
+        StatusCode::UNAUTHORIZED => "authentication_error",
+        StatusCode::FORBIDDEN => "permission_error",
+        StatusCode::NOT_FOUND => "not_found_error",
+        StatusCode::PAYLOAD_TOO_LARGE => "request_too_large",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limit_error",
+        status_code if status_code.as_u16() == 529 => "overloaded_error",
+        status_code if status_code.is_server_error() => "api_error",
+        _ => "invalid_request_error",
+    };
+    let message = message.unwrap_or_else(|| kind.to_string());
+
+    AnthropicApiError {
+        error: ErrorDetails {
+            message,
+            kind: kind.to_string(),
+        },
+        kind: ANTHROPIC_ERROR_TYPE.to_string(),
+    }
+}
+
+fn mime_from_data_uri(uri: &str) -> Option<infer::Type> {
     // Split on the first comma.  If no comma => not a data-URI.
     let (_first, b64) = uri.split_once(',')?;
 
@@ -264,3 +376,16 @@ pub(super) fn mime_from_data_uri(uri: &str) -> Option<infer::Type> {
 
     infer::get(&header[..n])
 }
+
+/// Split a `data:<media type>;base64,<data>` URI into its media type and
+/// base64 data, sniffing the media type from the data if the URI does not
+/// declare one.
+pub(crate) fn split_data_uri(uri: &str) -> Option<(String, &str)> {
+    let (media_type, data) = uri.strip_prefix("data:")?.split_once(',')?;
+    match media_type.split(';').next() {
+        Some(mime_type) if !mime_type.is_empty() => {
+            Some((mime_type.to_string(), data))
+        }
+        _ => Some((mime_from_data_uri(uri)?.mime_type().to_string(), data)),
+    }
+}
@@ -21,7 +21,7 @@ struct ProviderModels(
 impl ProviderModels {
     fn new(app_state: &AppState) -> Self {
         let mut map = HashMap::default();
-        for (provider, config) in app_state.config().providers.iter() {
+        for (provider, config) in app_state.providers().iter() {
             let models =
                 config.models.iter().map(|m| m.clone().into()).collect();
             map.insert(provider.clone(), models);
@@ -1,13 +1,29 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use async_openai::types::{
     CreateChatCompletionResponse, CreateChatCompletionStreamResponse,
 };
+use chrono::DateTime;
 use http::response::Parts;
+use uuid::Uuid;
 
-use super::{TryConvert, TryConvertStreamData};
+use super::{
+    TryConvert, TryConvertStreamData,
+    anthropic::OPENAI_CHAT_COMPLETION_OBJECT,
+    openai::{
+        MessagesStreamState, chat_request_from_messages,
+        messages_events_from_chat_chunk, messages_response_from_chat,
+    },
+    split_data_uri,
+};
 use crate::{
-    endpoints::ollama::chat_completions::CreateChatCompletionRequestOllama,
+    endpoints::ollama::{
+        chat::{
+            ChatRequest, ChatResponse, FunctionCall, Message, OllamaApiError,
+            Options, Role, ToolCall,
+        },
+        chat_completions::CreateChatCompletionRequestOllama,
+    },
     error::mapper::MapperError,
     middleware::mapper::{TryConvertError, model::ModelMapper},
     types::{model_id::ModelId, provider::InferenceProvider},
@@ -69,12 +85,14 @@ impl
     > for OllamaConverter
 {
     type Error = MapperError;
+    type State = ();
 
     fn try_convert_chunk(
         &self,
+        _state: &mut Self::State,
         value: CreateChatCompletionStreamResponse,
-    ) -> Result<Option<CreateChatCompletionStreamResponse>, Self::Error> {
-        Ok(Some(value))
+    ) -> Result<Vec<CreateChatCompletionStreamResponse>, Self::Error> {
+        Ok(vec![value])
     }
 }
 
@@ -94,3 +112,651 @@ impl
         Ok(value)
     }
 }
+
+impl
+    TryConvert<
+        anthropic_ai_sdk::types::message::CreateMessageParams,
+        CreateChatCompletionRequestOllama,
+    > for OllamaConverter
+{
+    type Error = MapperError;
+    fn try_convert(
+        &self,
+        value: anthropic_ai_sdk::types::message::CreateMessageParams,
+    ) -> Result<CreateChatCompletionRequestOllama, Self::Error> {
+        let source_model = ModelId::from_str(&value.model)?;
+        let target_model = self
+            .model_mapper
+            .map_model(&source_model, &InferenceProvider::Ollama)?;
+        tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
+        let request = chat_request_from_messages(value, &target_model)?;
+
+        Ok(CreateChatCompletionRequestOllama(request))
+    }
+}
+
+impl
+    TryConvert<
+        async_openai::types::CreateChatCompletionResponse,
+        anthropic_ai_sdk::types::message::CreateMessageResponse,
+    > for OllamaConverter
+{
+    type Error = MapperError;
+    fn try_convert(
+        &self,
+        value: async_openai::types::CreateChatCompletionResponse,
+    ) -> Result<
+        anthropic_ai_sdk::types::message::CreateMessageResponse,
+        Self::Error,
+    > {
+        messages_response_from_chat(value)
+    }
+}
+
+impl
+    TryConvertStreamData<
+        async_openai::types::CreateChatCompletionStreamResponse,
+        anthropic_ai_sdk::types::message::StreamEvent,
+    > for OllamaConverter
+{
+    type Error = MapperError;
+    type State = MessagesStreamState;
+
+    fn try_convert_chunk(
+        &self,
+        state: &mut Self::State,
+        value: async_openai::types::CreateChatCompletionStreamResponse,
+    ) -> Result<Vec<anthropic_ai_sdk::types::message::StreamEvent>, Self::Error>
+    {
+        messages_events_from_chat_chunk(state, value)
+    }
+}
+
+impl
+    TryConvertError<
+        async_openai::error::WrappedError,
+        crate::endpoints::anthropic::messages::AnthropicApiError,
+    > for OllamaConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_error(
+        &self,
+        resp_parts: &Parts,
+        value: async_openai::error::WrappedError,
+    ) -> Result<
+        crate::endpoints::anthropic::messages::AnthropicApiError,
+        Self::Error,
+    > {
+        Ok(super::anthropic_error_from_status(
+            resp_parts.status,
+            Some(value.error.message),
+        ))
+    }
+}
+
+/// Converts to Ollama's native chat API, for self-hosted providers with the
+/// `ollama` dialect.
+pub struct OllamaChatConverter {
+    provider: InferenceProvider,
+    model_mapper: ModelMapper,
+}
+
+impl OllamaChatConverter {
+    #[must_use]
+    pub fn new(provider: InferenceProvider, model_mapper: ModelMapper) -> Self {
+        Self {
+            provider,
+            model_mapper,
+        }
+    }
+
+    fn map_model(&self, model: &str) -> Result<ModelId, MapperError> {
+        let source_model = ModelId::from_str(model)?;
+        let target_model =
+            self.model_mapper.map_model(&source_model, &self.provider)?;
+        tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
+        Ok(target_model)
+    }
+}
+
+impl TryConvert<async_openai::types::CreateChatCompletionRequest, ChatRequest>
+    for OllamaChatConverter
+{
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: async_openai::types::CreateChatCompletionRequest,
+    ) -> Result<ChatRequest, Self::Error> {
+        let target_model = self.map_model(&value.model)?;
+        chat_request(value, &target_model, &self.provider)
+    }
+}
+
+impl TryConvert<ChatResponse, CreateChatCompletionResponse>
+    for OllamaChatConverter
+{
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: ChatResponse,
+    ) -> Result<CreateChatCompletionResponse, Self::Error> {
+        chat_completion_response(value)
+    }
+}
+
+/// State carried across the lines of a single Ollama stream while
+/// translating it to OpenAI chunks.
+#[derive(Debug, Default)]
+pub struct OllamaStreamState {
+    /// Ollama doesn't send an id, but OpenAI expects the same id on every
+    /// chunk.
+    id: Option<String>,
+    role_sent: bool,
+    /// The number of tool calls sent so far, since Ollama sends each tool
+    /// call whole but OpenAI indexes them across the stream.
+    tool_calls: u32,
+}
+
+impl TryConvertStreamData<ChatResponse, CreateChatCompletionStreamResponse>
+    for OllamaChatConverter
+{
+    type Error = MapperError;
+    type State = OllamaStreamState;
+
+    fn try_convert_chunk(
+        &self,
+        state: &mut Self::State,
+        value: ChatResponse,
+    ) -> Result<Vec<CreateChatCompletionStreamResponse>, Self::Error> {
+        chat_completion_chunk(state, value)
+    }
+}
+
+impl TryConvertError<OllamaApiError, async_openai::error::WrappedError>
+    for OllamaChatConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_error(
+        &self,
+        resp_parts: &Parts,
+        value: OllamaApiError,
+    ) -> Result<async_openai::error::WrappedError, Self::Error> {
+        Ok(super::openai_error_from_status(
+            resp_parts.status,
+            Some(value.error).filter(|message| !message.is_empty()),
+        ))
+    }
+}
+
+impl
+    TryConvert<
+        anthropic_ai_sdk::types::message::CreateMessageParams,
+        ChatRequest,
+    > for OllamaChatConverter
+{
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: anthropic_ai_sdk::types::message::CreateMessageParams,
+    ) -> Result<ChatRequest, Self::Error> {
+        let target_model = self.map_model(&value.model)?;
+        let request = chat_request_from_messages(value, &target_model)?;
+        chat_request(request, &target_model, &self.provider)
+    }
+}
+
+impl
+    TryConvert<
+        ChatResponse,
+        anthropic_ai_sdk::types::message::CreateMessageResponse,
+    > for OllamaChatConverter
+{
+    type Error = MapperError;
+
+    fn try_convert(
+        &self,
+        value: ChatResponse,
+    ) -> Result<
+        anthropic_ai_sdk::types::message::CreateMessageResponse,
+        Self::Error,
+    > {
+        messages_response_from_chat(chat_completion_response(value)?)
+    }
+}
+
+/// State carried across the lines of a single Ollama stream while
+/// translating it to Anthropic events, by way of OpenAI chunks.
+#[derive(Debug, Default)]
+pub struct OllamaMessagesStreamState {
+    chat: OllamaStreamState,
+    messages: MessagesStreamState,
+}
+
+impl
+    TryConvertStreamData<
+        ChatResponse,
+        anthropic_ai_sdk::types::message::StreamEvent,
+    > for OllamaChatConverter
+{
+    type Error = MapperError;
+    type State = OllamaMessagesStreamState;
+
+    fn try_convert_chunk(
+        &self,
+        state: &mut Self::State,
+        value: ChatResponse,
+    ) -> Result<Vec<anthropic_ai_sdk::types::message::StreamEvent>, Self::Error>
+    {
+        let mut events = Vec::new();
+        for chunk in chat_completion_chunk(&mut state.chat, value)? {
+            events.extend(messages_events_from_chat_chunk(
+                &mut state.messages,
+                chunk,
+            )?);
+        }
+        Ok(events)
+    }
+}
+
+impl
+    TryConvertError<
+        OllamaApiError,
+        crate::endpoints::anthropic::messages::AnthropicApiError,
+    > for OllamaChatConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_error(
+        &self,
+        resp_parts: &Parts,
+        value: OllamaApiError,
+    ) -> Result<
+        crate::endpoints::anthropic::messages::AnthropicApiError,
+        Self::Error,
+    > {
+        Ok(super::anthropic_error_from_status(
+            resp_parts.status,
+            Some(value.error).filter(|message| !message.is_empty()),
+        ))
+    }
+}
+
+fn text_message(role: Role, content: String) -> Message {
+    Message {
+        role,
+        content,
+        images: None,
+        tool_calls: None,
+        tool_name: None,
+    }
+}
+
+/// Ollama only takes images as bare base64 data; images referenced by URL
+/// are inlined by the multimodal layer before they get here.
+fn image_data(url: &str) -> Result<String, MapperError> {
+    split_data_uri(url)
+        .map(|(_media_type, data)| data.to_string())
+        .ok_or_else(|| {
+            MapperError::ImageMappingInvalid(
+                "Ollama only accepts base64 encoded images".to_string(),
+            )
+        })
+}
+
+/// Maps an OpenAI chat completions request to an Ollama chat request for
+/// `target_model`.
+#[allow(clippy::too_many_lines)]
+fn chat_request(
+    value: async_openai::types::CreateChatCompletionRequest,
+    target_model: &ModelId,
+    provider: &InferenceProvider,
+) -> Result<ChatRequest, MapperError> {
+    use async_openai::types as openai;
+
+    let mut messages = Vec::with_capacity(value.messages.len());
+    // Ollama matches tool results to calls by name rather than id
+    let mut tool_call_names: HashMap<String, String> = HashMap::new();
+    for message in value.messages {
+        let message = match message {
+            openai::ChatCompletionRequestMessage::Developer(message) => {
+                let content = match message.content {
+                    openai::ChatCompletionRequestDeveloperMessageContent::Text(text) => text,
+                    openai::ChatCompletionRequestDeveloperMessageContent::Array(parts) => parts
+                        .into_iter()
+                        .map(|part| part.text)
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                };
+                text_message(Role::System, content)
+            }
+            openai::ChatCompletionRequestMessage::System(message) => {
+                let content = match message.content {
+                    openai::ChatCompletionRequestSystemMessageContent::Text(text) => text,
+                    openai::ChatCompletionRequestSystemMessageContent::Array(parts) => parts
+                        .into_iter()
+                        .map(|part| match part {
+                            openai::ChatCompletionRequestSystemMessageContentPart::Text(text) => {
+                                text.text
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                };
+                text_message(Role::System, content)
+            }
+            openai::ChatCompletionRequestMessage::User(message) => {
+                match message.content {
+                    openai::ChatCompletionRequestUserMessageContent::Text(
+                        text,
+                    ) => text_message(Role::User, text),
+                    openai::ChatCompletionRequestUserMessageContent::Array(
+                        content,
+                    ) => {
+                        let mut texts = Vec::new();
+                        let mut images = Vec::new();
+                        for part in content {
+                            match part {
+                                openai::ChatCompletionRequestUserMessageContentPart::Text(text) => {
+                                    texts.push(text.text);
+                                }
+                                openai::ChatCompletionRequestUserMessageContentPart::ImageUrl(image) => {
+                                    images.push(image_data(&image.image_url.url)?);
+                                }
+                                // Ollama does not support audio
+                                openai::ChatCompletionRequestUserMessageContentPart::InputAudio(_audio) => {}
+                            }
+                        }
+                        Message {
+                            images: Some(images)
+                                .filter(|images| !images.is_empty()),
+                            ..text_message(Role::User, texts.join("\n"))
+                        }
+                    }
+                }
+            }
+            openai::ChatCompletionRequestMessage::Assistant(message) => {
+                let content = match message.content {
+                    Some(openai::ChatCompletionRequestAssistantMessageContent::Text(text)) => text,
+                    Some(openai::ChatCompletionRequestAssistantMessageContent::Array(content)) => content
+                        .into_iter()
+                        .map(|part| match part {
+                            openai::ChatCompletionRequestAssistantMessageContentPart::Text(text) => {
+                                text.text
+                            }
+                            openai::ChatCompletionRequestAssistantMessageContentPart::Refusal(refusal) => {
+                                refusal.refusal
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    None => String::new(),
+                };
+                let mut tool_calls = Vec::new();
+                for tool_call in message.tool_calls.unwrap_or_default() {
+                    let arguments = if tool_call.function.arguments.is_empty() {
+                        serde_json::Value::Object(serde_json::Map::new())
+                    } else {
+                        serde_json::from_str(&tool_call.function.arguments)?
+                    };
+                    tool_call_names
+                        .insert(tool_call.id, tool_call.function.name.clone());
+                    tool_calls.push(ToolCall {
+                        function: FunctionCall {
+                            name: tool_call.function.name,
+                            arguments,
+                        },
+                    });
+                }
+                Message {
+                    tool_calls: Some(tool_calls)
+                        .filter(|tool_calls| !tool_calls.is_empty()),
+                    ..text_message(Role::Assistant, content)
+                }
+            }
+            openai::ChatCompletionRequestMessage::Tool(message) => {
+                let content = match message.content {
+                    openai::ChatCompletionRequestToolMessageContent::Text(text) => text,
+                    openai::ChatCompletionRequestToolMessageContent::Array(content) => content
+                        .into_iter()
+                        .map(|part| match part {
+                            openai::ChatCompletionRequestToolMessageContentPart::Text(text) => {
+                                text.text
+                            }
+                        })
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                };
+                Message {
+                    tool_name: tool_call_names
+                        .get(&message.tool_call_id)
+                        .cloned(),
+                    ..text_message(Role::Tool, content)
+                }
+            }
+            openai::ChatCompletionRequestMessage::Function(message) => {
+                Message {
+                    tool_name: Some(message.name),
+                    ..text_message(
+                        Role::Tool,
+                        message.content.unwrap_or_default(),
+                    )
+                }
+            }
+        };
+        messages.push(message);
+    }
+
+    let format = match value.response_format {
+        Some(openai::ResponseFormat::JsonObject) => {
+            Some(serde_json::Value::from("json"))
+        }
+        Some(openai::ResponseFormat::JsonSchema { json_schema }) => Some(
+            json_schema
+                .schema
+                .unwrap_or_else(|| serde_json::Value::from("json")),
+        ),
+        Some(openai::ResponseFormat::Text) | None => None,
+    };
+    #[allow(deprecated)]
+    let options = Options {
+        temperature: value.temperature,
+        top_p: value.top_p,
+        num_predict: value.max_completion_tokens.or(value.max_tokens),
+        stop: match value.stop {
+            Some(openai::Stop::String(stop)) => Some(vec![stop]),
+            Some(openai::Stop::StringArray(stops)) => Some(stops),
+            None => None,
+        },
+        seed: value.seed,
+        presence_penalty: value.presence_penalty,
+        frequency_penalty: value.frequency_penalty,
+    };
+
+    Ok(ChatRequest {
+        provider: provider.clone(),
+        model: target_model.to_string(),
+        messages,
+        tools: value.tools,
+        format,
+        options: Some(options).filter(|options| *options != Options::default()),
+        stream: value.stream.unwrap_or(false),
+    })
+}
+
+fn created(created_at: &str) -> u32 {
+    DateTime::parse_from_rfc3339(created_at)
+        .ok()
+        .and_then(|created_at| u32::try_from(created_at.timestamp()).ok())
+        .unwrap_or(0)
+}
+
+fn finish_reason(
+    done_reason: Option<&str>,
+    has_tool_calls: bool,
+) -> async_openai::types::FinishReason {
+    use async_openai::types as openai;
+    match done_reason {
+        Some("length") => openai::FinishReason::Length,
+        _ if has_tool_calls => openai::FinishReason::ToolCalls,
+        _ => openai::FinishReason::Stop,
+    }
+}
+
+fn usage(value: &ChatResponse) -> Option<async_openai::types::CompletionUsage> {
+    if value.prompt_eval_count.is_none() && value.eval_count.is_none() {
+        return None;
+    }
+    let prompt_tokens = value.prompt_eval_count.unwrap_or(0);
+    let completion_tokens = value.eval_count.unwrap_or(0);
+    Some(async_openai::types::CompletionUsage {
+        prompt_tokens,
+        completion_tokens,
+        total_tokens: prompt_tokens + completion_tokens,
+        prompt_tokens_details: None,
+        completion_tokens_details: None,
+    })
+}
+
+fn tool_call_id() -> String {
+    format!("call_{}", Uuid::new_v4().simple())
+}
+
+fn chat_completion_response(
+    value: ChatResponse,
+) -> Result<CreateChatCompletionResponse, MapperError> {
+    use async_openai::types as openai;
+
+    let usage = usage(&value);
+    let message = value
+        .message
+        .unwrap_or_else(|| text_message(Role::Assistant, String::new()));
+    let tool_calls = message
+        .tool_calls
+        .unwrap_or_default()
+        .into_iter()
+        .map(|tool_call| {
+            Ok(openai::ChatCompletionMessageToolCall {
+                id: tool_call_id(),
+                r#type: openai::ChatCompletionToolType::Function,
+                function: openai::FunctionCall {
+                    name: tool_call.function.name,
+                    arguments: serde_json::to_string(
+                        &tool_call.function.arguments,
+                    )?,
+                },
+            })
+        })
+        .collect::<Result<Vec<_>, MapperError>>()?;
+    let finish_reason =
+        finish_reason(value.done_reason.as_deref(), !tool_calls.is_empty());
+
+    #[allow(deprecated)]
+    let message = openai::ChatCompletionResponseMessage {
+        content: Some(message.content),
+        refusal: None,
+        tool_calls: if tool_calls.is_empty() {
+            None
+        } else {
+            Some(tool_calls)
+        },
+        role: openai::Role::Assistant,
+        function_call: None,
+        audio: None,
+    };
+
+    Ok(CreateChatCompletionResponse {
+        id: String::from(Uuid::new_v4()),
+        choices: vec![openai::ChatChoice {
+            index: 0,
+            message,
+            finish_reason: Some(finish_reason),
+            logprobs: None,
+        }],
+        created: created(&value.created_at),
+        model: value.model,
+        object: OPENAI_CHAT_COMPLETION_OBJECT.to_string(),
+        usage,
+        service_tier: None,
+        system_fingerprint: None,
+    })
+}
+
+fn chat_completion_chunk(
+    state: &mut OllamaStreamState,
+    value: ChatResponse,
+) -> Result<Vec<CreateChatCompletionStreamResponse>, MapperError> {
+    use async_openai::types as openai;
+    const CHAT_COMPLETION_CHUNK_OBJECT: &str = "chat.completion.chunk";
+
+    let id = state
+        .id
+        .get_or_insert_with(|| String::from(Uuid::new_v4()))
+        .clone();
+    let usage = usage(&value);
+    let (content, tool_calls) = match value.message {
+        Some(message) => (
+            Some(message.content).filter(|content| !content.is_empty()),
+            message.tool_calls.unwrap_or_default(),
+        ),
+        None => (None, Vec::new()),
+    };
+    let mut tool_call_chunks = Vec::with_capacity(tool_calls.len());
+    for tool_call in tool_calls {
+        tool_call_chunks.push(openai::ChatCompletionMessageToolCallChunk {
+            index: state.tool_calls,
+            id: Some(tool_call_id()),
+            r#type: Some(openai::ChatCompletionToolType::Function),
+            function: Some(openai::FunctionCallStream {
+                name: Some(tool_call.function.name),
+                arguments: Some(serde_json::to_string(
+                    &tool_call.function.arguments,
+                )?),
+            }),
+        });
+        state.tool_calls += 1;
+    }
+    let finish_reason = value.done.then(|| {
+        finish_reason(value.done_reason.as_deref(), state.tool_calls > 0)
+    });
+    let role = if state.role_sent {
+        None
+    } else {
+        state.role_sent = true;
+        Some(openai::Role::Assistant)
+    };
+
+    #[allow(deprecated)]
+    let choice = openai::ChatChoiceStream {
+        index: 0,
+        delta: openai::ChatCompletionStreamResponseDelta {
+            role,
+            content,
+            tool_calls: if tool_call_chunks.is_empty() {
+                None
+            } else {
+                Some(tool_call_chunks)
+            },
+            refusal: None,
+            function_call: None,
+        },
+        finish_reason,
+        logprobs: None,
+    };
+
+    Ok(vec![CreateChatCompletionStreamResponse {
+        id,
+        choices: vec![choice],
+        created: created(&value.created_at),
+        model: value.model,
+        object: CHAT_COMPLETION_CHUNK_OBJECT.to_string(),
+        system_fingerprint: None,
+        service_tier: None,
+        // Ollama only counts tokens in the last line
+        usage: if value.done { usage } else { None },
+    }])
+}
@@ -1,9 +1,12 @@
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr};
 
 use http::{StatusCode, response::Parts};
 
 use super::{TryConvertStreamData, model::ModelMapper};
 use crate::{
+    endpoints::openai::embeddings::{
+        CreateEmbeddingRequest, CreateEmbeddingResponse,
+    },
     error::mapper::MapperError,
     middleware::mapper::{TryConvert, TryConvertError},
     types::{model_id::ModelId, provider::InferenceProvider},
@@ -30,7 +33,6 @@ impl
 {
     type Error = MapperError;
 
-    #[allow(clippy::too_many_lines)]
     fn try_convert(
         &self,
         value: anthropic_ai_sdk::types::message::CreateMessageParams,
@@ -38,236 +40,298 @@ impl
         async_openai::types::CreateChatCompletionRequest,
         Self::Error,
     > {
-        use anthropic_ai_sdk::types::message as anthropic;
-        use async_openai::types as openai;
         let source_model = ModelId::from_str(&value.model)?;
         let target_model = self
             .model_mapper
             .map_model(&source_model, &InferenceProvider::OpenAI)?;
-
         tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
-        let reasoning_effort = if let Some(thinking) = value.thinking {
-            match thinking.type_ {
-                anthropic::ThinkingType::Enabled => {
-                    #[allow(clippy::cast_precision_loss)]
-                    let reasoning_budget = thinking.budget_tokens as f64
-                        / f64::from(value.max_tokens);
-                    match reasoning_budget {
-                        reasoning_budget if reasoning_budget < 0.33 => {
-                            Some(openai::ReasoningEffort::Low)
-                        }
-                        reasoning_budget if reasoning_budget < 0.66 => {
-                            Some(openai::ReasoningEffort::Medium)
-                        }
-                        reasoning_budget if reasoning_budget <= 1.0 => {
-                            Some(openai::ReasoningEffort::High)
-                        }
-                        _ => Some(openai::ReasoningEffort::Medium),
+
+        chat_request_from_messages(value, &target_model)
+    }
+}
+
+/// Maps an Anthropic Messages request to an OpenAI chat completions request
+/// for `target_model`. Shared by every converter whose provider speaks the
+/// OpenAI format.
+#[allow(clippy::too_many_lines)]
+pub(super) fn chat_request_from_messages(
+    value: anthropic_ai_sdk::types::message::CreateMessageParams,
+    target_model: &ModelId,
+) -> Result<async_openai::types::CreateChatCompletionRequest, MapperError> {
+    use anthropic_ai_sdk::types::message as anthropic;
+    use async_openai::types as openai;
+    let reasoning_effort = if let Some(thinking) = value.thinking {
+        match thinking.type_ {
+            anthropic::ThinkingType::Enabled => {
+                #[allow(clippy::cast_precision_loss)]
+                let reasoning_budget =
+                    thinking.budget_tokens as f64 / f64::from(value.max_tokens);
+                match reasoning_budget {
+                    reasoning_budget if reasoning_budget < 0.33 => {
+                        Some(openai::ReasoningEffort::Low)
+                    }
+                    reasoning_budget if reasoning_budget < 0.66 => {
+                        Some(openai::ReasoningEffort::Medium)
                     }
+                    reasoning_budget if reasoning_budget <= 1.0 => {
+                        Some(openai::ReasoningEffort::High)
+                    }
+                    _ => Some(openai::ReasoningEffort::Medium),
                 }
             }
+        }
+    } else {
+        None
+    };
+
+    let max_completion_tokens = Some(value.max_tokens);
+    let stop = value.stop_sequences.map(openai::Stop::StringArray);
+    let stream = value.stream;
+    let stream_options = if stream.is_some_and(|s| s) {
+        Some(openai::ChatCompletionStreamOptions {
+            include_usage: true,
+        })
+    } else {
+        None
+    };
+    let temperature = value.temperature;
+    let top_p = value.top_p;
+    let tool_choice = match value.tool_choice {
+        Some(tool_choice) => match tool_choice {
+            anthropic::ToolChoice::Auto => {
+                Some(openai::ChatCompletionToolChoiceOption::Auto)
+            }
+            anthropic::ToolChoice::None => {
+                Some(openai::ChatCompletionToolChoiceOption::None)
+            }
+            anthropic::ToolChoice::Any => {
+                Some(openai::ChatCompletionToolChoiceOption::Required)
+            }
+            anthropic::ToolChoice::Tool { name } => {
+                let named_tool_choice = openai::ChatCompletionNamedToolChoice {
+                    r#type: openai::ChatCompletionToolType::Function,
+                    function: openai::FunctionName { name: name.clone() },
+                };
+                Some(openai::ChatCompletionToolChoiceOption::Named(
+                    named_tool_choice,
+                ))
+            }
+        },
+        None => None,
+    };
+    let tools: Option<Vec<openai::ChatCompletionTool>> =
+        if let Some(tools) = value.tools {
+            let mapped_tools: Vec<_> = tools
+                .into_iter()
+                .map(|tool| openai::ChatCompletionTool {
+                    r#type: openai::ChatCompletionToolType::Function,
+                    function: openai::FunctionObject {
+                        name: tool.name,
+                        description: tool.description,
+                        parameters: Some(tool.input_schema),
+                        strict: None,
+                    },
+                })
+                .collect();
+
+            Some(mapped_tools)
         } else {
             None
         };
-
-        let max_completion_tokens = Some(value.max_tokens);
-        let stop = value.stop_sequences.map(openai::Stop::StringArray);
-        let stream = value.stream;
-        let stream_options = if stream.is_some_and(|s| s) {
-            Some(openai::ChatCompletionStreamOptions {
-                include_usage: true,
-            })
-        } else {
-            None
-        };
-        let temperature = value.temperature;
-        let top_p = value.top_p;
-        let tool_choice = match value.tool_choice {
-            Some(tool_choice) => match tool_choice {
-                anthropic::ToolChoice::Auto => {
-                    Some(openai::ChatCompletionToolChoiceOption::Auto)
-                }
-                anthropic::ToolChoice::None => {
-                    Some(openai::ChatCompletionToolChoiceOption::None)
-                }
-                anthropic::ToolChoice::Any => {
-                    Some(openai::ChatCompletionToolChoiceOption::Required)
-                }
-                anthropic::ToolChoice::Tool { name } => {
-                    let named_tool_choice =
-                        openai::ChatCompletionNamedToolChoice {
-                            r#type: openai::ChatCompletionToolType::Function,
-                            function: openai::FunctionName {
-                                name: name.clone(),
-                            },
-                        };
-                    Some(openai::ChatCompletionToolChoiceOption::Named(
-                        named_tool_choice,
-                    ))
-                }
+    let mut metadata = value.metadata;
+    let user = metadata
+        .as_mut()
+        .and_then(|metadata| metadata.fields.remove("user_id"));
+    let metadata = match metadata {
+        Some(metadata) => Some(
+            serde_json::to_value(metadata)
+                .map_err(|_| MapperError::InvalidRequest)?,
+        ),
+        None => None,
+    };
+
+    let mut messages: Vec<openai::ChatCompletionRequestMessage> =
+        Vec::with_capacity(value.messages.len());
+    if let Some(system_prompt) = value.system {
+        // system rather than developer messages, since not every OpenAI
+        // compatible provider accepts the latter
+        messages.push(openai::ChatCompletionRequestMessage::System(
+            openai::ChatCompletionRequestSystemMessage {
+                content:
+                    openai::ChatCompletionRequestSystemMessageContent::Text(
+                        system_prompt,
+                    ),
+                name: None,
             },
-            None => None,
-        };
-        let tools: Option<Vec<openai::ChatCompletionTool>> =
-            if let Some(tools) = value.tools {
-                let mapped_tools: Vec<_> = tools
-                    .into_iter()
-                    .map(|tool| openai::ChatCompletionTool {
-                        r#type: openai::ChatCompletionToolType::Function,
-                        function: openai::FunctionObject {
-                            name: tool.name,
-                            description: tool.description,
-                            parameters: Some(tool.input_schema),
-                            strict: None,
-                        },
-                    })
-                    .collect();
-
-                Some(mapped_tools)
-            } else {
-                None
-            };
-        let mut metadata = value.metadata;
-        let user = metadata
-            .as_mut()
-            .and_then(|metadata| metadata.fields.remove("user_id"));
-        let metadata = match metadata {
-            Some(metadata) => Some(
-                serde_json::to_value(metadata)
-                    .map_err(|_| MapperError::InvalidRequest)?,
-            ),
-            None => None,
+        ));
+    }
+    for message in value.messages {
+        let blocks = match message.content {
+            anthropic::MessageContent::Text { content } => {
+                vec![anthropic::ContentBlock::Text { text: content }]
+            }
+            anthropic::MessageContent::Blocks { content } => content,
         };
-
-        let mut messages: Vec<openai::ChatCompletionRequestMessage> =
-            Vec::with_capacity(value.messages.len());
-        if let Some(system_prompt) = value.system {
-            messages.push(openai::ChatCompletionRequestMessage::Developer(openai::ChatCompletionRequestDeveloperMessage {
-                content: openai::ChatCompletionRequestDeveloperMessageContent::Text(system_prompt),
-                name: None,
-            }));
-        }
-        for message in value.messages {
-            let mapped_message: openai::ChatCompletionRequestMessage =
-                match message.role {
-                    anthropic::Role::Assistant => {
-                        let mapped_content: openai::ChatCompletionRequestAssistantMessageContent = match message.content {
-                        anthropic::MessageContent::Text { content } => {
-                            openai::ChatCompletionRequestAssistantMessageContent::Text(content)
+        match message.role {
+            anthropic::Role::Assistant => {
+                let mut content_parts = Vec::new();
+                let mut tool_calls = Vec::new();
+                for block in blocks {
+                    match block {
+                        anthropic::ContentBlock::Text { text, .. } => {
+                            content_parts.push(openai::ChatCompletionRequestAssistantMessageContentPart::Text(
+                                openai::ChatCompletionRequestMessageContentPartText { text },
+                            ));
                         }
-                        anthropic::MessageContent::Blocks { content } => {
-                            let blocks = content.into_iter().filter_map(|block| {
-                                match block {
-                                    anthropic::ContentBlock::Text { text, .. } => {
-                                        Some(openai::ChatCompletionRequestAssistantMessageContentPart::Text(openai::ChatCompletionRequestMessageContentPartText {
-                                            text
-                                        }))
+                        anthropic::ContentBlock::ToolUse {
+                            id,
+                            name,
+                            input,
+                        } => {
+                            tool_calls.push(
+                                openai::ChatCompletionMessageToolCall {
+                                    id,
+                                    r#type:
+                                        openai::ChatCompletionToolType::Function,
+                                    function: openai::FunctionCall {
+                                        name,
+                                        arguments: serde_json::to_string(
+                                            &input,
+                                        )?,
                                     },
-                                    anthropic::ContentBlock::Image { .. } |
-                                    anthropic::ContentBlock::ToolUse { .. } |
-                                    anthropic::ContentBlock::ToolResult { .. } |
-                                    anthropic::ContentBlock::Thinking { .. } |
-                                    anthropic::ContentBlock::RedactedThinking { .. } => {
-                                        None
-                                    }
-                                }
-                            }).collect();
-                            openai::ChatCompletionRequestAssistantMessageContent::Array(blocks)
+                                },
+                            );
                         }
-                    };
-                        #[allow(deprecated)]
-                        openai::ChatCompletionRequestMessage::Assistant(
-                            openai::ChatCompletionRequestAssistantMessage {
-                                content: Some(mapped_content),
-                                tool_calls: None,
-                                refusal: None,
-                                name: None,
-                                audio: None,
-                                function_call: None,
-                            },
-                        )
+                        anthropic::ContentBlock::Image { .. }
+                        | anthropic::ContentBlock::ToolResult { .. }
+                        | anthropic::ContentBlock::Thinking { .. }
+                        | anthropic::ContentBlock::RedactedThinking {
+                            ..
+                        } => {}
                     }
-                    anthropic::Role::User => {
-                        let content: openai::ChatCompletionRequestUserMessageContent  = match message.content {
-                        anthropic::MessageContent::Text { content } => {
-                            openai::ChatCompletionRequestUserMessageContent::Text(content)
+                }
+                let content = if content_parts.is_empty() {
+                    None
+                } else {
+                    Some(openai::ChatCompletionRequestAssistantMessageContent::Array(
+                        content_parts,
+                    ))
+                };
+                let tool_calls = if tool_calls.is_empty() {
+                    None
+                } else {
+                    Some(tool_calls)
+                };
+                #[allow(deprecated)]
+                messages.push(openai::ChatCompletionRequestMessage::Assistant(
+                    openai::ChatCompletionRequestAssistantMessage {
+                        content,
+                        tool_calls,
+                        refusal: None,
+                        name: None,
+                        audio: None,
+                        function_call: None,
+                    },
+                ));
+            }
+            anthropic::Role::User => {
+                let mut content_parts = Vec::new();
+                for block in blocks {
+                    match block {
+                        anthropic::ContentBlock::Text { text, .. } => {
+                            content_parts.push(openai::ChatCompletionRequestUserMessageContentPart::Text(
+                                openai::ChatCompletionRequestMessageContentPartText { text },
+                            ));
                         }
-                        anthropic::MessageContent::Blocks { content } => {
-                            let blocks = content.into_iter().filter_map(|block| {
-                                match block {
-                                    anthropic::ContentBlock::Text { text, .. } => {
-                                        Some(openai::ChatCompletionRequestUserMessageContentPart::Text(openai::ChatCompletionRequestMessageContentPartText {
-                                            text,
-                                        }))
-                                    },
-                                    anthropic::ContentBlock::Image { source } => {
-                                        let image_url = openai::ImageUrl {
-                                            url: source.data,
-                                            detail: None,
-                                        };
-                                        Some(openai::ChatCompletionRequestUserMessageContentPart::ImageUrl(openai::ChatCompletionRequestMessageContentPartImage {
-                                            image_url,
-                                        }))
+                        anthropic::ContentBlock::Image { source } => {
+                            let url = if source.type_ == "base64" {
+                                format!(
+                                    "data:{};base64,{}",
+                                    source.media_type, source.data
+                                )
+                            } else {
+                                source.data
+                            };
+                            content_parts.push(openai::ChatCompletionRequestUserMessageContentPart::ImageUrl(
+                                openai::ChatCompletionRequestMessageContentPartImage {
+                                    image_url: openai::ImageUrl { url, detail: None },
+                                },
+                            ));
+                        }
+                        // OpenAI expects tool results as their own messages
+                        // directly after the assistant message that called
+                        // the tools, so they go before the rest of the
+                        // user message
+                        anthropic::ContentBlock::ToolResult {
+                            tool_use_id,
+                            content,
+                        } => {
+                            messages.push(
+                                openai::ChatCompletionRequestMessage::Tool(
+                                    openai::ChatCompletionRequestToolMessage {
+                                        content: openai::ChatCompletionRequestToolMessageContent::Text(content),
+                                        tool_call_id: tool_use_id,
                                     },
-                                    anthropic::ContentBlock::ToolUse { .. } |
-                                    anthropic::ContentBlock::ToolResult { .. } |
-                                    anthropic::ContentBlock::Thinking { .. } |
-                                    anthropic::ContentBlock::RedactedThinking { .. } => {
-                                        None
-                                    }
-                                }
-                            }).collect();
-                            openai::ChatCompletionRequestUserMessageContent::Array(blocks)
+                                ),
+                            );
                         }
-                    };
-                        openai::ChatCompletionRequestMessage::User(
-                            openai::ChatCompletionRequestUserMessage {
-                                content,
-                                name: None,
-                            },
-                        )
+                        anthropic::ContentBlock::ToolUse { .. }
+                        | anthropic::ContentBlock::Thinking { .. }
+                        | anthropic::ContentBlock::RedactedThinking {
+                            ..
+                        } => {}
                     }
-                };
-            messages.push(mapped_message);
+                }
+                if !content_parts.is_empty() {
+                    messages.push(openai::ChatCompletionRequestMessage::User(
+                        openai::ChatCompletionRequestUserMessage {
+                            content: openai::ChatCompletionRequestUserMessageContent::Array(
+                                content_parts,
+                            ),
+                            name: None,
+                        },
+                    ));
+                }
+            }
         }
-
-        #[allow(deprecated)]
-        let request = async_openai::types::CreateChatCompletionRequest {
-            messages,
-            model: target_model.to_string(),
-            store: None,
-            reasoning_effort,
-            metadata,
-            parallel_tool_calls: None,
-            stop,
-            stream,
-            stream_options,
-            temperature,
-            top_p,
-            tools,
-            tool_choice,
-            user,
-            max_completion_tokens,
-            max_tokens: None,
-            frequency_penalty: None,
-            logit_bias: None,
-            logprobs: None,
-            n: None,
-            modalities: None,
-            presence_penalty: None,
-            prediction: None,
-            response_format: None,
-            seed: None,
-            service_tier: None,
-            top_logprobs: None,
-            audio: None,
-            function_call: None,
-            functions: None,
-            web_search_options: None,
-        };
-
-        Ok(request)
     }
+
+    #[allow(deprecated)]
+    let request = async_openai::types::CreateChatCompletionRequest {
+        messages,
+        model: target_model.to_string(),
+        store: None,
+        reasoning_effort,
+        metadata,
+        parallel_tool_calls: None,
+        stop,
+        stream,
+        stream_options,
+        temperature,
+        top_p,
+        tools,
+        tool_choice,
+        user,
+        max_completion_tokens,
+        max_tokens: None,
+        frequency_penalty: None,
+        logit_bias: None,
+        logprobs: None,
+        n: None,
+        modalities: None,
+        presence_penalty: None,
+        prediction: None,
+        response_format: None,
+        seed: None,
+        service_tier: None,
+        top_logprobs: None,
+        audio: None,
+        function_call: None,
+        functions: None,
+        web_search_options: None,
+    };
+
+    Ok(request)
 }
 
 impl
@@ -278,70 +342,179 @@ impl
 {
     type Error = MapperError;
 
-    #[allow(clippy::too_many_lines)]
     fn try_convert(
         &self,
-        mut value: async_openai::types::CreateChatCompletionResponse,
+        value: async_openai::types::CreateChatCompletionResponse,
     ) -> std::result::Result<
         anthropic_ai_sdk::types::message::CreateMessageResponse,
         Self::Error,
     > {
-        use anthropic_ai_sdk::types::message as anthropic;
-        let id = value.id;
-        let model = value.model;
-        let role = anthropic::Role::Assistant;
-        // not exposed by OpenAI
-        let stop_sequence: Option<String> = None;
-        // For Messages, this is always "message"
-        let type_ = ANTHROPIC_MESSAGE_TYPE.to_string();
-        let usage = value.usage.map_or(
-            anthropic::Usage {
-                input_tokens: 0,
-                output_tokens: 0,
-            },
-            |usage| anthropic::Usage {
-                input_tokens: usage.prompt_tokens,
-                output_tokens: usage.completion_tokens,
-            },
-        );
+        messages_response_from_chat(value)
+    }
+}
 
-        let openai_message = value.choices.remove(0);
-        let stop_reason = if openai_message.message.refusal.is_some() {
-            Some(anthropic::StopReason::Refusal)
-        } else {
-            None
-        };
-        let mut content: Vec<anthropic::ContentBlock> = Vec::new();
-
-        if let Some(tool_uses) = openai_message.message.tool_calls {
-            for tool_use in tool_uses {
-                if let Ok(input) =
-                    serde_json::from_str(&tool_use.function.arguments)
-                {
-                    let tool_use = anthropic::ContentBlock::ToolUse {
-                        id: tool_use.id,
-                        name: tool_use.function.name,
-                        input,
-                    };
-                    content.push(tool_use);
-                }
-            }
+/// Maps an OpenAI chat completions response to an Anthropic Messages
+/// response.
+pub(super) fn messages_response_from_chat(
+    value: async_openai::types::CreateChatCompletionResponse,
+) -> Result<anthropic_ai_sdk::types::message::CreateMessageResponse, MapperError>
+{
+    use anthropic_ai_sdk::types::message as anthropic;
+    let id = value.id;
+    let model = value.model;
+    let role = anthropic::Role::Assistant;
+    // not exposed by OpenAI
+    let stop_sequence: Option<String> = None;
+    // For Messages, this is always "message"
+    let type_ = ANTHROPIC_MESSAGE_TYPE.to_string();
+    let usage = value.usage.map_or(
+        anthropic::Usage {
+            input_tokens: 0,
+            output_tokens: 0,
+        },
+        |usage| anthropic::Usage {
+            input_tokens: usage.prompt_tokens,
+            output_tokens: usage.completion_tokens,
+        },
+    );
+
+    let openai_message = value
+        .choices
+        .into_iter()
+        .next()
+        .ok_or(MapperError::EmptyResponseBody)?;
+    let stop_reason = if openai_message.message.refusal.is_some() {
+        Some(anthropic::StopReason::Refusal)
+    } else {
+        openai_message
+            .finish_reason
+            .map(stop_reason_from_finish_reason)
+    };
+    let mut content: Vec<anthropic::ContentBlock> = Vec::new();
+
+    if let Some(text) = openai_message
+        .message
+        .content
+        .or(openai_message.message.refusal)
+    {
+        let text = anthropic::ContentBlock::Text { text };
+        content.push(text);
+    }
+    if let Some(tool_uses) = openai_message.message.tool_calls {
+        for tool_use in tool_uses {
+            let input = serde_json::from_str(&tool_use.function.arguments)
+                .unwrap_or_else(|_| serde_json::json!({}));
+            let tool_use = anthropic::ContentBlock::ToolUse {
+                id: tool_use.id,
+                name: tool_use.function.name,
+                input,
+            };
+            content.push(tool_use);
         }
-        if let Some(text) = openai_message.message.content {
-            let text = anthropic::ContentBlock::Text { text };
-            content.push(text);
+    }
+
+    Ok(anthropic::CreateMessageResponse {
+        content,
+        id,
+        model,
+        role,
+        stop_reason,
+        stop_sequence,
+        type_,
+        usage,
+    })
+}
+
+fn stop_reason_from_finish_reason(
+    finish_reason: async_openai::types::FinishReason,
+) -> anthropic_ai_sdk::types::message::StopReason {
+    use anthropic_ai_sdk::types::message as anthropic;
+    use async_openai::types as openai;
+    match finish_reason {
+        openai::FinishReason::Stop => anthropic::StopReason::EndTurn,
+        openai::FinishReason::Length => anthropic::StopReason::MaxTokens,
+        openai::FinishReason::ToolCalls
+        | openai::FinishReason::FunctionCall => anthropic::StopReason::ToolUse,
+        openai::FinishReason::ContentFilter => anthropic::StopReason::Refusal,
+    }
+}
+
+/// The kind of Anthropic content block a [`MessagesStreamState`] has open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OpenBlock {
+    Text,
+    /// A tool use block, by the OpenAI index of its tool call.
+    ToolUse(u32),
+}
+
+/// State carried across the chunks of a single OpenAI stream while
+/// translating it to Anthropic events.
+///
+/// Anthropic clients expect every content block to be explicitly started
+/// and stopped, and the message to be wrapped in `message_start` and
+/// `message_stop` events, none of which OpenAI sends.
+#[derive(Debug, Default)]
+pub struct MessagesStreamState {
+    started: bool,
+    /// The Anthropic index and kind of the content block currently open.
+    open_block: Option<(usize, OpenBlock)>,
+    block_count: usize,
+    /// Anthropic content block indices by OpenAI tool call index.
+    tool_blocks: HashMap<u32, usize>,
+    /// The stop reason of a finished stream whose usage, and therefore
+    /// `message_delta` event, arrives in a later chunk.
+    pending_stop_reason: Option<anthropic_ai_sdk::types::message::StopReason>,
+}
+
+impl MessagesStreamState {
+    fn close_block(
+        &mut self,
+        events: &mut Vec<anthropic_ai_sdk::types::message::StreamEvent>,
+    ) {
+        if let Some((index, _)) = self.open_block.take() {
+            events.push(
+                anthropic_ai_sdk::types::message::StreamEvent::ContentBlockStop {
+                    index,
+                },
+            );
         }
+    }
 
-        Ok(anthropic::CreateMessageResponse {
-            content,
-            id,
-            model,
-            role,
-            stop_reason,
-            stop_sequence,
-            type_,
-            usage,
-        })
+    fn start_block(
+        &mut self,
+        kind: OpenBlock,
+        content_block: anthropic_ai_sdk::types::message::ContentBlock,
+        events: &mut Vec<anthropic_ai_sdk::types::message::StreamEvent>,
+    ) -> usize {
+        self.close_block(events);
+        let index = self.block_count;
+        self.block_count += 1;
+        self.open_block = Some((index, kind));
+        events.push(
+            anthropic_ai_sdk::types::message::StreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            },
+        );
+        index
+    }
+
+    fn finish(
+        &mut self,
+        stop_reason: anthropic_ai_sdk::types::message::StopReason,
+        usage: anthropic_ai_sdk::types::message::StreamUsage,
+        events: &mut Vec<anthropic_ai_sdk::types::message::StreamEvent>,
+    ) {
+        use anthropic_ai_sdk::types::message as anthropic;
+        events.push(anthropic::StreamEvent::MessageDelta {
+            delta: anthropic::MessageDeltaContent {
+                stop_reason: Some(stop_reason),
+                // OpenAI doesn't provide the matched stop sequence
+                stop_sequence: None,
+            },
+            usage: Some(usage),
+        });
+        events.push(anthropic::StreamEvent::MessageStop);
     }
 }
 
@@ -352,227 +525,127 @@ impl
     > for OpenAIConverter
 {
     type Error = MapperError;
+    type State = MessagesStreamState;
 
-    #[allow(clippy::too_many_lines)]
     fn try_convert_chunk(
         &self,
+        state: &mut Self::State,
         value: async_openai::types::CreateChatCompletionStreamResponse,
     ) -> std::result::Result<
-        Option<anthropic_ai_sdk::types::message::StreamEvent>,
+        Vec<anthropic_ai_sdk::types::message::StreamEvent>,
         Self::Error,
     > {
-        use anthropic_ai_sdk::types::message as anthropic;
-        use async_openai::types as openai;
-
-        let choices = &value.choices;
-        if choices.is_empty() {
-            return Ok(None);
-        }
-        let first_choice = &choices[0];
-        let delta = &first_choice.delta;
-
-        // Priority 1: MessageStart Event (indicated by `role` field)
-        if let Some(openai_role) = delta.role {
-            let anthropic_role = match openai_role {
-                openai::Role::Assistant => anthropic::Role::Assistant,
-                openai::Role::User => anthropic::Role::User, /* Should not happen in assistant stream */
-                _ => return Err(MapperError::InvalidRequest), /* Or a more specific error */
-            };
-
-            let mut content_blocks: Vec<anthropic::ContentBlock> = Vec::new();
-
-            // Add text content if present in the MessageStart delta
-            if let Some(text) = &delta.content {
-                content_blocks
-                    .push(anthropic::ContentBlock::Text { text: text.clone() });
-            }
-
-            // Add tool_calls if present in the MessageStart delta
-            if let Some(tool_call_chunks) = &delta.tool_calls {
-                for tc_chunk in tool_call_chunks {
-                    if let (Some(id), Some(func), Some(name)) = (
-                        tc_chunk.id.as_ref(),
-                        tc_chunk.function.as_ref(),
-                        tc_chunk
-                            .function
-                            .as_ref()
-                            .and_then(|f| f.name.as_ref()),
-                    ) {
-                        let input_str =
-                            func.arguments.as_deref().unwrap_or("{}");
-                        if let Ok(input_json) = serde_json::from_str(input_str)
-                        {
-                            let tool_use_block =
-                                anthropic::ContentBlock::ToolUse {
-                                    id: id.clone(),
-                                    name: name.clone(),
-                                    input: input_json,
-                                };
-                            content_blocks.push(tool_use_block);
-                        }
-                        // Handle error or default for bad JSON
-                        let tool_use_block = anthropic::ContentBlock::ToolUse {
-                            id: id.clone(),
-                            name: name.clone(),
-                            input: serde_json::json!({}),
-                        };
-                        content_blocks.push(tool_use_block);
-                    }
-                }
-            }
-
-            // Note: Anthropic's MessageStartContent.usage is full usage,
-            // OpenAI stream start doesn't have it. It's sent with MessageDelta
-            // typically. So, we initialize usage with default/zero
-            // values.
-            let initial_usage = anthropic::Usage {
-                input_tokens: 0,
-                output_tokens: 0,
-            };
+        messages_events_from_chat_chunk(state, value)
+    }
+}
 
-            let message_start_content = anthropic::MessageStartContent {
+/// Maps a chunk of an OpenAI chat completions stream to the Anthropic
+/// Messages stream events it corresponds to.
+pub(super) fn messages_events_from_chat_chunk(
+    state: &mut MessagesStreamState,
+    value: async_openai::types::CreateChatCompletionStreamResponse,
+) -> Result<Vec<anthropic_ai_sdk::types::message::StreamEvent>, MapperError> {
+    use anthropic_ai_sdk::types::message as anthropic;
+
+    let mut events = Vec::new();
+    if !state.started {
+        state.started = true;
+        events.push(anthropic::StreamEvent::MessageStart {
+            message: anthropic::MessageStartContent {
                 id: value.id.clone(),
                 type_: ANTHROPIC_MESSAGE_TYPE.to_string(),
-                role: anthropic_role,
-                content: content_blocks,
+                role: anthropic::Role::Assistant,
+                content: Vec::new(),
                 model: value.model.clone(),
-                stop_reason: None, // Stop reason comes later
+                stop_reason: None,
                 stop_sequence: None,
-                usage: initial_usage,
-            };
-            return Ok(Some(anthropic::StreamEvent::MessageStart {
-                message: message_start_content,
-            }));
-        }
-
-        // Priority 2: MessageDelta Event (for finish_reason)
-        if let Some(finish_reason) = first_choice.finish_reason {
-            let anthropic_stop_reason = match finish_reason {
-                openai::FinishReason::Stop => anthropic::StopReason::EndTurn,
-                openai::FinishReason::Length => {
-                    anthropic::StopReason::MaxTokens
-                }
-                openai::FinishReason::ToolCalls
-                | openai::FinishReason::FunctionCall => {
-                    anthropic::StopReason::ToolUse
-                }
-                openai::FinishReason::ContentFilter => {
-                    anthropic::StopReason::Refusal
-                }
-            };
-
-            let stream_usage = value.usage.map_or_else(
-                || anthropic::StreamUsage {
-                    // Default if OpenAI chunk has no usage
+                // OpenAI only sends usage at the end of the stream
+                usage: anthropic::Usage {
                     input_tokens: 0,
                     output_tokens: 0,
                 },
-                |u| anthropic::StreamUsage {
-                    input_tokens: u.prompt_tokens,
-                    output_tokens: u.completion_tokens,
-                },
-            );
-
-            let message_delta_content = anthropic::MessageDeltaContent {
-                stop_reason: Some(anthropic_stop_reason),
-                stop_sequence: None, /* OpenAI stream doesn't provide the
-                                      * matched stop sequence in delta */
-            };
-            return Ok(Some(anthropic::StreamEvent::MessageDelta {
-                delta: message_delta_content,
-                usage: Some(stream_usage),
-            }));
+            },
+        });
+    }
+    let usage = value.usage.map(|usage| anthropic::StreamUsage {
+        input_tokens: usage.prompt_tokens,
+        output_tokens: usage.completion_tokens,
+    });
+
+    let Some(choice) = value.choices.into_iter().next() else {
+        // the usage chunk requested with `stream_options.include_usage`
+        // comes after the finish reason and has no choices
+        if let (Some(stop_reason), Some(usage)) =
+            (state.pending_stop_reason.take(), usage)
+        {
+            state.finish(stop_reason, usage, &mut events);
         }
+        return Ok(events);
+    };
+    let delta = choice.delta;
+
+    for text in [delta.content, delta.refusal]
+        .into_iter()
+        .flatten()
+        .filter(|text| !text.is_empty())
+    {
+        let index = match state.open_block {
+            Some((index, OpenBlock::Text)) => index,
+            _ => state.start_block(
+                OpenBlock::Text,
+                anthropic::ContentBlock::Text {
+                    text: String::new(),
+                },
+                &mut events,
+            ),
+        };
+        events.push(anthropic::StreamEvent::ContentBlockDelta {
+            index,
+            delta: anthropic::ContentBlockDelta::TextDelta { text },
+        });
+    }
 
-        // Priority 3: Tool Call Events
-        if let Some(tool_call_chunks) = &delta.tool_calls {
-            if let Some(tc_chunk) = tool_call_chunks.first() {
-                // Process only the first tool_call_chunk due to
-                // Option<StreamEvent> return
-                let anthropic_block_idx = tc_chunk.index as usize; // Use OpenAI's tool index as Anthropic's content block index
-
-                // Check if it's a new tool call (ContentBlockStart)
-                if let (Some(id), Some(func), Some(name)) = (
-                    tc_chunk.id.as_ref(),
-                    tc_chunk.function.as_ref(),
-                    tc_chunk.function.as_ref().and_then(|f| f.name.as_ref()),
-                ) {
-                    let input_str = func.arguments.as_deref().unwrap_or("{}");
-                    if let Ok(input_json) = serde_json::from_str(input_str) {
-                        let tool_use_block = anthropic::ContentBlock::ToolUse {
-                            id: id.clone(),
-                            name: name.clone(),
-                            input: input_json,
-                        };
-                        return Ok(Some(
-                            anthropic::StreamEvent::ContentBlockStart {
-                                index: anthropic_block_idx,
-                                content_block: tool_use_block,
-                            },
-                        ));
-                    }
-                    // Handle error or default for bad JSON
-                    let tool_use_block = anthropic::ContentBlock::ToolUse {
-                        id: id.clone(),
-                        name: name.clone(),
-                        input: serde_json::json!({}),
-                    };
-                    return Ok(Some(
-                        anthropic::StreamEvent::ContentBlockStart {
-                            index: anthropic_block_idx,
-                            content_block: tool_use_block,
-                        },
-                    ));
-                }
-                // Check if it's a delta for an existing tool call's arguments
-                // (ContentBlockDelta)
-                else if let Some(func_args) = tc_chunk
-                    .function
-                    .as_ref()
-                    .and_then(|f| f.arguments.as_ref())
-                {
-                    let input_json_delta =
-                        anthropic::ContentBlockDelta::InputJsonDelta {
-                            partial_json: func_args.clone(),
-                        };
-                    return Ok(Some(
-                        anthropic::StreamEvent::ContentBlockDelta {
-                            index: anthropic_block_idx,
-                            delta: input_json_delta,
-                        },
-                    ));
-                }
-            }
+    for tool_call in delta.tool_calls.unwrap_or_default() {
+        let (name, arguments) =
+            tool_call.function.map_or((None, None), |function| {
+                (function.name, function.arguments)
+            });
+        if let (Some(id), Some(name)) = (tool_call.id, name) {
+            let index = state.start_block(
+                OpenBlock::ToolUse(tool_call.index),
+                anthropic::ContentBlock::ToolUse {
+                    id,
+                    name,
+                    input: serde_json::json!({}),
+                },
+                &mut events,
+            );
+            state.tool_blocks.insert(tool_call.index, index);
         }
-
-        // Priority 4: Text Content Delta
-        if let Some(text_content) = &delta.content {
-            let text_delta = anthropic::ContentBlockDelta::TextDelta {
-                text: text_content.clone(),
-            };
-            // Convention: general text deltas map to Anthropic content block
-            // index 0
-            return Ok(Some(anthropic::StreamEvent::ContentBlockDelta {
-                index: 0,
-                delta: text_delta,
-            }));
+        if let Some(partial_json) =
+            arguments.filter(|arguments| !arguments.is_empty())
+            && let Some(index) = state.tool_blocks.get(&tool_call.index)
+        {
+            events.push(anthropic::StreamEvent::ContentBlockDelta {
+                index: *index,
+                delta: anthropic::ContentBlockDelta::InputJsonDelta {
+                    partial_json,
+                },
+            });
         }
+    }
 
-        // Priority 5: Refusal Content Delta (if OpenAI includes it separately)
-        if let Some(refusal_text) = &delta.refusal {
-            let refusal_delta = anthropic::ContentBlockDelta::TextDelta {
-                text: refusal_text.clone(),
-            };
-            // Convention: map refusal text to Anthropic content block index 0
-            // (or a specific index for refusals)
-            return Ok(Some(anthropic::StreamEvent::ContentBlockDelta {
-                index: 0,
-                delta: refusal_delta,
-            }));
+    if let Some(finish_reason) = choice.finish_reason {
+        state.close_block(&mut events);
+        let stop_reason = stop_reason_from_finish_reason(finish_reason);
+        if let Some(usage) = usage {
+            state.finish(stop_reason, usage, &mut events);
+        } else {
+            state.pending_stop_reason = Some(stop_reason);
         }
-
-        Ok(None) // No convertible event found in this chunk
     }
+
+    Ok(events)
 }
 
 impl
@@ -621,15 +694,17 @@ impl
     > for OpenAIConverter
 {
     type Error = MapperError;
+    type State = ();
 
     fn try_convert_chunk(
         &self,
+        _state: &mut Self::State,
         value: async_openai::types::CreateChatCompletionStreamResponse,
     ) -> Result<
-        Option<async_openai::types::CreateChatCompletionStreamResponse>,
+        Vec<async_openai::types::CreateChatCompletionStreamResponse>,
         Self::Error,
     > {
-        Ok(Some(value))
+        Ok(vec![value])
     }
 }
 
@@ -650,6 +725,75 @@ impl
     }
 }
 
+impl
+    TryConvertError<
+        async_openai::error::WrappedError,
+        crate::endpoints::anthropic::messages::AnthropicApiError,
+    > for OpenAIConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_error(
+        &self,
+        resp_parts: &Parts,
+        value: async_openai::error::WrappedError,
+    ) -> Result<
+        crate::endpoints::anthropic::messages::AnthropicApiError,
+        Self::Error,
+    > {
+        Ok(super::anthropic_error_from_status(
+            resp_parts.status,
+            Some(value.error.message),
+        ))
+    }
+}
+
+impl TryConvert<CreateEmbeddingRequest, CreateEmbeddingRequest>
+    for OpenAIConverter
+{
+    type Error = MapperError;
+    fn try_convert(
+        &self,
+        mut value: CreateEmbeddingRequest,
+    ) -> Result<CreateEmbeddingRequest, Self::Error> {
+        let source_model = ModelId::from_str(&value.model)?;
+        let target_model = self
+            .model_mapper
+            .map_model(&source_model, &InferenceProvider::OpenAI)?;
+        tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
+        value.model = target_model.to_string();
+
+        Ok(value)
+    }
+}
+
+impl TryConvert<CreateEmbeddingResponse, CreateEmbeddingResponse>
+    for OpenAIConverter
+{
+    type Error = MapperError;
+    fn try_convert(
+        &self,
+        value: CreateEmbeddingResponse,
+    ) -> Result<CreateEmbeddingResponse, Self::Error> {
+        Ok(value)
+    }
+}
+
+impl TryConvertStreamData<CreateEmbeddingResponse, CreateEmbeddingResponse>
+    for OpenAIConverter
+{
+    type Error = MapperError;
+    type State = ();
+
+    fn try_convert_chunk(
+        &self,
+        _state: &mut Self::State,
+        value: CreateEmbeddingResponse,
+    ) -> Result<Vec<CreateEmbeddingResponse>, Self::Error> {
+        Ok(vec![value])
+    }
+}
+
 pub(super) fn get_error_type(status_code: StatusCode) -> String {
     if status_code == StatusCode::TOO_MANY_REQUESTS {
         "tokens".to_string()
@@ -2,9 +2,20 @@ use std::str::FromStr;
 
 use http::response::Parts;
 
-use super::{TryConvertStreamData, model::ModelMapper};
+use super::{
+    TryConvertStreamData,
+    model::ModelMapper,
+    openai::{
+        MessagesStreamState, chat_request_from_messages,
+        messages_events_from_chat_chunk, messages_response_from_chat,
+    },
+};
 use crate::{
-    endpoints::openai::OpenAICompatibleChatCompletionRequest,
+    endpoints::openai::{
+        OpenAICompatibleChatCompletionRequest,
+        OpenAICompatibleEmbeddingRequest,
+        embeddings::{CreateEmbeddingRequest, CreateEmbeddingResponse},
+    },
     error::mapper::MapperError,
     middleware::mapper::{TryConvert, TryConvertError},
     types::{model_id::ModelId, provider::InferenceProvider},
@@ -72,15 +83,17 @@ impl
     > for OpenAICompatibleConverter
 {
     type Error = MapperError;
+    type State = ();
 
     fn try_convert_chunk(
         &self,
+        _state: &mut Self::State,
         value: async_openai::types::CreateChatCompletionStreamResponse,
     ) -> Result<
-        Option<async_openai::types::CreateChatCompletionStreamResponse>,
+        Vec<async_openai::types::CreateChatCompletionStreamResponse>,
         Self::Error,
     > {
-        Ok(Some(value))
+        Ok(vec![value])
     }
 }
 
@@ -100,3 +113,135 @@ impl
         Ok(value)
     }
 }
+
+impl
+    TryConvert<
+        anthropic_ai_sdk::types::message::CreateMessageParams,
+        OpenAICompatibleChatCompletionRequest,
+    > for OpenAICompatibleConverter
+{
+    type Error = MapperError;
+    fn try_convert(
+        &self,
+        value: anthropic_ai_sdk::types::message::CreateMessageParams,
+    ) -> Result<OpenAICompatibleChatCompletionRequest, Self::Error> {
+        let source_model = ModelId::from_str(&value.model)?;
+        let target_model =
+            self.model_mapper.map_model(&source_model, &self.provider)?;
+        tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
+        let request = chat_request_from_messages(value, &target_model)?;
+
+        Ok(OpenAICompatibleChatCompletionRequest {
+            provider: self.provider.clone(),
+            inner: request,
+        })
+    }
+}
+
+impl
+    TryConvert<
+        async_openai::types::CreateChatCompletionResponse,
+        anthropic_ai_sdk::types::message::CreateMessageResponse,
+    > for OpenAICompatibleConverter
+{
+    type Error = MapperError;
+    fn try_convert(
+        &self,
+        value: async_openai::types::CreateChatCompletionResponse,
+    ) -> Result<
+        anthropic_ai_sdk::types::message::CreateMessageResponse,
+        Self::Error,
+    > {
+        messages_response_from_chat(value)
+    }
+}
+
+impl
+    TryConvertStreamData<
+        async_openai::types::CreateChatCompletionStreamResponse,
+        anthropic_ai_sdk::types::message::StreamEvent,
+    > for OpenAICompatibleConverter
+{
+    type Error = MapperError;
+    type State = MessagesStreamState;
+
+    fn try_convert_chunk(
+        &self,
+        state: &mut Self::State,
+        value: async_openai::types::CreateChatCompletionStreamResponse,
+    ) -> Result<Vec<anthropic_ai_sdk::types::message::StreamEvent>, Self::Error>
+    {
+        messages_events_from_chat_chunk(state, value)
+    }
+}
+
+impl
+    TryConvertError<
+        async_openai::error::WrappedError,
+        crate::endpoints::anthropic::messages::AnthropicApiError,
+    > for OpenAICompatibleConverter
+{
+    type Error = MapperError;
+
+    fn try_convert_error(
+        &self,
+        resp_parts: &Parts,
+        value: async_openai::error::WrappedError,
+    ) -> Result<
+        crate::endpoints::anthropic::messages::AnthropicApiError,
+        Self::Error,
+    > {
+        Ok(super::anthropic_error_from_status(
+            resp_parts.status,
+            Some(value.error.message),
+        ))
+    }
+}
+
+impl TryConvert<CreateEmbeddingRequest, OpenAICompatibleEmbeddingRequest>
+    for OpenAICompatibleConverter
+{
+    type Error = MapperError;
+    fn try_convert(
+        &self,
+        mut value: CreateEmbeddingRequest,
+    ) -> Result<OpenAICompatibleEmbeddingRequest, Self::Error> {
+        let source_model = ModelId::from_str(&value.model)?;
+        let target_model =
+            self.model_mapper.map_model(&source_model, &self.provider)?;
+        tracing::trace!(source_model = ?source_model, target_model = ?target_model, "mapped model");
+        value.model = target_model.to_string();
+
+        Ok(OpenAICompatibleEmbeddingRequest {
+            provider: self.provider.clone(),
+            inner: value,
+        })
+    }
+}
+
+impl TryConvert<CreateEmbeddingResponse, CreateEmbeddingResponse>
+    for OpenAICompatibleConverter
+{
+    type Error = MapperError;
+    fn try_convert(
+        &self,
+        value: CreateEmbeddingResponse,
+    ) -> Result<CreateEmbeddingResponse, Self::Error> {
+        Ok(value)
+    }
+}
+
+impl TryConvertStreamData<CreateEmbeddingResponse, CreateEmbeddingResponse>
+    for OpenAICompatibleConverter
+{
+    type Error = MapperError;
+    type State = ();
+
+    fn try_convert_chunk(
+        &self,
+        _state: &mut Self::State,
+        value: CreateEmbeddingResponse,
+    ) -> Result<Vec<CreateEmbeddingResponse>, Self::Error> {
+        Ok(vec![value])
+    }
+}
@@ -4,15 +4,20 @@ use rustc_hash::FxHashMap as HashMap;
 
 use super::{
     EndpointConverter, TypedEndpointConverter, anthropic::AnthropicConverter,
-    model::ModelMapper, openai::OpenAIConverter,
-    openai_compatible::OpenAICompatibleConverter,
+    cohere::CohereConverter, gemini::GeminiConverter, model::ModelMapper,
+    openai::OpenAIConverter, openai_compatible::OpenAICompatibleConverter,
 };
 use crate::{
+    config::providers::{ProvidersConfig, SelfHostedDialect},
     endpoints::{
         self, ApiEndpoint, anthropic::Anthropic, bedrock::Bedrock,
-        google::Google, ollama::Ollama, openai::OpenAI,
+        cohere::Cohere, google::Google, ollama::Ollama, openai::OpenAI,
+    },
+    error::invalid_req::InvalidRequestError,
+    middleware::mapper::{
+        bedrock::BedrockConverter,
+        ollama::{OllamaChatConverter, OllamaConverter},
     },
-    middleware::mapper::{bedrock::BedrockConverter, ollama::OllamaConverter},
     types::provider::InferenceProvider,
 };
 
@@ -21,11 +26,29 @@ pub struct EndpointConverterRegistry(Arc<EndpointConverterRegistryInner>);
 
 impl EndpointConverterRegistry {
     #[must_use]
-    pub fn new(model_mapper: &ModelMapper) -> Self {
-        let inner = EndpointConverterRegistryInner::new(model_mapper);
+    pub fn new(
+        model_mapper: &ModelMapper,
+        providers: &ProvidersConfig,
+    ) -> Self {
+        let inner =
+            EndpointConverterRegistryInner::new(model_mapper, providers);
         Self(Arc::new(inner))
     }
 
+    /// The endpoint of `target_provider` that requests to `source_endpoint`
+    /// are sent to, which depends on the dialect of self-hosted providers.
+    pub fn target_endpoint(
+        &self,
+        source_endpoint: ApiEndpoint,
+        target_provider: &InferenceProvider,
+    ) -> Result<ApiEndpoint, InvalidRequestError> {
+        ApiEndpoint::mapped_with_dialect(
+            source_endpoint,
+            target_provider,
+            self.0.dialects.get(target_provider).copied(),
+        )
+    }
+
     #[must_use]
     pub fn get_converter(
         &self,
@@ -65,21 +88,25 @@ struct EndpointConverterRegistryInner {
         RegistryKey,
         Box<dyn EndpointConverter + Send + Sync + 'static>,
     >,
+    /// The dialects of the self-hosted providers.
+    dialects: HashMap<InferenceProvider, SelfHostedDialect>,
 }
 
 impl std::fmt::Debug for EndpointConverterRegistryInner {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut debug = f.debug_struct("EndpointConverterRegistryInner");
         debug.field("converters", &self.converters.keys().collect::<Vec<_>>());
+        debug.field("dialects", &self.dialects);
         debug.finish()
     }
 }
 
 impl EndpointConverterRegistryInner {
     #[allow(clippy::too_many_lines)]
-    fn new(model_mapper: &ModelMapper) -> Self {
+    fn new(model_mapper: &ModelMapper, providers: &ProvidersConfig) -> Self {
         let mut registry = Self {
             converters: HashMap::default(),
+            dialects: HashMap::default(),
         };
 
         let key = RegistryKey::new(
@@ -98,14 +125,12 @@ impl EndpointConverterRegistryInner {
             ApiEndpoint::OpenAI(OpenAI::chat_completions()),
             ApiEndpoint::Google(Google::generate_contents()),
         );
-        let converter = TypedEndpointConverter::<
-            endpoints::openai::ChatCompletions,
-            endpoints::google::GenerateContents,
-            OpenAICompatibleConverter,
-        >::new(OpenAICompatibleConverter::new(
-            InferenceProvider::GoogleGemini,
-            model_mapper.clone(),
-        ));
+        let converter =
+            TypedEndpointConverter::<
+                endpoints::google::GeminiChatCompletions,
+                endpoints::google::GenerateContents,
+                GeminiConverter,
+            >::new(GeminiConverter::new(model_mapper.clone()));
         registry.register_converter(key, converter);
 
         let key = RegistryKey::new(
@@ -231,9 +256,332 @@ impl EndpointConverterRegistryInner {
         ));
         registry.register_converter(key, converter);
 
+        registry.register_anthropic_ingress(model_mapper);
+        registry.register_embeddings(model_mapper);
+        registry.register_self_hosted(model_mapper, providers);
+
         registry
     }
 
+    /// Converters for requests that come in through the Anthropic Messages
+    /// API rather than OpenAI chat completions.
+    fn register_anthropic_ingress(&mut self, model_mapper: &ModelMapper) {
+        let key = RegistryKey::new(
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+        );
+        let converter =
+            TypedEndpointConverter::<
+                endpoints::anthropic::Messages,
+                endpoints::anthropic::Messages,
+                AnthropicConverter,
+            >::new(AnthropicConverter::new(model_mapper.clone()));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+            ApiEndpoint::OpenAI(OpenAI::chat_completions()),
+        );
+        let converter =
+            TypedEndpointConverter::<
+                endpoints::anthropic::Messages,
+                endpoints::openai::ChatCompletions,
+                OpenAIConverter,
+            >::new(OpenAIConverter::new(model_mapper.clone()));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+            ApiEndpoint::Google(Google::generate_contents()),
+        );
+        let converter =
+            TypedEndpointConverter::<
+                endpoints::anthropic::Messages,
+                endpoints::google::GenerateContents,
+                GeminiConverter,
+            >::new(GeminiConverter::new(model_mapper.clone()));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+            ApiEndpoint::Ollama(Ollama::chat_completions()),
+        );
+        let converter =
+            TypedEndpointConverter::<
+                endpoints::anthropic::Messages,
+                endpoints::ollama::chat_completions::ChatCompletions,
+                OllamaConverter,
+            >::new(OllamaConverter::new(model_mapper.clone()));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+            ApiEndpoint::OpenAICompatible {
+                provider: InferenceProvider::Named("mistral".into()),
+                openai_endpoint: OpenAI::chat_completions(),
+            },
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::anthropic::Messages,
+            endpoints::openai::OpenAICompatibleChatCompletions,
+            OpenAICompatibleConverter,
+        >::new(OpenAICompatibleConverter::new(
+            InferenceProvider::Named("mistral".into()),
+            model_mapper.clone(),
+        ));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+            ApiEndpoint::OpenAICompatible {
+                provider: InferenceProvider::Named("groq".into()),
+                openai_endpoint: OpenAI::chat_completions(),
+            },
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::anthropic::Messages,
+            endpoints::openai::OpenAICompatibleChatCompletions,
+            OpenAICompatibleConverter,
+        >::new(OpenAICompatibleConverter::new(
+            InferenceProvider::Named("groq".into()),
+            model_mapper.clone(),
+        ));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+            ApiEndpoint::OpenAICompatible {
+                provider: InferenceProvider::Named("deepseek".into()),
+                openai_endpoint: OpenAI::chat_completions(),
+            },
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::anthropic::Messages,
+            endpoints::openai::OpenAICompatibleChatCompletions,
+            OpenAICompatibleConverter,
+        >::new(OpenAICompatibleConverter::new(
+            InferenceProvider::Named("deepseek".into()),
+            model_mapper.clone(),
+        ));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+            ApiEndpoint::OpenAICompatible {
+                provider: InferenceProvider::Named("xai".into()),
+                openai_endpoint: OpenAI::chat_completions(),
+            },
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::anthropic::Messages,
+            endpoints::openai::OpenAICompatibleChatCompletions,
+            OpenAICompatibleConverter,
+        >::new(OpenAICompatibleConverter::new(
+            InferenceProvider::Named("xai".into()),
+            model_mapper.clone(),
+        ));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+            ApiEndpoint::OpenAICompatible {
+                provider: InferenceProvider::Named("hyperbolic".into()),
+                openai_endpoint: OpenAI::chat_completions(),
+            },
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::anthropic::Messages,
+            endpoints::openai::OpenAICompatibleChatCompletions,
+            OpenAICompatibleConverter,
+        >::new(OpenAICompatibleConverter::new(
+            InferenceProvider::Named("hyperbolic".into()),
+            model_mapper.clone(),
+        ));
+        self.register_converter(key, converter);
+    }
+
+    /// Converters for OpenAI embeddings requests, to the providers that
+    /// offer embedding models.
+    fn register_embeddings(&mut self, model_mapper: &ModelMapper) {
+        let key = RegistryKey::new(
+            ApiEndpoint::OpenAI(OpenAI::embeddings()),
+            ApiEndpoint::OpenAI(OpenAI::embeddings()),
+        );
+        let converter =
+            TypedEndpointConverter::<
+                endpoints::openai::Embeddings,
+                endpoints::openai::Embeddings,
+                OpenAIConverter,
+            >::new(OpenAIConverter::new(model_mapper.clone()));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::OpenAI(OpenAI::embeddings()),
+            ApiEndpoint::Google(Google::batch_embed_contents()),
+        );
+        let converter =
+            TypedEndpointConverter::<
+                endpoints::openai::Embeddings,
+                endpoints::google::BatchEmbedContents,
+                GeminiConverter,
+            >::new(GeminiConverter::new(model_mapper.clone()));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::OpenAI(OpenAI::embeddings()),
+            ApiEndpoint::Cohere(Cohere::embed()),
+        );
+        let converter =
+            TypedEndpointConverter::<
+                endpoints::openai::Embeddings,
+                endpoints::cohere::Embed,
+                CohereConverter,
+            >::new(CohereConverter::new(model_mapper.clone()));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::OpenAI(OpenAI::embeddings()),
+            ApiEndpoint::OpenAICompatible {
+                provider: InferenceProvider::Named("mistral".into()),
+                openai_endpoint: OpenAI::embeddings(),
+            },
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::openai::Embeddings,
+            endpoints::openai::OpenAICompatibleEmbeddings,
+            OpenAICompatibleConverter,
+        >::new(OpenAICompatibleConverter::new(
+            InferenceProvider::Named("mistral".into()),
+            model_mapper.clone(),
+        ));
+        self.register_converter(key, converter);
+    }
+
+    /// Converters for self-hosted providers, which are only known from the
+    /// providers config.
+    fn register_self_hosted(
+        &mut self,
+        model_mapper: &ModelMapper,
+        providers: &ProvidersConfig,
+    ) {
+        for (provider, config) in providers.iter() {
+            let Some(dialect) = config.dialect() else {
+                continue;
+            };
+            self.dialects.insert(provider.clone(), dialect);
+            match (provider, dialect) {
+                (InferenceProvider::Named(_), SelfHostedDialect::OpenAI) => {
+                    self.register_self_hosted_openai(model_mapper, provider);
+                }
+                (
+                    InferenceProvider::Ollama | InferenceProvider::Named(_),
+                    SelfHostedDialect::Ollama,
+                ) => {
+                    self.register_self_hosted_ollama(model_mapper, provider);
+                }
+                // the built-in providers only speak their own API
+                _ => {}
+            }
+        }
+    }
+
+    fn register_self_hosted_openai(
+        &mut self,
+        model_mapper: &ModelMapper,
+        provider: &InferenceProvider,
+    ) {
+        let target_endpoint = ApiEndpoint::OpenAICompatible {
+            provider: provider.clone(),
+            openai_endpoint: OpenAI::chat_completions(),
+        };
+        let key = RegistryKey::new(
+            ApiEndpoint::OpenAI(OpenAI::chat_completions()),
+            target_endpoint.clone(),
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::openai::ChatCompletions,
+            endpoints::openai::OpenAICompatibleChatCompletions,
+            OpenAICompatibleConverter,
+        >::new(OpenAICompatibleConverter::new(
+            provider.clone(),
+            model_mapper.clone(),
+        ));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+            target_endpoint,
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::anthropic::Messages,
+            endpoints::openai::OpenAICompatibleChatCompletions,
+            OpenAICompatibleConverter,
+        >::new(OpenAICompatibleConverter::new(
+            provider.clone(),
+            model_mapper.clone(),
+        ));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::OpenAI(OpenAI::embeddings()),
+            ApiEndpoint::OpenAICompatible {
+                provider: provider.clone(),
+                openai_endpoint: OpenAI::embeddings(),
+            },
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::openai::Embeddings,
+            endpoints::openai::OpenAICompatibleEmbeddings,
+            OpenAICompatibleConverter,
+        >::new(OpenAICompatibleConverter::new(
+            provider.clone(),
+            model_mapper.clone(),
+        ));
+        self.register_converter(key, converter);
+    }
+
+    fn register_self_hosted_ollama(
+        &mut self,
+        model_mapper: &ModelMapper,
+        provider: &InferenceProvider,
+    ) {
+        let target_endpoint = if *provider == InferenceProvider::Ollama {
+            ApiEndpoint::Ollama(Ollama::chat())
+        } else {
+            ApiEndpoint::OllamaCompatible {
+                provider: provider.clone(),
+                ollama_endpoint: Ollama::chat(),
+            }
+        };
+        let key = RegistryKey::new(
+            ApiEndpoint::OpenAI(OpenAI::chat_completions()),
+            target_endpoint.clone(),
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::openai::ChatCompletions,
+            endpoints::ollama::chat::Chat,
+            OllamaChatConverter,
+        >::new(OllamaChatConverter::new(
+            provider.clone(),
+            model_mapper.clone(),
+        ));
+        self.register_converter(key, converter);
+
+        let key = RegistryKey::new(
+            ApiEndpoint::Anthropic(Anthropic::messages()),
+            target_endpoint,
+        );
+        let converter = TypedEndpointConverter::<
+            endpoints::anthropic::Messages,
+            endpoints::ollama::chat::Chat,
+            OllamaChatConverter,
+        >::new(OllamaChatConverter::new(
+            provider.clone(),
+            model_mapper.clone(),
+        ));
+        self.register_converter(key, converter);
+    }
+
     fn register_converter<C>(&mut self, key: RegistryKey, converter: C)
     where
         C: EndpointConverter + Send + Sync + 'static,
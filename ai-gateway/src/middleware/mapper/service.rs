@@ -3,8 +3,11 @@ use std::{
     task::{Context, Poll},
 };
 
-use bytes::{BufMut, BytesMut};
-use futures::{TryStreamExt, future::BoxFuture};
+use bytes::Bytes;
+use futures::{
+    TryStreamExt,
+    future::{self, BoxFuture},
+};
 use http::uri::PathAndQuery;
 use tracing::{Instrument, info_span};
 
@@ -14,7 +17,7 @@ use crate::{
         api::ApiError, internal::InternalError, mapper::MapperError,
         stream::StreamError,
     },
-    middleware::mapper::registry::EndpointConverterRegistry,
+    middleware::mapper::{StreamState, registry::EndpointConverterRegistry},
     types::{
         extensions::MapperContext, provider::InferenceProvider,
         request::Request, response::Response,
@@ -90,8 +93,8 @@ where
                 InternalError::ExtensionNotFound("ApiEndpoint"),
             ))?;
             let source_endpoint_cloned = source_endpoint.clone();
-            let target_endpoint =
-                ApiEndpoint::mapped(source_endpoint, &target_provider)?;
+            let target_endpoint = converter_registry
+                .target_endpoint(source_endpoint, &target_provider)?;
             let target_endpoint_cloned = target_endpoint.clone();
             // serialization/deserialization should be done on a dedicated
             // thread
@@ -158,9 +161,11 @@ async fn map_request(
     let base_path = target_endpoint
         .path(mapper_ctx.model.as_ref(), mapper_ctx.is_stream)?;
 
+    // some paths already carry a query, e.g. Gemini's `alt=sse`
     let target_path_and_query =
         if let Some(query_params) = target_path_and_query.query() {
-            format!("{base_path}?{query_params}")
+            let separator = if base_path.contains('?') { '&' } else { '?' };
+            format!("{base_path}{separator}{query_params}")
         } else {
             base_path
         };
@@ -219,38 +224,20 @@ async fn map_response(
             .try_filter_map({
                 let captured_registry = converter_registry.clone();
                 let resp_parts = parts.clone();
-                let target_endpoint_cloned = target_endpoint.clone();
-                let source_endpoint_cloned = source_endpoint.clone();
+                let target_endpoint = target_endpoint.clone();
+                let source_endpoint = source_endpoint.clone();
+                // chunks are converted one after the other, so the state can
+                // be owned by the closure
+                let mut stream_state = StreamState::default();
                 move |bytes| {
-                    let registry_for_future = captured_registry.clone();
-                    let resp_parts = resp_parts.clone();
-                    let target_endpoint = target_endpoint_cloned.clone();
-                    let source_endpoint = source_endpoint_cloned.clone();
-                    async move {
-                        let converter = registry_for_future
-                            .get_converter(&target_endpoint, &source_endpoint)
-                            .ok_or_else(|| {
-                                InternalError::InvalidConverter(
-                                    target_endpoint.clone(),
-                                    source_endpoint.clone(),
-                                )
-                            })?;
-
-                        let converted_data = converter
-                            .convert_resp_body(resp_parts, bytes, is_stream)?;
-
-                        // add the `data: ` prefix expected by the OpenAI SDK
-                        if let Some(converted_data) = converted_data {
-                            let mut new_bytes = BytesMut::new();
-                            new_bytes.put("data: ".as_bytes());
-                            new_bytes.put(converted_data);
-                            new_bytes.put("\n\n".as_bytes());
-                            let data = new_bytes.freeze();
-                            Ok(Some(data))
-                        } else {
-                            Ok(converted_data)
-                        }
-                    }
+                    future::ready(map_stream_chunk(
+                        &captured_registry,
+                        &target_endpoint,
+                        &source_endpoint,
+                        resp_parts.clone(),
+                        bytes,
+                        &mut stream_state,
+                    ))
                 }
             });
         let final_body = axum_core::body::Body::new(
@@ -267,7 +254,12 @@ async fn map_response(
             .to_bytes();
 
         let mapped_body_bytes = converter
-            .convert_resp_body(parts.clone(), body_bytes, is_stream)?
+            .convert_resp_body(
+                parts.clone(),
+                body_bytes,
+                is_stream,
+                &mut StreamState::default(),
+            )?
             .ok_or(MapperError::EmptyResponseBody)
             .map_err(InternalError::MapperError)?;
         let final_body = axum_core::body::Body::from(mapped_body_bytes);
@@ -281,6 +273,26 @@ async fn map_response(
     }
 }
 
+fn map_stream_chunk(
+    converter_registry: &EndpointConverterRegistry,
+    target_endpoint: &ApiEndpoint,
+    source_endpoint: &ApiEndpoint,
+    resp_parts: http::response::Parts,
+    bytes: Bytes,
+    stream_state: &mut StreamState,
+) -> Result<Option<Bytes>, ApiError> {
+    let converter = converter_registry
+        .get_converter(target_endpoint, source_endpoint)
+        .ok_or_else(|| {
+            InternalError::InvalidConverter(
+                target_endpoint.clone(),
+                source_endpoint.clone(),
+            )
+        })?;
+
+    converter.convert_resp_body(resp_parts, bytes, true, stream_state)
+}
+
 #[derive(Debug, Clone)]
 pub struct Layer {
     endpoint_converter_registry: EndpointConverterRegistry,
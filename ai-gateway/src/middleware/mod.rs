@@ -1,7 +1,10 @@
 pub mod add_extension;
 pub mod auth;
 pub mod cache;
+pub mod embeddings;
+pub mod guardrails;
 pub mod mapper;
+pub mod multimodal;
 pub mod prompts;
 pub mod rate_limit;
 pub mod request_context;
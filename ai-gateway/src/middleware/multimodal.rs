@@ -0,0 +1,813 @@
+//! Checks the images and audio of OpenAI chat completion requests against
+//! the provider and model they are sent to.
+//!
+//! Sits in front of the mapper, like [`super::embeddings`]. Requests with
+//! images for a model that is not vision capable, or with audio for a model
+//! or provider that does not take audio, are rejected with
+//! [`InvalidRequestError::UnsupportedContent`], which routers fail over on.
+//!
+//! Every inlined image is checked against the image types the provider
+//! accepts, and images larger than the provider accepts are downscaled and
+//! re-encoded as JPEG.
+//!
+//! If [`MultimodalConfig::fetch_images`] is enabled, images referenced by
+//! http URL are fetched and inlined as base64 data URIs for providers that
+//! can't fetch them themselves. Only public addresses are fetched, redirects
+//! are not followed, and images are rejected as soon as their body passes
+//! [`MultimodalConfig::max_image_bytes`]. Otherwise these requests are
+//! rejected with [`InvalidRequestError::UnsupportedContent`].
+use std::{
+    io::Cursor,
+    net::IpAddr,
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use async_openai::types::{
+    ChatCompletionRequestMessage, ChatCompletionRequestUserMessageContent,
+    ChatCompletionRequestUserMessageContentPart, CreateChatCompletionRequest,
+};
+use base64::Engine;
+use bytes::Bytes;
+use futures::{StreamExt, future::BoxFuture};
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use http_body_util::BodyExt;
+use image::{DynamicImage, ImageFormat, imageops::FilterType};
+use tracing::debug;
+
+use crate::{
+    app_state::AppState,
+    config::{
+        multimodal::MultimodalConfig,
+        providers::{GlobalProviderConfig, ModelCapabilities},
+    },
+    endpoints::{ApiEndpoint, openai::OpenAI},
+    error::{
+        api::ApiError, init::InitError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+    middleware::mapper::{model::ModelMapper, split_data_uri},
+    types::{
+        body::Body, model_id::ModelId, provider::InferenceProvider,
+        request::Request, response::Response,
+    },
+};
+
+const MB: usize = 1024 * 1024;
+/// The image types accepted by most providers.
+const IMAGE_TYPES: &[&str] =
+    &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+#[derive(Debug, Clone)]
+pub struct Layer {
+    app_state: AppState,
+    model_mapper: ModelMapper,
+    client: reqwest::Client,
+    config: MultimodalConfig,
+}
+
+impl Layer {
+    pub fn new(
+        app_state: AppState,
+        model_mapper: ModelMapper,
+    ) -> Result<Self, InitError> {
+        let client = reqwest::Client::builder()
+            .connect_timeout(app_state.config().dispatcher.connection_timeout)
+            .timeout(app_state.config().dispatcher.timeout)
+            .tcp_nodelay(true)
+            .redirect(reqwest::redirect::Policy::none())
+            .no_proxy()
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()
+            .map_err(InitError::CreateReqwestClient)?;
+        let config = app_state.config().multimodal.clone();
+        Ok(Self {
+            app_state,
+            model_mapper,
+            client,
+            config,
+        })
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            app_state: self.app_state.clone(),
+            model_mapper: self.model_mapper.clone(),
+            client: self.client.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    app_state: AppState,
+    model_mapper: ModelMapper,
+    client: reqwest::Client,
+    config: MultimodalConfig,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request, Response = Response, Error = ApiError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "multimodal", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        if !matches!(
+            req.extensions().get::<ApiEndpoint>(),
+            Some(ApiEndpoint::OpenAI(OpenAI::ChatCompletions(_)))
+        ) {
+            return Box::pin(self.inner.call(req));
+        }
+        // see: https://docs.rs/tower/latest/tower/trait.Service.html#be-careful-when-cloning-inner-services
+        let mut inner = self.inner.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        let app_state = self.app_state.clone();
+        let model_mapper = self.model_mapper.clone();
+        let client = self.client.clone();
+        let config = self.config.clone();
+        Box::pin(async move {
+            let provider =
+                req.extensions().get::<InferenceProvider>().cloned().ok_or(
+                    InternalError::ExtensionNotFound("InferenceProvider"),
+                )?;
+            let (mut parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            // invalid bodies are left for the mapper to reject
+            let Ok(mut request) =
+                serde_json::from_slice::<CreateChatCompletionRequest>(&body)
+            else {
+                return inner
+                    .call(Request::from_parts(parts, Body::from(body)))
+                    .await;
+            };
+            let content = Content::of(&mut request);
+            if !content.has_images && !content.has_audio {
+                return inner
+                    .call(Request::from_parts(parts, Body::from(body)))
+                    .await;
+            }
+
+            let target_endpoint = ApiEndpoint::mapped_with_dialect(
+                ApiEndpoint::OpenAI(OpenAI::chat_completions()),
+                &provider,
+                app_state
+                    .providers()
+                    .get(&provider)
+                    .and_then(GlobalProviderConfig::dialect),
+            )?;
+            // models the mapper can't map are left for it to reject
+            let capabilities = ModelId::from_str(&request.model)
+                .ok()
+                .and_then(|source_model| {
+                    model_mapper.map_model(&source_model, &provider).ok()
+                })
+                .and_then(|target_model| {
+                    app_state
+                        .providers()
+                        .get(&provider)
+                        .map(|config| config.model_capabilities(&target_model))
+                })
+                .unwrap_or_default();
+            validate(content, &target_endpoint, &request.model, capabilities)?;
+
+            let limits = image_limits(&target_endpoint);
+            let mut inlined = false;
+            for part in user_content_parts(&mut request) {
+                let ChatCompletionRequestUserMessageContentPart::ImageUrl(
+                    image,
+                ) = part
+                else {
+                    continue;
+                };
+                let url = &mut image.image_url.url;
+                if url.starts_with("data:") {
+                    if let Some(downscaled) = fit_data_uri(url, &limits).await?
+                    {
+                        *url = downscaled;
+                        inlined = true;
+                    }
+                } else if !limits.fetches_urls && url.starts_with("http") {
+                    if !config.fetch_images {
+                        return Err(InvalidRequestError::UnsupportedContent(
+                            format!("{provider} does not fetch image URLs"),
+                        )
+                        .into());
+                    }
+                    *url = fetch_image(
+                        &client,
+                        url,
+                        &limits,
+                        config.max_image_bytes,
+                    )
+                    .await?;
+                    inlined = true;
+                }
+            }
+            if !inlined {
+                return inner
+                    .call(Request::from_parts(parts, Body::from(body)))
+                    .await;
+            }
+
+            let body = serde_json::to_vec(&request).map_err(|error| {
+                InternalError::Serialize {
+                    ty: "CreateChatCompletionRequest",
+                    error,
+                }
+            })?;
+            parts.headers.remove(CONTENT_LENGTH);
+            inner
+                .call(Request::from_parts(parts, Body::from(Bytes::from(body))))
+                .await
+        })
+    }
+}
+
+/// The kinds of content beyond text in a request.
+#[derive(Debug, Default, Clone, Copy)]
+struct Content {
+    has_images: bool,
+    has_audio: bool,
+}
+
+impl Content {
+    fn of(request: &mut CreateChatCompletionRequest) -> Self {
+        let mut content = Self::default();
+        for part in user_content_parts(request) {
+            match part {
+                ChatCompletionRequestUserMessageContentPart::ImageUrl(_) => {
+                    content.has_images = true;
+                }
+                ChatCompletionRequestUserMessageContentPart::InputAudio(_) => {
+                    content.has_audio = true;
+                }
+                ChatCompletionRequestUserMessageContentPart::Text(_) => {}
+            }
+        }
+        content
+    }
+}
+
+fn user_content_parts(
+    request: &mut CreateChatCompletionRequest,
+) -> impl Iterator<Item = &mut ChatCompletionRequestUserMessageContentPart> {
+    request
+        .messages
+        .iter_mut()
+        .filter_map(|message| match message {
+            ChatCompletionRequestMessage::User(message) => {
+                match &mut message.content {
+                    ChatCompletionRequestUserMessageContent::Array(parts) => {
+                        Some(parts)
+                    }
+                    ChatCompletionRequestUserMessageContent::Text(_) => None,
+                }
+            }
+            _ => None,
+        })
+        .flatten()
+}
+
+/// Reject content the target model can't take, rather than letting the
+/// mapper drop it or the provider reject it in its own words.
+fn validate(
+    content: Content,
+    target_endpoint: &ApiEndpoint,
+    model: &str,
+    capabilities: ModelCapabilities,
+) -> Result<(), InvalidRequestError> {
+    if content.has_images && capabilities.supports_vision == Some(false) {
+        return Err(InvalidRequestError::UnsupportedContent(format!(
+            "{model} does not support images"
+        )));
+    }
+    if content.has_audio
+        && (!accepts_audio(target_endpoint)
+            || capabilities.supports_audio == Some(false))
+    {
+        return Err(InvalidRequestError::UnsupportedContent(format!(
+            "{model} does not support audio"
+        )));
+    }
+    Ok(())
+}
+
+/// Whether `endpoint` takes audio at all.
+fn accepts_audio(endpoint: &ApiEndpoint) -> bool {
+    matches!(
+        endpoint,
+        ApiEndpoint::OpenAI(_)
+            | ApiEndpoint::OpenAICompatible { .. }
+            | ApiEndpoint::Google(_)
+    )
+}
+
+/// What a provider accepts of the images of a request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ImageLimits {
+    /// The size of the largest image, once decoded, if we know of a limit.
+    max_bytes: Option<usize>,
+    media_types: &'static [&'static str],
+    /// Whether the provider fetches images referenced by URL itself.
+    fetches_urls: bool,
+}
+
+impl ImageLimits {
+    fn check_type(&self, media_type: &str) -> Result<(), InvalidRequestError> {
+        if self.media_types.contains(&media_type) {
+            Ok(())
+        } else {
+            Err(InvalidRequestError::InvalidImage(format!(
+                "unsupported image type {media_type}, supported: {}",
+                self.media_types.join(", ")
+            )))
+        }
+    }
+
+    /// Whether an image of `size` bytes is small enough for the provider.
+    fn fits(&self, size: usize) -> bool {
+        self.max_bytes.is_none_or(|max_bytes| size <= max_bytes)
+    }
+}
+
+/// The image limits of `endpoint`, from the providers' documentation.
+fn image_limits(endpoint: &ApiEndpoint) -> ImageLimits {
+    match endpoint {
+        ApiEndpoint::Anthropic(_) => ImageLimits {
+            max_bytes: Some(5 * MB),
+            media_types: IMAGE_TYPES,
+            fetches_urls: true,
+        },
+        ApiEndpoint::Bedrock(_) => ImageLimits {
+            max_bytes: Some(3_750_000),
+            media_types: IMAGE_TYPES,
+            fetches_urls: false,
+        },
+        // arbitrary URLs can't be referenced, only uploaded files
+        ApiEndpoint::Google(_) => ImageLimits {
+            max_bytes: Some(20 * MB),
+            media_types: &[
+                "image/png",
+                "image/jpeg",
+                "image/webp",
+                "image/heic",
+                "image/heif",
+            ],
+            fetches_urls: false,
+        },
+        ApiEndpoint::Ollama(_) | ApiEndpoint::OllamaCompatible { .. } => {
+            ImageLimits {
+                max_bytes: None,
+                media_types: &["image/png", "image/jpeg"],
+                fetches_urls: false,
+            }
+        }
+        ApiEndpoint::OpenAI(_)
+        | ApiEndpoint::OpenAICompatible { .. }
+        | ApiEndpoint::Cohere(_) => ImageLimits {
+            max_bytes: Some(20 * MB),
+            media_types: IMAGE_TYPES,
+            fetches_urls: true,
+        },
+    }
+}
+
+/// Check the image of the data URI `uri`, and downscale it if it is too large
+/// for the provider. Returns the data URI of the downscaled image, if it was.
+async fn fit_data_uri(
+    uri: &str,
+    limits: &ImageLimits,
+) -> Result<Option<String>, ApiError> {
+    let (media_type, data) = split_data_uri(uri).ok_or_else(|| {
+        InvalidRequestError::InvalidImage(
+            "invalid data URI or unknown image type".to_string(),
+        )
+    })?;
+    limits.check_type(&media_type)?;
+    if limits.fits(decoded_len(data)) {
+        return Ok(None);
+    }
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .map_err(|_| {
+            InvalidRequestError::InvalidImage(
+                "invalid base64 image data".to_string(),
+            )
+        })?;
+    let (media_type, bytes) = fit_image(media_type, bytes, limits).await?;
+    Ok(Some(data_uri(&media_type, &bytes)))
+}
+
+/// Fetch the image at `url` and inline it as a base64 data URI, downscaled if
+/// it is too large for the provider.
+///
+/// Fetch errors are logged rather than returned, so the responses don't tell
+/// what the gateway can reach.
+async fn fetch_image(
+    client: &reqwest::Client,
+    url: &str,
+    limits: &ImageLimits,
+    max_fetch_bytes: usize,
+) -> Result<String, ApiError> {
+    let fetch_error = || {
+        InvalidRequestError::InvalidImage("failed to fetch image".to_string())
+    };
+    if !is_fetchable(url) {
+        return Err(fetch_error().into());
+    }
+    let response = client.get(url).send().await.map_err(|error| {
+        debug!(%error, "failed to fetch image");
+        fetch_error()
+    })?;
+    if !response.status().is_success() {
+        debug!(status = %response.status(), "failed to fetch image");
+        return Err(fetch_error().into());
+    }
+    let too_large = || {
+        InvalidRequestError::InvalidImage(format!(
+            "image is larger than the {max_fetch_bytes} bytes fetched"
+        ))
+    };
+    if response.content_length().is_some_and(|len| {
+        usize::try_from(len).unwrap_or(usize::MAX) > max_fetch_bytes
+    }) {
+        return Err(too_large().into());
+    }
+    let declared_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').next())
+        .map(str::trim)
+        .filter(|value| value.starts_with("image/"))
+        .map(ToString::to_string);
+
+    let mut bytes = Vec::new();
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|error| {
+            debug!(%error, "failed to fetch image");
+            fetch_error()
+        })?;
+        if bytes.len() + chunk.len() > max_fetch_bytes {
+            return Err(too_large().into());
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    let media_type = declared_type
+        .or_else(|| infer::get(&bytes).map(|t| t.mime_type().to_string()))
+        .ok_or_else(|| {
+            InvalidRequestError::InvalidImage("unknown image type".to_string())
+        })?;
+    limits.check_type(&media_type)?;
+    let (media_type, bytes) = fit_image(media_type, bytes, limits).await?;
+    Ok(data_uri(&media_type, &bytes))
+}
+
+/// Whether `url` is an http URL the gateway may fetch. Hosts given by name
+/// are checked once resolved, see [`PublicResolver`].
+fn is_fetchable(url: &str) -> bool {
+    let Ok(url) = url::Url::parse(url) else {
+        return false;
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return false;
+    }
+    match url.host() {
+        Some(url::Host::Domain(_)) => true,
+        Some(url::Host::Ipv4(ip)) => is_public(IpAddr::V4(ip)),
+        Some(url::Host::Ipv6(ip)) => is_public(IpAddr::V6(ip)),
+        None => false,
+    }
+}
+
+/// Resolves hosts to their public addresses only, so images can't be fetched
+/// from the network the gateway runs in.
+#[derive(Debug)]
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(async move {
+            let addrs = tokio::net::lookup_host((name.as_str(), 0))
+                .await?
+                .filter(|addr| is_public(addr.ip()))
+                .collect::<Vec<_>>();
+            if addrs.is_empty() {
+                return Err(
+                    format!("{} has no public address", name.as_str()).into()
+                );
+            }
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// Whether `ip` is a public address, rather than a private, loopback,
+/// link-local or otherwise reserved one.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // "this network", 0.0.0.0/8
+                || a == 0
+                // shared address space, 100.64.0.0/10
+                || (a == 100 && b & 0xc0 == 64))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public(IpAddr::V4(ip));
+            }
+            let first = ip.segments()[0];
+            !(ip.is_loopback()
+                || ip.is_unspecified()
+                || ip.is_multicast()
+                // unique local, fc00::/7
+                || first & 0xfe00 == 0xfc00
+                // link-local, fe80::/10
+                || first & 0xffc0 == 0xfe80)
+        }
+    }
+}
+
+/// Downscale the image in `bytes` if it is too large for the provider, on a
+/// blocking thread. Returns the media type and bytes of the image to send.
+async fn fit_image(
+    media_type: String,
+    bytes: Vec<u8>,
+    limits: &ImageLimits,
+) -> Result<(String, Vec<u8>), ApiError> {
+    match limits.max_bytes {
+        Some(max_bytes) if bytes.len() > max_bytes => {
+            let bytes = tokio::task::spawn_blocking(move || {
+                downscale(&bytes, max_bytes)
+            })
+            .await
+            .map_err(InternalError::MappingTaskError)??;
+            Ok(("image/jpeg".to_string(), bytes))
+        }
+        _ => Ok((media_type, bytes)),
+    }
+}
+
+/// Downscale the image in `bytes` until it fits in `max_bytes`, re-encoded as
+/// JPEG.
+fn downscale(
+    bytes: &[u8],
+    max_bytes: usize,
+) -> Result<Vec<u8>, InvalidRequestError> {
+    let mut image = image::load_from_memory(bytes)
+        .map(|image| DynamicImage::ImageRgb8(image.into_rgb8()))
+        .map_err(|error| {
+            debug!(%error, "failed to decode image");
+            InvalidRequestError::InvalidImage(
+                "failed to decode image".to_string(),
+            )
+        })?;
+    loop {
+        let mut encoded = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut encoded), ImageFormat::Jpeg)
+            .map_err(|error| {
+                debug!(%error, "failed to encode image");
+                InvalidRequestError::InvalidImage(
+                    "failed to downscale image".to_string(),
+                )
+            })?;
+        if encoded.len() <= max_bytes {
+            return Ok(encoded);
+        }
+        if image.width() <= 1 && image.height() <= 1 {
+            return Err(InvalidRequestError::InvalidImage(format!(
+                "image can't be downscaled to the {max_bytes} bytes supported"
+            )));
+        }
+        // the encoded size is roughly proportional to the number of pixels
+        #[allow(clippy::cast_precision_loss)]
+        let scale = (max_bytes as f64 / encoded.len() as f64).sqrt().min(0.9);
+        image = image.resize_exact(
+            scale_dimension(image.width(), scale),
+            scale_dimension(image.height(), scale),
+            FilterType::Triangle,
+        );
+    }
+}
+
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn scale_dimension(dimension: u32, scale: f64) -> u32 {
+    ((f64::from(dimension) * scale) as u32).max(1)
+}
+
+fn data_uri(media_type: &str, bytes: &[u8]) -> String {
+    format!(
+        "data:{media_type};base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    )
+}
+
+/// The number of bytes `data` decodes to, without decoding it.
+fn decoded_len(data: &str) -> usize {
+    let padding = data.bytes().rev().take_while(|b| *b == b'=').count();
+    (data.len() / 4 * 3).saturating_sub(padding)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+
+    use super::*;
+    use crate::endpoints::{anthropic::Anthropic, bedrock::Bedrock};
+
+    const MODEL: &str = "deepseek/deepseek-chat";
+
+    #[test]
+    fn decoded_len_accounts_for_padding() {
+        let engine = base64::engine::general_purpose::STANDARD;
+        for len in 0..16 {
+            let data = engine.encode(vec![0u8; len]);
+            assert_eq!(decoded_len(&data), len);
+        }
+    }
+
+    #[test]
+    fn images_are_rejected_for_models_without_vision() {
+        let content = Content {
+            has_images: true,
+            has_audio: false,
+        };
+        let endpoint = ApiEndpoint::OpenAI(OpenAI::chat_completions());
+        let no_vision = ModelCapabilities {
+            supports_vision: Some(false),
+            ..Default::default()
+        };
+        assert!(matches!(
+            validate(content, &endpoint, MODEL, no_vision),
+            Err(InvalidRequestError::UnsupportedContent(_))
+        ));
+        // unknown capabilities are left for the provider to decide
+        let unknown = ModelCapabilities {
+            context_window: NonZeroU32::new(128_000),
+            ..Default::default()
+        };
+        assert!(validate(content, &endpoint, MODEL, unknown).is_ok());
+    }
+
+    #[test]
+    fn audio_is_rejected_for_providers_without_audio() {
+        let content = Content {
+            has_images: false,
+            has_audio: true,
+        };
+        let anthropic = ApiEndpoint::Anthropic(Anthropic::messages());
+        assert!(matches!(
+            validate(content, &anthropic, MODEL, ModelCapabilities::default()),
+            Err(InvalidRequestError::UnsupportedContent(_))
+        ));
+        let openai = ApiEndpoint::OpenAI(OpenAI::chat_completions());
+        assert!(
+            validate(content, &openai, MODEL, ModelCapabilities::default())
+                .is_ok()
+        );
+    }
+
+    /// A PNG of noise, which doesn't compress.
+    fn noise_png(width: u32, height: u32) -> Vec<u8> {
+        let mut state = 0x2545_f491_u32;
+        let image = image::RgbImage::from_fn(width, height, |_, _| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let [r, g, b, _] = state.to_le_bytes();
+            image::Rgb([r, g, b])
+        });
+        let mut png = Vec::new();
+        DynamicImage::ImageRgb8(image)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        png
+    }
+
+    #[tokio::test]
+    async fn data_uris_are_checked_against_the_provider_limits() {
+        let limits = image_limits(&ApiEndpoint::Bedrock(Bedrock::converse()));
+        let engine = base64::engine::general_purpose::STANDARD;
+
+        let small = format!("data:image/png;base64,{}", engine.encode([0; 16]));
+        assert!(fit_data_uri(&small, &limits).await.unwrap().is_none());
+
+        // too large, and not an image that can be downscaled
+        let large =
+            format!("data:image/png;base64,{}", engine.encode(vec![0; 4 * MB]));
+        assert!(matches!(
+            fit_data_uri(&large, &limits).await,
+            Err(ApiError::InvalidRequest(InvalidRequestError::InvalidImage(
+                _
+            )))
+        ));
+
+        let tiff = format!("data:image/tiff;base64,{}", engine.encode([0; 16]));
+        assert!(matches!(
+            fit_data_uri(&tiff, &limits).await,
+            Err(ApiError::InvalidRequest(InvalidRequestError::InvalidImage(
+                _
+            )))
+        ));
+    }
+
+    #[tokio::test]
+    async fn large_images_are_downscaled() {
+        let limits = ImageLimits {
+            max_bytes: Some(64 * 1024),
+            media_types: IMAGE_TYPES,
+            fetches_urls: false,
+        };
+        let png = noise_png(512, 512);
+        assert!(png.len() > 64 * 1024);
+        let uri = data_uri("image/png", &png);
+
+        let downscaled = fit_data_uri(&uri, &limits).await.unwrap().unwrap();
+        let (media_type, data) = split_data_uri(&downscaled).unwrap();
+        assert_eq!(media_type, "image/jpeg");
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .unwrap();
+        assert!(bytes.len() <= 64 * 1024);
+        let image = image::load_from_memory(&bytes).unwrap();
+        assert!(image.width() < 512 && image.height() < 512);
+    }
+
+    #[test]
+    fn only_public_addresses_are_fetched() {
+        for ip in [
+            "127.0.0.1",
+            "10.0.0.1",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public(ip.parse().unwrap()), "{ip}");
+        }
+        for ip in ["8.8.8.8", "1.1.1.1", "2606:4700:4700::1111"] {
+            assert!(is_public(ip.parse().unwrap()), "{ip}");
+        }
+
+        assert!(is_fetchable("https://example.com/image.png"));
+        assert!(is_fetchable("http://8.8.8.8/image.png"));
+        assert!(!is_fetchable("http://127.0.0.1/image.png"));
+        assert!(!is_fetchable("http://[::1]/image.png"));
+        assert!(!is_fetchable("http://169.254.169.254/latest/meta-data"));
+        assert!(!is_fetchable("file:///etc/passwd"));
+    }
+
+    #[tokio::test]
+    async fn private_hosts_are_not_resolved() {
+        use reqwest::dns::Resolve;
+
+        let name = reqwest::dns::Name::from_str("localhost").unwrap();
+        assert!(PublicResolver.resolve(name).await.is_err());
+    }
+}
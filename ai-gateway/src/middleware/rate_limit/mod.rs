@@ -1,5 +1,6 @@
 pub mod cleanup;
 pub mod extractor;
+pub mod provider;
 pub mod redis_service;
 pub mod service;
 
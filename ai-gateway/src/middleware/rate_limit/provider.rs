@@ -0,0 +1,329 @@
+//! Limits on the requests and tokens the gateway sends to each provider, so
+//! that it stays within the quotas of the provider.
+//!
+//! The limits are the `limits` configured for a provider, or for one of its
+//! models or families, see [`GlobalProviderConfig::model_limits`]. Like most
+//! providers apply their quotas, each model of a provider is limited
+//! separately.
+//!
+//! [`GlobalProviderConfig::model_limits`]: crate::config::providers::GlobalProviderConfig::model_limits
+use std::{
+    num::NonZeroU32,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::Utc;
+use governor::{
+    DefaultDirectRateLimiter, Quota, RateLimiter,
+    clock::{Clock, DefaultClock},
+};
+use r2d2::Pool;
+use redis::{Client, Commands};
+use rustc_hash::FxHashMap as HashMap;
+
+use crate::{
+    config::{Config, providers::ProviderLimits, rate_limit::RateLimitStore},
+    error::{
+        api::ApiError,
+        init::InitError,
+        internal::InternalError,
+        invalid_req::{InvalidRequestError, TooManyRequestsError},
+    },
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+
+/// Providers count tokens differently, this is only used to estimate the
+/// size of a prompt before it is sent.
+const BYTES_PER_TOKEN: u64 = 4;
+const WINDOW: Duration = Duration::from_mins(1);
+
+#[derive(Debug)]
+enum Store {
+    /// Limiters are created on first use, and recreated if the limit they
+    /// were created with is changed by a config reload.
+    InMemory(
+        Mutex<HashMap<String, (NonZeroU32, Arc<DefaultDirectRateLimiter>)>>,
+    ),
+    Redis(Pool<Client>),
+}
+
+/// Enforces the `requests-per-minute` and `tokens-per-minute` limits of
+/// providers.
+///
+/// Uses the `rate-limit-store`, so with Redis the limits are shared between
+/// every replica of the gateway.
+#[derive(Debug)]
+pub struct ProviderRateLimiter {
+    store: Store,
+}
+
+impl ProviderRateLimiter {
+    pub fn new(config: &Config) -> Result<Self, InitError> {
+        let store = match &config.rate_limit_store {
+            Some(RateLimitStore::Redis(redis_config)) => {
+                let client =
+                    Client::open(redis_config.host_url.expose().clone())?;
+                // connect lazily, so that providers without limits keep
+                // working while Redis is unavailable
+                Store::Redis(Pool::builder().build_unchecked(client))
+            }
+            Some(RateLimitStore::InMemory) | None => {
+                Store::InMemory(Mutex::default())
+            }
+        };
+        Ok(Self { store })
+    }
+
+    /// Take one request and the estimated tokens of `body` from the limits
+    /// of `model`.
+    ///
+    /// # Errors
+    /// [`InvalidRequestError::TooManyRequests`] if either limit is exceeded.
+    pub fn check(
+        &self,
+        provider: &InferenceProvider,
+        model: &ModelId,
+        limits: ProviderLimits,
+        body: &[u8],
+    ) -> Result<(), ApiError> {
+        let key = format!("rl:per-provider:{provider}:{}", model.cache_key());
+        if let Some(rpm) = limits.requests_per_minute {
+            self.take(&format!("{key}:requests"), rpm, 1)?;
+        }
+        if let Some(tpm) = limits.tokens_per_minute {
+            let tpm = NonZeroU32::try_from(tpm).unwrap_or(NonZeroU32::MAX);
+            self.take(&format!("{key}:tokens"), tpm, estimate_tokens(body))?;
+        }
+        Ok(())
+    }
+
+    /// Take `cost` from a limit of `limit` per minute. A cost larger than
+    /// the limit takes the whole limit rather than never being allowed.
+    fn take(
+        &self,
+        key: &str,
+        limit: NonZeroU32,
+        cost: u64,
+    ) -> Result<(), ApiError> {
+        let cost = u32::try_from(cost)
+            .ok()
+            .and_then(NonZeroU32::new)
+            .map_or(NonZeroU32::MIN, |cost| cost.min(limit));
+        let retry_after = match &self.store {
+            Store::InMemory(limiters) => {
+                take_in_memory(limiters, key, limit, cost)
+            }
+            Store::Redis(pool) => take_redis(pool, key, limit, cost)?,
+        };
+        match retry_after {
+            None => Ok(()),
+            Some(retry_after) => {
+                tracing::debug!(
+                    key,
+                    ?retry_after,
+                    "provider rate limit exceeded"
+                );
+                Err(ApiError::InvalidRequest(
+                    InvalidRequestError::TooManyRequests(
+                        TooManyRequestsError {
+                            ratelimit_limit: u64::from(limit.get()),
+                            ratelimit_remaining: 0,
+                            // round up, like for the per API key limits
+                            retry_after: retry_after.as_secs() + 1,
+                        },
+                    ),
+                ))
+            }
+        }
+    }
+}
+
+fn take_in_memory(
+    limiters: &Mutex<
+        HashMap<String, (NonZeroU32, Arc<DefaultDirectRateLimiter>)>,
+    >,
+    key: &str,
+    limit: NonZeroU32,
+    cost: NonZeroU32,
+) -> Option<Duration> {
+    let limiter = {
+        let mut limiters = limiters
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        match limiters.get(key) {
+            Some((configured, limiter)) if *configured == limit => {
+                limiter.clone()
+            }
+            _ => {
+                let limiter =
+                    Arc::new(RateLimiter::direct(Quota::per_minute(limit)));
+                limiters.insert(key.to_string(), (limit, limiter.clone()));
+                limiter
+            }
+        }
+    };
+    match limiter.check_n(cost) {
+        Ok(Ok(())) => None,
+        Ok(Err(not_until)) => {
+            Some(not_until.wait_time_from(DefaultClock::default().now()))
+        }
+        // the cost is capped at the limit
+        Err(_) => Some(WINDOW),
+    }
+}
+
+/// The same GCRA as the Redis per API key rate limit, allowing a cost of more
+/// than one cell and counting in microseconds, since a token limit can allow
+/// more than one token per millisecond.
+fn take_redis(
+    pool: &Pool<Client>,
+    key: &str,
+    limit: NonZeroU32,
+    cost: NonZeroU32,
+) -> Result<Option<Duration>, ApiError> {
+    let mut conn = pool.get().map_err(InternalError::PoolError)?;
+    let now = Utc::now().timestamp_micros();
+    let window = i64::try_from(WINDOW.as_micros()).expect("window fits");
+    let interval = window / i64::from(limit.get());
+
+    let tat = conn
+        .get::<_, Option<i64>>(key)
+        .map_err(InternalError::RedisError)?
+        .unwrap_or(now)
+        .max(now);
+    let new_tat = tat + interval * i64::from(cost.get());
+    let allowed_at = new_tat - window;
+    if allowed_at > now {
+        let wait = u64::try_from(allowed_at - now).unwrap_or_default();
+        return Ok(Some(Duration::from_micros(wait)));
+    }
+    let _: () = conn
+        .set_ex(key, new_tat, WINDOW.as_secs() + 1)
+        .map_err(InternalError::RedisError)?;
+    Ok(None)
+}
+
+fn estimate_tokens(body: &[u8]) -> u64 {
+    u64::try_from(body.len())
+        .unwrap_or(u64::MAX)
+        .div_ceil(BYTES_PER_TOKEN)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU64;
+
+    use super::*;
+
+    fn limiter() -> ProviderRateLimiter {
+        ProviderRateLimiter {
+            store: Store::InMemory(Mutex::default()),
+        }
+    }
+
+    fn limits(rpm: Option<u32>, tpm: Option<u64>) -> ProviderLimits {
+        ProviderLimits {
+            requests_per_minute: rpm.and_then(NonZeroU32::new),
+            max_concurrent: None,
+            tokens_per_minute: tpm.and_then(NonZeroU64::new),
+        }
+    }
+
+    fn model(name: &str) -> ModelId {
+        ModelId::Unknown(name.to_string())
+    }
+
+    fn is_rate_limited(result: Result<(), ApiError>) -> bool {
+        matches!(
+            result,
+            Err(ApiError::InvalidRequest(
+                InvalidRequestError::TooManyRequests(_)
+            ))
+        )
+    }
+
+    #[test]
+    fn requests_per_minute_are_limited_per_model() {
+        let limiter = limiter();
+        let provider = InferenceProvider::OpenAI;
+        let limits = limits(Some(2), None);
+        for _ in 0..2 {
+            limiter
+                .check(&provider, &model("a"), limits, b"{}")
+                .unwrap();
+        }
+        assert!(is_rate_limited(limiter.check(
+            &provider,
+            &model("a"),
+            limits,
+            b"{}"
+        )));
+        // other models have their own limit
+        limiter
+            .check(&provider, &model("b"), limits, b"{}")
+            .unwrap();
+    }
+
+    #[test]
+    fn tokens_per_minute_count_the_prompt() {
+        let limiter = limiter();
+        let provider = InferenceProvider::Anthropic;
+        let limits = limits(None, Some(10));
+        // 32 bytes are estimated at 8 tokens
+        let body = [b'a'; 32];
+        limiter
+            .check(&provider, &model("a"), limits, &body)
+            .unwrap();
+        assert!(is_rate_limited(limiter.check(
+            &provider,
+            &model("a"),
+            limits,
+            &body
+        )));
+    }
+
+    #[test]
+    fn prompt_larger_than_the_limit_is_allowed_once() {
+        let limiter = limiter();
+        let provider = InferenceProvider::OpenAI;
+        let limits = limits(None, Some(10));
+        let body = [b'a'; 1000];
+        limiter
+            .check(&provider, &model("a"), limits, &body)
+            .unwrap();
+        assert!(is_rate_limited(limiter.check(
+            &provider,
+            &model("a"),
+            limits,
+            b"{}"
+        )));
+    }
+
+    #[test]
+    fn unset_limits_are_unbounded() {
+        let limiter = limiter();
+        for _ in 0..100 {
+            limiter
+                .check(
+                    &InferenceProvider::OpenAI,
+                    &model("a"),
+                    ProviderLimits::default(),
+                    b"{}",
+                )
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn changed_limit_replaces_the_limiter() {
+        let limiter = limiter();
+        let provider = InferenceProvider::OpenAI;
+        limiter
+            .check(&provider, &model("a"), limits(Some(1), None), b"{}")
+            .unwrap();
+        limiter
+            .check(&provider, &model("a"), limits(Some(5), None), b"{}")
+            .unwrap();
+    }
+}
@@ -10,6 +10,7 @@ use pin_project_lite::pin_project;
 
 use crate::{
     config::response_headers::ResponseHeadersConfig,
+    metering::RequestCost,
     types::{extensions::ProviderRequestId, provider::InferenceProvider},
 };
 
@@ -119,6 +120,31 @@ where
                     .insert("helicone-provider-req-id", provider_request_id.0);
             }
         }
+
+        if this.config.cost {
+            let request_cost =
+                response.extensions().get::<RequestCost>().copied();
+            if let Some(request_cost) = request_cost {
+                let headers = response.headers_mut();
+                headers.insert(
+                    "helicone-prompt-tokens",
+                    http::HeaderValue::from(request_cost.usage.prompt_tokens),
+                );
+                headers.insert(
+                    "helicone-completion-tokens",
+                    http::HeaderValue::from(
+                        request_cost.usage.completion_tokens,
+                    ),
+                );
+                if let Some(cost_usd) = request_cost.cost_usd
+                    && let Ok(header_value) = http::HeaderValue::from_str(
+                        &cost_usd.normalize().to_string(),
+                    )
+                {
+                    headers.insert("helicone-cost-usd", header_value);
+                }
+            }
+        }
         Poll::Ready(Ok(response))
     }
 }
@@ -154,6 +180,7 @@ mod tests {
         let config = ResponseHeadersConfig {
             provider: false,
             provider_request_id: false,
+            cost: false,
         };
 
         let mut service = ResponseHeaderService::new(
@@ -181,6 +208,7 @@ mod tests {
         let config = ResponseHeadersConfig {
             provider: true,
             provider_request_id: false,
+            cost: false,
         };
 
         let mut service = ResponseHeaderService::new(
@@ -210,6 +238,7 @@ mod tests {
         let config = ResponseHeadersConfig {
             provider: false,
             provider_request_id: true,
+            cost: false,
         };
 
         let mut service = ResponseHeaderService::new(
@@ -239,6 +268,7 @@ mod tests {
         let config = ResponseHeadersConfig {
             provider: true,
             provider_request_id: true,
+            cost: false,
         };
 
         let mut service = ResponseHeaderService::new(
@@ -269,11 +299,57 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn test_cost_headers_enabled() {
+        let config = ResponseHeadersConfig {
+            provider: false,
+            provider_request_id: false,
+            cost: true,
+        };
+
+        let mut service = ResponseHeaderService::new(
+            config,
+            create_mock_service(|| {
+                let mut response = Response::new("test".to_string());
+                response.extensions_mut().insert(RequestCost {
+                    usage: crate::metering::Usage {
+                        prompt_tokens: 1000,
+                        completion_tokens: 100,
+                        cached_prompt_tokens: 0,
+                    },
+                    cost_usd: Some(rust_decimal::Decimal::new(2100, 7)),
+                });
+                response
+            }),
+        );
+
+        let request = Request::new(());
+        let response =
+            service.ready().await.unwrap().call(request).await.unwrap();
+
+        assert_eq!(
+            response.headers().get("helicone-prompt-tokens").unwrap(),
+            "1000"
+        );
+        assert_eq!(
+            response
+                .headers()
+                .get("helicone-completion-tokens")
+                .unwrap(),
+            "100"
+        );
+        assert_eq!(
+            response.headers().get("helicone-cost-usd").unwrap(),
+            "0.00021"
+        );
+    }
+
     #[tokio::test]
     async fn test_missing_provider_extension() {
         let config = ResponseHeadersConfig {
             provider: true,
             provider_request_id: false,
+            cost: false,
         };
 
         let mut service = ResponseHeaderService::new(
@@ -293,6 +369,7 @@ mod tests {
         let config = ResponseHeadersConfig {
             provider: false,
             provider_request_id: true,
+            cost: false,
         };
 
         let mut service = ResponseHeaderService::new(
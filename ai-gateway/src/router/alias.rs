@@ -0,0 +1,212 @@
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use rustc_hash::FxHashMap as HashMap;
+use serde_json::Value;
+use tower::{Layer as _, ServiceExt};
+
+use crate::{
+    app_state::AppState,
+    config::router::RouterConfig,
+    dispatcher::{Dispatcher, DispatcherService},
+    error::{api::ApiError, init::InitError, internal::InternalError},
+    middleware::request_context,
+    router::failover::should_fail_over,
+    types::{
+        body::Body, model_id::ModelId, request::Request, response::Response,
+        router::RouterId,
+    },
+};
+
+type TargetService = request_context::Service<DispatcherService>;
+
+#[derive(Debug, Clone)]
+struct AliasTarget {
+    model: ModelId,
+    /// Always sends the request to `model`, regardless of the model of the
+    /// request.
+    dispatcher: TargetService,
+}
+
+type Aliases = HashMap<String, Arc<[AliasTarget]>>;
+
+/// Resolves requests for a model alias of a router, e.g. `smart`, to the
+/// `{provider}/{model}` targets of the alias.
+///
+/// The targets are tried in order until one of them does not respond with a
+/// server error or rate limit us, bypassing the load balancing strategy of
+/// the router. Requests for any other model are passed through.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    aliases: Option<Arc<Aliases>>,
+}
+
+impl Layer {
+    pub async fn for_router(
+        app_state: &AppState,
+        router_id: &RouterId,
+        router_config: &Arc<RouterConfig>,
+    ) -> Result<Self, InitError> {
+        let Some(model_aliases) = router_config
+            .model_aliases
+            .as_ref()
+            .filter(|aliases| !aliases.is_empty())
+        else {
+            return Ok(Self { aliases: None });
+        };
+
+        let request_context_layer =
+            request_context::Layer::for_router(router_config.clone());
+        let mut aliases = HashMap::default();
+        for (alias, models) in model_aliases {
+            let mut targets = Vec::with_capacity(models.len().get());
+            for model in models {
+                let Some(provider) = model.inference_provider() else {
+                    return Err(InitError::ModelIdNotRecognized(
+                        model.to_string(),
+                    ));
+                };
+                let dispatcher = Dispatcher::new_with_model_id(
+                    app_state.clone(),
+                    router_id,
+                    router_config,
+                    provider,
+                    model.clone(),
+                )
+                .await?;
+                targets.push(AliasTarget {
+                    model: model.clone(),
+                    dispatcher: request_context_layer.layer(dispatcher),
+                });
+            }
+            aliases.insert(alias.clone(), targets.into());
+        }
+
+        Ok(Self {
+            aliases: Some(Arc::new(aliases)),
+        })
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            aliases: self.aliases.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    aliases: Option<Arc<Aliases>>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request, Response = Response, Error = ApiError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "model_alias", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        let Some(aliases) = self.aliases.clone() else {
+            return Box::pin(self.inner.call(req));
+        };
+        let mut inner = self.inner.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            let Some((mut value, targets)) = resolve(&aliases, &body) else {
+                return inner
+                    .call(Request::from_parts(parts, Body::from(body)))
+                    .await;
+            };
+
+            let request = |body: Bytes| {
+                let mut request = Request::new(Body::from(body));
+                *request.method_mut() = parts.method.clone();
+                *request.uri_mut() = parts.uri.clone();
+                *request.version_mut() = parts.version;
+                *request.headers_mut() = parts.headers.clone();
+                *request.extensions_mut() = parts.extensions.clone();
+                request
+            };
+            let (last, targets) =
+                targets.split_last().ok_or(InternalError::Internal)?;
+            for target in targets {
+                let body = with_model(&mut value, &target.model)?;
+                let Ok(response) =
+                    target.dispatcher.clone().oneshot(request(body)).await;
+                if !should_fail_over(&response) {
+                    return Ok(response);
+                }
+                tracing::warn!(
+                    model = %target.model,
+                    status = %response.status(),
+                    "alias target failed, failing over to next target"
+                );
+            }
+            let body = with_model(&mut value, &last.model)?;
+            let Ok(response) =
+                last.dispatcher.clone().oneshot(request(body)).await;
+            Ok(response)
+        })
+    }
+}
+
+/// The parsed `body` and the targets of the alias it requests, if it is a
+/// JSON body whose `model` is an alias.
+fn resolve(
+    aliases: &Aliases,
+    body: &Bytes,
+) -> Option<(Value, Arc<[AliasTarget]>)> {
+    let value = serde_json::from_slice::<Value>(body).ok()?;
+    let model = value.get("model")?.as_str()?;
+    let targets = aliases.get(model)?.clone();
+    tracing::trace!(alias = model, "resolved model alias");
+    Some((value, targets))
+}
+
+/// Replace the model of the request `value` with `model`, in the
+/// `{provider}/{model}` form the mappers expect.
+fn with_model(value: &mut Value, model: &ModelId) -> Result<Bytes, ApiError> {
+    value["model"] = Value::String(format!(
+        "{}/{model}",
+        model.inference_provider().ok_or(InternalError::Internal)?
+    ));
+    let body = serde_json::to_vec(value).map_err(|error| {
+        InternalError::Serialize {
+            ty: "serde_json::Value",
+            error,
+        }
+    })?;
+    Ok(Bytes::from(body))
+}
@@ -0,0 +1,348 @@
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::{StreamExt, future::BoxFuture};
+use http::StatusCode;
+use http_body_util::BodyExt;
+use nonempty_collections::NEVec;
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use tokio::{
+    sync::mpsc::{Receiver, channel},
+    time::Instant,
+};
+use tower::{ServiceExt, discover::Change};
+use tracing::Instrument;
+
+use crate::{
+    app_state::AppState,
+    config::{balance::StreamFailoverConfig, router::RouterConfig},
+    discover::provider::key::Key,
+    dispatcher::{Dispatcher, DispatcherService},
+    error::{
+        api::ApiError, init::InitError, internal::InternalError,
+        invalid_req::ContentNotSupported,
+    },
+    types::{
+        extensions::MapperContext, provider::InferenceProvider,
+        request::Request, response::Response, router::RouterId,
+    },
+};
+
+const CHANNEL_CAPACITY: usize = 16;
+
+/// Tries the providers of a router one after the other until one of them
+/// does not respond with a server error or rate limit us.
+///
+/// Providers are skipped while the health monitor considers them unhealthy,
+/// i.e. while their circuit is open, unless every provider is unhealthy, in
+/// which case all of them are tried rather than failing outright.
+///
+/// With [`StreamFailoverConfig`], a streamed response is also failed over if
+/// its stream fails before any of it was sent to the client.
+pub struct FailoverRouter {
+    /// In the order they are tried.
+    providers: NEVec<InferenceProvider>,
+    stream_failover: Option<StreamFailoverConfig>,
+    dispatchers: HashMap<InferenceProvider, DispatcherService>,
+    unhealthy: HashSet<InferenceProvider>,
+    /// Providers removed and re-added by the health and rate limit monitors.
+    events: Receiver<Change<Key, DispatcherService>>,
+}
+
+impl std::fmt::Debug for FailoverRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FailoverRouter")
+            .field("providers", &self.providers)
+            .field("stream_failover", &self.stream_failover)
+            .field("unhealthy", &self.unhealthy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FailoverRouter {
+    pub async fn new(
+        app_state: AppState,
+        router_id: RouterId,
+        router_config: Arc<RouterConfig>,
+        providers: NEVec<InferenceProvider>,
+        stream_failover: Option<StreamFailoverConfig>,
+    ) -> Result<Self, InitError> {
+        let (change_tx, change_rx) = channel(CHANNEL_CAPACITY);
+        let (rate_limit_tx, rate_limit_rx) = channel(CHANNEL_CAPACITY);
+        // providers are keyed like for the latency strategy, so the same
+        // monitors can be reused
+        app_state
+            .add_provider_latency_router_health_monitor(
+                router_id.clone(),
+                router_config.clone(),
+                change_tx.clone(),
+            )
+            .await;
+        app_state
+            .add_rate_limit_tx(router_id.clone(), rate_limit_tx)
+            .await;
+        app_state
+            .add_rate_limit_rx(router_id.clone(), rate_limit_rx)
+            .await;
+        app_state
+            .add_provider_latency_router_rate_limit_monitor(
+                router_id.clone(),
+                router_config.clone(),
+                change_tx,
+            )
+            .await;
+
+        let mut dispatchers = HashMap::default();
+        for provider in &providers {
+            let dispatcher = Dispatcher::new(
+                app_state.clone(),
+                &router_id,
+                &router_config,
+                provider.clone(),
+            )
+            .await?;
+            dispatchers.insert(provider.clone(), dispatcher);
+        }
+
+        Ok(Self {
+            providers,
+            stream_failover,
+            dispatchers,
+            unhealthy: HashSet::default(),
+            events: change_rx,
+        })
+    }
+
+    fn handle_change(&mut self, change: Change<Key, DispatcherService>) {
+        match change {
+            Change::Insert(key, dispatcher) => {
+                tracing::debug!(provider = %key.provider, "provider healthy again");
+                self.unhealthy.remove(&key.provider);
+                self.dispatchers.insert(key.provider, dispatcher);
+            }
+            Change::Remove(key) => {
+                tracing::debug!(provider = %key.provider, "skipping unhealthy provider");
+                self.unhealthy.insert(key.provider);
+            }
+        }
+    }
+
+    /// The dispatchers to try, in order.
+    fn candidates(&self) -> Vec<(InferenceProvider, DispatcherService)> {
+        let healthy = self
+            .providers
+            .iter()
+            .filter(|provider| !self.unhealthy.contains(*provider))
+            .collect::<Vec<_>>();
+        let providers = if healthy.is_empty() {
+            self.providers.iter().collect()
+        } else {
+            healthy
+        };
+        providers
+            .into_iter()
+            .filter_map(|provider| {
+                let dispatcher = self.dispatchers.get(provider)?.clone();
+                Some((provider.clone(), dispatcher))
+            })
+            .collect()
+    }
+}
+
+impl tower::Service<Request> for FailoverRouter {
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Response, ApiError>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        while let Poll::Ready(Some(change)) = self.events.poll_recv(cx) {
+            self.handle_change(change);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    #[tracing::instrument(name = "failover", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        let mut candidates = self.candidates();
+        let stream_failover = self.stream_failover;
+        let span = tracing::Span::current();
+        Box::pin(
+            async move {
+                let last = candidates.pop().ok_or(InternalError::Internal)?;
+                let (parts, body) = req.into_parts();
+                let body = body
+                    .collect()
+                    .await
+                    .map_err(InternalError::CollectBodyError)?
+                    .to_bytes();
+                let request = |body: bytes::Bytes| {
+                    let mut request =
+                        Request::new(axum_core::body::Body::from(body));
+                    *request.method_mut() = parts.method.clone();
+                    *request.uri_mut() = parts.uri.clone();
+                    *request.version_mut() = parts.version;
+                    *request.headers_mut() = parts.headers.clone();
+                    *request.extensions_mut() = parts.extensions.clone();
+                    request
+                };
+
+                for (provider, dispatcher) in candidates {
+                    let sent_at = Instant::now();
+                    let Ok(response) =
+                        dispatcher.oneshot(request(body.clone())).await;
+                    if should_fail_over(&response) {
+                        tracing::warn!(
+                            provider = %provider,
+                            status = %response.status(),
+                            "provider failed, failing over to next provider"
+                        );
+                        continue;
+                    }
+                    let is_stream = response
+                        .extensions()
+                        .get::<MapperContext>()
+                        .is_some_and(|mapper_ctx| mapper_ctx.is_stream);
+                    let Some(stream_failover) =
+                        stream_failover.filter(|_| is_stream)
+                    else {
+                        return Ok(response);
+                    };
+                    match hold_back_stream_start(
+                        response,
+                        sent_at + stream_failover.window,
+                    )
+                    .await
+                    {
+                        Ok(response) => return Ok(response),
+                        Err(error) => tracing::warn!(
+                            provider = %provider,
+                            error = %error,
+                            "stream failed before it was forwarded, failing over \
+                             to next provider"
+                        ),
+                    }
+                }
+                let (_, dispatcher) = last;
+                let Ok(response) = dispatcher.oneshot(request(body)).await;
+                Ok(response)
+            }
+            .instrument(span),
+        )
+    }
+}
+
+/// Receive the chunks of a streamed `response` until its first chunk was
+/// received and `until` has passed, and put them back in front of the rest of
+/// the stream.
+///
+/// # Errors
+/// The error of the stream if it failed before then, in which case none of
+/// it was sent to the client.
+async fn hold_back_stream_start(
+    response: Response,
+    until: Instant,
+) -> Result<Response, axum_core::Error> {
+    let (parts, body) = response.into_parts();
+    let mut stream = body.into_data_stream();
+    let mut held_back: Vec<Bytes> = Vec::new();
+    loop {
+        let next = if held_back.is_empty() {
+            stream.next().await
+        } else {
+            match tokio::time::timeout_at(until, stream.next()).await {
+                Ok(next) => next,
+                Err(_elapsed) => break,
+            }
+        };
+        match next {
+            Some(Ok(chunk)) => held_back.push(chunk),
+            Some(Err(e)) => return Err(e),
+            None => break,
+        }
+    }
+
+    let stream =
+        futures::stream::iter(held_back.into_iter().map(Ok)).chain(stream);
+    Ok(Response::from_parts(
+        parts,
+        axum_core::body::Body::from_stream(stream),
+    ))
+}
+
+/// Whether another provider might succeed where the one that sent `response`
+/// failed, including when its model does not support the content of the
+/// request, e.g. images for a model that is not vision capable, and when a
+/// budget of the provider is exhausted.
+pub(crate) fn should_fail_over(response: &Response) -> bool {
+    let status = response.status();
+    status.is_server_error()
+        || status == StatusCode::TOO_MANY_REQUESTS
+        || status == StatusCode::PAYMENT_REQUIRED
+        || response.extensions().get::<ContentNotSupported>().is_some()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http_body_util::BodyExt;
+
+    use super::*;
+
+    fn stream_response(
+        chunks: Vec<Result<&'static str, std::io::Error>>,
+    ) -> Response {
+        Response::new(axum_core::body::Body::from_stream(
+            futures::stream::iter(chunks),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_held_back_chunks_are_forwarded() {
+        let response =
+            stream_response(vec![Ok("data: a\n\n"), Ok("data: b\n\n")]);
+        let response = hold_back_stream_start(
+            response,
+            Instant::now() + Duration::from_millis(10),
+        )
+        .await
+        .unwrap();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        assert_eq!(body, "data: a\n\ndata: b\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_failure_before_window_passed() {
+        let response = stream_response(vec![
+            Ok("data: a\n\n"),
+            Err(std::io::Error::other("connection reset")),
+        ]);
+        let result = hold_back_stream_start(
+            response,
+            Instant::now() + Duration::from_secs(10),
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_failure_after_window_passed_is_forwarded() {
+        let delayed_error = futures::stream::once(async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            Err::<&'static str, _>(std::io::Error::other("connection reset"))
+        });
+        let response = Response::new(axum_core::body::Body::from_stream(
+            futures::stream::iter(vec![Ok("data: a\n\n")]).chain(delayed_error),
+        ));
+        let response = hold_back_stream_start(response, Instant::now())
+            .await
+            .unwrap();
+        assert!(response.into_body().collect().await.is_err());
+    }
+}
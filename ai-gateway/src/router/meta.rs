@@ -68,6 +68,7 @@ impl MetaRouter {
             .layer(AsyncRequireAuthorizationLayer::new(
                 crate::middleware::auth::AuthService::new(app_state.clone()),
             ))
+            .layer(crate::virtual_keys::scope::Layer)
             .layer(RateLimitLayer::global(&app_state)?)
             .layer(CacheLayer::global(&app_state)?)
             .layer(ErrorHandlerLayer::new(app_state.clone()))
@@ -106,7 +107,10 @@ impl MetaRouter {
         let discovery_factory = RouterDiscoverFactory::new(app_state.clone());
         let mut router_factory =
             dynamic_router::router::make::MakeRouter::new(discovery_factory);
-        let dynamic_router = router_factory.call(None).await?;
+        // routers changed by reloading the config file
+        let (tx, rx) = tokio::sync::mpsc::channel(100);
+        app_state.set_router_tx(tx).await;
+        let dynamic_router = router_factory.call(Some(rx)).await?;
         let unified_api = ServiceBuilder::new()
             .layer(RateLimitLayer::unified_api(&app_state)?)
             .layer(CacheLayer::unified_api(&app_state)?)
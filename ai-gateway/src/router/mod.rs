@@ -1,10 +1,30 @@
+pub mod alias;
 pub mod direct;
+pub mod failover;
 pub mod latency;
 pub mod meta;
+pub mod overrides;
+pub mod queue;
 pub mod router_details;
 pub mod service;
 pub mod strategy;
+pub mod traffic_split;
 pub mod unified_api;
 
 pub(in crate::router) const FORCED_ROUTING_HEADER: http::HeaderName =
     http::HeaderName::from_static("helicone-forced-routing");
+/// Pins a request to a provider the router allows overrides for.
+pub const PROVIDER_OVERRIDE_HEADER: http::HeaderName =
+    http::HeaderName::from_static("x-gateway-provider");
+/// Pins a request to a model the router allows overrides for, either of the
+/// provider in [`PROVIDER_OVERRIDE_HEADER`] or as `{provider}/{model}`.
+pub const MODEL_OVERRIDE_HEADER: http::HeaderName =
+    http::HeaderName::from_static("x-gateway-model");
+/// The [`Priority`](queue::Priority) of a request in the queue of the router,
+/// one of `low`, `normal` or `high`.
+pub const PRIORITY_HEADER: http::HeaderName =
+    http::HeaderName::from_static("x-gateway-priority");
+/// Set on the responses of traffic split routers to the side that served the
+/// request, `baseline` or `candidate`.
+pub const SPLIT_HEADER: http::HeaderName =
+    http::HeaderName::from_static("x-gateway-split");
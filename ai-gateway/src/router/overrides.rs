@@ -0,0 +1,247 @@
+use std::{
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::future::BoxFuture;
+use http::HeaderName;
+use rustc_hash::FxHashMap as HashMap;
+use tower::{Layer as _, ServiceExt};
+
+use crate::{
+    app_state::AppState,
+    config::router::RouterConfig,
+    dispatcher::{Dispatcher, DispatcherService},
+    error::{api::ApiError, init::InitError, invalid_req::InvalidRequestError},
+    middleware::request_context,
+    router::{MODEL_OVERRIDE_HEADER, PROVIDER_OVERRIDE_HEADER},
+    types::{
+        model_id::ModelId, provider::InferenceProvider, request::Request,
+        response::Response, router::RouterId,
+    },
+};
+
+type TargetService = request_context::Service<DispatcherService>;
+
+#[derive(Debug, Clone)]
+struct ProviderOverride {
+    /// Sends requests to the provider, mapping their model as usual.
+    dispatcher: TargetService,
+    /// Always sends the request to the model, keyed by the model name as
+    /// written in the allowlist.
+    models: HashMap<String, TargetService>,
+}
+
+type Overrides = HashMap<InferenceProvider, ProviderOverride>;
+
+/// Pins requests with an `x-gateway-provider` and/or `x-gateway-model`
+/// header to that provider and model, bypassing the load balancing strategy
+/// and model aliases of the router.
+///
+/// Only the providers and models in the `allow-overrides` lists of the
+/// router's provider configs can be pinned, any other override is rejected.
+/// Requests without either header are passed through.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    overrides: Arc<Overrides>,
+}
+
+impl Layer {
+    pub async fn for_router(
+        app_state: &AppState,
+        router_id: &RouterId,
+        router_config: &Arc<RouterConfig>,
+    ) -> Result<Self, InitError> {
+        let request_context_layer =
+            request_context::Layer::for_router(router_config.clone());
+        let mut overrides = HashMap::default();
+        let allowlists = router_config.providers.iter().flatten().filter_map(
+            |(provider, config)| {
+                config
+                    .allow_overrides
+                    .as_ref()
+                    .map(|models| (provider, models))
+            },
+        );
+        for (provider, models) in allowlists {
+            let dispatcher = Dispatcher::new(
+                app_state.clone(),
+                router_id,
+                router_config,
+                provider.clone(),
+            )
+            .await?;
+            let mut model_dispatchers = HashMap::default();
+            for model in models {
+                let model_id =
+                    ModelId::from_str_and_provider(provider.clone(), model)
+                        .map_err(|_| {
+                            InitError::ModelIdNotRecognized(model.clone())
+                        })?;
+                let dispatcher = Dispatcher::new_with_model_id(
+                    app_state.clone(),
+                    router_id,
+                    router_config,
+                    provider.clone(),
+                    model_id,
+                )
+                .await?;
+                model_dispatchers.insert(
+                    model.clone(),
+                    request_context_layer.layer(dispatcher),
+                );
+            }
+            overrides.insert(
+                provider.clone(),
+                ProviderOverride {
+                    dispatcher: request_context_layer.layer(dispatcher),
+                    models: model_dispatchers,
+                },
+            );
+        }
+
+        Ok(Self {
+            overrides: Arc::new(overrides),
+        })
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            overrides: self.overrides.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    overrides: Arc<Overrides>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request, Response = Response, Error = ApiError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "overrides", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        let header = |name: &HeaderName| {
+            req.headers()
+                .get(name)
+                .map(|value| value.to_str().map(str::to_string))
+                .transpose()
+        };
+        let (provider, model) = match (
+            header(&PROVIDER_OVERRIDE_HEADER),
+            header(&MODEL_OVERRIDE_HEADER),
+        ) {
+            (Ok(None), Ok(None)) => return Box::pin(self.inner.call(req)),
+            (Ok(provider), Ok(model)) => (provider, model),
+            (Err(e), _) | (_, Err(e)) => {
+                return Box::pin(async move {
+                    Err(InvalidRequestError::InvalidRequestHeader(e).into())
+                });
+            }
+        };
+        let target = resolve(&self.overrides, provider, model);
+        Box::pin(async move {
+            let target = target?;
+            let Ok(response) = target.oneshot(req).await;
+            Ok(response)
+        })
+    }
+}
+
+/// The service for the pinned `provider` and `model`, if the router allows
+/// them.
+///
+/// Without a provider the model must be given as `{provider}/{model}`.
+fn resolve(
+    overrides: &Overrides,
+    provider: Option<String>,
+    model: Option<String>,
+) -> Result<TargetService, InvalidRequestError> {
+    let (provider, model, provider_header) = if let Some(provider) = provider {
+        (provider, model, PROVIDER_OVERRIDE_HEADER.as_str())
+    } else {
+        let model = model.unwrap_or_default();
+        let Some((provider, model)) = model.split_once('/') else {
+            return Err(not_allowed(
+                MODEL_OVERRIDE_HEADER.as_str(),
+                model,
+                overrides.iter().flat_map(|(provider, config)| {
+                    config
+                        .models
+                        .keys()
+                        .map(move |model| format!("{provider}/{model}"))
+                }),
+            ));
+        };
+        (
+            provider.to_string(),
+            Some(model.to_string()),
+            MODEL_OVERRIDE_HEADER.as_str(),
+        )
+    };
+
+    let Ok(inference_provider) = provider.parse::<InferenceProvider>();
+    let Some(provider_override) = overrides.get(&inference_provider) else {
+        return Err(not_allowed(
+            provider_header,
+            provider,
+            overrides.keys().map(ToString::to_string),
+        ));
+    };
+    let Some(model) = model else {
+        tracing::debug!(provider = %inference_provider, "pinned provider");
+        return Ok(provider_override.dispatcher.clone());
+    };
+    if let Some(target) = provider_override.models.get(&model) {
+        tracing::debug!(provider = %inference_provider, model, "pinned model");
+        Ok(target.clone())
+    } else {
+        Err(not_allowed(
+            MODEL_OVERRIDE_HEADER.as_str(),
+            model,
+            provider_override.models.keys().cloned(),
+        ))
+    }
+}
+
+fn not_allowed(
+    header: &'static str,
+    value: String,
+    permitted: impl Iterator<Item = String>,
+) -> InvalidRequestError {
+    let mut permitted = permitted.collect::<Vec<_>>();
+    permitted.sort_unstable();
+    InvalidRequestError::OverrideNotAllowed {
+        header,
+        value,
+        permitted: if permitted.is_empty() {
+            "none".to_string()
+        } else {
+            permitted.join(", ")
+        },
+    }
+}
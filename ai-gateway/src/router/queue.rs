@@ -0,0 +1,457 @@
+//! Queueing the requests of a router towards its providers.
+//!
+//! A router with a [`QueueConfig`] limits the number of requests in flight to
+//! each of its providers. Requests over the limit wait in a queue shared by
+//! all providers of the router and are sent as earlier requests complete,
+//! highest [`Priority`] first, then in order of arrival.
+//!
+//! Once the queue is full, a request sheds the newest waiting request with a
+//! lower priority, or is shed itself if there is none. Shed requests, like
+//! requests that waited longer than the `timeout` of the queue, are answered
+//! with a `503` and the `retry-after` of the queue.
+use std::{
+    cmp::Reverse,
+    num::NonZeroUsize,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll},
+};
+
+use rustc_hash::FxHashMap as HashMap;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+use crate::{
+    config::{queue::QueueConfig, router::RouterConfig},
+    error::api::ApiError,
+    router::PRIORITY_HEADER,
+    types::{provider::InferenceProvider, request::Request},
+    virtual_keys::VirtualKey,
+};
+
+/// The priority of a request in the queue of a router.
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Deserialize,
+    Serialize,
+    strum::Display,
+    strum::EnumString,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+impl Priority {
+    /// The priority of the `x-gateway-priority` header of the request, or of
+    /// its virtual key if it has none.
+    ///
+    /// The priority of a virtual key is also the highest one its requests
+    /// can ask for.
+    fn of_request(req: &Request) -> Self {
+        let requested = req
+            .headers()
+            .get(&PRIORITY_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| {
+                value
+                    .parse::<Self>()
+                    .inspect_err(|_| {
+                        tracing::debug!(
+                            priority = value,
+                            "invalid priority header"
+                        );
+                    })
+                    .ok()
+            });
+        let key_priority = req
+            .extensions()
+            .get::<VirtualKey>()
+            .and_then(|virtual_key| virtual_key.priority);
+        match (requested, key_priority) {
+            (Some(requested), Some(key_priority)) => {
+                requested.min(key_priority)
+            }
+            (Some(priority), None) | (None, Some(priority)) => priority,
+            (None, None) => Self::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Waiter {
+    provider: InferenceProvider,
+    priority: Priority,
+    seq: u64,
+    /// Dropping the sender sheds the request.
+    tx: oneshot::Sender<Permit>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    in_flight: HashMap<InferenceProvider, usize>,
+    waiting: Vec<Waiter>,
+    next_seq: u64,
+}
+
+/// The queue of a router, see the [module docs](self).
+///
+/// Cloning it shares the queue.
+#[derive(Debug, Clone)]
+pub struct RequestQueue {
+    config: Arc<QueueConfig>,
+    max_concurrency: Arc<HashMap<InferenceProvider, NonZeroUsize>>,
+    state: Arc<Mutex<State>>,
+}
+
+impl RequestQueue {
+    #[must_use]
+    pub fn for_router(router_config: &RouterConfig) -> Option<Self> {
+        let config = router_config.queue.clone()?;
+        let max_concurrency = router_config
+            .providers
+            .iter()
+            .flatten()
+            .filter_map(|(provider, config)| {
+                config
+                    .max_concurrency
+                    .map(|max_concurrency| (provider.clone(), max_concurrency))
+            })
+            .collect();
+        Some(Self {
+            config: Arc::new(config),
+            max_concurrency: Arc::new(max_concurrency),
+            state: Arc::default(),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    fn max_concurrency(&self, provider: &InferenceProvider) -> usize {
+        self.max_concurrency
+            .get(provider)
+            .copied()
+            .unwrap_or(self.config.max_concurrency)
+            .get()
+    }
+
+    fn permit(&self, provider: &InferenceProvider) -> Permit {
+        Permit {
+            queue: self.clone(),
+            provider: provider.clone(),
+        }
+    }
+
+    fn shed(&self) -> ApiError {
+        ApiError::Overloaded {
+            retry_after: self.config.retry_after.as_secs().max(1),
+        }
+    }
+
+    /// Wait for a request to `provider` to be allowed, which lasts as long
+    /// as the returned [`Permit`].
+    pub async fn acquire(
+        &self,
+        provider: &InferenceProvider,
+        priority: Priority,
+    ) -> Result<Permit, ApiError> {
+        let (seq, mut rx) = {
+            let mut state = self.lock();
+            let in_flight =
+                state.in_flight.entry(provider.clone()).or_default();
+            if *in_flight < self.max_concurrency(provider) {
+                *in_flight += 1;
+                return Ok(self.permit(provider));
+            }
+            // requests whose clients went away don't take up room
+            state.waiting.retain(|waiter| !waiter.tx.is_closed());
+            if state.waiting.len() >= self.config.max_size {
+                let lower = state
+                    .waiting
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, waiter)| waiter.priority < priority)
+                    .min_by_key(|(_, waiter)| {
+                        (waiter.priority, Reverse(waiter.seq))
+                    })
+                    .map(|(index, _)| index);
+                let Some(lower) = lower else {
+                    tracing::debug!(provider = %provider, priority = %priority, "queue full, shedding request");
+                    return Err(self.shed());
+                };
+                let shed = state.waiting.swap_remove(lower);
+                tracing::debug!(provider = %shed.provider, priority = %shed.priority, "queue full, shedding lower priority request");
+            }
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            let (tx, rx) = oneshot::channel();
+            state.waiting.push(Waiter {
+                provider: provider.clone(),
+                priority,
+                seq,
+                tx,
+            });
+            (seq, rx)
+        };
+
+        match tokio::time::timeout(self.config.timeout, &mut rx).await {
+            Ok(Ok(permit)) => Ok(permit),
+            Ok(Err(_)) => Err(self.shed()),
+            Err(_) => {
+                self.lock().waiting.retain(|waiter| waiter.seq != seq);
+                tracing::debug!(provider = %provider, priority = %priority, "timed out in queue");
+                // the permit may have been granted just before the timeout
+                rx.try_recv().map_err(|_| self.shed())
+            }
+        }
+    }
+
+    /// Hands the slot of a completed request to the next waiting request to
+    /// the same provider, if any.
+    fn release(&self, provider: &InferenceProvider) {
+        let next = {
+            let mut state = self.lock();
+            state.waiting.retain(|waiter| !waiter.tx.is_closed());
+            let next = state
+                .waiting
+                .iter()
+                .enumerate()
+                .filter(|(_, waiter)| &waiter.provider == provider)
+                .max_by_key(|(_, waiter)| {
+                    (waiter.priority, Reverse(waiter.seq))
+                })
+                .map(|(index, _)| index);
+            if let Some(next) = next {
+                Some(state.waiting.swap_remove(next))
+            } else {
+                if let Some(in_flight) = state.in_flight.get_mut(provider) {
+                    *in_flight = in_flight.saturating_sub(1);
+                }
+                None
+            }
+        };
+        // if the request went away in the meantime the permit is dropped
+        // again, outside of the lock
+        if let Some(next) = next {
+            let _ = next.tx.send(self.permit(provider));
+        }
+    }
+}
+
+/// A slot for a request in flight to a provider, freed on drop.
+#[derive(Debug)]
+pub struct Permit {
+    queue: RequestQueue,
+    provider: InferenceProvider,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        self.queue.release(&self.provider);
+    }
+}
+
+/// Adds the [`RequestQueue`] of the router and the [`Priority`] of the
+/// request to the extensions of requests, for the dispatchers to wait on.
+#[derive(Debug, Clone)]
+pub struct Layer {
+    queue: Option<RequestQueue>,
+}
+
+impl Layer {
+    #[must_use]
+    pub fn for_router(router_config: &RouterConfig) -> Self {
+        Self {
+            queue: RequestQueue::for_router(router_config),
+        }
+    }
+}
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service {
+            inner,
+            queue: self.queue.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+    queue: Option<RequestQueue>,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request) -> Self::Future {
+        if let Some(queue) = &self.queue {
+            let priority = Priority::of_request(&req);
+            req.extensions_mut().insert(queue.clone());
+            req.extensions_mut().insert(priority);
+        }
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    fn queue(max_concurrency: usize, max_size: usize) -> RequestQueue {
+        RequestQueue::for_router(&RouterConfig {
+            queue: Some(QueueConfig {
+                max_concurrency: NonZeroUsize::new(max_concurrency).unwrap(),
+                max_size,
+                timeout: Duration::from_millis(100),
+                retry_after: Duration::from_secs(2),
+            }),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    fn is_shed(result: Result<Permit, ApiError>) -> bool {
+        matches!(result, Err(ApiError::Overloaded { retry_after: 2 }))
+    }
+
+    #[tokio::test]
+    async fn waiting_requests_are_sent_by_priority() {
+        let queue = queue(1, 10);
+        let provider = InferenceProvider::OpenAI;
+        let permit = queue.acquire(&provider, Priority::Normal).await.unwrap();
+
+        let low = tokio::spawn({
+            let queue = queue.clone();
+            let provider = provider.clone();
+            async move { queue.acquire(&provider, Priority::Low).await }
+        });
+        tokio::task::yield_now().await;
+        let high = tokio::spawn({
+            let queue = queue.clone();
+            let provider = provider.clone();
+            async move { queue.acquire(&provider, Priority::High).await }
+        });
+        tokio::task::yield_now().await;
+
+        drop(permit);
+        let high = high.await.unwrap().unwrap();
+        assert!(!low.is_finished());
+        drop(high);
+        low.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn other_providers_are_not_limited() {
+        let queue = queue(1, 0);
+        let _permit = queue
+            .acquire(&InferenceProvider::OpenAI, Priority::Normal)
+            .await
+            .unwrap();
+        queue
+            .acquire(&InferenceProvider::Anthropic, Priority::Normal)
+            .await
+            .unwrap();
+        assert!(is_shed(
+            queue
+                .acquire(&InferenceProvider::OpenAI, Priority::High)
+                .await
+        ));
+    }
+
+    #[tokio::test]
+    async fn full_queue_sheds_lowest_priority() {
+        let queue = queue(1, 1);
+        let provider = InferenceProvider::OpenAI;
+        let _permit = queue.acquire(&provider, Priority::Normal).await.unwrap();
+
+        let low = tokio::spawn({
+            let queue = queue.clone();
+            let provider = provider.clone();
+            async move { queue.acquire(&provider, Priority::Low).await }
+        });
+        tokio::task::yield_now().await;
+        let normal = tokio::spawn({
+            let queue = queue.clone();
+            let provider = provider.clone();
+            async move { queue.acquire(&provider, Priority::Normal).await }
+        });
+        tokio::task::yield_now().await;
+
+        assert!(is_shed(low.await.unwrap()));
+        // the queue is full of requests of the same priority
+        assert!(is_shed(queue.acquire(&provider, Priority::Normal).await));
+        // and the waiting request times out
+        assert!(is_shed(normal.await.unwrap()));
+    }
+
+    #[test]
+    fn priority_is_capped_by_virtual_key() {
+        let request = |priority: Option<&str>, key: Option<Priority>| {
+            let mut builder = http::Request::builder();
+            if let Some(priority) = priority {
+                builder = builder.header(&PRIORITY_HEADER, priority);
+            }
+            let mut req =
+                builder.body(crate::types::body::Body::empty()).unwrap();
+            if let Some(key_priority) = key {
+                req.extensions_mut().insert(VirtualKey {
+                    id: uuid::Uuid::now_v7(),
+                    key_hash: String::new(),
+                    name: None,
+                    routers: None,
+                    models: None,
+                    spend_limit_usd: None,
+                    spent_usd: rust_decimal::Decimal::ZERO,
+                    expires_at: None,
+                    priority: Some(key_priority),
+                    created_at: chrono::Utc::now(),
+                    revoked_at: None,
+                });
+            }
+            Priority::of_request(&req)
+        };
+        assert_eq!(request(None, None), Priority::Normal);
+        assert_eq!(request(Some("high"), None), Priority::High);
+        assert_eq!(request(Some("urgent"), None), Priority::Normal);
+        assert_eq!(request(None, Some(Priority::Low)), Priority::Low);
+        assert_eq!(
+            request(Some("high"), Some(Priority::Normal)),
+            Priority::Normal
+        );
+        assert_eq!(request(Some("low"), Some(Priority::High)), Priority::Low);
+    }
+}
@@ -21,9 +21,13 @@ use crate::{
         invalid_req::InvalidRequestError,
     },
     middleware::{
-        cache::CacheLayer, prompts::PromptLayer, rate_limit, request_context,
+        cache::CacheLayer, guardrails::GuardrailLayer, prompts::PromptLayer,
+        rate_limit, request_context,
+    },
+    router::{
+        alias, meta::MIDDLEWARE_BUFFER_SIZE, overrides, queue,
+        strategy::RoutingStrategyService,
     },
-    router::{meta::MIDDLEWARE_BUFFER_SIZE, strategy::RoutingStrategyService},
     types::router::RouterId,
     utils::handle_error::ErrorHandlerLayer,
 };
@@ -58,9 +62,16 @@ impl Router {
         )
         .await?;
         let prompt_layer = PromptLayer::new(&app_state)?;
+        let guardrail_layer = GuardrailLayer::for_router(&router_config)?;
         let cache_layer = CacheLayer::for_router(&app_state, &router_config)?;
         let request_context_layer =
             request_context::Layer::for_router(router_config.clone());
+        let overrides_layer =
+            overrides::Layer::for_router(&app_state, &id, &router_config)
+                .await?;
+        let alias_layer =
+            alias::Layer::for_router(&app_state, &id, &router_config).await?;
+        let queue_layer = queue::Layer::for_router(&router_config);
         for (endpoint_type, balance_config) in
             router_config.load_balance.as_ref()
         {
@@ -74,9 +85,13 @@ impl Router {
             let service_stack = ServiceBuilder::new()
                 .layer(ErrorHandlerLayer::new(app_state.clone()))
                 .layer(prompt_layer.clone())
+                .layer(guardrail_layer.clone())
                 .layer(cache_layer.clone())
                 .layer(ErrorHandlerLayer::new(app_state.clone()))
                 .layer(rl_layer.clone())
+                .layer(queue_layer.clone())
+                .layer(overrides_layer.clone())
+                .layer(alias_layer.clone())
                 .map_err(|e| ApiError::from(InternalError::BufferError(e)))
                 .layer(buffer::BufferLayer::new(MIDDLEWARE_BUFFER_SIZE))
                 .layer(request_context_layer.clone())
@@ -118,7 +133,11 @@ impl tower::Service<crate::types::request::Request> for Router {
     }
 
     #[inline]
-    #[tracing::instrument(level = "debug", name = "router", skip_all)]
+    #[tracing::instrument(
+        name = "router",
+        skip_all,
+        fields(endpoint_type = tracing::field::Empty)
+    )]
     fn call(
         &mut self,
         mut req: crate::types::request::Request,
@@ -138,6 +157,8 @@ impl tower::Service<crate::types::request::Request> for Router {
         let api_endpoint = ApiEndpoint::new(extracted_path_and_query.path());
         if let Some(api_endpoint) = api_endpoint {
             let endpoint_type = api_endpoint.endpoint_type();
+            tracing::Span::current()
+                .record("endpoint_type", tracing::field::debug(endpoint_type));
             if let Some(balancer) = self.inner.get_mut(&endpoint_type) {
                 req.extensions_mut().insert(api_endpoint);
                 ResponseFuture::Inner {
@@ -6,7 +6,11 @@ use std::{
 use futures::{Future, ready};
 use pin_project_lite::pin_project;
 use tokio::sync::mpsc::channel;
-use tower::{Service, balance::p2c::Balance, load::PeakEwmaDiscover};
+use tower::{
+    Service,
+    balance::p2c::Balance,
+    load::{CompleteOnResponse, PeakEwmaDiscover, PendingRequestsDiscover},
+};
 use weighted_balance::{balance::WeightedBalance, weight::WeightedDiscover};
 
 use crate::{
@@ -17,7 +21,10 @@ use crate::{
         model, provider,
     },
     error::{api::ApiError, init::InitError, internal::InternalError},
-    router::latency::LatencyRouter,
+    router::{
+        failover::FailoverRouter, latency::LatencyRouter,
+        traffic_split::TrafficSplitRouter,
+    },
     types::{request::Request, response::Response, router::RouterId},
 };
 
@@ -40,6 +47,19 @@ pub enum RoutingStrategyService {
     ),
     /// Strategy:
     /// 1. receive request
+    /// 2. pick two random providers
+    /// 3. compare their number of in-flight requests, pick the lower one
+    /// 4. if provider with fewest in-flight requests does not have requested
+    ///    model, map it to a model offered by the target provider.
+    /// 5. send request
+    ProviderLeastPendingP2C(
+        Balance<
+            PendingRequestsDiscover<DispatcherDiscovery<provider::key::Key>>,
+            Request,
+        >,
+    ),
+    /// Strategy:
+    /// 1. receive request
     /// 2. according to configured weighted distribution, randomly sample a
     ///    single provider from the set of providers.
     /// 3. if the provider does not have requested model, map it to a model
@@ -72,6 +92,18 @@ pub enum RoutingStrategyService {
     /// 3. pick the lowest latency provider that serves the requested model
     /// 4. send request
     ModelLatency(LatencyRouter),
+    /// Strategy:
+    /// 1. receive request
+    /// 2. send it to the first provider in the configured order that is not
+    ///    unhealthy, mapping the model to one offered by the provider.
+    /// 3. on a server error or rate limit, retry with the next provider
+    Failover(FailoverRouter),
+    /// Strategy:
+    /// 1. receive request
+    /// 2. bucket it on the configured header or its API key
+    /// 3. send it to the candidate model if its bucket is within the candidate
+    ///    percentage, and to the baseline model otherwise
+    TrafficSplit(TrafficSplitRouter),
 }
 
 impl RoutingStrategyService {
@@ -90,6 +122,14 @@ impl RoutingStrategyService {
                 Self::provider_latency(app_state, router_id, router_config)
                     .await
             }
+            BalanceConfigInner::LeastPending { .. } => {
+                Self::provider_least_pending(
+                    app_state,
+                    router_id,
+                    router_config,
+                )
+                .await
+            }
             BalanceConfigInner::ModelWeighted { .. } => {
                 Self::model_weighted(app_state, router_id, router_config).await
             }
@@ -98,6 +138,34 @@ impl RoutingStrategyService {
                     .await
                     .map(Self::ModelLatency)
             }
+            BalanceConfigInner::Failover {
+                providers,
+                stream_failover,
+            } => FailoverRouter::new(
+                app_state,
+                router_id,
+                router_config,
+                providers.clone(),
+                *stream_failover,
+            )
+            .await
+            .map(Self::Failover),
+            BalanceConfigInner::TrafficSplit {
+                baseline,
+                candidate,
+                candidate_percent,
+                bucket_header,
+            } => TrafficSplitRouter::new(
+                app_state,
+                router_id,
+                router_config,
+                baseline.clone(),
+                candidate.clone(),
+                *candidate_percent,
+                bucket_header.as_deref(),
+            )
+            .await
+            .map(Self::TrafficSplit),
         }
     }
 
@@ -225,6 +293,54 @@ impl RoutingStrategyService {
 
         Ok(provider_balancer)
     }
+
+    async fn provider_least_pending(
+        app_state: AppState,
+        router_id: RouterId,
+        router_config: Arc<RouterConfig>,
+    ) -> Result<RoutingStrategyService, InitError> {
+        tracing::debug!("creating provider least pending routing strategy");
+        let (change_tx, change_rx) = channel(CHANNEL_CAPACITY);
+        let (rate_limit_tx, rate_limit_rx) = channel(CHANNEL_CAPACITY);
+        // the monitors only depend on the providers being keyed like for the
+        // latency strategy, not on how the balancer picks between them
+        app_state
+            .add_provider_latency_router_health_monitor(
+                router_id.clone(),
+                router_config.clone(),
+                change_tx.clone(),
+            )
+            .await;
+        app_state
+            .add_rate_limit_tx(router_id.clone(), rate_limit_tx)
+            .await;
+        app_state
+            .add_rate_limit_rx(router_id.clone(), rate_limit_rx)
+            .await;
+        app_state
+            .add_provider_latency_router_rate_limit_monitor(
+                router_id.clone(),
+                router_config.clone(),
+                change_tx,
+            )
+            .await;
+        let discovery = DispatcherDiscovery::<provider::key::Key>::new(
+            &app_state,
+            &router_id,
+            &router_config,
+            change_rx,
+        )
+        .await?;
+        let discovery = PendingRequestsDiscover::new(
+            discovery,
+            CompleteOnResponse::default(),
+        );
+        let provider_balancer = RoutingStrategyService::ProviderLeastPendingP2C(
+            Balance::new(discovery),
+        );
+
+        Ok(provider_balancer)
+    }
 }
 
 impl tower::Service<Request> for RoutingStrategyService {
@@ -241,6 +357,9 @@ impl tower::Service<Request> for RoutingStrategyService {
             RoutingStrategyService::ProviderLatencyPeakEwmaP2C(inner) => {
                 inner.poll_ready(cx)
             }
+            RoutingStrategyService::ProviderLeastPendingP2C(inner) => {
+                inner.poll_ready(cx)
+            }
             RoutingStrategyService::WeightedProvider(inner) => {
                 inner.poll_ready(cx)
             }
@@ -250,6 +369,12 @@ impl tower::Service<Request> for RoutingStrategyService {
             RoutingStrategyService::ModelLatency(inner) => {
                 return inner.poll_ready(cx);
             }
+            RoutingStrategyService::Failover(inner) => {
+                return inner.poll_ready(cx);
+            }
+            RoutingStrategyService::TrafficSplit(inner) => {
+                return inner.poll_ready(cx);
+            }
         }
         .map_err(InternalError::PollReadyError)
         .map_err(Into::into)
@@ -262,6 +387,11 @@ impl tower::Service<Request> for RoutingStrategyService {
                     future: inner.call(req),
                 }
             }
+            RoutingStrategyService::ProviderLeastPendingP2C(inner) => {
+                ResponseFuture::PendingRequests {
+                    future: inner.call(req),
+                }
+            }
             RoutingStrategyService::WeightedProvider(inner) => {
                 ResponseFuture::ProviderWeighted {
                     future: inner.call(req),
@@ -277,6 +407,16 @@ impl tower::Service<Request> for RoutingStrategyService {
                     future: inner.call(req),
                 }
             }
+            RoutingStrategyService::Failover(inner) => {
+                ResponseFuture::Failover {
+                    future: inner.call(req),
+                }
+            }
+            RoutingStrategyService::TrafficSplit(inner) => {
+                ResponseFuture::TrafficSplit {
+                    future: inner.call(req),
+                }
+            }
         }
     }
 }
@@ -292,6 +432,14 @@ pin_project! {
                 >
             >::Future,
         },
+        PendingRequests {
+            #[pin]
+            future: <
+                Balance<PendingRequestsDiscover<DispatcherDiscovery<provider::key::Key>>, Request> as tower::Service<
+                    Request,
+                >
+            >::Future,
+        },
         ProviderWeighted {
             #[pin]
             future: <
@@ -312,6 +460,14 @@ pin_project! {
             #[pin]
             future: <LatencyRouter as tower::Service<Request>>::Future,
         },
+        Failover {
+            #[pin]
+            future: <FailoverRouter as tower::Service<Request>>::Future,
+        },
+        TrafficSplit {
+            #[pin]
+            future: <TrafficSplitRouter as tower::Service<Request>>::Future,
+        },
     }
 }
 
@@ -329,6 +485,12 @@ impl Future for ResponseFuture {
                     .map_err(InternalError::LoadBalancerError)
                     .map_err(Into::into)
             )),
+            EnumProj::PendingRequests { future } => Poll::Ready(ready!(
+                future
+                    .poll(cx)
+                    .map_err(InternalError::LoadBalancerError)
+                    .map_err(Into::into)
+            )),
             EnumProj::ProviderWeighted { future }
             | EnumProj::ModelWeighted { future } => Poll::Ready(ready!(
                 future
@@ -339,6 +501,10 @@ impl Future for ResponseFuture {
             EnumProj::ModelLatency { future } => {
                 Poll::Ready(ready!(future.poll(cx)))
             }
+            EnumProj::Failover { future }
+            | EnumProj::TrafficSplit { future } => {
+                Poll::Ready(ready!(future.poll(cx)))
+            }
         }
     }
 }
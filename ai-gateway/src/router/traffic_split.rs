@@ -0,0 +1,416 @@
+use std::{
+    str::FromStr,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use http::{HeaderName, HeaderValue};
+use http_body_util::BodyExt;
+use opentelemetry::KeyValue;
+use rust_decimal::{Decimal, prelude::ToPrimitive};
+use rustc_hash::{FxHashMap as HashMap, FxHashSet as HashSet};
+use sha2::{Digest, Sha256};
+use tokio::{
+    sync::mpsc::{Receiver, channel},
+    time::Instant,
+};
+use tower::{ServiceExt, discover::Change};
+
+use crate::{
+    app_state::AppState,
+    config::router::RouterConfig,
+    discover::provider::key::Key,
+    dispatcher::{Dispatcher, DispatcherService},
+    error::{
+        api::ApiError, init::InitError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+    router::SPLIT_HEADER,
+    types::{
+        body::Body, extensions::RequestContext, model_id::ModelId,
+        provider::InferenceProvider, request::Request, response::Response,
+        router::RouterId,
+    },
+};
+
+const CHANNEL_CAPACITY: usize = 16;
+/// Requests are split into this many buckets, so the percentage of the
+/// candidate has a precision of 0.01.
+const BUCKETS: u64 = 10_000;
+
+/// The side of a [`TrafficSplitRouter`] that served a request, sent in the
+/// [`SPLIT_HEADER`] of the response.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, strum::AsRefStr, strum::IntoStaticStr,
+)]
+#[strum(serialize_all = "lowercase")]
+pub enum Split {
+    Baseline,
+    Candidate,
+}
+
+struct Target {
+    model: ModelId,
+    provider: InferenceProvider,
+    /// Always sends the request to `model`, regardless of the model of the
+    /// request.
+    dispatcher: DispatcherService,
+}
+
+/// Sends the requests for the baseline model in the first
+/// `candidate_percent` of the buckets to the candidate model and the rest to
+/// the baseline model. Requests for any other model of the baseline or
+/// candidate provider are passed through to it unchanged, and requests for
+/// the models of other providers are rejected.
+///
+/// The bucket of a request is the hash of its bucket key and the router, so
+/// a user keeps getting the same model, and raising the percentage only moves
+/// users from the baseline to the candidate.
+///
+/// While the provider of one of the models is unhealthy, all requests go to
+/// the other one.
+pub struct TrafficSplitRouter {
+    app_state: AppState,
+    router_id: RouterId,
+    baseline: Target,
+    candidate: Target,
+    /// Sends the requests for any other model than the baseline to the model
+    /// of the request, by provider.
+    pass_through: Arc<HashMap<InferenceProvider, DispatcherService>>,
+    candidate_buckets: u64,
+    bucket_header: Option<HeaderName>,
+    unhealthy: HashSet<InferenceProvider>,
+    /// Providers removed and re-added by the health and rate limit monitors.
+    events: Receiver<Change<Key, DispatcherService>>,
+}
+
+impl std::fmt::Debug for TrafficSplitRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TrafficSplitRouter")
+            .field("router_id", &self.router_id)
+            .field("baseline", &self.baseline.model)
+            .field("candidate", &self.candidate.model)
+            .field("candidate_buckets", &self.candidate_buckets)
+            .field("bucket_header", &self.bucket_header)
+            .field("unhealthy", &self.unhealthy)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TrafficSplitRouter {
+    pub async fn new(
+        app_state: AppState,
+        router_id: RouterId,
+        router_config: Arc<RouterConfig>,
+        baseline: ModelId,
+        candidate: ModelId,
+        candidate_percent: Decimal,
+        bucket_header: Option<&str>,
+    ) -> Result<Self, InitError> {
+        let bucket_header = bucket_header
+            .map(HeaderName::try_from)
+            .transpose()
+            .map_err(|e| {
+                InitError::InvalidBalancer(format!(
+                    "Invalid bucket header: {e}"
+                ))
+            })?;
+        let candidate_buckets = (candidate_percent
+            * Decimal::from(BUCKETS / 100))
+        .trunc()
+        .to_u64()
+        .filter(|buckets| *buckets <= BUCKETS)
+        .ok_or_else(|| {
+            InitError::InvalidBalancer(format!(
+                "Candidate percent must be between 0 and 100: \
+                 {candidate_percent}"
+            ))
+        })?;
+
+        let (change_tx, change_rx) = channel(CHANNEL_CAPACITY);
+        let (rate_limit_tx, rate_limit_rx) = channel(CHANNEL_CAPACITY);
+        // providers are keyed like for the latency strategy, so the same
+        // monitors can be reused
+        app_state
+            .add_provider_latency_router_health_monitor(
+                router_id.clone(),
+                router_config.clone(),
+                change_tx.clone(),
+            )
+            .await;
+        app_state
+            .add_rate_limit_tx(router_id.clone(), rate_limit_tx)
+            .await;
+        app_state
+            .add_rate_limit_rx(router_id.clone(), rate_limit_rx)
+            .await;
+        app_state
+            .add_provider_latency_router_rate_limit_monitor(
+                router_id.clone(),
+                router_config.clone(),
+                change_tx,
+            )
+            .await;
+
+        let baseline =
+            Target::new(&app_state, &router_id, &router_config, baseline)
+                .await?;
+        let candidate =
+            Target::new(&app_state, &router_id, &router_config, candidate)
+                .await?;
+        let mut pass_through = HashMap::default();
+        for provider in [&baseline.provider, &candidate.provider] {
+            if pass_through.contains_key(provider) {
+                continue;
+            }
+            let dispatcher = Dispatcher::new(
+                app_state.clone(),
+                &router_id,
+                &router_config,
+                provider.clone(),
+            )
+            .await?;
+            pass_through.insert(provider.clone(), dispatcher);
+        }
+
+        Ok(Self {
+            app_state,
+            router_id,
+            baseline,
+            candidate,
+            pass_through: Arc::new(pass_through),
+            candidate_buckets,
+            bucket_header,
+            unhealthy: HashSet::default(),
+            events: change_rx,
+        })
+    }
+
+    fn handle_change(&mut self, change: Change<Key, DispatcherService>) {
+        // the dispatchers of the monitors send requests to the model of the
+        // request, so only the health of the providers is tracked
+        match change {
+            Change::Insert(key, _dispatcher) => {
+                tracing::debug!(provider = %key.provider, "provider healthy again");
+                self.unhealthy.remove(&key.provider);
+            }
+            Change::Remove(key) => {
+                tracing::debug!(provider = %key.provider, "provider unhealthy");
+                self.unhealthy.insert(key.provider);
+            }
+        }
+    }
+
+    fn split(&self, req: &Request) -> Split {
+        let bucket = bucket_key(req, self.bucket_header.as_ref()).map_or_else(
+            || rand::random_range(0..BUCKETS),
+            |key| bucket(&self.router_id, &key),
+        );
+        let split = if bucket < self.candidate_buckets {
+            Split::Candidate
+        } else {
+            Split::Baseline
+        };
+        let baseline_healthy =
+            !self.unhealthy.contains(&self.baseline.provider);
+        let candidate_healthy =
+            !self.unhealthy.contains(&self.candidate.provider);
+        match split {
+            Split::Candidate if !candidate_healthy && baseline_healthy => {
+                Split::Baseline
+            }
+            Split::Baseline if !baseline_healthy && candidate_healthy => {
+                Split::Candidate
+            }
+            split => split,
+        }
+    }
+}
+
+impl Target {
+    async fn new(
+        app_state: &AppState,
+        router_id: &RouterId,
+        router_config: &Arc<RouterConfig>,
+        model: ModelId,
+    ) -> Result<Self, InitError> {
+        let provider = model.inference_provider().ok_or_else(|| {
+            InitError::ModelIdNotRecognized(model.to_string())
+        })?;
+        let dispatcher = Dispatcher::new_with_model_id(
+            app_state.clone(),
+            router_id,
+            router_config,
+            provider.clone(),
+            model.clone(),
+        )
+        .await?;
+        Ok(Self {
+            model,
+            provider,
+            dispatcher,
+        })
+    }
+}
+
+impl tower::Service<Request> for TrafficSplitRouter {
+    type Response = Response;
+    type Error = ApiError;
+    type Future = BoxFuture<'static, Result<Response, ApiError>>;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        while let Poll::Ready(Some(change)) = self.events.poll_recv(cx) {
+            self.handle_change(change);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    #[tracing::instrument(name = "traffic_split", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        let split = self.split(&req);
+        let target = match split {
+            Split::Baseline => &self.baseline,
+            Split::Candidate => &self.candidate,
+        };
+        let baseline = self.baseline.model.clone();
+        let baseline_provider = self.baseline.provider.clone();
+        let pass_through = self.pass_through.clone();
+        let target_model = target.model.clone();
+        let dispatcher = target.dispatcher.clone();
+        let metrics = self.app_state.0.metrics.clone();
+        let mut attributes = vec![
+            KeyValue::new("router_id", self.router_id.to_string()),
+            KeyValue::new("split", split.as_ref().to_string()),
+            KeyValue::new("model", target.model.to_string()),
+        ];
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let body = body
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            let model = requested_model(&body);
+            let req = Request::from_parts(parts, Body::from(body));
+            if model.as_ref() != Some(&baseline) {
+                // requests without a model are left for the mapper to reject
+                let provider = model
+                    .as_ref()
+                    .and_then(ModelId::inference_provider)
+                    .unwrap_or(baseline_provider);
+                let dispatcher = pass_through.get(&provider).cloned().ok_or(
+                    InvalidRequestError::UnsupportedProvider(provider.clone()),
+                )?;
+                tracing::trace!(%provider, "passing through request");
+                let Ok(response) = dispatcher.oneshot(req).await;
+                return Ok(response);
+            }
+
+            tracing::trace!(split = split.as_ref(), model = %target_model, "split request");
+            let start = Instant::now();
+            let Ok(mut response) = dispatcher.oneshot(req).await;
+            attributes.push(KeyValue::new(
+                "status",
+                i64::from(response.status().as_u16()),
+            ));
+            metrics.split_requests.add(1, &attributes);
+            metrics
+                .split_latency
+                .record(start.elapsed().as_secs_f64() * 1000.0, &attributes);
+            response
+                .headers_mut()
+                .insert(SPLIT_HEADER, HeaderValue::from_static(split.into()));
+            Ok(response)
+        })
+    }
+}
+
+/// The model of the request, if it has a JSON body with a valid `model`.
+fn requested_model(body: &Bytes) -> Option<ModelId> {
+    let value = serde_json::from_slice::<serde_json::Value>(body).ok()?;
+    ModelId::from_str(value.get("model")?.as_str()?).ok()
+}
+
+/// The value of `bucket_header`, or else the API key of the request.
+fn bucket_key(
+    req: &Request,
+    bucket_header: Option<&HeaderName>,
+) -> Option<Vec<u8>> {
+    bucket_header
+        .and_then(|header| req.headers().get(header))
+        .map(|value| value.as_bytes().to_vec())
+        .or_else(|| {
+            req.extensions()
+                .get::<Arc<RequestContext>>()?
+                .auth_context
+                .as_ref()
+                .map(|auth_ctx| auth_ctx.api_key.expose().as_bytes().to_vec())
+        })
+}
+
+fn bucket(router_id: &RouterId, key: &[u8]) -> u64 {
+    let mut hasher = Sha256::new();
+    hasher.update(router_id.as_ref().as_bytes());
+    hasher.update(b"/");
+    hasher.update(key);
+    let digest = hasher.finalize();
+    let mut prefix = [0; 8];
+    prefix.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(prefix) % BUCKETS
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_is_stable() {
+        let router_id = RouterId::Named("my-router".into());
+        let other_router_id = RouterId::Named("other".into());
+        assert_eq!(
+            bucket(&router_id, b"user-1"),
+            bucket(&router_id, b"user-1")
+        );
+        assert_ne!(
+            (0..100)
+                .map(|i| bucket(&router_id, format!("user-{i}").as_bytes()))
+                .collect::<Vec<_>>(),
+            (0..100)
+                .map(|i| bucket(
+                    &other_router_id,
+                    format!("user-{i}").as_bytes()
+                ))
+                .collect::<Vec<_>>(),
+        );
+    }
+
+    #[test]
+    fn test_requested_model() {
+        let body = Bytes::from_static(
+            br#"{"model": "openai/gpt-4o-mini", "messages": []}"#,
+        );
+        assert_eq!(
+            requested_model(&body),
+            Some(ModelId::from_str("openai/gpt-4o-mini").unwrap())
+        );
+        assert_eq!(requested_model(&Bytes::from_static(b"{}")), None);
+        assert_eq!(requested_model(&Bytes::from_static(b"not json")), None);
+    }
+
+    #[test]
+    fn test_buckets_are_uniform() {
+        let router_id = RouterId::Named("my-router".into());
+        // 10% of the buckets
+        let candidate = (0..10_000)
+            .filter(|i| {
+                bucket(&router_id, format!("user-{i}").as_bytes()) < 1_000
+            })
+            .count();
+        assert!((800..1_200).contains(&candidate), "{candidate}");
+    }
+}
@@ -14,7 +14,11 @@ use tower::Service as _;
 
 use crate::{
     app_state::AppState,
-    endpoints::{ApiEndpoint, openai::OpenAI},
+    endpoints::{
+        ApiEndpoint,
+        anthropic::Anthropic,
+        openai::{OpenAI, embeddings::CreateEmbeddingRequest},
+    },
     error::{
         api::ApiError, init::InitError, internal::InternalError,
         invalid_req::InvalidRequestError,
@@ -112,6 +116,8 @@ impl ResponseFuture {
 
 pub enum UnifiedApi {
     ChatCompletions(),
+    Embeddings(),
+    Messages(),
 }
 
 impl TryFrom<&str> for UnifiedApi {
@@ -119,6 +125,8 @@ impl TryFrom<&str> for UnifiedApi {
     fn try_from(value: &str) -> Result<Self, Self::Error> {
         match value {
             "chat/completions" => Ok(Self::ChatCompletions()),
+            "embeddings" => Ok(Self::Embeddings()),
+            "anthropic/v1/messages" => Ok(Self::Messages()),
             _ => {
                 Err(InvalidRequestError::UnsupportedEndpoint(value.to_string()))
             }
@@ -164,18 +172,25 @@ impl Future for ResponseFuture {
                     match unified_api {
                         UnifiedApi::ChatCompletions() => {
                             // since we *need* to have first class support for
-                            // the OpenAI endpoint
-                            // in order to
-                            // deserialize it and extract the model
-                            // id (in order to know the appropriate
-                            // provider), we can only support OpenAI chat
-                            // completions
-                            // as the endpoint for now.
-
+                            // an endpoint in order to deserialize it and
+                            // extract the model id (in order to know the
+                            // appropriate provider), only OpenAI chat
+                            // completions and embeddings, and Anthropic
+                            // messages are supported as endpoints for now.
                             parts.extensions.insert(ApiEndpoint::OpenAI(
                                 OpenAI::chat_completions(),
                             ));
                         }
+                        UnifiedApi::Embeddings() => {
+                            parts.extensions.insert(ApiEndpoint::OpenAI(
+                                OpenAI::embeddings(),
+                            ));
+                        }
+                        UnifiedApi::Messages() => {
+                            parts.extensions.insert(ApiEndpoint::Anthropic(
+                                Anthropic::messages(),
+                            ));
+                        }
                     }
 
                     this.state.set(State::DetermineProvider {
@@ -190,15 +205,33 @@ impl Future for ResponseFuture {
                     let body = collected_body
                         .take()
                         .expect("future polled after completion");
-                    let deserialized_body = serde_json::from_slice::<
-                        async_openai::types::CreateChatCompletionRequest,
-                    >(&body)
-                    .map_err(InvalidRequestError::InvalidRequestBody)?;
-                    let source_model =
-                        ModelId::from_str(&deserialized_body.model)
-                            .map_err(InternalError::MapperError)?;
                     let mut parts =
                         parts.take().expect("future polled after completion");
+                    let model = match parts.extensions.get::<ApiEndpoint>() {
+                        Some(ApiEndpoint::Anthropic(_)) => {
+                            serde_json::from_slice::<
+                                anthropic_ai_sdk::types::message::CreateMessageParams,
+                            >(&body)
+                            .map_err(InvalidRequestError::InvalidRequestBody)?
+                            .model
+                        }
+                        Some(ApiEndpoint::OpenAI(OpenAI::Embeddings(_))) => {
+                            serde_json::from_slice::<CreateEmbeddingRequest>(
+                                &body,
+                            )
+                            .map_err(InvalidRequestError::InvalidRequestBody)?
+                            .model
+                        }
+                        _ => {
+                            serde_json::from_slice::<
+                                async_openai::types::CreateChatCompletionRequest,
+                            >(&body)
+                            .map_err(InvalidRequestError::InvalidRequestBody)?
+                            .model
+                        }
+                    };
+                    let source_model = ModelId::from_str(&model)
+                        .map_err(InternalError::MapperError)?;
                     let provider = match source_model {
                         ModelId::ModelIdWithVersion { provider, .. } => {
                             provider
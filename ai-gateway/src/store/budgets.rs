@@ -0,0 +1,157 @@
+use sqlx::{AnyPool, any::AnyPoolOptions};
+use tracing::error;
+
+use crate::{
+    config::budgets::BudgetsStoreConfig,
+    error::{init::InitError, internal::InternalError},
+};
+
+/// The budgets set and removed at runtime, by name. A removed budget is kept
+/// with a `NULL` budget, so that budgets of the config stay removed.
+const CREATE_BUDGETS_TABLE: &str = r"CREATE TABLE IF NOT EXISTS budgets (
+    name TEXT PRIMARY KEY,
+    budget TEXT
+)";
+
+/// The spend of the budgets in nano USD, by name and start of the period as
+/// unix milliseconds.
+const CREATE_SPEND_TABLE: &str = r"CREATE TABLE IF NOT EXISTS budget_spend (
+    name TEXT NOT NULL,
+    period_start BIGINT NOT NULL,
+    spent_nano_usd BIGINT NOT NULL,
+    PRIMARY KEY (name, period_start)
+)";
+
+/// The budgets and their spend, see [`crate::budgets`].
+#[derive(Debug, Clone)]
+pub struct BudgetStore {
+    pub pool: AnyPool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct DbBudget {
+    pub name: String,
+    /// The budget as JSON, `None` if it was removed.
+    pub budget: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct DbBudgetSpend {
+    pub name: String,
+    pub period_start: i64,
+    pub spent_nano_usd: i64,
+}
+
+impl BudgetStore {
+    /// Connect to the database of `config`, creating the tables of the
+    /// budgets if they don't exist yet.
+    pub async fn connect(
+        config: &BudgetsStoreConfig,
+    ) -> Result<Self, InitError> {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(config.max_connections)
+            .connect(config.database_url.expose())
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, "failed to create budgets database pool");
+                InitError::DatabaseConnection(e)
+            })?;
+        for statement in [CREATE_BUDGETS_TABLE, CREATE_SPEND_TABLE] {
+            sqlx::query(statement)
+                .execute(&pool)
+                .await
+                .map_err(|e| InitError::InitBudgets(e.to_string()))?;
+        }
+        Ok(Self { pool })
+    }
+
+    pub async fn get_budgets(&self) -> Result<Vec<DbBudget>, InternalError> {
+        let res =
+            sqlx::query_as::<_, DbBudget>(r"SELECT name, budget FROM budgets")
+                .fetch_all(&self.pool)
+                .await
+                .inspect_err(|e| {
+                    error!(error = %e, "failed to get budgets");
+                })?;
+        Ok(res)
+    }
+
+    /// Set the budget `name` to `budget`, or mark it as removed if `None`.
+    pub async fn set_budget(
+        &self,
+        name: &str,
+        budget: Option<String>,
+    ) -> Result<(), InternalError> {
+        sqlx::query(
+            r"INSERT INTO budgets (name, budget) VALUES ($1, $2)
+             ON CONFLICT (name) DO UPDATE SET budget = excluded.budget",
+        )
+        .bind(name)
+        .bind(budget)
+        .execute(&self.pool)
+        .await
+        .inspect_err(|e| {
+            error!(error = %e, "failed to set budget");
+        })?;
+        Ok(())
+    }
+
+    /// The spend of the periods starting at or after `since`.
+    pub async fn get_spend(
+        &self,
+        since: i64,
+    ) -> Result<Vec<DbBudgetSpend>, InternalError> {
+        let res = sqlx::query_as::<_, DbBudgetSpend>(
+            r"SELECT name, period_start, spent_nano_usd FROM budget_spend
+             WHERE period_start >= $1",
+        )
+        .bind(since)
+        .fetch_all(&self.pool)
+        .await
+        .inspect_err(|e| {
+            error!(error = %e, "failed to get spend of budgets");
+        })?;
+        Ok(res)
+    }
+
+    /// Add `nano_usd` to the spend of the budget `name` in the period
+    /// starting at `period_start`, returning its new spend.
+    pub async fn add_spent_nano_usd(
+        &self,
+        name: &str,
+        period_start: i64,
+        nano_usd: i64,
+    ) -> Result<i64, InternalError> {
+        let res = sqlx::query_scalar::<_, i64>(
+            r"INSERT INTO budget_spend (name, period_start, spent_nano_usd)
+             VALUES ($1, $2, $3)
+             ON CONFLICT (name, period_start) DO UPDATE
+             SET spent_nano_usd =
+                 budget_spend.spent_nano_usd + excluded.spent_nano_usd
+             RETURNING spent_nano_usd",
+        )
+        .bind(name)
+        .bind(period_start)
+        .bind(nano_usd)
+        .fetch_one(&self.pool)
+        .await
+        .inspect_err(|e| {
+            error!(error = %e, "failed to update spend of budget");
+        })?;
+        Ok(res)
+    }
+
+    /// Forget the spend of the budget `name`, e.g. when it covers other
+    /// requests than before.
+    pub async fn delete_spend(&self, name: &str) -> Result<(), InternalError> {
+        sqlx::query(r"DELETE FROM budget_spend WHERE name = $1")
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .inspect_err(|e| {
+                error!(error = %e, "failed to delete spend of budget");
+            })?;
+        Ok(())
+    }
+}
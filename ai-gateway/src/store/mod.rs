@@ -1,10 +1,32 @@
+use rust_decimal::{Decimal, prelude::ToPrimitive};
 use sqlx::{PgPool, postgres::PgPoolOptions};
 
-use crate::{config::database::DatabaseConfig, error::init::InitError};
+use crate::{
+    config::database::DatabaseConfig,
+    error::{init::InitError, internal::InternalError},
+};
 
+pub mod budgets;
 pub mod db_listener;
 pub mod minio;
 pub mod router;
+pub mod virtual_keys;
+
+/// Amounts that are added to atomically are stored in nano USD, as both
+/// sqlite and postgres can add integers but not decimal strings.
+const NANO_USD_SCALE: u32 = 9;
+
+pub(crate) fn to_nano_usd(usd: Decimal) -> Result<i64, InternalError> {
+    (usd * Decimal::from(10_i64.pow(NANO_USD_SCALE)))
+        .round()
+        .to_i64()
+        .ok_or(InternalError::Internal)
+}
+
+#[must_use]
+pub(crate) fn from_nano_usd(nano_usd: i64) -> Decimal {
+    Decimal::new(nano_usd, NANO_USD_SCALE).normalize()
+}
 
 pub async fn connect(config: &DatabaseConfig) -> Result<PgPool, InitError> {
     let pool = PgPoolOptions::new()
@@ -0,0 +1,194 @@
+use sqlx::{AnyPool, any::AnyPoolOptions};
+use tracing::error;
+
+use crate::{
+    config::virtual_keys::VirtualKeysConfig,
+    error::{init::InitError, internal::InternalError},
+};
+
+/// Works with both sqlite and postgres, hence the plain column types:
+/// timestamps are unix milliseconds, amounts are decimal strings and lists
+/// are JSON arrays. The spend is in nano USD instead, so that replicas can
+/// add to it atomically, see [`VirtualKeyStore::add_spent_nano_usd`].
+const CREATE_TABLE: &str = r"CREATE TABLE IF NOT EXISTS virtual_keys (
+    id TEXT PRIMARY KEY,
+    key_hash TEXT NOT NULL UNIQUE,
+    name TEXT,
+    routers TEXT,
+    models TEXT,
+    spend_limit_usd TEXT,
+    spent_nano_usd BIGINT NOT NULL DEFAULT 0,
+    expires_at BIGINT,
+    priority TEXT,
+    created_at BIGINT NOT NULL,
+    revoked_at BIGINT
+)";
+
+/// The virtual keys, see [`crate::virtual_keys`].
+#[derive(Debug, Clone)]
+pub struct VirtualKeyStore {
+    pub pool: AnyPool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, sqlx::FromRow)]
+pub struct DbVirtualKey {
+    pub id: String,
+    pub key_hash: String,
+    pub name: Option<String>,
+    pub routers: Option<String>,
+    pub models: Option<String>,
+    pub spend_limit_usd: Option<String>,
+    pub spent_nano_usd: i64,
+    pub expires_at: Option<i64>,
+    pub priority: Option<String>,
+    pub created_at: i64,
+    pub revoked_at: Option<i64>,
+}
+
+impl VirtualKeyStore {
+    /// Connect to the database of `config`, creating the table of the keys
+    /// if it doesn't exist yet.
+    pub async fn connect(
+        config: &VirtualKeysConfig,
+    ) -> Result<Self, InitError> {
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(config.max_connections)
+            .connect(config.database_url.expose())
+            .await
+            .map_err(|e| {
+                tracing::error!(error = %e, "failed to create virtual keys database pool");
+                InitError::DatabaseConnection(e)
+            })?;
+        sqlx::query(CREATE_TABLE)
+            .execute(&pool)
+            .await
+            .map_err(|e| InitError::InitVirtualKeys(e.to_string()))?;
+        Ok(Self { pool })
+    }
+
+    pub async fn get_all(&self) -> Result<Vec<DbVirtualKey>, InternalError> {
+        let res = sqlx::query_as::<_, DbVirtualKey>(
+            r"SELECT id, key_hash, name, routers, models, spend_limit_usd,
+                     spent_nano_usd, expires_at, priority, created_at,
+                     revoked_at
+             FROM virtual_keys",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .inspect_err(|e| {
+            error!(error = %e, "failed to get all virtual keys");
+        })?;
+        Ok(res)
+    }
+
+    pub async fn get_by_key_hash(
+        &self,
+        key_hash: &str,
+    ) -> Result<Option<DbVirtualKey>, InternalError> {
+        let res = sqlx::query_as::<_, DbVirtualKey>(
+            r"SELECT id, key_hash, name, routers, models, spend_limit_usd,
+                     spent_nano_usd, expires_at, priority, created_at,
+                     revoked_at
+             FROM virtual_keys
+             WHERE key_hash = $1",
+        )
+        .bind(key_hash)
+        .fetch_optional(&self.pool)
+        .await
+        .inspect_err(|e| {
+            error!(error = %e, "failed to get virtual key");
+        })?;
+        Ok(res)
+    }
+
+    pub async fn get_by_id(
+        &self,
+        id: &str,
+    ) -> Result<Option<DbVirtualKey>, InternalError> {
+        let res = sqlx::query_as::<_, DbVirtualKey>(
+            r"SELECT id, key_hash, name, routers, models, spend_limit_usd,
+                     spent_nano_usd, expires_at, priority, created_at,
+                     revoked_at
+             FROM virtual_keys
+             WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .inspect_err(|e| {
+            error!(error = %e, "failed to get virtual key");
+        })?;
+        Ok(res)
+    }
+
+    pub async fn insert(
+        &self,
+        key: &DbVirtualKey,
+    ) -> Result<(), InternalError> {
+        sqlx::query(
+            r"INSERT INTO virtual_keys (id, key_hash, name, routers, models,
+                 spend_limit_usd, spent_nano_usd, expires_at, priority,
+                 created_at, revoked_at)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+        )
+        .bind(&key.id)
+        .bind(&key.key_hash)
+        .bind(&key.name)
+        .bind(&key.routers)
+        .bind(&key.models)
+        .bind(&key.spend_limit_usd)
+        .bind(key.spent_nano_usd)
+        .bind(key.expires_at)
+        .bind(&key.priority)
+        .bind(key.created_at)
+        .bind(key.revoked_at)
+        .execute(&self.pool)
+        .await
+        .inspect_err(|e| {
+            error!(error = %e, "failed to insert virtual key");
+        })?;
+        Ok(())
+    }
+
+    pub async fn set_revoked_at(
+        &self,
+        id: &str,
+        revoked_at: i64,
+    ) -> Result<(), InternalError> {
+        sqlx::query(
+            r"UPDATE virtual_keys SET revoked_at = $1
+             WHERE id = $2 AND revoked_at IS NULL",
+        )
+        .bind(revoked_at)
+        .bind(id)
+        .execute(&self.pool)
+        .await
+        .inspect_err(|e| {
+            error!(error = %e, "failed to revoke virtual key");
+        })?;
+        Ok(())
+    }
+
+    /// Add `nano_usd` to the spend of the key with `id`, returning its new
+    /// spend, or `None` if there is no such key.
+    pub async fn add_spent_nano_usd(
+        &self,
+        id: &str,
+        nano_usd: i64,
+    ) -> Result<Option<i64>, InternalError> {
+        let res = sqlx::query_scalar::<_, i64>(
+            r"UPDATE virtual_keys SET spent_nano_usd = spent_nano_usd + $1
+             WHERE id = $2
+             RETURNING spent_nano_usd",
+        )
+        .bind(nano_usd)
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await
+        .inspect_err(|e| {
+            error!(error = %e, "failed to update spend of virtual key");
+        })?;
+        Ok(res)
+    }
+}
@@ -1,10 +1,13 @@
 use std::{
     borrow::Cow,
+    cmp::Ordering,
     fmt::{self, Display},
     str::FromStr,
 };
 
-use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use chrono::{
+    DateTime, Datelike, FixedOffset, NaiveDate, NaiveDateTime, TimeZone, Utc,
+};
 use derive_more::AsRef;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
@@ -21,16 +24,89 @@ pub enum Version {
     Preview,
     /// A specific version of a preview model based on the date it was released.
     DateVersionedPreview {
-        date: DateTime<Utc>,
+        date: DateTime<FixedOffset>,
         /// The format of the date so we know how to re-serialize it
         format: &'static str,
     },
     /// A version of the model based on the date it was released.
     Date {
-        date: DateTime<Utc>,
+        date: DateTime<FixedOffset>,
         /// The format of the date so we know how to re-serialize it
         format: &'static str,
     },
+    /// A numbered revision of the model, e.g. the `002` of Gemini's
+    /// `gemini-1.5-pro-002`. Kept as written so leading zeros survive.
+    Revision(String),
+}
+
+impl Version {
+    /// Whether this refers to the latest version, whether or not that is
+    /// spelled out.
+    #[must_use]
+    pub fn is_latest(&self) -> bool {
+        matches!(self, Version::ImplicitLatest | Version::Latest)
+    }
+
+    /// Whether both versions refer to the same snapshot, treating implicit
+    /// and explicit latest as the same version.
+    fn same_target(&self, other: &Version) -> bool {
+        self == other || (self.is_latest() && other.is_latest())
+    }
+
+    /// The stable form of this version used by [`ModelId::cache_key`].
+    fn cache_key(&self) -> String {
+        match self {
+            Version::ImplicitLatest => "implicit-latest".to_string(),
+            Version::Latest => "latest".to_string(),
+            Version::Preview => "preview".to_string(),
+            Version::DateVersionedPreview { date, format } => format!(
+                "preview-date:{}:{format}",
+                date.to_utc().format(RFC3339_UTC_FORMAT)
+            ),
+            Version::Date { date, format } => {
+                format!(
+                    "date:{}:{format}",
+                    date.to_utc().format(RFC3339_UTC_FORMAT)
+                )
+            }
+            Version::Revision(revision) => format!("revision:{revision}"),
+        }
+    }
+}
+
+/// Orders versions from oldest to newest: dated versions chronologically
+/// regardless of their format (a preview before the release of the same
+/// day), then numbered revisions, [`Version::Preview`], [`Version::Latest`]
+/// and finally [`Version::ImplicitLatest`].
+impl Ord for Version {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // the format only breaks ties so that the order agrees with `==`
+        fn key(
+            version: &Version,
+        ) -> (u8, Option<DateTime<FixedOffset>>, bool, &str) {
+            match version {
+                Version::DateVersionedPreview { date, format } => {
+                    (0, Some(*date), false, format)
+                }
+                Version::Date { date, format } => {
+                    (0, Some(*date), true, format)
+                }
+                // revisions are zero-padded to the same width, so comparing
+                // them as strings orders them numerically
+                Version::Revision(revision) => (1, None, false, revision),
+                Version::Preview => (2, None, false, ""),
+                Version::Latest => (3, None, false, ""),
+                Version::ImplicitLatest => (4, None, false, ""),
+            }
+        }
+        key(self).cmp(&key(other))
+    }
+}
+
+impl PartialOrd for Version {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl<'de> Deserialize<'de> for Version {
@@ -64,6 +140,7 @@ impl Display for Version {
             Version::Date { date, format } => {
                 write!(f, "{}", date.format(format))
             }
+            Version::Revision(revision) => write!(f, "{revision}"),
         }
     }
 }
@@ -89,6 +166,8 @@ impl FromStr for Version {
                 date: dt,
                 format: fmt,
             })
+        } else if is_revision(input) {
+            Ok(Version::Revision(input.to_string()))
         } else if input.is_empty() {
             Ok(Version::ImplicitLatest)
         } else {
@@ -136,6 +215,11 @@ impl std::fmt::Display for ModelName<'_> {
     }
 }
 
+/// A model of a provider.
+///
+/// `==` compares the exact form a model was written in, so that parsing and
+/// displaying a `ModelId` round-trips. Use [`ModelId::same_target`] to check
+/// whether two ids refer to the same model, e.g. for routing decisions.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum ModelId {
     ModelIdWithVersion {
@@ -147,6 +231,10 @@ pub enum ModelId {
     Unknown(String),
 }
 
+/// Trailing name segments that are part of a model's name rather than its
+/// version, e.g. `gpt-4-turbo-preview` or `mistral-7b-instruct`.
+pub const DEFAULT_MODEL_SUFFIXES: &[&str] = &["preview", "instruct"];
+
 impl ModelId {
     /// Create a `ModelId` from a string and an inference provider.
     ///
@@ -155,17 +243,38 @@ impl ModelId {
     pub(crate) fn from_str_and_provider(
         request_style: InferenceProvider,
         s: &str,
+    ) -> Result<Self, MapperError> {
+        Self::from_str_and_provider_with_suffixes(
+            request_style,
+            s,
+            DEFAULT_MODEL_SUFFIXES,
+        )
+    }
+
+    /// Like [`ModelId::from_str_and_provider`], but with the given list of
+    /// name suffixes instead of [`DEFAULT_MODEL_SUFFIXES`].
+    ///
+    /// A `{model}-{version}` style name ending in `-{suffix}` is kept whole
+    /// as the model name with an implicit latest version. Dated names such
+    /// as `o1-preview-2024-09-12` do not end in a suffix and are still
+    /// parsed as dated versions.
+    pub(crate) fn from_str_and_provider_with_suffixes<S: AsRef<str>>(
+        request_style: InferenceProvider,
+        s: &str,
+        suffixes: &[S],
     ) -> Result<Self, MapperError> {
         match request_style {
             InferenceProvider::OpenAI => {
-                let model_with_version = ModelIdWithVersion::from_str(s)?;
+                let model_with_version =
+                    ModelIdWithVersion::parse_with_suffixes(s, suffixes)?;
                 Ok(ModelId::ModelIdWithVersion {
                     provider: InferenceProvider::OpenAI,
                     id: model_with_version,
                 })
             }
             InferenceProvider::Anthropic => {
-                let model_with_version = ModelIdWithVersion::from_str(s)?;
+                let model_with_version =
+                    ModelIdWithVersion::parse_with_suffixes(s, suffixes)?;
                 Ok(ModelId::ModelIdWithVersion {
                     provider: InferenceProvider::Anthropic,
                     id: model_with_version,
@@ -180,14 +289,16 @@ impl ModelId {
                 Ok(ModelId::Ollama(ollama_model))
             }
             InferenceProvider::GoogleGemini => {
-                let model_with_version = ModelIdWithVersion::from_str(s)?;
+                let model_with_version =
+                    ModelIdWithVersion::parse_with_revision(s, suffixes)?;
                 Ok(ModelId::ModelIdWithVersion {
                     provider: InferenceProvider::GoogleGemini,
                     id: model_with_version,
                 })
             }
             InferenceProvider::Named(name) => {
-                let model_with_version = ModelIdWithVersion::from_str(s)?;
+                let model_with_version =
+                    ModelIdWithVersion::parse_with_suffixes(s, suffixes)?;
                 Ok(ModelId::ModelIdWithVersion {
                     provider: InferenceProvider::Named(name),
                     id: model_with_version,
@@ -196,6 +307,136 @@ impl ModelId {
         }
     }
 
+    /// Start building a `ModelId` for `provider`, see [`ModelIdBuilder`].
+    #[must_use]
+    pub fn builder(provider: InferenceProvider) -> ModelIdBuilder {
+        ModelIdBuilder {
+            provider,
+            model: None,
+            version: None,
+        }
+    }
+
+    /// Create a `ModelId` from its parts, validated like
+    /// [`ModelIdBuilder::build`].
+    pub fn new(
+        provider: InferenceProvider,
+        model: &str,
+        version: Version,
+    ) -> Result<Self, MapperError> {
+        Self::builder(provider)
+            .model(model)
+            .version(version)
+            .build()
+    }
+
+    /// Whether `self` and `other` refer to the same model, regardless of how
+    /// they are written.
+    ///
+    /// Unlike `==`, this treats an implicit latest version (`gpt-4`) and an
+    /// explicit one (`gpt-4-latest`) as the same, as well as an untagged
+    /// Ollama model and its `latest` tag.
+    #[must_use]
+    pub fn same_target(&self, other: &ModelId) -> bool {
+        match (self, other) {
+            (
+                ModelId::ModelIdWithVersion { provider, id },
+                ModelId::ModelIdWithVersion {
+                    provider: other_provider,
+                    id: other_id,
+                },
+            ) => {
+                provider == other_provider
+                    && id.model == other_id.model
+                    && id.version.same_target(&other_id.version)
+            }
+            (ModelId::Bedrock(this), ModelId::Bedrock(other)) => {
+                this.geo == other.geo
+                    && this.provider == other.provider
+                    && this.model == other.model
+                    && this.bedrock_internal_version
+                        == other.bedrock_internal_version
+                    && this.version.same_target(&other.version)
+            }
+            (ModelId::Ollama(this), ModelId::Ollama(other)) => {
+                this.model == other.model
+                    && this.tag.as_deref().unwrap_or("latest")
+                        == other.tag.as_deref().unwrap_or("latest")
+            }
+            (ModelId::Unknown(this), ModelId::Unknown(other)) => this == other,
+            (
+                ModelId::ModelIdWithVersion { .. }
+                | ModelId::Bedrock(_)
+                | ModelId::Ollama(_)
+                | ModelId::Unknown(_),
+                _,
+            ) => false,
+        }
+    }
+
+    /// A stable string identifying this model, suitable for use as a cache
+    /// key.
+    ///
+    /// Unlike [`Display`], which is meant for humans and may change, this
+    /// format is versioned and guaranteed to stay the same across releases:
+    ///
+    /// - `v2|{provider}|{model}|{version}`
+    /// - `v2|bedrock|{geo}|{model provider}|{model}|{version}|{internal
+    ///   version}`
+    /// - `v2|ollama|{model}|{tag}`
+    /// - `v2|unknown|{model}`
+    ///
+    /// where `{version}` is one of `implicit-latest`, `latest`, `preview`,
+    /// `date:{timestamp}:{format}`, `preview-date:{timestamp}:{format}` or
+    /// `revision:{revision}` with `{timestamp}` in UTC, e.g.
+    /// `2024-08-06T00:00:00Z`,
+    /// and absent optional components are empty. Any change to this format
+    /// must bump the `v1` prefix.
+    #[must_use]
+    pub fn cache_key(&self) -> String {
+        const CACHE_KEY_VERSION: &str = "v2";
+        match self {
+            ModelId::ModelIdWithVersion { provider, id } => format!(
+                "{CACHE_KEY_VERSION}|{provider}|{}|{}",
+                id.model,
+                id.version.cache_key()
+            ),
+            ModelId::Bedrock(model) => format!(
+                "{CACHE_KEY_VERSION}|bedrock|{}|{}|{}|{}|{}",
+                model.geo.as_deref().unwrap_or_default(),
+                model.provider,
+                model.model,
+                model.version.cache_key(),
+                model.bedrock_internal_version
+            ),
+            ModelId::Ollama(model) => format!(
+                "{CACHE_KEY_VERSION}|ollama|{}|{}",
+                model.model,
+                model.tag.as_deref().unwrap_or_default()
+            ),
+            ModelId::Unknown(model) => {
+                format!("{CACHE_KEY_VERSION}|unknown|{model}")
+            }
+        }
+    }
+
+    /// Split a model string into its base name and version without any
+    /// provider context.
+    ///
+    /// This applies only the generic date, `latest` and `preview` splitting
+    /// used for `{model}-{version}` style names. Provider-specific heuristics
+    /// (e.g. Bedrock's `{provider}.{model}-v1:0` or Ollama's `{model}:{tag}`)
+    /// are skipped, so prefer [`ModelId::from_str_and_provider`] whenever the
+    /// provider is known.
+    #[must_use]
+    pub fn parse_unqualified(s: &str) -> (String, Version) {
+        let (model, version) = parse_model_and_version(s, '-');
+        (
+            model.to_string(),
+            version.unwrap_or(Version::ImplicitLatest),
+        )
+    }
+
     #[must_use]
     pub fn inference_provider(&self) -> Option<InferenceProvider> {
         match self {
@@ -254,6 +495,7 @@ impl ModelId {
                     version: Version::Latest,
                     bedrock_internal_version: bedrock_model_id
                         .bedrock_internal_version,
+                    arn_prefix: bedrock_model_id.arn_prefix,
                 })
             }
             ModelId::Ollama(ollama_model_id) => {
@@ -290,6 +532,95 @@ impl Serialize for ModelId {
     }
 }
 
+/// Builds a [`ModelId`] that is guaranteed to be what parsing its string
+/// form in the context of its provider would produce.
+///
+/// ```rust,ignore
+/// let model = ModelId::builder(InferenceProvider::Anthropic)
+///     .model("claude-3-opus")
+///     .version(Version::Latest)
+///     .build()?;
+/// assert_eq!(model.to_string(), "claude-3-opus-latest");
+/// ```
+#[derive(Debug, Clone)]
+pub struct ModelIdBuilder {
+    provider: InferenceProvider,
+    model: Option<String>,
+    version: Option<Version>,
+}
+
+impl ModelIdBuilder {
+    /// The model name, without its version.
+    ///
+    /// For Bedrock and Ollama models this is the full model string, e.g.
+    /// `anthropic.claude-3-haiku-20240307-v1:0` or `gemma3:1b`.
+    #[must_use]
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// The model version, defaults to [`Version::ImplicitLatest`].
+    ///
+    /// Only supported for `{model}-{version}` style providers.
+    #[must_use]
+    pub fn version(mut self, version: Version) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// Build the `ModelId`, failing if the model is missing or the model and
+    /// version would not parse back into the same parts.
+    pub fn build(self) -> Result<ModelId, MapperError> {
+        let Some(model) = self.model else {
+            return Err(MapperError::InvalidModelName(
+                "Model name is required".to_string(),
+            ));
+        };
+        let Some(version) = self.version else {
+            return ModelId::from_str_and_provider(self.provider, &model);
+        };
+        if matches!(
+            self.provider,
+            InferenceProvider::Bedrock | InferenceProvider::Ollama
+        ) {
+            return Err(MapperError::InvalidModelName(format!(
+                "Version cannot be set separately for {} models, include it \
+                 in the model name",
+                self.provider
+            )));
+        }
+
+        let expected = ModelIdWithVersion { model, version };
+        let model_id = ModelId::from_str_and_provider(
+            self.provider,
+            &expected.to_string(),
+        )?;
+        match &model_id {
+            ModelId::ModelIdWithVersion { id, .. } if *id == expected => {
+                Ok(model_id)
+            }
+            _ => Err(MapperError::InvalidModelName(format!(
+                "Model '{}' with version '{}' parses as '{}' with version '{}'",
+                expected.model,
+                expected.version,
+                model_id.as_model_name(),
+                model_id_version(&model_id)
+            ))),
+        }
+    }
+}
+
+fn model_id_version(model_id: &ModelId) -> String {
+    match model_id {
+        ModelId::ModelIdWithVersion { id, .. } => id.version.to_string(),
+        ModelId::Bedrock(model) => model.version.to_string(),
+        ModelId::Ollama(_) | ModelId::Unknown(_) => {
+            Version::ImplicitLatest.to_string()
+        }
+    }
+}
+
 /// Parse a model id in the format `{provider}/{model_name}` to a `ModelId`.
 impl FromStr for ModelId {
     type Err = MapperError;
@@ -408,6 +739,18 @@ impl FromStr for ModelIdWithVersion {
     type Err = MapperError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_with_suffixes(s, DEFAULT_MODEL_SUFFIXES)
+    }
+}
+
+impl ModelIdWithVersion {
+    /// Parse `s`, keeping names that end in one of `suffixes` whole.
+    ///
+    /// See [`ModelId::from_str_and_provider_with_suffixes`].
+    pub fn parse_with_suffixes<S: AsRef<str>>(
+        s: &str,
+        suffixes: &[S],
+    ) -> Result<Self, MapperError> {
         // Validate input string
         if s.is_empty() {
             return Err(MapperError::InvalidModelName(
@@ -433,6 +776,18 @@ impl FromStr for ModelIdWithVersion {
             ));
         }
 
+        let has_suffix = s.rsplit_once('-').is_some_and(|(_, last)| {
+            suffixes
+                .iter()
+                .any(|suffix| last.eq_ignore_ascii_case(suffix.as_ref()))
+        });
+        if has_suffix {
+            return Ok(ModelIdWithVersion {
+                model: s.to_string(),
+                version: Version::ImplicitLatest,
+            });
+        }
+
         let (model, version) = parse_model_and_version(s, '-');
         Ok(ModelIdWithVersion {
             model: model.to_string(),
@@ -441,6 +796,34 @@ impl FromStr for ModelIdWithVersion {
     }
 }
 
+impl ModelIdWithVersion {
+    /// Like [`ModelIdWithVersion::parse_with_suffixes`], but a trailing
+    /// numbered revision such as the `002` of `gemini-1.5-pro-002` is parsed
+    /// as [`Version::Revision`].
+    ///
+    /// Only Gemini names its stable snapshots this way, other providers use
+    /// plain numbers as part of the model name (e.g. `davinci-002`). Gemini's
+    /// embedding models are the exception: the number of
+    /// `text-embedding-004` is a generation, not a snapshot, so it stays part
+    /// of the name.
+    pub fn parse_with_revision<S: AsRef<str>>(
+        s: &str,
+        suffixes: &[S],
+    ) -> Result<Self, MapperError> {
+        if let Some((model, revision)) = s.rsplit_once('-')
+            && !model.is_empty()
+            && !model.ends_with("embedding")
+            && is_revision(revision)
+        {
+            return Ok(ModelIdWithVersion {
+                model: model.to_string(),
+                version: Version::Revision(revision.to_string()),
+            });
+        }
+        Self::parse_with_suffixes(s, suffixes)
+    }
+}
+
 impl Display for ModelIdWithVersion {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match &self.version {
@@ -526,6 +909,9 @@ impl Serialize for OllamaModelId {
 /// Has the format of:
 /// `{geo}?.{provider}.{model}(-version)?-{bedrock_internal_version}`
 /// amazon.nova-pro-v1:0
+///
+/// or that of the ARN of a foundation model or inference profile, e.g.
+/// `arn:aws:bedrock:us-east-1::foundation-model/amazon.nova-pro-v1:0`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BedrockModelId {
     pub geo: Option<String>,
@@ -533,12 +919,56 @@ pub struct BedrockModelId {
     pub model: String,
     pub version: Version,
     pub bedrock_internal_version: String,
+    /// For models referenced by ARN, the ARN up to and including the
+    /// resource type, e.g. `arn:aws:bedrock:us-east-1::foundation-model/`.
+    pub arn_prefix: Option<String>,
+}
+
+/// The Bedrock resource types whose ARNs end in a model id.
+const BEDROCK_ARN_RESOURCE_TYPES: [&str; 2] =
+    ["foundation-model", "inference-profile"];
+
+impl BedrockModelId {
+    /// Parse an ARN of the form
+    /// `arn:{partition}:bedrock:{region}:{account}?:{resource type}/{model}`.
+    fn from_arn(arn: &str) -> Result<Self, MapperError> {
+        let invalid = || MapperError::InvalidModelName(arn.to_string());
+        // the model id itself may contain colons, e.g. `-v1:0`
+        let mut parts = arn.splitn(6, ':');
+        let (Some("arn"), Some(_partition), Some("bedrock"), Some(_region)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(invalid());
+        };
+        let _account = parts.next().ok_or_else(invalid)?;
+        let resource = parts.next().ok_or_else(invalid)?;
+        let (resource_type, model_id) =
+            resource.split_once('/').ok_or_else(invalid)?;
+        if !BEDROCK_ARN_RESOURCE_TYPES.contains(&resource_type) {
+            return Err(invalid());
+        }
+
+        let mut bedrock_model_id = Self::from_str(model_id)?;
+        bedrock_model_id.arn_prefix =
+            Some(arn[..arn.len() - model_id.len()].to_string());
+        Ok(bedrock_model_id)
+    }
+
+    /// Whether this model was referenced by ARN.
+    #[must_use]
+    pub fn is_arn(&self) -> bool {
+        self.arn_prefix.is_some()
+    }
 }
 
 impl FromStr for BedrockModelId {
     type Err = MapperError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("arn:") {
+            return Self::from_arn(s);
+        }
+
         // Count the number of dots to determine if geo is present
         let dot_count = s.chars().filter(|&c| c == '.').count();
 
@@ -588,12 +1018,16 @@ impl FromStr for BedrockModelId {
             model: model.to_string(),
             version: version.unwrap_or(Version::ImplicitLatest),
             bedrock_internal_version: bedrock_version.to_string(),
+            arn_prefix: None,
         })
     }
 }
 
 impl Display for BedrockModelId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(arn_prefix) = &self.arn_prefix {
+            f.write_str(arn_prefix)?;
+        }
         match (&self.geo, &self.version) {
             (Some(geo), Version::ImplicitLatest) => write!(
                 f,
@@ -646,18 +1080,37 @@ impl Serialize for BedrockModelId {
     }
 }
 
-fn parse_date(input: &str) -> Option<(DateTime<Utc>, &'static str)> {
-    // try YYYY-MM-DD first
-    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
-        if let Some(naive_dt) = date.and_hms_opt(0, 0, 0) {
-            return Some((Utc.from_utc_datetime(&naive_dt), "%Y-%m-%d"));
+/// Formats of a full date version component, tried in order.
+const DATE_FORMATS: [&str; 2] = ["%Y-%m-%d", "%Y%m%d"];
+/// RFC 3339 in UTC, kept as is when re-serializing.
+const RFC3339_UTC_FORMAT: &str = "%Y-%m-%dT%H:%M:%SZ";
+/// Any other RFC 3339 timestamp, re-serialized with its original offset.
+const RFC3339_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%:z";
+/// Formats without a year, which assume the current year. A version in one
+/// of these is only used if no version with a year is found.
+const YEARLESS_DATE_FORMATS: [&str; 2] = ["%m-%d", "%m%d"];
+
+fn parse_date(input: &str) -> Option<(DateTime<FixedOffset>, &'static str)> {
+    for format in DATE_FORMATS {
+        if let Ok(date) = NaiveDate::parse_from_str(input, format) {
+            if let Some(naive_dt) = date.and_hms_opt(0, 0, 0) {
+                return Some((
+                    Utc.from_utc_datetime(&naive_dt).fixed_offset(),
+                    format,
+                ));
+            }
         }
     }
-    // then YYYYMMDD
-    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y%m%d") {
-        if let Some(naive_dt) = date.and_hms_opt(0, 0, 0) {
-            return Some((Utc.from_utc_datetime(&naive_dt), "%Y%m%d"));
-        }
+    if let Ok(naive_dt) =
+        NaiveDateTime::parse_from_str(input, RFC3339_UTC_FORMAT)
+    {
+        return Some((
+            Utc.from_utc_datetime(&naive_dt).fixed_offset(),
+            RFC3339_UTC_FORMAT,
+        ));
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(input) {
+        return Some((date, RFC3339_FORMAT));
     }
     // then MM-DD (assume current year)
     if let Ok(date) = NaiveDate::parse_from_str(
@@ -665,7 +1118,10 @@ fn parse_date(input: &str) -> Option<(DateTime<Utc>, &'static str)> {
         "%Y-%m-%d",
     ) {
         if let Some(naive_dt) = date.and_hms_opt(0, 0, 0) {
-            return Some((Utc.from_utc_datetime(&naive_dt), "%m-%d"));
+            return Some((
+                Utc.from_utc_datetime(&naive_dt).fixed_offset(),
+                "%m-%d",
+            ));
         }
     }
     // then MMDD (assume current year)
@@ -674,12 +1130,20 @@ fn parse_date(input: &str) -> Option<(DateTime<Utc>, &'static str)> {
         "%Y%m%d",
     ) {
         if let Some(naive_dt) = date.and_hms_opt(0, 0, 0) {
-            return Some((Utc.from_utc_datetime(&naive_dt), "%m%d"));
+            return Some((
+                Utc.from_utc_datetime(&naive_dt).fixed_offset(),
+                "%m%d",
+            ));
         }
     }
     None
 }
 
+/// Revisions are three digit numbers, e.g. `001`.
+fn is_revision(input: &str) -> bool {
+    input.len() == 3 && input.bytes().all(|b| b.is_ascii_digit())
+}
+
 fn parse_model_and_version(
     s: &str,
     separator: char,
@@ -730,8 +1194,8 @@ fn parse_model_and_version(
         // Try parsing as date first (prioritize full dates like YYYY-MM-DD over
         // MM-DD)
         if let Some((dt, fmt)) = parse_date(candidate) {
-            // Prefer YYYY-MM-DD and YYYYMMDD formats over MM-DD
-            if fmt == "%Y-%m-%d" || fmt == "%Y%m%d" {
+            // Prefer dates with a year over MM-DD
+            if !YEARLESS_DATE_FORMATS.contains(&fmt) {
                 let model = &s[..*idx];
                 return (
                     model,
@@ -970,8 +1434,11 @@ mod tests {
             panic!("Expected ModelIdWithVersion with OpenAI provider");
         };
         assert!(matches!(provider, InferenceProvider::OpenAI));
-        assert_eq!(model_with_version.model, "o1");
-        assert!(matches!(model_with_version.version, Version::Preview));
+        assert_eq!(model_with_version.model, "o1-preview");
+        assert!(matches!(
+            model_with_version.version,
+            Version::ImplicitLatest
+        ));
 
         assert_eq!(result.to_string(), model_id_str);
     }
@@ -1257,6 +1724,65 @@ mod tests {
         assert!(result.is_ok());
     }
 
+    #[test]
+    fn test_bedrock_foundation_model_arn() {
+        let arn = "arn:aws:bedrock:us-east-1::foundation-model/anthropic.\
+                   claude-3-5-sonnet-20240620-v1:0";
+        let result =
+            ModelId::from_str_and_provider(InferenceProvider::Bedrock, arn)
+                .unwrap();
+        let ModelId::Bedrock(bedrock_model) = &result else {
+            panic!("Expected Bedrock ModelId, got: {result:?}");
+        };
+        assert_eq!(bedrock_model.geo, None);
+        assert_eq!(bedrock_model.provider, "anthropic");
+        assert_eq!(bedrock_model.model, "claude-3-5-sonnet");
+        assert_eq!(bedrock_model.bedrock_internal_version, "v1:0");
+        assert_eq!(
+            bedrock_model.arn_prefix.as_deref(),
+            Some("arn:aws:bedrock:us-east-1::foundation-model/")
+        );
+        assert_eq!(result.to_string(), arn);
+
+        let model_id = ModelId::from_str_and_provider(
+            InferenceProvider::Bedrock,
+            "anthropic.claude-3-5-sonnet-20240620-v1:0",
+        )
+        .unwrap();
+        assert_ne!(result, model_id);
+        assert!(result.same_target(&model_id));
+    }
+
+    #[test]
+    fn test_bedrock_inference_profile_arn() {
+        let arn = "arn:aws:bedrock:us-east-1:123456789012:inference-profile/\
+                   us.anthropic.claude-3-5-sonnet-20240620-v1:0";
+        let result = ModelId::from_str(&format!("bedrock/{arn}")).unwrap();
+        let ModelId::Bedrock(bedrock_model) = &result else {
+            panic!("Expected Bedrock ModelId, got: {result:?}");
+        };
+        assert_eq!(bedrock_model.geo.as_deref(), Some("us"));
+        assert_eq!(bedrock_model.model, "claude-3-5-sonnet");
+        assert!(bedrock_model.is_arn());
+        assert_eq!(result.to_string(), arn);
+    }
+
+    #[test]
+    fn test_bedrock_invalid_arn() {
+        for arn in [
+            "arn:aws:bedrock:us-east-1:123456789012:provisioned-model/abc123",
+            "arn:aws:s3:::bucket/anthropic.claude-3-haiku-20240307-v1:0",
+            "arn:aws:bedrock:us-east-1::foundation-model",
+        ] {
+            let result =
+                ModelId::from_str_and_provider(InferenceProvider::Bedrock, arn);
+            assert!(
+                matches!(result, Err(MapperError::InvalidModelName(_))),
+                "{arn}: {result:?}"
+            );
+        }
+    }
+
     #[test]
     fn test_bedrock_openai_invalid_format() {
         let result = ModelId::from_str_and_provider(
@@ -1995,6 +2521,97 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_from_str_google_gemini_revision() {
+        let model_str = "gemini/gemini-1.5-pro-002";
+        let result = ModelId::from_str(model_str).unwrap();
+
+        let ModelId::ModelIdWithVersion {
+            provider: InferenceProvider::GoogleGemini,
+            id: model_with_version,
+        } = &result
+        else {
+            panic!("Expected ModelIdWithVersion with GoogleGemini provider");
+        };
+        assert_eq!(model_with_version.model, "gemini-1.5-pro");
+        assert_eq!(
+            model_with_version.version,
+            Version::Revision("002".to_string())
+        );
+        assert_eq!(result.to_string(), "gemini-1.5-pro-002");
+        assert_eq!(result.cache_key(), "v2|gemini|gemini-1.5-pro|revision:002");
+
+        let lite = ModelId::from_str_and_provider(
+            InferenceProvider::GoogleGemini,
+            "gemini-2.0-flash-lite-001",
+        )
+        .unwrap();
+        assert_eq!(lite.as_model_name().to_string(), "gemini-2.0-flash-lite");
+
+        // only Gemini names its snapshots with revisions
+        let openai = ModelId::from_str_and_provider(
+            InferenceProvider::OpenAI,
+            "davinci-002",
+        )
+        .unwrap();
+        assert_eq!(openai.as_model_name().to_string(), "davinci-002");
+    }
+
+    #[test]
+    fn test_embedding_model_names_are_kept_whole() {
+        let name = |provider: InferenceProvider, s: &str| {
+            ModelId::from_str_and_provider(provider, s)
+                .unwrap()
+                .as_model_name()
+                .to_string()
+        };
+
+        assert_eq!(
+            name(InferenceProvider::GoogleGemini, "text-embedding-004"),
+            "text-embedding-004"
+        );
+        assert_eq!(
+            name(InferenceProvider::GoogleGemini, "gemini-embedding-001"),
+            "gemini-embedding-001"
+        );
+        assert_eq!(
+            name(InferenceProvider::OpenAI, "text-embedding-3-small"),
+            "text-embedding-3-small"
+        );
+        assert_eq!(
+            name(InferenceProvider::OpenAI, "text-embedding-ada-002"),
+            "text-embedding-ada-002"
+        );
+    }
+
+    #[test]
+    fn test_google_gemini_revision_order() {
+        let parse = |s: &str| {
+            let ModelId::ModelIdWithVersion { id, .. } =
+                ModelId::from_str_and_provider(
+                    InferenceProvider::GoogleGemini,
+                    s,
+                )
+                .unwrap()
+            else {
+                panic!("Expected ModelIdWithVersion");
+            };
+            id.version
+        };
+
+        assert!(parse("gemini-1.5-pro-001") < parse("gemini-1.5-pro-002"));
+        assert!(parse("gemini-1.5-pro-002") < parse("gemini-1.5-pro-latest"));
+        assert!(parse("gemini-1.5-pro-002") < parse("gemini-1.5-pro"));
+        assert!(
+            ModelId::new(
+                InferenceProvider::GoogleGemini,
+                "gemini-1.5-pro",
+                Version::Revision("002".to_string()),
+            )
+            .is_ok()
+        );
+    }
+
     #[test]
     fn test_from_str_invalid_no_slash() {
         let result = ModelId::from_str("gpt-4");
@@ -2428,4 +3045,330 @@ mod tests {
 
         assert_eq!(model_with_version.to_string(), model_id_str);
     }
+
+    #[test]
+    fn test_parse_unqualified_splits_dated_model() {
+        let (model, version) =
+            ModelId::parse_unqualified("claude-3-opus-20240229");
+        assert_eq!(model, "claude-3-opus");
+        let Version::Date { date, format } = version else {
+            panic!("Expected date version");
+        };
+        let expected_dt: DateTime<Utc> =
+            "2024-02-29T00:00:00Z".parse().unwrap();
+        assert_eq!(date, expected_dt);
+        assert_eq!(format, "%Y%m%d");
+
+        let (model, version) = ModelId::parse_unqualified("gpt-4o");
+        assert_eq!(model, "gpt-4o");
+        assert_eq!(version, Version::ImplicitLatest);
+
+        // provider-specific parsing is skipped
+        let (model, version) =
+            ModelId::parse_unqualified("anthropic.claude-3-haiku-v1:0");
+        assert_eq!(model, "anthropic.claude-3-haiku-v1:0");
+        assert_eq!(version, Version::ImplicitLatest);
+    }
+
+    #[test]
+    fn test_cache_key_is_pinned() {
+        let cases = [
+            (
+                InferenceProvider::OpenAI,
+                "gpt-4",
+                "v2|openai|gpt-4|implicit-latest",
+            ),
+            (
+                InferenceProvider::OpenAI,
+                "gpt-4-2024-08-15",
+                "v2|openai|gpt-4|date:2024-08-15T00:00:00Z:%Y-%m-%d",
+            ),
+            (
+                InferenceProvider::OpenAI,
+                "o1-preview-2024-09-12",
+                "v2|openai|o1|preview-date:2024-09-12T00:00:00Z:%Y-%m-%d",
+            ),
+            (
+                InferenceProvider::OpenAI,
+                "o1-preview",
+                "v2|openai|o1-preview|implicit-latest",
+            ),
+            (
+                InferenceProvider::Anthropic,
+                "claude-3-opus-20240229",
+                "v2|anthropic|claude-3-opus|date:2024-02-29T00:00:00Z:%Y%m%d",
+            ),
+            (
+                InferenceProvider::Anthropic,
+                "claude-3-7-sonnet-latest",
+                "v2|anthropic|claude-3-7-sonnet|latest",
+            ),
+            (
+                InferenceProvider::Bedrock,
+                "us.anthropic.claude-3-haiku-20240307-v1:0",
+                concat!(
+                    "v2|bedrock|us|anthropic|claude-3-haiku|",
+                    "date:2024-03-07T00:00:00Z:%Y%m%d|v1:0"
+                ),
+            ),
+            (
+                InferenceProvider::Ollama,
+                "gemma3:1b",
+                "v2|ollama|gemma3|1b",
+            ),
+            (InferenceProvider::Ollama, "gemma3", "v2|ollama|gemma3|"),
+            (
+                InferenceProvider::Named("groq".into()),
+                "meta-llama/llama-guard-4-12b",
+                "v2|groq|meta-llama/llama-guard-4-12b|implicit-latest",
+            ),
+        ];
+        for (provider, model, expected) in cases {
+            let model_id =
+                ModelId::from_str_and_provider(provider, model).unwrap();
+            assert_eq!(model_id.cache_key(), expected, "cache key for {model}");
+        }
+        assert_eq!(
+            ModelId::Unknown("custom".to_string()).cache_key(),
+            "v2|unknown|custom"
+        );
+    }
+
+    #[test]
+    fn test_cache_key_keeps_the_time_of_day() {
+        let key = |model: &str| {
+            ModelId::from_str_and_provider(InferenceProvider::OpenAI, model)
+                .unwrap()
+                .cache_key()
+        };
+        assert_eq!(
+            key("gpt-4-2024-05-13T10:30:00Z"),
+            "v2|openai|gpt-4|date:2024-05-13T10:30:00Z:%Y-%m-%dT%H:%M:%SZ"
+        );
+        assert_ne!(
+            key("gpt-4-2024-05-13T10:30:00Z"),
+            key("gpt-4-2024-05-13T18:00:00Z")
+        );
+        // the same instant written with another offset is the same snapshot
+        assert_eq!(
+            key("gpt-4-2024-05-13T12:30:00+02:00"),
+            key("gpt-4-2024-05-13T10:30:00+00:00")
+        );
+    }
+
+    #[test]
+    fn test_rfc3339_versions_round_trip() {
+        for model in [
+            "gpt-4-2024-05-13T10:30:00Z",
+            "gpt-4-2024-05-13T12:30:00+02:00",
+            "gpt-4-2024-05-13T05:00:00-05:30",
+        ] {
+            let id = ModelIdWithVersion::from_str(model).unwrap();
+            assert_eq!(id.to_string(), model);
+            let reparsed =
+                ModelIdWithVersion::from_str(&id.to_string()).unwrap();
+            assert_eq!(reparsed, id);
+        }
+    }
+
+    #[test]
+    fn test_name_suffixes_are_not_versions() {
+        let parse = |s: &str| {
+            let ModelId::ModelIdWithVersion { id, .. } =
+                ModelId::from_str_and_provider(InferenceProvider::OpenAI, s)
+                    .unwrap()
+            else {
+                panic!("Expected ModelIdWithVersion for {s}");
+            };
+            id
+        };
+
+        let id = parse("gpt-4-turbo-preview");
+        assert_eq!(id.model, "gpt-4-turbo-preview");
+        assert_eq!(id.version, Version::ImplicitLatest);
+        assert_eq!(id.to_string(), "gpt-4-turbo-preview");
+
+        let id = parse("mistral-7b-instruct");
+        assert_eq!(id.model, "mistral-7b-instruct");
+        assert_eq!(id.version, Version::ImplicitLatest);
+
+        let id = parse("claude-3-7-sonnet-latest");
+        assert_eq!(id.model, "claude-3-7-sonnet");
+        assert_eq!(id.version, Version::Latest);
+
+        let id = parse("gpt-4o-2024-08-06");
+        assert_eq!(id.model, "gpt-4o");
+        assert!(matches!(
+            id.version,
+            Version::Date {
+                format: "%Y-%m-%d",
+                ..
+            }
+        ));
+
+        let id = parse("o1-preview-2024-09-12");
+        assert_eq!(id.model, "o1");
+        assert!(matches!(id.version, Version::DateVersionedPreview { .. }));
+
+        // without the suffix list, `preview` is parsed as a version again
+        let ModelId::ModelIdWithVersion { id, .. } =
+            ModelId::from_str_and_provider_with_suffixes::<&str>(
+                InferenceProvider::OpenAI,
+                "gpt-4-turbo-preview",
+                &[],
+            )
+            .unwrap()
+        else {
+            panic!("Expected ModelIdWithVersion");
+        };
+        assert_eq!(id.model, "gpt-4-turbo");
+        assert_eq!(id.version, Version::Preview);
+    }
+
+    #[test]
+    fn test_model_id_builder_validates_parts() {
+        let model = ModelId::builder(InferenceProvider::Anthropic)
+            .model("claude-3-opus")
+            .version(Version::Latest)
+            .build()
+            .unwrap();
+        assert_eq!(model.to_string(), "claude-3-opus-latest");
+        assert_eq!(
+            model,
+            ModelId::from_str_and_provider(
+                InferenceProvider::Anthropic,
+                "claude-3-opus-latest"
+            )
+            .unwrap()
+        );
+
+        let date = Utc
+            .with_ymd_and_hms(2024, 8, 6, 0, 0, 0)
+            .unwrap()
+            .fixed_offset();
+        let model = ModelId::new(
+            InferenceProvider::OpenAI,
+            "gpt-4o",
+            Version::Date {
+                date,
+                format: "%Y-%m-%d",
+            },
+        )
+        .unwrap();
+        assert_eq!(model.to_string(), "gpt-4o-2024-08-06");
+
+        let model = ModelId::builder(InferenceProvider::Ollama)
+            .model("gemma3:1b")
+            .build()
+            .unwrap();
+        assert!(matches!(model, ModelId::Ollama(_)));
+
+        // `preview` is part of the name, so it can't also be the version
+        assert!(
+            ModelId::new(InferenceProvider::OpenAI, "gpt-4", Version::Preview)
+                .is_err()
+        );
+        assert!(
+            ModelId::new(InferenceProvider::Ollama, "gemma3", Version::Latest)
+                .is_err()
+        );
+        assert!(ModelId::builder(InferenceProvider::OpenAI).build().is_err());
+    }
+
+    #[test]
+    fn test_same_target_ignores_implicit_vs_explicit_latest() {
+        let parse = |provider: InferenceProvider, s: &str| {
+            ModelId::from_str_and_provider(provider, s).unwrap()
+        };
+
+        let implicit = parse(InferenceProvider::OpenAI, "gpt-4");
+        let explicit = parse(InferenceProvider::OpenAI, "gpt-4-latest");
+        assert_ne!(implicit, explicit);
+        assert!(implicit.same_target(&explicit));
+        assert!(explicit.same_target(&implicit));
+
+        let dated = parse(InferenceProvider::OpenAI, "gpt-4-2024-08-06");
+        assert!(!implicit.same_target(&dated));
+        assert!(dated.same_target(&dated.clone()));
+        let other_provider = parse(InferenceProvider::Anthropic, "gpt-4");
+        assert!(!implicit.same_target(&other_provider));
+
+        let untagged = parse(InferenceProvider::Ollama, "gemma3");
+        let latest = parse(InferenceProvider::Ollama, "gemma3:latest");
+        let sized = parse(InferenceProvider::Ollama, "gemma3:1b");
+        assert_ne!(untagged, latest);
+        assert!(untagged.same_target(&latest));
+        assert!(!untagged.same_target(&sized));
+    }
+
+    #[test]
+    fn test_version_order() {
+        let parse = |s: &str| ModelIdWithVersion::from_str(s).unwrap().version;
+        let opus_feb = parse("claude-3-opus-20240229");
+        let opus_mar = parse("claude-3-opus-2024-03-07");
+        let sonnet = parse("claude-3-sonnet");
+        assert!(opus_feb < opus_mar);
+        assert!(opus_mar < sonnet);
+        assert_eq!(sonnet, Version::ImplicitLatest);
+        assert!(Version::Latest < Version::ImplicitLatest);
+        assert!(Version::Preview < Version::Latest);
+        assert!(parse("gpt-4-2024-08-06") < Version::Preview);
+
+        // same day in different formats is ordered but not equal
+        let compact = parse("claude-3-opus-20240307");
+        assert_ne!(compact, opus_mar);
+        assert_ne!(compact.cmp(&opus_mar), Ordering::Equal);
+        assert_eq!(compact.cmp(&compact.clone()), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_hyphenated_and_rfc3339_date_versions() {
+        let model_id_str = "gpt-4-2024-05-13";
+        let result = ModelId::from_str_and_provider(
+            InferenceProvider::OpenAI,
+            model_id_str,
+        )
+        .unwrap();
+        let ModelId::ModelIdWithVersion { id, .. } = &result else {
+            panic!("Expected ModelIdWithVersion");
+        };
+        assert_eq!(id.model, "gpt-4");
+        let Version::Date { date, format } = &id.version else {
+            panic!("Expected date version");
+        };
+        let expected_dt: DateTime<Utc> =
+            "2024-05-13T00:00:00Z".parse().unwrap();
+        assert_eq!(*date, expected_dt);
+        assert_eq!(*format, "%Y-%m-%d");
+        assert_eq!(result.to_string(), model_id_str);
+        assert_eq!(
+            serde_json::to_string(&id).unwrap(),
+            format!("\"{model_id_str}\"")
+        );
+        let round_tripped: ModelIdWithVersion =
+            serde_json::from_str(&serde_json::to_string(&id).unwrap()).unwrap();
+        assert_eq!(&round_tripped, id);
+
+        let model_id_str = "gpt-4-2024-05-13T10:30:00Z";
+        let id = ModelIdWithVersion::from_str(model_id_str).unwrap();
+        assert_eq!(id.model, "gpt-4");
+        let Version::Date { date, .. } = &id.version else {
+            panic!("Expected date version");
+        };
+        let expected_dt: DateTime<Utc> =
+            "2024-05-13T10:30:00Z".parse().unwrap();
+        assert_eq!(*date, expected_dt);
+        assert_eq!(id.to_string(), model_id_str);
+
+        let id =
+            ModelIdWithVersion::from_str("gpt-4-2024-05-13T12:30:00+02:00")
+                .unwrap();
+        assert_eq!(id.model, "gpt-4");
+        assert_eq!(id.to_string(), "gpt-4-2024-05-13T12:30:00+02:00");
+
+        // not a date in any format, so part of the name
+        let id = ModelIdWithVersion::from_str("gpt-4-2024-13-45").unwrap();
+        assert_eq!(id.model, "gpt-4-2024-13-45");
+        assert_eq!(id.version, Version::ImplicitLatest);
+    }
 }
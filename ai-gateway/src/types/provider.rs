@@ -8,7 +8,10 @@ use tokio::sync::RwLock;
 
 use super::secret::Secret;
 use crate::{
-    config::{Config, providers::ProvidersConfig},
+    config::{
+        Config,
+        providers::{ProvidersConfig, ProvidersConfigError},
+    },
     endpoints::ApiEndpoint,
     error::provider::ProviderError,
     metrics::Metrics,
@@ -56,16 +59,16 @@ impl Serialize for ModelProvider {
     }
 }
 
+/// A provider requests can be routed to, keyed by its name in the config
+/// (see [`AsRef<str>`](#impl-AsRef<str>-for-InferenceProvider)).
+///
+/// Built-in names take precedence over [`InferenceProvider::Named`]: `openai`
+/// always deserializes to [`InferenceProvider::OpenAI`], and a
+/// `Named("openai")` constructed in code is equal to (and hashes like)
+/// [`InferenceProvider::OpenAI`], so the two can never end up as distinct
+/// keys of the same map.
 #[derive(
-    Debug,
-    Clone,
-    Default,
-    Eq,
-    Hash,
-    PartialEq,
-    EnumIter,
-    serde::Serialize,
-    serde::Deserialize,
+    Debug, Clone, Default, EnumIter, serde::Serialize, serde::Deserialize,
 )]
 #[serde(rename_all = "kebab-case")]
 pub enum InferenceProvider {
@@ -110,12 +113,25 @@ impl InferenceProvider {
                     .map(ApiEndpoint::Google)
                     .collect()
             }
+            InferenceProvider::Named(name)
+                if name == crate::endpoints::cohere::COHERE =>
+            {
+                crate::endpoints::cohere::Cohere::iter()
+                    .map(ApiEndpoint::Cohere)
+                    .collect()
+            }
             InferenceProvider::Named(_) => {
                 crate::endpoints::openai::OpenAI::iter()
                     .map(|endpoint| ApiEndpoint::OpenAICompatible {
                         provider: self.clone(),
                         openai_endpoint: endpoint,
                     })
+                    // in case it is self-hosted with the native Ollama dialect
+                    .chain(std::iter::once(ApiEndpoint::OllamaCompatible {
+                        provider: self.clone(),
+                        ollama_endpoint: crate::endpoints::ollama::Ollama::chat(
+                        ),
+                    }))
                     .collect()
             }
         }
@@ -141,6 +157,25 @@ impl InferenceProvider {
             _ => Err(ProviderError::InvalidProviderName(provider_name.into())),
         }
     }
+
+    /// The name of an [`InferenceProvider::Named`] provider trimmed and
+    /// lowercased, so that `OpenRouter` and ` openrouter ` name the same
+    /// provider.
+    ///
+    /// Built-in names must match exactly: built-in providers are returned
+    /// as is, and so is a name that only matches a built-in provider once
+    /// normalized, e.g. `OpenAI`.
+    #[must_use]
+    pub fn normalized(&self) -> Self {
+        let InferenceProvider::Named(name) = self else {
+            return self.clone();
+        };
+        let normalized = name.trim().to_lowercase();
+        match InferenceProvider::from_str(&normalized) {
+            Ok(provider @ InferenceProvider::Named(_)) => provider,
+            _ => self.clone(),
+        }
+    }
 }
 
 impl FromStr for InferenceProvider {
@@ -173,6 +208,20 @@ impl AsRef<str> for InferenceProvider {
     }
 }
 
+impl PartialEq for InferenceProvider {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ref() == other.as_ref()
+    }
+}
+
+impl Eq for InferenceProvider {}
+
+impl std::hash::Hash for InferenceProvider {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_ref().hash(state);
+    }
+}
+
 impl std::fmt::Display for InferenceProvider {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -188,6 +237,8 @@ pub enum ProviderKey {
     AwsCredentials {
         access_key: Secret<String>,
         secret_key: Secret<String>,
+        /// Set for temporary credentials, e.g. those of an SSO session.
+        session_token: Option<Secret<String>>,
     },
     NotRequired,
 }
@@ -209,21 +260,37 @@ impl ProviderKey {
             ProviderKey::AwsCredentials {
                 access_key,
                 secret_key,
+                ..
             } => (Some(access_key), Some(secret_key)),
             _ => (None, None),
         }
     }
 
+    #[must_use]
+    pub fn aws_session_token(&self) -> Option<&Secret<String>> {
+        match self {
+            ProviderKey::AwsCredentials { session_token, .. } => {
+                session_token.as_ref()
+            }
+            _ => None,
+        }
+    }
+
     #[must_use]
     pub fn from_env(provider: &InferenceProvider) -> Option<Self> {
         if *provider == InferenceProvider::Bedrock {
-            if let (Ok(access_key), Ok(secret_key)) = (
-                std::env::var("AWS_ACCESS_KEY"),
-                std::env::var("AWS_SECRET_KEY"),
-            ) {
+            // the names used by the AWS CLI and SDKs are accepted as well
+            let access_key = std::env::var("AWS_ACCESS_KEY")
+                .or_else(|_| std::env::var("AWS_ACCESS_KEY_ID"));
+            let secret_key = std::env::var("AWS_SECRET_KEY")
+                .or_else(|_| std::env::var("AWS_SECRET_ACCESS_KEY"));
+            if let (Ok(access_key), Ok(secret_key)) = (access_key, secret_key) {
                 Some(ProviderKey::AwsCredentials {
                     access_key: Secret::from(access_key),
                     secret_key: Secret::from(secret_key),
+                    session_token: std::env::var("AWS_SESSION_TOKEN")
+                        .ok()
+                        .map(Secret::from),
                 })
             } else {
                 None
@@ -247,17 +314,19 @@ pub enum ProviderKeys {
 }
 
 impl ProviderKeys {
-    #[must_use]
-    pub fn new(config: &Config, metrics: &Metrics) -> Self {
+    pub fn new(
+        config: &Config,
+        metrics: &Metrics,
+    ) -> Result<Self, ProvidersConfigError> {
         if config.deployment_target.is_cloud() {
-            Self::Cloud(RwLock::new(HashMap::default()))
+            Ok(Self::Cloud(RwLock::new(HashMap::default())))
         } else {
-            let keys = ProviderKeyMap::from_env(&config.providers);
+            let keys = ProviderKeyMap::from_env(&config.providers)?;
             metrics
                 .routers
                 .provider_api_keys
                 .add(i64::try_from(keys.len()).unwrap_or(i64::MAX), &[]);
-            Self::Sidecar(ProviderKeyMap::from_env(&config.providers))
+            Ok(Self::Sidecar(keys))
         }
     }
 
@@ -327,21 +396,33 @@ impl ProviderKeyMap {
         Self(Arc::new(provider_keys))
     }
 
-    pub fn from_env(providers_config: &ProvidersConfig) -> Self {
+    /// The keys of the providers, read from the variable declared with
+    /// `api-key-env` or else from the default variables of the provider.
+    ///
+    /// A declared variable that is not set is an error rather than a
+    /// provider without a key.
+    pub fn from_env(
+        providers_config: &ProvidersConfig,
+    ) -> Result<Self, ProvidersConfigError> {
         tracing::debug!("Discovering provider keys");
         let mut keys = HashMap::default();
 
-        for (provider, _config) in providers_config.iter() {
+        for (provider, config) in providers_config.iter() {
             if provider == &InferenceProvider::Ollama {
                 // ollama doesn't require an API key
                 continue;
             }
-            if let Some(key) = ProviderKey::from_env(provider) {
+            // bedrock can't declare a key, see `ProvidersConfig::validate`
+            if let Some(key) = config
+                .resolve_api_key()?
+                .map(|key| ProviderKey::Secret(Secret::from(key)))
+                .or_else(|| ProviderKey::from_env(provider))
+            {
                 keys.insert(provider.clone(), key);
             }
         }
 
-        Self(Arc::new(keys))
+        Ok(Self(Arc::new(keys)))
     }
 }
 
@@ -349,6 +430,23 @@ impl ProviderKeyMap {
 mod tests {
     use super::*;
 
+    #[test]
+    fn declared_but_unset_api_key_is_an_error() {
+        let yaml = r"
+openai:
+  models:
+    - gpt-4o
+  base-url: https://api.openai.com
+  api-key-env: AI_GATEWAY_TEST_UNSET_PROVIDER_KEY
+";
+        let config: ProvidersConfig = serde_yml::from_str(yaml).unwrap();
+        assert!(matches!(
+            ProviderKeyMap::from_env(&config),
+            Err(ProvidersConfigError::MissingApiKey(var))
+                if var == "AI_GATEWAY_TEST_UNSET_PROVIDER_KEY"
+        ));
+    }
+
     #[test]
     fn inference_provider_as_ref() {
         let named_provider = InferenceProvider::Named("test".into());
@@ -362,4 +460,108 @@ mod tests {
         let named_provider_str = named_provider.to_string();
         assert_eq!("test", named_provider_str);
     }
+
+    fn built_in_providers() -> impl Iterator<Item = InferenceProvider> {
+        InferenceProvider::iter()
+            .filter(|provider| !matches!(provider, InferenceProvider::Named(_)))
+    }
+
+    #[test]
+    fn inference_provider_serde_keys() {
+        let expected = [
+            (InferenceProvider::OpenAI, "openai"),
+            (InferenceProvider::Anthropic, "anthropic"),
+            (InferenceProvider::Bedrock, "bedrock"),
+            (InferenceProvider::Ollama, "ollama"),
+            (InferenceProvider::GoogleGemini, "gemini"),
+            (InferenceProvider::Named("groq".into()), "groq"),
+            (
+                InferenceProvider::Named("azure-openai".into()),
+                "azure-openai",
+            ),
+        ];
+        assert_eq!(built_in_providers().count() + 2, expected.len());
+
+        for (provider, key) in expected {
+            assert_eq!(provider.as_ref(), key);
+            assert_eq!(provider.to_string(), key);
+            assert_eq!(
+                serde_json::to_string(&provider).unwrap(),
+                format!("\"{key}\"")
+            );
+            let deserialized: InferenceProvider =
+                serde_json::from_str(&format!("\"{key}\"")).unwrap();
+            assert_eq!(deserialized, provider);
+            assert_eq!(
+                std::mem::discriminant(&deserialized),
+                std::mem::discriminant(&provider)
+            );
+            assert_eq!(InferenceProvider::from_str(key).unwrap(), deserialized);
+        }
+    }
+
+    #[test]
+    fn inference_provider_map_keys_round_trip() {
+        let map = built_in_providers()
+            .chain([InferenceProvider::Named("groq".into())])
+            .zip(0..)
+            .collect::<indexmap::IndexMap<_, usize>>();
+        let yaml = serde_yml::to_string(&map).unwrap();
+        let deserialized: indexmap::IndexMap<InferenceProvider, usize> =
+            serde_yml::from_str(&yaml).unwrap();
+        assert_eq!(deserialized, map);
+        assert!(matches!(
+            deserialized.keys().next(),
+            Some(InferenceProvider::OpenAI)
+        ));
+    }
+
+    #[test]
+    fn normalized_named_providers() {
+        for name in ["openrouter", "OpenRouter", " openrouter ", "OPENROUTER\t"]
+        {
+            let InferenceProvider::Named(normalized) =
+                InferenceProvider::Named(name.into()).normalized()
+            else {
+                panic!("expected a named provider for {name:?}");
+            };
+            assert_eq!(normalized, "openrouter");
+        }
+        for provider in built_in_providers() {
+            assert_eq!(provider.normalized().as_ref(), provider.as_ref());
+        }
+        let InferenceProvider::Named(name) =
+            InferenceProvider::Named("OpenAI".into()).normalized()
+        else {
+            panic!("built-in names are not normalized");
+        };
+        assert_eq!(name, "OpenAI");
+    }
+
+    #[test]
+    fn built_in_names_take_precedence_over_named() {
+        for provider in built_in_providers() {
+            let named = InferenceProvider::Named(provider.as_ref().into());
+            assert_eq!(named, provider);
+
+            let deserialized: InferenceProvider =
+                serde_json::from_str(&serde_json::to_string(&named).unwrap())
+                    .unwrap();
+            assert!(!matches!(deserialized, InferenceProvider::Named(_)));
+
+            let mut map = indexmap::IndexMap::new();
+            map.insert(provider.clone(), "built-in");
+            map.insert(named, "named");
+            assert_eq!(map.len(), 1);
+        }
+        // keys are case-sensitive, so these stay named providers
+        assert!(matches!(
+            serde_json::from_str::<InferenceProvider>("\"OpenAI\"").unwrap(),
+            InferenceProvider::Named(_)
+        ));
+        assert_ne!(
+            InferenceProvider::Named("OpenAI".into()),
+            InferenceProvider::OpenAI
+        );
+    }
 }
@@ -0,0 +1,179 @@
+//! `/v1/keys`, minting, listing and revoking virtual keys.
+//!
+//! - `POST /v1/keys` mints a key with the scopes of the [`NewVirtualKey`] body.
+//!   The response is the only time the key itself is returned.
+//! - `GET /v1/keys` lists all keys, without the keys themselves.
+//! - `DELETE /v1/keys/{id}` revokes a key.
+//!
+//! Requires the admin key, see [`AdminConfig`], and is only served if one is
+//! configured along with [`VirtualKeysConfig`].
+//!
+//! [`AdminConfig`]: crate::config::admin::AdminConfig
+//! [`VirtualKeysConfig`]: crate::config::virtual_keys::VirtualKeysConfig
+use std::task::{Context, Poll};
+
+use axum_core::response::{IntoResponse, Response};
+use futures::future::{BoxFuture, Either};
+use http::{Method, StatusCode};
+use http_body_util::BodyExt;
+use serde::Serialize;
+use tower::{Layer, Service};
+use uuid::Uuid;
+
+use super::{NewVirtualKey, VirtualKey};
+use crate::{
+    admin::on_admin_port,
+    app_state::AppState,
+    error::{
+        api::ApiError, auth::AuthError, internal::InternalError,
+        invalid_req::InvalidRequestError,
+    },
+    types::{json::Json, request::Request},
+};
+
+pub const KEYS_PATH: &str = "/v1/keys";
+
+#[derive(Debug, Clone)]
+pub struct KeysLayer {
+    app_state: AppState,
+}
+
+impl KeysLayer {
+    #[must_use]
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+}
+
+impl<S> Layer<S> for KeysLayer {
+    type Service = KeysService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        KeysService {
+            inner,
+            app_state: self.app_state.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct KeysService<S> {
+    inner: S,
+    app_state: AppState,
+}
+
+impl<S> Service<Request> for KeysService<S>
+where
+    S: Service<Request, Response = Response>,
+    S::Error: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Either<
+        BoxFuture<'static, Result<Self::Response, Self::Error>>,
+        S::Future,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request) -> Self::Future {
+        let path = req.uri().path();
+        let is_keys_path = path == KEYS_PATH
+            || path
+                .strip_prefix(KEYS_PATH)
+                .is_some_and(|rest| rest.starts_with('/'));
+        if !is_keys_path
+            || !self.app_state.config().admin.serves(on_admin_port(&req))
+            || self.app_state.0.virtual_keys.is_none()
+        {
+            return Either::Right(self.inner.call(req));
+        }
+
+        let app_state = self.app_state.clone();
+        Either::Left(Box::pin(async move {
+            let authorization = req
+                .headers()
+                .get(http::header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok());
+            if !app_state.config().admin.is_authorized(authorization) {
+                return Ok(ApiError::Authentication(
+                    AuthError::InvalidCredentials,
+                )
+                .into_response());
+            }
+            Ok(keys_response(&app_state, req)
+                .await
+                .unwrap_or_else(IntoResponse::into_response))
+        }))
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct KeysResponse {
+    data: Vec<VirtualKey>,
+}
+
+#[derive(Debug, Serialize)]
+struct MintedKey {
+    key: String,
+    #[serde(flatten)]
+    virtual_key: VirtualKey,
+}
+
+async fn keys_response(
+    app_state: &AppState,
+    req: Request,
+) -> Result<Response, ApiError> {
+    let virtual_keys = app_state
+        .0
+        .virtual_keys
+        .as_ref()
+        .ok_or(InternalError::Internal)?;
+    let id = req
+        .uri()
+        .path()
+        .strip_prefix(KEYS_PATH)
+        .and_then(|rest| rest.strip_prefix('/'))
+        .map(ToString::to_string);
+    match (req.method().clone(), id) {
+        (Method::GET, None) => Ok(Json(KeysResponse {
+            data: virtual_keys.list().await?,
+        })
+        .into_response()),
+        (Method::POST, None) => {
+            let body = req
+                .into_body()
+                .collect()
+                .await
+                .map_err(InternalError::CollectBodyError)?
+                .to_bytes();
+            let new_key = serde_json::from_slice::<NewVirtualKey>(&body)
+                .map_err(InvalidRequestError::InvalidRequestBody)?;
+            let (key, virtual_key) = virtual_keys.mint(new_key).await?;
+            tracing::info!(key_id = %virtual_key.id, "minted virtual key");
+            Ok((
+                StatusCode::CREATED,
+                Json(MintedKey {
+                    key: key.expose().clone(),
+                    virtual_key,
+                }),
+            )
+                .into_response())
+        }
+        (Method::DELETE, Some(id)) => {
+            let not_found =
+                || InvalidRequestError::NotFound(format!("key {id}"));
+            let key_id = id.parse::<Uuid>().map_err(|_| not_found())?;
+            let virtual_key =
+                virtual_keys.revoke(key_id).await?.ok_or_else(not_found)?;
+            tracing::info!(key_id = %virtual_key.id, "revoked virtual key");
+            Ok(Json(virtual_key).into_response())
+        }
+        _ => Ok(StatusCode::METHOD_NOT_ALLOWED.into_response()),
+    }
+}
@@ -0,0 +1,480 @@
+//! API keys issued by the gateway, so that apps and teams can be handed a
+//! key of their own instead of sharing provider keys.
+//!
+//! Keys are minted, listed and revoked with the `/v1/keys` admin endpoint,
+//! see [`endpoint`]. Only the hash of a key is stored, the key itself is
+//! returned once when it is minted. A key can be limited to:
+//! - a set of routers, checked when the request is authenticated,
+//! - a set of models, checked against the `model` of the request before it is
+//!   routed, see [`scope`],
+//! - a spend ceiling in USD, compared to the cost of its requests so far,
+//! - an expiry.
+//!
+//! A key can also set the priority of its requests in the queues of routers,
+//! see [`crate::router::queue`].
+//!
+//! The keys are cached in memory and shared with other replicas through
+//! the database: a key that is not cached yet is looked up in the database,
+//! and the cache is reloaded every
+//! [`refresh_interval`](VirtualKeysConfig::refresh_interval) so that keys
+//! revoked and spend recorded by other replicas are taken into account, see
+//! [`refresh`].
+pub mod endpoint;
+pub mod refresh;
+pub mod scope;
+
+use chrono::{DateTime, Utc};
+use rand::{Rng, distr::Alphanumeric};
+use rust_decimal::Decimal;
+use rustc_hash::FxHashMap as HashMap;
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::{
+    config::virtual_keys::VirtualKeysConfig,
+    control_plane::types::hash_key,
+    error::{auth::AuthError, init::InitError, internal::InternalError},
+    router::queue::Priority,
+    store::{
+        from_nano_usd, to_nano_usd,
+        virtual_keys::{DbVirtualKey, VirtualKeyStore},
+    },
+    types::{router::RouterId, secret::Secret},
+};
+
+/// Virtual keys start with this prefix, to tell them apart from Helicone
+/// API keys.
+pub const VIRTUAL_KEY_PREFIX: &str = "sk-gw-";
+const KEY_LENGTH: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VirtualKey {
+    pub id: Uuid,
+    /// Usage of the key can be looked up with this hash, see
+    /// [`crate::metering::endpoint`].
+    pub key_hash: String,
+    pub name: Option<String>,
+    /// The routers the key can be used with, any router and the unified
+    /// API if `None`.
+    pub routers: Option<Vec<RouterId>>,
+    /// The models the key can request, any model if `None`.
+    pub models: Option<Vec<String>>,
+    pub spend_limit_usd: Option<Decimal>,
+    pub spent_usd: Decimal,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// The default and highest priority of the requests of the key.
+    pub priority: Option<Priority>,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl VirtualKey {
+    /// Whether the key can send a request to `router_id` at `now`, `None`
+    /// for requests to the unified API and direct proxies.
+    pub fn authorize(
+        &self,
+        router_id: Option<&RouterId>,
+        now: DateTime<Utc>,
+    ) -> Result<(), AuthError> {
+        if self.revoked_at.is_some() {
+            return Err(AuthError::InvalidCredentials);
+        }
+        if self.expires_at.is_some_and(|expires_at| expires_at <= now) {
+            return Err(AuthError::ExpiredCredentials);
+        }
+        if let Some(routers) = &self.routers
+            && !router_id.is_some_and(|router_id| routers.contains(router_id))
+        {
+            return Err(AuthError::RouterNotPermitted);
+        }
+        if self
+            .spend_limit_usd
+            .is_some_and(|spend_limit| self.spent_usd >= spend_limit)
+        {
+            return Err(AuthError::SpendLimitExceeded);
+        }
+        Ok(())
+    }
+
+    /// Whether the key can request `model`.
+    ///
+    /// Models of the allowlist are matched exactly, except for a trailing
+    /// `*` which matches any suffix, e.g. `openai/*`.
+    #[must_use]
+    pub fn allows_model(&self, model: &str) -> bool {
+        self.models.as_ref().is_none_or(|models| {
+            models
+                .iter()
+                .any(|allowed| match allowed.strip_suffix('*') {
+                    Some(prefix) => model.starts_with(prefix),
+                    None => allowed == model,
+                })
+        })
+    }
+}
+
+impl TryFrom<DbVirtualKey> for VirtualKey {
+    type Error = InternalError;
+
+    fn try_from(value: DbVirtualKey) -> Result<Self, Self::Error> {
+        fn list<T: DeserializeOwned>(
+            value: Option<String>,
+        ) -> Result<Option<Vec<T>>, InternalError> {
+            value
+                .map(|value| serde_json::from_str(&value))
+                .transpose()
+                .map_err(|error| InternalError::Deserialize {
+                    ty: "Vec<String>",
+                    error,
+                })
+        }
+        let timestamp = |millis: i64| {
+            DateTime::from_timestamp_millis(millis)
+                .ok_or(InternalError::Internal)
+        };
+        let decimal = |value: &str| {
+            value
+                .parse::<Decimal>()
+                .map_err(|_| InternalError::Internal)
+        };
+        Ok(Self {
+            id: value.id.parse().map_err(|_| InternalError::Internal)?,
+            key_hash: value.key_hash,
+            name: value.name,
+            routers: list(value.routers)?,
+            models: list(value.models)?,
+            spend_limit_usd: value
+                .spend_limit_usd
+                .as_deref()
+                .map(decimal)
+                .transpose()?,
+            spent_usd: from_nano_usd(value.spent_nano_usd),
+            expires_at: value.expires_at.map(timestamp).transpose()?,
+            priority: value
+                .priority
+                .map(|priority| priority.parse())
+                .transpose()
+                .map_err(|_| InternalError::Internal)?,
+            created_at: timestamp(value.created_at)?,
+            revoked_at: value.revoked_at.map(timestamp).transpose()?,
+        })
+    }
+}
+
+impl TryFrom<&VirtualKey> for DbVirtualKey {
+    type Error = InternalError;
+
+    fn try_from(value: &VirtualKey) -> Result<Self, Self::Error> {
+        fn list<T: Serialize>(
+            value: Option<&Vec<T>>,
+        ) -> Result<Option<String>, InternalError> {
+            value
+                .map(serde_json::to_string)
+                .transpose()
+                .map_err(|error| InternalError::Serialize {
+                    ty: "Vec<String>",
+                    error,
+                })
+        }
+        Ok(Self {
+            id: value.id.to_string(),
+            key_hash: value.key_hash.clone(),
+            name: value.name.clone(),
+            routers: list(value.routers.as_ref())?,
+            models: list(value.models.as_ref())?,
+            spend_limit_usd: value
+                .spend_limit_usd
+                .map(|spend_limit| spend_limit.to_string()),
+            spent_nano_usd: to_nano_usd(value.spent_usd)?,
+            expires_at: value.expires_at.map(|t| t.timestamp_millis()),
+            priority: value.priority.map(|priority| priority.to_string()),
+            created_at: value.created_at.timestamp_millis(),
+            revoked_at: value.revoked_at.map(|t| t.timestamp_millis()),
+        })
+    }
+}
+
+/// The scopes of a key to mint.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NewVirtualKey {
+    pub name: Option<String>,
+    pub routers: Option<Vec<RouterId>>,
+    pub models: Option<Vec<String>>,
+    pub spend_limit_usd: Option<Decimal>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub priority: Option<Priority>,
+}
+
+/// The virtual keys, by the hash of the key.
+#[derive(Debug)]
+pub struct VirtualKeys {
+    store: VirtualKeyStore,
+    keys: RwLock<HashMap<String, VirtualKey>>,
+}
+
+impl VirtualKeys {
+    pub async fn new(config: &VirtualKeysConfig) -> Result<Self, InitError> {
+        let store = VirtualKeyStore::connect(config).await?;
+        let keys = Self::load(&store)
+            .await
+            .map_err(|e| InitError::InitVirtualKeys(e.to_string()))?;
+        tracing::info!("loaded {} virtual keys", keys.len());
+        Ok(Self {
+            store,
+            keys: RwLock::new(keys),
+        })
+    }
+
+    async fn load(
+        store: &VirtualKeyStore,
+    ) -> Result<HashMap<String, VirtualKey>, InternalError> {
+        store
+            .get_all()
+            .await?
+            .into_iter()
+            .map(|key| {
+                VirtualKey::try_from(key).map(|key| (key.key_hash.clone(), key))
+            })
+            .collect()
+    }
+
+    #[must_use]
+    pub fn is_virtual_key(api_key: &str) -> bool {
+        api_key.starts_with(VIRTUAL_KEY_PREFIX)
+    }
+
+    /// The key with `key_hash`, looked up in the database if it is not
+    /// cached yet, e.g. because it was minted by another replica.
+    pub async fn get(&self, key_hash: &str) -> Option<VirtualKey> {
+        if let Some(key) = self.keys.read().await.get(key_hash) {
+            return Some(key.clone());
+        }
+        let key = match self.store.get_by_key_hash(key_hash).await {
+            Ok(key) => key?,
+            Err(e) => {
+                tracing::warn!(error = %e, "failed to look up virtual key");
+                return None;
+            }
+        };
+        let key = VirtualKey::try_from(key)
+            .inspect_err(|e| {
+                tracing::warn!(error = %e, "failed to read virtual key");
+            })
+            .ok()?;
+        self.keys
+            .write()
+            .await
+            .insert(key.key_hash.clone(), key.clone());
+        Some(key)
+    }
+
+    /// Reload every key from the database, so that keys revoked and spend
+    /// recorded by other replicas are taken into account.
+    pub async fn refresh(&self) -> Result<(), InternalError> {
+        let keys = Self::load(&self.store).await?;
+        *self.keys.write().await = keys;
+        Ok(())
+    }
+
+    /// All keys, including revoked and expired keys, oldest first.
+    pub async fn list(&self) -> Result<Vec<VirtualKey>, InternalError> {
+        self.refresh().await?;
+        let mut keys =
+            self.keys.read().await.values().cloned().collect::<Vec<_>>();
+        keys.sort_by_key(|key| (key.created_at, key.id));
+        Ok(keys)
+    }
+
+    /// Mint a key with the scopes of `new_key`, returning the key itself
+    /// along with its record.
+    pub async fn mint(
+        &self,
+        new_key: NewVirtualKey,
+    ) -> Result<(Secret<String>, VirtualKey), InternalError> {
+        let secret = rand::rng()
+            .sample_iter(&Alphanumeric)
+            .take(KEY_LENGTH)
+            .map(char::from)
+            .collect::<String>();
+        let api_key = format!("{VIRTUAL_KEY_PREFIX}{secret}");
+        let key = VirtualKey {
+            id: Uuid::now_v7(),
+            key_hash: hash_key(&api_key),
+            name: new_key.name,
+            routers: new_key.routers,
+            models: new_key.models,
+            spend_limit_usd: new_key.spend_limit_usd,
+            spent_usd: Decimal::ZERO,
+            expires_at: new_key.expires_at,
+            priority: new_key.priority,
+            created_at: Utc::now(),
+            revoked_at: None,
+        };
+        self.store.insert(&DbVirtualKey::try_from(&key)?).await?;
+        self.keys
+            .write()
+            .await
+            .insert(key.key_hash.clone(), key.clone());
+        Ok((Secret::from(api_key), key))
+    }
+
+    /// Revoke the key with `id`, returning `None` if there is no such key.
+    ///
+    /// Keys already revoked keep the time they were first revoked at.
+    pub async fn revoke(
+        &self,
+        id: Uuid,
+    ) -> Result<Option<VirtualKey>, InternalError> {
+        let id = id.to_string();
+        self.store
+            .set_revoked_at(&id, Utc::now().timestamp_millis())
+            .await?;
+        let Some(key) = self.store.get_by_id(&id).await? else {
+            return Ok(None);
+        };
+        let key = VirtualKey::try_from(key)?;
+        self.keys
+            .write()
+            .await
+            .insert(key.key_hash.clone(), key.clone());
+        Ok(Some(key))
+    }
+
+    /// Add `cost_usd` to the spend of the key with `key_hash`, if it is a
+    /// virtual key.
+    ///
+    /// The spend is added to the one in the database, so the spend of every
+    /// replica counts towards the spend limit of the key.
+    pub async fn add_spend(&self, key_hash: &str, cost_usd: Decimal) {
+        let Some(id) = self.get(key_hash).await.map(|key| key.id) else {
+            return;
+        };
+        let spent_usd = match to_nano_usd(cost_usd) {
+            Ok(cost_nano_usd) => self
+                .store
+                .add_spent_nano_usd(&id.to_string(), cost_nano_usd)
+                .await
+                .map(|spent| spent.map(from_nano_usd)),
+            Err(e) => Err(e),
+        };
+        let mut keys = self.keys.write().await;
+        let Some(key) = keys.get_mut(key_hash) else {
+            return;
+        };
+        match spent_usd {
+            Ok(Some(spent_usd)) => key.spent_usd = spent_usd,
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!(error = %e, key_id = %id, "failed to persist spend of virtual key");
+                key.spent_usd += cost_usd;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key() -> VirtualKey {
+        VirtualKey {
+            id: Uuid::now_v7(),
+            key_hash: hash_key("sk-gw-test"),
+            name: None,
+            routers: None,
+            models: None,
+            spend_limit_usd: None,
+            spent_usd: Decimal::ZERO,
+            expires_at: None,
+            priority: None,
+            created_at: Utc::now(),
+            revoked_at: None,
+        }
+    }
+
+    #[test]
+    fn test_authorize_routers() {
+        let router_id = RouterId::Named("my-router".into());
+        let other_router_id = RouterId::Named("other".into());
+        let key = VirtualKey {
+            routers: Some(vec![router_id.clone()]),
+            ..key()
+        };
+        let now = Utc::now();
+        assert!(key.authorize(Some(&router_id), now).is_ok());
+        assert!(matches!(
+            key.authorize(Some(&other_router_id), now),
+            Err(AuthError::RouterNotPermitted)
+        ));
+        assert!(matches!(
+            key.authorize(None, now),
+            Err(AuthError::RouterNotPermitted)
+        ));
+        assert!(key().authorize(None, now).is_ok());
+    }
+
+    #[test]
+    fn test_authorize_expiry_and_spend() {
+        let now = Utc::now();
+        let expired = VirtualKey {
+            expires_at: Some(now),
+            ..key()
+        };
+        assert!(matches!(
+            expired.authorize(None, now),
+            Err(AuthError::ExpiredCredentials)
+        ));
+        let spent = VirtualKey {
+            spend_limit_usd: Some(Decimal::ONE),
+            spent_usd: Decimal::ONE,
+            ..key()
+        };
+        assert!(matches!(
+            spent.authorize(None, now),
+            Err(AuthError::SpendLimitExceeded)
+        ));
+        let revoked = VirtualKey {
+            revoked_at: Some(now),
+            ..key()
+        };
+        assert!(matches!(
+            revoked.authorize(None, now),
+            Err(AuthError::InvalidCredentials)
+        ));
+    }
+
+    #[test]
+    fn test_allows_model() {
+        let key = VirtualKey {
+            models: Some(vec![
+                "openai/gpt-4o-mini".to_string(),
+                "anthropic/*".to_string(),
+            ]),
+            ..key()
+        };
+        assert!(key.allows_model("openai/gpt-4o-mini"));
+        assert!(key.allows_model("anthropic/claude-3-5-haiku"));
+        assert!(!key.allows_model("openai/gpt-4o"));
+        assert!(!key.allows_model("gpt-4o-mini"));
+        assert!(self::key().allows_model("openai/gpt-4o"));
+    }
+
+    #[test]
+    fn test_db_round_trip() {
+        let key = VirtualKey {
+            routers: Some(vec![RouterId::Named("my-router".into())]),
+            models: Some(vec!["openai/gpt-4o-mini".to_string()]),
+            spend_limit_usd: Some(Decimal::new(1050, 2)),
+            spent_usd: Decimal::new(885, 8),
+            expires_at: DateTime::from_timestamp_millis(1_700_000_000_000),
+            priority: Some(Priority::High),
+            created_at: DateTime::from_timestamp_millis(1_600_000_000_000)
+                .unwrap(),
+            ..key()
+        };
+        let db_key = DbVirtualKey::try_from(&key).unwrap();
+        assert_eq!(VirtualKey::try_from(db_key).unwrap(), key);
+    }
+}
@@ -0,0 +1,59 @@
+use futures::future::BoxFuture;
+use meltdown::Token;
+use tokio::time::{MissedTickBehavior, interval};
+use tracing::{info, warn};
+
+use crate::{app_state::AppState, error::runtime::RuntimeError};
+
+/// Reloads the virtual keys from the database every
+/// [`refresh_interval`](crate::config::virtual_keys::VirtualKeysConfig::refresh_interval),
+/// see [`VirtualKeys::refresh`](super::VirtualKeys::refresh).
+#[derive(Debug, Clone)]
+pub struct KeysRefresher {
+    app_state: AppState,
+}
+
+impl KeysRefresher {
+    /// `None` unless virtual keys are configured.
+    #[must_use]
+    pub fn new(app_state: AppState) -> Option<Self> {
+        app_state
+            .0
+            .virtual_keys
+            .is_some()
+            .then_some(Self { app_state })
+    }
+}
+
+impl meltdown::Service for KeysRefresher {
+    type Future = BoxFuture<'static, Result<(), RuntimeError>>;
+
+    fn run(self, mut token: Token) -> Self::Future {
+        Box::pin(async move {
+            let (Some(virtual_keys), Some(config)) = (
+                self.app_state.0.virtual_keys.as_ref(),
+                self.app_state.config().virtual_keys.as_ref(),
+            ) else {
+                return Ok(());
+            };
+            let mut interval = interval(config.refresh_interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            // the keys were just loaded
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        if let Err(e) = virtual_keys.refresh().await {
+                            warn!(error = %e, "failed to refresh virtual keys");
+                        }
+                    }
+                    () = &mut token => {
+                        info!(name = "virtual-keys-refresher", "task shutting down");
+                        break;
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,112 @@
+use std::task::{Context, Poll};
+
+use axum_core::response::IntoResponse;
+use futures::future::BoxFuture;
+use http_body_util::BodyExt;
+use serde_json::Value;
+
+use super::VirtualKey;
+use crate::{
+    error::{api::ApiError, auth::AuthError, internal::InternalError},
+    router::MODEL_OVERRIDE_HEADER,
+    types::{body::Body, request::Request, response::Response},
+};
+
+/// Rejects requests authenticated with a virtual key for a model that is not
+/// in the allowlist of the key, before they are routed.
+///
+/// The `model` of the request body is checked as written by the client,
+/// i.e. before model aliases are resolved, as well as the model of an
+/// `x-gateway-model` override. Requests without a model are rejected too,
+/// since it can't be told which model they would use. All other requests
+/// are passed through.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Layer;
+
+impl<S> tower::Layer<S> for Layer {
+    type Service = Service<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Service { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Service<S> {
+    inner: S,
+}
+
+impl<S> tower::Service<Request> for Service<S>
+where
+    S: tower::Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    #[inline]
+    fn poll_ready(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    #[tracing::instrument(name = "virtual_key_scope", skip_all)]
+    fn call(&mut self, req: Request) -> Self::Future {
+        let Some(virtual_key) = req
+            .extensions()
+            .get::<VirtualKey>()
+            .filter(|virtual_key| virtual_key.models.is_some())
+            .cloned()
+        else {
+            return Box::pin(self.inner.call(req));
+        };
+        let mut inner = self.inner.clone();
+        std::mem::swap(&mut self.inner, &mut inner);
+        Box::pin(async move {
+            let (parts, body) = req.into_parts();
+            let body = match body.collect().await {
+                Ok(body) => body.to_bytes(),
+                Err(e) => {
+                    return Ok(ApiError::from(
+                        InternalError::CollectBodyError(e),
+                    )
+                    .into_response());
+                }
+            };
+            let override_model = parts
+                .headers
+                .get(&MODEL_OVERRIDE_HEADER)
+                .and_then(|value| value.to_str().ok());
+            let model =
+                serde_json::from_slice::<Value>(&body)
+                    .ok()
+                    .and_then(|value| {
+                        value.get("model")?.as_str().map(str::to_string)
+                    });
+            let models = [model.as_deref(), override_model]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+            let denied = if models.is_empty() {
+                Some("none")
+            } else {
+                models
+                    .into_iter()
+                    .find(|model| !virtual_key.allows_model(model))
+            };
+            if let Some(denied) = denied {
+                tracing::debug!(key_id = %virtual_key.id, model = denied, "model not permitted for virtual key");
+                return Ok(ApiError::Authentication(
+                    AuthError::ModelNotPermitted(denied.to_string()),
+                )
+                .into_response());
+            }
+            inner
+                .call(Request::from_parts(parts, Body::from(body)))
+                .await
+        })
+    }
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    app::AppResponse,
+    config::{
+        Config,
+        balance::{BalanceConfig, BalanceConfigInner},
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    discover::provider_models::ModelDiscovery,
+    endpoints::EndpointType,
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId, secret::Secret},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use nonempty_collections::nev;
+use serde_json::json;
+use tower::Service;
+
+fn config() -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing the admin API
+    config.helicone.features = HeliconeFeatures::None;
+    config.admin.api_key = Some(Secret::from("admin-key".to_string()));
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig(HashMap::from([(
+                EndpointType::Chat,
+                BalanceConfigInner::Failover {
+                    providers: nev![
+                        InferenceProvider::OpenAI,
+                        InferenceProvider::Anthropic
+                    ],
+                    stream_failover: None,
+                },
+            )])),
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn admin_request(method: Method, path: &str) -> Request<axum_core::body::Body> {
+    Request::builder()
+        .method(method)
+        .uri(format!("http://router.helicone.com/v1/admin{path}"))
+        .header("authorization", "Bearer admin-key")
+        .body(axum_core::body::Body::empty())
+        .unwrap()
+}
+
+fn chat_request() -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+async fn json_body(response: AppResponse) -> serde_json::Value {
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&body).unwrap()
+}
+
+/// Test that a provider disabled with the admin API is not routed to until
+/// it is enabled again.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn disabled_provider_is_failed_over() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness
+        .call(admin_request(Method::POST, "/providers/openai/disable"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert_eq!(body["provider"], "openai");
+    assert_eq!(body["enabled"], false);
+
+    let response = harness
+        .call(admin_request(Method::GET, "/providers"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    let openai = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["provider"] == "openai")
+        .unwrap();
+    assert_eq!(openai["enabled"], false);
+    assert!(!openai["models"].as_array().unwrap().is_empty());
+
+    // served by anthropic
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _response_body = response.into_body().collect().await.unwrap();
+
+    let response = harness
+        .call(admin_request(Method::POST, "/providers/openai/enable"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // served by openai
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _response_body = response.into_body().collect().await.unwrap();
+}
+
+/// Test that a provider disabled with the admin API stays disabled when the
+/// models of the providers are refreshed.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn disabled_provider_stays_disabled_after_refresh() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness
+        .call(admin_request(Method::POST, "/providers/openai/disable"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    ModelDiscovery::new(harness.app_factory.state.clone())
+        .unwrap()
+        .refresh()
+        .await
+        .unwrap();
+
+    let response = harness
+        .call(admin_request(Method::GET, "/providers"))
+        .await
+        .unwrap();
+    let body = json_body(response).await;
+    let openai = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["provider"] == "openai")
+        .unwrap();
+    assert_eq!(openai["enabled"], false);
+
+    // served by anthropic
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _response_body = response.into_body().collect().await.unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn admin_api_inspects_and_flushes_state() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness
+        .call(admin_request(Method::GET, "/routers"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert!(body["data"]["my-router"].is_object());
+
+    let response = harness
+        .call(admin_request(Method::GET, "/health"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    let openai = body["data"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|entry| entry["provider"] == "openai")
+        .unwrap();
+    assert_eq!(openai["healthy"], true);
+    assert_eq!(openai["enabled"], true);
+
+    let response = harness
+        .call(admin_request(Method::DELETE, "/cache"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+    let mut request = admin_request(Method::DELETE, "/cache");
+    request.headers_mut().remove("authorization");
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+/// Test that providers are found by any name requests can use for them, not
+/// just their canonical name.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn provider_is_found_by_alias() {
+    let mut config = config();
+    config
+        .providers
+        .get_mut(&InferenceProvider::OpenAI)
+        .unwrap()
+        .aliases
+        .insert(InferenceProvider::Named("azure-openai".into()));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness
+        .call(admin_request(
+            Method::POST,
+            "/providers/Azure-OpenAI/disable",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = json_body(response).await;
+    assert_eq!(body["provider"], "openai");
+    assert_eq!(body["enabled"], false);
+    assert!(
+        !harness.app_factory.state.providers()[&InferenceProvider::OpenAI]
+            .enabled
+    );
+}
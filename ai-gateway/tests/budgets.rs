@@ -0,0 +1,190 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    budgets::Budgets,
+    config::{
+        Config,
+        budgets::{Budget, BudgetPeriod, BudgetsStoreConfig},
+        helicone::HeliconeFeatures,
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId, secret::Secret},
+};
+use chrono::Utc;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use rust_decimal::Decimal;
+use serde_json::json;
+use tower::Service;
+
+fn chat_request() -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+/// Test that requests are rejected once the budget of their router is
+/// exhausted, until its limit is raised with the admin endpoint.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn exhausted_budget_rejects_requests() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.admin.api_key = Some(Secret::from("admin-key".to_string()));
+    config.budgets.limits.insert(
+        "my-router".to_string(),
+        Budget {
+            // less than the cost of a single request
+            limit_usd: Decimal::new(1, 6),
+            period: BudgetPeriod::Monthly,
+            api_key_hash: None,
+            router: Some(RouterId::Named("my-router".into())),
+            provider: None,
+        },
+    );
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 2.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _body = response.into_body().collect().await.unwrap();
+    // spend is recorded in the background once the response is read
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+    assert!(response.headers().contains_key(http::header::RETRY_AFTER));
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body["error"]["code"], "budget_exceeded");
+
+    let request = Request::builder()
+        .method(Method::PUT)
+        .uri("http://router.helicone.com/v1/budgets/my-router")
+        .header("authorization", "Bearer admin-key")
+        .body(axum_core::body::Body::from(
+            serde_json::to_vec(&json!({
+                "limit-usd": "10",
+                "period": "monthly",
+                "router": "my-router"
+            }))
+            .unwrap(),
+        ))
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    // the spend of the budget is kept when only its limit changes
+    assert_eq!(body["spent_usd"], "0.00000885");
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+/// Test that the spend of budgets and the budgets set at runtime are kept in
+/// the store, so that they survive restarts and are shared by replicas.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn budgets_are_kept_in_the_store() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.admin.api_key = Some(Secret::from("admin-key".to_string()));
+    let database = std::env::temp_dir()
+        .join(format!("ai-gateway-budgets-{}.db", uuid::Uuid::now_v7()));
+    config.budgets.store = Some(BudgetsStoreConfig {
+        database_url: Secret::from(format!(
+            "sqlite://{}?mode=rwc",
+            database.display()
+        )),
+        ..Default::default()
+    });
+    let router_id = RouterId::Named("my-router".into());
+    config.budgets.limits.insert(
+        "my-router".to_string(),
+        Budget {
+            // less than the cost of a single request
+            limit_usd: Decimal::new(1, 6),
+            period: BudgetPeriod::Monthly,
+            api_key_hash: None,
+            router: Some(router_id.clone()),
+            provider: None,
+        },
+    );
+    let budgets_config = config.budgets.clone();
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness.call(chat_request()).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _body = response.into_body().collect().await.unwrap();
+    // spend is recorded in the background once the response is read
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let request = Request::builder()
+        .method(Method::PUT)
+        .uri("http://router.helicone.com/v1/budgets/openai")
+        .header("authorization", "Bearer admin-key")
+        .body(axum_core::body::Body::from(
+            serde_json::to_vec(&json!({
+                "limit-usd": "10",
+                "period": "daily",
+                "provider": "openai"
+            }))
+            .unwrap(),
+        ))
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // as if the gateway was restarted, or for another replica
+    let budgets = Budgets::new(&budgets_config).await.unwrap();
+    let now = Utc::now();
+    let statuses = budgets.list(now);
+    let names = statuses
+        .iter()
+        .map(|status| status.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["my-router", "openai"]);
+    assert_eq!(statuses[0].spent_usd, Decimal::new(885, 8));
+    assert_eq!(statuses[1].spent_usd, Decimal::ZERO);
+    assert!(
+        budgets
+            .check(None, Some(&router_id), &InferenceProvider::OpenAI, now)
+            .is_err()
+    );
+}
@@ -0,0 +1,184 @@
+use std::{collections::HashMap, path::PathBuf};
+
+use ai_gateway::{
+    config::{Config, helicone::HeliconeFeatures},
+    discover::reload::{ConfigReloader, ReloadError},
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+
+fn config() -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're not testing authentication
+    config.helicone.features = HeliconeFeatures::None;
+    config
+}
+
+/// Write `yaml` to a config file for the reloader.
+fn config_file(name: &str, yaml: &str) -> PathBuf {
+    let path = std::env::temp_dir()
+        .join(format!("ai-gateway-{name}-{}.yaml", std::process::id()));
+    std::fs::write(&path, yaml).unwrap();
+    path
+}
+
+fn chat_request(router: &str) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri(format!(
+            "http://router.helicone.com/router/{router}/chat/completions"
+        ))
+        .body(request_body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn reload_swaps_providers_and_routers() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let app_state = harness.app_factory.state.clone();
+
+    let path = config_file(
+        "reload-success",
+        r"
+providers:
+  ollama:
+    enabled: false
+routers:
+  my-router:
+    load-balance:
+      chat:
+        strategy: failover
+        providers:
+          - openai
+  new-router:
+    load-balance:
+      chat:
+        strategy: failover
+        providers:
+          - anthropic
+",
+    );
+    let mut reloader = ConfigReloader::new(app_state.clone(), Some(path), None);
+    reloader.reload().await.unwrap();
+
+    assert!(!app_state.providers()[&InferenceProvider::Ollama].enabled);
+    assert!(
+        app_state
+            .0
+            .health_monitors
+            .read()
+            .await
+            .contains_key(&RouterId::Named(CompactString::new("new-router")))
+    );
+
+    for router in ["my-router", "new-router"] {
+        let response = harness.call(chat_request(router)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let _response_body = response.into_body().collect().await.unwrap();
+    }
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn failed_reload_keeps_running_config() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let app_state = harness.app_factory.state.clone();
+    let my_router = RouterId::Named(CompactString::new("my-router"));
+    let rate_limit_tx = app_state.get_rate_limit_tx(&my_router).await.unwrap();
+
+    // the invalid bucket header is only rejected when the router is built
+    let path = config_file(
+        "reload-failure",
+        r"
+providers:
+  ollama:
+    enabled: false
+routers:
+  my-router:
+    load-balance:
+      chat:
+        strategy: failover
+        providers:
+          - anthropic
+  broken-router:
+    load-balance:
+      chat:
+        strategy: traffic-split
+        baseline: openai/gpt-4o-mini
+        candidate: anthropic/claude-3-haiku-20240307
+        candidate-percent: 10
+        bucket-header: not a header
+",
+    );
+    let mut reloader = ConfigReloader::new(app_state.clone(), Some(path), None);
+    let result = reloader.reload().await;
+    assert!(
+        matches!(
+            &result,
+            Err(ReloadError::BuildRouter(RouterId::Named(id), _))
+                if id == "broken-router"
+        ),
+        "{result:?}"
+    );
+
+    assert!(app_state.providers()[&InferenceProvider::Ollama].enabled);
+    assert!(
+        app_state
+            .get_rate_limit_tx(&my_router)
+            .await
+            .unwrap()
+            .same_channel(&rate_limit_tx)
+    );
+    assert!(
+        !app_state.0.health_monitors.read().await.contains_key(
+            &RouterId::Named(CompactString::new("broken-router"))
+        )
+    );
+
+    // still served by openai
+    let response = harness.call(chat_request("my-router")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _response_body = response.into_body().collect().await.unwrap();
+}
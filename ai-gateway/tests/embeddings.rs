@@ -0,0 +1,92 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{Config, helicone::HeliconeFeatures},
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+};
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use tower::Service;
+
+async fn harness(stub: &'static str, times: u64) -> Harness {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing embeddings mapping
+    config.helicone.features = HeliconeFeatures::None;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            (stub, times.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await
+}
+
+fn embeddings_request(body: &Value) -> Request<axum_core::body::Body> {
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/ai/embeddings")
+        .header("content-type", "application/json")
+        .body(axum_core::body::Body::from(
+            serde_json::to_vec(body).unwrap(),
+        ))
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn openai_embeddings() {
+    let mut harness = harness("success:openai:embeddings", 1).await;
+
+    let request = embeddings_request(&json!({
+        "model": "openai/text-embedding-3-small",
+        "input": "Hello, world!"
+    }));
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<Value>(&body).unwrap();
+    assert_eq!(body["data"].as_array().unwrap().len(), 1);
+    assert_eq!(body["usage"]["prompt_tokens"], 8);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn gemini_embeddings_are_mapped_to_openai_format() {
+    let mut harness = harness("success:gemini:batch_embed_contents", 1).await;
+
+    let request = embeddings_request(&json!({
+        "model": "gemini/text-embedding-004",
+        "input": ["Hello, world!", "Goodbye, world!"],
+        "encoding_format": "base64"
+    }));
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<Value>(&body).unwrap();
+    assert_eq!(body["object"], "list");
+    assert_eq!(body["model"], "text-embedding-004");
+    let data = body["data"].as_array().unwrap();
+    assert_eq!(data.len(), 2);
+    assert_eq!(data[1]["index"], 1);
+    assert!(data[1]["embedding"].is_string());
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn embeddings_dimensions_are_validated() {
+    let mut harness = harness("success:openai:embeddings", 0).await;
+
+    let request = embeddings_request(&json!({
+        "model": "openai/text-embedding-3-small",
+        "input": "Hello, world!",
+        "dimensions": 4096
+    }));
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
@@ -13,6 +13,7 @@ use ai_gateway::{
 };
 use compact_str::CompactString;
 use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
 use nonempty_collections::nes;
 use serde_json::json;
 use tower::Service;
@@ -35,6 +36,9 @@ fn p2c_config_openai_anthropic_google() -> RouterConfigs {
             cache: None,
             retries: None,
             rate_limit: None,
+            queue: None,
+            guardrails: None,
+            model_aliases: None,
             providers: None,
         },
     )]))
@@ -141,3 +145,67 @@ async fn anthropic_slow() {
         assert_eq!(response.status(), StatusCode::OK);
     }
 }
+
+#[tokio::test]
+#[serial_test::serial]
+async fn least_pending_spreads_sequential_requests() {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing load balancing behavior
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig(HashMap::from([(
+                EndpointType::Chat,
+                BalanceConfigInner::LeastPending {
+                    providers: nes![
+                        InferenceProvider::OpenAI,
+                        InferenceProvider::Anthropic
+                    ],
+                },
+            )])),
+            ..Default::default()
+        },
+    )]));
+    // requests are sent one after the other, so no provider ever has a
+    // request in flight and ties are broken at random
+    let requests = 100;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", (25..75).into()),
+            ("success:anthropic:messages", (25..75).into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let body_bytes = serde_json::to_vec(&json!({
+        "model": "openai/gpt-4o-mini",
+        "messages": [
+            {
+                "role": "user",
+                "content": "Hello, world!"
+            }
+        ]
+    }))
+    .unwrap();
+
+    for _ in 0..requests {
+        let request_body = axum_core::body::Body::from(body_bytes.clone());
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("http://router.helicone.com/router/my-router/chat/completions")
+            .body(request_body)
+            .unwrap();
+        let response = harness.call(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let _response_body = response.into_body().collect().await.unwrap();
+    }
+
+    // sleep so that the background task for logging can complete
+    tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+}
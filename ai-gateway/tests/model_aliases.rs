@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use nonempty_collections::nev;
+use serde_json::json;
+use tower::Service;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn alias_fails_over_across_targets() {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing alias resolution
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            model_aliases: Some(HashMap::from([(
+                "smart".to_string(),
+                nev![
+                    "openai/gpt-4o-mini".parse().unwrap(),
+                    "anthropic/claude-sonnet-4-0".parse().unwrap(),
+                ],
+            )])),
+            ..RouterConfigs::test_default()
+                .get(&RouterId::Named(CompactString::new("my-router")))
+                .cloned()
+                .unwrap()
+        },
+    )]));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("internal_error:openai:chat_completion", 1.into()),
+            ("success:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "smart",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _response_body = response.into_body().collect().await.unwrap();
+}
@@ -0,0 +1,166 @@
+use std::{collections::HashMap, str::FromStr};
+
+use ai_gateway::{
+    config::{
+        Config,
+        helicone::HeliconeFeatures,
+        providers::{ModelCapabilities, ModelMetadata},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{model_id::ModelId, provider::InferenceProvider},
+};
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+
+/// A 1x1 PNG.
+const PNG_DATA_URI: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+
+fn image_request(model: &str) -> Request<axum_core::body::Body> {
+    image_url_request(model, PNG_DATA_URI)
+}
+
+fn image_url_request(model: &str, url: &str) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": [
+                        { "type": "text", "text": "What is in this image?" },
+                        {
+                            "type": "image_url",
+                            "image_url": { "url": url }
+                        }
+                    ]
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/ai/chat/completions")
+        .body(request_body)
+        .unwrap()
+}
+
+/// Test that a base64 image in an OpenAI request is sent to Anthropic as a
+/// base64 image block.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn openai_image_to_anthropic() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("image:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness
+        .call(image_request("anthropic/claude-sonnet-4-0"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(
+        body["choices"][0]["message"]["content"],
+        "The image is a single white pixel."
+    );
+}
+
+/// Test that images are rejected without reaching the provider when the
+/// model is not vision capable.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn image_for_model_without_vision_is_rejected() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config
+        .providers
+        .get_mut(&InferenceProvider::OpenAI)
+        .unwrap()
+        .model_metadata
+        .insert(
+            ModelId::from_str("openai/gpt-4o-mini").unwrap(),
+            ModelMetadata {
+                capabilities: Some(ModelCapabilities {
+                    supports_vision: Some(false),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        );
+
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness
+        .call(image_request("openai/gpt-4o-mini"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body["error"]["code"], "unsupported_content");
+}
+
+/// Test that images referenced by URL are rejected for providers that can't
+/// fetch them, unless fetching images is enabled.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn image_url_is_rejected_without_fetching() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    assert!(!config.multimodal.fetch_images);
+
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:bedrock:converse", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness
+        .call(image_url_request(
+            "bedrock/anthropic.claude-3-5-sonnet-20240620-v1:0",
+            "http://169.254.169.254/latest/meta-data",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body["error"]["code"], "unsupported_content");
+}
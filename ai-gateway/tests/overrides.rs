@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs, RouterProviderConfig},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+
+fn config() -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing override resolution
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            providers: Some(HashMap::from([(
+                InferenceProvider::Anthropic,
+                RouterProviderConfig {
+                    base_url: None,
+                    version: None,
+                    allow_overrides: Some(vec!["claude-3-5-haiku".to_string()]),
+                    retries: None,
+                    max_concurrency: None,
+                },
+            )])),
+            ..RouterConfigs::test_default()
+                .get(&RouterId::Named(CompactString::new("my-router")))
+                .cloned()
+                .unwrap()
+        },
+    )]));
+    config
+}
+
+fn request(headers: &[(&str, &str)]) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions");
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    request.body(request_body).unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn allowed_overrides_pin_the_provider_and_model() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:anthropic:messages", 2.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness
+        .call(request(&[("x-gateway-provider", "anthropic")]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _response_body = response.into_body().collect().await.unwrap();
+
+    let response = harness
+        .call(request(&[(
+            "x-gateway-model",
+            "anthropic/claude-3-5-haiku",
+        )]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _response_body = response.into_body().collect().await.unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn disallowed_overrides_are_forbidden() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness
+        .call(request(&[("x-gateway-provider", "gemini")]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(message.contains("anthropic"), "{message}");
+
+    let response = harness
+        .call(request(&[
+            ("x-gateway-provider", "anthropic"),
+            ("x-gateway-model", "claude-opus-4-0"),
+        ]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    let message = body["error"]["message"].as_str().unwrap();
+    assert!(message.contains("claude-3-5-haiku"), "{message}");
+}
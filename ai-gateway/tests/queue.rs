@@ -0,0 +1,83 @@
+use std::{collections::HashMap, num::NonZeroUsize};
+
+use ai_gateway::{
+    config::{
+        Config,
+        helicone::HeliconeFeatures,
+        queue::QueueConfig,
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::router::RouterId,
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+
+fn request(priority: Option<&str>) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    let mut request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions");
+    if let Some(priority) = priority {
+        request = request.header("x-gateway-priority", priority);
+    }
+    request.body(request_body).unwrap()
+}
+
+/// Test that a request frees its slot in the queue of the router once its
+/// response is read, so that a queue without room for waiting requests
+/// still serves requests one after the other.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn queue_slots_are_freed_after_the_response() {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing the queue
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            queue: Some(QueueConfig {
+                max_concurrency: NonZeroUsize::new(1).unwrap(),
+                max_size: 0,
+                ..Default::default()
+            }),
+            ..RouterConfigs::test_default()
+                .get(&RouterId::Named(CompactString::new("my-router")))
+                .cloned()
+                .unwrap()
+        },
+    )]));
+
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 3.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    for priority in [None, Some("high"), Some("not-a-priority")] {
+        let response = harness.call(request(priority)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let _body = response.into_body().collect().await.unwrap();
+    }
+}
@@ -5,11 +5,11 @@ use ai_gateway::{
         Config,
         balance::BalanceConfig,
         helicone::HeliconeFeatures,
-        retry::RetryConfig,
-        router::{RouterConfig, RouterConfigs},
+        retry::RetryPolicy,
+        router::{RouterConfig, RouterConfigs, RouterProviderConfig},
     },
     tests::{TestDefault, harness::Harness, mock::MockArgs},
-    types::router::RouterId,
+    types::{provider::InferenceProvider, router::RouterId},
 };
 use compact_str::CompactString;
 use http::{Method, Request, StatusCode};
@@ -24,7 +24,7 @@ async fn unified_api() {
     // Disable auth for this test since we're testing basic passthrough
     // functionality
     config.helicone.features = HeliconeFeatures::All;
-    config.unified_api.retries = Some(RetryConfig::test_default());
+    config.unified_api.retries = Some(RetryPolicy::test_default());
 
     let mock_args = MockArgs::builder()
         .stubs(HashMap::from([
@@ -83,7 +83,7 @@ async fn router() {
         RouterId::Named(CompactString::new("my-router")),
         RouterConfig {
             load_balance: BalanceConfig::openai_chat(),
-            retries: Some(RetryConfig::test_default()),
+            retries: Some(RetryPolicy::test_default()),
             ..Default::default()
         },
     )]));
@@ -134,3 +134,187 @@ async fn router() {
     // sleep so that the background task for logging can complete
     tokio::time::sleep(std::time::Duration::from_millis(10)).await;
 }
+
+fn router_config(
+    retries: RetryPolicy,
+    openai_retries: Option<RetryPolicy>,
+) -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing the retry policy
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::openai_chat(),
+            retries: Some(retries),
+            providers: openai_retries.map(|retries| {
+                HashMap::from([(
+                    InferenceProvider::OpenAI,
+                    RouterProviderConfig {
+                        base_url: None,
+                        version: None,
+                        allow_overrides: None,
+                        retries: Some(retries),
+                        max_concurrency: None,
+                    },
+                )])
+            }),
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+async fn call_router(
+    config: Config,
+    stub: &'static str,
+    times: u64,
+) -> StatusCode {
+    call_router_with(config, stub, times, false).await
+}
+
+async fn call_router_with(
+    config: Config,
+    stub: &'static str,
+    times: u64,
+    stream: bool,
+) -> StatusCode {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            (stub, times.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ],
+            "stream": stream
+        }))
+        .unwrap(),
+    );
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .header("content-type", "application/json")
+        .body(request_body)
+        .unwrap();
+
+    let response = harness.call(request).await.unwrap();
+    let status = response.status();
+    let _response_body = response.into_body().collect().await.unwrap();
+    status
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn rate_limits_are_retried() {
+    let retries = RetryPolicy {
+        respect_retry_after: false,
+        ..RetryPolicy::test_default()
+    };
+    let status = call_router(
+        router_config(retries, None),
+        "rate_limit:openai:chat_completion",
+        3,
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn retry_after_beyond_max_is_not_retried() {
+    // the stub asks to retry after 2s
+    let retries = RetryPolicy {
+        max_retry_after: std::time::Duration::from_secs(1),
+        ..RetryPolicy::test_default()
+    };
+    let status = call_router(
+        router_config(retries, None),
+        "rate_limit:openai:chat_completion",
+        1,
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn provider_policy_replaces_router_policy() {
+    let openai_retries = RetryPolicy {
+        retryable_status_codes: Vec::new(),
+        ..RetryPolicy::test_default()
+    };
+    let status = call_router(
+        router_config(RetryPolicy::test_default(), Some(openai_retries)),
+        "internal_error:openai:chat_completion",
+        1,
+    )
+    .await;
+    assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn error_codes_are_retried() {
+    let retries = RetryPolicy {
+        retryable_status_codes: Vec::new(),
+        retryable_error_codes: vec!["rate_limit_exceeded".to_string()],
+        respect_retry_after: false,
+        ..RetryPolicy::test_default()
+    };
+    let status = call_router(
+        router_config(retries, None),
+        "rate_limit:openai:chat_completion",
+        3,
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn error_codes_are_retried_when_streaming() {
+    let retries = RetryPolicy {
+        retryable_status_codes: Vec::new(),
+        retryable_error_codes: vec!["rate_limit_exceeded".to_string()],
+        respect_retry_after: false,
+        ..RetryPolicy::test_default()
+    };
+    let status = call_router_with(
+        router_config(retries, None),
+        "rate_limit:openai:chat_completion",
+        3,
+        true,
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // neither the status nor the error code is retryable
+    let retries = RetryPolicy {
+        retryable_status_codes: Vec::new(),
+        respect_retry_after: false,
+        ..RetryPolicy::test_default()
+    };
+    let status = call_router_with(
+        router_config(retries, None),
+        "rate_limit:openai:chat_completion",
+        1,
+        true,
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
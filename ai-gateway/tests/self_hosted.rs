@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::BalanceConfig,
+        helicone::HeliconeFeatures,
+        providers::{SelfHostedConfig, SelfHostedDialect},
+        router::{RouterConfig, RouterConfigs},
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{provider::InferenceProvider, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+
+/// Test that OpenAI requests to a provider with the native Ollama dialect
+/// are sent to `/api/chat` and the response is mapped back.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn ollama_native_dialect() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config
+        .providers
+        .get_mut(&InferenceProvider::Ollama)
+        .unwrap()
+        .self_hosted = Some(SelfHostedConfig {
+        dialect: SelfHostedDialect::Ollama,
+        ..Default::default()
+    });
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig::ollama_chat(),
+            ..Default::default()
+        },
+    )]));
+
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:ollama:chat", 1.into()),
+            ("success:ollama:chat_completions", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "ollama/llama3",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(
+        body["choices"][0]["message"]["content"],
+        "Gooooood morning!!!! Let's do some Rust coding!"
+    );
+    assert_eq!(body["choices"][0]["finish_reason"], "stop");
+    assert_eq!(body["usage"]["total_tokens"], 49);
+}
@@ -12,6 +12,7 @@ use ai_gateway::{
 };
 use compact_str::CompactString;
 use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
 use serde_json::json;
 use tower::Service;
 
@@ -59,7 +60,7 @@ async fn openai() {
 }
 
 /// Sending a request to https://localhost/router should
-// result in the proxied request targeting https://generativelanguage.googleapis.com/v1beta/openai/chat/completions
+// result in the proxied request targeting https://generativelanguage.googleapis.com/v1beta/models/gemini-2.0-flash:generateContent
 #[tokio::test]
 #[serial_test::serial(default_mock)]
 async fn google_with_openai_request_style() {
@@ -104,6 +105,13 @@ async fn google_with_openai_request_style() {
         .unwrap();
     let response = harness.call(request).await.unwrap();
     assert_eq!(response.status(), StatusCode::OK);
+    // the native Gemini response is mapped back to the OpenAI format
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body["object"], "chat.completion");
+    assert_eq!(body["choices"][0]["message"]["role"], "assistant");
+    assert_eq!(body["choices"][0]["finish_reason"], "stop");
+    assert_eq!(body["usage"]["total_tokens"], 1634);
 
     let request_body = axum_core::body::Body::from(
         serde_json::to_vec(&json!({
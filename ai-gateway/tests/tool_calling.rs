@@ -0,0 +1,200 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{Config, helicone::HeliconeFeatures},
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+};
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+
+fn weather_tool_parameters() -> serde_json::Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "location": { "type": "string" }
+        },
+        "required": ["location"]
+    })
+}
+
+/// Test that the tools and tool calls of an OpenAI request are translated
+/// for an Anthropic model, and that the `tool_use` blocks of the response are
+/// translated back to tool calls.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn openai_tool_calls_to_anthropic() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("tool_use:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "anthropic/claude-sonnet-4-0",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "What's the weather in London and Berlin?"
+                },
+                {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [
+                        {
+                            "id": "call_1",
+                            "type": "function",
+                            "function": {
+                                "name": "get_weather",
+                                "arguments": "{\"location\":\"London\"}"
+                            }
+                        },
+                        {
+                            "id": "call_2",
+                            "type": "function",
+                            "function": {
+                                "name": "get_weather",
+                                "arguments": "{\"location\":\"Berlin\"}"
+                            }
+                        }
+                    ]
+                },
+                {
+                    "role": "tool",
+                    "tool_call_id": "call_1",
+                    "content": "12 degrees, cloudy"
+                },
+                {
+                    "role": "tool",
+                    "tool_call_id": "call_2",
+                    "content": "18 degrees, sunny"
+                },
+                {
+                    "role": "user",
+                    "content": "And in Paris?"
+                }
+            ],
+            "tools": [
+                {
+                    "type": "function",
+                    "function": {
+                        "name": "get_weather",
+                        "description": "Get the current weather",
+                        "parameters": weather_tool_parameters()
+                    }
+                }
+            ],
+            "tool_choice": "auto"
+        }))
+        .unwrap(),
+    );
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/ai/chat/completions")
+        .header("content-type", "application/json")
+        .body(request_body)
+        .unwrap();
+
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    let choice = &body["choices"][0];
+    assert_eq!(choice["finish_reason"], "tool_calls");
+    assert_eq!(
+        choice["message"]["content"],
+        "I'll check the weather in Paris."
+    );
+    let tool_call = &choice["message"]["tool_calls"][0];
+    assert_eq!(tool_call["id"], "toolu_01A09q90qw90lq917835lq9");
+    assert_eq!(tool_call["function"]["name"], "get_weather");
+    let arguments: serde_json::Value = serde_json::from_str(
+        tool_call["function"]["arguments"].as_str().unwrap(),
+    )
+    .unwrap();
+    assert_eq!(arguments, json!({ "location": "Paris" }));
+}
+
+/// Test that the tools of an Anthropic Messages request are translated for
+/// an OpenAI model, and that parallel tool calls of the response are
+/// translated back to `tool_use` blocks.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn anthropic_tool_use_to_openai() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("tool_calls:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "max_tokens": 1024,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "What's the weather in Paris and London?"
+                }
+            ],
+            "tools": [
+                {
+                    "name": "get_weather",
+                    "description": "Get the current weather",
+                    "input_schema": weather_tool_parameters()
+                }
+            ],
+            "tool_choice": { "type": "any" }
+        }))
+        .unwrap(),
+    );
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/ai/anthropic/v1/messages")
+        .header("content-type", "application/json")
+        .body(request_body)
+        .unwrap();
+
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["stop_reason"], "tool_use");
+    let tool_uses = body["content"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter(|block| block["type"] == "tool_use")
+        .collect::<Vec<_>>();
+    assert_eq!(tool_uses.len(), 2);
+    assert_eq!(tool_uses[0]["id"], "call_abc123");
+    assert_eq!(tool_uses[0]["name"], "get_weather");
+    assert_eq!(tool_uses[0]["input"], json!({ "location": "Paris" }));
+    assert_eq!(tool_uses[1]["input"], json!({ "location": "London" }));
+}
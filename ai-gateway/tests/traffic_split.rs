@@ -0,0 +1,173 @@
+use std::{collections::HashMap, str::FromStr};
+
+use ai_gateway::{
+    config::{
+        Config,
+        balance::{BalanceConfig, BalanceConfigInner},
+        helicone::HeliconeFeatures,
+        router::{RouterConfig, RouterConfigs},
+    },
+    endpoints::EndpointType,
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::{model_id::ModelId, router::RouterId},
+};
+use compact_str::CompactString;
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use rust_decimal::Decimal;
+use serde_json::json;
+use tower::Service;
+
+fn config(candidate_percent: Decimal) -> Config {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're not testing authentication
+    config.helicone.features = HeliconeFeatures::None;
+    config.routers = RouterConfigs::new(HashMap::from([(
+        RouterId::Named(CompactString::new("my-router")),
+        RouterConfig {
+            load_balance: BalanceConfig(HashMap::from([(
+                EndpointType::Chat,
+                BalanceConfigInner::TrafficSplit {
+                    baseline: ModelId::from_str("openai/gpt-4o-mini").unwrap(),
+                    candidate: ModelId::from_str(
+                        "anthropic/claude-3-haiku-20240307",
+                    )
+                    .unwrap(),
+                    candidate_percent,
+                    bucket_header: Some("x-user-id".to_string()),
+                },
+            )])),
+            ..Default::default()
+        },
+    )]));
+    config
+}
+
+fn chat_request() -> Request<axum_core::body::Body> {
+    chat_request_for("openai/gpt-4o-mini")
+}
+
+fn chat_request_for(model: &str) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .header("x-user-id", "user-1")
+        .body(request_body)
+        .unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn all_requests_go_to_candidate_at_100_percent() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:anthropic:messages", 2.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config(Decimal::ONE_HUNDRED))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    for _ in 0..2 {
+        let response = harness.call(chat_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-gateway-split"], "candidate");
+        let _response_body = response.into_body().collect().await.unwrap();
+    }
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn no_requests_go_to_candidate_at_0_percent() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 2.into()),
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config(Decimal::ZERO))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    for _ in 0..2 {
+        let response = harness.call(chat_request()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["x-gateway-split"], "baseline");
+        let _response_body = response.into_body().collect().await.unwrap();
+    }
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn other_models_are_passed_through() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 2.into()),
+            ("success:anthropic:messages", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config(Decimal::ONE_HUNDRED))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    for _ in 0..2 {
+        let response = harness
+            .call(chat_request_for("openai/gpt-4o"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key("x-gateway-split"));
+        let _response_body = response.into_body().collect().await.unwrap();
+    }
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn models_of_other_providers_are_rejected() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 0.into()),
+            ("success:anthropic:messages", 0.into()),
+            ("success:gemini:generate_content", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config(Decimal::ONE_HUNDRED))
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let response = harness
+        .call(chat_request_for("gemini/gemini-2.0-flash"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let _response_body = response.into_body().collect().await.unwrap();
+}
@@ -3,8 +3,10 @@ use std::collections::HashMap;
 use ai_gateway::{
     config::{Config, helicone::HeliconeFeatures},
     tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::provider::InferenceProvider,
 };
 use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
 use serde_json::json;
 use tower::Service;
 
@@ -153,3 +155,160 @@ async fn mistral_unified_api() {
     let response = harness.call(request).await.unwrap();
     assert_eq!(response.status(), StatusCode::OK);
 }
+
+/// Test that chat completions to a provider in deployment mode are sent to
+/// `/openai/deployments/{deployment}/chat/completions?api-version=...`.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn deployment_unified_api() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    let mistral = config
+        .providers
+        .get_mut(&InferenceProvider::Named("mistral".into()))
+        .unwrap();
+    mistral.deployments = true;
+    mistral.version = vec!["2024-10-21".to_string()];
+
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:mistral:deployment_chat_completion", 1.into()),
+            ("success:mistral:chat_completion", 0.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "mistral/mistral-large-latest",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/ai/chat/completions")
+        .header("content-type", "application/json")
+        .body(request_body)
+        .unwrap();
+
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+/// Test that Anthropic Messages requests sent to the /ai base url are
+/// translated to OpenAI chat completions when the `model` field names an
+/// OpenAI model, and that the response is translated back.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn anthropic_messages_to_openai_unified_api() {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing basic passthrough
+    // functionality
+    config.helicone.features = HeliconeFeatures::None;
+
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "max_tokens": 1024,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/ai/anthropic/v1/messages")
+        .header("content-type", "application/json")
+        .body(request_body)
+        .unwrap();
+
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["type"], "message");
+    assert_eq!(body["role"], "assistant");
+}
+
+/// Test that Anthropic Messages requests sent to the /ai base url are
+/// passed through to Anthropic when the `model` field names an Anthropic
+/// model.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn anthropic_messages_to_anthropic_unified_api() {
+    let mut config = Config::test_default();
+    // Disable auth for this test since we're testing basic passthrough
+    // functionality
+    config.helicone.features = HeliconeFeatures::None;
+
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:anthropic:messages", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "anthropic/claude-sonnet-4-0",
+            "max_tokens": 1024,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/ai/anthropic/v1/messages")
+        .header("content-type", "application/json")
+        .body(request_body)
+        .unwrap();
+
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
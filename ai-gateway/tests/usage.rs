@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{Config, helicone::HeliconeFeatures},
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::secret::Secret,
+};
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::json;
+use tower::Service;
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn usage_is_metered_and_priced() {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.admin.api_key = Some(Secret::from("admin-key".to_string()));
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": "openai/gpt-4o-mini",
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/router/my-router/chat/completions")
+        .body(request_body)
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("helicone-prompt-tokens").unwrap(),
+        "19"
+    );
+    assert_eq!(
+        response
+            .headers()
+            .get("helicone-completion-tokens")
+            .unwrap(),
+        "10"
+    );
+    // 19 * $0.15 + 10 * $0.60 per million tokens
+    assert_eq!(
+        response.headers().get("helicone-cost-usd").unwrap(),
+        "0.00000885"
+    );
+    let _body = response.into_body().collect().await.unwrap();
+    // usage is recorded in the background once the response is read
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("http://router.helicone.com/v1/usage")
+        .body(axum_core::body::Body::empty())
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("http://router.helicone.com/v1/usage?provider=openai")
+        .header("authorization", "Bearer admin-key")
+        .body(axum_core::body::Body::empty())
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body["data"][0]["provider"], "openai");
+    assert_eq!(body["data"][0]["model"], "gpt-4o-mini");
+    assert_eq!(body["total"]["requests"], 1);
+    assert_eq!(body["total"]["prompt_tokens"], 19);
+    assert_eq!(body["total"]["completion_tokens"], 10);
+    assert_eq!(body["total"]["cost_usd"], "0.00000885");
+}
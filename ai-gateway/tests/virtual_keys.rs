@@ -0,0 +1,364 @@
+use std::collections::HashMap;
+
+use ai_gateway::{
+    config::{
+        Config, helicone::HeliconeFeatures, virtual_keys::VirtualKeysConfig,
+    },
+    tests::{TestDefault, harness::Harness, mock::MockArgs},
+    types::secret::Secret,
+    virtual_keys::{NewVirtualKey, VirtualKeys},
+};
+use http::{Method, Request, StatusCode};
+use http_body_util::BodyExt;
+use rust_decimal::Decimal;
+use serde_json::json;
+use tower::Service;
+
+fn config() -> Config {
+    let mut config = Config::test_default();
+    config.helicone.features = HeliconeFeatures::None;
+    config.admin.api_key = Some(Secret::from("admin-key".to_string()));
+    let database = std::env::temp_dir()
+        .join(format!("ai-gateway-keys-{}.db", uuid::Uuid::now_v7()));
+    config.virtual_keys = Some(VirtualKeysConfig {
+        database_url: Secret::from(format!(
+            "sqlite://{}?mode=rwc",
+            database.display()
+        )),
+        ..Default::default()
+    });
+    config
+}
+
+async fn mint_key(
+    harness: &mut Harness,
+    scopes: serde_json::Value,
+) -> serde_json::Value {
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("http://router.helicone.com/v1/keys")
+        .header("authorization", "Bearer admin-key")
+        .body(axum_core::body::Body::from(
+            serde_json::to_vec(&scopes).unwrap(),
+        ))
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&body).unwrap()
+}
+
+fn chat_request(
+    uri: &str,
+    api_key: Option<&str>,
+    model: &str,
+) -> Request<axum_core::body::Body> {
+    let request_body = axum_core::body::Body::from(
+        serde_json::to_vec(&json!({
+            "model": model,
+            "messages": [
+                {
+                    "role": "user",
+                    "content": "Hello, world!"
+                }
+            ]
+        }))
+        .unwrap(),
+    );
+    let mut request = Request::builder().method(Method::POST).uri(uri);
+    if let Some(api_key) = api_key {
+        request = request.header("authorization", format!("Bearer {api_key}"));
+    }
+    request.body(request_body).unwrap()
+}
+
+const ROUTER_URI: &str =
+    "http://router.helicone.com/router/my-router/chat/completions";
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn virtual_key_scopes_are_enforced() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    let minted = mint_key(
+        &mut harness,
+        json!({
+            "name": "my-app",
+            "routers": ["my-router"],
+            "models": ["openai/gpt-4o-mini"]
+        }),
+    )
+    .await;
+    let api_key = minted["key"].as_str().unwrap().to_string();
+    assert!(api_key.starts_with("sk-gw-"));
+
+    let response = harness
+        .call(chat_request(
+            ROUTER_URI,
+            Some(&api_key),
+            "openai/gpt-4o-mini",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _body = response.into_body().collect().await.unwrap();
+
+    let response = harness
+        .call(chat_request(ROUTER_URI, Some(&api_key), "openai/gpt-4o"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = harness
+        .call(chat_request(
+            "http://router.helicone.com/ai/chat/completions",
+            Some(&api_key),
+            "openai/gpt-4o-mini",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    let response = harness
+        .call(chat_request(ROUTER_URI, None, "openai/gpt-4o-mini"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let response = harness
+        .call(chat_request(
+            ROUTER_URI,
+            Some("sk-gw-not-a-minted-key"),
+            "openai/gpt-4o-mini",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let request = Request::builder()
+        .method(Method::DELETE)
+        .uri(format!(
+            "http://router.helicone.com/v1/keys/{}",
+            minted["id"].as_str().unwrap()
+        ))
+        .header("authorization", "Bearer admin-key")
+        .body(axum_core::body::Body::empty())
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = harness
+        .call(chat_request(
+            ROUTER_URI,
+            Some(&api_key),
+            "openai/gpt-4o-mini",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn virtual_key_spend_limit_is_enforced() {
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config())
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+
+    // less than the cost of a single request
+    let minted =
+        mint_key(&mut harness, json!({ "spend_limit_usd": "0.000001" })).await;
+    let api_key = minted["key"].as_str().unwrap().to_string();
+
+    let response = harness
+        .call(chat_request(
+            ROUTER_URI,
+            Some(&api_key),
+            "openai/gpt-4o-mini",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _body = response.into_body().collect().await.unwrap();
+    // spend is recorded in the background once the response is read
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let response = harness
+        .call(chat_request(
+            ROUTER_URI,
+            Some(&api_key),
+            "openai/gpt-4o-mini",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("http://router.helicone.com/v1/keys")
+        .header("authorization", "Bearer admin-key")
+        .body(axum_core::body::Body::empty())
+        .unwrap();
+    let response = harness.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body = serde_json::from_slice::<serde_json::Value>(&body).unwrap();
+    assert_eq!(body["data"][0]["spent_usd"], "0.00000885");
+    assert!(body["data"][0].get("key").is_none());
+}
+
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn virtual_key_spend_is_recorded_with_observability() {
+    let mut config = config();
+    config.helicone.features = HeliconeFeatures::All;
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 1.into()),
+            ("success:minio:upload_request", 1.into()),
+            ("success:jawn:log_request", 1.into()),
+            ("success:jawn:sign_s3_url", 1.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .with_mock_auth()
+        .build()
+        .await;
+
+    // less than the cost of a single request
+    let minted =
+        mint_key(&mut harness, json!({ "spend_limit_usd": "0.000001" })).await;
+    let api_key = minted["key"].as_str().unwrap().to_string();
+
+    let response = harness
+        .call(chat_request(
+            ROUTER_URI,
+            Some(&api_key),
+            "openai/gpt-4o-mini",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let _body = response.into_body().collect().await.unwrap();
+    // spend is recorded by the logger once the response is read
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    let response = harness
+        .call(chat_request(
+            ROUTER_URI,
+            Some(&api_key),
+            "openai/gpt-4o-mini",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+}
+
+/// Keys minted, spent and revoked by another replica sharing the database
+/// are taken into account.
+#[tokio::test]
+#[serial_test::serial(default_mock)]
+async fn virtual_keys_are_shared_across_replicas() {
+    let config = config();
+    let other_replica = VirtualKeys::new(config.virtual_keys.as_ref().unwrap())
+        .await
+        .unwrap();
+    let mock_args = MockArgs::builder()
+        .stubs(HashMap::from([
+            ("success:openai:chat_completion", 2.into()),
+            ("success:minio:upload_request", 0.into()),
+            ("success:jawn:log_request", 0.into()),
+        ]))
+        .build();
+    let mut harness = Harness::builder()
+        .with_config(config)
+        .with_mock_args(mock_args)
+        .build()
+        .await;
+    let (limited_key, limited) = other_replica
+        .mint(NewVirtualKey {
+            spend_limit_usd: Some(Decimal::ONE),
+            ..Default::default()
+        })
+        .await
+        .unwrap();
+    let (revoked_key, revoked) =
+        other_replica.mint(NewVirtualKey::default()).await.unwrap();
+    for api_key in [&limited_key, &revoked_key] {
+        let response = harness
+            .call(chat_request(
+                ROUTER_URI,
+                Some(api_key.expose()),
+                "openai/gpt-4o-mini",
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let _body = response.into_body().collect().await.unwrap();
+    }
+    // spend is recorded in the background once the response is read
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+    other_replica
+        .add_spend(&limited.key_hash, Decimal::ONE)
+        .await;
+    other_replica.revoke(revoked.id).await.unwrap();
+    harness
+        .app_factory
+        .state
+        .0
+        .virtual_keys
+        .as_ref()
+        .unwrap()
+        .refresh()
+        .await
+        .unwrap();
+
+    let response = harness
+        .call(chat_request(
+            ROUTER_URI,
+            Some(limited_key.expose()),
+            "openai/gpt-4o-mini",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+    let response = harness
+        .call(chat_request(
+            ROUTER_URI,
+            Some(revoked_key.expose()),
+            "openai/gpt-4o-mini",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let spent_usd = other_replica
+        .get(&limited.key_hash)
+        .await
+        .unwrap()
+        .spent_usd;
+    assert_eq!(spent_usd, Decimal::ONE + Decimal::new(885, 8));
+}
@@ -67,7 +67,8 @@ pub fn router(app_state: AppState) -> Router {
         .route("/v1/chat/completions", post(routes::openai::handler))
         .route("/v1/messages", post(routes::anthropic::handler))
         .route(
-            "/v1beta/openai/chat/completions",
+            // `{model}:generateContent`, axum can't match part of a segment
+            "/v1beta/models/{model_action}",
             post(routes::gemini::handler),
         )
         .route("/model/{modelId}/converse", post(routes::bedrock::handler))
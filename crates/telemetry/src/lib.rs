@@ -2,6 +2,8 @@ pub mod make_span;
 pub mod tracing;
 pub mod utils;
 
+use std::sync::OnceLock;
+
 use opentelemetry::{
     TraceId, global,
     trace::{TracerProvider, noop::NoopTextMapPropagator},
@@ -42,6 +44,10 @@ pub struct Config {
     pub propagate: bool,
     #[serde(default)]
     pub format: Format,
+    /// Exposes the metrics of the gateway for Prometheus to scrape at
+    /// `/metrics`, whatever the exporter.
+    #[serde(default)]
+    pub prometheus: bool,
 }
 
 impl Default for Config {
@@ -53,6 +59,7 @@ impl Default for Config {
             otlp_endpoint: default_otlp_endpoint(),
             propagate: default_true(),
             format: Format::default(),
+            prometheus: false,
         }
     }
 }
@@ -103,10 +110,21 @@ pub enum TelemetryError {
     InvalidLogDirective(#[from] ParseError),
     #[error("Subscriber error: {0}")]
     Subscriber(#[from] TryInitError),
+    #[error("Prometheus exporter build error: {0}")]
+    PrometheusExporterBuild(opentelemetry_sdk::metrics::MetricError),
     #[error("Otel http metrics error")]
     OtelHttpMetrics,
 }
 
+static PROMETHEUS_REGISTRY: OnceLock<prometheus::Registry> = OnceLock::new();
+
+/// The registry the metrics are exported to for Prometheus, if enabled with
+/// [`Config::prometheus`].
+#[must_use]
+pub fn prometheus_registry() -> Option<&'static prometheus::Registry> {
+    PROMETHEUS_REGISTRY.get()
+}
+
 fn resource(config: &Config) -> Resource {
     Resource::builder()
         .with_service_name(config.service_name.clone())
@@ -143,7 +161,17 @@ pub fn init_telemetry(
     match config.exporter {
         Exporter::Stdout => {
             let tracer_provider = init_stdout(&resource, config)?;
-            Ok((None, tracer_provider, None))
+            let metrics_provider = if config.prometheus {
+                let provider = SdkMeterProvider::builder()
+                    .with_reader(prometheus_exporter()?)
+                    .with_resource(resource)
+                    .build();
+                global::set_meter_provider(provider.clone());
+                Some(provider)
+            } else {
+                None
+            };
+            Ok((None, tracer_provider, metrics_provider))
         }
         Exporter::Otlp => {
             let (logger_provider, tracer_provider, metrics_provider) =
@@ -243,8 +271,7 @@ fn init_otlp_pipeline(
         .try_init()?;
 
     // metrics
-    let metrics_provider = metrics_provider(config, resource)
-        .map_err(TelemetryError::MetricExporterBuild)?;
+    let metrics_provider = metrics_provider(config, resource)?;
 
     global::set_meter_provider(metrics_provider.clone());
     global::set_tracer_provider(tracer_provider.clone());
@@ -359,15 +386,28 @@ fn logger_provider(
 fn metrics_provider(
     config: &Config,
     resource: Resource,
-) -> Result<SdkMeterProvider, ExporterBuildError> {
+) -> Result<SdkMeterProvider, TelemetryError> {
     let exporter = MetricExporter::builder()
         .with_tonic()
         .with_endpoint(config.otlp_endpoint.clone())
-        .build()?;
-    Ok(SdkMeterProvider::builder()
+        .build()
+        .map_err(TelemetryError::MetricExporterBuild)?;
+    let mut builder = SdkMeterProvider::builder()
         .with_periodic_exporter(exporter)
-        .with_resource(resource)
-        .build())
+        .with_resource(resource);
+    if config.prometheus {
+        builder = builder.with_reader(prometheus_exporter()?);
+    }
+    Ok(builder.build())
+}
+
+fn prometheus_exporter()
+-> Result<opentelemetry_prometheus::PrometheusExporter, TelemetryError> {
+    let registry = PROMETHEUS_REGISTRY.get_or_init(prometheus::Registry::new);
+    opentelemetry_prometheus::exporter()
+        .with_registry(registry.clone())
+        .build()
+        .map_err(TelemetryError::PrometheusExporterBuild)
 }
 
 #[derive(Debug)]
@@ -1,4 +1,6 @@
-use http::HeaderValue;
+use http::{HeaderMap, HeaderValue};
+use opentelemetry::global;
+use opentelemetry_http::HeaderInjector;
 use tower_http::request_id::RequestId;
 
 #[derive(Clone, Default)]
@@ -19,3 +21,16 @@ impl tower_http::request_id::MakeRequestId for MakeRequestId {
         Some(RequestId::new(header))
     }
 }
+
+/// Adds the context of the current span to `headers`, e.g. as a
+/// `traceparent` header, so that upstream services continue the trace.
+///
+/// Does nothing unless [`crate::Config::propagate`] is set.
+pub fn inject_current_context(headers: &mut HeaderMap) {
+    use tracing::Span;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    let context = Span::current().context();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&context, &mut HeaderInjector(headers));
+    });
+}